@@ -0,0 +1,27 @@
+// Writes manifest.json next to package.json: the service version and a
+// SHA-256 for every file in dist/. The app checks it before using the
+// bundled service (see src-tauri/src/claude_service.rs).
+
+import { createHash } from "node:crypto";
+import { readFileSync, readdirSync, writeFileSync } from "node:fs";
+import { dirname, join, relative } from "node:path";
+import { fileURLToPath } from "node:url";
+
+const root = join(dirname(fileURLToPath(import.meta.url)), "..");
+const dist = join(root, "dist");
+
+function walk(dir) {
+  return readdirSync(dir, { withFileTypes: true }).flatMap((entry) => {
+    const path = join(dir, entry.name);
+    return entry.isDirectory() ? walk(path) : [path];
+  });
+}
+
+const { version } = JSON.parse(readFileSync(join(root, "package.json"), "utf8"));
+const files = {};
+for (const path of walk(dist).sort()) {
+  files[relative(root, path).split("\\").join("/")] = createHash("sha256").update(readFileSync(path)).digest("hex");
+}
+
+writeFileSync(join(root, "manifest.json"), JSON.stringify({ version, files }, null, 2) + "\n");
+console.log(`Wrote manifest.json for ${Object.keys(files).length} files (v${version})`);
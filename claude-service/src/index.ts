@@ -5,10 +5,11 @@
  * Communicates via JSON over stdin/stdout.
  *
  * Input commands (JSON per line):
- * - { type: "start", workingDir, model?, systemPrompt?, resumeSessionId? }
+ * - { type: "start", workingDir, model?, resumeSessionId?, permissionMode?, appendSystemPrompt?, maxTurns? }
  * - { type: "message", content }
  * - { type: "interrupt" }
  * - { type: "changeModel", model }
+ * - { type: "reloadSkills" }
  * - { type: "stop" }
  *
  * Output events (JSON per line):
@@ -19,6 +20,7 @@
  * - { type: "result", content, usage }
  * - { type: "error", message }
  * - { type: "ready" }
+ * - { type: "skills_reloaded", skills }
  */
 
 import { createInterface } from 'readline';
@@ -46,8 +48,14 @@ interface StartCommand {
   projectId?: string;
   // ACE: Alternatively, pass the full playbook directly
   playbook?: Playbook;
+  // Agent profile settings, applied to every query in the session
+  permissionMode?: PermissionMode;
+  appendSystemPrompt?: string;
+  maxTurns?: number;
 }
 
+type PermissionMode = 'default' | 'acceptEdits' | 'bypassPermissions' | 'plan';
+
 interface MessageCommand {
   type: 'message';
   content: string;
@@ -63,11 +71,15 @@ interface ChangeModelCommand {
   model: string;
 }
 
+interface ReloadSkillsCommand {
+  type: 'reloadSkills';
+}
+
 interface StopCommand {
   type: 'stop';
 }
 
-type Command = StartCommand | MessageCommand | InterruptCommand | ChangeModelCommand | StopCommand;
+type Command = StartCommand | MessageCommand | InterruptCommand | ChangeModelCommand | ReloadSkillsCommand | StopCommand;
 
 // Output event types
 interface OutputEvent {
@@ -135,6 +147,9 @@ let currentSessionId: string | null = null;
 let resumeSessionId: string | null = null;
 let currentModel: string = 'sonnet';
 let workingDir: string = process.cwd();
+let sessionPermissionMode: PermissionMode | null = null;
+let sessionAppendPrompt: string | null = null;
+let sessionMaxTurns: number | null = null;
 type NocurSwiftRunner = {
   command: string;
   argsPrefix: string[];
@@ -950,6 +965,8 @@ async function processQuery(prompt: string, options: {
   systemPrompt?: string;
   resumeSessionId?: string;
   skipPermissions?: boolean;
+  permissionMode?: PermissionMode;
+  maxTurns?: number;
   agentMode?: 'build' | 'plan';
 }) {
   const nocurServer = createNocurSwiftServer();
@@ -1007,8 +1024,9 @@ After creating new .swift files, call project_add_files to add them to the Xcode
       mcpServers: { 'nocur-swift': nocurServer },
       // Context management - CRITICAL to prevent "prompt too long" errors
       maxOutputTokens: 16000,  // Limit per-response output (was 128000 - way too high)
-      // No turn limit - let the agent work until it's done or hits context limits
-      // The SDK will auto-compact conversation history to stay within context window
+      // No turn limit unless the session's profile sets one - let the agent work until
+      // it's done or hits context limits. The SDK will auto-compact conversation history
+      // to stay within context window
       // In plan mode, restrict to read-only tools
       allowedTools: options.agentMode === 'plan' ? [
         // Read-only tools for plan mode
@@ -1064,10 +1082,16 @@ After creating new .swift files, call project_add_files to add them to the Xcode
       queryOptions.resume = options.resumeSessionId;
     }
 
-    if (options.skipPermissions) {
+    if (options.permissionMode) {
+      queryOptions.permissionMode = options.permissionMode;
+    } else if (options.skipPermissions) {
       queryOptions.permissionMode = 'bypassPermissions';
     }
 
+    if (options.maxTurns) {
+      queryOptions.maxTurns = options.maxTurns;
+    }
+
     // Use string prompt directly for simpler interaction
     const queryGenerator = query({
       prompt,
@@ -1225,6 +1249,33 @@ async function loadPlaybookFromStorage(projectId: string): Promise<Playbook | nu
   }
 }
 
+// Skill names from ~/.claude/skills and the project's .claude/skills
+async function scanSkills(): Promise<string[]> {
+  const fs = await import('fs/promises');
+  const path = await import('path');
+  const os = await import('os');
+
+  const skills: string[] = [];
+  const dirs = [path.join(os.homedir(), '.claude/skills'), path.join(workingDir, '.claude/skills')];
+  for (const dir of dirs) {
+    let entries: string[];
+    try {
+      entries = await fs.readdir(dir);
+    } catch {
+      continue;
+    }
+    for (const entry of entries) {
+      try {
+        await fs.access(path.join(dir, entry, 'SKILL.md'));
+        if (!skills.includes(entry)) skills.push(entry);
+      } catch {
+        // Not a skill directory
+      }
+    }
+  }
+  return skills;
+}
+
 // Handle incoming commands
 async function handleCommand(command: Command) {
   switch (command.type) {
@@ -1233,6 +1284,9 @@ async function handleCommand(command: Command) {
       nocurSwiftRunner = await resolveNocurSwiftRunner(command.nocurSwiftPath);
       currentModel = command.model || 'sonnet';
       resumeSessionId = command.resumeSessionId || null;
+      sessionPermissionMode = command.permissionMode || null;
+      sessionAppendPrompt = command.appendSystemPrompt || null;
+      sessionMaxTurns = command.maxTurns || null;
 
       // Initialize ACE
       aceManager = createACEManager();
@@ -1287,8 +1341,12 @@ async function handleCommand(command: Command) {
       await processQuery(command.content, {
         model: currentModel,
         skipPermissions: true, // For now, skip permissions in SDK mode
+        permissionMode: sessionPermissionMode || undefined,
+        systemPrompt: sessionAppendPrompt || undefined,
+        maxTurns: sessionMaxTurns || undefined,
         resumeSessionId: resumeSessionId || undefined,
-        agentMode: command.agentMode || 'build',
+        // A plan-mode profile plans unless the message asks otherwise
+        agentMode: command.agentMode || (sessionPermissionMode === 'plan' ? 'plan' : 'build'),
       });
       // After first query, use the currentSessionId for subsequent queries
       // (the SDK creates a new session if we always pass resumeSessionId)
@@ -1311,6 +1369,12 @@ async function handleCommand(command: Command) {
       emit({ type: 'model_changed', model: currentModel });
       break;
 
+    case 'reloadSkills':
+      // Every query starts Claude Code afresh and it reads the skill
+      // directories then, so the next turn already sees the change
+      emit({ type: 'skills_reloaded', skills: await scanSkills() });
+      break;
+
     case 'stop':
       currentQuery = null;
       // Clean up LSP manager
@@ -10,6 +10,8 @@ use std::hash::{Hash, Hasher};
 use std::path::PathBuf;
 use sha2::{Digest, Sha256};
 
+use crate::migrations::{Document, Migration, SchemaVersion};
+
 /// Bullet section types
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 #[serde(rename_all = "snake_case")]
@@ -60,6 +62,44 @@ pub struct Playbook {
     pub bullets: Vec<Bullet>,
     pub created_at: u64,
     pub updated_at: u64,
+    /// Curator operations applied to this playbook, oldest first
+    #[serde(default)]
+    pub history: Vec<PlaybookHistoryEntry>,
+    #[serde(default)]
+    pub schema_version: SchemaVersion<PLAYBOOK_SCHEMA_VERSION>,
+}
+
+/// A change the curator proposes for a playbook
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "UPPERCASE", rename_all_fields = "camelCase")]
+pub enum CurationOperation {
+    Add {
+        section: BulletSection,
+        content: String,
+    },
+    Update {
+        bullet_id: String,
+        content: String,
+    },
+    Deactivate {
+        bullet_id: String,
+    },
+    Merge {
+        merge_into_id: String,
+        merge_from_ids: Vec<String>,
+        content: String,
+    },
+}
+
+/// A curator operation as applied, with the reflections that prompted it
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PlaybookHistoryEntry {
+    pub operation: CurationOperation,
+    /// The bullet created by an ADD
+    pub bullet_id: Option<String>,
+    pub reflection_ids: Vec<String>,
+    pub applied_at: u64,
 }
 
 /// Bullet tag entry from reflector
@@ -94,6 +134,9 @@ pub struct StoredReflection {
     pub reflection: ReflectionResult,
     pub bullets_used: Vec<String>,
     pub created_at: u64,
+    /// Set once the curator has turned this reflection into playbook changes
+    #[serde(default)]
+    pub curated: bool,
 }
 
 /// Reflections log for a project
@@ -102,6 +145,8 @@ pub struct StoredReflection {
 pub struct ReflectionsLog {
     pub project_id: String,
     pub reflections: Vec<StoredReflection>,
+    #[serde(default)]
+    pub schema_version: SchemaVersion<REFLECTION_SCHEMA_VERSION>,
 }
 
 /// ACE configuration
@@ -160,10 +205,90 @@ fn legacy_project_id(path: &str) -> String {
     format!("{:016x}", hasher.finish())
 }
 
+// ============ Migrations ============
+
+pub const PLAYBOOK_SCHEMA_VERSION: u32 = PLAYBOOK_MIGRATIONS.len() as u32;
+pub const REFLECTION_SCHEMA_VERSION: u32 = REFLECTION_MIGRATIONS.len() as u32;
+
+pub const PLAYBOOK_MIGRATIONS: &[Migration] = &[Migration {
+    description: "Rename from the DefaultHasher project ID",
+    apply: rename_legacy_playbook,
+}];
+
+pub const REFLECTION_MIGRATIONS: &[Migration] = &[Migration {
+    description: "Rename from the DefaultHasher project ID",
+    apply: rename_legacy_reflections,
+}];
+
+fn json_files(dir: Result<PathBuf, String>) -> Vec<PathBuf> {
+    let Ok(entries) = dir.and_then(|dir| fs::read_dir(dir).map_err(|e| e.to_string())) else {
+        return vec![];
+    };
+    entries
+        .filter_map(|e| e.ok())
+        .map(|e| e.path())
+        .filter(|p| p.extension().is_some_and(|ext| ext == "json"))
+        .collect()
+}
+
+pub fn playbook_files() -> Vec<PathBuf> {
+    json_files(get_ace_dir().map(|dir| dir.join("playbooks")))
+}
+
+pub fn reflection_files() -> Vec<PathBuf> {
+    json_files(get_ace_dir().map(|dir| dir.join("reflections")))
+}
+
+/// Point `document` and the `projectId` of each of its `items` at `project_id`,
+/// and move it to `<project_id>.json`
+fn set_project_id(document: &mut Document, items: &str, project_id: &str) {
+    if let Some(entries) = document.value.get_mut(items).and_then(|v| v.as_array_mut()) {
+        for entry in entries.iter_mut().filter_map(|e| e.as_object_mut()) {
+            entry.insert("projectId".to_string(), project_id.into());
+        }
+    }
+    if let Some(object) = document.value.as_object_mut() {
+        object.insert("projectId".to_string(), project_id.into());
+    }
+    document.path = document.path.with_file_name(format!("{}.json", project_id));
+}
+
+/// Playbooks were once named by a `DefaultHasher` hash of the path. The
+/// playbook records its path, so the current ID can be worked out from it.
+fn rename_legacy_playbook(document: &mut Document) -> Result<(), String> {
+    let project_path = document
+        .value
+        .get("projectPath")
+        .and_then(|v| v.as_str())
+        .ok_or("no projectPath")?
+        .to_string();
+    let stem = document.path.file_stem().unwrap_or_default().to_string_lossy().to_string();
+    if stem == legacy_project_id(&project_path) {
+        set_project_id(document, "bullets", &generate_project_id(&project_path));
+    }
+    Ok(())
+}
+
+/// Reflections logs don't record their path, so a legacy one is renamed
+/// through the playbook of the same project. One with no playbook is left as
+/// it is.
+fn rename_legacy_reflections(document: &mut Document) -> Result<(), String> {
+    let stem = document.path.file_stem().unwrap_or_default().to_string_lossy().to_string();
+    let project_path = playbook_files().into_iter().find_map(|path| {
+        let content = fs::read_to_string(path).ok()?;
+        let playbook: serde_json::Value = serde_json::from_str(&content).ok()?;
+        let project_path = playbook.get("projectPath")?.as_str()?.to_string();
+        (legacy_project_id(&project_path) == stem).then_some(project_path)
+    });
+    if let Some(project_path) = project_path {
+        set_project_id(document, "reflections", &generate_project_id(&project_path));
+    }
+    Ok(())
+}
+
 /// Get the ACE data directory
 fn get_ace_dir() -> Result<PathBuf, String> {
-    let home = std::env::var("HOME").map_err(|_| "HOME not set")?;
-    let ace_dir = PathBuf::from(home).join(".config/nocur/ace");
+    let ace_dir = crate::paths::home_dir()?.join(".config/nocur/ace");
     Ok(ace_dir)
 }
 
@@ -217,26 +342,6 @@ pub fn load_playbook(project_path: &str) -> Result<Option<Playbook>, String> {
     let path = playbooks_dir.join(format!("{}.json", project_id));
 
     if !path.exists() {
-        // Backward compatibility: migrate legacy DefaultHasher-based IDs.
-        let legacy_id = legacy_project_id(project_path);
-        let legacy_path = playbooks_dir.join(format!("{}.json", legacy_id));
-        if legacy_path.exists() {
-            let content = fs::read_to_string(&legacy_path)
-                .map_err(|e| format!("Failed to read playbook: {}", e))?;
-            let mut playbook: Playbook = serde_json::from_str(&content)
-                .map_err(|e| format!("Failed to parse playbook: {}", e))?;
-
-            playbook.project_id = project_id.clone();
-            playbook.project_path = project_path.to_string();
-            for bullet in &mut playbook.bullets {
-                bullet.project_id = project_id.clone();
-            }
-
-            save_playbook(&playbook)?;
-            let _ = fs::remove_file(&legacy_path);
-            return Ok(Some(playbook));
-        }
-
         return Ok(None);
     }
 
@@ -257,6 +362,7 @@ pub fn save_playbook(playbook: &Playbook) -> Result<(), String> {
         .map_err(|e| format!("Failed to serialize playbook: {}", e))?;
     fs::write(&path, content)
         .map_err(|e| format!("Failed to write playbook: {}", e))?;
+    crate::prefetch::invalidate(None, Some(crate::prefetch::PrefetchItem::Playbook));
 
     Ok(())
 }
@@ -279,6 +385,8 @@ pub fn create_playbook(project_path: &str) -> Result<Playbook, String> {
         bullets: vec![],
         created_at: now,
         updated_at: now,
+        history: vec![],
+        schema_version: SchemaVersion::default(),
     };
 
     save_playbook(&playbook)?;
@@ -448,29 +556,6 @@ pub fn load_reflections(project_path: &str) -> Result<Vec<StoredReflection>, Str
     let path = reflections_dir.join(format!("{}.json", project_id));
 
     if !path.exists() {
-        // Backward compatibility: migrate legacy DefaultHasher-based IDs.
-        let legacy_id = legacy_project_id(project_path);
-        let legacy_path = reflections_dir.join(format!("{}.json", legacy_id));
-        if legacy_path.exists() {
-            let content = fs::read_to_string(&legacy_path)
-                .map_err(|e| format!("Failed to read reflections: {}", e))?;
-            let mut log: ReflectionsLog = serde_json::from_str(&content)
-                .map_err(|e| format!("Failed to parse reflections: {}", e))?;
-
-            log.project_id = project_id.clone();
-            for reflection in &mut log.reflections {
-                reflection.project_id = project_id.clone();
-            }
-
-            let migrated = log.reflections.clone();
-            let content = serde_json::to_string_pretty(&log)
-                .map_err(|e| format!("Failed to serialize reflections: {}", e))?;
-            fs::write(&path, content)
-                .map_err(|e| format!("Failed to write reflections: {}", e))?;
-            let _ = fs::remove_file(&legacy_path);
-            return Ok(migrated);
-        }
-
         return Ok(vec![]);
     }
 
@@ -497,6 +582,7 @@ pub fn save_reflection(project_path: &str, reflection: StoredReflection) -> Resu
         ReflectionsLog {
             project_id: project_id.clone(),
             reflections: vec![],
+            schema_version: SchemaVersion::default(),
         }
     };
 
@@ -510,6 +596,139 @@ pub fn save_reflection(project_path: &str, reflection: StoredReflection) -> Resu
     Ok(())
 }
 
+/// Append applied curator operations to a playbook's history
+pub fn record_history(project_path: &str, entries: Vec<PlaybookHistoryEntry>) -> Result<(), String> {
+    let mut playbook = get_or_create_playbook(project_path)?;
+    playbook.history.extend(entries);
+    save_playbook(&playbook)
+}
+
+/// Flag reflections as handled by the curator
+pub fn mark_reflections_curated(project_path: &str, reflection_ids: &[String]) -> Result<(), String> {
+    let project_id = generate_project_id(project_path);
+    let path = get_reflections_dir()?.join(format!("{}.json", project_id));
+    if !path.exists() {
+        return Ok(());
+    }
+
+    let content = fs::read_to_string(&path)
+        .map_err(|e| format!("Failed to read reflections: {}", e))?;
+    let mut log: ReflectionsLog = serde_json::from_str(&content)
+        .map_err(|e| format!("Failed to parse reflections: {}", e))?;
+
+    for reflection in &mut log.reflections {
+        if reflection_ids.contains(&reflection.id) {
+            reflection.curated = true;
+        }
+    }
+
+    let content = serde_json::to_string_pretty(&log)
+        .map_err(|e| format!("Failed to serialize reflections: {}", e))?;
+    fs::write(&path, content)
+        .map_err(|e| format!("Failed to write reflections: {}", e))?;
+
+    Ok(())
+}
+
+/// Helpful vs harmful tags applied on one day (UTC)
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TagTrendDay {
+    /// YYYY-MM-DD
+    pub date: String,
+    pub helpful: u32,
+    pub harmful: u32,
+}
+
+/// At-a-glance playbook numbers; all zeros for a project without a playbook
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PlaybookStats {
+    pub total_bullets: usize,
+    pub active_bullets: usize,
+    pub bullets_by_section: std::collections::HashMap<String, usize>,
+    pub total_helpful: i64,
+    pub total_harmful: i64,
+    pub reflections_count: usize,
+    pub last_reflection_at: Option<u64>,
+    /// Rough size of the active bullets when injected into a prompt
+    pub estimated_tokens: usize,
+    /// Last 7 days, oldest first
+    pub tag_trend: Vec<TagTrendDay>,
+}
+
+const TREND_DAYS: i64 = 7;
+
+/// Compute stats from one read each of the playbook and reflections files.
+/// Missing or unreadable files count as empty.
+pub fn get_stats(project_path: &str) -> PlaybookStats {
+    let playbook = load_playbook(project_path).unwrap_or_else(|e| {
+        log::warn!("ACE stats: {}", e);
+        None
+    });
+    let reflections = load_reflections(project_path).unwrap_or_else(|e| {
+        log::warn!("ACE stats: {}", e);
+        vec![]
+    });
+    let bullets = playbook.as_ref().map(|p| p.bullets.as_slice()).unwrap_or_default();
+    let active: Vec<&Bullet> = bullets.iter().filter(|b| b.active).collect();
+
+    let mut bullets_by_section = std::collections::HashMap::new();
+    for bullet in &active {
+        let section = serde_json::to_value(&bullet.section)
+            .ok()
+            .and_then(|v| v.as_str().map(String::from))
+            .unwrap_or_default();
+        *bullets_by_section.entry(section).or_insert(0) += 1;
+    }
+
+    // Bullets are rendered as `[id] content`; ~4 characters per token
+    let estimated_tokens = active
+        .iter()
+        .map(|b| (b.id.len() + b.content.chars().count() + 3).div_ceil(4))
+        .sum();
+
+    // Tag events are only timestamped on the reflections that produced them
+    let today = chrono::Utc::now().date_naive();
+    let mut tag_trend: Vec<TagTrendDay> = (0..TREND_DAYS)
+        .rev()
+        .map(|days_ago| TagTrendDay {
+            date: (today - chrono::Duration::days(days_ago)).format("%Y-%m-%d").to_string(),
+            helpful: 0,
+            harmful: 0,
+        })
+        .collect();
+    for reflection in &reflections {
+        let Some(day) = chrono::DateTime::from_timestamp_millis(reflection.created_at as i64) else {
+            continue;
+        };
+        let days_ago = (today - day.date_naive()).num_days();
+        if !(0..TREND_DAYS).contains(&days_ago) {
+            continue;
+        }
+        let entry = &mut tag_trend[(TREND_DAYS - 1 - days_ago) as usize];
+        for tag in &reflection.reflection.bullet_tags {
+            match tag.tag {
+                BulletTag::Helpful => entry.helpful += 1,
+                BulletTag::Harmful => entry.harmful += 1,
+                BulletTag::Neutral => {}
+            }
+        }
+    }
+
+    PlaybookStats {
+        total_bullets: bullets.len(),
+        active_bullets: active.len(),
+        bullets_by_section,
+        total_helpful: bullets.iter().map(|b| b.helpful_count as i64).sum(),
+        total_harmful: bullets.iter().map(|b| b.harmful_count as i64).sum(),
+        reflections_count: reflections.len(),
+        last_reflection_at: reflections.iter().map(|r| r.created_at).max(),
+        estimated_tokens,
+        tag_trend,
+    }
+}
+
 /// List all playbook project IDs
 pub fn list_playbooks() -> Result<Vec<String>, String> {
     let playbooks_dir = get_playbooks_dir()?;
@@ -0,0 +1,320 @@
+//! ACE curator pass
+//!
+//! Batches reflections the curator hasn't seen yet, asks `curator_model` for
+//! delta operations against the current playbook, validates them and applies
+//! the valid ones through the regular bullet functions. Applied operations are
+//! recorded in the playbook history and the reflections marked curated.
+
+use serde::{Deserialize, Serialize};
+use std::sync::atomic::{AtomicBool, Ordering};
+use tauri::AppHandle;
+
+use crate::ace::{self, CurationOperation, PlaybookHistoryEntry, StoredReflection};
+
+/// Reflections sent to the curator per pass
+const CURATION_BATCH_SIZE: usize = 10;
+/// Default cap on ADD operations per pass
+const DEFAULT_MAX_NEW_BULLETS: usize = 5;
+/// Bullets are meant to be a few sentences
+const MAX_BULLET_CHARS: usize = 600;
+/// With auto_curate on, run a pass once this many reflections are waiting
+const AUTO_CURATE_EVERY: usize = 5;
+
+/// Keeps back-to-back reflections from starting overlapping passes
+static AUTO_CURATION_RUNNING: AtomicBool = AtomicBool::new(false);
+
+const CURATOR_SYSTEM_PROMPT: &str = r#"You are a Curator agent in an Agentic Context Engineering (ACE) system. You maintain a playbook of reusable knowledge, updating it from reflections on recent agent runs. Propose targeted DELTA OPERATIONS only; never rewrite the playbook.
+
+Sections: strategies_and_hard_rules, useful_code_snippets, troubleshooting_and_pitfalls, apis_to_use_for_specific_information, verification_checklist, domain_glossary
+
+Operations:
+- ADD: new bullet in a section
+- UPDATE: change an existing bullet's content (bulletId)
+- DEACTIVATE: retire a harmful or obsolete bullet (bulletId)
+- MERGE: fold similar bullets into one (mergeIntoId, mergeFromIds, content)
+
+Output ONLY a JSON object, no markdown code blocks or other text:
+{
+  "reasoning": "Why these changes are needed...",
+  "operations": [
+    {"type": "ADD", "section": "strategies_and_hard_rules", "content": "..."},
+    {"type": "UPDATE", "bulletId": "strat-abc123", "content": "..."},
+    {"type": "DEACTIVATE", "bulletId": "code-xyz789"},
+    {"type": "MERGE", "mergeIntoId": "trou-111", "mergeFromIds": ["trou-222"], "content": "..."}
+  ]
+}
+
+Only reference bullet IDs that appear in the playbook. Avoid duplicates, keep content to 1-3 actionable sentences, weigh the helpful/harmful counts, and return an empty operations array if nothing should change."#;
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CurationOptions {
+    pub max_new_bullets: Option<usize>,
+    /// Validate and return the operations without applying them
+    #[serde(default)]
+    pub dry_run: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RejectedOperation {
+    pub operation: serde_json::Value,
+    pub reason: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CurationReport {
+    pub dry_run: bool,
+    pub reasoning: String,
+    /// Valid operations: applied, or what would be applied in a dry run
+    pub operations: Vec<CurationOperation>,
+    pub rejected: Vec<RejectedOperation>,
+    pub reflection_ids: Vec<String>,
+}
+
+/// Emitted when automatic curation fails; the playbook is left as it was
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CurationFailed {
+    pub project_path: String,
+    pub error: String,
+}
+
+fn render_playbook(playbook: &ace::Playbook) -> String {
+    let active: Vec<String> = playbook
+        .bullets
+        .iter()
+        .filter(|b| b.active)
+        .map(|b| {
+            let section = serde_json::to_value(&b.section)
+                .ok()
+                .and_then(|v| v.as_str().map(String::from))
+                .unwrap_or_default();
+            format!(
+                "[{}] ({}) helpful={} harmful={} :: {}",
+                b.id, section, b.helpful_count, b.harmful_count, b.content
+            )
+        })
+        .collect();
+    if active.is_empty() {
+        "(empty)".to_string()
+    } else {
+        active.join("\n")
+    }
+}
+
+fn render_reflection(stored: &StoredReflection) -> String {
+    let r = &stored.reflection;
+    let tags = r
+        .bullet_tags
+        .iter()
+        .map(|t| {
+            let tag = match t.tag {
+                ace::BulletTag::Helpful => "helpful",
+                ace::BulletTag::Harmful => "harmful",
+                ace::BulletTag::Neutral => "neutral",
+            };
+            format!("{}: {}", t.id, tag)
+        })
+        .collect::<Vec<_>>()
+        .join(", ");
+    format!(
+        "### Reflection {} ({})\nTask: {}\nError: {}\nRoot cause: {}\nCorrect approach: {}\nKey insight: {}\nBullet feedback: {}",
+        stored.id,
+        stored.outcome,
+        crate::claude::truncate_to_char_boundary(&stored.task, 500),
+        r.error_identification,
+        r.root_cause_analysis,
+        r.correct_approach,
+        r.key_insight,
+        if tags.is_empty() { "none".to_string() } else { tags },
+    )
+}
+
+/// Check an operation against the playbook; Err carries the rejection reason
+fn validate(operation: &CurationOperation, playbook: &ace::Playbook) -> Result<(), String> {
+    let exists = |id: &str| playbook.bullets.iter().any(|b| b.id == id && b.active);
+    let check_content = |content: &str| {
+        let len = content.trim().chars().count();
+        if len == 0 {
+            Err("content is empty".to_string())
+        } else if len > MAX_BULLET_CHARS {
+            Err(format!("content is {} characters; the limit is {}", len, MAX_BULLET_CHARS))
+        } else {
+            Ok(())
+        }
+    };
+
+    match operation {
+        CurationOperation::Add { content, .. } => check_content(content),
+        CurationOperation::Update { bullet_id, content } => {
+            if !exists(bullet_id) {
+                return Err(format!("bullet {} does not exist", bullet_id));
+            }
+            check_content(content)
+        }
+        CurationOperation::Deactivate { bullet_id } => {
+            if exists(bullet_id) {
+                Ok(())
+            } else {
+                Err(format!("bullet {} does not exist", bullet_id))
+            }
+        }
+        CurationOperation::Merge { merge_into_id, merge_from_ids, content } => {
+            if merge_from_ids.is_empty() {
+                return Err("mergeFromIds is empty".to_string());
+            }
+            if merge_from_ids.contains(merge_into_id) {
+                return Err(format!("cannot merge {} into itself", merge_into_id));
+            }
+            if let Some(missing) = std::iter::once(merge_into_id).chain(merge_from_ids).find(|id| !exists(id)) {
+                return Err(format!("bullet {} does not exist", missing));
+            }
+            check_content(content)
+        }
+    }
+}
+
+/// Apply one validated operation; returns the new bullet's ID for ADD
+fn apply(project_path: &str, operation: &CurationOperation) -> Result<Option<String>, String> {
+    match operation {
+        CurationOperation::Add { section, content } => {
+            ace::add_bullet(project_path, section.clone(), content.trim().to_string()).map(|b| Some(b.id))
+        }
+        CurationOperation::Update { bullet_id, content } => {
+            ace::update_bullet(project_path, bullet_id, content.trim().to_string()).map(|_| None)
+        }
+        CurationOperation::Deactivate { bullet_id } => ace::delete_bullet(project_path, bullet_id).map(|_| None),
+        CurationOperation::Merge { merge_into_id, merge_from_ids, content } => {
+            ace::update_bullet(project_path, merge_into_id, content.trim().to_string())?;
+            for id in merge_from_ids {
+                ace::delete_bullet(project_path, id)?;
+            }
+            Ok(None)
+        }
+    }
+}
+
+/// Run one curator pass over the oldest uncurated reflections
+pub fn run_curation(project_path: &str, options: &CurationOptions) -> Result<CurationReport, String> {
+    let config = ace::load_ace_config();
+    let playbook = ace::get_or_create_playbook(project_path)?;
+
+    let batch: Vec<StoredReflection> = ace::load_reflections(project_path)?
+        .into_iter()
+        .filter(|r| !r.curated)
+        .take(CURATION_BATCH_SIZE)
+        .collect();
+    let reflection_ids: Vec<String> = batch.iter().map(|r| r.id.clone()).collect();
+
+    if batch.is_empty() {
+        return Ok(CurationReport {
+            dry_run: options.dry_run,
+            reasoning: "No uncurated reflections".to_string(),
+            operations: vec![],
+            rejected: vec![],
+            reflection_ids,
+        });
+    }
+
+    let prompt = format!(
+        "## Current Playbook\n{}\n\n## Recent Reflections\n{}\n\nPropose delta operations to improve the playbook.",
+        render_playbook(&playbook),
+        batch.iter().map(render_reflection).collect::<Vec<_>>().join("\n\n"),
+    );
+    let response = crate::ace_reflect::run_one_shot(&config.curator_model, CURATOR_SYSTEM_PROMPT, &prompt)?;
+    let json = crate::ace_reflect::extract_json_object(&response)?;
+
+    let reasoning = json.get("reasoning").and_then(|r| r.as_str()).unwrap_or("").to_string();
+    let max_new = options.max_new_bullets.unwrap_or(DEFAULT_MAX_NEW_BULLETS);
+
+    let mut operations = Vec::new();
+    let mut rejected = Vec::new();
+    let mut adds = 0;
+
+    let proposed = json.get("operations").and_then(|o| o.as_array()).cloned().unwrap_or_default();
+    for raw in proposed {
+        let operation: CurationOperation = match serde_json::from_value(raw.clone()) {
+            Ok(op) => op,
+            Err(e) => {
+                rejected.push(RejectedOperation { operation: raw, reason: format!("invalid operation: {}", e) });
+                continue;
+            }
+        };
+        if let Err(reason) = validate(&operation, &playbook) {
+            rejected.push(RejectedOperation { operation: raw, reason });
+            continue;
+        }
+        if matches!(operation, CurationOperation::Add { .. }) {
+            if adds >= max_new {
+                rejected.push(RejectedOperation {
+                    operation: raw,
+                    reason: format!("exceeds the limit of {} new bullets", max_new),
+                });
+                continue;
+            }
+            adds += 1;
+        }
+        operations.push(operation);
+    }
+
+    if !options.dry_run {
+        let now = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_millis() as u64;
+        let mut history = Vec::new();
+        for operation in &operations {
+            let bullet_id = apply(project_path, operation)?;
+            history.push(PlaybookHistoryEntry {
+                operation: operation.clone(),
+                bullet_id,
+                reflection_ids: reflection_ids.clone(),
+                applied_at: now,
+            });
+        }
+        ace::record_history(project_path, history)?;
+        ace::mark_reflections_curated(project_path, &reflection_ids)?;
+    }
+
+    Ok(CurationReport {
+        dry_run: options.dry_run,
+        reasoning,
+        operations,
+        rejected,
+        reflection_ids,
+    })
+}
+
+/// With auto_curate on, start a background pass once enough reflections are waiting.
+/// Results are emitted as `ace-curation-complete` or `ace-curation-failed`.
+pub fn maybe_auto_curate(app_handle: AppHandle, project_path: String) {
+    if !ace::load_ace_config().auto_curate {
+        return;
+    }
+    let pending = ace::load_reflections(&project_path)
+        .map(|r| r.iter().filter(|r| !r.curated).count())
+        .unwrap_or(0);
+    if pending < AUTO_CURATE_EVERY || AUTO_CURATION_RUNNING.swap(true, Ordering::SeqCst) {
+        return;
+    }
+
+    std::thread::spawn(move || {
+        let result = run_curation(&project_path, &CurationOptions::default());
+        AUTO_CURATION_RUNNING.store(false, Ordering::SeqCst);
+        match result {
+            Ok(report) => {
+                log::info!("ACE curation applied {} operation(s) for {}", report.operations.len(), project_path);
+                crate::event_journal::emit_stamped(&app_handle, "ace-curation-complete", report);
+            }
+            Err(error) => {
+                log::warn!("ACE curation failed for {}: {}", project_path, error);
+                crate::event_journal::emit_stamped(&app_handle, "ace-curation-failed", CurationFailed {
+                    project_path,
+                    error,
+                });
+            }
+        }
+    });
+}
@@ -0,0 +1,258 @@
+//! ACE reflector pipeline
+//!
+//! Turns a finished session into a stored reflection: the tail of the session
+//! transcript and the playbook bullets it referenced go to a one-shot `claude -p`
+//! run with the reflector prompt, and the JSON it returns is saved and its bullet
+//! tags applied to the playbook.
+
+use std::io::Write;
+use std::process::{Command, Stdio};
+
+use tauri::AppHandle;
+
+use crate::ace::{self, BulletTag, BulletTagEntry, ReflectionResult, StoredReflection};
+
+/// How much of the transcript the reflector sees
+const REFLECTION_MESSAGE_COUNT: usize = 20;
+/// Per-message cap so one huge tool dump can't crowd out the rest
+const MAX_MESSAGE_CHARS: usize = 4000;
+
+const REFLECTOR_SYSTEM_PROMPT: &str = r#"You are a Reflector agent in an Agentic Context Engineering (ACE) system. Analyze the agent's execution trace and outcome to find what went well or poorly, the root cause of any errors, and a reusable lesson, then tag which playbook bullets were helpful, harmful, or neutral.
+
+Output ONLY a JSON object with this exact structure, no markdown code blocks or other text:
+{
+  "reasoning": "Your overall analysis of the execution...",
+  "errorIdentification": "What specific error or issue occurred (or 'None' if successful)...",
+  "rootCauseAnalysis": "Why the error happened / what led to success...",
+  "correctApproach": "What should be done differently next time...",
+  "keyInsight": "A reusable strategy or lesson learned...",
+  "bulletTags": [{"id": "bullet-id", "tag": "helpful"}]
+}
+
+bulletTags must only contain IDs from the listed playbook bullets. Use "helpful" if the bullet contributed to good decisions, "harmful" if it led to errors, and "neutral" if it had no clear effect."#;
+
+/// Emitted when a reflection can't be produced; the session itself is unaffected
+#[derive(Debug, Clone, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReflectionFailed {
+    pub project_path: String,
+    pub session_id: String,
+    pub error: String,
+}
+
+/// Run the reflector over a session and store the result
+pub fn run_reflection(
+    project_path: &str,
+    session_id: &str,
+    task: &str,
+    outcome: &str,
+) -> Result<StoredReflection, String> {
+    let config = ace::load_ace_config();
+    let playbook = ace::get_or_create_playbook(project_path)?;
+
+    let messages = crate::read_session_messages(project_path, session_id)?;
+    if messages.is_empty() {
+        return Err(format!("No messages found for session {}", session_id));
+    }
+    let recent = &messages[messages.len().saturating_sub(REFLECTION_MESSAGE_COUNT)..];
+
+    // The service renders bullets as `[id]` markers and the agent cites them by ID,
+    // so any active bullet ID showing up in the transcript was in play
+    let bullets: Vec<&ace::Bullet> = playbook
+        .bullets
+        .iter()
+        .filter(|b| b.active && messages.iter().any(|m| m.content.contains(&b.id)))
+        .collect();
+    let bullet_ids: Vec<String> = bullets.iter().map(|b| b.id.clone()).collect();
+
+    // Fall back to the latest prompt when the caller doesn't know the task
+    let task = if task.trim().is_empty() {
+        messages
+            .iter()
+            .rev()
+            .find(|m| m.message_type == "user")
+            .map(|m| crate::claude::truncate_to_char_boundary(&m.content, 500).to_string())
+            .unwrap_or_default()
+    } else {
+        task.to_string()
+    };
+
+    let trace = recent
+        .iter()
+        .map(|m| {
+            let tools = m
+                .tools_used
+                .as_ref()
+                .map(|t| {
+                    let names: Vec<&str> = t.iter().map(|t| t.name.as_str()).collect();
+                    format!("\n(tools: {})", names.join(", "))
+                })
+                .unwrap_or_default();
+            format!("[{}] {}{}", m.message_type, crate::claude::truncate_to_char_boundary(&m.content, MAX_MESSAGE_CHARS), tools)
+        })
+        .collect::<Vec<_>>()
+        .join("\n\n");
+    let bullet_reference = bullets
+        .iter()
+        .map(|b| format!("[{}] {}", b.id, b.content))
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    let prompt = format!(
+        "## Task\n{}\n\n## Agent Trace\n{}\n\n## Outcome\n{}\n\n## Playbook Bullets Used\n{}",
+        task,
+        trace,
+        outcome,
+        if bullet_reference.is_empty() { "None reported".to_string() } else { bullet_reference },
+    );
+
+    let response = run_one_shot(&config.reflector_model, REFLECTOR_SYSTEM_PROMPT, &prompt)?;
+    let mut reflection = parse_reflection(&response)?;
+    reflection.bullet_tags.retain(|t| bullet_ids.contains(&t.id));
+
+    let stored = StoredReflection {
+        id: format!("ref-{}", uuid::Uuid::new_v4()),
+        project_id: playbook.project_id.clone(),
+        session_id: session_id.to_string(),
+        task,
+        outcome: outcome.to_string(),
+        reflection: reflection.clone(),
+        bullets_used: bullet_ids,
+        curated: false,
+        created_at: std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_millis() as u64,
+    };
+
+    ace::save_reflection(project_path, stored.clone())?;
+    if !reflection.bullet_tags.is_empty() {
+        ace::update_bullet_tags(project_path, reflection.bullet_tags)?;
+    }
+
+    Ok(stored)
+}
+
+/// One-shot `claude -p` call returning the reply text; the prompt goes over stdin
+/// since traces can exceed argv limits
+pub(crate) fn run_one_shot(model: &str, system_prompt: &str, prompt: &str) -> Result<String, String> {
+    let mut child = Command::new("claude")
+        .args([
+            "-p",
+            "--model", model,
+            "--output-format", "json",
+            "--system-prompt", system_prompt,
+        ])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| format!("Failed to run claude: {}", e))?;
+
+    if let Some(mut stdin) = child.stdin.take() {
+        stdin
+            .write_all(prompt.as_bytes())
+            .map_err(|e| format!("Failed to send prompt to claude -p: {}", e))?;
+    }
+
+    let output = child
+        .wait_with_output()
+        .map_err(|e| format!("Failed to wait for claude: {}", e))?;
+    if !output.status.success() {
+        return Err(format!("claude -p failed: {}", String::from_utf8_lossy(&output.stderr).trim()));
+    }
+
+    // --output-format json wraps the reply: {"type":"result","result":"...","is_error":false}
+    let envelope: serde_json::Value = serde_json::from_slice(&output.stdout)
+        .map_err(|e| format!("Failed to parse claude -p output: {}", e))?;
+    if envelope.get("is_error").and_then(|e| e.as_bool()).unwrap_or(false) {
+        return Err(format!(
+            "claude -p returned an error: {}",
+            envelope.get("result").and_then(|r| r.as_str()).unwrap_or("unknown error")
+        ));
+    }
+    envelope
+        .get("result")
+        .and_then(|r| r.as_str())
+        .map(String::from)
+        .ok_or("claude -p output had no result".to_string())
+}
+
+/// The outermost JSON object in a model reply, ignoring code fences or prose around it
+pub(crate) fn extract_json_object(response: &str) -> Result<serde_json::Value, String> {
+    let start = response.find('{').ok_or("Response contained no JSON object")?;
+    let end = response.rfind('}').filter(|&end| end > start).ok_or("Response contained no JSON object")?;
+    serde_json::from_str(&response[start..=end]).map_err(|e| format!("Failed to parse response JSON: {}", e))
+}
+
+/// Parse the reflector's JSON, tolerating code fences and snake_case keys
+fn parse_reflection(response: &str) -> Result<ReflectionResult, String> {
+    let json = extract_json_object(response)?;
+
+    let field = |camel: &str, snake: &str| {
+        json.get(camel)
+            .or_else(|| json.get(snake))
+            .and_then(|v| v.as_str())
+            .unwrap_or("")
+            .to_string()
+    };
+
+    let bullet_tags = json
+        .get("bulletTags")
+        .or_else(|| json.get("bullet_tags"))
+        .and_then(|t| t.as_array())
+        .map(|tags| {
+            tags.iter()
+                .filter_map(|t| {
+                    let tag = match t.get("tag")?.as_str()? {
+                        "helpful" => BulletTag::Helpful,
+                        "harmful" => BulletTag::Harmful,
+                        "neutral" => BulletTag::Neutral,
+                        _ => return None,
+                    };
+                    Some(BulletTagEntry { id: t.get("id")?.as_str()?.to_string(), tag })
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+
+    Ok(ReflectionResult {
+        reasoning: field("reasoning", "reasoning"),
+        error_identification: field("errorIdentification", "error_identification"),
+        root_cause_analysis: field("rootCauseAnalysis", "root_cause_analysis"),
+        correct_approach: field("correctApproach", "correct_approach"),
+        key_insight: field("keyInsight", "key_insight"),
+        bullet_tags,
+    })
+}
+
+/// Whether a finished turn in `project_path` should be reflected on automatically
+pub fn should_auto_reflect(project_path: &str) -> bool {
+    let config = ace::load_ace_config();
+    config.enabled
+        && config.auto_reflect
+        && ace::load_playbook(project_path)
+            .ok()
+            .flatten()
+            .is_some_and(|p| p.ace_enabled)
+}
+
+/// Reflect on a session in the background. Failures are logged and reported as
+/// `ace-reflection-failed`; success emits `ace-reflection-complete`.
+pub fn spawn_reflection(app_handle: AppHandle, project_path: String, session_id: String, task: String, outcome: String) {
+    std::thread::spawn(move || match run_reflection(&project_path, &session_id, &task, &outcome) {
+        Ok(reflection) => {
+            log::info!("ACE reflection {} saved for session {}", reflection.id, session_id);
+            crate::event_journal::emit_stamped(&app_handle, "ace-reflection-complete", reflection);
+            crate::ace_curate::maybe_auto_curate(app_handle, project_path);
+        }
+        Err(error) => {
+            log::warn!("ACE reflection failed for session {}: {}", session_id, error);
+            crate::event_journal::emit_stamped(&app_handle, "ace-reflection-failed", ReflectionFailed {
+                project_path,
+                session_id,
+                error,
+            });
+        }
+    });
+}
@@ -0,0 +1,303 @@
+//! Command bridge for the agent
+//!
+//! A Unix socket (same shape as the permission server) that lets the claude-service
+//! and skills run nocur operations as structured calls instead of shelling out to
+//! xcodebuild. Each request is one JSON line:
+//!
+//! `{"token": "...", "command": "build_project", "args": {"projectPath": "..."}}`
+//!
+//! and gets one JSON line back: `{"ok": true, "result": ...}` or
+//! `{"ok": false, "error": "..."}`. Results are the same JSON the Tauri commands
+//! return, and the commands emit their usual UI events while they run.
+//!
+//! `build_project`, `run_project`, `run_tests` and `take_screenshot` are the
+//! structured operations; the rest give the agent compact views of state.
+
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use std::io::{BufRead, BufReader, Write};
+use std::os::unix::fs::PermissionsExt;
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+use tauri::{AppHandle, Manager};
+
+use crate::DeviceInfo;
+
+fn socket_path() -> std::path::PathBuf {
+    std::env::temp_dir().join("nocur-agent-bridge.sock")
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AgentBridgeInfo {
+    pub socket_path: String,
+    pub token: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct BridgeRequest {
+    token: String,
+    command: String,
+    #[serde(default)]
+    args: serde_json::Value,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct BuildArgs {
+    project_path: Option<String>,
+    scheme: Option<String>,
+    /// Build configuration, Debug when not given
+    configuration: Option<String>,
+    device: Option<DeviceInfo>,
+    /// run_project only
+    auto_stream_logs: Option<bool>,
+    env_profile: Option<String>,
+    launch_env: Option<std::collections::HashMap<String, String>>,
+    /// Wait for a run already in progress instead of failing
+    queue: Option<bool>,
+    launch_overrides: Option<crate::launch_overrides::LaunchOverrides>,
+    /// Don't bring up the Simulator window or capture live frames
+    headless: Option<bool>,
+    /// build_project only: signing overrides for a device build
+    development_team: Option<String>,
+    code_sign_identity: Option<String>,
+    /// run_tests only, in `-only-testing` form
+    only_testing: Option<Vec<String>>,
+}
+
+pub struct AgentBridge {
+    /// Generated per app run; nothing outside this process and the sessions it
+    /// spawns ever sees it
+    token: String,
+    running: Arc<Mutex<bool>>,
+}
+
+impl AgentBridge {
+    pub fn new() -> Self {
+        Self {
+            token: uuid::Uuid::new_v4().to_string(),
+            running: Arc::new(Mutex::new(false)),
+        }
+    }
+
+    pub fn info(&self) -> AgentBridgeInfo {
+        AgentBridgeInfo {
+            socket_path: socket_path().to_string_lossy().to_string(),
+            token: self.token.clone(),
+        }
+    }
+
+    pub fn start(&self, app_handle: AppHandle) {
+        {
+            let mut running = self.running.lock();
+            if *running {
+                log::info!("Agent bridge already running");
+                return;
+            }
+            *running = true;
+        }
+
+        let socket_path = socket_path();
+        let _ = std::fs::remove_file(&socket_path);
+
+        let running = self.running.clone();
+        let token = self.token.clone();
+
+        thread::spawn(move || {
+            let listener = match UnixListener::bind(&socket_path) {
+                Ok(l) => l,
+                Err(e) => {
+                    log::error!("Failed to bind agent bridge socket: {}", e);
+                    *running.lock() = false;
+                    return;
+                }
+            };
+
+            // Restrict socket permissions to the current user.
+            if let Err(e) = std::fs::set_permissions(&socket_path, std::fs::Permissions::from_mode(0o600)) {
+                log::warn!("Failed to set permissions on {}: {}", socket_path.display(), e);
+            }
+
+            log::info!("Agent bridge listening on {}", socket_path.display());
+
+            // Set socket to non-blocking for graceful shutdown
+            listener.set_nonblocking(true).ok();
+
+            while *running.lock() {
+                match listener.accept() {
+                    Ok((stream, _)) => {
+                        let app_clone = app_handle.clone();
+                        let token_clone = token.clone();
+                        thread::spawn(move || {
+                            handle_connection(stream, &token_clone, app_clone);
+                        });
+                    }
+                    Err(ref e) if e.kind() == std::io::ErrorKind::WouldBlock => {
+                        thread::sleep(Duration::from_millis(100));
+                    }
+                    Err(e) => {
+                        log::error!("Failed to accept agent bridge connection: {}", e);
+                    }
+                }
+            }
+
+            log::info!("Agent bridge stopped");
+            let _ = std::fs::remove_file(&socket_path);
+        });
+    }
+}
+
+fn write_reply(stream: &mut UnixStream, reply: serde_json::Value) {
+    if let Err(e) = writeln!(stream, "{}", reply) {
+        log::error!("Failed to write agent bridge response: {}", e);
+    }
+    let _ = stream.flush();
+}
+
+fn handle_connection(mut stream: UnixStream, token: &str, app_handle: AppHandle) {
+    // The accepted stream inherits non-blocking mode from the listener
+    stream.set_nonblocking(false).ok();
+    stream.set_read_timeout(Some(Duration::from_secs(10))).ok();
+    stream.set_write_timeout(Some(Duration::from_secs(5))).ok();
+
+    let mut line = String::new();
+    let read = match stream.try_clone() {
+        Ok(s) => BufReader::new(s).read_line(&mut line),
+        Err(e) => Err(e),
+    };
+    if let Err(e) = read {
+        log::error!("Failed to read from agent bridge socket: {}", e);
+        return;
+    }
+
+    let request: BridgeRequest = match serde_json::from_str(&line) {
+        Ok(r) => r,
+        Err(e) => {
+            write_reply(&mut stream, serde_json::json!({ "ok": false, "error": format!("Invalid request: {}", e) }));
+            return;
+        }
+    };
+
+    if request.token != token {
+        log::warn!("Rejected agent bridge request with bad token: {}", request.command);
+        write_reply(&mut stream, serde_json::json!({ "ok": false, "error": "Invalid token" }));
+        return;
+    }
+
+    log::info!("Agent bridge command: {}", request.command);
+    let result = tauri::async_runtime::block_on(dispatch(&request.command, request.args, app_handle));
+
+    let reply = match result {
+        Ok(value) => serde_json::json!({ "ok": true, "result": value }),
+        Err(error) => serde_json::json!({ "ok": false, "error": error }),
+    };
+    write_reply(&mut stream, reply);
+}
+
+fn to_json<T: Serialize>(value: T) -> Result<serde_json::Value, String> {
+    serde_json::to_value(value).map_err(|e| format!("Failed to serialize result: {}", e))
+}
+
+async fn dispatch(command: &str, args: serde_json::Value, app_handle: AppHandle) -> Result<serde_json::Value, String> {
+    let build_args = || -> Result<BuildArgs, String> {
+        if args.is_null() {
+            return Ok(BuildArgs::default());
+        }
+        serde_json::from_value(args.clone()).map_err(|e| format!("Invalid arguments for {}: {}", command, e))
+    };
+
+    match command {
+        "build_project" => {
+            let a = build_args()?;
+            to_json(
+                crate::build_project(a.project_path, a.scheme, a.configuration, a.device, a.development_team, a.code_sign_identity, app_handle)
+                    .await
+                    .map_err(|e| e.to_string())?,
+            )
+        }
+        "run_project" => {
+            let a = build_args()?;
+            to_json(
+                crate::run_project(a.project_path, a.scheme, a.configuration, a.device, a.auto_stream_logs, a.env_profile, a.launch_env, a.queue, a.launch_overrides, a.headless, app_handle)
+                    .await
+                    .map_err(|e| e.to_string())?,
+            )
+        }
+        "run_tests" => {
+            let a = build_args()?;
+            let project_path = a.project_path.ok_or("run_tests requires projectPath")?;
+            to_json(
+                crate::run_tests(project_path, a.scheme, a.device, a.only_testing, app_handle)
+                    .await
+                    .map_err(|e| e.to_string())?,
+            )
+        }
+        "take_screenshot" => {
+            let options = match args.get("options") {
+                Some(o) => Some(serde_json::from_value(o.clone())
+                    .map_err(|e| format!("Invalid arguments for {}: {}", command, e))?),
+                None => None,
+            };
+            // The agent is who looks at these, so they're sized for a prompt
+            to_json(crate::capture_screenshot(None, options, crate::screenshot_presets::AGENT_PRESET, &app_handle)?)
+        }
+        "verify_change" => {
+            let project_path = args
+                .get("projectPath")
+                .and_then(|p| p.as_str())
+                .ok_or("verify_change requires projectPath")?
+                .to_string();
+            let options = match args.get("options") {
+                Some(o) => serde_json::from_value(o.clone())
+                    .map_err(|e| format!("Invalid arguments for {}: {}", command, e))?,
+                None => crate::verify::VerifyOptions::default(),
+            };
+            to_json(crate::verify::verify_change(project_path, options, app_handle).await.map_err(|e| e.to_string())?)
+        }
+        "wait_for_ui_idle" => {
+            let device_id = args.get("deviceId").and_then(|d| d.as_str()).map(String::from);
+            let device = crate::simulator_target(device_id, app_handle.state::<Mutex<crate::AppState>>().inner())
+                .map_err(|e| e.to_string())?;
+            let options: crate::ui_idle::IdleOptions = match args.get("options") {
+                Some(o) => serde_json::from_value(o.clone())
+                    .map_err(|e| format!("Invalid arguments for {}: {}", command, e))?,
+                None => crate::ui_idle::IdleOptions::default(),
+            };
+            let waited = tauri::async_runtime::spawn_blocking(move || crate::ui_idle::wait_for_idle(&device, &options))
+                .await
+                .map_err(|e| format!("Idle wait task failed: {}", e))?;
+            to_json(waited?)
+        }
+        "get_view_hierarchy_compact" => {
+            let max_tokens = args.get("maxTokens").and_then(|t| t.as_u64()).map(|t| t as usize);
+            let focus = args.get("focusAccessibilityId").and_then(|f| f.as_str()).map(String::from);
+            let compact = tauri::async_runtime::spawn_blocking(move || crate::view_hierarchy_compact(max_tokens, focus))
+                .await
+                .map_err(|e| format!("View hierarchy task failed: {}", e))?;
+            to_json(compact?)
+        }
+        "get_logs_compact" => {
+            let max_tokens = args.get("maxTokens").and_then(|t| t.as_u64()).map(|t| t as usize);
+            let since = args.get("since").and_then(|s| s.as_u64());
+            to_json(crate::logs_compact(&app_handle, max_tokens, since)?)
+        }
+        "get_build_failure_context" => {
+            let build_id = args.get("buildId").and_then(|b| b.as_str()).map(String::from);
+            let project_path = args.get("projectPath").and_then(|p| p.as_str()).map(String::from);
+            let max_tokens = args
+                .get("maxTokens")
+                .and_then(|t| t.as_u64())
+                .map_or(crate::context_budget::DEFAULT_MAX_TOKENS, |t| t as usize);
+            let context = tauri::async_runtime::spawn_blocking(move || {
+                crate::build_failure::for_build(project_path.as_deref(), build_id.as_deref(), max_tokens)
+            })
+            .await
+            .map_err(|e| format!("Build failure context task failed: {}", e))?;
+            to_json(context?)
+        }
+        other => Err(format!("Unknown bridge command: {}", other)),
+    }
+}
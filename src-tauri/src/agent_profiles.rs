@@ -0,0 +1,84 @@
+//! Saved Claude session templates ("agents")
+//!
+//! An `AgentProfile` bundles what a kind of session usually starts with: the
+//! model, skills, permission mode, extra system prompt and a turn limit.
+//! Profiles are kept in the user preferences; "Builder" and "Planner" are
+//! written there the first time profiles are read, and can be edited or
+//! deleted like any other.
+
+use serde::{Deserialize, Serialize};
+
+/// Permission modes the Agent SDK accepts
+const PERMISSION_MODES: &[&str] = &["default", "acceptEdits", "bypassPermissions", "plan"];
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct AgentProfile {
+    pub name: String,
+    /// "sonnet", "opus" or "haiku"; None uses the session default
+    pub model: Option<String>,
+    pub skills: Vec<String>,
+    /// One of the SDK's permission modes; None uses the session default
+    pub permission_mode: Option<String>,
+    /// Added to the end of the system prompt
+    pub append_system_prompt: Option<String>,
+    /// Turns per query before the SDK stops; None means no limit
+    pub max_turns: Option<u32>,
+}
+
+fn default_profiles() -> Vec<AgentProfile> {
+    vec![
+        AgentProfile {
+            name: "Builder".to_string(),
+            model: Some("sonnet".to_string()),
+            permission_mode: Some("acceptEdits".to_string()),
+            ..Default::default()
+        },
+        AgentProfile {
+            name: "Planner".to_string(),
+            model: Some("haiku".to_string()),
+            permission_mode: Some("plan".to_string()),
+            ..Default::default()
+        },
+    ]
+}
+
+/// The saved profiles, seeding the defaults when none have ever been saved.
+/// Returns whether `saved` was seeded and needs writing back.
+pub fn ensure_defaults(saved: &mut Option<Vec<AgentProfile>>) -> bool {
+    if saved.is_some() {
+        return false;
+    }
+    *saved = Some(default_profiles());
+    true
+}
+
+pub fn find<'a>(profiles: &'a [AgentProfile], name: &str) -> Option<&'a AgentProfile> {
+    profiles.iter().find(|p| p.name == name)
+}
+
+/// Check a profile before it's saved, so a typo fails here instead of when a
+/// session starts
+pub fn validate(profile: &AgentProfile) -> Result<(), String> {
+    if profile.name.trim().is_empty() {
+        return Err("Profile name can't be empty".to_string());
+    }
+    if let Some(model) = profile.model.as_deref().filter(|m| !matches!(*m, "sonnet" | "opus" | "haiku")) {
+        return Err(format!("Unknown model '{}'; use sonnet, opus or haiku", model));
+    }
+    if let Some(mode) = profile.permission_mode.as_deref().filter(|m| !PERMISSION_MODES.contains(m)) {
+        return Err(format!("Unknown permission mode '{}'; use one of {}", mode, PERMISSION_MODES.join(", ")));
+    }
+    if profile.max_turns == Some(0) {
+        return Err("maxTurns must be at least 1".to_string());
+    }
+    Ok(())
+}
+
+/// Save `profile`, replacing one with the same name
+pub fn upsert(profiles: &mut Vec<AgentProfile>, profile: AgentProfile) {
+    match profiles.iter_mut().find(|p| p.name == profile.name) {
+        Some(existing) => *existing = profile,
+        None => profiles.push(profile),
+    }
+}
@@ -0,0 +1,263 @@
+//! UserDefaults inspection and editing for apps running in the simulator.
+//!
+//! Reads go through `defaults export <domain> -` so we get real plist XML instead
+//! of the `defaults read` pretty-printer output. Scalar writes use `defaults write`
+//! with a type flag; dictionaries and arrays round-trip the whole domain through a
+//! temp plist and `defaults import`.
+
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine as _};
+use serde::{Deserialize, Serialize};
+use std::process::Command;
+
+use crate::errors::CommandError;
+
+/// Upper bound on the JSON we hand back to the frontend
+const MAX_DEFAULTS_BYTES: usize = 256 * 1024;
+/// Individual values larger than this are replaced with a placeholder
+const MAX_VALUE_BYTES: usize = 16 * 1024;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AppDefaults {
+    pub device_id: String,
+    pub bundle_id: String,
+    pub values: serde_json::Map<String, serde_json::Value>,
+    pub key_count: usize,
+    pub truncated: bool,
+}
+
+/// Whether `device_id` names a simulator (or the `booted` alias)
+fn is_simulator(device_id: &str) -> bool {
+    if device_id == "booted" {
+        return true;
+    }
+
+    let Ok(output) = Command::new("xcrun")
+        .args(["simctl", "list", "devices", "-j"])
+        .output()
+    else {
+        return false;
+    };
+
+    let Ok(json) = serde_json::from_slice::<serde_json::Value>(&output.stdout) else {
+        return false;
+    };
+
+    json.get("devices")
+        .and_then(|d| d.as_object())
+        .map(|runtimes| {
+            runtimes.values().filter_map(|v| v.as_array()).flatten().any(|device| {
+                device.get("udid").and_then(|u| u.as_str()) == Some(device_id)
+            })
+        })
+        .unwrap_or(false)
+}
+
+fn require_simulator(device_id: &str) -> Result<(), CommandError> {
+    if is_simulator(device_id) {
+        Ok(())
+    } else {
+        Err(CommandError::Unsupported(format!(
+            "UserDefaults access is only supported on simulators; {} is not a simulator",
+            device_id
+        )))
+    }
+}
+
+fn simctl_defaults(device_id: &str, args: &[&str]) -> Result<std::process::Output, CommandError> {
+    Command::new("xcrun")
+        .args(["simctl", "spawn", device_id, "defaults"])
+        .args(args)
+        .output()
+        .map_err(|e| CommandError::Failed(format!("Failed to run defaults: {}", e)))
+}
+
+fn plist_to_json(value: &plist::Value) -> serde_json::Value {
+    match value {
+        plist::Value::Dictionary(dict) => serde_json::Value::Object(
+            dict.iter().map(|(k, v)| (k.clone(), plist_to_json(v))).collect(),
+        ),
+        plist::Value::Array(items) => serde_json::Value::Array(items.iter().map(plist_to_json).collect()),
+        plist::Value::Boolean(b) => serde_json::Value::Bool(*b),
+        plist::Value::Integer(i) => i
+            .as_signed()
+            .map(serde_json::Value::from)
+            .or_else(|| i.as_unsigned().map(serde_json::Value::from))
+            .unwrap_or(serde_json::Value::Null),
+        plist::Value::Real(r) => serde_json::Value::from(*r),
+        plist::Value::String(s) => serde_json::Value::String(s.clone()),
+        plist::Value::Date(d) => serde_json::Value::String(d.to_xml_format()),
+        plist::Value::Data(bytes) => serde_json::Value::String(BASE64.encode(bytes)),
+        plist::Value::Uid(uid) => serde_json::Value::from(uid.get()),
+        _ => serde_json::Value::Null,
+    }
+}
+
+fn json_to_plist(value: &serde_json::Value) -> Result<plist::Value, CommandError> {
+    Ok(match value {
+        serde_json::Value::Object(map) => {
+            let mut dict = plist::Dictionary::new();
+            for (k, v) in map {
+                dict.insert(k.clone(), json_to_plist(v)?);
+            }
+            plist::Value::Dictionary(dict)
+        }
+        serde_json::Value::Array(items) => {
+            plist::Value::Array(items.iter().map(json_to_plist).collect::<Result<_, _>>()?)
+        }
+        serde_json::Value::Bool(b) => plist::Value::Boolean(*b),
+        serde_json::Value::Number(n) => match (n.as_i64(), n.as_f64()) {
+            (Some(i), _) => plist::Value::Integer(i.into()),
+            (None, Some(f)) => plist::Value::Real(f),
+            _ => return Err(CommandError::Failed(format!("Unsupported number: {}", n))),
+        },
+        serde_json::Value::String(s) => plist::Value::String(s.clone()),
+        serde_json::Value::Null => {
+            return Err(CommandError::Failed("null can't be stored in UserDefaults".to_string()))
+        }
+    })
+}
+
+/// Read an app's defaults domain as JSON, capped at `MAX_DEFAULTS_BYTES`
+pub fn read_app_defaults(device_id: &str, bundle_id: &str) -> Result<AppDefaults, CommandError> {
+    require_simulator(device_id)?;
+
+    let output = simctl_defaults(device_id, &["export", bundle_id, "-"])?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(CommandError::Failed(format!("defaults export failed: {}", stderr.trim())));
+    }
+
+    let dict = match plist::Value::from_reader_xml(output.stdout.as_slice()) {
+        Ok(plist::Value::Dictionary(dict)) => dict,
+        Ok(_) => plist::Dictionary::new(),
+        Err(e) => return Err(CommandError::Failed(format!("Failed to parse defaults plist: {}", e))),
+    };
+
+    let key_count = dict.len();
+    let mut values = serde_json::Map::new();
+    let mut total_bytes = 0usize;
+    let mut truncated = false;
+
+    for (key, value) in dict.iter() {
+        let mut json = plist_to_json(value);
+        let mut size = json.to_string().len();
+        if size > MAX_VALUE_BYTES {
+            json = serde_json::Value::String(format!("<{} bytes, truncated>", size));
+            size = json.to_string().len();
+            truncated = true;
+        }
+        if total_bytes + key.len() + size > MAX_DEFAULTS_BYTES {
+            truncated = true;
+            break;
+        }
+        total_bytes += key.len() + size;
+        values.insert(key.clone(), json);
+    }
+
+    Ok(AppDefaults {
+        device_id: device_id.to_string(),
+        bundle_id: bundle_id.to_string(),
+        values,
+        key_count,
+        truncated,
+    })
+}
+
+/// Write a single default. `value_type` is one of bool, int, float, string, dict, array.
+pub fn write_app_default(
+    device_id: &str,
+    bundle_id: &str,
+    key: &str,
+    value: &serde_json::Value,
+    value_type: &str,
+) -> Result<(), CommandError> {
+    require_simulator(device_id)?;
+
+    let scalar = match value_type {
+        "bool" => {
+            let b = value
+                .as_bool()
+                .or_else(|| value.as_str().map(|s| matches!(s, "true" | "YES" | "1")))
+                .ok_or("Expected a boolean value")?;
+            Some(("-bool", if b { "YES" } else { "NO" }.to_string()))
+        }
+        "int" => {
+            let i = value
+                .as_i64()
+                .or_else(|| value.as_str().and_then(|s| s.trim().parse().ok()))
+                .ok_or("Expected an integer value")?;
+            Some(("-int", i.to_string()))
+        }
+        "float" => {
+            let f = value
+                .as_f64()
+                .or_else(|| value.as_str().and_then(|s| s.trim().parse().ok()))
+                .ok_or("Expected a numeric value")?;
+            Some(("-float", f.to_string()))
+        }
+        "string" => Some((
+            "-string",
+            value.as_str().map(String::from).unwrap_or_else(|| value.to_string()),
+        )),
+        "dict" | "array" => None,
+        other => return Err(CommandError::Failed(format!("Unsupported defaults type: {}", other))),
+    };
+
+    if let Some((flag, arg)) = scalar {
+        let output = simctl_defaults(device_id, &["write", bundle_id, key, flag, &arg])?;
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            return Err(CommandError::Failed(format!("defaults write failed: {}", stderr.trim())));
+        }
+        return Ok(());
+    }
+
+    let plist_value = json_to_plist(value)?;
+    match (value_type, &plist_value) {
+        ("dict", plist::Value::Dictionary(_)) | ("array", plist::Value::Array(_)) => {}
+        _ => return Err(CommandError::Failed(format!("Value is not a {}", value_type))),
+    }
+
+    // Round-trip the whole domain through a temp plist so nested values keep their types
+    let temp_file = std::env::temp_dir().join(format!(
+        "nocur_defaults_{}_{}.plist",
+        std::process::id(),
+        uuid::Uuid::new_v4()
+    ));
+    let temp_path = temp_file.to_string_lossy().to_string();
+
+    let result = (|| {
+        // `defaults import` replaces the whole domain, so only an app that has
+        // never written a default may start from an empty one
+        let export = simctl_defaults(device_id, &["export", bundle_id, &temp_path])?;
+        let mut dict = if export.status.success() {
+            match plist::Value::from_file(&temp_file) {
+                Ok(plist::Value::Dictionary(dict)) => dict,
+                Ok(_) => return Err(CommandError::Failed(format!("The defaults of {} aren't a dictionary", bundle_id))),
+                Err(e) => return Err(CommandError::Failed(format!("Failed to parse the exported defaults: {}", e))),
+            }
+        } else {
+            let stderr = String::from_utf8_lossy(&export.stderr);
+            if !stderr.contains("does not exist") {
+                return Err(CommandError::Failed(format!("defaults export failed: {}", stderr.trim())));
+            }
+            plist::Dictionary::new()
+        };
+
+        dict.insert(key.to_string(), plist_value);
+        plist::Value::Dictionary(dict)
+            .to_file_xml(&temp_file)
+            .map_err(|e| CommandError::Failed(format!("Failed to write temp plist: {}", e)))?;
+
+        let import = simctl_defaults(device_id, &["import", bundle_id, &temp_path])?;
+        if !import.status.success() {
+            let stderr = String::from_utf8_lossy(&import.stderr);
+            return Err(CommandError::Failed(format!("defaults import failed: {}", stderr.trim())));
+        }
+        Ok(())
+    })();
+
+    let _ = std::fs::remove_file(&temp_file);
+    result
+}
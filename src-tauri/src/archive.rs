@@ -0,0 +1,285 @@
+//! Archiving a project and exporting an IPA
+//!
+//! `xcodebuild archive` writes a Release .xcarchive under the temp directory,
+//! then `xcodebuild -exportArchive` signs it for the chosen distribution with
+//! a generated ExportOptions.plist. The IPA lands in
+//! `~/.nocur/exports/<project id>/<timestamp>/`. Both steps stream through
+//! build events and can be stopped with `cancel_build`; their errors are
+//! parsed and classified like a build's.
+//!
+//! Archives are only needed until they're exported, so each new archive
+//! first removes those older than the `archiveMaxAgeHours` preference.
+
+use serde::{Deserialize, Serialize};
+use std::io::{BufRead, BufReader};
+use std::path::{Path, PathBuf};
+use std::process::{Command, ExitStatus, Stdio};
+use std::sync::atomic::Ordering;
+use std::time::{Duration, Instant, SystemTime};
+use tauri::{AppHandle, Manager};
+
+use crate::messages::msg;
+use crate::signing::SigningOverrides;
+use crate::{BuildError, BuildState};
+
+pub const DEFAULT_MAX_AGE_HOURS: u64 = 24;
+
+/// The ExportOptions.plist `method`
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum ExportMethod {
+    #[default]
+    Development,
+    AdHoc,
+    AppStore,
+}
+
+impl ExportMethod {
+    /// The names Xcode 15.2 and earlier use; later versions still accept them
+    fn plist_value(self) -> &'static str {
+        match self {
+            ExportMethod::Development => "development",
+            ExportMethod::AdHoc => "ad-hoc",
+            ExportMethod::AppStore => "app-store",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ArchiveResult {
+    pub success: bool,
+    pub archive_path: Option<String>,
+    pub ipa_path: Option<String>,
+    /// Errors from whichever step failed, signing ones categorized
+    pub errors: Vec<BuildError>,
+    /// "archive" or "export" when one failed
+    pub failed_step: Option<String>,
+    pub output: String,
+    pub archive_time: f64,
+    /// Stopped by `cancel_build`
+    pub cancelled: bool,
+}
+
+pub struct ArchiveRequest {
+    pub project_path: String,
+    pub project_file: PathBuf,
+    pub is_workspace: bool,
+    pub scheme: String,
+    pub export_method: ExportMethod,
+    pub signing: SigningOverrides,
+    pub max_age: Duration,
+}
+
+fn archives_dir(project_path: &str) -> PathBuf {
+    std::env::temp_dir().join("nocur-archives").join(crate::ace::generate_project_id(project_path))
+}
+
+fn exports_dir(project_path: &str) -> PathBuf {
+    crate::paths::nocur_dir().join("exports").join(crate::ace::generate_project_id(project_path))
+}
+
+/// Remove the project's archives last modified more than `max_age` ago
+pub fn remove_old_archives(project_path: &str, max_age: Duration) {
+    let Ok(entries) = std::fs::read_dir(archives_dir(project_path)) else {
+        return;
+    };
+    let now = SystemTime::now();
+    for path in entries.filter_map(|e| e.ok()).map(|e| e.path()) {
+        let age = std::fs::metadata(&path)
+            .and_then(|m| m.modified())
+            .ok()
+            .and_then(|modified| now.duration_since(modified).ok());
+        if age.is_some_and(|age| age > max_age) {
+            if let Err(e) = std::fs::remove_dir_all(&path) {
+                log::warn!("Failed to remove old archive {}: {}", path.display(), e);
+            }
+        }
+    }
+}
+
+fn write_export_options(path: &Path, method: ExportMethod, signing: &SigningOverrides) -> Result<(), String> {
+    let mut options = plist::Dictionary::new();
+    options.insert("method".to_string(), method.plist_value().into());
+    options.insert("destination".to_string(), "export".into());
+    options.insert("signingStyle".to_string(), "automatic".into());
+    if let Some(team) = &signing.development_team {
+        options.insert("teamID".to_string(), team.clone().into());
+    }
+    if method == ExportMethod::AppStore {
+        options.insert("uploadSymbols".to_string(), true.into());
+    }
+    plist::Value::Dictionary(options)
+        .to_file_xml(path)
+        .map_err(|e| format!("Failed to write {}: {}", path.display(), e))
+}
+
+/// Run `cmd` with its output streamed as build events; returns its exit status,
+/// its output, and whether `cancel_build` stopped it
+fn run_streamed(app_handle: &AppHandle, project_path: &str, mut cmd: Command, tool: &str) -> Result<(ExitStatus, String, bool), String> {
+    cmd.current_dir(project_path);
+    cmd.stdout(Stdio::piped());
+    cmd.stderr(Stdio::piped());
+    // Its own process group, so `cancel_build` stops what it spawns too
+    #[cfg(unix)]
+    std::os::unix::process::CommandExt::process_group(&mut cmd, 0);
+
+    crate::emit_build_event(app_handle, "output", msg!("build.starting_tool", tool = tool));
+    let mut child = cmd.spawn().map_err(|e| format!("Failed to start {}: {}", tool, e))?;
+    if let Some(build_state) = app_handle.try_state::<BuildState>() {
+        build_state.cancelled.store(false, Ordering::SeqCst);
+        *build_state.child_pid.write().unwrap_or_else(|e| e.into_inner()) = Some(child.id());
+    }
+
+    let stdout = child.stdout.take().ok_or("Failed to capture stdout")?;
+    let stderr = child.stderr.take().ok_or("Failed to capture stderr")?;
+    let app_stdout = app_handle.clone();
+    let stdout_handle = std::thread::spawn(move || {
+        let mut output = String::new();
+        for line in BufReader::new(stdout).lines().map_while(Result::ok) {
+            output.push_str(&line);
+            output.push('\n');
+            let trimmed = line.trim();
+            if trimmed.contains("error:") {
+                crate::emit_build_event(&app_stdout, "error", trimmed);
+            } else if trimmed.starts_with("Compiling") || trimmed.starts_with("Compile") {
+                if let Some(file) = trimmed.split_whitespace().last() {
+                    crate::emit_build_event(&app_stdout, "output", msg!("build.compiling_file", file = file));
+                }
+            } else if trimmed.starts_with("CodeSign") || trimmed.starts_with("Signing") {
+                crate::emit_build_event(&app_stdout, "output", msg!("build.signing"));
+            } else if trimmed.starts_with("**") {
+                crate::emit_build_event(&app_stdout, "output", trimmed);
+            }
+        }
+        output
+    });
+    let app_stderr = app_handle.clone();
+    let stderr_handle = std::thread::spawn(move || {
+        let mut output = String::new();
+        for line in BufReader::new(stderr).lines().map_while(Result::ok) {
+            output.push_str(&line);
+            output.push('\n');
+            if line.contains("error:") {
+                crate::emit_build_event(&app_stderr, "error", line.trim());
+            }
+        }
+        output
+    });
+
+    let status = child.wait().map_err(|e| format!("Failed to wait for {}: {}", tool, e))?;
+    let output = format!(
+        "{}\n{}",
+        stdout_handle.join().unwrap_or_default(),
+        stderr_handle.join().unwrap_or_default()
+    );
+    let cancelled = app_handle.try_state::<BuildState>().is_some_and(|build_state| {
+        *build_state.child_pid.write().unwrap_or_else(|e| e.into_inner()) = None;
+        build_state.cancelled.swap(false, Ordering::SeqCst)
+    });
+    Ok((status, output, cancelled))
+}
+
+/// Run one step, adding its output to `result`; false when it failed or was
+/// cancelled, with `result` saying which
+fn run_step(
+    app_handle: &AppHandle,
+    request: &ArchiveRequest,
+    cmd: Command,
+    step: &str,
+    tool: &str,
+    result: &mut ArchiveResult,
+) -> Result<bool, String> {
+    let (status, output, cancelled) = run_streamed(app_handle, &request.project_path, cmd, tool)?;
+    result.output.push_str(&output);
+    if cancelled {
+        // `cancel_build` emits the "cancelled" event
+        result.cancelled = true;
+        return Ok(false);
+    }
+    if !status.success() {
+        let (mut errors, _) = crate::parse_build_errors(&output, Path::new(&request.project_path));
+        crate::signing::classify(&mut errors);
+        crate::emit_build_event(app_handle, "completed", msg!("archive.failed", step = step, count = errors.len()));
+        result.errors = errors;
+        result.failed_step = Some(step.to_string());
+        return Ok(false);
+    }
+    Ok(true)
+}
+
+/// The .ipa `-exportArchive` wrote into `dir`
+fn find_ipa(dir: &Path) -> Option<PathBuf> {
+    std::fs::read_dir(dir)
+        .ok()?
+        .filter_map(|e| e.ok())
+        .map(|e| e.path())
+        .find(|p| p.extension().is_some_and(|ext| ext == "ipa"))
+}
+
+/// Archive `request.scheme` for generic iOS and export it with `request.export_method`
+pub fn archive(app_handle: &AppHandle, request: &ArchiveRequest) -> Result<ArchiveResult, String> {
+    let start_time = Instant::now();
+    remove_old_archives(&request.project_path, request.max_age);
+
+    let stamp = chrono::Local::now().format("%Y%m%d-%H%M%S").to_string();
+    let archive_path = archives_dir(&request.project_path).join(format!("{}-{}.xcarchive", stamp, request.scheme));
+    let export_dir = exports_dir(&request.project_path).join(&stamp);
+    let mut result = ArchiveResult {
+        success: false,
+        archive_path: None,
+        ipa_path: None,
+        errors: Vec::new(),
+        failed_step: None,
+        output: String::new(),
+        archive_time: 0.0,
+        cancelled: false,
+    };
+
+    crate::emit_build_event(app_handle, "started", msg!("archive.started", scheme = request.scheme, method = request.export_method.plist_value()));
+    crate::emit_build_event(app_handle, "output", msg!("build.project", path = request.project_file.display()));
+    let signing_settings = request.signing.settings();
+    if !signing_settings.is_empty() {
+        crate::emit_build_event(app_handle, "output", msg!("build.signing_overrides", settings = signing_settings.join(", ")));
+    }
+
+    let mut cmd = Command::new("xcodebuild");
+    cmd.arg(if request.is_workspace { "-workspace" } else { "-project" }).arg(&request.project_file);
+    cmd.args(["-scheme", &request.scheme, "-configuration", "Release", "-destination", "generic/platform=iOS"]);
+    cmd.arg("-archivePath").arg(&archive_path);
+    cmd.arg("-allowProvisioningUpdates");
+    request.signing.apply(&mut cmd);
+    cmd.arg("archive");
+    let archived = run_step(app_handle, request, cmd, "archive", "xcodebuild archive", &mut result)?;
+    result.archive_time = start_time.elapsed().as_secs_f64();
+    if !archived {
+        return Ok(result);
+    }
+    result.archive_path = Some(archive_path.to_string_lossy().to_string());
+
+    std::fs::create_dir_all(&export_dir).map_err(|e| format!("Failed to create {}: {}", export_dir.display(), e))?;
+    let options_path = export_dir.join("ExportOptions.plist");
+    write_export_options(&options_path, request.export_method, &request.signing)?;
+    crate::emit_build_event(app_handle, "output", msg!("archive.exporting", path = export_dir.display()));
+    let mut cmd = Command::new("xcodebuild");
+    cmd.arg("-exportArchive");
+    cmd.arg("-archivePath").arg(&archive_path);
+    cmd.arg("-exportPath").arg(&export_dir);
+    cmd.arg("-exportOptionsPlist").arg(&options_path);
+    cmd.arg("-allowProvisioningUpdates");
+    let exported = run_step(app_handle, request, cmd, "export", "xcodebuild -exportArchive", &mut result)?;
+    result.archive_time = start_time.elapsed().as_secs_f64();
+    if !exported {
+        return Ok(result);
+    }
+
+    let ipa = find_ipa(&export_dir).ok_or_else(|| format!("xcodebuild -exportArchive left no .ipa in {}", export_dir.display()))?;
+    crate::emit_build_event(
+        app_handle,
+        "completed",
+        msg!("archive.succeeded", seconds = format!("{:.1}", result.archive_time), path = ipa.display()),
+    );
+    result.ipa_path = Some(ipa.to_string_lossy().to_string());
+    result.success = true;
+    Ok(result)
+}
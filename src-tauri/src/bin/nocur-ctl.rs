@@ -0,0 +1,235 @@
+//! nocur-ctl: drive the running Nocur app from a terminal
+//!
+//! Talks to the app's control server over ~/.nocur/control.sock, authenticating
+//! with the token the app writes to ~/.nocur/control.token on launch.
+
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+use std::io::{BufRead, BufReader, Write};
+use std::os::unix::net::UnixStream;
+use std::path::PathBuf;
+use std::process::ExitCode;
+
+const USAGE: &str = "Usage: nocur-ctl <command> [options]
+
+Commands:
+  build [--project PATH] [--scheme NAME]     Build the project
+  run [--project PATH] [--scheme NAME] [--env-profile NAME] [--logs]
+                                             Build, install and launch
+  stop [--bundle-id ID] [--device ID]        Terminate the app (default: the last one run)
+  screenshot [--output FILE]                 Save a simulator screenshot (default: screenshot.png)
+  devices                                    List simulators and connected devices
+  send <message>                             Send a message to the active agent session
+
+--project defaults to the active session's project and the device to the one
+selected in the app. --env-profile launches with <project>/.nocur/env/NAME.env.";
+
+fn nocur_dir() -> PathBuf {
+    dirs::home_dir().unwrap_or_else(|| PathBuf::from(".")).join(".nocur")
+}
+
+/// `--name value` pairs and bare words, in order
+struct Args {
+    flags: Vec<(String, Option<String>)>,
+    words: Vec<String>,
+}
+
+impl Args {
+    fn parse(raw: &[String]) -> Self {
+        let mut flags = Vec::new();
+        let mut words = Vec::new();
+        let mut iter = raw.iter().peekable();
+        while let Some(arg) = iter.next() {
+            if let Some(name) = arg.strip_prefix("--") {
+                let value = match iter.peek() {
+                    Some(next) if !next.starts_with("--") && name != "logs" => iter.next().cloned(),
+                    _ => None,
+                };
+                flags.push((name.to_string(), value));
+            } else {
+                words.push(arg.clone());
+            }
+        }
+        Self { flags, words }
+    }
+
+    fn value(&self, name: &str) -> Option<String> {
+        self.flags.iter().rev().find(|(n, _)| n == name).and_then(|(_, v)| v.clone())
+    }
+
+    fn has(&self, name: &str) -> bool {
+        self.flags.iter().any(|(n, _)| n == name)
+    }
+
+    /// Fail on flags the command doesn't take
+    fn only(&self, allowed: &[&str]) -> Result<(), String> {
+        match self.flags.iter().find(|(n, _)| !allowed.contains(&n.as_str())) {
+            Some((name, _)) => Err(format!("Unknown option --{}", name)),
+            None => Ok(()),
+        }
+    }
+}
+
+fn call(method: &str, params: serde_json::Value) -> Result<serde_json::Value, String> {
+    let dir = nocur_dir();
+    let token = std::fs::read_to_string(dir.join("control.token"))
+        .map_err(|_| "Nocur isn't running (no control token found)".to_string())?;
+    let mut stream = UnixStream::connect(dir.join("control.sock"))
+        .map_err(|e| format!("Couldn't connect to Nocur: {}", e))?;
+
+    let request = serde_json::json!({
+        "id": 1,
+        "token": token.trim(),
+        "method": method,
+        "params": params,
+    });
+    writeln!(stream, "{}", request).map_err(|e| format!("Failed to send request: {}", e))?;
+
+    let mut line = String::new();
+    BufReader::new(&stream)
+        .read_line(&mut line)
+        .map_err(|e| format!("Failed to read response: {}", e))?;
+    let mut reply: serde_json::Value =
+        serde_json::from_str(&line).map_err(|e| format!("Invalid response from Nocur: {}", e))?;
+
+    if let Some(error) = reply.get("error") {
+        return Err(error
+            .get("message")
+            .and_then(|m| m.as_str())
+            .unwrap_or("Unknown error")
+            .to_string());
+    }
+    Ok(reply.get_mut("result").map(|r| r.take()).unwrap_or_default())
+}
+
+fn str_field<'a>(value: &'a serde_json::Value, key: &str) -> &'a str {
+    value.get(key).and_then(|v| v.as_str()).unwrap_or("")
+}
+
+/// Print a BuildResult; returns whether it succeeded
+fn print_build_result(result: &serde_json::Value, verb: &str) -> bool {
+    let success = result.get("success").and_then(|s| s.as_bool()).unwrap_or(false);
+    let warnings = result.get("warnings").and_then(|w| w.as_u64()).unwrap_or(0);
+    let time = result
+        .get("buildTime")
+        .and_then(|t| t.as_f64())
+        .map(|t| format!(" in {:.1}s", t))
+        .unwrap_or_default();
+
+    if success {
+        println!("{} succeeded{} ({} warning{})", verb, time, warnings, if warnings == 1 { "" } else { "s" });
+        if let Some(pid) = result.pointer("/launch/pid").and_then(|p| p.as_u64()) {
+            println!("Launched {} (pid {})", str_field(result, "bundleId"), pid);
+        }
+        return true;
+    }
+
+    eprintln!("{} failed: {}", verb, str_field(result, "output").lines().next().unwrap_or(""));
+    for error in result.get("errors").and_then(|e| e.as_array()).into_iter().flatten() {
+        let file = error
+            .get("relativePath")
+            .or_else(|| error.get("file"))
+            .and_then(|f| f.as_str());
+        let location = match (file, error.get("line").and_then(|l| l.as_u64())) {
+            (Some(file), Some(line)) => match error.get("column").and_then(|c| c.as_u64()) {
+                Some(column) => format!("{}:{}:{}: ", file, line, column),
+                None => format!("{}:{}: ", file, line),
+            },
+            (Some(file), None) => format!("{}: ", file),
+            _ => String::new(),
+        };
+        eprintln!("  {}{}", location, str_field(error, "message"));
+    }
+    false
+}
+
+fn print_devices(result: &serde_json::Value) {
+    let devices = result.get("devices").and_then(|d| d.as_array()).cloned().unwrap_or_default();
+    if devices.is_empty() {
+        println!("No devices found");
+        return;
+    }
+    let name_width = devices.iter().map(|d| str_field(d, "name").len()).max().unwrap_or(0);
+    for device in &devices {
+        println!(
+            "{:<name_width$}  {:<7}  {:<9}  {:<12}  {}",
+            str_field(device, "name"),
+            str_field(device, "osVersion"),
+            str_field(device, "deviceType"),
+            str_field(device, "state"),
+            str_field(device, "id"),
+        );
+    }
+}
+
+fn run(raw: &[String]) -> Result<bool, String> {
+    let Some((command, rest)) = raw.split_first() else {
+        return Err(USAGE.to_string());
+    };
+    let args = Args::parse(rest);
+
+    match command.as_str() {
+        "build" | "run" => {
+            args.only(&["project", "scheme", "env-profile", "logs"])?;
+            let params = serde_json::json!({
+                "projectPath": args.value("project"),
+                "scheme": args.value("scheme"),
+                "envProfile": args.value("env-profile"),
+                "autoStreamLogs": args.has("logs"),
+            });
+            let result = call(command, params)?;
+            Ok(print_build_result(&result, if command == "build" { "Build" } else { "Run" }))
+        }
+        "stop" => {
+            args.only(&["bundle-id", "device"])?;
+            let params = serde_json::json!({
+                "bundleId": args.value("bundle-id"),
+                "deviceId": args.value("device"),
+            });
+            let result = call("stop", params)?;
+            println!("Stopped {}", str_field(&result, "bundleId"));
+            Ok(true)
+        }
+        "screenshot" => {
+            args.only(&["output"])?;
+            let output = args.value("output").unwrap_or_else(|| "screenshot.png".to_string());
+            let result = call("screenshot", serde_json::Value::Null)?;
+            let data_url = result.as_str().unwrap_or("");
+            let encoded = data_url.split_once(',').map(|(_, data)| data).unwrap_or(data_url);
+            let bytes = BASE64.decode(encoded).map_err(|e| format!("Invalid screenshot data: {}", e))?;
+            std::fs::write(&output, bytes).map_err(|e| format!("Failed to write {}: {}", output, e))?;
+            println!("Saved screenshot to {}", output);
+            Ok(true)
+        }
+        "devices" => {
+            args.only(&[])?;
+            print_devices(&call("devices", serde_json::Value::Null)?);
+            Ok(true)
+        }
+        "send" => {
+            args.only(&[])?;
+            if args.words.is_empty() {
+                return Err("Usage: nocur-ctl send <message>".to_string());
+            }
+            call("sendMessage", serde_json::json!({ "message": args.words.join(" ") }))?;
+            println!("Sent");
+            Ok(true)
+        }
+        "help" | "--help" | "-h" => {
+            println!("{}", USAGE);
+            Ok(true)
+        }
+        other => Err(format!("Unknown command '{}'\n\n{}", other, USAGE)),
+    }
+}
+
+fn main() -> ExitCode {
+    let raw: Vec<String> = std::env::args().skip(1).collect();
+    match run(&raw) {
+        Ok(true) => ExitCode::SUCCESS,
+        Ok(false) => ExitCode::FAILURE,
+        Err(message) => {
+            eprintln!("{}", message);
+            ExitCode::FAILURE
+        }
+    }
+}
@@ -0,0 +1,294 @@
+//! The parts of a failed build's output worth an agent's context
+//!
+//! A failed build's output is kept like every build's (see `build_logs`), and
+//! its record in build_runs.json gets a `FailureIndex`: byte ranges of the
+//! lines around each parsed error, of the last `TAIL_LINES` lines and of any
+//! linker or signing section, plus the failing target and phase from
+//! xcodebuild's "The following build commands failed:" summary. A
+//! `FailureContext` reads only those ranges back and fits them to a token
+//! budget (estimated as in `context_budget`), errors first and the tail last.
+
+use serde::{Deserialize, Serialize};
+use std::fs::File;
+use std::io::{Read, Seek, SeekFrom};
+use std::path::Path;
+
+use crate::context_budget::estimate_tokens;
+use crate::BuildError;
+
+/// Lines kept either side of an error's line
+const CONTEXT_LINES: usize = 10;
+const TAIL_LINES: usize = 100;
+/// Lines kept either side of a signing error
+const SIGNING_CONTEXT_LINES: usize = 3;
+/// A linker section without its "linker command failed" line stops here
+const MAX_LINKER_LINES: usize = 60;
+/// Errors indexed per build; the rest are only counted
+const MAX_INDEXED_ERRORS: usize = 50;
+const FAILED_COMMANDS: &str = "The following build commands failed:";
+
+/// A byte range of a build log
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LogSpan {
+    pub start: u64,
+    pub end: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct IndexedError {
+    pub error: BuildError,
+    /// The lines around it; None when the message wasn't found in the output
+    pub context: Option<LogSpan>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LogSection {
+    /// "linker" or "signing"
+    pub kind: String,
+    pub span: LogSpan,
+}
+
+/// Where to find what matters in a failed build's log, kept on its `BuildRun`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FailureIndex {
+    pub log_path: String,
+    pub errors: Vec<IndexedError>,
+    /// Errors beyond `MAX_INDEXED_ERRORS`
+    #[serde(default)]
+    pub unindexed_errors: usize,
+    pub tail: LogSpan,
+    pub sections: Vec<LogSection>,
+    /// e.g. "App", from "(in target 'App' from project 'App')"
+    pub failing_target: Option<String>,
+    /// The failed command's first word, e.g. "CompileSwift", "Ld", "CodeSign"
+    pub failing_phase: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ErrorContext {
+    #[serde(flatten)]
+    pub error: BuildError,
+    /// The raw output around the error
+    pub context: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SectionText {
+    pub kind: String,
+    pub text: String,
+}
+
+/// `get_build_failure_context`
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FailureContext {
+    pub build_id: Option<String>,
+    /// Seconds since the epoch
+    pub finished_at: u64,
+    pub failing_target: Option<String>,
+    pub failing_phase: Option<String>,
+    pub errors: Vec<ErrorContext>,
+    pub sections: Vec<SectionText>,
+    /// The end of the output
+    pub tail: String,
+    pub estimated_tokens: usize,
+    /// Errors, contexts, sections and tail lines left out to fit the budget
+    pub omitted_count: usize,
+}
+
+/// Byte offset of each line's start, plus the end of the text
+fn line_starts(output: &str) -> Vec<usize> {
+    let mut starts = vec![0];
+    starts.extend(output.match_indices('\n').map(|(i, _)| i + 1));
+    if starts.last() != Some(&output.len()) {
+        starts.push(output.len());
+    }
+    starts
+}
+
+/// Lines `first..=last` (clamped) as a byte range
+fn span(starts: &[usize], first: usize, last: usize) -> LogSpan {
+    let line_count = starts.len() - 1;
+    if line_count == 0 {
+        return LogSpan { start: 0, end: 0 };
+    }
+    let first = first.min(line_count - 1);
+    let last = last.min(line_count - 1).max(first);
+    LogSpan { start: starts[first] as u64, end: starts[last + 1] as u64 }
+}
+
+fn failing_command(lines: &[&str]) -> (Option<String>, Option<String>) {
+    let Some(header) = lines.iter().position(|l| l.contains(FAILED_COMMANDS)) else {
+        return (None, None);
+    };
+    let Some(command) = lines[header + 1..].iter().map(|l| l.trim()).find(|l| !l.is_empty()) else {
+        return (None, None);
+    };
+    let phase = command.split_whitespace().next().map(String::from);
+    let target = command
+        .split_once("(in target '")
+        .and_then(|(_, rest)| rest.split_once('\''))
+        .map(|(target, _)| target.to_string());
+    (target, phase)
+}
+
+/// Ranges of `(first line, last line)` for linker and signing failures
+fn sections(lines: &[&str]) -> Vec<(&'static str, usize, usize)> {
+    let mut found = Vec::new();
+    let linker_start = lines
+        .iter()
+        .position(|l| l.contains("Undefined symbols for architecture") || l.trim_start().starts_with("ld: "));
+    if let Some(start) = linker_start {
+        let end = lines[start..]
+            .iter()
+            .take(MAX_LINKER_LINES)
+            .position(|l| l.contains("linker command failed"))
+            .map_or((start + MAX_LINKER_LINES).min(lines.len()) - 1, |offset| start + offset);
+        found.push(("linker", start, end));
+    }
+
+    let mut signing: Vec<(usize, usize)> = Vec::new();
+    for (i, line) in lines.iter().enumerate() {
+        let is_signing_error = line.contains("error") && crate::signing::category(line).is_some();
+        if !is_signing_error || line.contains(FAILED_COMMANDS) {
+            continue;
+        }
+        let (first, last) = (i.saturating_sub(SIGNING_CONTEXT_LINES), (i + SIGNING_CONTEXT_LINES).min(lines.len() - 1));
+        match signing.last_mut() {
+            Some((_, previous_last)) if first <= *previous_last + 1 => *previous_last = last,
+            _ => signing.push((first, last)),
+        }
+    }
+    found.extend(signing.into_iter().map(|(first, last)| ("signing", first, last)));
+    found
+}
+
+/// Index the failed build's `output`, as written to `log_path`
+pub fn index(log_path: &Path, output: &str, errors: &[BuildError]) -> FailureIndex {
+    let lines: Vec<&str> = output.lines().collect();
+    let starts = line_starts(output);
+    let line_count = starts.len() - 1;
+    let indexed = errors
+        .iter()
+        .take(MAX_INDEXED_ERRORS)
+        .map(|error| {
+            let at = lines.iter().position(|l| l.contains(&error.message));
+            IndexedError {
+                error: error.clone(),
+                context: at.map(|i| span(&starts, i.saturating_sub(CONTEXT_LINES), i + CONTEXT_LINES)),
+            }
+        })
+        .collect();
+    let (failing_target, failing_phase) = failing_command(&lines);
+
+    FailureIndex {
+        log_path: log_path.to_string_lossy().to_string(),
+        errors: indexed,
+        unindexed_errors: errors.len().saturating_sub(MAX_INDEXED_ERRORS),
+        tail: span(&starts, line_count.saturating_sub(TAIL_LINES), line_count),
+        sections: sections(&lines)
+            .into_iter()
+            .map(|(kind, first, last)| LogSection { kind: kind.to_string(), span: span(&starts, first, last) })
+            .collect(),
+        failing_target,
+        failing_phase,
+    }
+}
+
+fn read_span(file: &mut File, span: LogSpan) -> Option<String> {
+    let mut buffer = vec![0; span.end.saturating_sub(span.start) as usize];
+    file.seek(SeekFrom::Start(span.start)).ok()?;
+    file.read_exact(&mut buffer).ok()?;
+    Some(String::from_utf8_lossy(&buffer).trim_end().to_string())
+}
+
+/// The indexed parts of the build's log, fitted to `max_tokens`: every error
+/// first, then their surrounding lines, the linker and signing sections, and
+/// as much of the tail as is left room for
+pub fn context(
+    index: &FailureIndex,
+    build_id: Option<String>,
+    finished_at: u64,
+    max_tokens: usize,
+) -> Result<FailureContext, String> {
+    let mut file = File::open(&index.log_path)
+        .map_err(|e| format!("The build's log is no longer available ({}): {}", index.log_path, e))?;
+    let mut used = 0;
+    let mut omitted = index.unindexed_errors;
+    let mut fits = |text: &str| {
+        let tokens = estimate_tokens(text);
+        let fit = used + tokens <= max_tokens;
+        if fit {
+            used += tokens;
+        }
+        fit
+    };
+
+    let mut errors = Vec::new();
+    for indexed in &index.errors {
+        match serde_json::to_string(&indexed.error) {
+            Ok(json) if fits(&json) => errors.push(ErrorContext { error: indexed.error.clone(), context: None }),
+            _ => omitted += 1,
+        }
+    }
+    for (error, indexed) in errors.iter_mut().zip(&index.errors) {
+        let Some(text) = indexed.context.and_then(|span| read_span(&mut file, span)) else {
+            continue;
+        };
+        match fits(&text) {
+            true => error.context = Some(text),
+            false => omitted += 1,
+        }
+    }
+    let mut sections = Vec::new();
+    for section in &index.sections {
+        let Some(text) = read_span(&mut file, section.span) else {
+            continue;
+        };
+        match fits(&text) {
+            true => sections.push(SectionText { kind: section.kind.clone(), text }),
+            false => omitted += 1,
+        }
+    }
+
+    // The tail's last lines matter most, so it's cut from the front
+    let tail_text = read_span(&mut file, index.tail).unwrap_or_default();
+    let mut tail_lines: Vec<&str> = Vec::new();
+    for line in tail_text.lines().rev() {
+        if !fits(line) {
+            break;
+        }
+        tail_lines.push(line);
+    }
+    omitted += tail_text.lines().count() - tail_lines.len();
+    tail_lines.reverse();
+
+    Ok(FailureContext {
+        build_id,
+        finished_at,
+        failing_target: index.failing_target.clone(),
+        failing_phase: index.failing_phase.clone(),
+        errors,
+        sections,
+        tail: tail_lines.join("\n"),
+        estimated_tokens: used,
+        omitted_count: omitted,
+    })
+}
+
+/// `context` for the build with `build_id`, or the newest failed build of
+/// `project_path` (or of any project)
+pub fn for_build(project_path: Option<&str>, build_id: Option<&str>, max_tokens: usize) -> Result<FailureContext, String> {
+    let run = crate::build_history::failed_run(project_path, build_id).ok_or_else(|| match build_id {
+        Some(id) => format!("No failed build {} with a kept log", id),
+        None => "No failed build with a kept log".to_string(),
+    })?;
+    let index = run.failure.as_ref().ok_or("No failed build with a kept log")?;
+    context(index, run.build_id.clone(), run.finished_at, max_tokens)
+}
@@ -0,0 +1,321 @@
+//! Which Xcode last built each project, and the project's recent builds
+//!
+//! The first build after an Xcode update often fails on module caches the
+//! old compiler left in DerivedData. The version behind each project's last
+//! successful build is kept in `~/.nocur/build_history.json`, so a build can
+//! tell the toolchain changed and clean the stale caches before it trips on them.
+//!
+//! Every finished build is also appended to `~/.nocur/build_runs.json`, tagged
+//! with the Claude session that was active, for session reports. A simulator
+//! launch after the build adds its launch time, flagged when it's well over the
+//! median of the launches before it. Each run names its kept output (see
+//! `build_logs`), and a failed one also keeps where to find its errors in it;
+//! see `build_failure`.
+
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Folders inside DerivedData built by a specific compiler. Package checkouts
+/// (SourcePackages) are left alone so an offline rebuild still works.
+const COMPILER_CACHE_DIRS: &[&str] = &["Build", "ModuleCache.noindex"];
+
+/// Builds kept per project in the run log
+const MAX_RUNS: usize = 100;
+
+/// Earlier launches a launch time is compared against
+const LAUNCH_MEDIAN_WINDOW: usize = 10;
+/// Fewer earlier launches than this and there's no median to compare with
+const LAUNCH_MEDIAN_MIN: usize = 3;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BuildRecord {
+    /// e.g. "Xcode 16.2 (16C5032a)"
+    pub xcode_version: String,
+    /// Seconds since the epoch
+    pub succeeded_at: u64,
+}
+
+/// One finished build
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BuildRun {
+    /// The Claude session active when it finished
+    pub session_id: Option<String>,
+    pub succeeded: bool,
+    /// Seconds since the epoch
+    pub finished_at: u64,
+    pub duration_secs: f64,
+    pub error_count: usize,
+    /// Set by `run_project` on a simulator; see `launch_timing`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub launch_duration_ms: Option<u64>,
+    /// The launch was slower than the trailing median by more than the threshold
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub launch_regression: bool,
+    /// The event journal's ID for the build; these restart with the app
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub build_id: Option<String>,
+    /// Set on a failed build whose output was kept
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub failure: Option<crate::build_failure::FailureIndex>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub scheme: Option<String>,
+    /// The device's name; None for the default simulator
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub device: Option<String>,
+    /// The kept output, for `read_build_log`; see `build_logs`. Cleared in
+    /// `list_build_history` once the log has been rotated out.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub log_id: Option<String>,
+}
+
+/// What `record_run` records about a finished build
+pub struct FinishedBuild {
+    pub session_id: Option<String>,
+    pub succeeded: bool,
+    pub duration_secs: f64,
+    pub error_count: usize,
+    pub build_id: Option<String>,
+    pub failure: Option<crate::build_failure::FailureIndex>,
+    pub scheme: Option<String>,
+    pub device: Option<String>,
+    pub log_id: Option<String>,
+}
+
+/// A timed launch, as `get_launch_time_history` reports it
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LaunchTime {
+    /// When the build before the launch finished, seconds since the epoch
+    pub finished_at: u64,
+    pub session_id: Option<String>,
+    pub launch_duration_ms: u64,
+    /// Median of up to `LAUNCH_MEDIAN_WINDOW` launches before this one
+    pub median_ms: Option<u64>,
+    pub regression: bool,
+}
+
+fn history_path() -> PathBuf {
+    crate::paths::nocur_dir().join("build_history.json")
+}
+
+fn read_history() -> BTreeMap<String, BuildRecord> {
+    std::fs::read_to_string(history_path())
+        .ok()
+        .and_then(|content| serde_json::from_str(&content).ok())
+        .unwrap_or_default()
+}
+
+/// The selected Xcode, from `xcodebuild -version`
+pub fn xcode_version() -> Option<String> {
+    let output = Command::new("xcodebuild").arg("-version").output().ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let mut lines = stdout.lines().map(str::trim);
+    let version = lines.next().filter(|l| !l.is_empty())?.to_string();
+    match lines.find_map(|l| l.strip_prefix("Build version ")) {
+        Some(build) => Some(format!("{} ({})", version, build)),
+        None => Some(version),
+    }
+}
+
+/// The Xcode behind the project's last successful build
+pub fn last_xcode_version(project_path: &str) -> Option<String> {
+    read_history()
+        .remove(&crate::ace::generate_project_id(project_path))
+        .map(|r| r.xcode_version)
+}
+
+pub fn record_success(project_path: &str, xcode_version: &str) -> Result<(), String> {
+    let mut history = read_history();
+    history.insert(
+        crate::ace::generate_project_id(project_path),
+        BuildRecord {
+            xcode_version: xcode_version.to_string(),
+            succeeded_at: SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_secs(),
+        },
+    );
+
+    let path = history_path();
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent).map_err(|e| format!("Failed to create {}: {}", parent.display(), e))?;
+    }
+    let content = serde_json::to_string_pretty(&history)
+        .map_err(|e| format!("Failed to serialize build history: {}", e))?;
+    std::fs::write(&path, content).map_err(|e| format!("Failed to write {}: {}", path.display(), e))
+}
+
+fn write_runs(runs: &BTreeMap<String, Vec<BuildRun>>) -> Result<(), String> {
+    let path = runs_path();
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent).map_err(|e| format!("Failed to create {}: {}", parent.display(), e))?;
+    }
+    let content = serde_json::to_string_pretty(runs).map_err(|e| format!("Failed to serialize build runs: {}", e))?;
+    std::fs::write(&path, content).map_err(|e| format!("Failed to write {}: {}", path.display(), e))
+}
+
+fn runs_path() -> PathBuf {
+    crate::paths::nocur_dir().join("build_runs.json")
+}
+
+fn read_runs() -> BTreeMap<String, Vec<BuildRun>> {
+    std::fs::read_to_string(runs_path())
+        .ok()
+        .and_then(|content| serde_json::from_str(&content).ok())
+        .unwrap_or_default()
+}
+
+pub fn record_run(project_path: &str, build: FinishedBuild) -> Result<(), String> {
+    let mut runs = read_runs();
+    let project_runs = runs.entry(crate::ace::generate_project_id(project_path)).or_default();
+    project_runs.push(BuildRun {
+        session_id: build.session_id,
+        succeeded: build.succeeded,
+        finished_at: SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_secs(),
+        duration_secs: build.duration_secs,
+        error_count: build.error_count,
+        launch_duration_ms: None,
+        launch_regression: false,
+        build_id: build.build_id,
+        failure: build.failure,
+        scheme: build.scheme,
+        device: build.device,
+        log_id: build.log_id,
+    });
+    let excess = project_runs.len().saturating_sub(MAX_RUNS);
+    project_runs.drain(..excess);
+    write_runs(&runs)
+}
+
+fn median(mut values: Vec<u64>) -> Option<u64> {
+    if values.len() < LAUNCH_MEDIAN_MIN {
+        return None;
+    }
+    values.sort_unstable();
+    let mid = values.len() / 2;
+    Some(if values.len() % 2 == 0 { (values[mid - 1] + values[mid]) / 2 } else { values[mid] })
+}
+
+/// Launch times among `runs`, each compared with the ones before it
+fn launch_times(runs: &[BuildRun]) -> Vec<LaunchTime> {
+    let mut earlier: Vec<u64> = Vec::new();
+    let mut times = Vec::new();
+    for run in runs {
+        let Some(duration) = run.launch_duration_ms else {
+            continue;
+        };
+        let window = earlier[earlier.len().saturating_sub(LAUNCH_MEDIAN_WINDOW)..].to_vec();
+        times.push(LaunchTime {
+            finished_at: run.finished_at,
+            session_id: run.session_id.clone(),
+            launch_duration_ms: duration,
+            median_ms: median(window),
+            regression: run.launch_regression,
+        });
+        earlier.push(duration);
+    }
+    times
+}
+
+/// Attach a launch time to the project's latest build and judge it against
+/// the trailing median; a regression is more than `threshold_percent` slower
+pub fn record_launch(project_path: &str, launch_duration_ms: u64, threshold_percent: u32) -> Result<LaunchTime, String> {
+    let mut runs = read_runs();
+    let project_runs = runs.get_mut(&crate::ace::generate_project_id(project_path)).ok_or("No build recorded for the launch")?;
+    let median = median(launch_times(project_runs).iter().rev().take(LAUNCH_MEDIAN_WINDOW).map(|t| t.launch_duration_ms).collect());
+    let run = project_runs.last_mut().ok_or("No build recorded for the launch")?;
+    let regression = median.is_some_and(|median| {
+        launch_duration_ms as f64 > median as f64 * (1.0 + f64::from(threshold_percent) / 100.0)
+    });
+    run.launch_duration_ms = Some(launch_duration_ms);
+    run.launch_regression = regression;
+    let time = LaunchTime {
+        finished_at: run.finished_at,
+        session_id: run.session_id.clone(),
+        launch_duration_ms,
+        median_ms: median,
+        regression,
+    };
+    write_runs(&runs)?;
+    Ok(time)
+}
+
+/// The project's timed launches, newest first
+pub fn launch_history(project_path: &str, limit: usize) -> Vec<LaunchTime> {
+    let runs = read_runs().remove(&crate::ace::generate_project_id(project_path)).unwrap_or_default();
+    let mut times = launch_times(&runs);
+    times.reverse();
+    times.truncate(limit);
+    times
+}
+
+/// The project's builds that finished during `session_id`, oldest first
+pub fn runs_for_session(project_path: &str, session_id: &str) -> Vec<BuildRun> {
+    read_runs()
+        .remove(&crate::ace::generate_project_id(project_path))
+        .unwrap_or_default()
+        .into_iter()
+        .filter(|run| run.session_id.as_deref() == Some(session_id))
+        .collect()
+}
+
+/// The project's builds, newest first
+pub fn project_runs(project_path: &str, limit: usize) -> Vec<BuildRun> {
+    let mut runs = read_runs().remove(&crate::ace::generate_project_id(project_path)).unwrap_or_default();
+    runs.reverse();
+    runs.truncate(limit);
+    for run in &mut runs {
+        if run.log_id.as_deref().is_some_and(|id| crate::build_logs::find(id).is_none()) {
+            run.log_id = None;
+        }
+    }
+    runs
+}
+
+/// The newest failed build with a kept log: the one with `build_id` if given,
+/// of `project_path` if given, else of any project
+pub fn failed_run(project_path: Option<&str>, build_id: Option<&str>) -> Option<BuildRun> {
+    let mut runs = read_runs();
+    let candidates: Vec<BuildRun> = match project_path {
+        Some(path) => runs.remove(&crate::ace::generate_project_id(path)).unwrap_or_default(),
+        None => runs.into_values().flatten().collect(),
+    };
+    candidates
+        .into_iter()
+        .filter(|run| run.failure.is_some())
+        .filter(|run| build_id.map_or(true, |id| run.build_id.as_deref() == Some(id)))
+        .max_by_key(|run| run.finished_at)
+}
+
+/// Remove what the previous compiler built from `derived_data`
+pub fn clean_compiler_caches(derived_data: &Path) -> Result<(), String> {
+    for dir in COMPILER_CACHE_DIRS.iter().map(|name| derived_data.join(name)) {
+        if dir.exists() {
+            std::fs::remove_dir_all(&dir).map_err(|e| format!("Failed to remove {}: {}", dir.display(), e))?;
+        }
+    }
+    Ok(())
+}
+
+/// Error text that means DerivedData was built by another compiler and a
+/// clean build fixes it
+pub fn is_stale_cache_error(message: &str) -> bool {
+    const SIGNATURES: &[&str] = &[
+        "compiled with a different version",
+        "cannot be imported by the swift",
+        "module file was created by a",
+        "malformed or corrupted ast file",
+        "has been modified since the module file",
+        "has been modified since the precompiled header",
+        "was built by a different version",
+        "module cache path",
+    ];
+    let lower = message.to_lowercase();
+    SIGNATURES.iter().any(|s| lower.contains(s))
+}
@@ -0,0 +1,71 @@
+//! Every build's output, kept on disk
+//!
+//! `BuildResult.output` is gone once the caller drops it, so each finished
+//! build's redacted output is written to `~/.nocur/build_logs/<project id>/`
+//! as `<timestamp>-<build id>.log`; the file stem is the build's `log_id`.
+//! Its record in build_runs.json is the index: scheme, device, outcome,
+//! duration and error count. A failed build's `FailureIndex` points into the
+//! same file.
+//!
+//! Only the newest `buildLogRetention` logs per project are kept.
+
+use std::path::{Path, PathBuf};
+
+pub const DEFAULT_RETENTION: usize = 20;
+
+fn root() -> PathBuf {
+    crate::paths::nocur_dir().join("build_logs")
+}
+
+fn logs_dir(project_path: &str) -> PathBuf {
+    root().join(crate::ace::generate_project_id(project_path))
+}
+
+/// Write a build's output, already redacted, and drop the project's logs
+/// beyond the newest `retention`. Returns the log's ID and path.
+pub fn write(project_path: &str, build_id: Option<&str>, output: &str, retention: usize) -> Result<(String, PathBuf), String> {
+    let dir = logs_dir(project_path);
+    std::fs::create_dir_all(&dir).map_err(|e| format!("Failed to create {}: {}", dir.display(), e))?;
+    let stamp = chrono::Utc::now().format("%Y%m%d-%H%M%S%.3f");
+    let log_id = format!("{}-{}", stamp, build_id.unwrap_or("build"));
+    let log_path = dir.join(format!("{}.log", log_id));
+    std::fs::write(&log_path, output).map_err(|e| format!("Failed to write {}: {}", log_path.display(), e))?;
+    prune(&dir, retention.max(1));
+    Ok((log_id, log_path))
+}
+
+/// Keep the newest `retention` logs; names start with a timestamp
+fn prune(dir: &Path, retention: usize) {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return;
+    };
+    let mut logs: Vec<PathBuf> = entries
+        .filter_map(|e| e.ok())
+        .map(|e| e.path())
+        .filter(|p| p.extension().is_some_and(|ext| ext == "log"))
+        .collect();
+    logs.sort();
+    let excess = logs.len().saturating_sub(retention);
+    for old in &logs[..excess] {
+        let _ = std::fs::remove_file(old);
+    }
+}
+
+/// Where the log with `log_id` is, if it's still kept. IDs are unique across
+/// projects, so any project's logs are searched.
+pub fn find(log_id: &str) -> Option<PathBuf> {
+    if log_id.is_empty() || log_id.contains(['/', '\\']) || log_id.starts_with('.') {
+        return None;
+    }
+    std::fs::read_dir(root())
+        .ok()?
+        .filter_map(|e| e.ok())
+        .map(|e| e.path().join(format!("{}.log", log_id)))
+        .find(|path| path.is_file())
+}
+
+/// The full output of the build with `log_id`
+pub fn read(log_id: &str) -> Result<String, String> {
+    let path = find(log_id).ok_or_else(|| format!("No build log {}; it may have been rotated out", log_id))?;
+    std::fs::read_to_string(&path).map_err(|e| format!("Failed to read {}: {}", path.display(), e))
+}
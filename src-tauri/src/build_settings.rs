@@ -0,0 +1,126 @@
+//! Resolved build settings from `xcodebuild -showBuildSettings`
+//!
+//! Unlike `project::build_setting`, which reads literal values out of the
+//! pbxproj, this asks xcodebuild, so `$(inherited)`, xcconfig files and
+//! per-configuration overrides are resolved. It takes seconds, so results are
+//! cached per (project, scheme, configuration) until the project file changes.
+
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::OnceLock;
+use std::time::SystemTime;
+
+use crate::schemes::project_modified;
+
+/// The settings of the scheme's app target
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BuildSettings {
+    pub target: String,
+    /// PRODUCT_BUNDLE_IDENTIFIER
+    pub bundle_id: Option<String>,
+    /// DEVELOPMENT_TEAM
+    pub development_team: Option<String>,
+    /// IPHONEOS_DEPLOYMENT_TARGET, e.g. "17.0"
+    pub deployment_target: Option<String>,
+    /// TARGETED_DEVICE_FAMILY: 1 is iPhone, 2 iPad, 3 Apple TV, 4 Apple Watch, 6 Mac Catalyst, 7 Vision
+    pub device_families: Vec<u32>,
+    /// SWIFT_VERSION, e.g. "5.0"
+    pub swift_version: Option<String>,
+}
+
+/// One target's entry in `-showBuildSettings -json`
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct TargetSettings {
+    #[serde(default)]
+    target: String,
+    #[serde(default)]
+    build_settings: HashMap<String, String>,
+}
+
+type CacheKey = (PathBuf, String, String);
+
+fn cache() -> &'static Mutex<HashMap<CacheKey, (SystemTime, BuildSettings)>> {
+    static CACHE: OnceLock<Mutex<HashMap<CacheKey, (SystemTime, BuildSettings)>>> = OnceLock::new();
+    CACHE.get_or_init(Default::default)
+}
+
+fn show_build_settings(
+    project_file: &Path,
+    is_workspace: bool,
+    scheme: &str,
+    configuration: &str,
+) -> Result<Vec<TargetSettings>, String> {
+    let flag = if is_workspace { "-workspace" } else { "-project" };
+    let output = Command::new("xcodebuild")
+        .args(["-showBuildSettings", "-json", flag])
+        .arg(project_file)
+        .args(["-scheme", scheme, "-configuration", configuration])
+        .output()
+        .map_err(|e| format!("Failed to run xcodebuild -showBuildSettings: {}", e))?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        let reason = stderr
+            .lines()
+            .find(|line| line.contains("error:"))
+            .unwrap_or_else(|| stderr.trim());
+        return Err(format!("xcodebuild -showBuildSettings failed: {}", reason.trim()));
+    }
+
+    // Like -list, warnings can come before the JSON
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let json = stdout.find('[').map_or(stdout.as_ref(), |start| &stdout[start..]);
+    serde_json::from_str(json).map_err(|e| format!("Failed to parse xcodebuild -showBuildSettings output: {}", e))
+}
+
+/// The target that builds the app, else the first one the scheme builds
+fn app_target(targets: Vec<TargetSettings>) -> Option<TargetSettings> {
+    let is_app = |t: &TargetSettings| {
+        t.build_settings.get("PRODUCT_TYPE").is_some_and(|p| p == "com.apple.product-type.application")
+    };
+    match targets.iter().position(is_app) {
+        Some(index) => targets.into_iter().nth(index),
+        None => targets.into_iter().next(),
+    }
+}
+
+fn setting(settings: &HashMap<String, String>, name: &str) -> Option<String> {
+    settings.get(name).map(|v| v.trim().to_string()).filter(|v| !v.is_empty())
+}
+
+/// The resolved settings of the scheme's app target for `configuration`
+pub fn build_settings(
+    project_file: &Path,
+    is_workspace: bool,
+    scheme: &str,
+    configuration: &str,
+) -> Result<BuildSettings, String> {
+    let key = (project_file.to_path_buf(), scheme.to_string(), configuration.to_string());
+    let modified = project_modified(project_file);
+    if let Some((_, cached)) = cache().lock().get(&key).filter(|(at, _)| Some(*at) == modified) {
+        return Ok(cached.clone());
+    }
+
+    let target = app_target(show_build_settings(project_file, is_workspace, scheme, configuration)?)
+        .ok_or_else(|| format!("Scheme '{}' doesn't build any targets", scheme))?;
+    let settings = &target.build_settings;
+    let resolved = BuildSettings {
+        bundle_id: setting(settings, "PRODUCT_BUNDLE_IDENTIFIER"),
+        development_team: setting(settings, "DEVELOPMENT_TEAM"),
+        deployment_target: setting(settings, "IPHONEOS_DEPLOYMENT_TARGET"),
+        device_families: setting(settings, "TARGETED_DEVICE_FAMILY")
+            .map(|families| families.split(',').filter_map(|f| f.trim().parse().ok()).collect())
+            .unwrap_or_default(),
+        swift_version: setting(settings, "SWIFT_VERSION"),
+        target: target.target,
+    };
+
+    if let Some(modified) = modified {
+        cache().lock().insert(key, (modified, resolved.clone()));
+    }
+    Ok(resolved)
+}
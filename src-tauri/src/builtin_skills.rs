@@ -0,0 +1,121 @@
+//! Built-in skill library
+//!
+//! Curated skill templates bundled into the app binary. Installed copies carry
+//! provenance keys in their frontmatter (`nocur-builtin`, `nocur-version`,
+//! `nocur-hash`) so we can tell an untouched install from a user-edited one
+//! and offer updates later.
+
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+struct BuiltinSkill {
+    id: &'static str,
+    version: u32,
+    content: &'static str,
+}
+
+const BUILTIN_SKILLS: &[BuiltinSkill] = &[
+    BuiltinSkill {
+        id: "build-and-verify",
+        version: 3,
+        content: include_str!("../../templates/claude-code/.claude/skills/build-and-verify/SKILL.md"),
+    },
+    BuiltinSkill {
+        id: "screenshot-after-change",
+        version: 1,
+        content: include_str!("../../templates/claude-code/.claude/skills/screenshot-after-change/SKILL.md"),
+    },
+    BuiltinSkill {
+        id: "ios-verification",
+        version: 1,
+        content: include_str!("../../templates/claude-code/.claude/skills/ios-verification/SKILL.md"),
+    },
+];
+
+const PREVIEW_CHARS: usize = 280;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BuiltinSkillInfo {
+    pub id: String,
+    pub name: String,
+    pub description: String,
+    pub version: u32,
+    pub preview: String,
+    pub content: String,
+}
+
+/// Split a SKILL.md into its frontmatter lines and body
+fn split_frontmatter(content: &str) -> (Vec<&str>, &str) {
+    let Some(rest) = content.strip_prefix("---\n") else {
+        return (Vec::new(), content);
+    };
+    match rest.find("\n---") {
+        Some(end) => {
+            let body = rest[end + 4..].trim_start_matches('\n');
+            (rest[..end].lines().collect(), body)
+        }
+        None => (Vec::new(), content),
+    }
+}
+
+fn frontmatter_value<'a>(lines: &[&'a str], key: &str) -> Option<&'a str> {
+    lines.iter().find_map(|line| {
+        line.strip_prefix(key)
+            .and_then(|rest| rest.strip_prefix(':'))
+            .map(|v| v.trim())
+    })
+}
+
+fn body_hash(body: &str) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(body.as_bytes());
+    hasher.finalize().iter().take(8).map(|b| format!("{:02x}", b)).collect()
+}
+
+fn to_info(skill: &BuiltinSkill) -> BuiltinSkillInfo {
+    let (frontmatter, body) = split_frontmatter(skill.content);
+    BuiltinSkillInfo {
+        id: skill.id.to_string(),
+        name: frontmatter_value(&frontmatter, "name").unwrap_or(skill.id).to_string(),
+        description: frontmatter_value(&frontmatter, "description").unwrap_or("").to_string(),
+        version: skill.version,
+        preview: body.chars().take(PREVIEW_CHARS).collect(),
+        content: skill.content.to_string(),
+    }
+}
+
+pub fn list_builtin_skills() -> Vec<BuiltinSkillInfo> {
+    BUILTIN_SKILLS.iter().map(to_info).collect()
+}
+
+/// Render a built-in skill with provenance keys added to its frontmatter
+pub fn render_builtin_skill(id: &str) -> Result<String, String> {
+    let skill = BUILTIN_SKILLS
+        .iter()
+        .find(|s| s.id == id)
+        .ok_or_else(|| format!("Unknown built-in skill: {}", id))?;
+
+    let (frontmatter, body) = split_frontmatter(skill.content);
+    let mut out = String::from("---\n");
+    for line in frontmatter {
+        out.push_str(line);
+        out.push('\n');
+    }
+    out.push_str(&format!("nocur-builtin: {}\n", skill.id));
+    out.push_str(&format!("nocur-version: {}\n", skill.version));
+    out.push_str(&format!("nocur-hash: {}\n", body_hash(body)));
+    out.push_str("---\n\n");
+    out.push_str(body);
+    Ok(out)
+}
+
+/// Whether an installed SKILL.md differs from what we originally wrote.
+/// Files without provenance keys are treated as user-owned.
+pub fn is_user_modified(existing: &str) -> bool {
+    let (frontmatter, body) = split_frontmatter(existing);
+    match frontmatter_value(&frontmatter, "nocur-hash") {
+        Some(hash) => hash != body_hash(body),
+        None => true,
+    }
+}
@@ -0,0 +1,282 @@
+//! Git checkpoints taken before each agent turn.
+//!
+//! A checkpoint is a commit of the full working tree (tracked + untracked, minus
+//! ignored files) stored under `refs/nocur/checkpoints/<session>/<n>`. It is built
+//! with a throwaway index file, `commit-tree` and `update-ref`, so the user's
+//! branch, index and working tree are never touched when taking one.
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::dry_run::DryRunReport;
+
+const CHECKPOINT_REF_PREFIX: &str = "refs/nocur/checkpoints";
+const CHECKPOINT_MAX_AGE_SECS: u64 = 7 * 24 * 60 * 60;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Checkpoint {
+    /// Full ref name, e.g. `refs/nocur/checkpoints/<session>/3`
+    pub id: String,
+    pub session_id: String,
+    pub number: u32,
+    pub commit: String,
+    pub created_at: u64,
+    pub message: String,
+}
+
+fn git_command(working_dir: &str, args: &[&str]) -> Command {
+    let mut cmd = Command::new("git");
+    cmd.args(args).current_dir(working_dir);
+    cmd
+}
+
+fn run_git(mut cmd: Command) -> Result<String, String> {
+    let output = cmd.output().map_err(|e| format!("Failed to run git: {}", e))?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(format!("git failed: {}", stderr.trim()));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+fn git(working_dir: &str, args: &[&str]) -> Result<String, String> {
+    run_git(git_command(working_dir, args))
+}
+
+/// Session IDs end up in ref names, so keep them to a safe character set
+fn ref_component(session_id: &str) -> String {
+    session_id
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() || c == '-' { c } else { '_' })
+        .collect()
+}
+
+fn now_secs() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs()
+}
+
+/// List checkpoint refs under `prefix`, newest first
+fn read_checkpoint_refs(working_dir: &str, prefix: &str) -> Result<Vec<Checkpoint>, String> {
+    let stdout = git(
+        working_dir,
+        &[
+            "for-each-ref",
+            "--format=%(refname)%09%(objectname)%09%(creatordate:unix)%09%(contents:subject)",
+            prefix,
+        ],
+    )?;
+
+    let mut checkpoints: Vec<Checkpoint> = stdout
+        .lines()
+        .filter_map(|line| {
+            let mut parts = line.splitn(4, '\t');
+            let id = parts.next()?.to_string();
+            let commit = parts.next()?.to_string();
+            let created_at = parts.next()?.parse().unwrap_or(0);
+            let message = parts.next().unwrap_or("").to_string();
+
+            let rest = id.strip_prefix(CHECKPOINT_REF_PREFIX)?.trim_start_matches('/');
+            let (session_id, number) = rest.rsplit_once('/')?;
+            Some(Checkpoint {
+                session_id: session_id.to_string(),
+                number: number.parse().ok()?,
+                id,
+                commit,
+                created_at,
+                message,
+            })
+        })
+        .collect();
+
+    checkpoints.sort_by(|a, b| b.created_at.cmp(&a.created_at).then(b.number.cmp(&a.number)));
+    Ok(checkpoints)
+}
+
+pub fn list_checkpoints(working_dir: &str, session_id: &str) -> Result<Vec<Checkpoint>, String> {
+    let prefix = format!("{}/{}/", CHECKPOINT_REF_PREFIX, ref_component(session_id));
+    read_checkpoint_refs(working_dir, &prefix)
+}
+
+/// Delete checkpoints (for any session) older than seven days
+pub fn prune_checkpoints(working_dir: &str) -> Result<usize, String> {
+    let cutoff = now_secs().saturating_sub(CHECKPOINT_MAX_AGE_SECS);
+    let stale: Vec<Checkpoint> = read_checkpoint_refs(working_dir, CHECKPOINT_REF_PREFIX)?
+        .into_iter()
+        .filter(|c| c.created_at < cutoff)
+        .collect();
+
+    for checkpoint in &stale {
+        git(working_dir, &["update-ref", "-d", &checkpoint.id])?;
+    }
+    Ok(stale.len())
+}
+
+/// Snapshot the working tree into a new checkpoint ref for `session_id`
+pub fn create_checkpoint(working_dir: &str, session_id: &str) -> Result<Checkpoint, String> {
+    if git(working_dir, &["rev-parse", "--is-inside-work-tree"])? != "true" {
+        return Err("Not a git repository".to_string());
+    }
+
+    let head = git(working_dir, &["rev-parse", "--verify", "-q", "HEAD"]).ok();
+
+    // Seed a throwaway index from the real one so `add -A` can reuse its stat cache
+    let real_index = PathBuf::from(working_dir).join(git(working_dir, &["rev-parse", "--git-path", "index"])?);
+    let temp_index = std::env::temp_dir().join(format!("nocur-checkpoint-{}.index", uuid::Uuid::new_v4()));
+    if real_index.exists() {
+        std::fs::copy(&real_index, &temp_index)
+            .map_err(|e| format!("Failed to prepare checkpoint index: {}", e))?;
+    }
+
+    let tree = snapshot_tree(working_dir, &temp_index);
+    let _ = std::fs::remove_file(&temp_index);
+    let tree = tree?;
+
+    let session_ref = ref_component(session_id);
+    let number = list_checkpoints(working_dir, session_id)?
+        .iter()
+        .map(|c| c.number)
+        .max()
+        .unwrap_or(0)
+        + 1;
+    let message = format!("nocur checkpoint {} {}", session_id, number);
+
+    let mut commit_tree = git_command(working_dir, &["commit-tree", &tree, "-m", &message]);
+    if let Some(ref parent) = head {
+        commit_tree.args(["-p", parent]);
+    }
+    // Checkpoints must work even when the user has no git identity configured
+    commit_tree
+        .env("GIT_AUTHOR_NAME", "nocur")
+        .env("GIT_AUTHOR_EMAIL", "nocur@localhost")
+        .env("GIT_COMMITTER_NAME", "nocur")
+        .env("GIT_COMMITTER_EMAIL", "nocur@localhost");
+    let commit = run_git(commit_tree)?;
+
+    let ref_name = format!("{}/{}/{}", CHECKPOINT_REF_PREFIX, session_ref, number);
+    git(working_dir, &["update-ref", &ref_name, &commit])?;
+
+    if let Err(e) = prune_checkpoints(working_dir) {
+        log::warn!("Failed to prune old checkpoints: {}", e);
+    }
+
+    Ok(Checkpoint {
+        id: ref_name,
+        session_id: session_ref,
+        number,
+        commit,
+        created_at: now_secs(),
+        message,
+    })
+}
+
+fn snapshot_tree(working_dir: &str, index: &Path) -> Result<String, String> {
+    let mut add = git_command(working_dir, &["add", "-A"]);
+    add.env("GIT_INDEX_FILE", index);
+    run_git(add)?;
+
+    let mut write_tree = git_command(working_dir, &["write-tree"]);
+    write_tree.env("GIT_INDEX_FILE", index);
+    run_git(write_tree)
+}
+
+/// The tree a checkpoint restores
+fn checkpoint_tree(working_dir: &str, checkpoint_id: &str) -> Result<String, String> {
+    if !checkpoint_id.starts_with(CHECKPOINT_REF_PREFIX) {
+        return Err(format!("Not a checkpoint: {}", checkpoint_id));
+    }
+    let tree_spec = format!("{}^{{tree}}", checkpoint_id);
+    git(working_dir, &["rev-parse", "--verify", &tree_spec])
+}
+
+/// Make the working tree match a checkpoint exactly. Files created after the
+/// checkpoint are removed (ignored files are left alone) and the index is reset
+/// back to HEAD, so the restored changes show up as ordinary uncommitted edits.
+pub fn restore_checkpoint(working_dir: &str, checkpoint_id: &str) -> Result<(), String> {
+    let tree = checkpoint_tree(working_dir, checkpoint_id)?;
+    git(working_dir, &["read-tree", "--reset", "-u", &tree])?;
+    git(working_dir, &["clean", "-fd"])?;
+    if git(working_dir, &["rev-parse", "--verify", "-q", "HEAD"]).is_ok() {
+        git(working_dir, &["reset", "-q"])?;
+    }
+    Ok(())
+}
+
+/// What `restore_checkpoint` would change: tracked files that differ from the
+/// checkpoint are rewritten, and files it doesn't have, tracked or not, are deleted
+pub fn restore_dry_run(working_dir: &str, checkpoint_id: &str) -> Result<DryRunReport, String> {
+    let tree = checkpoint_tree(working_dir, checkpoint_id)?;
+    let in_tree: HashSet<String> = git(working_dir, &["ls-tree", "-r", "--name-only", &tree])?
+        .lines()
+        .map(String::from)
+        .collect();
+
+    let mut would_modify = Vec::new();
+    let mut deleted = Vec::new();
+    let changes = git(working_dir, &["diff", "--name-status", "--no-renames", &tree])?;
+    for (status, file) in changes.lines().filter_map(|line| line.split_once('\t')) {
+        match status {
+            // In the working tree, not the checkpoint
+            "A" => deleted.push(file.to_string()),
+            _ => would_modify.push(file.to_string()),
+        }
+    }
+    // `clean -fd` takes untracked files; ones the checkpoint has are overwritten instead
+    for file in git(working_dir, &["ls-files", "--others", "--exclude-standard"])?.lines() {
+        match in_tree.contains(file) {
+            true => would_modify.push(file.to_string()),
+            false => deleted.push(file.to_string()),
+        }
+    }
+
+    let root = Path::new(working_dir);
+    let mut report = DryRunReport::deleting(deleted.iter().map(|file| root.join(file)).collect());
+    report.would_modify = would_modify;
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn repo() -> tempfile::TempDir {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().to_str().unwrap();
+        git(path, &["init", "-q"]).unwrap();
+        std::fs::write(dir.path().join("kept.swift"), "let a = 1\n").unwrap();
+        std::fs::write(dir.path().join("edited.swift"), "let b = 1\n").unwrap();
+        git(path, &["add", "-A"]).unwrap();
+        git(path, &["-c", "user.name=test", "-c", "user.email=test@localhost", "commit", "-qm", "initial"]).unwrap();
+        dir
+    }
+
+    #[test]
+    fn checkpoint_tree_rejects_other_refs() {
+        let repo = repo();
+        assert!(checkpoint_tree(repo.path().to_str().unwrap(), "HEAD").is_err());
+    }
+
+    #[test]
+    fn restore_changes_what_its_dry_run_reports() {
+        let repo = repo();
+        let working_dir = repo.path().to_str().unwrap();
+        let checkpoint = create_checkpoint(working_dir, "session").unwrap();
+        std::fs::write(repo.path().join("edited.swift"), "let b = 2\n").unwrap();
+        std::fs::write(repo.path().join("added.swift"), "let c = 1\n").unwrap();
+
+        let report = restore_dry_run(working_dir, &checkpoint.id).unwrap();
+        assert_eq!(report.would_modify, vec!["edited.swift".to_string()]);
+        assert_eq!(report.would_delete, vec![repo.path().join("added.swift")]);
+
+        restore_checkpoint(working_dir, &checkpoint.id).unwrap();
+        assert_eq!(std::fs::read_to_string(repo.path().join("edited.swift")).unwrap(), "let b = 1\n");
+        assert!(!repo.path().join("added.swift").exists());
+        assert!(repo.path().join("kept.swift").exists());
+    }
+}
@@ -1,15 +1,21 @@
 use serde::{Deserialize, Serialize};
 use std::io::{BufRead, BufReader, Write};
 use std::process::{Child, Command, Stdio};
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
 use std::sync::{Arc, Mutex};
 use std::thread;
-use tauri::{AppHandle, Emitter};
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Manager};
 use uuid::Uuid;
 
+use crate::event_coalescer::CoalesceStats;
+use crate::event_journal::Channel;
+use crate::messages::{msg, Message};
+
 /// Safely truncate a string at a character boundary
 /// This avoids panicking when the target byte index is in the middle of a multi-byte UTF-8 char
-fn truncate_to_char_boundary(s: &str, max_bytes: usize) -> &str {
+pub(crate) fn truncate_to_char_boundary(s: &str, max_bytes: usize) -> &str {
     if s.len() <= max_bytes {
         return s;
     }
@@ -21,6 +27,117 @@ fn truncate_to_char_boundary(s: &str, max_bytes: usize) -> &str {
     &s[..end]
 }
 
+/// Longest partial event held while waiting for the rest of it
+const MAX_EVENT_BUFFER_BYTES: usize = 10 * 1024 * 1024;
+
+/// Every service event is `JSON.stringify`'d with `type` as its first key
+const EVENT_START: &str = "{\"type\"";
+
+/// One item recovered from the service's stdout
+enum AssembledEvent {
+    Event(serde_json::Value, String),
+    /// Text that isn't part of any event (stderr noise, a corrupted event)
+    Garbage(String),
+    /// The partial event grew past `MAX_EVENT_BUFFER_BYTES` and was discarded
+    Overflow(usize),
+}
+
+/// Byte offset just past the object starting at `text[0]`, or None if it isn't closed yet
+fn balanced_object_end(text: &str) -> Option<usize> {
+    let mut depth = 0usize;
+    let mut in_string = false;
+    let mut escaped = false;
+
+    for (i, b) in text.bytes().enumerate() {
+        if in_string {
+            match b {
+                _ if escaped => escaped = false,
+                b'\\' => escaped = true,
+                b'"' => in_string = false,
+                _ => {}
+            }
+            continue;
+        }
+        match b {
+            b'"' => in_string = true,
+            b'{' => depth += 1,
+            b'}' => {
+                depth = depth.saturating_sub(1);
+                if depth == 0 {
+                    return Some(i + 1);
+                }
+            }
+            _ => {}
+        }
+    }
+    None
+}
+
+/// Reassembles service events from stdout reads. Events split across reads are
+/// joined, several events on one line are separated, and anything between events
+/// is reported as garbage instead of taking the next event down with it.
+#[derive(Default)]
+struct EventAssembler {
+    buffer: String,
+}
+
+impl EventAssembler {
+    fn push(&mut self, chunk: &str) -> Vec<AssembledEvent> {
+        // The service never emits raw newlines inside an event, so a newline in
+        // the middle of one came from a split write and is dropped
+        self.buffer.push_str(chunk.trim_end_matches(['\n', '\r']));
+        let mut out = Vec::new();
+
+        loop {
+            let Some(start) = self.buffer.find(EVENT_START) else {
+                // Keep a possibly truncated marker at the end for the next read
+                let keep_from = self.buffer.rfind('{').filter(|&i| EVENT_START.starts_with(&self.buffer[i..]));
+                let noise_end = keep_from.unwrap_or(self.buffer.len());
+                let noise = self.buffer[..noise_end].trim().to_string();
+                if !noise.is_empty() {
+                    out.push(AssembledEvent::Garbage(noise));
+                }
+                self.buffer.drain(..noise_end);
+                break;
+            };
+
+            let noise = self.buffer[..start].trim().to_string();
+            if !noise.is_empty() {
+                out.push(AssembledEvent::Garbage(noise));
+            }
+            self.buffer.drain(..start);
+
+            match balanced_object_end(&self.buffer) {
+                Some(end) => {
+                    let candidate: String = self.buffer.drain(..end).collect();
+                    match serde_json::from_str(&candidate) {
+                        Ok(json) => out.push(AssembledEvent::Event(json, candidate)),
+                        Err(_) => out.push(AssembledEvent::Garbage(candidate)),
+                    }
+                }
+                None => {
+                    // A later event that's already complete means this one was corrupted
+                    // (e.g. cut short by interleaved output); give up on it and resync
+                    match self.buffer[1..].find(EVENT_START).map(|i| i + 1) {
+                        Some(next) if balanced_object_end(&self.buffer[next..]).is_some() => {
+                            let corrupted: String = self.buffer.drain(..next).collect();
+                            out.push(AssembledEvent::Garbage(corrupted));
+                        }
+                        _ => break,
+                    }
+                }
+            }
+        }
+
+        if self.buffer.len() > MAX_EVENT_BUFFER_BYTES {
+            out.push(AssembledEvent::Overflow(self.buffer.len()));
+            self.buffer.clear();
+        }
+
+        out
+    }
+}
+
 /// Events emitted to the frontend
 #[derive(Debug, Clone, Serialize)]
 #[serde(rename_all = "camelCase")]
@@ -30,6 +147,9 @@ pub struct ClaudeEvent {
     pub tool_name: Option<String>,
     pub tool_input: Option<String>,
     pub tool_id: Option<String>,
+    /// Display fields pulled out of `tool_input` for tool_use events
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tool_summary: Option<crate::tool_summary::ToolSummary>,
     pub is_error: bool,
     pub raw_json: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -73,6 +193,17 @@ pub struct ClaudeEvent {
     pub ace_outcome: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub ace_task_duration: Option<u64>,
+    /// Catalog key and arguments `content` was rendered from, for errors nocur writes itself
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub message_key: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub message_args: Option<std::collections::BTreeMap<String, String>>,
+    /// Bytes cut from `content` to keep the event small; the full text is
+    /// available from `get_full_event_content` with `event_id`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub truncated_bytes: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub event_id: Option<String>,
 }
 
 impl Default for ClaudeEvent {
@@ -83,6 +214,7 @@ impl Default for ClaudeEvent {
             tool_name: None,
             tool_input: None,
             tool_id: None,
+            tool_summary: None,
             is_error: false,
             raw_json: None,
             skills: None,
@@ -103,10 +235,399 @@ impl Default for ClaudeEvent {
             ace_bullets_included: None,
             ace_outcome: None,
             ace_task_duration: None,
+            message_key: None,
+            message_args: None,
+            truncated_bytes: None,
+            event_id: None,
+        }
+    }
+}
+
+impl ClaudeEvent {
+    /// An error event whose text comes from the message catalog
+    fn error(message: Message) -> Self {
+        Self {
+            event_type: "error".to_string(),
+            message_key: message.key(),
+            message_args: message.args(),
+            content: message.text,
+            is_error: true,
+            ..Default::default()
         }
     }
 }
 
+/// Streaming speed of one assistant turn, emitted as `claude-metrics`
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TurnMetrics {
+    pub session_id: String,
+    /// Output tokens from `usage` events, or a chars/4 estimate before the first one arrives
+    pub tokens_out: u64,
+    pub chars_out: u64,
+    /// Time since the message was sent
+    pub elapsed_ms: u64,
+    /// Output rate between the first and latest content
+    pub tokens_per_sec: f64,
+    pub time_to_first_token_ms: Option<u64>,
+    /// True once the turn's `result` has arrived
+    pub complete: bool,
+    /// Distinct files touched by Edit, MultiEdit or Write so far
+    pub files_changed: usize,
+}
+
+/// `claude-metrics` is emitted at most this often while a turn streams
+const METRICS_EMIT_INTERVAL: Duration = Duration::from_millis(500);
+
+struct TurnTracker {
+    started_at: Instant,
+    first_content_at: Option<Instant>,
+    last_content_at: Option<Instant>,
+    chars_out: u64,
+    usage_tokens: u64,
+    last_emit: Option<Instant>,
+    edited_files: std::collections::HashSet<String>,
+}
+
+impl TurnTracker {
+    fn new() -> Self {
+        Self {
+            started_at: Instant::now(),
+            first_content_at: None,
+            last_content_at: None,
+            chars_out: 0,
+            usage_tokens: 0,
+            last_emit: None,
+            edited_files: std::collections::HashSet::new(),
+        }
+    }
+
+    fn record_content(&mut self, chars: usize) {
+        let now = Instant::now();
+        self.first_content_at.get_or_insert(now);
+        self.last_content_at = Some(now);
+        self.chars_out += chars as u64;
+    }
+
+    fn should_emit(&mut self) -> bool {
+        let due = self.last_emit.map_or(true, |t| t.elapsed() >= METRICS_EMIT_INTERVAL);
+        if due {
+            self.last_emit = Some(Instant::now());
+        }
+        due
+    }
+
+    fn snapshot(&self, session_id: &str, complete: bool) -> TurnMetrics {
+        let tokens_out = if self.usage_tokens > 0 { self.usage_tokens } else { self.chars_out / 4 };
+        let streaming_secs = match (self.first_content_at, self.last_content_at) {
+            (Some(first), Some(last)) => last.duration_since(first).as_secs_f64(),
+            _ => 0.0,
+        };
+        TurnMetrics {
+            session_id: session_id.to_string(),
+            tokens_out,
+            chars_out: self.chars_out,
+            elapsed_ms: self.started_at.elapsed().as_millis() as u64,
+            tokens_per_sec: if streaming_secs > 0.0 { tokens_out as f64 / streaming_secs } else { 0.0 },
+            time_to_first_token_ms: self
+                .first_content_at
+                .map(|t| t.duration_since(self.started_at).as_millis() as u64),
+            complete,
+            files_changed: self.edited_files.len(),
+        }
+    }
+}
+
+/// Feed a parsed service event into the current turn's metrics. Returns the
+/// final metrics when the event ends the turn.
+fn track_turn_metrics(
+    event: &ClaudeEvent,
+    session_id: &str,
+    turn: &Mutex<Option<TurnTracker>>,
+    last_turn: &Mutex<Option<TurnMetrics>>,
+    app_handle: &AppHandle,
+) -> Option<TurnMetrics> {
+    let Ok(mut turn) = turn.lock() else {
+        return None;
+    };
+
+    let metrics = match event.event_type.as_str() {
+        "assistant" | "tool_use" => {
+            let chars = event.content.len() + event.tool_input.as_ref().map_or(0, |i| i.len());
+            let tracker = turn.get_or_insert_with(TurnTracker::new);
+            tracker.record_content(chars);
+            if let Some(path) = edited_file(event) {
+                tracker.edited_files.insert(path);
+            }
+            tracker.should_emit().then(|| tracker.snapshot(session_id, false))
+        }
+        "usage" => {
+            let tracker = turn.get_or_insert_with(TurnTracker::new);
+            tracker.usage_tokens += event.output_tokens.unwrap_or(0);
+            tracker.should_emit().then(|| tracker.snapshot(session_id, false))
+        }
+        "result" => turn.take().map(|tracker| {
+            let metrics = tracker.snapshot(session_id, true);
+            if let Ok(mut last) = last_turn.lock() {
+                *last = Some(metrics.clone());
+            }
+            metrics
+        }),
+        _ => None,
+    };
+
+    let metrics = metrics?;
+    crate::event_journal::emit_stamped(app_handle, "claude-metrics", metrics.clone());
+    metrics.complete.then_some(metrics)
+}
+
+/// Post a notification for a finished turn if the user's policy asks for one
+fn notify_turn_complete(metrics: &TurnMetrics, app_handle: &AppHandle) {
+    let files = match metrics.files_changed {
+        0 => "no files changed".to_string(),
+        1 => "1 file changed".to_string(),
+        n => format!("{} files changed", n),
+    };
+    let elapsed = Duration::from_millis(metrics.elapsed_ms);
+    crate::notifications::notify_completion(
+        app_handle,
+        elapsed,
+        &format!("Claude finished in {} — {}", crate::notifications::format_duration(elapsed), files),
+        crate::notifications::CompletedItem::new("turn", Some(metrics.session_id.clone())),
+    );
+}
+
+/// Default for the `stallTimeoutSecs` preference
+pub const DEFAULT_STALL_TIMEOUT: Duration = Duration::from_secs(120);
+/// A tool that reported progress this recently is still working, however long
+/// the service has otherwise been quiet
+const TOOL_PROGRESS_GRACE: Duration = Duration::from_secs(10 * 60);
+const WATCHDOG_INTERVAL: Duration = Duration::from_secs(5);
+
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum StallRecovery {
+    /// Send Interrupt and wait for the service to answer
+    Interrupt,
+    /// Kill the service and resume the conversation in a new one
+    Restart,
+    /// Keep waiting; the watchdog fires again after another quiet window
+    Ignore,
+}
+
+#[derive(Default)]
+struct TurnWatchState {
+    /// A message is waiting for its result
+    in_flight: bool,
+    last_event_at: Option<Instant>,
+    /// Latest `tool_progress` of the tool that's running, if any
+    last_progress_at: Option<Instant>,
+    /// `turn_stalled` was already emitted for this silence
+    reported: bool,
+    /// The event that ended the last turn: "result", "error", "interrupted" or "stopped"
+    outcome: Option<String>,
+}
+
+/// When the service last said anything during a turn, shared between the
+/// session, its stdout reader and the watchdog
+#[derive(Clone, Default)]
+pub struct TurnWatch(Arc<Mutex<TurnWatchState>>);
+
+impl TurnWatch {
+    fn start_turn(&self) {
+        if let Ok(mut state) = self.0.lock() {
+            *state = TurnWatchState {
+                in_flight: true,
+                last_event_at: Some(Instant::now()),
+                ..Default::default()
+            };
+        }
+    }
+
+    fn record(&self, event: &ClaudeEvent) {
+        let Ok(mut state) = self.0.lock() else {
+            return;
+        };
+        let now = Instant::now();
+        state.last_event_at = Some(now);
+        state.reported = false;
+        match event.event_type.as_str() {
+            "tool_progress" => state.last_progress_at = Some(now),
+            "tool_result" => state.last_progress_at = None,
+            "result" | "error" | "interrupted" | "stopped" => {
+                state.in_flight = false;
+                state.last_progress_at = None;
+                state.outcome = Some(event.event_type.clone());
+            }
+            _ => {}
+        }
+    }
+
+    /// How long the turn has been silent, the first time it passes `timeout`
+    fn check_stalled(&self, timeout: Duration) -> Option<Duration> {
+        let mut state = self.0.lock().ok()?;
+        if !state.in_flight || state.reported {
+            return None;
+        }
+        if state.last_progress_at.is_some_and(|t| t.elapsed() < TOOL_PROGRESS_GRACE) {
+            return None;
+        }
+        let silent = state.last_event_at?.elapsed();
+        if silent < timeout {
+            return None;
+        }
+        state.reported = true;
+        Some(silent)
+    }
+
+    /// Restart the quiet window from now
+    pub fn snooze(&self) {
+        if let Ok(mut state) = self.0.lock() {
+            state.last_event_at = Some(Instant::now());
+            state.reported = false;
+        }
+    }
+
+    /// How the last turn ended; None while it's running or before the first
+    pub fn outcome(&self) -> Option<String> {
+        self.0.lock().ok().and_then(|state| state.outcome.clone().filter(|_| !state.in_flight))
+    }
+
+    pub fn last_event_at(&self) -> Option<Instant> {
+        self.0.lock().ok().and_then(|state| state.last_event_at)
+    }
+
+    /// Block until an event arrives after `since` or `timeout` passes; true if one did
+    pub fn wait_for_event(&self, since: Option<Instant>, timeout: Duration) -> bool {
+        let deadline = Instant::now() + timeout;
+        while Instant::now() < deadline {
+            if self.last_event_at() != since {
+                return true;
+            }
+            thread::sleep(Duration::from_millis(200));
+        }
+        false
+    }
+}
+
+/// Emit on `claude-event` through the journal, so a reloaded frontend can replay
+/// it; tool input is redacted first
+pub(crate) fn emit_claude_event(app_handle: &AppHandle, session_id: &str, mut event: ClaudeEvent) {
+    event.tool_input = event.tool_input.map(|input| crate::redaction::redact(&input));
+    crate::event_journal::emit(app_handle, Channel::Claude, Some(session_id.to_string()), event);
+}
+
+/// Watch a session for turns that stop producing events and emit `turn_stalled`
+/// once per silence. Runs until `alive` is cleared.
+fn spawn_stall_watchdog(watch: TurnWatch, timeout: Duration, session_id: String, alive: Arc<AtomicBool>, app_handle: AppHandle) {
+    thread::spawn(move || {
+        while alive.load(Ordering::Relaxed) {
+            thread::sleep(WATCHDOG_INTERVAL);
+            let Some(silent) = watch.check_stalled(timeout) else {
+                continue;
+            };
+            log::warn!("Claude turn stalled: no events for {}s", silent.as_secs());
+            let message = msg!("agent.turn_stalled", seconds = silent.as_secs());
+            emit_claude_event(&app_handle, &session_id, ClaudeEvent {
+                event_type: "turn_stalled".to_string(),
+                session_id: Some(session_id.clone()),
+                duration: Some(silent.as_secs_f64()),
+                message_key: message.key(),
+                message_args: message.args(),
+                content: message.text,
+                ..Default::default()
+            });
+        }
+    });
+}
+
+/// Start an ACE reflection when a turn finishes and the project has auto-reflect on
+fn maybe_auto_reflect(event: &ClaudeEvent, working_dir: &str, sdk_session_id: Option<&str>, app_handle: &AppHandle) {
+    if event.event_type != "result" {
+        return;
+    }
+    // Reflection reads Claude Code's transcript, which is filed under its own session ID
+    let Some(session_id) = sdk_session_id else {
+        return;
+    };
+    if !crate::ace_reflect::should_auto_reflect(working_dir) {
+        return;
+    }
+
+    let outcome = match event.result_subtype.as_deref() {
+        Some("success" | "end_turn") => "success",
+        Some(s) if s.starts_with("error") || s == "max_turns" => "failure",
+        _ => "unknown",
+    };
+    crate::ace_reflect::spawn_reflection(
+        app_handle.clone(),
+        working_dir.to_string(),
+        session_id.to_string(),
+        String::new(),
+        outcome.to_string(),
+    );
+}
+
+/// Keep an agent screenshot in the project's history, sized with the agent
+/// preset, and point the event at the stored copy; the service's temp file is
+/// left as it was
+fn store_agent_screenshot(event: &mut ClaudeEvent, working_dir: &str, session_id: &str, app_handle: &AppHandle) {
+    use crate::screenshot_presets;
+
+    if event.event_type != "agent_screenshot" || event.content.is_empty() {
+        return;
+    }
+    let source = screenshot_presets::resolve_with_preferences(None, screenshot_presets::AGENT_PRESET)
+        .and_then(|options| screenshot_presets::encode(Path::new(&event.content), &options))
+        .map(|image| image.path)
+        .unwrap_or_else(|e| {
+            log::warn!("Failed to resize agent screenshot: {}", e);
+            event.content.clone()
+        });
+    let context = crate::screenshots::CaptureContext::current(
+        app_handle,
+        crate::screenshots::CaptureTrigger::Agent,
+        Some(session_id.to_string()),
+    );
+    match crate::screenshots::register(working_dir, Path::new(&source), context) {
+        Ok(record) => {
+            event.content = record.path.clone();
+            event.tool_input = Some(record.path);
+        }
+        Err(e) => log::warn!("Failed to keep agent screenshot: {}", e),
+    }
+}
+
+/// How many agent-edited paths a session remembers for autocomplete
+const MAX_RECENT_EDITS: usize = 20;
+
+/// Remember the file an Edit/Write tool call touched, most recent first
+/// The file an Edit, MultiEdit or Write tool call targets
+fn edited_file(event: &ClaudeEvent) -> Option<String> {
+    if event.event_type != "tool_use"
+        || !matches!(event.tool_name.as_deref(), Some("Edit" | "MultiEdit" | "Write"))
+    {
+        return None;
+    }
+    event
+        .tool_input
+        .as_deref()
+        .and_then(|input| serde_json::from_str::<serde_json::Value>(input).ok())
+        .and_then(|input| input.get("file_path").and_then(|p| p.as_str()).map(String::from))
+}
+
+fn track_recent_edit(event: &ClaudeEvent, recent_edits: &Mutex<Vec<String>>) {
+    let Some(path) = edited_file(event) else {
+        return;
+    };
+
+    if let Ok(mut recent) = recent_edits.lock() {
+        recent.retain(|p| p != &path);
+        recent.insert(0, path);
+        recent.truncate(MAX_RECENT_EDITS);
+    }
+}
+
 /// Commands sent to the claude-service
 #[derive(Debug, Serialize)]
 #[serde(tag = "type", rename_all = "camelCase")]
@@ -124,6 +645,12 @@ enum ServiceCommand {
         // ACE: Project ID for playbook lookup (generated by ace::generate_project_id)
         #[serde(rename = "projectId")]
         project_id: Option<String>,
+        #[serde(rename = "permissionMode", skip_serializing_if = "Option::is_none")]
+        permission_mode: Option<String>,
+        #[serde(rename = "appendSystemPrompt", skip_serializing_if = "Option::is_none")]
+        append_system_prompt: Option<String>,
+        #[serde(rename = "maxTurns", skip_serializing_if = "Option::is_none")]
+        max_turns: Option<u32>,
     },
     Message {
         content: String,
@@ -134,6 +661,8 @@ enum ServiceCommand {
     ChangeModel {
         model: String,
     },
+    /// Pick up skills created since the session started
+    ReloadSkills,
     Stop,
 }
 
@@ -172,23 +701,46 @@ impl Default for ClaudeModel {
     }
 }
 
+/// What an agent profile adds to a session beyond its model and skills
+#[derive(Debug, Clone, Default)]
+pub struct ProfileSettings {
+    /// The profile the session was started from
+    pub name: Option<String>,
+    pub permission_mode: Option<String>,
+    pub append_system_prompt: Option<String>,
+    pub max_turns: Option<u32>,
+}
+
 /// Session configuration for starting Claude
 #[derive(Debug, Clone, Default)]
 pub struct ClaudeSessionConfig {
     pub model: Option<ClaudeModel>,
     pub resume_session_id: Option<String>,
     pub skip_permissions: bool,
+    pub profile: ProfileSettings,
 }
 
 pub struct ClaudeSession {
     child: Arc<Mutex<Option<Child>>>,
     stdin_writer: Arc<Mutex<Option<std::process::ChildStdin>>>,
     session_id: String,
-    #[allow(dead_code)]
     working_dir: String,
-    #[allow(dead_code)]
     skip_permissions: bool,
     model: Option<ClaudeModel>,
+    /// Conversation ID the SDK reported in `system_init`, used to resume
+    sdk_session_id: Arc<Mutex<Option<String>>>,
+    /// Metrics for the turn currently streaming, shared with the stdout reader
+    turn: Arc<Mutex<Option<TurnTracker>>>,
+    watch: TurnWatch,
+    /// Cleared on stop so the watchdog thread exits
+    alive: Arc<AtomicBool>,
+    last_turn: Arc<Mutex<Option<TurnMetrics>>>,
+    /// Service output that couldn't be parsed into an event
+    parse_failures: Arc<AtomicU64>,
+    /// Absolute paths the agent has edited or written, most recent first
+    recent_edits: Arc<Mutex<Vec<String>>>,
+    coalesce_stats: Arc<Mutex<CoalesceStats>>,
+    profile: ProfileSettings,
 }
 
 impl ClaudeSession {
@@ -199,42 +751,38 @@ impl ClaudeSession {
         })
     }
 
-    pub fn new_with_config(working_dir: &str, app_handle: AppHandle, config: ClaudeSessionConfig) -> Result<Self, String> {
+    pub fn new_with_config(working_dir: &str, app_handle: AppHandle, mut config: ClaudeSessionConfig) -> Result<Self, String> {
+        crate::safe_mode::restrict_session(&app_handle, &mut config);
+
         // Generate session ID (actual session ID comes from the service)
         let session_id = config.resume_session_id.clone()
             .unwrap_or_else(|| Uuid::new_v4().to_string());
 
         log::info!("Starting Claude SDK service with working_dir: {}", working_dir);
         log::info!("Session ID: {}", session_id);
+        if let Err(e) = crate::session_identity::assign(&session_id) {
+            log::warn!("Failed to assign an identity to session {}: {}", session_id, e);
+        }
         if let Some(ref model) = config.model {
             log::info!("Model: {}", model.as_str());
         }
 
         let repo_root = crate::paths::resolve_repo_root();
-        let service_path = std::env::var("NOCUR_CLAUDE_SERVICE_PATH")
-            .ok()
-            .map(PathBuf::from)
-            .filter(|p| p.exists())
-            .or_else(|| {
-                repo_root
-                    .as_ref()
-                    .map(|root| crate::paths::claude_service_entry(root))
-            })
-            .unwrap_or_else(|| PathBuf::from("claude-service/dist/index.js"));
-
-        if !service_path.exists() {
-            let expected = repo_root
-                .as_ref()
-                .map(|root| crate::paths::claude_service_entry(root))
-                .unwrap_or_else(|| PathBuf::from("claude-service/dist/index.js"));
-            return Err(format!(
-                "Claude service entry not found at {}. Build it with `cd claude-service && pnpm build`.",
-                expected.display()
-            ));
-        }
-
+        let service_path = crate::paths::resolve_claude_service_entry().ok_or_else(|| {
+            let searched: Vec<String> = crate::paths::claude_service_candidates()
+                .iter()
+                .map(|p| format!("  {}", p.display()))
+                .collect();
+            format!(
+                "Claude service not found. Looked in:\n{}\nReinstall Nocur, or build it with `cd claude-service && pnpm build`.",
+                searched.join("\n")
+            )
+        })?;
+
+        // The service's package root (dist/..), so node resolves its node_modules
         let service_cwd = repo_root
             .clone()
+            .or_else(|| service_path.parent().and_then(Path::parent).map(Path::to_path_buf))
             .or_else(|| std::env::current_dir().ok())
             .unwrap_or_else(|| PathBuf::from("."));
 
@@ -248,7 +796,10 @@ impl ClaudeSession {
             .stdin(Stdio::piped())
             .stdout(Stdio::piped())
             .stderr(Stdio::piped())
-            .env("NOCUR_PROJECT_DIR", working_dir);
+            // Claude Code's global env first, so nocur's own variables win
+            .envs(crate::read_user_preferences().map(|p| p.claude_env).unwrap_or_default())
+            .env("NOCUR_PROJECT_DIR", working_dir)
+            .envs(crate::runtime_env::nocur_env(Some(working_dir), Some(&session_id)));
 
         if let Some(ref root) = repo_root {
             cmd.env("NOCUR_REPO_ROOT", root);
@@ -256,6 +807,11 @@ impl ClaudeSession {
         if let Some(ref swift) = nocur_swift_path {
             cmd.env("NOCUR_SWIFT_PATH", swift);
         }
+        if let Some(bridge) = app_handle.try_state::<crate::agent_bridge::AgentBridge>() {
+            let info = bridge.info();
+            cmd.env("NOCUR_AGENT_BRIDGE_SOCKET", info.socket_path);
+            cmd.env("NOCUR_AGENT_BRIDGE_TOKEN", info.token);
+        }
 
         let mut child = cmd.spawn()
             .map_err(|e| format!("Failed to spawn claude-service: {}. Is Node.js installed?", e))?;
@@ -277,30 +833,96 @@ impl ClaudeSession {
         let child_arc = Arc::new(Mutex::new(Some(child)));
         let stdin_arc = Arc::new(Mutex::new(Some(stdin)));
 
+        let turn = Arc::new(Mutex::new(None));
+        let last_turn = Arc::new(Mutex::new(None));
+        let parse_failures = Arc::new(AtomicU64::new(0));
+        let recent_edits = Arc::new(Mutex::new(Vec::new()));
+        let watch = TurnWatch::default();
+        let alive = Arc::new(AtomicBool::new(true));
+        let shared_sdk_session_id = Arc::new(Mutex::new(config.resume_session_id.clone()));
+        let coalesce_stats = Arc::new(Mutex::new(CoalesceStats::default()));
+
+        // A zero cap leaves event content untruncated
+        let max_content_bytes = crate::read_user_preferences()
+            .ok()
+            .and_then(|p| p.max_event_content_bytes)
+            .unwrap_or(crate::event_coalescer::DEFAULT_MAX_CONTENT_BYTES);
+        let event_tx = crate::event_coalescer::spawn_emitter(
+            app_handle.clone(),
+            session_id.clone(),
+            max_content_bytes,
+            coalesce_stats.clone(),
+        );
+
         // Spawn stdout reader thread
         let app_stdout = app_handle.clone();
+        let metrics_session_id = session_id.clone();
+        let turn_reader = turn.clone();
+        let last_turn_reader = last_turn.clone();
+        let parse_failures_reader = parse_failures.clone();
+        let recent_edits_reader = recent_edits.clone();
+        let reader_working_dir = working_dir.to_string();
+        let watch_reader = watch.clone();
+        let sdk_session_id_reader = shared_sdk_session_id.clone();
+        let mut sdk_session_id = config.resume_session_id.clone();
         thread::spawn(move || {
-            let reader = BufReader::new(stdout);
-
-            for line in reader.lines() {
-                match line {
-                    Ok(line) if !line.trim().is_empty() => {
+            let mut reader = BufReader::new(stdout);
+            let mut assembler = EventAssembler::default();
+            let mut raw = Vec::new();
+
+            loop {
+                raw.clear();
+                match reader.read_until(b'\n', &mut raw) {
+                    Ok(0) => break,
+                    Ok(_) => {
+                        // Lossy so a stray invalid byte can't end the reader
+                        let chunk = String::from_utf8_lossy(&raw);
+                        if chunk.trim().is_empty() {
+                            continue;
+                        }
                         // Truncate at char boundary to avoid panic with multi-byte UTF-8 chars
-                        let truncated = truncate_to_char_boundary(&line, 200);
+                        let truncated = truncate_to_char_boundary(&chunk, 200);
                         log::debug!("Service stdout: {}", truncated);
 
-                        if let Ok(json) = serde_json::from_str::<serde_json::Value>(&line) {
-                            if let Some(event) = parse_service_event(&json, &line) {
-                                log::info!("Emitting event: type={}, content_len={}",
-                                    event.event_type, event.content.len());
-                                let _ = app_stdout.emit("claude-event", event);
+                        for item in assembler.push(&chunk) {
+                            match item {
+                                AssembledEvent::Event(json, line) => {
+                                    if let Some(mut event) = parse_service_event(&json, &line, &reader_working_dir) {
+                                        watch_reader.record(&event);
+                                        store_agent_screenshot(&mut event, &reader_working_dir, &metrics_session_id, &app_stdout);
+                                        if let Some(metrics) = track_turn_metrics(&event, &metrics_session_id, &turn_reader, &last_turn_reader, &app_stdout) {
+                                            notify_turn_complete(&metrics, &app_stdout);
+                                        }
+                                        track_recent_edit(&event, &recent_edits_reader);
+                                        crate::session_reports::record_event(&app_stdout, &metrics_session_id, &event);
+                                        if event.event_type == "system_init" && event.session_id.is_some() {
+                                            sdk_session_id = event.session_id.clone();
+                                            if let Ok(mut shared) = sdk_session_id_reader.lock() {
+                                                *shared = sdk_session_id.clone();
+                                            }
+                                        }
+                                        maybe_auto_reflect(&event, &reader_working_dir, sdk_session_id.as_deref(), &app_stdout);
+                                        log::debug!("Queueing event: type={}, content_len={}",
+                                            event.event_type, event.content.len());
+                                        let _ = event_tx.send(event);
+                                    }
+                                }
+                                AssembledEvent::Garbage(text) => {
+                                    parse_failures_reader.fetch_add(1, Ordering::Relaxed);
+                                    let truncated = truncate_to_char_boundary(&text, 100);
+                                    log::warn!("Failed to parse JSON: {}", truncated);
+                                }
+                                AssembledEvent::Overflow(bytes) => {
+                                    parse_failures_reader.fetch_add(1, Ordering::Relaxed);
+                                    log::error!("Dropped {} bytes of unterminated service output", bytes);
+                                    let _ = event_tx.send(ClaudeEvent::error(msg!(
+                                        "agent.malformed_output_discarded",
+                                        megabytes = bytes / (1024 * 1024),
+                                    )));
+                                }
                             }
-                        } else {
-                            let truncated = truncate_to_char_boundary(&line, 100);
-                            log::warn!("Failed to parse JSON: {}", truncated);
                         }
                     }
-                    Ok(_) => {} // Empty line, skip
                     Err(e) => {
                         log::error!("Error reading stdout: {}", e);
                         break;
@@ -312,6 +934,7 @@ impl ClaudeSession {
 
         // Spawn stderr reader thread
         let app_stderr = app_handle.clone();
+        let stderr_session_id = session_id.clone();
         thread::spawn(move || {
             let reader = BufReader::new(stderr);
 
@@ -322,7 +945,7 @@ impl ClaudeSession {
                         // Only emit real errors
                         let lower = line.to_lowercase();
                         if lower.contains("error") || lower.contains("failed") || lower.contains("exception") {
-                            let _ = app_stderr.emit("claude-event", ClaudeEvent {
+                            emit_claude_event(&app_stderr, &stderr_session_id, ClaudeEvent {
                                 event_type: "error".to_string(),
                                 content: line,
                                 is_error: true,
@@ -340,6 +963,16 @@ impl ClaudeSession {
             log::info!("Claude service stderr reader finished");
         });
 
+        // A zero timeout turns the watchdog off
+        let stall_timeout = crate::read_user_preferences()
+            .ok()
+            .and_then(|p| p.stall_timeout_secs)
+            .map(Duration::from_secs)
+            .unwrap_or(DEFAULT_STALL_TIMEOUT);
+        if !stall_timeout.is_zero() {
+            spawn_stall_watchdog(watch.clone(), stall_timeout, session_id.clone(), alive.clone(), app_handle.clone());
+        }
+
         let session = Self {
             child: child_arc,
             stdin_writer: stdin_arc.clone(),
@@ -347,6 +980,15 @@ impl ClaudeSession {
             working_dir: working_dir.to_string(),
             skip_permissions: config.skip_permissions,
             model: config.model.clone(),
+            sdk_session_id: shared_sdk_session_id,
+            turn,
+            watch,
+            alive,
+            last_turn,
+            parse_failures,
+            recent_edits,
+            coalesce_stats,
+            profile: config.profile.clone(),
         };
 
         // Generate ACE project ID for playbook lookup
@@ -361,6 +1003,9 @@ impl ClaudeSession {
             resume_session_id: config.resume_session_id,
             skip_permissions: config.skip_permissions,
             project_id: Some(project_id),
+            permission_mode: config.profile.permission_mode,
+            append_system_prompt: config.profile.append_system_prompt,
+            max_turns: config.profile.max_turns,
         };
 
         let json_line = serde_json::to_string(&start_cmd)
@@ -379,6 +1024,8 @@ impl ClaudeSession {
             }
         }
 
+        crate::session_reports::begin(&app_handle, &session_id, working_dir);
+
         Ok(session)
     }
 
@@ -387,11 +1034,57 @@ impl ClaudeSession {
         &self.session_id
     }
 
+    /// Get the directory this session runs in
+    pub fn get_working_dir(&self) -> &str {
+        &self.working_dir
+    }
+
     /// Get the model being used
     pub fn get_model(&self) -> Option<&ClaudeModel> {
         self.model.as_ref()
     }
 
+    /// How much service output has failed to parse this session
+    pub fn parse_failures(&self) -> u64 {
+        self.parse_failures.load(Ordering::Relaxed)
+    }
+
+    /// Files the agent has edited or written this session, most recent first
+    pub fn recent_edits(&self) -> Vec<String> {
+        self.recent_edits.lock().map(|r| r.clone()).unwrap_or_default()
+    }
+
+    pub fn skip_permissions(&self) -> bool {
+        self.skip_permissions
+    }
+
+    pub fn profile(&self) -> &ProfileSettings {
+        &self.profile
+    }
+
+    pub fn coalesce_stats(&self) -> CoalesceStats {
+        self.coalesce_stats.lock().map(|s| s.clone()).unwrap_or_default()
+    }
+
+    /// The SDK's conversation ID once the service has reported it
+    pub fn sdk_session_id(&self) -> Option<String> {
+        self.sdk_session_id.lock().ok().and_then(|id| id.clone())
+    }
+
+    /// Whether `id` is this session's ID or its SDK conversation ID
+    pub fn has_id(&self, id: &str) -> bool {
+        self.session_id == id || self.sdk_session_id().as_deref() == Some(id)
+    }
+
+    pub fn turn_watch(&self) -> TurnWatch {
+        self.watch.clone()
+    }
+
+    /// Metrics for the most recently completed turn
+    pub fn last_turn_metrics(&self) -> Option<TurnMetrics> {
+        self.last_turn.lock().ok().and_then(|m| m.clone())
+    }
+
     pub fn send_message(&self, message: &str, agent_mode: Option<&str>, app_handle: AppHandle) -> Result<(), String> {
         log::info!("Sending message to Claude: {}", truncate_to_char_boundary(message, 100));
 
@@ -416,8 +1109,13 @@ impl ClaudeSession {
                 .map_err(|e| format!("Failed to flush stdin: {}", e))?;
             log::info!("Message sent successfully");
 
+            if let Ok(mut turn) = self.turn.lock() {
+                *turn = Some(TurnTracker::new());
+            }
+            self.watch.start_turn();
+
             // Emit a "sent" event
-            let _ = app_handle.emit("claude-event", ClaudeEvent {
+            emit_claude_event(&app_handle, &self.session_id, ClaudeEvent {
                 event_type: "message_sent".to_string(),
                 ..Default::default()
             });
@@ -451,6 +1149,25 @@ impl ClaudeSession {
         }
     }
 
+    pub fn reload_skills(&self) -> Result<(), String> {
+        let json_line = serde_json::to_string(&ServiceCommand::ReloadSkills)
+            .map_err(|e| format!("Failed to serialize reload skills command: {}", e))?;
+
+        let mut stdin_guard = self.stdin_writer.lock()
+            .map_err(|e| format!("Failed to lock stdin: {}", e))?;
+
+        if let Some(ref mut stdin) = *stdin_guard {
+            writeln!(stdin, "{}", json_line)
+                .map_err(|e| format!("Failed to write to stdin: {}", e))?;
+            stdin.flush()
+                .map_err(|e| format!("Failed to flush stdin: {}", e))?;
+            log::info!("Reload skills command sent");
+            Ok(())
+        } else {
+            Err("Stdin not available".to_string())
+        }
+    }
+
     pub fn interrupt(&self) -> Result<(), String> {
         let cmd = ServiceCommand::Interrupt;
 
@@ -474,6 +1191,7 @@ impl ClaudeSession {
 
     pub fn stop(&self) {
         log::info!("Stopping Claude SDK service");
+        self.alive.store(false, Ordering::Relaxed);
 
         // Kill the child process FIRST for immediate termination, but also reap it in a
         // background thread to avoid zombie processes.
@@ -500,7 +1218,7 @@ impl Drop for ClaudeSession {
 }
 
 /// Parse events from the claude-service
-fn parse_service_event(json: &serde_json::Value, raw_line: &str) -> Option<ClaudeEvent> {
+fn parse_service_event(json: &serde_json::Value, raw_line: &str, working_dir: &str) -> Option<ClaudeEvent> {
     let event_type = json.get("type")
         .and_then(|t| t.as_str())
         .unwrap_or("unknown")
@@ -572,6 +1290,9 @@ fn parse_service_event(json: &serde_json::Value, raw_line: &str) -> Option<Claud
             let tool_id = json.get("toolId")
                 .and_then(|i| i.as_str())
                 .map(String::from);
+            let tool_summary = tool_name.as_deref().map(|name| {
+                crate::tool_summary::summarize(name, tool_input.as_deref().unwrap_or("{}"), Some(working_dir))
+            });
 
             Some(ClaudeEvent {
                 event_type: "tool_use".to_string(),
@@ -579,6 +1300,7 @@ fn parse_service_event(json: &serde_json::Value, raw_line: &str) -> Option<Claud
                 tool_name,
                 tool_input,
                 tool_id,
+                tool_summary,
                 raw_json: Some(raw_line.to_string()),
                 ..Default::default()
             })
@@ -665,15 +1387,12 @@ fn parse_service_event(json: &serde_json::Value, raw_line: &str) -> Option<Claud
         "error" => {
             let message = json.get("message")
                 .and_then(|m| m.as_str())
-                .unwrap_or("Unknown error")
-                .to_string();
+                .map(Message::raw)
+                .unwrap_or_else(|| msg!("agent.unknown_error"));
 
             Some(ClaudeEvent {
-                event_type: "error".to_string(),
-                content: message,
-                is_error: true,
                 raw_json: Some(raw_line.to_string()),
-                ..Default::default()
+                ..ClaudeEvent::error(message)
             })
         }
         "interrupted" => {
@@ -696,6 +1415,18 @@ fn parse_service_event(json: &serde_json::Value, raw_line: &str) -> Option<Claud
                 ..Default::default()
             })
         }
+        "skills_reloaded" => {
+            let skills = json.get("skills")
+                .and_then(|s| s.as_array())
+                .map(|names| names.iter().filter_map(|n| n.as_str()).collect::<Vec<_>>().join(", "))
+                .unwrap_or_default();
+            Some(ClaudeEvent {
+                event_type: "skills_reloaded".to_string(),
+                content: skills,
+                raw_json: Some(raw_line.to_string()),
+                ..Default::default()
+            })
+        }
         "agent_screenshot" => {
             // When the agent takes a screenshot, pass the filepath to the frontend
             // Frontend uses convertFileSrc to load the image
@@ -815,6 +1546,7 @@ pub struct SavedSession {
     pub model: Option<String>,
     pub created_at: u64, // Unix timestamp
     pub last_message_preview: Option<String>,
+    pub identity: Option<crate::session_identity::SessionIdentity>,
 }
 
 pub struct ClaudeState {
@@ -854,6 +1586,7 @@ impl ClaudeState {
             // Check if already in history
             if !self.session_history.iter().any(|s| s.session_id == session_id) {
                 let saved = SavedSession {
+                    identity: crate::session_identity::get(&session_id),
                     session_id,
                     model,
                     created_at: std::time::SystemTime::now()
@@ -883,8 +1616,94 @@ impl ClaudeState {
         self.session_history.iter().rev().cloned().collect()
     }
 
+    /// Metrics for the last completed turn of the active session
+    pub fn get_last_turn_metrics(&self, session_id: &str) -> Option<TurnMetrics> {
+        self.session
+            .as_ref()
+            .filter(|s| s.get_session_id() == session_id)
+            .and_then(|s| s.last_turn_metrics())
+    }
+
+    /// Files the active session's agent has edited, most recent first
+    pub fn recent_edits(&self) -> Vec<String> {
+        self.session.as_ref().map(|s| s.recent_edits()).unwrap_or_default()
+    }
+
+    pub fn get_coalesce_stats(&self, session_id: &str) -> Option<CoalesceStats> {
+        self.session
+            .as_ref()
+            .filter(|s| s.get_session_id() == session_id)
+            .map(|s| s.coalesce_stats())
+    }
+
     /// Get current session ID if active
     pub fn get_current_session_id(&self) -> Option<String> {
         self.session.as_ref().map(|s| s.get_session_id().to_string())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// What came out of a push, in order
+    fn summarize(events: Vec<AssembledEvent>) -> Vec<String> {
+        events
+            .into_iter()
+            .map(|event| match event {
+                AssembledEvent::Event(json, _) => format!("event:{}", json["type"].as_str().unwrap_or_default()),
+                AssembledEvent::Garbage(text) => format!("garbage:{}", text),
+                AssembledEvent::Overflow(bytes) => format!("overflow:{}", bytes),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn joins_an_event_split_across_reads() {
+        let mut assembler = EventAssembler::default();
+        assert!(assembler.push(r#"{"type":"assistant","text":"braces } in"#).is_empty());
+        assert!(assembler.push(r#" a string { too"#).is_empty());
+        assert_eq!(summarize(assembler.push("\"}\n")), vec!["event:assistant"]);
+    }
+
+    #[test]
+    fn joins_an_event_split_inside_its_marker() {
+        let mut assembler = EventAssembler::default();
+        assert!(assembler.push(r#"{"ty"#).is_empty());
+        assert_eq!(summarize(assembler.push(r#"pe":"result"}"#)), vec!["event:result"]);
+    }
+
+    #[test]
+    fn separates_several_events_in_one_read() {
+        let mut assembler = EventAssembler::default();
+        let events = assembler.push("{\"type\":\"a\"}{\"type\":\"b\"}\n{\"type\":\"c\",\"escaped\":\"\\\"}\"}\n");
+        assert_eq!(summarize(events), vec!["event:a", "event:b", "event:c"]);
+    }
+
+    #[test]
+    fn reports_text_between_events_as_garbage() {
+        let mut assembler = EventAssembler::default();
+        let events = assembler.push("warning: deprecated {\"type\":\"a\"} trailing noise\n");
+        assert_eq!(summarize(events), vec!["garbage:warning: deprecated", "event:a", "garbage:trailing noise"]);
+    }
+
+    #[test]
+    fn resyncs_past_a_corrupted_event() {
+        let mut assembler = EventAssembler::default();
+        let events = assembler.push(r#"{"type":"a","items":[1,{"type":"b"}"#);
+        assert_eq!(summarize(events), vec![r#"garbage:{"type":"a","items":[1,"#, "event:b"]);
+    }
+
+    #[test]
+    fn discards_an_event_past_the_buffer_limit() {
+        let mut assembler = EventAssembler::default();
+        let start = r#"{"type":"huge","data":""#;
+        assert!(assembler.push(start).is_empty());
+        let events = assembler.push(&"x".repeat(MAX_EVENT_BUFFER_BYTES));
+        assert_eq!(summarize(events), vec![format!("overflow:{}", start.len() + MAX_EVENT_BUFFER_BYTES)]);
+
+        // The rest of the dropped event is noise; the next one comes through
+        let events = assembler.push("xxxx\"}\n{\"type\":\"after\"}\n");
+        assert_eq!(summarize(events), vec!["garbage:xxxx\"}", "event:after"]);
+    }
+}
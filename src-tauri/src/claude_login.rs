@@ -0,0 +1,326 @@
+//! Guided `claude` login for onboarding
+//!
+//! Instead of opening Terminal and leaving the user to find the command, the
+//! CLI's login flow runs under a PTY held here. Its output streams out as
+//! `claude-login-output`, the sign-in URL is picked out of it for an "Open
+//! browser" button, and the login probe runs until the account works, at
+//! which point `claude-login-complete` carries the new status and the PTY is
+//! killed. If the CLI exits or times out first, `claude-login-exit` says so.
+//! Only one login runs at a time.
+
+use crate::ClaudeCodeStatus;
+use parking_lot::Mutex;
+use portable_pty::{native_pty_system, Child, CommandBuilder, MasterPty, PtySize};
+use regex::Regex;
+use serde::Serialize;
+use std::io::{Read, Write};
+use std::process::Command;
+use std::sync::mpsc;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Manager};
+
+/// `claude setup-token` first shipped in this version; older 1.x CLIs use `claude login`
+const SETUP_TOKEN_SINCE: (u32, u32, u32) = (1, 0, 44);
+/// Before 1.0 the CLI could only log in through the interactive `/login`
+const MINIMUM_VERSION: (u32, u32, u32) = (1, 0, 0);
+
+/// How long to wait for the CLI to print its sign-in URL
+const URL_TIMEOUT: Duration = Duration::from_secs(30);
+const POLL_INTERVAL: Duration = Duration::from_secs(5);
+/// Give up on a login nobody finished
+const LOGIN_TIMEOUT: Duration = Duration::from_secs(10 * 60);
+/// Output kept for the error when the CLI exits without a URL
+const OUTPUT_TAIL_CHARS: usize = 500;
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase", tag = "state")]
+pub enum LoginStart {
+    /// The probe already works; nothing was spawned
+    AlreadyLoggedIn { status: ClaudeCodeStatus },
+    /// The installed CLI has no login command to drive
+    CliTooOld { version: String, minimum: String },
+    /// The login is running; `url` is where the user signs in
+    Started {
+        command: String,
+        url: Option<String>,
+        code: Option<String>,
+    },
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LoginOutput {
+    pub data: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LoginComplete {
+    pub status: ClaudeCodeStatus,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LoginExit {
+    pub reason: String,
+}
+
+struct LoginSession {
+    id: String,
+    // Dropping the master closes the PTY, so it's held for the session's life
+    _master: Box<dyn MasterPty + Send>,
+    writer: Box<dyn Write + Send>,
+    child: Box<dyn Child + Send + Sync>,
+}
+
+impl LoginSession {
+    fn kill(mut self) {
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+#[derive(Default)]
+pub struct ClaudeLogin {
+    session: Mutex<Option<LoginSession>>,
+}
+
+impl ClaudeLogin {
+    fn is_current(&self, id: &str) -> bool {
+        self.session.lock().as_ref().is_some_and(|s| s.id == id)
+    }
+
+    /// Remove the session if it's still `id`, so a cancelled login's poller
+    /// can't end a newer one
+    fn take_if_current(&self, id: &str) -> Option<LoginSession> {
+        let mut session = self.session.lock();
+        if session.as_ref().is_some_and(|s| s.id == id) {
+            session.take()
+        } else {
+            None
+        }
+    }
+
+    /// Send text (e.g. the code shown in the browser) to the login prompt
+    pub fn submit(&self, input: &str) -> Result<(), String> {
+        let mut session = self.session.lock();
+        let session = session.as_mut().ok_or("No login in progress")?;
+        session
+            .writer
+            .write_all(format!("{}\r", input.trim()).as_bytes())
+            .and_then(|_| session.writer.flush())
+            .map_err(|e| format!("Failed to write to login: {}", e))
+    }
+
+    /// Kill the running login, if any
+    pub fn cancel(&self) {
+        if let Some(session) = self.session.lock().take() {
+            session.kill();
+        }
+    }
+}
+
+fn parse_version(output: &str) -> Option<(u32, u32, u32)> {
+    let version = output.split_whitespace().next()?;
+    let mut parts = version.split('.').map(|p| p.parse::<u32>().ok());
+    Some((parts.next()??, parts.next().flatten().unwrap_or(0), parts.next().flatten().unwrap_or(0)))
+}
+
+fn format_version((major, minor, patch): (u32, u32, u32)) -> String {
+    format!("{}.{}.{}", major, minor, patch)
+}
+
+/// The installed CLI's version, e.g. "1.0.98 (Claude Code)" -> (1, 0, 98)
+fn cli_version() -> Option<(u32, u32, u32)> {
+    let output = Command::new("claude").arg("--version").output().ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    parse_version(&String::from_utf8_lossy(&output.stdout))
+}
+
+fn strip_ansi(text: &str) -> String {
+    Regex::new(r"\x1b\[[0-9;?]*[A-Za-z]|\x1b\][^\x07]*\x07")
+        .map(|re| re.replace_all(text, "").to_string())
+        .unwrap_or_else(|_| text.to_string())
+}
+
+/// The sign-in URL and, for device-style flows, the code to enter there
+fn find_url_and_code(output: &str) -> (Option<String>, Option<String>) {
+    let text = strip_ansi(output);
+    let url = Regex::new(r"https://[^\s\x1b]+")
+        .ok()
+        .and_then(|re| re.find(&text).map(|m| m.as_str().to_string()));
+    let code = Regex::new(r"(?i)\bcode:?\s+([A-Z0-9]{4,}(?:-[A-Z0-9]{4,})*)\b")
+        .ok()
+        .and_then(|re| re.captures(&text).map(|c| c[1].to_string()));
+    (url, code)
+}
+
+fn tail(text: &str) -> String {
+    let text = strip_ansi(text);
+    let skip = text.chars().count().saturating_sub(OUTPUT_TAIL_CHARS);
+    text.chars().skip(skip).collect::<String>().trim().to_string()
+}
+
+/// Start the guided login, replacing one already running
+pub fn start(app_handle: AppHandle) -> Result<LoginStart, String> {
+    let login = app_handle.state::<ClaudeLogin>();
+    login.cancel();
+
+    let which = Command::new("which").arg("claude").output().map_err(|e| e.to_string())?;
+    if !which.status.success() {
+        return Err("Claude Code is not installed".to_string());
+    }
+    let claude_path = String::from_utf8_lossy(&which.stdout).trim().to_string();
+
+    if crate::network::is_offline() {
+        return Err("Can't sign in while offline".to_string());
+    }
+    let status = crate::refresh_claude_status(claude_path.clone())?;
+    if status.logged_in {
+        return Ok(LoginStart::AlreadyLoggedIn { status });
+    }
+
+    let version = cli_version().ok_or("Couldn't read the Claude Code version")?;
+    if version < MINIMUM_VERSION {
+        return Ok(LoginStart::CliTooOld {
+            version: format_version(version),
+            minimum: format_version(MINIMUM_VERSION),
+        });
+    }
+    let subcommand = if version >= SETUP_TOKEN_SINCE { "setup-token" } else { "login" };
+
+    // Wide enough that the URL never wraps onto a second line
+    let pair = native_pty_system()
+        .openpty(PtySize { rows: 40, cols: 1000, pixel_width: 0, pixel_height: 0 })
+        .map_err(|e| format!("Failed to open PTY: {}", e))?;
+    let mut cmd = CommandBuilder::new(&claude_path);
+    cmd.arg(subcommand);
+    cmd.cwd(crate::paths::home_dir_or_cwd());
+    cmd.env("TERM", "xterm-256color");
+    let child = pair
+        .slave
+        .spawn_command(cmd)
+        .map_err(|e| format!("Failed to run claude {}: {}", subcommand, e))?;
+    // The child holds its own handle; keeping ours would stop EOF arriving on exit
+    drop(pair.slave);
+
+    let mut reader = pair
+        .master
+        .try_clone_reader()
+        .map_err(|e| format!("Failed to read from PTY: {}", e))?;
+    let writer = pair
+        .master
+        .take_writer()
+        .map_err(|e| format!("Failed to write to PTY: {}", e))?;
+
+    let id = uuid::Uuid::new_v4().to_string();
+    *login.session.lock() = Some(LoginSession {
+        id: id.clone(),
+        _master: pair.master,
+        writer,
+        child,
+    });
+
+    let output = Arc::new(Mutex::new(String::new()));
+    let (found_tx, found_rx) = mpsc::channel();
+    {
+        let output = output.clone();
+        let app_handle = app_handle.clone();
+        std::thread::spawn(move || {
+            let mut buf = [0u8; 4096];
+            let mut found_tx = Some(found_tx);
+            loop {
+                let n = match reader.read(&mut buf) {
+                    Ok(0) | Err(_) => break,
+                    Ok(n) => n,
+                };
+                let data = String::from_utf8_lossy(&buf[..n]).to_string();
+                crate::event_journal::emit_stamped(&app_handle, "claude-login-output", LoginOutput { data: data.clone() });
+
+                let mut output = output.lock();
+                output.push_str(&data);
+                if let (Some(url), code) = find_url_and_code(&output) {
+                    if let Some(tx) = found_tx.take() {
+                        let _ = tx.send((url, code));
+                    }
+                }
+            }
+            // Dropping the sender unblocks `start` if no URL ever appeared
+        });
+    }
+
+    spawn_poller(app_handle.clone(), id.clone(), claude_path);
+
+    match found_rx.recv_timeout(URL_TIMEOUT) {
+        Ok((url, code)) => Ok(LoginStart::Started {
+            command: format!("claude {}", subcommand),
+            url: Some(url),
+            code,
+        }),
+        // Still running but no URL yet; the output events show what it's asking
+        Err(mpsc::RecvTimeoutError::Timeout) => Ok(LoginStart::Started {
+            command: format!("claude {}", subcommand),
+            url: None,
+            code: None,
+        }),
+        Err(mpsc::RecvTimeoutError::Disconnected) => {
+            if let Some(session) = login.take_if_current(&id) {
+                session.kill();
+            }
+            let output = tail(&output.lock());
+            if output.contains("Unknown command") || output.contains("unknown command") {
+                return Ok(LoginStart::CliTooOld {
+                    version: format_version(version),
+                    minimum: format_version(SETUP_TOKEN_SINCE),
+                });
+            }
+            Err(format!("claude {} exited before printing a sign-in URL: {}", subcommand, output))
+        }
+    }
+}
+
+/// Probe until the account works, the CLI gives up or the login is cancelled
+fn spawn_poller(app_handle: AppHandle, id: String, claude_path: String) {
+    std::thread::spawn(move || {
+        let started = Instant::now();
+        loop {
+            std::thread::sleep(POLL_INTERVAL);
+            let login = app_handle.state::<ClaudeLogin>();
+            if !login.is_current(&id) {
+                return;
+            }
+
+            let exited = login
+                .session
+                .lock()
+                .as_mut()
+                .is_some_and(|s| matches!(s.child.try_wait(), Ok(Some(_))));
+
+            if let Ok(status) = crate::refresh_claude_status(claude_path.clone()) {
+                if status.logged_in {
+                    if let Some(session) = login.take_if_current(&id) {
+                        session.kill();
+                        crate::event_journal::emit_stamped(&app_handle, "claude-login-complete", LoginComplete { status });
+                    }
+                    return;
+                }
+            }
+
+            let reason = if exited {
+                "claude exited without finishing the login"
+            } else if started.elapsed() > LOGIN_TIMEOUT {
+                "The login timed out"
+            } else {
+                continue;
+            };
+            if let Some(session) = login.take_if_current(&id) {
+                session.kill();
+                crate::event_journal::emit_stamped(&app_handle, "claude-login-exit", LoginExit { reason: reason.to_string() });
+            }
+            return;
+        }
+    });
+}
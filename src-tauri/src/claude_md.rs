@@ -0,0 +1,265 @@
+//! Reading and editing a project's CLAUDE.md one section at a time
+//!
+//! A section is a heading line and everything up to the next heading of any
+//! level; text before the first heading is a section without a heading.
+//! Headings inside fenced code blocks don't count.
+//!
+//! Every read returns a SHA-256 of the file. Writes take the hash the caller
+//! last saw and are refused with the current document when the file has
+//! changed since, so an edit never silently overwrites the agent's (or the
+//! user's editor's). Writes go to a temp file that's renamed over CLAUDE.md.
+
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use std::path::{Path, PathBuf};
+
+const FILE_NAME: &str = "CLAUDE.md";
+
+/// Section `append_rule` adds to, the one the built-in template ends with
+const RULES_HEADING: &str = "Guidelines";
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ClaudeMdSection {
+    /// Heading text without the `#`s; None for text before the first heading
+    pub heading: Option<String>,
+    /// 1-6, or 0 for the text before the first heading
+    pub level: u8,
+    /// Byte range of the section in the file, heading line included
+    pub start: usize,
+    pub end: usize,
+    /// The section's text below its heading line
+    pub body: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ClaudeMdDocument {
+    pub path: String,
+    pub exists: bool,
+    pub content: String,
+    /// SHA-256 of `content`; pass it back to write
+    pub hash: String,
+    pub sections: Vec<ClaudeMdSection>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "status", rename_all = "camelCase")]
+pub enum ClaudeMdWrite {
+    Written { document: ClaudeMdDocument },
+    /// The file changed after the caller read it; nothing was written
+    Conflict { current: ClaudeMdDocument },
+}
+
+fn claude_md_path(project_path: &str) -> PathBuf {
+    Path::new(project_path).join(FILE_NAME)
+}
+
+fn hash(content: &str) -> String {
+    Sha256::digest(content.as_bytes()).iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// `(level, text)` if `line` is an ATX heading
+fn parse_heading(line: &str) -> Option<(u8, String)> {
+    let trimmed = line.trim_end_matches(['\n', '\r']);
+    let hashes = trimmed.bytes().take_while(|&b| b == b'#').count();
+    if hashes == 0 || hashes > 6 {
+        return None;
+    }
+    let rest = &trimmed[hashes..];
+    if !rest.is_empty() && !rest.starts_with([' ', '\t']) {
+        return None;
+    }
+    // Closing #s are decoration: "## Build ##"
+    let text = rest.trim().trim_end_matches('#').trim_end();
+    Some((hashes as u8, text.to_string()))
+}
+
+pub fn parse_sections(content: &str) -> Vec<ClaudeMdSection> {
+    // (heading, level, start, body_start)
+    let mut starts: Vec<(Option<String>, u8, usize, usize)> = Vec::new();
+    let mut in_fence = false;
+    let mut offset = 0;
+
+    for line in content.split_inclusive('\n') {
+        let trimmed = line.trim_start();
+        if trimmed.starts_with("```") || trimmed.starts_with("~~~") {
+            in_fence = !in_fence;
+        } else if !in_fence {
+            if let Some((level, text)) = parse_heading(line) {
+                starts.push((Some(text), level, offset, offset + line.len()));
+            }
+        }
+        offset += line.len();
+    }
+
+    let first_heading = starts.first().map_or(content.len(), |s| s.2);
+    if first_heading > 0 {
+        starts.insert(0, (None, 0, 0, 0));
+    }
+
+    let ends: Vec<usize> = starts.iter().skip(1).map(|s| s.2).chain([content.len()]).collect();
+    starts
+        .into_iter()
+        .zip(ends)
+        .map(|((heading, level, start, body_start), end)| ClaudeMdSection {
+            heading,
+            level,
+            start,
+            end,
+            body: content[body_start..end].to_string(),
+        })
+        .collect()
+}
+
+fn document(path: &Path, exists: bool, content: String) -> ClaudeMdDocument {
+    ClaudeMdDocument {
+        path: path.to_string_lossy().to_string(),
+        exists,
+        hash: hash(&content),
+        sections: parse_sections(&content),
+        content,
+    }
+}
+
+pub fn read(project_path: &str) -> Result<ClaudeMdDocument, String> {
+    let path = claude_md_path(project_path);
+    match std::fs::read_to_string(&path) {
+        Ok(content) => Ok(document(&path, true, content)),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(document(&path, false, String::new())),
+        Err(e) => Err(format!("Failed to read {}: {}", path.display(), e)),
+    }
+}
+
+/// Current content, or the built-in template when the project has no CLAUDE.md
+fn read_or_template(project_path: &str) -> Result<(ClaudeMdDocument, String), String> {
+    let current = read(project_path)?;
+    if current.exists {
+        let content = current.content.clone();
+        return Ok((current, content));
+    }
+    let name = Path::new(project_path)
+        .file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .unwrap_or_else(|| "App".to_string());
+    let bundle_id = format!("com.example.{}", name.to_lowercase().replace('-', ""));
+    Ok((current, crate::project::render_claude_md(&name, &bundle_id)))
+}
+
+fn write_atomic(path: &Path, content: &str) -> Result<(), String> {
+    let staged = path.with_extension("md.nocur-tmp");
+    std::fs::write(&staged, content).map_err(|e| format!("Failed to write {}: {}", staged.display(), e))?;
+    std::fs::rename(&staged, path).map_err(|e| {
+        let _ = std::fs::remove_file(&staged);
+        format!("Failed to replace {}: {}", path.display(), e)
+    })
+}
+
+/// Write `content` unless the file no longer matches `expected_hash`
+fn write_checked(project_path: &str, current: ClaudeMdDocument, expected_hash: Option<&str>, content: String) -> Result<ClaudeMdWrite, String> {
+    if expected_hash.is_some_and(|expected| expected != current.hash) {
+        return Ok(ClaudeMdWrite::Conflict { current });
+    }
+    let path = claude_md_path(project_path);
+    write_atomic(&path, &content)?;
+    Ok(ClaudeMdWrite::Written { document: document(&path, true, content) })
+}
+
+fn ensure_trailing_newline(text: &mut String) {
+    if !text.is_empty() && !text.ends_with('\n') {
+        text.push('\n');
+    }
+}
+
+/// Replace the body of the section titled `heading` (case-insensitive), or
+/// add it as a new `##` section at the end when there's none
+pub fn update_section(
+    project_path: &str,
+    heading: &str,
+    new_content: &str,
+    expected_hash: Option<&str>,
+) -> Result<ClaudeMdWrite, String> {
+    let heading = heading.trim();
+    if heading.is_empty() || heading.contains('\n') {
+        return Err("Section heading must be a single non-empty line".to_string());
+    }
+    let (current, mut content) = read_or_template(project_path)?;
+
+    let matches: Vec<ClaudeMdSection> = parse_sections(&content)
+        .into_iter()
+        .filter(|s| s.heading.as_deref().is_some_and(|h| h.eq_ignore_ascii_case(heading)))
+        .collect();
+
+    let mut body = new_content.trim_end().to_string();
+    if !body.is_empty() {
+        body = format!("{}\n", body.trim_start_matches('\n'));
+    }
+
+    match matches.as_slice() {
+        [] => {
+            ensure_trailing_newline(&mut content);
+            if !content.is_empty() {
+                content.push('\n');
+            }
+            content.push_str(&format!("## {}\n{}", heading, body));
+        }
+        [section] => {
+            let body_start = section.end - section.body.len();
+            // Keep the blank line that separated this section from the next
+            let separator = if section.end < content.len() && !body.is_empty() { "\n" } else { "" };
+            content.replace_range(body_start..section.end, &format!("{}{}", body, separator));
+        }
+        _ => return Err(format!("CLAUDE.md has {} sections titled '{}'", matches.len(), heading)),
+    }
+
+    write_checked(project_path, current, expected_hash, content)
+}
+
+/// Add `text` as a bullet under the Guidelines section, creating the section
+/// (or the file, from the template) when missing. A rule that's already
+/// listed isn't added again.
+pub fn append_rule(project_path: &str, text: &str) -> Result<ClaudeMdWrite, String> {
+    let rule = text.split_whitespace().collect::<Vec<_>>().join(" ");
+    let rule = rule.trim_start_matches(['-', '*', ' ']);
+    if rule.is_empty() {
+        return Err("Rule text is empty".to_string());
+    }
+    let bullet = format!("- {}", rule);
+    let (current, mut content) = read_or_template(project_path)?;
+
+    let section = parse_sections(&content)
+        .into_iter()
+        .find(|s| s.heading.as_deref().is_some_and(|h| h.eq_ignore_ascii_case(RULES_HEADING)));
+
+    match section {
+        Some(section) if section.body.lines().any(|line| line.trim() == bullet) => {
+            if current.exists {
+                return Ok(ClaudeMdWrite::Written { document: current });
+            }
+        }
+        Some(section) => {
+            let body_start = section.end - section.body.len();
+            let body_trimmed = section.body.trim_end();
+            if body_trimmed.is_empty() {
+                content.insert_str(body_start, &format!("{}\n", bullet));
+            } else {
+                // After the section's last non-blank line
+                let insert_at = body_start + body_trimmed.len();
+                content.insert_str(insert_at, &format!("\n{}", bullet));
+                if insert_at + 1 + bullet.len() == content.len() {
+                    content.push('\n');
+                }
+            }
+        }
+        None => {
+            ensure_trailing_newline(&mut content);
+            if !content.is_empty() {
+                content.push('\n');
+            }
+            content.push_str(&format!("## {}\n{}\n", RULES_HEADING, bullet));
+        }
+    }
+
+    // Appending never loses anything, so it doesn't need the caller's hash
+    write_checked(project_path, current, None, content)
+}
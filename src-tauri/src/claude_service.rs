@@ -0,0 +1,175 @@
+//! The bundled claude-service and its installed copy
+//!
+//! Release builds ship the built service (dist, package.json, node_modules) as
+//! a Tauri resource with a `manifest.json` of SHA-256 checksums for `dist/`,
+//! written by `claude-service/scripts/write-manifest.mjs`. The bundle is
+//! verified at startup; one that fails is skipped by the resolver.
+//!
+//! `install` copies the bundle into ~/.nocur/claude-service, which the
+//! resolver prefers, so a patched service survives restarts and the copy is
+//! refreshed when an app update ships a new version.
+
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+pub const MANIFEST_FILE: &str = "manifest.json";
+
+/// Result of verifying the bundled service at startup
+static BUNDLE_VERIFIED: OnceLock<bool> = OnceLock::new();
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ServiceManifest {
+    pub version: String,
+    /// Path relative to the service directory -> hex SHA-256
+    pub files: BTreeMap<String, String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ClaudeServiceInstall {
+    pub version: String,
+    pub path: String,
+    /// False when the installed copy already matched the bundle
+    pub updated: bool,
+    /// Version that was installed before, if any
+    pub previous_version: Option<String>,
+}
+
+/// Where `install` puts the service
+pub fn installed_dir() -> PathBuf {
+    crate::paths::nocur_dir().join("claude-service")
+}
+
+/// The service bundled with the app, if this build has one
+pub fn bundled_dir() -> Option<PathBuf> {
+    crate::paths::resource_dir()
+        .map(|dir| dir.join("claude-service"))
+        .filter(|dir| dir.join(MANIFEST_FILE).exists())
+}
+
+/// False only if the bundled service was checked and didn't match its manifest
+pub fn bundle_verified() -> bool {
+    BUNDLE_VERIFIED.get().copied().unwrap_or(true)
+}
+
+fn read_manifest(dir: &Path) -> Result<ServiceManifest, String> {
+    let path = dir.join(MANIFEST_FILE);
+    let content = std::fs::read_to_string(&path)
+        .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+    serde_json::from_str(&content).map_err(|e| format!("Invalid manifest {}: {}", path.display(), e))
+}
+
+fn file_hash(path: &Path) -> Result<String, String> {
+    let bytes = std::fs::read(path).map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+    Ok(Sha256::digest(&bytes).iter().map(|b| format!("{:02x}", b)).collect())
+}
+
+/// Check every file in `dir`'s manifest against its checksum
+pub fn verify(dir: &Path) -> Result<ServiceManifest, String> {
+    let manifest = read_manifest(dir)?;
+    if manifest.files.is_empty() {
+        return Err(format!("Manifest in {} lists no files", dir.display()));
+    }
+    for (file, expected) in &manifest.files {
+        let actual = file_hash(&dir.join(file))?;
+        if &actual != expected {
+            return Err(format!("{} doesn't match its checksum in {}", file, dir.display()));
+        }
+    }
+    Ok(manifest)
+}
+
+/// Verify the bundle and refresh an installed copy left by an older app version.
+/// Called once at startup.
+pub fn verify_at_startup() {
+    let Some(bundle) = bundled_dir() else {
+        return;
+    };
+    let manifest = match verify(&bundle) {
+        Ok(manifest) => manifest,
+        Err(e) => {
+            log::error!("Bundled claude-service failed verification: {}", e);
+            let _ = BUNDLE_VERIFIED.set(false);
+            return;
+        }
+    };
+    let _ = BUNDLE_VERIFIED.set(true);
+    log::info!("Bundled claude-service {} verified", manifest.version);
+
+    let installed = installed_dir();
+    if let Ok(current) = read_manifest(&installed) {
+        if current.version != manifest.version {
+            match install() {
+                Ok(result) => log::info!("Updated installed claude-service {} -> {}", current.version, result.version),
+                Err(e) => log::warn!("Failed to update installed claude-service: {}", e),
+            }
+        }
+    }
+}
+
+fn copy_dir(from: &Path, to: &Path) -> Result<(), String> {
+    std::fs::create_dir_all(to).map_err(|e| format!("Failed to create {}: {}", to.display(), e))?;
+    let entries = std::fs::read_dir(from).map_err(|e| format!("Failed to read {}: {}", from.display(), e))?;
+    for entry in entries.flatten() {
+        let source = entry.path();
+        let target = to.join(entry.file_name());
+        // Follow symlinks so the copy doesn't point back into the app bundle
+        if source.is_dir() {
+            copy_dir(&source, &target)?;
+        } else {
+            std::fs::copy(&source, &target)
+                .map_err(|e| format!("Failed to copy {}: {}", source.display(), e))?;
+        }
+    }
+    Ok(())
+}
+
+/// Copy the bundled service into ~/.nocur/claude-service, replacing what's
+/// there unless it already matches. The new copy is staged next to the old one
+/// and swapped in, so a failed copy leaves the previous install working.
+pub fn install() -> Result<ClaudeServiceInstall, String> {
+    let bundle = bundled_dir().ok_or_else(|| {
+        "This build doesn't include the claude-service. Build it with `cd claude-service && pnpm build`.".to_string()
+    })?;
+    let manifest = verify(&bundle)?;
+    let target = installed_dir();
+    let previous = read_manifest(&target).ok();
+
+    if previous.as_ref().is_some_and(|p| p.version == manifest.version) && verify(&target).is_ok() {
+        return Ok(ClaudeServiceInstall {
+            version: manifest.version,
+            path: target.to_string_lossy().to_string(),
+            updated: false,
+            previous_version: previous.map(|p| p.version),
+        });
+    }
+
+    let staging = target.with_extension("installing");
+    let _ = std::fs::remove_dir_all(&staging);
+    if let Err(e) = copy_dir(&bundle, &staging).and_then(|_| verify(&staging).map(|_| ())) {
+        let _ = std::fs::remove_dir_all(&staging);
+        return Err(e);
+    }
+
+    let old = target.with_extension("old");
+    let _ = std::fs::remove_dir_all(&old);
+    if target.exists() {
+        std::fs::rename(&target, &old).map_err(|e| format!("Failed to move aside {}: {}", target.display(), e))?;
+    }
+    if let Err(e) = std::fs::rename(&staging, &target) {
+        let _ = std::fs::rename(&old, &target);
+        return Err(format!("Failed to install to {}: {}", target.display(), e));
+    }
+    let _ = std::fs::remove_dir_all(&old);
+
+    log::info!("Installed claude-service {} to {}", manifest.version, target.display());
+    Ok(ClaudeServiceInstall {
+        version: manifest.version,
+        path: target.to_string_lossy().to_string(),
+        updated: true,
+        previous_version: previous.map(|p| p.version),
+    })
+}
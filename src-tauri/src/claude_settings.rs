@@ -0,0 +1,303 @@
+//! Taking over what the user already set up for Claude Code
+//!
+//! Sessions only load project settings, so the default model, permission
+//! rules and env in `~/.claude/settings.json` are otherwise ignored, and every
+//! tool call the user long since allowed goes through a permission prompt
+//! again. `read` collects those from the global file and, for a project, its
+//! `.claude/settings.json` and `.claude/settings.local.json`; the caller keeps
+//! them in the preferences and hands the rules to the permission server. The
+//! settings files are only ever read here.
+//!
+//! Rules use Claude Code's syntax: `Tool`, `Tool(*)`, `Bash(npm run test:*)`,
+//! `Edit(src/**)`, `WebFetch(domain:example.com)`. A rule with a specifier we
+//! can't evaluate is skipped and reported rather than guessed at.
+
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+const FILE_TOOLS: &[&str] = &["Read", "Edit", "Write", "MultiEdit", "NotebookEdit", "Glob", "Grep"];
+const SHELL_OPERATORS: &[&str] = &["&&", "||", ";", "|", "`", "$(", ">", "<", "\n"];
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct PermissionRules {
+    pub allow: Vec<String>,
+    pub deny: Vec<String>,
+}
+
+impl PermissionRules {
+    fn merge(&mut self, other: &PermissionRules) {
+        for rule in &other.allow {
+            if !self.allow.contains(rule) {
+                self.allow.push(rule.clone());
+            }
+        }
+        for rule in &other.deny {
+            if !self.deny.contains(rule) {
+                self.deny.push(rule.clone());
+            }
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.allow.is_empty() && self.deny.is_empty()
+    }
+
+    /// `Some((approve, rule))` when a rule decides the call; deny rules win
+    pub fn decide(&self, tool_name: &str, tool_input: &Value, cwd: Option<&Path>) -> Option<(bool, String)> {
+        let matching = |rules: &[String]| rules.iter().find(|rule| rule_matches(rule, tool_name, tool_input, cwd)).cloned();
+        matching(&self.deny)
+            .map(|rule| (false, rule))
+            .or_else(|| matching(&self.allow).map(|rule| (true, rule)))
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ImportedSetting {
+    pub source: String,
+    /// "model", "permissions.allow", "permissions.deny" or "env"
+    pub field: String,
+    pub value: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SkippedSetting {
+    pub source: String,
+    pub field: String,
+    pub value: String,
+    pub reason: String,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ImportReport {
+    /// Settings files that were found and read
+    pub sources: Vec<String>,
+    pub imported: Vec<ImportedSetting>,
+    pub skipped: Vec<SkippedSetting>,
+}
+
+/// What `read` found, for the caller to store
+#[derive(Debug, Default)]
+pub struct ClaudeSettings {
+    /// nocur's name for it: "sonnet", "opus" or "haiku"
+    pub model: Option<String>,
+    pub global_rules: PermissionRules,
+    pub project_rules: PermissionRules,
+    pub env: BTreeMap<String, String>,
+    pub report: ImportReport,
+}
+
+pub fn global_settings_path() -> Option<PathBuf> {
+    crate::paths::home_dir().ok().map(|home| home.join(".claude/settings.json"))
+}
+
+/// nocur's model for a Claude Code model setting, e.g. "claude-opus-4-5" -> "opus"
+fn map_model(model: &str) -> Option<&'static str> {
+    let model = model.to_lowercase();
+    ["opus", "sonnet", "haiku"].into_iter().find(|name| model.contains(name))
+}
+
+/// `(tool, specifier)` for "Tool(specifier)" or "Tool"
+fn parse_rule(rule: &str) -> Option<(&str, Option<&str>)> {
+    let rule = rule.trim();
+    match rule.split_once('(') {
+        Some((tool, rest)) => rest.strip_suffix(')').map(|specifier| (tool.trim(), Some(specifier.trim()))),
+        None if !rule.is_empty() => Some((rule, None)),
+        None => None,
+    }
+}
+
+/// Why `rule` can't be evaluated here, or None when it can
+fn unsupported(rule: &str) -> Option<String> {
+    let Some((tool, specifier)) = parse_rule(rule) else {
+        return Some("Not a permission rule".to_string());
+    };
+    match specifier {
+        None | Some("*") | Some("") => None,
+        Some(_) if tool == "Bash" || FILE_TOOLS.contains(&tool) => None,
+        Some(specifier) if tool == "WebFetch" && specifier.starts_with("domain:") => None,
+        Some(_) => Some(format!("Specifiers for {} aren't supported", tool)),
+    }
+}
+
+fn glob_regex(glob: &str) -> Option<Regex> {
+    let mut pattern = String::from("^");
+    let mut chars = glob.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '*' if chars.peek() == Some(&'*') => {
+                chars.next();
+                pattern.push_str(".*");
+            }
+            '*' => pattern.push_str("[^/]*"),
+            '?' => pattern.push_str("[^/]"),
+            c => pattern.push_str(&regex::escape(&c.to_string())),
+        }
+    }
+    pattern.push('$');
+    Regex::new(&pattern).ok()
+}
+
+/// A path rule as an absolute glob: `//abs` is absolute, `~/` is home, and
+/// anything else is relative to the session's directory
+fn absolute_glob(specifier: &str, cwd: Option<&Path>) -> Option<String> {
+    if let Some(absolute) = specifier.strip_prefix("//") {
+        return Some(format!("/{}", absolute));
+    }
+    if let Some(home_relative) = specifier.strip_prefix("~/") {
+        let home = crate::paths::home_dir().ok()?;
+        return Some(format!("{}/{}", home.display(), home_relative));
+    }
+    let relative = specifier.trim_start_matches("./").trim_start_matches('/');
+    Some(format!("{}/{}", cwd?.display(), relative))
+}
+
+fn rule_matches(rule: &str, tool_name: &str, tool_input: &Value, cwd: Option<&Path>) -> bool {
+    let Some((tool, specifier)) = parse_rule(rule) else {
+        return false;
+    };
+    // "mcp__server" covers every tool of that server
+    let tool_matches = tool == tool_name || (tool.starts_with("mcp__") && tool_name.starts_with(&format!("{}__", tool)));
+    if !tool_matches {
+        return false;
+    }
+    let specifier = match specifier {
+        None | Some("*") | Some("") => return true,
+        Some(specifier) => specifier,
+    };
+    let input = |key: &str| tool_input.get(key).and_then(Value::as_str);
+
+    if tool == "Bash" {
+        let Some(command) = input("command").map(str::trim) else {
+            return false;
+        };
+        // A prefix says nothing about what's chained after it; those still get asked
+        if SHELL_OPERATORS.iter().any(|op| command.contains(op)) {
+            return false;
+        }
+        return match specifier.strip_suffix(":*").or_else(|| specifier.strip_suffix('*')) {
+            Some(prefix) => command.starts_with(prefix.trim_end()),
+            None => command == specifier,
+        };
+    }
+    if FILE_TOOLS.contains(&tool) {
+        let Some(path) = input("file_path").or_else(|| input("notebook_path")).or_else(|| input("path")) else {
+            return false;
+        };
+        let Some(glob) = absolute_glob(specifier, cwd) else {
+            return false;
+        };
+        let path = match Path::new(path).is_absolute() {
+            true => PathBuf::from(path),
+            false => cwd.map_or_else(|| PathBuf::from(path), |cwd| cwd.join(path)),
+        };
+        return glob_regex(&glob).is_some_and(|re| re.is_match(&path.to_string_lossy()));
+    }
+    if let Some(domain) = specifier.strip_prefix("domain:") {
+        let host = input("url")
+            .and_then(|url| url.split("://").nth(1))
+            .and_then(|rest| rest.split(['/', ':', '?']).next())
+            .unwrap_or("");
+        return host == domain || host.ends_with(&format!(".{}", domain));
+    }
+    false
+}
+
+/// Read one settings file into `settings`
+fn read_file(path: &Path, settings: &mut ClaudeSettings, project: bool) {
+    let Ok(content) = std::fs::read_to_string(path) else {
+        return;
+    };
+    let source = path.to_string_lossy().to_string();
+    let json: Value = match serde_json::from_str(&content) {
+        Ok(json) => json,
+        Err(e) => {
+            settings.report.skipped.push(SkippedSetting {
+                source: source.clone(),
+                field: String::new(),
+                value: String::new(),
+                reason: format!("Failed to parse: {}", e),
+            });
+            return;
+        }
+    };
+    settings.report.sources.push(source.clone());
+    let report = &mut settings.report;
+
+    if let Some(model) = json.get("model").and_then(Value::as_str) {
+        match map_model(model) {
+            // Project settings are already applied by the session itself
+            Some(mapped) if !project => {
+                settings.model = Some(mapped.to_string());
+                report.imported.push(ImportedSetting { source: source.clone(), field: "model".to_string(), value: mapped.to_string() });
+            }
+            Some(_) => {}
+            None => report.skipped.push(SkippedSetting {
+                source: source.clone(),
+                field: "model".to_string(),
+                value: model.to_string(),
+                reason: "Not a model nocur offers".to_string(),
+            }),
+        }
+    }
+
+    let rules = if project { &mut settings.project_rules } else { &mut settings.global_rules };
+    for (field, target) in [("allow", &mut rules.allow), ("deny", &mut rules.deny)] {
+        let entries = json.pointer(&format!("/permissions/{}", field)).and_then(Value::as_array);
+        for rule in entries.into_iter().flatten().filter_map(Value::as_str) {
+            let field = format!("permissions.{}", field);
+            if let Some(reason) = unsupported(rule) {
+                report.skipped.push(SkippedSetting { source: source.clone(), field, value: rule.to_string(), reason });
+                continue;
+            }
+            if !target.iter().any(|r| r == rule) {
+                target.push(rule.to_string());
+            }
+            report.imported.push(ImportedSetting { source: source.clone(), field, value: rule.to_string() });
+        }
+    }
+
+    // A project's env is applied by the session itself too
+    let env = json.get("env").and_then(Value::as_object).filter(|_| !project);
+    for (key, value) in env.into_iter().flatten() {
+        match value.as_str() {
+            Some(value) => {
+                settings.env.insert(key.clone(), value.to_string());
+                report.imported.push(ImportedSetting { source: source.clone(), field: "env".to_string(), value: key.clone() });
+            }
+            None => report.skipped.push(SkippedSetting {
+                source: source.clone(),
+                field: "env".to_string(),
+                value: key.clone(),
+                reason: "Not a string".to_string(),
+            }),
+        }
+    }
+}
+
+/// The global settings and, with `project_path`, the project's
+pub fn read(project_path: Option<&str>) -> ClaudeSettings {
+    let mut settings = ClaudeSettings::default();
+    if let Some(path) = global_settings_path() {
+        read_file(&path, &mut settings, false);
+    }
+    if let Some(project) = project_path.map(Path::new) {
+        for name in ["settings.json", "settings.local.json"] {
+            read_file(&project.join(".claude").join(name), &mut settings, true);
+        }
+    }
+    settings
+}
+
+/// `base` with `other`'s rules added
+pub fn merged(base: &PermissionRules, other: &PermissionRules) -> PermissionRules {
+    let mut rules = base.clone();
+    rules.merge(other);
+    rules
+}
@@ -0,0 +1,272 @@
+//! In-memory trace of recent Tauri commands
+//!
+//! Commands wrapped in `traced!` record their name, a redacted digest of their
+//! arguments, when they started, how long they took and whether they returned Ok.
+//! The last `MAX_ENTRIES` are kept for `get_command_trace`; with the
+//! `traceCommandsToFile` preference on, each entry is also appended to
+//! ~/.nocur/logs/commands.jsonl.
+//!
+//! Tauri gives the invoke handler no view of a command's result, so tracing is
+//! done inside the command body. That also covers calls the agent bridge makes
+//! directly, which is why the buffer is a static rather than managed state.
+
+use parking_lot::Mutex;
+use serde::Serialize;
+use std::collections::VecDeque;
+use std::io::Write;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
+
+const MAX_ENTRIES: usize = 500;
+/// Serialized size above which an argument is summarized instead of copied
+const MAX_ARG_BYTES: usize = 256;
+/// Argument and field names whose values never reach the trace
+const SENSITIVE_NAMES: &[&str] = &["token", "key", "password"];
+
+static TRACE: CommandTrace = CommandTrace {
+    entries: Mutex::new(VecDeque::new()),
+    persist: AtomicBool::new(false),
+};
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CommandTraceEntry {
+    pub command: String,
+    /// JSON object of the serializable arguments, redacted and size-capped
+    pub args_digest: String,
+    /// Unix milliseconds
+    pub started_at: u64,
+    pub duration_ms: u64,
+    pub ok: bool,
+}
+
+struct CommandTrace {
+    entries: Mutex<VecDeque<CommandTraceEntry>>,
+    persist: AtomicBool,
+}
+
+fn log_path() -> PathBuf {
+    crate::paths::nocur_dir().join("logs").join("commands.jsonl")
+}
+
+fn append_to_log(entry: &CommandTraceEntry) {
+    let path = log_path();
+    if let Some(parent) = path.parent() {
+        let _ = std::fs::create_dir_all(parent);
+    }
+    let Ok(line) = serde_json::to_string(entry) else {
+        return;
+    };
+    if let Ok(mut file) = std::fs::OpenOptions::new().create(true).append(true).open(&path) {
+        let _ = writeln!(file, "{}", line);
+    }
+}
+
+/// Turn appending to commands.jsonl on or off
+pub fn set_persist(enabled: bool) {
+    TRACE.persist.store(enabled, Ordering::Relaxed);
+}
+
+/// The most recent entries, newest first
+pub fn recent(limit: Option<usize>) -> Vec<CommandTraceEntry> {
+    let entries = TRACE.entries.lock();
+    entries
+        .iter()
+        .rev()
+        .take(limit.unwrap_or(MAX_ENTRIES))
+        .cloned()
+        .collect()
+}
+
+fn is_sensitive(name: &str) -> bool {
+    let name = name.to_lowercase();
+    SENSITIVE_NAMES.iter().any(|s| name.contains(s))
+}
+
+fn redact(value: &mut serde_json::Value) {
+    match value {
+        serde_json::Value::Object(map) => {
+            for (name, field) in map.iter_mut() {
+                if is_sensitive(name) {
+                    *field = serde_json::Value::String("[redacted]".to_string());
+                } else {
+                    redact(field);
+                }
+            }
+        }
+        serde_json::Value::Array(items) => items.iter_mut().for_each(redact),
+        _ => {}
+    }
+}
+
+/// Collects serialized output until the cap, then refuses more so a large
+/// payload is never copied in full
+struct CappedWriter(Vec<u8>);
+
+impl Write for CappedWriter {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        if self.0.len() + buf.len() > MAX_ARG_BYTES {
+            return Err(std::io::Error::other("argument too large to trace"));
+        }
+        self.0.extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+fn digest_value<T: Serialize>(value: &T) -> serde_json::Value {
+    let mut writer = CappedWriter(Vec::new());
+    if serde_json::to_writer(&mut writer, value).is_err() {
+        return serde_json::Value::String(format!("<more than {} bytes>", MAX_ARG_BYTES));
+    }
+    let mut value: serde_json::Value = serde_json::from_slice(&writer.0).unwrap_or_default();
+    redact(&mut value);
+    value
+}
+
+/// Wraps a command argument for `traced!`: arguments that serialize are
+/// digested, injected ones like `State` and `AppHandle` are skipped
+pub struct Arg<'a, T>(pub &'a T);
+
+pub trait DigestSerialize {
+    fn digest(&self) -> Option<serde_json::Value>;
+}
+
+impl<T: Serialize> DigestSerialize for Arg<'_, T> {
+    fn digest(&self) -> Option<serde_json::Value> {
+        Some(digest_value(self.0))
+    }
+}
+
+pub trait DigestOther {
+    fn digest(&self) -> Option<serde_json::Value>;
+}
+
+impl<T> DigestOther for &Arg<'_, T> {
+    fn digest(&self) -> Option<serde_json::Value> {
+        None
+    }
+}
+
+/// Whether a command's return value counts as success: `Err` is a failure,
+/// anything that isn't a `Result` always succeeds
+pub trait ResultOutcome {
+    fn trace_ok(&self) -> bool;
+}
+
+impl<T, E> ResultOutcome for Result<T, E> {
+    fn trace_ok(&self) -> bool {
+        self.is_ok()
+    }
+}
+
+pub trait PlainOutcome {
+    fn trace_ok(&self) -> bool;
+}
+
+impl<T> PlainOutcome for &T {
+    fn trace_ok(&self) -> bool {
+        true
+    }
+}
+
+/// A command invocation in progress
+pub struct Span {
+    command: &'static str,
+    args_digest: String,
+    started_at: u64,
+    started: Instant,
+}
+
+impl Span {
+    pub fn start(command: &'static str, args: Vec<(&str, Option<serde_json::Value>)>) -> Self {
+        let digest: serde_json::Map<String, serde_json::Value> = args
+            .into_iter()
+            .filter_map(|(name, value)| {
+                let value = if is_sensitive(name) {
+                    serde_json::Value::String("[redacted]".to_string())
+                } else {
+                    value?
+                };
+                Some((name.to_string(), value))
+            })
+            .collect();
+        Self {
+            command,
+            args_digest: serde_json::Value::Object(digest).to_string(),
+            started_at: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|d| d.as_millis() as u64)
+                .unwrap_or(0),
+            started: Instant::now(),
+        }
+    }
+
+    pub fn finish(self, ok: bool) {
+        let entry = CommandTraceEntry {
+            command: self.command.to_string(),
+            args_digest: self.args_digest,
+            started_at: self.started_at,
+            duration_ms: self.started.elapsed().as_millis() as u64,
+            ok,
+        };
+        if TRACE.persist.load(Ordering::Relaxed) {
+            append_to_log(&entry);
+        }
+        let mut entries = TRACE.entries.lock();
+        if entries.len() == MAX_ENTRIES {
+            entries.pop_front();
+        }
+        entries.push_back(entry);
+    }
+}
+
+/// Wrap a command so each call is recorded in the trace. Goes around the whole
+/// item, doc comments and `#[tauri::command]` included.
+macro_rules! traced {
+    (
+        $(#[$attr:meta])*
+        async fn $name:ident($($arg:ident: $ty:ty),* $(,)?) -> $ret:ty $body:block
+    ) => {
+        $(#[$attr])*
+        // A command's arguments are its IPC signature
+        #[allow(clippy::too_many_arguments)]
+        async fn $name($($arg: $ty),*) -> $ret {
+            #[allow(unused_imports)]
+            use $crate::command_trace::{DigestOther as _, DigestSerialize as _, PlainOutcome as _, ResultOutcome as _};
+            let span = $crate::command_trace::Span::start(
+                stringify!($name),
+                vec![$((stringify!($arg), (&$crate::command_trace::Arg(&$arg)).digest())),*],
+            );
+            let result: $ret = async move $body.await;
+            span.finish((&result).trace_ok());
+            result
+        }
+    };
+    (
+        $(#[$attr:meta])*
+        fn $name:ident($($arg:ident: $ty:ty),* $(,)?) -> $ret:ty $body:block
+    ) => {
+        $(#[$attr])*
+        // A command's arguments are its IPC signature
+        #[allow(clippy::too_many_arguments)]
+        fn $name($($arg: $ty),*) -> $ret {
+            #[allow(unused_imports)]
+            use $crate::command_trace::{DigestOther as _, DigestSerialize as _, PlainOutcome as _, ResultOutcome as _};
+            let span = $crate::command_trace::Span::start(
+                stringify!($name),
+                vec![$((stringify!($arg), (&$crate::command_trace::Arg(&$arg)).digest())),*],
+            );
+            #[allow(clippy::redundant_closure_call)]
+            let result: $ret = (move || $body)();
+            span.finish((&result).trace_ok());
+            result
+        }
+    };
+}
+
+pub(crate) use traced;
@@ -0,0 +1,555 @@
+//! Moving nocur's configuration to another Mac
+//!
+//! `export` writes a zip (made with `ditto`) holding `manifest.json`, the
+//! preferences (agent profiles, log filters, screenshot presets and the rest)
+//! and optionally the ACE playbooks from ~/.config/nocur/ace/playbooks and the
+//! skills in ~/.claude/skills. State that only means something on this Mac,
+//! like open sessions and device usage, stays behind. A file the redaction
+//! patterns find a secret in is left out, and so is a Claude env variable with
+//! one; the manifest lists what was omitted and why.
+//!
+//! `import` checks the manifest's version and plans everything before touching
+//! anything. With `Merge` what's already here wins a conflict (a same-named
+//! skill, playbook, agent profile, filter...), with `Replace` the bundle does;
+//! neither deletes what the bundle doesn't have. The current configuration is
+//! first exported in full to ~/.nocur/backups, then each changed file or
+//! directory is staged next to its target and swapped in. A failed swap puts
+//! back the ones already made.
+
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use crate::UserPreferences;
+
+/// Bumped when the layout changes in a way older versions can't read
+pub const BUNDLE_VERSION: u32 = 1;
+const MANIFEST_FILE: &str = "manifest.json";
+const PREFERENCES_FILE: &str = "preferences.json";
+
+#[derive(Debug, Clone, Copy, Default, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct ExportOptions {
+    pub include_playbooks: bool,
+    pub include_skills: bool,
+    /// Session names and badges; they only match sessions on this Mac
+    pub include_session_names: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Omitted {
+    /// Path in the bundle, or e.g. "preferences.json#claudeEnv.OPENAI_API_KEY"
+    pub path: String,
+    pub reason: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Manifest {
+    pub version: u32,
+    pub created_at: String,
+    pub app_version: String,
+    /// "preferences", "playbooks", "skills", "sessionNames"
+    pub includes: Vec<String>,
+    pub files: Vec<String>,
+    #[serde(default)]
+    pub omitted: Vec<Omitted>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ImportStrategy {
+    /// Keep what's here when both have something by the same name
+    Merge,
+    /// Take the bundle's
+    Replace,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Conflict {
+    /// "skill", "playbook", "agentProfile", "logFilter", "screenshotPreset",
+    /// "redactionPattern", "claudeEnv" or "sessionName"
+    pub kind: String,
+    pub name: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ImportPreview {
+    pub manifest: Manifest,
+    pub conflicts: Vec<Conflict>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ImportResult {
+    pub manifest: Manifest,
+    /// Resolved by the strategy: kept for a merge, overwritten for a replace
+    pub conflicts: Vec<Conflict>,
+    /// Files and directories written
+    pub applied: Vec<String>,
+    /// Full export of the configuration before the import
+    pub backup_path: String,
+}
+
+/// A directory under the temp dir, removed when dropped
+struct TempDir(PathBuf);
+
+impl TempDir {
+    fn new(prefix: &str) -> Result<Self, String> {
+        let dir = std::env::temp_dir().join(format!("{}-{}", prefix, uuid::Uuid::new_v4()));
+        fs::create_dir_all(&dir).map_err(|e| format!("Failed to create {}: {}", dir.display(), e))?;
+        Ok(Self(dir))
+    }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.0);
+    }
+}
+
+fn playbooks_dir() -> Result<PathBuf, String> {
+    Ok(crate::paths::home_dir()?.join(".config/nocur/ace/playbooks"))
+}
+
+fn skills_dir() -> Result<PathBuf, String> {
+    Ok(crate::paths::claude_dir()?.join("skills"))
+}
+
+fn remove(path: &Path) -> std::io::Result<()> {
+    match path.is_dir() {
+        true => fs::remove_dir_all(path),
+        false => fs::remove_file(path),
+    }
+}
+
+fn run_ditto(args: &[&str], from: &Path, to: &Path) -> Result<(), String> {
+    let output = Command::new("ditto")
+        .args(args)
+        .arg(from)
+        .arg(to)
+        .output()
+        .map_err(|e| format!("Failed to run ditto: {}", e))?;
+    if !output.status.success() {
+        return Err(format!("ditto failed: {}", String::from_utf8_lossy(&output.stderr).trim()));
+    }
+    Ok(())
+}
+
+/// Write `contents` into the bundle at `bundle_path`, unless it's text with a secret in it
+fn add_file(
+    bundle: &Path,
+    bundle_path: &str,
+    contents: &[u8],
+    omit_secrets: bool,
+    manifest: &mut Manifest,
+) -> Result<(), String> {
+    if omit_secrets {
+        let kinds = std::str::from_utf8(contents).map(crate::redaction::secret_kinds).unwrap_or_default();
+        if !kinds.is_empty() {
+            manifest.omitted.push(Omitted {
+                path: bundle_path.to_string(),
+                reason: format!("Looks like it contains a secret ({})", kinds.join(", ")),
+            });
+            return Ok(());
+        }
+    }
+    let target = bundle.join(bundle_path);
+    if let Some(parent) = target.parent() {
+        fs::create_dir_all(parent).map_err(|e| format!("Failed to create {}: {}", parent.display(), e))?;
+    }
+    fs::write(&target, contents).map_err(|e| format!("Failed to write {}: {}", target.display(), e))?;
+    manifest.files.push(bundle_path.to_string());
+    Ok(())
+}
+
+/// Every file under `dir` into the bundle under `bundle_dir`
+fn add_tree(bundle: &Path, bundle_dir: &str, dir: &Path, omit_secrets: bool, manifest: &mut Manifest) -> Result<(), String> {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return Ok(());
+    };
+    let mut entries: Vec<_> = entries.filter_map(|e| e.ok()).collect();
+    entries.sort_by_key(|e| e.file_name());
+    for entry in entries {
+        let path = entry.path();
+        let bundle_path = format!("{}/{}", bundle_dir, entry.file_name().to_string_lossy());
+        if path.is_dir() {
+            add_tree(bundle, &bundle_path, &path, omit_secrets, manifest)?;
+        } else {
+            let contents = fs::read(&path).map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+            add_file(bundle, &bundle_path, &contents, omit_secrets, manifest)?;
+        }
+    }
+    Ok(())
+}
+
+/// Drop what only means something on this Mac
+fn strip_local_state(prefs: &mut UserPreferences, include_session_names: bool) {
+    prefs.active_sessions.clear();
+    prefs.checkpoint_projects.clear();
+    prefs.device_last_used.clear();
+    if !include_session_names {
+        prefs.session_names.clear();
+        prefs.session_badges.clear();
+    }
+}
+
+fn write_bundle(output_path: &Path, options: ExportOptions, omit_secrets: bool) -> Result<Manifest, String> {
+    let staging = TempDir::new("nocur-export")?;
+    let bundle = staging.0.join("nocur-configuration");
+    fs::create_dir_all(&bundle).map_err(|e| format!("Failed to create {}: {}", bundle.display(), e))?;
+
+    let mut includes = vec!["preferences".to_string()];
+    if options.include_playbooks {
+        includes.push("playbooks".to_string());
+    }
+    if options.include_skills {
+        includes.push("skills".to_string());
+    }
+    if options.include_session_names {
+        includes.push("sessionNames".to_string());
+    }
+    let mut manifest = Manifest {
+        version: BUNDLE_VERSION,
+        created_at: chrono::Utc::now().to_rfc3339(),
+        app_version: env!("CARGO_PKG_VERSION").to_string(),
+        includes,
+        files: Vec::new(),
+        omitted: Vec::new(),
+    };
+
+    let mut prefs = crate::read_user_preferences()?;
+    if omit_secrets {
+        strip_local_state(&mut prefs, options.include_session_names);
+        let secret_env: Vec<String> = prefs
+            .claude_env
+            .iter()
+            .filter(|(key, value)| !crate::redaction::secret_kinds(&format!("{}={}", key, value)).is_empty())
+            .map(|(key, _)| key.clone())
+            .collect();
+        for key in secret_env {
+            prefs.claude_env.remove(&key);
+            manifest.omitted.push(Omitted {
+                path: format!("{}#claudeEnv.{}", PREFERENCES_FILE, key),
+                reason: "Looks like a secret".to_string(),
+            });
+        }
+    }
+    let content = serde_json::to_string_pretty(&prefs).map_err(|e| format!("Failed to serialize preferences: {}", e))?;
+    add_file(&bundle, PREFERENCES_FILE, content.as_bytes(), omit_secrets, &mut manifest)?;
+
+    if options.include_playbooks {
+        add_tree(&bundle, "playbooks", &playbooks_dir()?, omit_secrets, &mut manifest)?;
+    }
+    if options.include_skills {
+        add_tree(&bundle, "skills", &skills_dir()?, omit_secrets, &mut manifest)?;
+    }
+
+    let content = serde_json::to_string_pretty(&manifest).map_err(|e| format!("Failed to serialize manifest: {}", e))?;
+    fs::write(bundle.join(MANIFEST_FILE), content).map_err(|e| format!("Failed to write manifest: {}", e))?;
+
+    if let Some(parent) = output_path.parent() {
+        fs::create_dir_all(parent).map_err(|e| format!("Failed to create {}: {}", parent.display(), e))?;
+    }
+    run_ditto(&["-c", "-k", "--sequesterRsrc"], &bundle, output_path)?;
+    Ok(manifest)
+}
+
+/// Write the selected configuration to the zip at `output_path`
+pub fn export(output_path: &Path, options: ExportOptions) -> Result<Manifest, String> {
+    write_bundle(output_path, options, true)
+}
+
+/// Everything, secrets included, to ~/.nocur/backups
+fn backup() -> Result<PathBuf, String> {
+    let path = crate::paths::nocur_dir()
+        .join("backups")
+        .join(format!("configuration-{}.zip", chrono::Local::now().format("%Y%m%d-%H%M%S")));
+    let options = ExportOptions { include_playbooks: true, include_skills: true, include_session_names: true };
+    write_bundle(&path, options, false)?;
+    Ok(path)
+}
+
+/// Unpack the bundle and check it's one this version can read
+fn open(path: &Path) -> Result<(TempDir, Manifest), String> {
+    if !path.exists() {
+        return Err(format!("No configuration bundle at {}", path.display()));
+    }
+    let dir = TempDir::new("nocur-import")?;
+    run_ditto(&["-x", "-k"], path, &dir.0)?;
+    let content = fs::read_to_string(dir.0.join(MANIFEST_FILE))
+        .map_err(|_| format!("{} isn't a nocur configuration bundle: it has no manifest", path.display()))?;
+    let manifest: Manifest =
+        serde_json::from_str(&content).map_err(|e| format!("Failed to parse the bundle's manifest: {}", e))?;
+    if manifest.version == 0 || manifest.version > BUNDLE_VERSION {
+        return Err(format!(
+            "The bundle is version {}; this version of nocur reads up to version {}",
+            manifest.version, BUNDLE_VERSION
+        ));
+    }
+    Ok((dir, manifest))
+}
+
+/// `local` with `imported`'s entries added; a same-named entry that differs is
+/// a conflict, resolved for the bundle when `replace`
+fn merge_named<T: Clone>(
+    local: Vec<T>,
+    imported: Vec<T>,
+    name: impl Fn(&T) -> &str,
+    same: impl Fn(&T, &T) -> bool,
+    kind: &str,
+    replace: bool,
+    conflicts: &mut Vec<Conflict>,
+) -> Vec<T> {
+    let mut merged = local;
+    for item in imported {
+        match merged.iter_mut().find(|existing| name(existing) == name(&item)) {
+            None => merged.push(item),
+            Some(existing) if !same(existing, &item) => {
+                conflicts.push(Conflict { kind: kind.to_string(), name: name(&item).to_string() });
+                if replace {
+                    *existing = item;
+                }
+            }
+            Some(_) => {}
+        }
+    }
+    merged
+}
+
+fn merge_map<M, T>(local: M, imported: M, kind: &str, replace: bool, conflicts: &mut Vec<Conflict>) -> M
+where
+    M: IntoIterator<Item = (String, T)> + FromIterator<(String, T)>,
+    T: Clone + PartialEq,
+{
+    merge_named(
+        local.into_iter().collect(),
+        imported.into_iter().collect(),
+        |(name, _)| name.as_str(),
+        |(_, a), (_, b)| a == b,
+        kind,
+        replace,
+        conflicts,
+    )
+    .into_iter()
+    .collect()
+}
+
+fn merge_preferences(
+    local: UserPreferences,
+    imported: UserPreferences,
+    strategy: ImportStrategy,
+    session_names: bool,
+    conflicts: &mut Vec<Conflict>,
+) -> UserPreferences {
+    let replace = strategy == ImportStrategy::Replace;
+    // Plain settings come from whichever side wins; named entries are merged below
+    let mut merged = match replace {
+        true => UserPreferences {
+            active_sessions: local.active_sessions.clone(),
+            checkpoint_projects: local.checkpoint_projects.clone(),
+            device_last_used: local.device_last_used.clone(),
+            ..imported.clone()
+        },
+        false => local.clone(),
+    };
+
+    merged.agent_profiles = match (local.agent_profiles, imported.agent_profiles) {
+        (local, Some(imported)) => Some(merge_named(
+            local.unwrap_or_default(),
+            imported,
+            |p| p.name.as_str(),
+            |a, b| a == b,
+            "agentProfile",
+            replace,
+            conflicts,
+        )),
+        (local, None) => local,
+    };
+    merged.log_filters = merge_map(local.log_filters, imported.log_filters, "logFilter", replace, conflicts);
+    merged.screenshot_presets =
+        merge_map(local.screenshot_presets, imported.screenshot_presets, "screenshotPreset", replace, conflicts);
+    merged.redaction_patterns = merge_named(
+        local.redaction_patterns,
+        imported.redaction_patterns,
+        |p| p.name.as_str(),
+        |a, b| a.regex == b.regex,
+        "redactionPattern",
+        replace,
+        conflicts,
+    );
+    merged.claude_env = merge_map(local.claude_env, imported.claude_env, "claudeEnv", replace, conflicts);
+    if session_names {
+        merged.session_names = merge_map(local.session_names, imported.session_names, "sessionName", replace, conflicts);
+        // A badge goes with its name, so its conflicts are the name's
+        merged.session_badges = merge_map(local.session_badges, imported.session_badges, "sessionBadge", replace, &mut Vec::new());
+    } else {
+        merged.session_names = local.session_names;
+        merged.session_badges = local.session_badges;
+    }
+    merged
+}
+
+/// What an import would change
+struct Plan {
+    conflicts: Vec<Conflict>,
+    preferences: Option<UserPreferences>,
+    /// (from the unpacked bundle, target)
+    copies: Vec<(PathBuf, PathBuf)>,
+}
+
+fn same_file(a: &Path, b: &Path) -> bool {
+    a.is_file() && b.is_file() && fs::read(a).ok() == fs::read(b).ok()
+}
+
+fn plan(bundle: &Path, manifest: &Manifest, strategy: ImportStrategy) -> Result<Plan, String> {
+    let mut conflicts = Vec::new();
+
+    let preferences = match fs::read_to_string(bundle.join(PREFERENCES_FILE)) {
+        Ok(content) => {
+            let imported: UserPreferences =
+                serde_json::from_str(&content).map_err(|e| format!("Failed to parse the bundle's preferences: {}", e))?;
+            let local = crate::read_user_preferences()?;
+            let session_names = manifest.includes.iter().any(|i| i == "sessionNames");
+            Some(merge_preferences(local, imported, strategy, session_names, &mut conflicts))
+        }
+        Err(_) => None,
+    };
+
+    let mut copies = Vec::new();
+    for (bundle_dir, kind, target_dir) in [("playbooks", "playbook", playbooks_dir()?), ("skills", "skill", skills_dir()?)] {
+        let Ok(entries) = fs::read_dir(bundle.join(bundle_dir)) else {
+            continue;
+        };
+        let mut entries: Vec<_> = entries.filter_map(|e| e.ok()).collect();
+        entries.sort_by_key(|e| e.file_name());
+        for entry in entries {
+            let target = target_dir.join(entry.file_name());
+            if target.exists() {
+                if same_file(&entry.path(), &target) {
+                    continue;
+                }
+                conflicts.push(Conflict { kind: kind.to_string(), name: entry.file_name().to_string_lossy().to_string() });
+                if strategy == ImportStrategy::Merge {
+                    continue;
+                }
+            }
+            copies.push((entry.path(), target));
+        }
+    }
+
+    Ok(Plan { conflicts, preferences, copies })
+}
+
+/// The bundle's manifest and the conflicts importing it would run into
+pub fn preview(path: &Path, strategy: ImportStrategy) -> Result<ImportPreview, String> {
+    let (bundle, manifest) = open(path)?;
+    let plan = plan(&bundle.0, &manifest, strategy)?;
+    Ok(ImportPreview { manifest, conflicts: plan.conflicts })
+}
+
+fn sibling(target: &Path, suffix: &str) -> PathBuf {
+    let name = target.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_default();
+    target.with_file_name(format!(".{}.{}", name, suffix))
+}
+
+fn copy_tree(from: &Path, to: &Path) -> Result<(), String> {
+    if from.is_dir() {
+        fs::create_dir_all(to).map_err(|e| format!("Failed to create {}: {}", to.display(), e))?;
+        for entry in fs::read_dir(from).map_err(|e| format!("Failed to read {}: {}", from.display(), e))? {
+            let entry = entry.map_err(|e| format!("Failed to read {}: {}", from.display(), e))?;
+            copy_tree(&entry.path(), &to.join(entry.file_name()))?;
+        }
+        Ok(())
+    } else {
+        fs::copy(from, to)
+            .map(|_| ())
+            .map_err(|e| format!("Failed to copy {}: {}", from.display(), e))
+    }
+}
+
+/// Move each staged path over its target, or on a failure undo the moves made
+fn swap_in(swaps: &[(PathBuf, PathBuf)]) -> Result<(), String> {
+    let mut done: Vec<(&Path, Option<PathBuf>)> = Vec::new();
+    for (staged, target) in swaps {
+        let previous = target.exists().then(|| sibling(target, "nocur-previous"));
+        let moved = match &previous {
+            Some(previous) => fs::rename(target, previous),
+            None => Ok(()),
+        }
+        .and_then(|_| fs::rename(staged, target));
+        if let Err(e) = moved {
+            done.push((target.as_path(), previous));
+            for (target, previous) in done.into_iter().rev() {
+                match previous {
+                    Some(previous) if previous.exists() => {
+                        let _ = remove(target);
+                        let _ = fs::rename(previous, target);
+                    }
+                    Some(_) => {}
+                    None => {
+                        let _ = remove(target);
+                    }
+                }
+            }
+            for (staged, _) in swaps {
+                let _ = remove(staged);
+            }
+            return Err(format!("Failed to replace {}: {}; nothing was imported", target.display(), e));
+        }
+        done.push((target.as_path(), previous));
+    }
+    for previous in done.into_iter().filter_map(|(_, previous)| previous) {
+        let _ = remove(&previous);
+    }
+    Ok(())
+}
+
+/// Import the bundle at `path`, backing up the current configuration first
+pub fn import(path: &Path, strategy: ImportStrategy) -> Result<ImportResult, String> {
+    let (bundle, manifest) = open(path)?;
+    let plan = plan(&bundle.0, &manifest, strategy)?;
+    let backup_path = backup()?;
+
+    // Stage everything first, so a failure here leaves the configuration untouched
+    let mut swaps = Vec::new();
+    let staging = (|| {
+        if let Some(preferences) = &plan.preferences {
+            let target = crate::get_preferences_path();
+            let staged = sibling(&target, "nocur-import");
+            let content = serde_json::to_string_pretty(preferences)
+                .map_err(|e| format!("Failed to serialize preferences: {}", e))?;
+            fs::write(&staged, content).map_err(|e| format!("Failed to write {}: {}", staged.display(), e))?;
+            swaps.push((staged, target));
+        }
+        for (from, target) in &plan.copies {
+            if let Some(parent) = target.parent() {
+                fs::create_dir_all(parent).map_err(|e| format!("Failed to create {}: {}", parent.display(), e))?;
+            }
+            let staged = sibling(target, "nocur-import");
+            let _ = remove(&staged);
+            // Pushed first so a partial copy is cleaned up too
+            swaps.push((staged.clone(), target.clone()));
+            copy_tree(from, &staged)?;
+        }
+        Ok::<_, String>(())
+    })();
+    if let Err(e) = staging {
+        for (staged, _) in &swaps {
+            let _ = remove(staged);
+        }
+        return Err(e);
+    }
+    swap_in(&swaps)?;
+
+    Ok(ImportResult {
+        manifest,
+        conflicts: plan.conflicts,
+        applied: swaps.iter().map(|(_, target)| target.to_string_lossy().to_string()).collect(),
+        backup_path: backup_path.to_string_lossy().to_string(),
+    })
+}
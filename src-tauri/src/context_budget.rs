@@ -0,0 +1,306 @@
+//! Fitting the view hierarchy and logs into a token budget
+//!
+//! A raw `nocur-swift ui hierarchy` dump or a log capture can run to tens of
+//! thousands of tokens, which is most of an agent's context. The compact forms
+//! here prune to an estimated budget instead and say how much they left out
+//! (`omitted_count`), so the agent knows to ask again more specifically, e.g.
+//! with a focus element or a later `since`.
+//!
+//! Tokens are estimated at four bytes each, which is close enough for JSON and
+//! log text to keep well clear of the real limit.
+
+use serde::{Deserialize, Serialize};
+use std::collections::{HashSet, VecDeque};
+
+use crate::SimulatorLogEntry;
+
+pub const DEFAULT_MAX_TOKENS: usize = 4000;
+/// Labels and values are cut to this many characters
+const MAX_LABEL_CHARS: usize = 80;
+/// Log messages are cut to this many characters
+const MAX_LOG_LINE_CHARS: usize = 400;
+/// Per-entry overhead of a log line's timestamp, level and process
+const LOG_ENTRY_OVERHEAD_TOKENS: usize = 12;
+
+pub fn estimate_tokens(text: &str) -> usize {
+    text.len().div_ceil(4)
+}
+
+fn estimate_json_tokens<T: Serialize>(value: &T) -> usize {
+    serde_json::to_string(value).map(|s| estimate_tokens(&s)).unwrap_or(0)
+}
+
+fn clip(text: &str, max_chars: usize) -> String {
+    if text.chars().count() <= max_chars {
+        return text.to_string();
+    }
+    format!("{}…", text.chars().take(max_chars).collect::<String>())
+}
+
+fn is_zero(n: &usize) -> bool {
+    *n == 0
+}
+
+fn default_true() -> bool {
+    true
+}
+
+// ============ View hierarchy ============
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct Frame {
+    pub x: f64,
+    pub y: f64,
+    pub width: f64,
+    pub height: f64,
+}
+
+/// A node of nocur-swift's `ViewNode` tree
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ViewNode {
+    pub class_name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub frame: Option<Frame>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub accessibility_identifier: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub accessibility_label: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub accessibility_value: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub accessibility_traits: Option<Vec<String>>,
+    #[serde(default = "default_true")]
+    pub is_enabled: bool,
+    #[serde(default)]
+    pub is_hidden: bool,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub children: Vec<ViewNode>,
+    /// Descendants pruned from under this node
+    #[serde(default, skip_serializing_if = "is_zero")]
+    pub omitted_children: usize,
+}
+
+impl ViewNode {
+    fn count(&self) -> usize {
+        1 + self.children.iter().map(ViewNode::count).sum::<usize>()
+    }
+
+    fn is_visible(&self) -> bool {
+        !self.is_hidden && self.frame.map_or(true, |f| f.width > 0.0 && f.height > 0.0)
+    }
+
+    /// The node on its own, for costing it without its subtree
+    fn without_children(&self) -> ViewNode {
+        ViewNode { children: Vec::new(), omitted_children: 0, ..self.clone() }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct HierarchyData {
+    capture_method: Option<String>,
+    bundle_id: Option<String>,
+    root: ViewNode,
+}
+
+#[derive(Debug, Deserialize)]
+struct HierarchyOutput {
+    success: bool,
+    data: Option<HierarchyData>,
+    error: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CompactHierarchy {
+    pub capture_method: Option<String>,
+    pub bundle_id: Option<String>,
+    pub root: ViewNode,
+    /// The element the tree was cut down to, when one was asked for
+    pub focus_accessibility_id: Option<String>,
+    pub estimated_tokens: usize,
+    /// Nodes left out, whether invisible, outside the focus or over budget
+    pub omitted_count: usize,
+}
+
+/// Drop hidden and zero-size nodes (with everything under them) and clip
+/// long text; returns how many nodes went
+fn prune_invisible(node: &mut ViewNode) -> usize {
+    node.accessibility_label = node.accessibility_label.as_deref().map(|l| clip(l, MAX_LABEL_CHARS));
+    node.accessibility_value = node.accessibility_value.as_deref().map(|v| clip(v, MAX_LABEL_CHARS));
+
+    let mut omitted = 0;
+    node.children.retain(|child| {
+        let visible = child.is_visible();
+        if !visible {
+            omitted += child.count();
+        }
+        visible
+    });
+    for child in &mut node.children {
+        omitted += prune_invisible(child);
+    }
+    omitted
+}
+
+/// Cut `node` down to the path to the element with `id` plus that element's
+/// subtree; returns how many nodes went, or None if there's no such element
+fn focus_on(node: &mut ViewNode, id: &str) -> Option<usize> {
+    if node.accessibility_identifier.as_deref() == Some(id) {
+        return Some(0);
+    }
+    let (index, below) = node
+        .children
+        .iter_mut()
+        .enumerate()
+        .find_map(|(i, child)| focus_on(child, id).map(|omitted| (i, omitted)))?;
+    let kept = node.children.swap_remove(index);
+    let siblings: usize = node.children.iter().map(ViewNode::count).sum();
+    node.children = vec![kept];
+    node.omitted_children += siblings;
+    Some(siblings + below)
+}
+
+/// `node` with only the descendants whose index paths are in `kept`
+fn rebuild(node: &ViewNode, path: &mut Vec<usize>, kept: &HashSet<Vec<usize>>, omitted: &mut usize) -> ViewNode {
+    let mut out = ViewNode { omitted_children: node.omitted_children, ..node.without_children() };
+    for (i, child) in node.children.iter().enumerate() {
+        path.push(i);
+        if kept.contains(path.as_slice()) {
+            out.children.push(rebuild(child, path, kept, omitted));
+        } else {
+            out.omitted_children += child.count();
+            *omitted += child.count();
+        }
+        path.pop();
+    }
+    out
+}
+
+/// Keep nodes breadth-first until `max_tokens` is spent, so the top of the
+/// tree survives and the deepest leaves go first. The root is always kept.
+fn fit_to_budget(root: ViewNode, max_tokens: usize) -> (ViewNode, usize) {
+    let mut kept: HashSet<Vec<usize>> = HashSet::new();
+    let mut spent = 0;
+    let mut queue: VecDeque<(Vec<usize>, &ViewNode)> = VecDeque::from([(Vec::new(), &root)]);
+    while let Some((path, node)) = queue.pop_front() {
+        let cost = estimate_json_tokens(&node.without_children());
+        if !path.is_empty() && spent + cost > max_tokens {
+            break;
+        }
+        spent += cost;
+        for (i, child) in node.children.iter().enumerate() {
+            let mut child_path = path.clone();
+            child_path.push(i);
+            queue.push_back((child_path, child));
+        }
+        kept.insert(path);
+    }
+
+    let mut omitted = 0;
+    let compact = rebuild(&root, &mut Vec::new(), &kept, &mut omitted);
+    (compact, omitted)
+}
+
+/// Compact form of `nocur-swift ui hierarchy` output
+pub fn compact_hierarchy(raw: &str, max_tokens: usize, focus_accessibility_id: Option<String>) -> Result<CompactHierarchy, String> {
+    let output: HierarchyOutput =
+        serde_json::from_str(raw).map_err(|e| format!("Failed to parse view hierarchy: {}", e))?;
+    if !output.success {
+        return Err(output.error.unwrap_or_else(|| "Failed to capture the view hierarchy".to_string()));
+    }
+    let data = output.data.ok_or("View hierarchy output has no data")?;
+
+    let mut root = data.root;
+    let mut omitted = prune_invisible(&mut root);
+    if let Some(id) = &focus_accessibility_id {
+        omitted += focus_on(&mut root, id)
+            .ok_or_else(|| format!("No visible element with accessibility identifier '{}'", id))?;
+    }
+    let (root, over_budget) = fit_to_budget(root, max_tokens);
+    omitted += over_budget;
+
+    Ok(CompactHierarchy {
+        capture_method: data.capture_method,
+        bundle_id: data.bundle_id,
+        estimated_tokens: estimate_json_tokens(&root),
+        root,
+        focus_accessibility_id,
+        omitted_count: omitted,
+    })
+}
+
+// ============ Logs ============
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CompactLogs {
+    /// Oldest first
+    pub entries: Vec<SimulatorLogEntry>,
+    /// Entries at or after `since`, before sampling
+    pub total: usize,
+    pub estimated_tokens: usize,
+    pub omitted_count: usize,
+}
+
+fn is_error_level(entry: &SimulatorLogEntry) -> bool {
+    entry.level == "error" || entry.level == "fault"
+}
+
+fn entry_tokens(entry: &SimulatorLogEntry) -> usize {
+    estimate_tokens(&entry.process) + estimate_tokens(&entry.message) + LOG_ENTRY_OVERHEAD_TOKENS
+}
+
+/// Log entries from `since` (ms since the epoch) on, fit to `max_tokens`.
+/// Every error and fault is kept; the rest of the budget goes to lines from
+/// the start and end of the range alternately, so both how it began and
+/// where it got to are there.
+pub fn compact_logs(entries: &[SimulatorLogEntry], max_tokens: usize, since: Option<u64>) -> CompactLogs {
+    let entries: Vec<SimulatorLogEntry> = entries
+        .iter()
+        .filter(|e| since.map_or(true, |since| e.timestamp >= since))
+        .map(|e| SimulatorLogEntry { message: clip(&e.message, MAX_LOG_LINE_CHARS), ..e.clone() })
+        .collect();
+    let total = entries.len();
+
+    let mut keep = vec![false; total];
+    let mut spent = 0;
+    for (i, entry) in entries.iter().enumerate() {
+        if is_error_level(entry) {
+            keep[i] = true;
+            spent += entry_tokens(entry);
+        }
+    }
+
+    let others: Vec<usize> = (0..total).filter(|&i| !keep[i]).collect();
+    let (mut head, mut tail) = (0, others.len());
+    let mut from_head = true;
+    while head < tail {
+        let i = if from_head { others[head] } else { others[tail - 1] };
+        let cost = entry_tokens(&entries[i]);
+        if spent + cost > max_tokens {
+            break;
+        }
+        spent += cost;
+        keep[i] = true;
+        if from_head {
+            head += 1;
+        } else {
+            tail -= 1;
+        }
+        from_head = !from_head;
+    }
+
+    let kept: Vec<SimulatorLogEntry> = entries
+        .into_iter()
+        .zip(keep)
+        .filter_map(|(entry, keep)| keep.then_some(entry))
+        .collect();
+    CompactLogs {
+        omitted_count: total - kept.len(),
+        estimated_tokens: kept.iter().map(entry_tokens).sum(),
+        entries: kept,
+        total,
+    }
+}
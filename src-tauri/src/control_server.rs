@@ -0,0 +1,383 @@
+//! Local control server for `nocur-ctl`
+//!
+//! Lets a terminal drive the running app instead of starting a second one. It
+//! listens on ~/.nocur/control.sock and speaks JSON-RPC style, one request per
+//! line:
+//!
+//! `{"id": 1, "token": "...", "method": "build", "params": {"scheme": "App"}}`
+//!
+//! answered with `{"id": 1, "result": ...}` or
+//! `{"id": 1, "error": {"code": -32000, "message": "..."}}`. As with the agent
+//! bridge, the token is generated per app run; it's written to
+//! ~/.nocur/control.token (readable only by the user) so `nocur-ctl` can find it.
+//! Only the methods in `dispatch` are exposed, and only one build or run goes at
+//! a time per working directory; different worktrees build side by side.
+
+use parking_lot::Mutex;
+use serde::Deserialize;
+use std::collections::HashSet;
+use std::io::{BufRead, BufReader, Write};
+use std::os::unix::fs::{OpenOptionsExt, PermissionsExt};
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+use tauri::{AppHandle, Manager};
+
+use crate::claude::ClaudeState;
+use crate::errors::CommandError;
+use crate::{AppState, DeviceInfo};
+
+const PARSE_ERROR: i64 = -32700;
+const METHOD_NOT_FOUND: i64 = -32601;
+const INVALID_PARAMS: i64 = -32602;
+/// The command ran and returned an error
+const COMMAND_FAILED: i64 = -32000;
+const UNAUTHORIZED: i64 = -32001;
+/// Another build or run is in progress
+const BUSY: i64 = -32002;
+
+fn socket_path() -> PathBuf {
+    crate::paths::nocur_dir().join("control.sock")
+}
+
+fn token_path() -> PathBuf {
+    crate::paths::nocur_dir().join("control.token")
+}
+
+#[derive(Debug, Deserialize)]
+struct ControlRequest {
+    #[serde(default)]
+    id: serde_json::Value,
+    #[serde(default)]
+    token: String,
+    method: String,
+    #[serde(default)]
+    params: serde_json::Value,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct BuildParams {
+    /// Defaults to the active session's project
+    project_path: Option<String>,
+    scheme: Option<String>,
+    /// Build configuration, Debug when not given
+    configuration: Option<String>,
+    /// Defaults to the device selected in the app
+    device: Option<DeviceInfo>,
+    auto_stream_logs: Option<bool>,
+    /// Profile from the project's .nocur/env to launch with
+    env_profile: Option<String>,
+    launch_env: Option<std::collections::HashMap<String, String>>,
+    /// Locale, region and text size for a simulator launch
+    launch_overrides: Option<crate::launch_overrides::LaunchOverrides>,
+    /// Don't bring up the Simulator window or capture live frames
+    headless: Option<bool>,
+    /// Signing overrides for a device build; "build" only
+    development_team: Option<String>,
+    code_sign_identity: Option<String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct StopParams {
+    /// Defaults to the last app run through the control server
+    bundle_id: Option<String>,
+    device_id: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct SendMessageParams {
+    message: String,
+    agent_mode: Option<String>,
+}
+
+struct RpcError {
+    code: i64,
+    message: String,
+}
+
+impl RpcError {
+    fn new(code: i64, message: impl Into<String>) -> Self {
+        Self { code, message: message.into() }
+    }
+}
+
+impl From<String> for RpcError {
+    fn from(message: String) -> Self {
+        Self::new(COMMAND_FAILED, message)
+    }
+}
+
+impl From<CommandError> for RpcError {
+    fn from(error: CommandError) -> Self {
+        Self::new(COMMAND_FAILED, error.to_string())
+    }
+}
+
+/// State shared by every connection
+struct Shared {
+    token: String,
+    /// Working directories with a build or run in flight
+    building: Mutex<HashSet<PathBuf>>,
+    /// Bundle ID of the last successful run, for `stop` without arguments
+    last_bundle_id: Mutex<Option<String>>,
+}
+
+/// Takes a working directory out of `building` when its build or run finishes,
+/// however it finishes
+struct BuildSlot<'a> {
+    building: &'a Mutex<HashSet<PathBuf>>,
+    dir: PathBuf,
+}
+
+impl<'a> BuildSlot<'a> {
+    fn acquire(building: &'a Mutex<HashSet<PathBuf>>, dir: PathBuf) -> Option<Self> {
+        building.lock().insert(dir.clone()).then_some(Self { building, dir })
+    }
+}
+
+impl Drop for BuildSlot<'_> {
+    fn drop(&mut self) {
+        self.building.lock().remove(&self.dir);
+    }
+}
+
+pub struct ControlServer {
+    shared: Arc<Shared>,
+    running: Arc<Mutex<bool>>,
+}
+
+impl ControlServer {
+    pub fn new() -> Self {
+        Self {
+            shared: Arc::new(Shared {
+                token: uuid::Uuid::new_v4().to_string(),
+                building: Mutex::new(HashSet::new()),
+                last_bundle_id: Mutex::new(None),
+            }),
+            running: Arc::new(Mutex::new(false)),
+        }
+    }
+
+    pub fn start(&self, app_handle: AppHandle) {
+        {
+            let mut running = self.running.lock();
+            if *running {
+                log::info!("Control server already running");
+                return;
+            }
+            *running = true;
+        }
+
+        if let Err(e) = write_token(&self.shared.token) {
+            log::error!("Failed to write control token: {}", e);
+            *self.running.lock() = false;
+            return;
+        }
+
+        let socket_path = socket_path();
+        let _ = std::fs::remove_file(&socket_path);
+
+        let running = self.running.clone();
+        let shared = self.shared.clone();
+
+        thread::spawn(move || {
+            let listener = match UnixListener::bind(&socket_path) {
+                Ok(l) => l,
+                Err(e) => {
+                    log::error!("Failed to bind control socket: {}", e);
+                    *running.lock() = false;
+                    return;
+                }
+            };
+
+            if let Err(e) = std::fs::set_permissions(&socket_path, std::fs::Permissions::from_mode(0o600)) {
+                log::warn!("Failed to set permissions on {}: {}", socket_path.display(), e);
+            }
+
+            log::info!("Control server listening on {}", socket_path.display());
+
+            // Non-blocking so the loop notices `stop`
+            listener.set_nonblocking(true).ok();
+
+            while *running.lock() {
+                match listener.accept() {
+                    Ok((stream, _)) => {
+                        let app_clone = app_handle.clone();
+                        let shared_clone = shared.clone();
+                        thread::spawn(move || {
+                            handle_connection(stream, &shared_clone, app_clone);
+                        });
+                    }
+                    Err(ref e) if e.kind() == std::io::ErrorKind::WouldBlock => {
+                        thread::sleep(Duration::from_millis(100));
+                    }
+                    Err(e) => {
+                        log::error!("Failed to accept control connection: {}", e);
+                    }
+                }
+            }
+
+            log::info!("Control server stopped");
+        });
+    }
+
+    /// Stop accepting connections and remove the socket and token; called on app exit
+    pub fn stop(&self) {
+        *self.running.lock() = false;
+        // Removed here rather than by the listener thread, which may not get to
+        // run again before the process exits
+        let _ = std::fs::remove_file(socket_path());
+        let _ = std::fs::remove_file(token_path());
+    }
+}
+
+fn write_token(token: &str) -> Result<(), String> {
+    let dir = crate::paths::nocur_dir();
+    std::fs::create_dir_all(&dir).map_err(|e| format!("Failed to create {}: {}", dir.display(), e))?;
+    let path = token_path();
+    // Created private rather than tightened after the write; `mode` only
+    // applies to new files, so drop any left by a crash
+    let _ = std::fs::remove_file(&path);
+    let mut file = std::fs::OpenOptions::new()
+        .write(true)
+        .create(true)
+        .truncate(true)
+        .mode(0o600)
+        .open(&path)
+        .map_err(|e| format!("Failed to open {}: {}", path.display(), e))?;
+    file.write_all(token.as_bytes())
+        .map_err(|e| format!("Failed to write {}: {}", path.display(), e))
+}
+
+fn write_reply(stream: &mut UnixStream, id: &serde_json::Value, result: Result<serde_json::Value, RpcError>) {
+    let reply = match result {
+        Ok(value) => serde_json::json!({ "id": id, "result": value }),
+        Err(error) => serde_json::json!({ "id": id, "error": { "code": error.code, "message": error.message } }),
+    };
+    if let Err(e) = writeln!(stream, "{}", reply) {
+        log::error!("Failed to write control response: {}", e);
+    }
+    let _ = stream.flush();
+}
+
+/// Serve requests until the client hangs up
+fn handle_connection(mut stream: UnixStream, shared: &Shared, app_handle: AppHandle) {
+    // The accepted stream inherits non-blocking mode from the listener
+    stream.set_nonblocking(false).ok();
+    stream.set_write_timeout(Some(Duration::from_secs(5))).ok();
+
+    let reader = match stream.try_clone() {
+        Ok(s) => BufReader::new(s),
+        Err(e) => {
+            log::error!("Failed to read from control socket: {}", e);
+            return;
+        }
+    };
+
+    for line in reader.lines() {
+        let Ok(line) = line else {
+            break;
+        };
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        let request: ControlRequest = match serde_json::from_str(&line) {
+            Ok(r) => r,
+            Err(e) => {
+                write_reply(&mut stream, &serde_json::Value::Null, Err(RpcError::new(PARSE_ERROR, format!("Invalid request: {}", e))));
+                continue;
+            }
+        };
+
+        if request.token != shared.token {
+            log::warn!("Rejected control request with bad token: {}", request.method);
+            write_reply(&mut stream, &request.id, Err(RpcError::new(UNAUTHORIZED, "Invalid token")));
+            continue;
+        }
+
+        log::info!("Control command: {}", request.method);
+        let result = tauri::async_runtime::block_on(dispatch(&request.method, request.params, shared, app_handle.clone()));
+        write_reply(&mut stream, &request.id, result);
+    }
+}
+
+fn to_json<T: serde::Serialize>(value: T) -> Result<serde_json::Value, RpcError> {
+    serde_json::to_value(value).map_err(|e| RpcError::from(format!("Failed to serialize result: {}", e)))
+}
+
+fn parse_params<T: serde::de::DeserializeOwned + Default>(method: &str, params: serde_json::Value) -> Result<T, RpcError> {
+    if params.is_null() {
+        return Ok(T::default());
+    }
+    serde_json::from_value(params).map_err(|e| RpcError::new(INVALID_PARAMS, format!("Invalid params for {}: {}", method, e)))
+}
+
+/// Fill in what the app already knows: the session's project and the selected device
+fn resolve_build_params(mut params: BuildParams, app_handle: &AppHandle) -> BuildParams {
+    if params.project_path.is_none() {
+        params.project_path = app_handle
+            .state::<Mutex<ClaudeState>>()
+            .lock()
+            .session
+            .as_ref()
+            .map(|s| s.get_working_dir().to_string());
+    }
+    if params.device.is_none() {
+        params.device = app_handle.state::<Mutex<AppState>>().lock().selected_device.clone();
+    }
+    params
+}
+
+async fn dispatch(
+    method: &str,
+    params: serde_json::Value,
+    shared: &Shared,
+    app_handle: AppHandle,
+) -> Result<serde_json::Value, RpcError> {
+    match method {
+        "build" | "run" => {
+            let p = resolve_build_params(parse_params::<BuildParams>(method, params)?, &app_handle);
+            // Worktrees resolve to different directories, so they don't block each other
+            let dir = p
+                .project_path
+                .as_deref()
+                .map(|path| std::fs::canonicalize(path).unwrap_or_else(|_| PathBuf::from(path)))
+                .unwrap_or_default();
+            let _slot = BuildSlot::acquire(&shared.building, dir)
+                .ok_or_else(|| RpcError::new(BUSY, "A build or run is already in progress for this directory"))?;
+            if method == "build" {
+                to_json(crate::build_project(p.project_path, p.scheme, p.configuration, p.device, p.development_team, p.code_sign_identity, app_handle).await?)
+            } else {
+                let result = crate::run_project(p.project_path, p.scheme, p.configuration, p.device, p.auto_stream_logs, p.env_profile, p.launch_env, None, p.launch_overrides, p.headless, app_handle).await?;
+                if result.success {
+                    *shared.last_bundle_id.lock() = result.bundle_id.clone();
+                }
+                to_json(result)
+            }
+        }
+        "stop" => {
+            let p = parse_params::<StopParams>(method, params)?;
+            let bundle_id = p
+                .bundle_id
+                .or_else(|| shared.last_bundle_id.lock().clone())
+                .ok_or_else(|| RpcError::new(INVALID_PARAMS, "No app to stop; pass a bundle ID"))?;
+            crate::terminate_app_on_simulator(bundle_id.clone(), p.device_id, app_handle.state()).await?;
+            to_json(serde_json::json!({ "bundleId": bundle_id }))
+        }
+        "screenshot" => to_json(crate::take_screenshot(None, None, app_handle).await?),
+        "devices" => to_json(crate::list_devices(None, None, None, app_handle.state()).await?),
+        "sendMessage" => {
+            let p: SendMessageParams = serde_json::from_value(params)
+                .map_err(|e| RpcError::new(INVALID_PARAMS, format!("Invalid params for {}: {}", method, e)))?;
+            crate::send_claude_message(p.message, p.agent_mode, app_handle.clone(), app_handle.state()).await?;
+            to_json(serde_json::Value::Null)
+        }
+        other => Err(RpcError::new(METHOD_NOT_FOUND, format!("Unknown method: {}", other))),
+    }
+}
@@ -0,0 +1,187 @@
+//! Noticing an app that crashes on every launch
+//!
+//! After a simulator launch, `watch_launch` keeps an eye on the app's process
+//! for `CRASH_WINDOW`. An exit inside it is emitted as `app-terminated` and
+//! counts as a crash on launch for that project and bundle ID; an app that
+//! stays up resets the count. At `THRESHOLD` crashes in a row the latest crash
+//! report goes out with `crash-loop-detected`, and `run_project` stops
+//! relaunching the app until `reset_crash_loop_counter` is called.
+//!
+//! Physical devices aren't watched: their process isn't visible from the Mac.
+
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::process::Command;
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Manager};
+
+use crate::CrashReport;
+
+/// An exit this soon after launch counts as crashing on launch
+pub const CRASH_WINDOW: Duration = Duration::from_secs(10);
+/// Crashes on launch in a row that make a loop
+pub const THRESHOLD: u32 = 3;
+const POLL_INTERVAL: Duration = Duration::from_millis(250);
+/// How long to wait for ReportCrash to write the report after the exit
+const REPORT_WAIT: Duration = Duration::from_secs(5);
+
+#[derive(Debug, Default)]
+struct History {
+    consecutive_crashes: u32,
+    latest_report: Option<CrashReport>,
+}
+
+/// `crash-loop-detected`, and what `run_project` reports while backing off
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CrashLoopDetected {
+    pub project_path: String,
+    pub bundle_id: String,
+    pub consecutive_crashes: u32,
+    pub report: Option<CrashReport>,
+    /// One line from the report, e.g. "EXC_BAD_ACCESS (SIGSEGV): Namespace SIGNAL, Code 11"
+    pub summary: String,
+}
+
+/// `app-terminated`
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AppTerminated {
+    pub project_path: String,
+    pub bundle_id: String,
+    pub pid: u32,
+    pub ms_after_launch: u64,
+    pub crash_report: Option<CrashReport>,
+}
+
+/// Consecutive crashes on launch per (project, bundle ID)
+#[derive(Default)]
+pub struct CrashLoops {
+    launches: Mutex<HashMap<(String, String), History>>,
+}
+
+impl CrashLoops {
+    pub fn consecutive_crashes(&self, project_path: &str, bundle_id: &str) -> u32 {
+        self.launches
+            .lock()
+            .get(&(project_path.to_string(), bundle_id.to_string()))
+            .map_or(0, |h| h.consecutive_crashes)
+    }
+
+    /// The loop the app is in, if it's crashed on launch `THRESHOLD` times in a row
+    pub fn looping(&self, project_path: &str, bundle_id: &str) -> Option<CrashLoopDetected> {
+        let launches = self.launches.lock();
+        let history = launches.get(&(project_path.to_string(), bundle_id.to_string()))?;
+        (history.consecutive_crashes >= THRESHOLD).then(|| detected(project_path, bundle_id, history))
+    }
+
+    /// Count a launch's outcome; returns the loop when this crash completes one
+    fn record(
+        &self,
+        project_path: &str,
+        bundle_id: &str,
+        crashed: bool,
+        report: Option<CrashReport>,
+    ) -> Option<CrashLoopDetected> {
+        let mut launches = self.launches.lock();
+        let history = launches.entry((project_path.to_string(), bundle_id.to_string())).or_default();
+        if !crashed {
+            *history = History::default();
+            return None;
+        }
+        history.consecutive_crashes += 1;
+        if report.is_some() {
+            history.latest_report = report;
+        }
+        (history.consecutive_crashes == THRESHOLD).then(|| detected(project_path, bundle_id, history))
+    }
+
+    /// Forget the project's crashes so its next run launches again; true if
+    /// any were counted
+    pub fn reset(&self, project_path: &str) -> bool {
+        let mut launches = self.launches.lock();
+        let before = launches.len();
+        launches.retain(|(project, _), _| project != project_path);
+        launches.len() != before
+    }
+}
+
+fn detected(project_path: &str, bundle_id: &str, history: &History) -> CrashLoopDetected {
+    CrashLoopDetected {
+        project_path: project_path.to_string(),
+        bundle_id: bundle_id.to_string(),
+        consecutive_crashes: history.consecutive_crashes,
+        summary: history
+            .latest_report
+            .as_ref()
+            .map(summary)
+            .unwrap_or_else(|| "No crash report was written".to_string()),
+        report: history.latest_report.clone(),
+    }
+}
+
+pub fn summary(report: &CrashReport) -> String {
+    match (&report.exception_type, &report.crash_reason) {
+        (Some(exception), Some(reason)) => format!("{}: {}", exception, reason),
+        (Some(text), None) | (None, Some(text)) => text.clone(),
+        (None, None) => format!("{} crashed", report.process_name),
+    }
+}
+
+fn is_running(pid: u32) -> bool {
+    Command::new("kill")
+        .args(["-0", &pid.to_string()])
+        .output()
+        .map(|o| o.status.success())
+        .unwrap_or(false)
+}
+
+/// The newest crash report for `bundle_id` written since `since`, waiting a
+/// little for it to appear
+fn crash_report_since(bundle_id: &str, since: u64) -> Option<CrashReport> {
+    let deadline = Instant::now() + REPORT_WAIT;
+    loop {
+        let report = crate::collect_crash_reports(Some(bundle_id), Some(since)).ok().and_then(|r| r.into_iter().next());
+        if report.is_some() || Instant::now() >= deadline {
+            return report;
+        }
+        std::thread::sleep(Duration::from_millis(500));
+    }
+}
+
+/// Watch the launched app for `CRASH_WINDOW` and count the outcome. Blocks;
+/// returns the loop when this launch completes one.
+pub fn watch_launch(app_handle: &AppHandle, project_path: &str, bundle_id: &str, pid: u32) -> Option<CrashLoopDetected> {
+    let launched_at = Instant::now();
+    let launched_secs = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs();
+    while launched_at.elapsed() < CRASH_WINDOW {
+        if !is_running(pid) {
+            break;
+        }
+        std::thread::sleep(POLL_INTERVAL);
+    }
+    let crashed = !is_running(pid);
+    let report = crashed.then(|| {
+        let ms_after_launch = launched_at.elapsed().as_millis() as u64;
+        let crash_report = crash_report_since(bundle_id, launched_secs);
+        crate::event_journal::emit_stamped(app_handle, "app-terminated", AppTerminated {
+            project_path: project_path.to_string(),
+            bundle_id: bundle_id.to_string(),
+            pid,
+            ms_after_launch,
+            crash_report: crash_report.clone(),
+        });
+        crash_report
+    });
+
+    let detected = app_handle.state::<CrashLoops>().record(project_path, bundle_id, crashed, report.flatten());
+    if let Some(detected) = &detected {
+        log::warn!("{} crashed on launch {} times in a row: {}", bundle_id, detected.consecutive_crashes, detected.summary);
+        crate::event_journal::emit_stamped(app_handle, "crash-loop-detected", detected.clone());
+    }
+    detected
+}
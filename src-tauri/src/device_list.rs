@@ -0,0 +1,584 @@
+//! Device listing straight from simctl and devicectl
+//!
+//! `list_devices` used to ask nocur-swift, which only reshapes what
+//! `simctl list devices --json` and `devicectl list devices --json-output`
+//! already report. Both tools now run concurrently and are parsed here, with
+//! the same mapping nocur-swift's `DeviceManager` applies. nocur-swift is only
+//! used when one of them produces output that doesn't parse.
+//!
+//! devicectl's schema moved between Xcode 15 and 16: Xcode 16 reports the UDID
+//! as `hardwareProperties.udid` and a `tunnelState`, while Xcode 15 only has
+//! the UDID inside `potentialHostnames` and no tunnel state.
+//!
+//! Simulators whose runtime is gone are listed as unavailable; `arrange`
+//! leaves them out unless asked, and can group and reorder the list for the
+//! picker.
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::Path;
+use std::process::Command;
+use std::time::Instant;
+
+use crate::{DeviceInfo, DeviceListResult, DeviceState, DeviceType};
+
+/// How long one source took, returned with the list for diagnostics
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DeviceSourceTiming {
+    /// "simctl", "devicectl" or "nocur-swift"
+    pub source: String,
+    pub duration_ms: u64,
+    pub device_count: usize,
+    /// Why the source gave no devices, if it failed
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+// ============ simctl ============
+
+#[derive(Debug, Deserialize)]
+struct SimctlList {
+    /// Runtime identifier -> devices
+    devices: HashMap<String, Vec<SimctlDevice>>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct SimctlDevice {
+    udid: String,
+    name: String,
+    state: String,
+    #[serde(default)]
+    is_available: bool,
+}
+
+/// "com.apple.CoreSimulator.SimRuntime.iOS-18-0" -> "18.0"
+fn runtime_os_version(runtime: &str) -> String {
+    runtime
+        .trim_start_matches("com.apple.CoreSimulator.SimRuntime.")
+        .trim_start_matches("iOS-")
+        .replace('-', ".")
+}
+
+fn parse_simctl(json: &str) -> Result<Vec<DeviceInfo>, String> {
+    let list: SimctlList = serde_json::from_str(json).map_err(|e| format!("Failed to parse simctl output: {}", e))?;
+    let mut devices = Vec::new();
+    for (runtime, runtime_devices) in list.devices {
+        // watchOS, tvOS and visionOS runtimes are listed too
+        if !runtime.contains("iOS") && !runtime.contains("iPhoneOS") {
+            continue;
+        }
+        let os_version = runtime_os_version(&runtime);
+        for device in runtime_devices {
+            let state = match device.state.as_str() {
+                _ if !device.is_available => DeviceState::Unavailable,
+                "Booted" => DeviceState::Booted,
+                "Shutdown" => DeviceState::Shutdown,
+                _ => DeviceState::Unavailable,
+            };
+            devices.push(DeviceInfo {
+                id: device.udid,
+                core_device_id: None,
+                // A simulator's name is its model
+                model: device.name.clone(),
+                name: device.name,
+                os_version: os_version.clone(),
+                device_type: DeviceType::Simulator,
+                state,
+                is_available: device.is_available,
+                pairing_state: None,
+                developer_mode_enabled: None,
+            });
+        }
+    }
+    Ok(devices)
+}
+
+// ============ devicectl ============
+
+#[derive(Debug, Deserialize)]
+struct DevicectlOutput {
+    result: DevicectlResult,
+}
+
+#[derive(Debug, Deserialize)]
+struct DevicectlResult {
+    #[serde(default)]
+    devices: Vec<DevicectlDevice>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct DevicectlDevice {
+    /// CoreDevice UUID, what devicectl's `--device` takes
+    identifier: String,
+    #[serde(default)]
+    device_properties: DevicectlDeviceProperties,
+    #[serde(default)]
+    hardware_properties: DevicectlHardwareProperties,
+    #[serde(default)]
+    connection_properties: DevicectlConnectionProperties,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct DevicectlDeviceProperties {
+    name: Option<String>,
+    os_version_number: Option<String>,
+    /// "enabled" or "disabled"; missing before iOS 16
+    developer_mode_status: Option<String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct DevicectlHardwareProperties {
+    platform: Option<String>,
+    marketing_name: Option<String>,
+    /// Xcode 16 and later
+    udid: Option<String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct DevicectlConnectionProperties {
+    pairing_state: Option<String>,
+    /// Xcode 16 and later: "connected", "disconnected" or "unavailable"
+    tunnel_state: Option<String>,
+    /// Xcode 15: set while the device is reachable, e.g. "wired"
+    transport_type: Option<String>,
+    #[serde(default)]
+    potential_hostnames: Vec<String>,
+}
+
+/// The xcodebuild UDID (8 hex digits, a dash, 16 hex digits) from a
+/// `<UDID>.coredevice.local` hostname
+fn udid_from_hostnames(hostnames: &[String]) -> Option<String> {
+    hostnames
+        .iter()
+        .filter_map(|h| h.strip_suffix(".coredevice.local"))
+        .find(|candidate| {
+            candidate.len() == 25
+                && candidate.find('-') == Some(8)
+                && candidate.chars().filter(|&c| c == '-').count() == 1
+        })
+        .map(String::from)
+}
+
+fn parse_devicectl(json: &str) -> Result<Vec<DeviceInfo>, String> {
+    let output: DevicectlOutput =
+        serde_json::from_str(json).map_err(|e| format!("Failed to parse devicectl output: {}", e))?;
+    let devices = output
+        .result
+        .devices
+        .into_iter()
+        .filter(|d| matches!(d.hardware_properties.platform.as_deref(), Some("iOS" | "iPhoneOS")))
+        .map(|device| {
+            let connection = &device.connection_properties;
+            let paired = connection.pairing_state.as_deref() == Some("paired");
+            let reachable = match connection.tunnel_state.as_deref() {
+                Some(tunnel) => tunnel != "unavailable",
+                None => connection.transport_type.is_some(),
+            };
+            let state = match (paired, reachable) {
+                (true, true) => DeviceState::Connected,
+                (true, false) => DeviceState::Disconnected,
+                (false, _) => DeviceState::Unavailable,
+            };
+            let id = device
+                .hardware_properties
+                .udid
+                .clone()
+                .or_else(|| udid_from_hostnames(&connection.potential_hostnames))
+                .unwrap_or_else(|| device.identifier.clone());
+
+            DeviceInfo {
+                id,
+                name: device.device_properties.name.clone().unwrap_or_else(|| "Unknown Device".to_string()),
+                model: device.hardware_properties.marketing_name.clone().unwrap_or_else(|| "Unknown".to_string()),
+                os_version: device.device_properties.os_version_number.clone().unwrap_or_else(|| "Unknown".to_string()),
+                device_type: DeviceType::Physical,
+                is_available: state == DeviceState::Connected,
+                state,
+                pairing_state: connection.pairing_state.clone(),
+                developer_mode_enabled: device.device_properties.developer_mode_status.as_deref().map(|s| s == "enabled"),
+                core_device_id: Some(device.identifier),
+            }
+        })
+        .collect();
+    Ok(devices)
+}
+
+// ============ Listing ============
+
+/// What one source produced: devices, or whether its failure means the
+/// listing as a whole can't be trusted
+enum SourceOutcome {
+    Devices(Vec<DeviceInfo>),
+    /// The tool couldn't run or reported an error; treated as no devices
+    Failed(String),
+    /// The tool answered with something we can't read; use nocur-swift instead
+    Unparseable(String),
+}
+
+fn run_simctl() -> SourceOutcome {
+    let output = match Command::new("xcrun").args(["simctl", "list", "devices", "--json"]).output() {
+        Ok(output) if output.status.success() => output,
+        Ok(output) => return SourceOutcome::Failed(String::from_utf8_lossy(&output.stderr).trim().to_string()),
+        Err(e) => return SourceOutcome::Failed(format!("Failed to run simctl: {}", e)),
+    };
+    match parse_simctl(&String::from_utf8_lossy(&output.stdout)) {
+        Ok(devices) => SourceOutcome::Devices(devices),
+        Err(e) => SourceOutcome::Unparseable(e),
+    }
+}
+
+fn run_devicectl() -> SourceOutcome {
+    // devicectl only writes JSON to a file
+    let temp_file = std::env::temp_dir().join(format!("devicectl_{}.json", uuid::Uuid::new_v4()));
+    let output = Command::new("xcrun")
+        .args(["devicectl", "list", "devices", "--json-output"])
+        .arg(&temp_file)
+        .output();
+    let data = std::fs::read_to_string(&temp_file);
+    let _ = std::fs::remove_file(&temp_file);
+
+    match output {
+        Ok(output) if output.status.success() => {}
+        // Older Xcodes have no devicectl; nocur-swift lists no devices then either
+        Ok(output) => return SourceOutcome::Failed(String::from_utf8_lossy(&output.stderr).trim().to_string()),
+        Err(e) => return SourceOutcome::Failed(format!("Failed to run devicectl: {}", e)),
+    }
+    let Ok(data) = data else {
+        return SourceOutcome::Unparseable("devicectl wrote no JSON output".to_string());
+    };
+    match parse_devicectl(&data) {
+        Ok(devices) => SourceOutcome::Devices(devices),
+        Err(e) => SourceOutcome::Unparseable(e),
+    }
+}
+
+fn timed(source: &str, run: fn() -> SourceOutcome) -> (SourceOutcome, DeviceSourceTiming) {
+    let started = Instant::now();
+    let outcome = run();
+    let (device_count, error) = match &outcome {
+        SourceOutcome::Devices(devices) => (devices.len(), None),
+        SourceOutcome::Failed(e) | SourceOutcome::Unparseable(e) => (0, Some(e.clone())),
+    };
+    let timing = DeviceSourceTiming {
+        source: source.to_string(),
+        duration_ms: started.elapsed().as_millis() as u64,
+        device_count,
+        error,
+    };
+    (outcome, timing)
+}
+
+/// Booted and connected devices first, then by name, as nocur-swift sorts them
+fn sort_devices(devices: &mut [DeviceInfo]) {
+    devices.sort_by(|a, b| {
+        let active = |d: &DeviceInfo| matches!(d.state, DeviceState::Booted | DeviceState::Connected);
+        active(b).cmp(&active(a)).then_with(|| a.name.cmp(&b.name))
+    });
+}
+
+fn result_from(devices: Vec<DeviceInfo>, timings: Vec<DeviceSourceTiming>) -> DeviceListResult {
+    let count = |device_type: DeviceType| devices.iter().filter(|d| d.device_type == device_type).count() as i32;
+    DeviceListResult {
+        simulator_count: count(DeviceType::Simulator),
+        physical_count: count(DeviceType::Physical),
+        devices,
+        timings,
+        groups: None,
+    }
+}
+
+/// Simulators and physical devices from simctl and devicectl, run side by
+/// side. None if either one's output couldn't be parsed.
+fn list_native() -> (Option<DeviceListResult>, Vec<DeviceSourceTiming>) {
+    let ((simulators, simctl_timing), (physical, devicectl_timing)) = std::thread::scope(|scope| {
+        let devicectl = scope.spawn(|| timed("devicectl", run_devicectl));
+        let simctl = timed("simctl", run_simctl);
+        let devicectl = devicectl.join().unwrap_or_else(|_| {
+            let error = "devicectl listing panicked".to_string();
+            let timing = DeviceSourceTiming {
+                source: "devicectl".to_string(),
+                duration_ms: 0,
+                device_count: 0,
+                error: Some(error.clone()),
+            };
+            (SourceOutcome::Unparseable(error), timing)
+        });
+        (simctl, devicectl)
+    });
+    let timings = vec![simctl_timing, devicectl_timing];
+
+    let mut devices = Vec::new();
+    for outcome in [simulators, physical] {
+        match outcome {
+            SourceOutcome::Devices(found) => devices.extend(found),
+            SourceOutcome::Failed(_) => {}
+            SourceOutcome::Unparseable(_) => return (None, timings),
+        }
+    }
+    sort_devices(&mut devices);
+    (Some(result_from(devices, timings.clone())), timings)
+}
+
+/// The listing from nocur-swift's `device list`
+fn list_via_nocur_swift() -> Result<DeviceListResult, String> {
+    let output = match crate::paths::resolve_nocur_swift_binary() {
+        Some(binary) => Command::new(binary).args(["device", "list"]).output(),
+        None => Command::new("swift")
+            .args(["run", "nocur-swift", "device", "list"])
+            .current_dir(Path::new(env!("CARGO_MANIFEST_DIR")).parent().unwrap_or(Path::new(".")).join("nocur-swift"))
+            .output(),
+    }
+    .map_err(|e| format!("Failed to run nocur-swift: {}", e))?;
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(format!("nocur-swift device list failed: {}", stderr));
+    }
+
+    let json: serde_json::Value = serde_json::from_slice(&output.stdout)
+        .map_err(|e| format!("Failed to parse device list: {}", e))?;
+    let data = json.get("data").ok_or("Missing data field in response")?;
+    serde_json::from_value(data.clone()).map_err(|e| format!("Failed to parse device list data: {}", e))
+}
+
+/// Every iOS simulator and physical device, with how long each source took.
+/// Physical devices listed natively already carry pairing and Developer Mode.
+pub fn list_devices() -> Result<DeviceListResult, String> {
+    let (native, mut timings) = list_native();
+    if let Some(result) = native {
+        return Ok(result);
+    }
+    log::warn!("Couldn't parse simctl/devicectl device listing, falling back to nocur-swift: {:?}", timings);
+
+    let started = Instant::now();
+    let mut result = list_via_nocur_swift()?;
+    timings.push(DeviceSourceTiming {
+        source: "nocur-swift".to_string(),
+        duration_ms: started.elapsed().as_millis() as u64,
+        device_count: result.devices.len(),
+        error: None,
+    });
+    result.timings = timings;
+    Ok(result)
+}
+
+// ============ Arranging ============
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DeviceGrouping {
+    /// By iOS version: a simulator's runtime, a device's installed iOS
+    Runtime,
+    /// Simulators, then physical devices
+    Type,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DeviceGroup {
+    /// "iOS 18.0", or "simulator" / "physical"
+    pub key: String,
+    pub devices: Vec<DeviceInfo>,
+    pub simulator_count: i32,
+    pub physical_count: i32,
+}
+
+/// Numeric parts of "18.0.1", for newest-first ordering
+fn version_parts(version: &str) -> Vec<u32> {
+    version.split('.').map(|part| part.parse().unwrap_or(0)).collect()
+}
+
+fn group(devices: &[DeviceInfo], grouping: DeviceGrouping) -> Vec<DeviceGroup> {
+    let key = |device: &DeviceInfo| match grouping {
+        DeviceGrouping::Runtime => format!("iOS {}", device.os_version),
+        DeviceGrouping::Type => match device.device_type {
+            DeviceType::Simulator => "simulator".to_string(),
+            DeviceType::Physical => "physical".to_string(),
+        },
+    };
+    let mut groups: Vec<DeviceGroup> = Vec::new();
+    for device in devices {
+        let key = key(device);
+        let index = match groups.iter().position(|g| g.key == key) {
+            Some(index) => index,
+            None => {
+                groups.push(DeviceGroup { key, devices: Vec::new(), simulator_count: 0, physical_count: 0 });
+                groups.len() - 1
+            }
+        };
+        let group = &mut groups[index];
+        match device.device_type {
+            DeviceType::Simulator => group.simulator_count += 1,
+            DeviceType::Physical => group.physical_count += 1,
+        }
+        group.devices.push(device.clone());
+    }
+    match grouping {
+        DeviceGrouping::Runtime => groups.sort_by(|a, b| {
+            let version = |g: &DeviceGroup| version_parts(g.key.trim_start_matches("iOS "));
+            version(b).cmp(&version(a))
+        }),
+        DeviceGrouping::Type => groups.sort_by_key(|g| g.key != "simulator"),
+    }
+    groups
+}
+
+/// `result` as the picker asked for it: without unavailable simulators unless
+/// `include_unavailable`, devices in `last_used` (ID -> unix seconds) first,
+/// most recent first, and grouped when `grouping` is given. The counts are
+/// of the devices left in the list; each group has its own.
+pub fn arrange(
+    mut result: DeviceListResult,
+    include_unavailable: bool,
+    grouping: Option<DeviceGrouping>,
+    last_used: Option<&HashMap<String, u64>>,
+) -> DeviceListResult {
+    if !include_unavailable {
+        result.devices.retain(|d| d.device_type == DeviceType::Physical || d.is_available);
+    }
+    if let Some(last_used) = last_used {
+        // Stable, so the rest keep the active-first order
+        result.devices.sort_by_key(|d| std::cmp::Reverse(last_used.get(&d.id).copied().unwrap_or(0)));
+    }
+    let counted = result_from(result.devices, result.timings);
+    DeviceListResult { groups: grouping.map(|grouping| group(&counted.devices, grouping)), ..counted }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    type Summary = (String, Option<String>, String, String, String, DeviceState, bool, Option<String>, Option<bool>);
+
+    fn summarize(mut devices: Vec<DeviceInfo>, sort: bool) -> Vec<Summary> {
+        // simctl's runtimes come out of a map
+        if sort {
+            devices.sort_by(|a, b| a.id.cmp(&b.id));
+        }
+        devices
+            .into_iter()
+            .map(|d| {
+                (d.id, d.core_device_id, d.name, d.model, d.os_version, d.state, d.is_available, d.pairing_state, d.developer_mode_enabled)
+            })
+            .collect()
+    }
+
+    fn simulator(id: &str, name: &str, os: &str, state: DeviceState, available: bool) -> Summary {
+        (id.into(), None, name.into(), name.into(), os.into(), state, available, None, None)
+    }
+
+    /// `ids` is the UDID and the CoreDevice ID
+    fn physical(
+        [id, core_id]: [&str; 2],
+        name: &str,
+        model: &str,
+        os: &str,
+        state: DeviceState,
+        pairing: &str,
+        developer_mode: Option<bool>,
+    ) -> Summary {
+        let available = state == DeviceState::Connected;
+        (id.into(), Some(core_id.into()), name.into(), model.into(), os.into(), state, available, Some(pairing.into()), developer_mode)
+    }
+
+    #[test]
+    fn parses_simctl_from_xcode_15() {
+        let devices = parse_simctl(include_str!("../tests/fixtures/devices/simctl_xcode15.json")).unwrap();
+        assert!(devices.iter().all(|d| d.device_type == DeviceType::Simulator));
+        assert_eq!(
+            summarize(devices, true),
+            vec![
+                simulator("6D4A1C2B-3E5F-4A6B-8C7D-9E0F1A2B3C4D", "iPhone 15", "17.0", DeviceState::Booted, true),
+                simulator("A1B2C3D4-E5F6-4789-9ABC-DEF012345678", "iPad Pro (11-inch) (4th generation)", "17.0", DeviceState::Shutdown, true),
+                // Its runtime is gone
+                simulator("F0E1D2C3-B4A5-4968-8776-655443322110", "iPhone 14", "16.4", DeviceState::Unavailable, false),
+            ]
+        );
+    }
+
+    #[test]
+    fn parses_simctl_from_xcode_16() {
+        let devices = parse_simctl(include_str!("../tests/fixtures/devices/simctl_xcode16.json")).unwrap();
+        assert_eq!(
+            summarize(devices, true),
+            vec![
+                simulator("2B7E5F10-94C3-4D2A-B8E6-1F0A9C3D5E72", "iPhone 16 Pro", "18.0", DeviceState::Shutdown, true),
+                simulator("93D0C1AE-6F2B-4E8C-A7D5-0B1C2D3E4F50", "iPhone SE (3rd generation)", "18.0", DeviceState::Booted, true),
+            ]
+        );
+    }
+
+    #[test]
+    fn parses_devicectl_from_xcode_15() {
+        let devices = parse_devicectl(include_str!("../tests/fixtures/devices/devicectl_xcode15.json")).unwrap();
+        assert!(devices.iter().all(|d| d.device_type == DeviceType::Physical));
+        assert_eq!(
+            summarize(devices, false),
+            vec![
+                physical(
+                    ["00008120-001A2B3C4D5E801E", "8F1C2A4B-3D5E-4F60-9A7B-1C2D3E4F5A6B"],
+                    "Dev iPhone",
+                    "iPhone 14 Pro",
+                    "17.0.3",
+                    DeviceState::Connected,
+                    "paired",
+                    Some(true),
+                ),
+                // Paired, but with no transport it isn't reachable
+                physical(
+                    ["00008103-000E14C21E38001E", "5A6B7C8D-9E0F-4A1B-8C2D-3E4F5A6B7C8D"],
+                    "Test iPad",
+                    "iPad Pro (11-inch) (3rd generation)",
+                    "16.6",
+                    DeviceState::Disconnected,
+                    "paired",
+                    None,
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn parses_devicectl_from_xcode_16() {
+        let devices = parse_devicectl(include_str!("../tests/fixtures/devices/devicectl_xcode16.json")).unwrap();
+        assert_eq!(
+            summarize(devices, false),
+            vec![
+                physical(
+                    ["00008140-0011223344556677", "1E2D3C4B-5A69-4788-9766-554433221100"],
+                    "Work iPhone",
+                    "iPhone 16 Pro",
+                    "18.0",
+                    DeviceState::Connected,
+                    "paired",
+                    Some(true),
+                ),
+                physical(
+                    ["00008110-000A1B2C3D4E5F60", "6F5E4D3C-2B1A-4098-8776-A5B4C3D2E1F0"],
+                    "Old iPhone",
+                    "iPhone 13",
+                    "17.6.1",
+                    DeviceState::Disconnected,
+                    "paired",
+                    Some(false),
+                ),
+                // No UDID anywhere, so the CoreDevice ID stands in
+                physical(
+                    ["0A9B8C7D-6E5F-4A3B-9C2D-1E0F9A8B7C6D", "0A9B8C7D-6E5F-4A3B-9C2D-1E0F9A8B7C6D"],
+                    "Borrowed iPhone",
+                    "iPhone 15",
+                    "18.0",
+                    DeviceState::Unavailable,
+                    "unpaired",
+                    None,
+                ),
+            ]
+        );
+    }
+}
@@ -0,0 +1,410 @@
+//! Pre-install checks for physical devices
+//!
+//! Installs to a device fail late (after a full build and a devicectl round-trip)
+//! and with opaque errors when the bundle ID is unusable, the free-provisioning app
+//! limit is hit, or the profile doesn't cover the device. These checks catch the
+//! common cases up front from the built app's embedded profile, the local profile
+//! store and the device's installed-apps listing.
+//!
+//! Device readiness (trusted pairing and Developer Mode) is checked separately,
+//! before a build, since neither can be fixed by rebuilding.
+
+use plist::Value;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+use crate::messages::{msg, Message};
+
+/// Free (personal team) accounts can have this many developer apps on a device
+const FREE_ACCOUNT_APP_LIMIT: usize = 3;
+/// Free provisioning profiles are issued for 7 days; paid ones for a year
+const FREE_PROFILE_MAX_LIFETIME: Duration = Duration::from_secs(8 * 24 * 60 * 60);
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PreflightFinding {
+    /// "blocking" or "warning"
+    pub severity: String,
+    pub code: String,
+    pub message: String,
+    /// Catalog key and arguments `message` was rendered from
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub message_key: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub message_args: Option<BTreeMap<String, String>>,
+}
+
+impl PreflightFinding {
+    fn new(severity: &str, code: &str, message: Message) -> Self {
+        Self {
+            severity: severity.to_string(),
+            code: code.to_string(),
+            message_key: message.key(),
+            message_args: message.args(),
+            message: message.text,
+        }
+    }
+
+    pub(crate) fn blocking(code: &str, message: Message) -> Self {
+        Self::new("blocking", code, message)
+    }
+
+    pub(crate) fn warning(code: &str, message: Message) -> Self {
+        Self::new("warning", code, message)
+    }
+
+    pub fn to_message(&self) -> Message {
+        Message {
+            key: self.message_key.clone(),
+            args: self.message_args.clone().unwrap_or_default(),
+            text: self.message.clone(),
+        }
+    }
+
+    pub fn is_blocking(&self) -> bool {
+        self.severity == "blocking"
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ProvisioningProfile {
+    pub uuid: String,
+    pub name: String,
+    pub team_id: Option<String>,
+    pub team_name: Option<String>,
+    /// `application-identifier` entitlement, e.g. `ABCDE12345.com.example.*`
+    pub app_id: Option<String>,
+    pub expiration: Option<SystemTime>,
+    pub creation: Option<SystemTime>,
+    pub provisioned_devices: Vec<String>,
+    pub provisions_all_devices: bool,
+    pub path: String,
+}
+
+impl ProvisioningProfile {
+    fn is_free(&self) -> bool {
+        match (self.creation, self.expiration) {
+            (Some(created), Some(expires)) => expires
+                .duration_since(created)
+                .map(|lifetime| lifetime <= FREE_PROFILE_MAX_LIFETIME)
+                .unwrap_or(false),
+            _ => false,
+        }
+    }
+
+    pub(crate) fn is_expired(&self) -> bool {
+        self.expiration.map(|e| e < SystemTime::now()).unwrap_or(false)
+    }
+
+    pub(crate) fn covers_device(&self, udid: &str) -> bool {
+        self.provisions_all_devices
+            || self.provisioned_devices.iter().any(|d| d.eq_ignore_ascii_case(udid))
+    }
+
+    pub(crate) fn matches_bundle_id(&self, bundle_id: &str) -> bool {
+        let Some(app_id) = self.app_id.as_deref() else {
+            return false;
+        };
+        // Strip the team prefix
+        let pattern = app_id.split_once('.').map(|(_, rest)| rest).unwrap_or(app_id);
+        match pattern.strip_suffix('*') {
+            Some(prefix) => bundle_id.starts_with(prefix),
+            None => pattern == bundle_id,
+        }
+    }
+}
+
+/// Extract the plist payload from a CMS-signed .mobileprovision
+fn parse_profile(path: &Path) -> Option<ProvisioningProfile> {
+    let data = std::fs::read(path).ok()?;
+    let start = data.windows(5).position(|w| w == b"<?xml")?;
+    let end_marker = b"</plist>";
+    let end = data.windows(end_marker.len()).rposition(|w| w == end_marker)? + end_marker.len();
+    let dict = Value::from_reader_xml(&data[start..end]).ok()?.into_dictionary()?;
+
+    let string = |key: &str| dict.get(key).and_then(|v| v.as_string()).map(String::from);
+    let date = |key: &str| dict.get(key).and_then(|v| v.as_date()).map(SystemTime::from);
+    let entitlements = dict.get("Entitlements").and_then(|v| v.as_dictionary());
+
+    Some(ProvisioningProfile {
+        uuid: string("UUID")?,
+        name: string("Name").unwrap_or_default(),
+        team_id: dict
+            .get("TeamIdentifier")
+            .and_then(|v| v.as_array())
+            .and_then(|a| a.first())
+            .and_then(|v| v.as_string())
+            .map(String::from),
+        team_name: string("TeamName"),
+        app_id: entitlements
+            .and_then(|e| e.get("application-identifier"))
+            .and_then(|v| v.as_string())
+            .map(String::from),
+        expiration: date("ExpirationDate"),
+        creation: date("CreationDate"),
+        provisioned_devices: dict
+            .get("ProvisionedDevices")
+            .and_then(|v| v.as_array())
+            .map(|a| a.iter().filter_map(|v| v.as_string()).map(String::from).collect())
+            .unwrap_or_default(),
+        provisions_all_devices: dict
+            .get("ProvisionsAllDevices")
+            .and_then(|v| v.as_boolean())
+            .unwrap_or(false),
+        path: path.to_string_lossy().to_string(),
+    })
+}
+
+fn profile_dirs() -> Vec<PathBuf> {
+    let home = crate::paths::home_dir_or_cwd();
+    vec![
+        home.join("Library/MobileDevice/Provisioning Profiles"),
+        // Xcode 16+ keeps profiles here instead
+        home.join("Library/Developer/Xcode/UserData/Provisioning Profiles"),
+    ]
+}
+
+/// All provisioning profiles installed on this Mac
+pub fn list_provisioning_profiles() -> Vec<ProvisioningProfile> {
+    profile_dirs()
+        .iter()
+        .filter_map(|dir| std::fs::read_dir(dir).ok())
+        .flatten()
+        .filter_map(|e| e.ok())
+        .map(|e| e.path())
+        .filter(|p| p.extension().is_some_and(|ext| ext == "mobileprovision"))
+        .filter_map(|p| parse_profile(&p))
+        .collect()
+}
+
+/// An app as reported by `devicectl device info apps`
+pub(crate) struct InstalledApp {
+    pub bundle_id: String,
+    pub name: String,
+    pub built_by_developer: bool,
+    /// Path of the installed .app bundle
+    pub path: Option<String>,
+}
+
+pub(crate) fn list_installed_apps(devicectl_id: &str) -> Option<Vec<InstalledApp>> {
+    let result = crate::devicectl::result_value(&["device", "info", "apps", "--device", devicectl_id])?;
+    let apps = result.get("apps")?.as_array()?;
+    Some(
+        apps.iter()
+            .filter_map(|app| {
+                Some(InstalledApp {
+                    bundle_id: app.get("bundleIdentifier")?.as_str()?.to_string(),
+                    name: app.get("name").and_then(|n| n.as_str()).unwrap_or("").to_string(),
+                    built_by_developer: app
+                        .get("builtByDeveloper")
+                        .and_then(|b| b.as_bool())
+                        .unwrap_or(false),
+                    path: app
+                        .get("url")
+                        .and_then(|u| u.as_str())
+                        .map(|u| u.strip_prefix("file://").unwrap_or(u).trim_end_matches('/').to_string()),
+                })
+            })
+            .collect(),
+    )
+}
+
+fn is_valid_bundle_id(bundle_id: &str) -> bool {
+    Regex::new(r"^[A-Za-z0-9-]+(\.[A-Za-z0-9-]+)+$")
+        .map(|re| re.is_match(bundle_id))
+        .unwrap_or(false)
+}
+
+/// Run all install checks for a built app. `device_udid` is the xcodebuild UDID
+/// (what profiles list); `devicectl_id` is the CoreDevice identifier.
+pub fn preflight(app_path: &str, bundle_id: &str, device_udid: &str, devicectl_id: &str) -> Vec<PreflightFinding> {
+    let mut findings = Vec::new();
+
+    if !is_valid_bundle_id(bundle_id) {
+        findings.push(PreflightFinding::blocking(
+            "invalidBundleId",
+            msg!("preflight.invalid_bundle_id", bundle_id = bundle_id),
+        ));
+    }
+
+    // The profile embedded at signing time is what the device will check;
+    // fall back to the local store if the build didn't embed one
+    let profile = parse_profile(&Path::new(app_path).join("embedded.mobileprovision")).or_else(|| {
+        list_provisioning_profiles()
+            .into_iter()
+            .filter(|p| !p.is_expired() && p.matches_bundle_id(bundle_id) && p.covers_device(device_udid))
+            .max_by_key(|p| p.expiration)
+    });
+
+    match &profile {
+        None => findings.push(PreflightFinding::warning(
+            "noProfile",
+            msg!("preflight.no_profile", bundle_id = bundle_id),
+        )),
+        Some(profile) => {
+            if profile.is_expired() {
+                findings.push(PreflightFinding::blocking(
+                    "profileExpired",
+                    msg!("preflight.profile_expired", profile = profile.name),
+                ));
+            }
+            if !profile.covers_device(device_udid) {
+                findings.push(PreflightFinding::blocking(
+                    "deviceNotProvisioned",
+                    msg!("preflight.device_not_provisioned", udid = device_udid, profile = profile.name),
+                ));
+            }
+            if profile.app_id.is_some() && !profile.matches_bundle_id(bundle_id) {
+                findings.push(PreflightFinding::warning(
+                    "profileBundleMismatch",
+                    msg!("preflight.profile_bundle_mismatch", profile = profile.name, bundle_id = bundle_id),
+                ));
+            }
+        }
+    }
+
+    match list_installed_apps(devicectl_id) {
+        None => findings.push(PreflightFinding::warning(
+            "appsUnavailable",
+            msg!("preflight.apps_unavailable"),
+        )),
+        Some(apps) => {
+            let already_installed = apps.iter().find(|a| a.bundle_id == bundle_id);
+
+            // devicectl doesn't expose the signing team, so a same-ID app built by a
+            // developer is the best signal of a possible cross-team conflict
+            if let Some(existing) = already_installed.filter(|a| a.built_by_developer) {
+                findings.push(PreflightFinding::warning(
+                    "bundleIdInstalled",
+                    msg!("preflight.bundle_id_installed", name = existing.name, bundle_id = bundle_id),
+                ));
+            }
+
+            let is_free = profile.as_ref().map(|p| p.is_free()).unwrap_or(false);
+            let developer_apps = apps.iter().filter(|a| a.built_by_developer).count();
+            if is_free && already_installed.is_none() && developer_apps >= FREE_ACCOUNT_APP_LIMIT {
+                findings.push(PreflightFinding::blocking(
+                    "freeAccountLimit",
+                    msg!("preflight.free_account_limit", limit = FREE_ACCOUNT_APP_LIMIT, count = developer_apps),
+                ));
+            }
+        }
+    }
+
+    findings
+}
+
+// ============ Device Readiness ============
+
+/// Pairing and Developer Mode as devicectl reports them. Either is None when
+/// devicectl didn't say (older devices don't report Developer Mode at all).
+#[derive(Debug, Clone, Default)]
+pub struct DeviceStatus {
+    /// devicectl's `pairingState`, e.g. "paired" or "unpaired"
+    pub pairing_state: Option<String>,
+    pub developer_mode_enabled: Option<bool>,
+}
+
+/// Read the status out of a device object from `list devices` or `device info details`
+fn parse_device_status(device: &serde_json::Value) -> DeviceStatus {
+    DeviceStatus {
+        pairing_state: device
+            .pointer("/connectionProperties/pairingState")
+            .and_then(|p| p.as_str())
+            .map(String::from),
+        developer_mode_enabled: device
+            .pointer("/deviceProperties/developerModeStatus")
+            .and_then(|d| d.as_str())
+            .map(|status| status == "enabled"),
+    }
+}
+
+/// Status of every device devicectl knows about, keyed by both its CoreDevice
+/// identifier and its UDID
+pub fn list_device_statuses() -> HashMap<String, DeviceStatus> {
+    let mut statuses = HashMap::new();
+    let Some(result) = crate::devicectl::result_value(&["list", "devices"]) else {
+        return statuses;
+    };
+    for device in result.get("devices").and_then(|d| d.as_array()).into_iter().flatten() {
+        let status = parse_device_status(device);
+        if let Some(udid) = device.pointer("/hardwareProperties/udid").and_then(|u| u.as_str()) {
+            statuses.insert(udid.to_string(), status.clone());
+        }
+        if let Some(identifier) = device.get("identifier").and_then(|i| i.as_str()) {
+            statuses.insert(identifier.to_string(), status);
+        }
+    }
+    statuses
+}
+
+/// Status of one device from `device info details`, which is fresher than the
+/// listing; falls back to the listing when the device doesn't answer
+pub fn device_status(device_id: &str) -> Option<DeviceStatus> {
+    crate::devicectl::result_value(&["device", "info", "details", "--device", device_id])
+        .map(|details| parse_device_status(&details))
+        .filter(|status| status.pairing_state.is_some() || status.developer_mode_enabled.is_some())
+        .or_else(|| list_device_statuses().remove(device_id))
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DeviceReadiness {
+    pub device_id: String,
+    /// No blocking findings
+    pub ready: bool,
+    pub pairing_state: Option<String>,
+    pub developer_mode_enabled: Option<bool>,
+    pub findings: Vec<PreflightFinding>,
+}
+
+/// Whether a physical device can take a build at all. `device_id` may be the
+/// CoreDevice identifier or the UDID.
+pub fn device_readiness(device_id: &str) -> DeviceReadiness {
+    let status = device_status(device_id);
+    let mut findings = Vec::new();
+
+    match &status {
+        None => findings.push(PreflightFinding::blocking(
+            "deviceNotFound",
+            msg!("readiness.device_not_found"),
+        )),
+        Some(status) => {
+            match status.pairing_state.as_deref() {
+                Some("paired") => {}
+                Some(_) => findings.push(PreflightFinding::blocking(
+                    "notPaired",
+                    msg!("readiness.not_paired"),
+                )),
+                None => findings.push(PreflightFinding::warning(
+                    "pairingUnknown",
+                    msg!("readiness.pairing_unknown"),
+                )),
+            }
+            match status.developer_mode_enabled {
+                Some(true) => {}
+                Some(false) => findings.push(PreflightFinding::blocking(
+                    "developerModeOff",
+                    msg!("readiness.developer_mode_off"),
+                )),
+                None => findings.push(PreflightFinding::warning(
+                    "developerModeUnknown",
+                    msg!("readiness.developer_mode_unknown"),
+                )),
+            }
+        }
+    }
+
+    let status = status.unwrap_or_default();
+    DeviceReadiness {
+        device_id: device_id.to_string(),
+        ready: !findings.iter().any(|f| f.is_blocking()),
+        pairing_state: status.pairing_state,
+        developer_mode_enabled: status.developer_mode_enabled,
+        findings,
+    }
+}
@@ -0,0 +1,488 @@
+//! Running `xcrun devicectl` and reading what it reports
+//!
+//! devicectl's human-readable output changes between Xcode releases, so calls
+//! pass `--json-output <temp file>` and read the result with the structs here.
+//! A failure comes back as a `DevicectlError` with the CoreDevice error domain
+//! and code from the JSON, not just whatever was on stderr.
+//!
+//! Whether the installed devicectl can write JSON is checked once per run from
+//! `devicectl --version`. When it can't, calls go by the exit status and the
+//! process list falls back to scraping the text output.
+//!
+//! The shapes differ between Xcode 15 and 16: a launched process's executable
+//! is a URL string in one and a `{ "relative": ... }` object in the other, and
+//! 16 reports the PID at the top of a launch result as well as under `process`.
+
+use regex::Regex;
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+use serde_json::Value;
+use std::ffi::OsStr;
+use std::fmt;
+use std::process::{Command, ExitStatus};
+use std::sync::OnceLock;
+
+/// devicectl's user-facing failure, as parsed from its JSON or stderr
+#[derive(Debug, Clone, Default)]
+pub struct DevicectlError {
+    /// e.g. "com.apple.dt.CoreDeviceError"
+    pub domain: Option<String>,
+    pub code: Option<i64>,
+    pub message: String,
+    pub stderr: String,
+}
+
+impl DevicectlError {
+    fn spawn(e: std::io::Error) -> Self {
+        Self { message: format!("Failed to run devicectl: {}", e), ..Default::default() }
+    }
+
+    /// "CoreDeviceError 3002", when devicectl reported a code
+    pub fn code_label(&self) -> Option<String> {
+        let code = self.code?;
+        let domain = self.domain.as_deref().unwrap_or("devicectl");
+        Some(format!("{} {}", domain.rsplit('.').next().unwrap_or(domain), code))
+    }
+
+    /// Text worth matching against known failure causes
+    pub fn text(&self) -> String {
+        format!("{}\n{}", self.message, self.stderr)
+    }
+
+    /// A message for the user, with the error code when there is one
+    pub fn summary(&self) -> String {
+        let friendly = friendly_message(&self.text());
+        match self.code_label() {
+            Some(code) => format!("{} ({})", friendly, code),
+            None => friendly,
+        }
+    }
+}
+
+impl fmt::Display for DevicectlError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.code_label() {
+            Some(code) => write!(f, "{} ({})", self.message, code),
+            None => write!(f, "{}", self.message),
+        }
+    }
+}
+
+impl From<DevicectlError> for String {
+    fn from(error: DevicectlError) -> Self {
+        error.summary()
+    }
+}
+
+/// Map devicectl's error text to a user-friendly message
+pub fn friendly_message(text: &str) -> String {
+    // Common error patterns and their user-friendly messages
+    if text.contains("device is not connected") || text.contains("no device found") {
+        return "Device is not connected. Check USB cable or WiFi connection.".to_string();
+    }
+
+    if text.contains("tunnel") && text.contains("unavailable") {
+        return "Cannot establish connection to device. Try unplugging and reconnecting, or restarting the device.".to_string();
+    }
+
+    if text.contains("timed out") || text.contains("timeout") {
+        return "Operation timed out. The device may be busy or unresponsive.".to_string();
+    }
+
+    if text.contains("not paired") || text.contains("pairing") {
+        return "Device is not trusted. Connect via USB and tap 'Trust' on the device.".to_string();
+    }
+
+    if text.contains("code signing") || text.contains("provisioning") {
+        return "Code signing error. Check your provisioning profile and signing certificate.".to_string();
+    }
+
+    if text.contains("disk space") || text.contains("storage") {
+        return "Not enough storage on device. Free up space and try again.".to_string();
+    }
+
+    if text.contains("locked") {
+        return "Device is locked. Unlock the device and try again.".to_string();
+    }
+
+    // Return first line of error if no specific pattern matched
+    text.lines().map(str::trim).find(|l| !l.is_empty()).unwrap_or("Unknown error").to_string()
+}
+
+// ============ JSON shapes ============
+
+#[derive(Debug, Deserialize)]
+struct Envelope {
+    #[serde(default)]
+    result: Option<Value>,
+    #[serde(default)]
+    error: Option<ErrorInfo>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ErrorInfo {
+    code: Option<i64>,
+    domain: Option<String>,
+    #[serde(default, rename = "userInfo")]
+    user_info: serde_json::Map<String, Value>,
+}
+
+/// userInfo values are wrapped by type, e.g. `{ "string": "..." }`
+fn user_info_string(value: &Value) -> Option<String> {
+    match value {
+        Value::String(s) => Some(s.clone()),
+        Value::Object(map) => map.get("string").and_then(|v| v.as_str()).map(String::from),
+        _ => None,
+    }
+}
+
+impl ErrorInfo {
+    fn description(&self) -> Option<String> {
+        ["NSLocalizedDescription", "NSLocalizedFailureReason"]
+            .iter()
+            .filter_map(|key| self.user_info.get(*key).and_then(user_info_string))
+            .find(|s| !s.is_empty())
+    }
+}
+
+/// A file URL as devicectl writes it: a string in the process list, an object
+/// in Xcode 15's launch result
+fn url_path(value: &Value) -> Option<String> {
+    let url = match value {
+        Value::String(s) => s.as_str(),
+        Value::Object(map) => map.get("relative").and_then(|v| v.as_str())?,
+        _ => return None,
+    };
+    Some(url.strip_prefix("file://").unwrap_or(url).to_string())
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RunningProcess {
+    pub process_identifier: u32,
+    #[serde(default, alias = "executableURL")]
+    executable: Option<Value>,
+}
+
+impl RunningProcess {
+    /// Path of the process's executable on the device
+    pub fn executable_path(&self) -> Option<String> {
+        self.executable.as_ref().and_then(url_path)
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ProcessList {
+    #[serde(default)]
+    running_processes: Vec<RunningProcess>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct InstalledApplication {
+    #[serde(default, rename = "installationURL")]
+    installation_url: Option<Value>,
+}
+
+impl InstalledApplication {
+    /// Where the app landed on the device
+    pub fn installation_path(&self) -> Option<String> {
+        self.installation_url.as_ref().and_then(url_path)
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct InstallResult {
+    #[serde(default)]
+    installed_applications: Vec<InstalledApplication>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct LaunchResult {
+    process: Option<RunningProcess>,
+    process_identifier: Option<u32>,
+}
+
+// ============ Running devicectl ============
+
+/// devicectl versions are the CoreDevice build, e.g. "397.24"
+fn parse_version(output: &str) -> Option<(u32, u32)> {
+    let version = output.split_whitespace().next()?;
+    let mut parts = version.split('.').map(|p| p.parse::<u32>().ok());
+    Some((parts.next()??, parts.next().flatten().unwrap_or(0)))
+}
+
+/// Whether this devicectl takes `--json-output`, checked once per run.
+/// Every devicectl that can report its version has had the flag, so one that
+/// can't is treated as too old to trust with it.
+pub fn json_output_supported() -> bool {
+    static SUPPORTED: OnceLock<bool> = OnceLock::new();
+    *SUPPORTED.get_or_init(|| {
+        let version = Command::new("xcrun")
+            .args(["devicectl", "--version"])
+            .output()
+            .ok()
+            .filter(|o| o.status.success())
+            .and_then(|o| parse_version(&String::from_utf8_lossy(&o.stdout)));
+        log::info!("devicectl version {:?}, JSON output {}", version, if version.is_some() { "supported" } else { "unsupported" });
+        version.is_some()
+    })
+}
+
+/// What a successful devicectl call produced
+pub struct Output {
+    /// The `result` object, when JSON output is supported
+    pub result: Option<Value>,
+    pub stdout: String,
+}
+
+/// Run `xcrun devicectl <args>`, with `--json-output` when supported
+pub fn run<I, S>(args: I) -> Result<Output, DevicectlError>
+where
+    I: IntoIterator<Item = S>,
+    S: AsRef<OsStr>,
+{
+    let mut cmd = Command::new("xcrun");
+    cmd.arg("devicectl").args(args);
+
+    let json_output = json_output_supported();
+    let temp_file = std::env::temp_dir().join(format!("devicectl_{}.json", uuid::Uuid::new_v4()));
+    if json_output {
+        cmd.arg("--json-output").arg(&temp_file);
+    }
+
+    let output = cmd.output().map_err(DevicectlError::spawn);
+    let data = json_output.then(|| std::fs::read_to_string(&temp_file).ok()).flatten();
+    let _ = std::fs::remove_file(&temp_file);
+    let output = output?;
+
+    let stdout = String::from_utf8_lossy(&output.stdout).to_string();
+    let stderr = String::from_utf8_lossy(&output.stderr).to_string();
+    interpret(data, output.status, stdout, stderr)
+}
+
+/// A finished call's outcome from its JSON output, when there is any, and
+/// its exit status
+fn interpret(data: Option<String>, status: ExitStatus, stdout: String, stderr: String) -> Result<Output, DevicectlError> {
+    let envelope = data.and_then(|data| match serde_json::from_str::<Envelope>(&data) {
+        Ok(envelope) => Some(envelope),
+        Err(e) => {
+            log::warn!("Couldn't parse devicectl JSON output: {}", e);
+            None
+        }
+    });
+
+    let (result, error) = match envelope {
+        Some(envelope) => (envelope.result, envelope.error),
+        None => (None, None),
+    };
+    if !status.success() || error.is_some() {
+        let message = error
+            .as_ref()
+            .and_then(ErrorInfo::description)
+            .or_else(|| stderr.lines().map(str::trim).find(|l| !l.is_empty()).map(String::from))
+            .unwrap_or_else(|| format!("devicectl exited with {}", status));
+        return Err(DevicectlError {
+            domain: error.as_ref().and_then(|e| e.domain.clone()),
+            code: error.as_ref().and_then(|e| e.code),
+            message,
+            stderr,
+        });
+    }
+    Ok(Output { result, stdout })
+}
+
+/// `result` read as `T`. A shape we don't know is logged rather than failing
+/// a call devicectl says succeeded.
+fn parse_result<T: DeserializeOwned>(result: Option<Value>, what: &str) -> Option<T> {
+    match serde_json::from_value(result?) {
+        Ok(parsed) => Some(parsed),
+        Err(e) => {
+            log::warn!("Unexpected devicectl {} result: {}", what, e);
+            None
+        }
+    }
+}
+
+/// Run devicectl and return the parsed `result` object, or None on any failure
+pub fn result_value(args: &[&str]) -> Option<Value> {
+    if !json_output_supported() {
+        return None;
+    }
+    run(args).ok()?.result
+}
+
+/// Processes running on the device
+pub fn running_processes(device_id: &str) -> Result<Vec<RunningProcess>, DevicectlError> {
+    let output = run(["device", "info", "processes", "--device", device_id])?;
+    match parse_result::<ProcessList>(output.result, "process list") {
+        Some(list) => Ok(list.running_processes),
+        None => Ok(scrape_processes(&output.stdout)),
+    }
+}
+
+/// The process list from devicectl's text output, for when there's no JSON.
+/// Lines look like "58681   /private/var/containers/Bundle/Application/.../App.app/App".
+fn scrape_processes(stdout: &str) -> Vec<RunningProcess> {
+    let Ok(line_re) = Regex::new(r"^\s*(\d+)\s+(\S.*?)\s*$") else {
+        return Vec::new();
+    };
+    stdout
+        .lines()
+        .filter_map(|line| {
+            let captures = line_re.captures(line)?;
+            Some(RunningProcess {
+                process_identifier: captures[1].parse().ok()?,
+                executable: Some(Value::String(captures[2].to_string())),
+            })
+        })
+        .collect()
+}
+
+pub struct Installed {
+    /// Where the app landed, when devicectl said
+    pub installation_path: Option<String>,
+    pub stdout: String,
+}
+
+/// Install the app bundle at `app_path`
+pub fn install_app(device_id: &str, app_path: &str, timeout_secs: u64) -> Result<Installed, DevicectlError> {
+    let timeout = timeout_secs.to_string();
+    let output = run(["device", "install", "app", "--device", device_id, app_path, "--timeout", &timeout])?;
+    let installation_path = parse_result::<InstallResult>(output.result, "install")
+        .and_then(|r| r.installed_applications.into_iter().find_map(|app| app.installation_path()));
+    Ok(Installed { installation_path, stdout: output.stdout })
+}
+
+pub struct Launched {
+    /// The launched process, when devicectl said
+    pub pid: Option<u32>,
+    pub stdout: String,
+}
+
+/// Launch `bundle_id`; `extra_args` go before the bundle ID (e.g. environment)
+pub fn launch_app(device_id: &str, bundle_id: &str, extra_args: &[String], timeout_secs: u64) -> Result<Launched, DevicectlError> {
+    let timeout = timeout_secs.to_string();
+    let mut args: Vec<&str> = vec!["device", "process", "launch", "--device", device_id];
+    args.extend(extra_args.iter().map(String::as_str));
+    args.extend([bundle_id, "--timeout", &timeout]);
+
+    let output = run(&args)?;
+    let pid = parse_result::<LaunchResult>(output.result, "launch")
+        .and_then(|r| r.process.map(|p| p.process_identifier).or(r.process_identifier));
+    Ok(Launched { pid, stdout: output.stdout })
+}
+
+pub fn terminate_process(device_id: &str, pid: u32) -> Result<(), DevicectlError> {
+    let pid = pid.to_string();
+    run(["device", "process", "terminate", "--device", device_id, "--pid", &pid]).map(|_| ())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::os::unix::process::ExitStatusExt;
+
+    const SUCCESS: i32 = 0;
+    /// Exit code 1, as a wait status
+    const FAILURE: i32 = 1 << 8;
+
+    fn interpret_fixture(json: &str, status: i32, stderr: &str) -> Result<Output, DevicectlError> {
+        interpret(Some(json.to_string()), ExitStatus::from_raw(status), String::new(), stderr.to_string())
+    }
+
+    const APP_PATH: &str = "/private/var/containers/Bundle/Application/9A8B7C6D-5E4F-4A3B-8C2D-1E0F9A8B7C6D/App.app";
+
+    #[test]
+    fn reads_an_xcode_15_launch() {
+        let output = interpret_fixture(include_str!("../tests/fixtures/devicectl/launch_xcode15.json"), SUCCESS, "").unwrap();
+        let launch: LaunchResult = parse_result(output.result, "launch").unwrap();
+        assert_eq!(launch.process_identifier, None);
+        let process = launch.process.unwrap();
+        assert_eq!(process.process_identifier, 4242);
+        assert_eq!(
+            process.executable_path().as_deref(),
+            Some("/private/var/containers/Bundle/Application/0E3B5C1D-7A2F-4B9E-8D6C-5F4A3B2C1D0E/App.app/App")
+        );
+    }
+
+    #[test]
+    fn reads_an_xcode_16_launch() {
+        let output = interpret_fixture(include_str!("../tests/fixtures/devicectl/launch_xcode16.json"), SUCCESS, "").unwrap();
+        let launch: LaunchResult = parse_result(output.result, "launch").unwrap();
+        assert_eq!(launch.process_identifier, Some(5151));
+        let process = launch.process.unwrap();
+        assert_eq!(process.process_identifier, 5151);
+        assert_eq!(process.executable_path(), Some(format!("{}/App", APP_PATH)));
+    }
+
+    #[test]
+    fn reads_an_install() {
+        let output = interpret_fixture(include_str!("../tests/fixtures/devicectl/install.json"), SUCCESS, "").unwrap();
+        let install: InstallResult = parse_result(output.result, "install").unwrap();
+        let paths: Vec<_> = install.installed_applications.iter().map(|app| app.installation_path()).collect();
+        assert_eq!(paths, vec![Some(format!("{}/", APP_PATH))]);
+    }
+
+    #[test]
+    fn reads_the_process_list() {
+        let output = interpret_fixture(include_str!("../tests/fixtures/devicectl/processes.json"), SUCCESS, "").unwrap();
+        let list: ProcessList = parse_result(output.result, "process list").unwrap();
+        let processes: Vec<_> = list.running_processes.iter().map(|p| (p.process_identifier, p.executable_path())).collect();
+        assert_eq!(
+            processes,
+            vec![(1, Some("/sbin/launchd".to_string())), (88, None), (5151, Some(format!("{}/App", APP_PATH)))]
+        );
+    }
+
+    #[test]
+    fn scrapes_the_text_process_list() {
+        let stdout = "Running processes on Work iPhone:\nPID     Executable\n1       /sbin/launchd\n5151    /private/var/App Folder/App.app/App  \n";
+        let processes: Vec<_> = scrape_processes(stdout).iter().map(|p| (p.process_identifier, p.executable_path())).collect();
+        assert_eq!(
+            processes,
+            vec![(1, Some("/sbin/launchd".to_string())), (5151, Some("/private/var/App Folder/App.app/App".to_string()))]
+        );
+    }
+
+    #[test]
+    fn reports_the_error_domain_and_code() {
+        let stderr = "ERROR: Failed to install the app on the device. (com.apple.dt.CoreDeviceError error 3002.)\n";
+        let Err(error) = interpret_fixture(include_str!("../tests/fixtures/devicectl/install_error.json"), FAILURE, stderr) else {
+            panic!("expected an error");
+        };
+        assert_eq!(error.domain.as_deref(), Some("com.apple.dt.CoreDeviceError"));
+        assert_eq!(error.code, Some(3002));
+        assert_eq!(error.message, "Failed to install the app on the device.");
+        assert_eq!(error.code_label().as_deref(), Some("CoreDeviceError 3002"));
+        assert_eq!(error.summary(), "Failed to install the app on the device. (CoreDeviceError 3002)");
+    }
+
+    #[test]
+    fn falls_back_to_the_failure_reason() {
+        let Err(error) = interpret_fixture(include_str!("../tests/fixtures/devicectl/launch_error_locked.json"), FAILURE, "") else {
+            panic!("expected an error");
+        };
+        assert_eq!(error.code, Some(10002));
+        assert!(error.message.starts_with("The request was denied by service delegate"));
+        assert_eq!(error.summary(), "Device is locked. Unlock the device and try again. (CoreDeviceError 10002)");
+    }
+
+    #[test]
+    fn an_error_in_the_json_fails_a_zero_exit() {
+        let error = interpret_fixture(include_str!("../tests/fixtures/devicectl/install_error.json"), SUCCESS, "").err();
+        assert_eq!(error.and_then(|e| e.code), Some(3002));
+    }
+
+    #[test]
+    fn without_json_the_error_is_stderr() {
+        let stderr = "\nERROR: The specified device was not found. (com.apple.dt.CoreDeviceError error 1000.)\n";
+        let Err(error) = interpret(None, ExitStatus::from_raw(FAILURE), String::new(), stderr.to_string()) else {
+            panic!("expected an error");
+        };
+        assert_eq!((error.domain, error.code), (None, None));
+        assert_eq!(error.message, "ERROR: The specified device was not found. (com.apple.dt.CoreDeviceError error 1000.)");
+    }
+}
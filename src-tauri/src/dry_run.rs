@@ -0,0 +1,39 @@
+//! Dry runs of commands that delete or discard work
+//!
+//! `remove_worktree`, `cleanup_project_storage`, `restore_checkpoint`,
+//! `adopt_parallel_result` and `clean_project` take `dryRun`. Each works out what it's going to touch with one planning
+//! function, which a dry run reports instead of carrying out, so the preview
+//! can't drift from what the real run does. A dry run changes nothing, so it
+//! needs no confirmation or safe mode token.
+
+use serde::Serialize;
+use std::path::PathBuf;
+
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DryRunReport {
+    /// Files and directories that would be deleted
+    pub would_delete: Vec<PathBuf>,
+    /// Files whose contents would change or be lost, relative to the working directory
+    pub would_modify: Vec<String>,
+    /// Git branches that would be deleted
+    pub would_delete_branches: Vec<String>,
+    /// Size of `would_delete`, as far as sizing got in time
+    pub bytes_affected: u64,
+}
+
+impl DryRunReport {
+    /// A report deleting `paths`, sized
+    pub fn deleting(paths: Vec<PathBuf>) -> Self {
+        let bytes_affected = paths.iter().map(|path| crate::storage::path_size(path)).sum();
+        Self { would_delete: paths, would_modify: Vec::new(), would_delete_branches: Vec::new(), bytes_affected }
+    }
+}
+
+/// A destructive command's result, or what it would have done
+#[derive(Debug, Clone, Serialize)]
+#[serde(untagged)]
+pub enum Outcome<T> {
+    Applied(T),
+    DryRun(DryRunReport),
+}
@@ -0,0 +1,328 @@
+//! Per-project launch environment profiles
+//!
+//! A profile is a dotenv file at `<project>/.nocur/env/<name>.env`. `run_project`
+//! loads the one it's given and passes its variables to the launched app: for
+//! the simulator as `SIMCTL_CHILD_`-prefixed variables on simctl, for devices
+//! through devicectl's `--environment-variables`. Variables passed explicitly
+//! win over the profile's.
+//!
+//! The parser takes the usual dotenv forms: `#` comments, blank lines, an
+//! optional `export ` prefix, and values that are bare (`#` after whitespace
+//! starts a comment), single-quoted (literal) or double-quoted (with `\n`,
+//! `\t`, `\"` and `\\` escapes, and able to span lines).
+
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+
+const ENV_DIR: &str = ".nocur/env";
+const EXTENSION: &str = "env";
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EnvProfile {
+    pub name: String,
+    pub path: String,
+    pub var_count: usize,
+}
+
+fn env_dir(project_path: &str) -> PathBuf {
+    Path::new(project_path).join(ENV_DIR)
+}
+
+/// Profile names become file names, so keep them to one safe path component
+fn validate_name(name: &str) -> Result<(), String> {
+    let valid = !name.is_empty()
+        && !name.starts_with('.')
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'));
+    if valid {
+        Ok(())
+    } else {
+        Err(format!("Invalid profile name '{}': use letters, digits, '-', '_' and '.'", name))
+    }
+}
+
+fn profile_path(project_path: &str, name: &str) -> Result<PathBuf, String> {
+    validate_name(name)?;
+    Ok(env_dir(project_path).join(format!("{}.{}", name, EXTENSION)))
+}
+
+fn is_valid_key(key: &str) -> bool {
+    let mut chars = key.chars();
+    chars.next().is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+/// Parse dotenv content into variables in file order; later duplicates win
+pub fn parse_env(content: &str) -> Result<Vec<(String, String)>, String> {
+    let mut vars: Vec<(String, String)> = Vec::new();
+    let mut lines = content.lines().enumerate();
+
+    while let Some((index, raw)) = lines.next() {
+        let line = raw.trim_start();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let line = line.strip_prefix("export ").map(str::trim_start).unwrap_or(line);
+        let (key, rest) = line
+            .split_once('=')
+            .ok_or_else(|| format!("Line {}: expected KEY=value", index + 1))?;
+        let key = key.trim();
+        if !is_valid_key(key) {
+            return Err(format!("Line {}: invalid variable name '{}'", index + 1, key));
+        }
+        let rest = rest.trim_start();
+
+        let value = match rest.chars().next() {
+            Some('\'') => {
+                let body = &rest[1..];
+                let end = body
+                    .find('\'')
+                    .ok_or_else(|| format!("Line {}: unterminated single quote", index + 1))?;
+                body[..end].to_string()
+            }
+            Some('"') => {
+                // Double-quoted values may continue onto following lines
+                let mut body = rest[1..].to_string();
+                loop {
+                    if let Some(value) = unescape_double_quoted(&body) {
+                        break value;
+                    }
+                    match lines.next() {
+                        Some((_, next)) => {
+                            body.push('\n');
+                            body.push_str(next);
+                        }
+                        None => return Err(format!("Line {}: unterminated double quote", index + 1)),
+                    }
+                }
+            }
+            _ => {
+                let end = rest
+                    .char_indices()
+                    .find(|&(i, c)| c == '#' && rest[..i].ends_with(char::is_whitespace))
+                    .map_or(rest.len(), |(i, _)| i);
+                rest[..end].trim_end().to_string()
+            }
+        };
+
+        vars.retain(|(k, _)| k != key);
+        vars.push((key.to_string(), value));
+    }
+    Ok(vars)
+}
+
+/// The value up to the closing quote with escapes applied, or None if `body`
+/// has no closing quote yet
+fn unescape_double_quoted(body: &str) -> Option<String> {
+    let mut value = String::new();
+    let mut chars = body.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' => return Some(value),
+            '\\' => match chars.next()? {
+                'n' => value.push('\n'),
+                't' => value.push('\t'),
+                'r' => value.push('\r'),
+                other @ ('"' | '\\' | '$') => value.push(other),
+                other => {
+                    value.push('\\');
+                    value.push(other);
+                }
+            },
+            _ => value.push(c),
+        }
+    }
+    None
+}
+
+/// Write `value` so `parse_env` reads it back unchanged
+fn format_value(value: &str) -> String {
+    let bare = !value.is_empty()
+        && value
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.' | '/' | ':' | ',' | '@' | '+'));
+    if bare {
+        return value.to_string();
+    }
+    let mut quoted = String::from("\"");
+    for c in value.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            '\t' => quoted.push_str("\\t"),
+            '\r' => quoted.push_str("\\r"),
+            _ => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+/// Profiles under the project's `.nocur/env`, sorted by name
+pub fn list_profiles(project_path: &str) -> Result<Vec<EnvProfile>, String> {
+    let dir = env_dir(project_path);
+    let Ok(entries) = std::fs::read_dir(&dir) else {
+        return Ok(Vec::new());
+    };
+    let mut profiles: Vec<EnvProfile> = entries
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == EXTENSION))
+        .filter_map(|path| {
+            let name = path.file_stem()?.to_string_lossy().to_string();
+            // A profile that doesn't parse is still listed so it can be fixed
+            let var_count = std::fs::read_to_string(&path)
+                .ok()
+                .and_then(|content| parse_env(&content).ok())
+                .map_or(0, |vars| vars.len());
+            Some(EnvProfile { name, path: path.to_string_lossy().to_string(), var_count })
+        })
+        .collect();
+    profiles.sort_by(|a, b| a.name.cmp(&b.name));
+    Ok(profiles)
+}
+
+pub fn read_profile(project_path: &str, name: &str) -> Result<BTreeMap<String, String>, String> {
+    let path = profile_path(project_path, name)?;
+    let content = std::fs::read_to_string(&path)
+        .map_err(|e| format!("Failed to read env profile '{}': {}", name, e))?;
+    let vars = parse_env(&content).map_err(|e| format!("Invalid env profile '{}': {}", name, e))?;
+    Ok(vars.into_iter().collect())
+}
+
+/// Replace the profile's contents with `vars`. Comments in an existing file
+/// aren't kept.
+pub fn save_profile(project_path: &str, name: &str, vars: &BTreeMap<String, String>) -> Result<EnvProfile, String> {
+    let path = profile_path(project_path, name)?;
+    if let Some(key) = vars.keys().find(|k| !is_valid_key(k)) {
+        return Err(format!("Invalid variable name '{}'", key));
+    }
+    let dir = env_dir(project_path);
+    std::fs::create_dir_all(&dir).map_err(|e| format!("Failed to create {}: {}", dir.display(), e))?;
+
+    let content: String = vars
+        .iter()
+        .map(|(key, value)| format!("{}={}\n", key, format_value(value)))
+        .collect();
+    std::fs::write(&path, content).map_err(|e| format!("Failed to write {}: {}", path.display(), e))?;
+
+    Ok(EnvProfile {
+        name: name.to_string(),
+        path: path.to_string_lossy().to_string(),
+        var_count: vars.len(),
+    })
+}
+
+/// The environment to launch with: the profile's variables, overridden by `explicit`
+pub fn launch_environment(
+    project_path: Option<&str>,
+    profile: Option<&str>,
+    explicit: Option<&HashMap<String, String>>,
+) -> Result<BTreeMap<String, String>, String> {
+    let mut env = match (project_path, profile) {
+        (Some(project_path), Some(name)) => read_profile(project_path, name)?,
+        (None, Some(name)) => return Err(format!("Env profile '{}' needs a project path", name)),
+        _ => BTreeMap::new(),
+    };
+    if let Some(explicit) = explicit {
+        env.extend(explicit.iter().map(|(k, v)| (k.clone(), v.clone())));
+    }
+    Ok(env)
+}
+
+/// simctl passes `SIMCTL_CHILD_FOO` to the launched app as `FOO`
+pub fn simctl_child_env(env: &BTreeMap<String, String>) -> Vec<(String, String)> {
+    env.iter().map(|(k, v)| (format!("SIMCTL_CHILD_{}", k), v.clone())).collect()
+}
+
+/// The `--environment-variables` argument for `devicectl device process launch`
+pub fn devicectl_env_args(env: &BTreeMap<String, String>) -> Vec<String> {
+    if env.is_empty() {
+        return Vec::new();
+    }
+    vec!["--environment-variables".to_string(), serde_json::to_string(env).unwrap_or_default()]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parsed(content: &str) -> Vec<(String, String)> {
+        parse_env(content).unwrap()
+    }
+
+    fn var(key: &str, value: &str) -> (String, String) {
+        (key.to_string(), value.to_string())
+    }
+
+    #[test]
+    fn parses_dotenv_forms() {
+        let content = r#"
+# A comment
+  # An indented one
+export API_URL=https://example.com/v1
+PLAIN = value with spaces  
+SINGLE='literal \n $HOME "quoted"'
+DOUBLE="tab\there \"quoted\" back\\slash \$HOME"
+INLINE=value # a comment
+HASH=color#fff
+"#;
+        assert_eq!(
+            parsed(content),
+            vec![
+                var("API_URL", "https://example.com/v1"),
+                var("PLAIN", "value with spaces"),
+                var("SINGLE", r#"literal \n $HOME "quoted""#),
+                var("DOUBLE", "tab\there \"quoted\" back\\slash $HOME"),
+                var("INLINE", "value"),
+                var("HASH", "color#fff"),
+            ]
+        );
+    }
+
+    #[test]
+    fn double_quoted_values_span_lines() {
+        let content = "CERT=\"-----BEGIN-----\nline two\n-----END-----\"\nNEXT=1\n";
+        assert_eq!(
+            parsed(content),
+            vec![var("CERT", "-----BEGIN-----\nline two\n-----END-----"), var("NEXT", "1")]
+        );
+    }
+
+    #[test]
+    fn later_definitions_win() {
+        assert_eq!(parsed("A=1\nB=2\nA=3\n"), vec![var("B", "2"), var("A", "3")]);
+    }
+
+    #[test]
+    fn rejects_malformed_lines() {
+        assert!(parse_env("NO_EQUALS\n").unwrap_err().starts_with("Line 1"));
+        assert!(parse_env("A=1\n1BAD=2\n").unwrap_err().starts_with("Line 2"));
+        assert!(parse_env("A='open\n").is_err());
+        assert!(parse_env("A=\"open\nstill open\n").is_err());
+    }
+
+    #[test]
+    fn formatted_values_parse_back() {
+        let values = [
+            "plain",
+            "https://example.com/path?q=1&r=2",
+            "with spaces",
+            "",
+            "quote \" and backslash \\",
+            "multi\nline\r\nvalue",
+            "tab\there",
+            "# not a comment",
+            "trailing #comment",
+            "$HOME and ${PATH}",
+            "'single'",
+            "ünïcödé ✓",
+        ];
+        for value in values {
+            let content = format!("KEY={}\n", format_value(value));
+            assert_eq!(parsed(&content), vec![var("KEY", value)], "{:?}", content);
+        }
+    }
+}
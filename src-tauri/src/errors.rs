@@ -0,0 +1,65 @@
+use serde::Serialize;
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MissingTool {
+    pub tool: String,
+    /// e.g. "brew install tuist"
+    pub install_command: String,
+}
+
+/// Error for commands whose callers need to branch on the kind of failure.
+/// Serializes as `{ "kind": "unsupported", "message": "..." }`.
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "kind", content = "message", rename_all = "camelCase")]
+pub enum CommandError {
+    /// The operation can't be performed on this target (e.g. a physical device)
+    Unsupported(String),
+    /// The requested model isn't known or isn't included in the user's plan
+    ModelUnavailable(String),
+    /// Needs the network and there's no connection; retry once back online
+    Offline(String),
+    /// Another run of the project holds its run lock; `message` is the
+    /// `RunInProgress` object rather than a string
+    RunInProgress(crate::run_lock::RunInProgress),
+    /// A command-line tool the operation needs isn't installed; `message` is
+    /// the `MissingTool` object
+    MissingTool(MissingTool),
+    /// Anything else; carries the same message a `String` error would
+    Failed(String),
+}
+
+impl std::fmt::Display for CommandError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CommandError::Unsupported(msg)
+            | CommandError::ModelUnavailable(msg)
+            | CommandError::Offline(msg)
+            | CommandError::Failed(msg) => write!(f, "{}", msg),
+            CommandError::RunInProgress(run) => write!(
+                f,
+                "A run of {} is already in progress (run {}, started {}s ago)",
+                run.project_path,
+                run.run_id,
+                run.elapsed_ms / 1000
+            ),
+            CommandError::MissingTool(missing) => write!(
+                f,
+                "{} isn't installed; install it with `{}`",
+                missing.tool, missing.install_command
+            ),
+        }
+    }
+}
+
+impl From<String> for CommandError {
+    fn from(message: String) -> Self {
+        CommandError::Failed(message)
+    }
+}
+
+impl From<&str> for CommandError {
+    fn from(message: &str) -> Self {
+        CommandError::Failed(message.to_string())
+    }
+}
@@ -0,0 +1,227 @@
+//! Batching of a session's `claude-event` stream before it reaches the webview
+//!
+//! A tool that dumps a large file can make the service stream hundreds of
+//! events a second, and the webview IPC can't keep up. The stdout reader hands
+//! its events to an emitter thread that merges runs of assistant text deltas,
+//! and of `tool_result` chunks for the same tool, until the batch is 50 ms old
+//! or holds 64 KB. Any other event flushes the batch and goes out at once, so
+//! `result`, `error` and `interrupted` are never held back.
+//!
+//! Content over the session's cap is cut at a character boundary. The event
+//! then carries `truncatedBytes` and an `eventId`, and `full_content` reads
+//! the whole text back from a spool file in the temp directory.
+
+use serde::Serialize;
+use std::path::PathBuf;
+use std::sync::mpsc::{self, RecvTimeoutError, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+use tauri::AppHandle;
+use uuid::Uuid;
+
+use crate::claude::{emit_claude_event, truncate_to_char_boundary, ClaudeEvent};
+
+/// Oldest a batch gets before it's emitted
+const FLUSH_INTERVAL: Duration = Duration::from_millis(50);
+
+/// Content size at which a batch is emitted without waiting
+const FLUSH_BYTES: usize = 64 * 1024;
+
+/// Largest `content` emitted when the preference isn't set
+pub const DEFAULT_MAX_CONTENT_BYTES: usize = 256 * 1024;
+
+/// Per-session counters, exposed through `get_event_coalescing_stats`
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CoalesceStats {
+    /// Events the service sent
+    pub events_received: u64,
+    /// Events emitted to the frontend
+    pub events_emitted: u64,
+    /// Received events folded into an earlier one
+    pub events_merged: u64,
+    /// Most received events carried by one emitted event
+    pub largest_batch: u64,
+    pub flushes_on_size: u64,
+    pub flushes_on_time: u64,
+    pub events_truncated: u64,
+    /// Content cut from truncated events, recoverable from the spool
+    pub bytes_truncated: u64,
+}
+
+struct Batch {
+    event: ClaudeEvent,
+    started: Instant,
+    count: u64,
+}
+
+/// Whether `event` is a chunk of a stream that can be merged into others
+fn is_mergeable(event: &ClaudeEvent) -> bool {
+    match event.event_type.as_str() {
+        "assistant" => event.tool_name.is_none(),
+        "tool_result" => true,
+        _ => false,
+    }
+}
+
+fn same_stream(a: &ClaudeEvent, b: &ClaudeEvent) -> bool {
+    a.event_type == b.event_type && a.tool_id == b.tool_id
+}
+
+struct Coalescer {
+    batch: Option<Batch>,
+    /// 0 leaves content uncapped
+    max_content_bytes: usize,
+    stats: Arc<Mutex<CoalesceStats>>,
+}
+
+impl Coalescer {
+    fn deadline(&self) -> Option<Instant> {
+        self.batch.as_ref().map(|b| b.started + FLUSH_INTERVAL)
+    }
+
+    /// Events ready to emit after taking in `event`, oldest first
+    fn push(&mut self, event: ClaudeEvent) -> Vec<ClaudeEvent> {
+        self.update_stats(|s| s.events_received += 1);
+        let mut out = Vec::new();
+
+        if let Some(batch) = self.batch.as_mut() {
+            if is_mergeable(&event) && same_stream(&batch.event, &event) {
+                batch.event.content.push_str(&event.content);
+                // The raw line only describes the first chunk
+                batch.event.raw_json = None;
+                batch.count += 1;
+                self.update_stats(|s| s.events_merged += 1);
+                if self.batch.as_ref().is_some_and(|b| b.event.content.len() >= FLUSH_BYTES) {
+                    self.update_stats(|s| s.flushes_on_size += 1);
+                    out.extend(self.flush());
+                }
+                return out;
+            }
+            out.extend(self.flush());
+        }
+
+        if is_mergeable(&event) && event.content.len() < FLUSH_BYTES {
+            self.batch = Some(Batch { event, started: Instant::now(), count: 1 });
+        } else {
+            out.push(self.finish(event, 1));
+        }
+        out
+    }
+
+    /// Emit the batch because it reached its deadline
+    fn flush_on_time(&mut self) -> Option<ClaudeEvent> {
+        if self.batch.is_some() {
+            self.update_stats(|s| s.flushes_on_time += 1);
+        }
+        self.flush()
+    }
+
+    fn flush(&mut self) -> Option<ClaudeEvent> {
+        let batch = self.batch.take()?;
+        Some(self.finish(batch.event, batch.count))
+    }
+
+    /// Apply the content cap and count the event as emitted
+    fn finish(&mut self, mut event: ClaudeEvent, count: u64) -> ClaudeEvent {
+        let cap = self.max_content_bytes;
+        if cap > 0 && event.content.len() > cap {
+            let kept = truncate_to_char_boundary(&event.content, cap).len();
+            let cut = event.content.len() - kept;
+            let event_id = Uuid::new_v4().to_string();
+            match spool(&event_id, &event.content) {
+                Ok(()) => event.event_id = Some(event_id),
+                Err(e) => log::warn!("Failed to spool truncated {} event: {}", event.event_type, e),
+            }
+            event.content.truncate(kept);
+            event.truncated_bytes = Some(cut);
+            event.raw_json = None;
+            self.update_stats(|s| {
+                s.events_truncated += 1;
+                s.bytes_truncated += cut as u64;
+            });
+        } else if cap > 0 && event.raw_json.as_ref().is_some_and(|raw| raw.len() > cap) {
+            event.raw_json = None;
+        }
+        self.update_stats(|s| {
+            s.events_emitted += 1;
+            s.largest_batch = s.largest_batch.max(count);
+        });
+        event
+    }
+
+    fn update_stats(&self, f: impl FnOnce(&mut CoalesceStats)) {
+        if let Ok(mut stats) = self.stats.lock() {
+            f(&mut stats);
+        }
+    }
+}
+
+/// Start the thread that coalesces and emits `session_id`'s events. It runs
+/// until the returned sender is dropped, then emits whatever it's still holding.
+pub fn spawn_emitter(
+    app_handle: AppHandle,
+    session_id: String,
+    max_content_bytes: usize,
+    stats: Arc<Mutex<CoalesceStats>>,
+) -> Sender<ClaudeEvent> {
+    let (tx, rx) = mpsc::channel::<ClaudeEvent>();
+    thread::spawn(move || {
+        let mut coalescer = Coalescer { batch: None, max_content_bytes, stats };
+        loop {
+            let received = match coalescer.deadline() {
+                Some(deadline) => rx.recv_timeout(deadline.saturating_duration_since(Instant::now())),
+                None => rx.recv().map_err(|_| RecvTimeoutError::Disconnected),
+            };
+            match received {
+                Ok(event) => {
+                    for event in coalescer.push(event) {
+                        emit_claude_event(&app_handle, &session_id, event);
+                    }
+                }
+                Err(RecvTimeoutError::Timeout) => {
+                    if let Some(event) = coalescer.flush_on_time() {
+                        emit_claude_event(&app_handle, &session_id, event);
+                    }
+                }
+                Err(RecvTimeoutError::Disconnected) => {
+                    if let Some(event) = coalescer.flush() {
+                        emit_claude_event(&app_handle, &session_id, event);
+                    }
+                    break;
+                }
+            }
+        }
+        log::info!("Claude event emitter finished");
+    });
+    tx
+}
+
+fn spool_dir() -> PathBuf {
+    std::env::temp_dir().join("nocur-event-spool")
+}
+
+fn spool(event_id: &str, content: &str) -> Result<(), String> {
+    let dir = spool_dir();
+    std::fs::create_dir_all(&dir).map_err(|e| format!("Failed to create {}: {}", dir.display(), e))?;
+    std::fs::write(dir.join(event_id), content).map_err(|e| format!("Failed to write spool file: {}", e))
+}
+
+/// The untruncated content of an event emitted with `truncatedBytes`
+pub fn full_content(event_id: &str) -> Result<String, String> {
+    // IDs are UUIDs, which also keeps them from naming anything outside the spool
+    Uuid::parse_str(event_id).map_err(|_| format!("Invalid event ID: {}", event_id))?;
+    std::fs::read_to_string(spool_dir().join(event_id))
+        .map_err(|e| format!("Full content for event {} isn't available: {}", event_id, e))
+}
+
+/// Remove spool files left by earlier runs
+pub fn clear_spool() {
+    let dir = spool_dir();
+    if dir.exists() {
+        if let Err(e) = std::fs::remove_dir_all(&dir) {
+            log::warn!("Failed to clear {}: {}", dir.display(), e);
+        }
+    }
+}
@@ -0,0 +1,278 @@
+//! Event stamping, and recent events per channel for catching up after a
+//! frontend reload
+//!
+//! Every event the backend emits carries an `EventStamp`: a `seq` from one
+//! counter shared by all events, the emit time in microseconds, the Claude
+//! session it belongs to and its source. Stamping and emitting happen under
+//! one lock, so events from build, log and Claude threads reach the frontend
+//! in `seq` order and a transcript can be rebuilt exactly. Journaled channels
+//! go out through `emit`, which also keeps the last few hundred; everything
+//! else goes through `emit_stamped`.
+//!
+//! Reloading the webview drops everything it had rendered, while the backend
+//! carries on. On mount the frontend subscribes, calls `replay_events`, and
+//! drops live events whose `seq` it already replayed.
+
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{SystemTime, UNIX_EPOCH};
+use tauri::{AppHandle, Emitter, Manager, Runtime};
+
+/// Next `seq`, shared by every event so their order is total
+static NEXT_SEQ: AtomicU64 = AtomicU64::new(1);
+/// Held from taking a `seq` until the event is emitted
+static EMIT_LOCK: Mutex<()> = Mutex::new(());
+
+/// Ordering and attribution for an emitted event, added to its payload as `stamp`
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EventStamp {
+    pub seq: u64,
+    pub emitted_at_micros: u64,
+    /// The Claude session the event came from; for other events, the session
+    /// that last emitted, so a build the agent started is attributed to it
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub session_id: Option<String>,
+    /// That session's badge, so the event can be shown in its colors
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub session_identity: Option<crate::session_identity::SessionIdentity>,
+    /// Name of the event it was emitted as
+    pub source: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Channel {
+    /// `claude-event`, keyed by session ID
+    Claude,
+    /// `build-event`, keyed by build ID
+    Build,
+    /// `simulator-log`, unkeyed
+    Log,
+}
+
+impl Channel {
+    fn event_name(self) -> &'static str {
+        match self {
+            Channel::Claude => "claude-event",
+            Channel::Build => "build-event",
+            Channel::Log => "simulator-log",
+        }
+    }
+
+    fn capacity(self) -> usize {
+        match self {
+            Channel::Claude => 500,
+            Channel::Build => 200,
+            Channel::Log => 500,
+        }
+    }
+}
+
+struct JournalEntry {
+    seq: u64,
+    key: Option<String>,
+    payload: serde_json::Value,
+}
+
+#[derive(Default)]
+struct ChannelLog {
+    entries: VecDeque<JournalEntry>,
+    /// `seq` of the newest entry dropped for space
+    dropped_through: u64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReplayResult {
+    /// Payloads as they were emitted, `seq` included, oldest first
+    pub events: Vec<serde_json::Value>,
+    /// Highest `seq` handed out so far on any channel
+    pub latest_seq: u64,
+    /// Events after `since_seq` were dropped to stay within the limit
+    pub truncated: bool,
+}
+
+pub struct EventJournal {
+    claude: Mutex<ChannelLog>,
+    build: Mutex<ChannelLog>,
+    log: Mutex<ChannelLog>,
+    /// ID of the build in progress (or last finished), assigned on "started"
+    build_id: AtomicU64,
+    /// Session of the latest `claude-event`
+    active_session: Mutex<Option<String>>,
+}
+
+impl EventJournal {
+    pub fn new() -> Self {
+        Self {
+            claude: Mutex::new(ChannelLog::default()),
+            build: Mutex::new(ChannelLog::default()),
+            log: Mutex::new(ChannelLog::default()),
+            build_id: AtomicU64::new(0),
+            active_session: Mutex::new(None),
+        }
+    }
+
+    fn channel(&self, channel: Channel) -> &Mutex<ChannelLog> {
+        match channel {
+            Channel::Claude => &self.claude,
+            Channel::Build => &self.build,
+            Channel::Log => &self.log,
+        }
+    }
+
+    /// Start a new build and return its ID
+    pub fn begin_build(&self) -> String {
+        (self.build_id.fetch_add(1, Ordering::SeqCst) + 1).to_string()
+    }
+
+    pub fn current_build(&self) -> String {
+        self.build_id.load(Ordering::SeqCst).to_string()
+    }
+
+    /// Stamp `payload`, emit it and keep it. Both happen under the channel's
+    /// lock, so a replay never sees an entry out of `seq` order.
+    fn record(&self, app_handle: &AppHandle, channel: Channel, key: Option<String>, payload: serde_json::Value) {
+        let session_id = if channel == Channel::Claude {
+            *self.active_session.lock() = key.clone();
+            key.clone()
+        } else {
+            self.active_session.lock().clone()
+        };
+        let mut log = self.channel(channel).lock();
+        let (seq, payload) = stamp_and_emit(app_handle, channel.event_name(), session_id, payload);
+        if log.entries.len() >= channel.capacity() {
+            if let Some(dropped) = log.entries.pop_front() {
+                log.dropped_through = dropped.seq;
+            }
+        }
+        log.entries.push_back(JournalEntry { seq, key, payload });
+    }
+
+    fn active_session(&self) -> Option<String> {
+        self.active_session.lock().clone()
+    }
+
+    /// Events on `channel` after `since_seq`, only those for `key` if given
+    pub fn replay(&self, channel: Channel, since_seq: u64, key: Option<&str>) -> ReplayResult {
+        let log = self.channel(channel).lock();
+        ReplayResult {
+            events: log
+                .entries
+                .iter()
+                .filter(|e| e.seq > since_seq)
+                .filter(|e| key.is_none() || e.key.as_deref() == key)
+                .map(|e| e.payload.clone())
+                .collect(),
+            latest_seq: NEXT_SEQ.load(Ordering::SeqCst) - 1,
+            truncated: since_seq < log.dropped_through,
+        }
+    }
+}
+
+/// Add the next stamp to `payload` and emit it as `event`; returns the `seq`
+/// and the stamped payload. A payload that isn't a JSON object has nowhere to
+/// put the stamp and goes out as it is.
+fn stamp_and_emit<R: Runtime>(
+    app_handle: &AppHandle<R>,
+    event: &str,
+    session_id: Option<String>,
+    mut payload: serde_json::Value,
+) -> (u64, serde_json::Value) {
+    let _guard = EMIT_LOCK.lock();
+    let seq = NEXT_SEQ.fetch_add(1, Ordering::SeqCst);
+    let stamp = EventStamp {
+        seq,
+        emitted_at_micros: SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_micros() as u64,
+        session_identity: session_id.as_deref().and_then(crate::session_identity::get),
+        session_id,
+        source: event.to_string(),
+    };
+    match (payload.as_object_mut(), serde_json::to_value(&stamp)) {
+        (Some(object), Ok(stamp)) => {
+            object.insert("stamp".to_string(), stamp);
+        }
+        _ => log::warn!("{} event isn't an object; emitting it unstamped", event),
+    }
+    let _ = app_handle.emit(event, &payload);
+    (seq, payload)
+}
+
+/// Journal `payload` and emit it on `channel`'s event
+pub fn emit<S: Serialize + Clone>(app_handle: &AppHandle, channel: Channel, key: Option<String>, payload: S) {
+    let Some(journal) = app_handle.try_state::<EventJournal>() else {
+        emit_stamped(app_handle, channel.event_name(), payload);
+        return;
+    };
+    match serde_json::to_value(&payload) {
+        Ok(value) => journal.record(app_handle, channel, key, value),
+        Err(e) => {
+            log::warn!("Failed to journal {} event: {}", channel.event_name(), e);
+            let _ = app_handle.emit(channel.event_name(), payload);
+        }
+    }
+}
+
+/// Stamp `payload` and emit it as `event`, without journaling it. Every event
+/// outside the journaled channels should go out through here.
+pub fn emit_stamped<S: Serialize + Clone>(app_handle: &AppHandle, event: &str, payload: S) {
+    let session_id = app_handle.try_state::<EventJournal>().and_then(|journal| journal.active_session());
+    match serde_json::to_value(&payload) {
+        Ok(value) => {
+            stamp_and_emit(app_handle, event, session_id, value);
+        }
+        Err(e) => {
+            log::warn!("Failed to stamp {} event: {}", event, e);
+            let _ = app_handle.emit(event, payload);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+    use tauri::Listener;
+
+    #[test]
+    fn seq_is_monotonic_across_threads() {
+        const THREADS: usize = 8;
+        const PER_THREAD: usize = 200;
+        let app = tauri::test::mock_app();
+        let handle = app.handle().clone();
+        let received = Arc::new(Mutex::new(Vec::new()));
+        let sink = received.clone();
+        handle.listen_any("journal-test", move |event| {
+            let payload: serde_json::Value = serde_json::from_str(event.payload()).unwrap();
+            sink.lock().push(payload["stamp"]["seq"].as_u64().unwrap());
+        });
+
+        let threads: Vec<_> = (0..THREADS)
+            .map(|_| {
+                let handle = handle.clone();
+                std::thread::spawn(move || {
+                    (0..PER_THREAD)
+                        .map(|i| stamp_and_emit(&handle, "journal-test", None, serde_json::json!({ "i": i })).0)
+                        .collect::<Vec<u64>>()
+                })
+            })
+            .collect();
+        let mut all = Vec::new();
+        for thread in threads {
+            let seqs = thread.join().unwrap();
+            assert!(seqs.windows(2).all(|w| w[0] < w[1]), "a thread's seqs went backwards");
+            all.extend(seqs);
+        }
+        all.sort_unstable();
+        all.dedup();
+        assert_eq!(all.len(), THREADS * PER_THREAD, "a seq was handed out twice");
+
+        // Emitted in the order the seqs were taken
+        let received = received.lock();
+        assert_eq!(received.len(), THREADS * PER_THREAD);
+        assert!(received.windows(2).all(|w| w[0] < w[1]), "events were emitted out of seq order");
+    }
+}
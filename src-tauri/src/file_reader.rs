@@ -0,0 +1,198 @@
+//! Reading project files a range of lines at a time
+//!
+//! Agents generate fixtures tens of megabytes long, and sending one whole over
+//! IPC freezes the viewer. `stats` tells the UI how big a file is (and whether
+//! it's binary) before it asks for content; `read` returns a line range, or
+//! the whole file only while it's under `MAX_UNRANGED_BYTES`.
+//!
+//! Range reads seek straight to the first line through an index of line start
+//! offsets, built on the first read and cached per path until the file's
+//! modification time changes.
+
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{BufReader, Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, OnceLock};
+use std::time::SystemTime;
+
+/// Larger files have to be read by range
+pub const MAX_UNRANGED_BYTES: u64 = 1024 * 1024;
+/// A file with a NUL byte in its first 8 KB is treated as binary
+const BINARY_SNIFF_BYTES: usize = 8 * 1024;
+const MAX_CACHED_INDEXES: usize = 32;
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FileStats {
+    pub path: String,
+    pub byte_size: u64,
+    /// None for binary files, which aren't indexed
+    pub line_count: Option<u64>,
+    pub is_binary: bool,
+}
+
+/// 1-based and inclusive; an `end_line` past the end stops at the last line
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LineRange {
+    pub start_line: u64,
+    pub end_line: u64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FileChunk {
+    pub content: String,
+    /// The lines actually returned; 0 and 0 for an empty file
+    pub start_line: u64,
+    pub end_line: u64,
+    pub total_lines: u64,
+    pub byte_size: u64,
+}
+
+/// Byte offset of the start of each line
+struct LineIndex {
+    starts: Vec<u64>,
+    byte_size: u64,
+}
+
+impl LineIndex {
+    fn build(path: &Path) -> Result<Self, String> {
+        let file = File::open(path).map_err(|e| format!("Failed to open {}: {}", path.display(), e))?;
+        let mut reader = BufReader::new(file);
+        let mut starts = Vec::new();
+        let mut buf = vec![0u8; 64 * 1024];
+        let mut offset = 0u64;
+        let mut at_line_start = true;
+        loop {
+            let n = reader.read(&mut buf).map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+            if n == 0 {
+                break;
+            }
+            for (i, byte) in buf[..n].iter().enumerate() {
+                if at_line_start {
+                    starts.push(offset + i as u64);
+                    at_line_start = false;
+                }
+                if *byte == b'\n' {
+                    at_line_start = true;
+                }
+            }
+            offset += n as u64;
+        }
+        Ok(Self { starts, byte_size: offset })
+    }
+
+    /// Byte span of lines `first..=last` (0-based)
+    fn span(&self, first: usize, last: usize) -> (u64, u64) {
+        let end = self.starts.get(last + 1).copied().unwrap_or(self.byte_size);
+        (self.starts[first], end)
+    }
+}
+
+type IndexCache = HashMap<PathBuf, (SystemTime, Arc<LineIndex>)>;
+
+fn index_cache() -> &'static Mutex<IndexCache> {
+    static CACHE: OnceLock<Mutex<IndexCache>> = OnceLock::new();
+    CACHE.get_or_init(Default::default)
+}
+
+fn line_index(path: &Path) -> Result<Arc<LineIndex>, String> {
+    let modified = std::fs::metadata(path)
+        .and_then(|m| m.modified())
+        .map_err(|e| format!("Failed to stat {}: {}", path.display(), e))?;
+    if let Some((_, index)) = index_cache().lock().get(path).filter(|(at, _)| *at == modified) {
+        return Ok(index.clone());
+    }
+
+    let index = Arc::new(LineIndex::build(path)?);
+    let mut cache = index_cache().lock();
+    if cache.len() >= MAX_CACHED_INDEXES && !cache.contains_key(path) {
+        if let Some(oldest) = cache.iter().min_by_key(|(_, (at, _))| *at).map(|(p, _)| p.clone()) {
+            cache.remove(&oldest);
+        }
+    }
+    cache.insert(path.to_path_buf(), (modified, index.clone()));
+    Ok(index)
+}
+
+/// `file_path` under `project_path`, refusing paths that lead outside it
+fn resolve(project_path: &str, file_path: &str) -> Result<PathBuf, String> {
+    let root = std::fs::canonicalize(project_path).map_err(|e| format!("Invalid project path {}: {}", project_path, e))?;
+    let path = std::fs::canonicalize(root.join(file_path)).map_err(|e| format!("Failed to open {}: {}", file_path, e))?;
+    if !path.starts_with(&root) {
+        return Err(format!("{} is outside the project", file_path));
+    }
+    Ok(path)
+}
+
+fn is_binary(path: &Path) -> Result<bool, String> {
+    let file = File::open(path).map_err(|e| format!("Failed to open {}: {}", path.display(), e))?;
+    let mut head = Vec::with_capacity(BINARY_SNIFF_BYTES);
+    file.take(BINARY_SNIFF_BYTES as u64)
+        .read_to_end(&mut head)
+        .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+    Ok(head.contains(&0))
+}
+
+pub fn stats(project_path: &str, file_path: &str) -> Result<FileStats, String> {
+    let path = resolve(project_path, file_path)?;
+    let byte_size = std::fs::metadata(&path)
+        .map_err(|e| format!("Failed to stat {}: {}", path.display(), e))?
+        .len();
+    let is_binary = is_binary(&path)?;
+    let line_count = match is_binary {
+        true => None,
+        false => Some(line_index(&path)?.starts.len() as u64),
+    };
+    Ok(FileStats { path: path.to_string_lossy().to_string(), byte_size, line_count, is_binary })
+}
+
+/// The lines in `range`, or the whole file when it's small enough
+pub fn read(project_path: &str, file_path: &str, range: Option<LineRange>) -> Result<FileChunk, String> {
+    let path = resolve(project_path, file_path)?;
+    if is_binary(&path)? {
+        return Err(format!("{} is a binary file", file_path));
+    }
+    let index = line_index(&path)?;
+    let total_lines = index.starts.len() as u64;
+
+    let (first, last) = match range {
+        Some(range) => {
+            if range.start_line == 0 || range.end_line < range.start_line {
+                return Err(format!("Invalid line range {}-{}", range.start_line, range.end_line));
+            }
+            (range.start_line - 1, range.end_line.min(total_lines).saturating_sub(1))
+        }
+        None if index.byte_size > MAX_UNRANGED_BYTES => {
+            return Err(format!(
+                "{} is {} bytes ({} lines); request a line range",
+                file_path, index.byte_size, total_lines
+            ));
+        }
+        None => (0, total_lines.saturating_sub(1)),
+    };
+    if total_lines == 0 || first >= total_lines {
+        return Ok(FileChunk { content: String::new(), start_line: 0, end_line: 0, total_lines, byte_size: index.byte_size });
+    }
+
+    let (start, end) = index.span(first as usize, last as usize);
+    let mut file = File::open(&path).map_err(|e| format!("Failed to open {}: {}", path.display(), e))?;
+    file.seek(SeekFrom::Start(start))
+        .map_err(|e| format!("Failed to seek in {}: {}", path.display(), e))?;
+    let mut bytes = Vec::with_capacity((end - start) as usize);
+    file.take(end - start)
+        .read_to_end(&mut bytes)
+        .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+
+    Ok(FileChunk {
+        content: String::from_utf8_lossy(&bytes).to_string(),
+        start_line: first + 1,
+        end_line: last + 1,
+        total_lines,
+        byte_size: index.byte_size,
+    })
+}
@@ -0,0 +1,93 @@
+//! Whether the simulator pane's live frames are being captured
+//!
+//! The pane's live view is a `simctl io screenshot` every half second, taken
+//! through `capture_stream_frame`. Nobody sees those frames while the window
+//! is minimized or hidden, or during a headless run, so the frontend turns
+//! streaming off with `set_streaming_enabled` and a headless `run_project` or
+//! `verify_change` holds it off while it runs. A frame asked for in the
+//! meantime is skipped instead of captured.
+//!
+//! The stats count both, with the wall time captures took. std can't read a
+//! child's CPU time, so the capture time (almost all of it simctl and
+//! encoding) stands in for it, and skipped frames times the average capture
+//! is the estimate of what was saved.
+
+use parking_lot::Mutex;
+use serde::Serialize;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::time::Duration;
+
+static ENABLED: AtomicBool = AtomicBool::new(true);
+/// Headless runs going on; frames are skipped while there's any
+static HEADLESS_RUNS: AtomicUsize = AtomicUsize::new(0);
+static STATS: Mutex<Counters> = Mutex::new(Counters { captured: 0, skipped: 0, capture_time: Duration::ZERO });
+
+struct Counters {
+    captured: u64,
+    skipped: u64,
+    capture_time: Duration,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StreamStats {
+    /// Set by `set_streaming_enabled`
+    pub enabled: bool,
+    pub headless_runs: usize,
+    pub frames_captured: u64,
+    pub frames_skipped: u64,
+    pub capture_ms_total: u64,
+    pub average_capture_ms: f64,
+    /// `frames_skipped` times `average_capture_ms`
+    pub estimated_saved_ms: u64,
+}
+
+pub fn set_enabled(enabled: bool) {
+    ENABLED.store(enabled, Ordering::SeqCst);
+}
+
+/// Whether a frame asked for now should be captured
+pub fn is_active() -> bool {
+    ENABLED.load(Ordering::SeqCst) && HEADLESS_RUNS.load(Ordering::SeqCst) == 0
+}
+
+pub fn record_captured(took: Duration) {
+    let mut stats = STATS.lock();
+    stats.captured += 1;
+    stats.capture_time += took;
+}
+
+pub fn record_skipped() {
+    STATS.lock().skipped += 1;
+}
+
+pub fn stats() -> StreamStats {
+    let stats = STATS.lock();
+    let capture_ms_total = stats.capture_time.as_millis() as u64;
+    let average_capture_ms = if stats.captured == 0 { 0.0 } else { capture_ms_total as f64 / stats.captured as f64 };
+    StreamStats {
+        enabled: ENABLED.load(Ordering::SeqCst),
+        headless_runs: HEADLESS_RUNS.load(Ordering::SeqCst),
+        frames_captured: stats.captured,
+        frames_skipped: stats.skipped,
+        capture_ms_total,
+        average_capture_ms,
+        estimated_saved_ms: (stats.skipped as f64 * average_capture_ms) as u64,
+    }
+}
+
+/// Keeps frames from being captured until dropped
+pub struct HeadlessRun(());
+
+impl HeadlessRun {
+    pub fn start() -> Self {
+        HEADLESS_RUNS.fetch_add(1, Ordering::SeqCst);
+        Self(())
+    }
+}
+
+impl Drop for HeadlessRun {
+    fn drop(&mut self) {
+        HEADLESS_RUNS.fetch_sub(1, Ordering::SeqCst);
+    }
+}
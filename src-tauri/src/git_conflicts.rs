@@ -0,0 +1,159 @@
+//! Merge conflict listing and per-file resolution
+//!
+//! Works on whatever left the index unmerged (a worktree merge, `git pull`, a
+//! cherry-pick), so files can be resolved one at a time instead of by hand.
+
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use std::process::Command;
+
+/// `git status --porcelain` codes for unmerged paths
+const UNMERGED_CODES: &[&str] = &["UU", "AA", "DD", "AU", "UA", "DU", "UD"];
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MergeConflict {
+    /// Relative to the path the listing was run in
+    pub file: String,
+    /// Porcelain status, e.g. "UU" (both modified) or "AA" (both added)
+    pub status: String,
+    /// `<<<<<<<` markers currently in the working copy
+    pub hunks: usize,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ConflictContent {
+    /// Common ancestor (stage 1); None if the file was added on both sides
+    pub base: Option<String>,
+    /// Our side (stage 2); None if we deleted it
+    pub ours: Option<String>,
+    /// Their side (stage 3); None if they deleted it
+    pub theirs: Option<String>,
+    /// The working copy with conflict markers
+    pub working: Option<String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ConflictResolution {
+    Ours,
+    Theirs,
+    /// Write this content and stage it
+    Content(String),
+}
+
+fn git(path: &str, args: &[&str]) -> Result<std::process::Output, String> {
+    Command::new("git")
+        .args(args)
+        .current_dir(path)
+        .output()
+        .map_err(|e| format!("Failed to run git {}: {}", args.first().unwrap_or(&""), e))
+}
+
+fn git_ok(path: &str, args: &[&str]) -> Result<(), String> {
+    let output = git(path, args)?;
+    if output.status.success() {
+        Ok(())
+    } else {
+        Err(format!(
+            "git {} failed: {}",
+            args.first().unwrap_or(&""),
+            String::from_utf8_lossy(&output.stderr).trim()
+        ))
+    }
+}
+
+fn count_conflict_hunks(path: &Path) -> usize {
+    std::fs::read_to_string(path)
+        .map(|content| content.lines().filter(|l| l.starts_with("<<<<<<<")).count())
+        .unwrap_or(0)
+}
+
+/// Every unmerged file under `path`
+pub fn list_merge_conflicts(path: &str) -> Result<Vec<MergeConflict>, String> {
+    // The `.` pathspec limits the listing to `path`, but porcelain paths are
+    // always relative to the repo root
+    let output = git(path, &["status", "--porcelain", "--untracked-files=no", "--", "."])?;
+    if !output.status.success() {
+        return Err(format!("git status failed: {}", String::from_utf8_lossy(&output.stderr).trim()));
+    }
+    let repo_root = git(path, &["rev-parse", "--show-toplevel"])
+        .ok()
+        .filter(|o| o.status.success())
+        .map(|o| String::from_utf8_lossy(&o.stdout).trim().to_string())
+        .unwrap_or_else(|| path.to_string());
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let conflicts = stdout
+        .lines()
+        .filter(|line| line.len() > 3 && UNMERGED_CODES.contains(&&line[..2]))
+        .map(|line| {
+            let repo_relative = &line[3..];
+            let absolute = Path::new(&repo_root).join(repo_relative);
+            let file = crate::paths::normalize_project_path(Path::new(path), &absolute.to_string_lossy())
+                .relative
+                .unwrap_or_else(|| repo_relative.to_string());
+            MergeConflict {
+                hunks: count_conflict_hunks(&absolute),
+                status: line[..2].to_string(),
+                file,
+            }
+        })
+        .collect();
+    Ok(conflicts)
+}
+
+/// One index stage of an unmerged file, or None when that side doesn't have it
+fn stage_content(path: &str, stage: u8, file: &str) -> Option<String> {
+    // `./` makes the path relative to `path` rather than the repo root
+    let spec = format!(":{}:./{}", stage, file);
+    let output = git(path, &["show", &spec]).ok()?;
+    output
+        .status
+        .success()
+        .then(|| String::from_utf8_lossy(&output.stdout).to_string())
+}
+
+pub fn get_conflict_content(path: &str, file: &str) -> Result<ConflictContent, String> {
+    let content = ConflictContent {
+        base: stage_content(path, 1, file),
+        ours: stage_content(path, 2, file),
+        theirs: stage_content(path, 3, file),
+        working: std::fs::read_to_string(Path::new(path).join(file)).ok(),
+    };
+    if content.base.is_none() && content.ours.is_none() && content.theirs.is_none() {
+        return Err(format!("{} has no merge conflict", file));
+    }
+    Ok(content)
+}
+
+/// Resolve one file and stage the result. Taking a side that deleted the file
+/// removes it.
+pub fn resolve_conflict(path: &str, file: &str, resolution: ConflictResolution) -> Result<(), String> {
+    let (stage, side_flag) = match resolution {
+        ConflictResolution::Ours => (2, "--ours"),
+        ConflictResolution::Theirs => (3, "--theirs"),
+        ConflictResolution::Content(content) => {
+            std::fs::write(Path::new(path).join(file), content)
+                .map_err(|e| format!("Failed to write {}: {}", file, e))?;
+            return git_ok(path, &["add", "--", file]);
+        }
+    };
+
+    if stage_content(path, stage, file).is_some() {
+        git_ok(path, &["checkout", side_flag, "--", file])?;
+        git_ok(path, &["add", "--", file])
+    } else {
+        git_ok(path, &["rm", "--quiet", "--", file])
+    }
+}
+
+/// Abandon the merge in progress and restore the pre-merge state
+pub fn abort_merge(path: &str) -> Result<(), String> {
+    let merging = git(path, &["rev-parse", "--verify", "--quiet", "MERGE_HEAD"])?.status.success();
+    if !merging {
+        return Err("No merge in progress".to_string());
+    }
+    git_ok(path, &["merge", "--abort"])
+}
@@ -0,0 +1,446 @@
+//! Changed-file listing for the diff panel
+//!
+//! Staged and unstaged changes are diffed separately (`--cached` against HEAD,
+//! then the working tree against the index) so a file edited after staging
+//! shows up on both sides with its own counts. Renames and copies are detected
+//! on both sides and keep the old path next to the new one. git's output is
+//! read NUL-separated (`-z`), so paths with tabs, quotes or non-ASCII bytes
+//! come through unquoted.
+//!
+//! A single file's diff can also come back parsed into hunks, with the file's
+//! language and (for Swift and JSON) token classes per line; see `syntax`.
+//! Line content stops after `DEFAULT_MAX_CONTENT_BYTES` (or the caller's
+//! limit) so a diff of a generated multi-megabyte file doesn't stall the IPC
+//! layer; hunks past it keep their header and are marked `content_omitted`.
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use crate::paths;
+use crate::syntax::{has_lexer, highlight_line, language_for_path, LexState, TokenRange};
+
+/// Line content a structured diff returns before omitting the rest
+pub const DEFAULT_MAX_CONTENT_BYTES: usize = 2 * 1024 * 1024;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GitChangedFile {
+    /// Relative to the project root (repo-relative if the file is outside it)
+    pub path: String,
+    pub absolute_path: String,
+    pub status: String, // "M" modified, "A" added, "D" deleted, "R" renamed, "C" copied, "?" untracked
+    pub additions: u32,
+    pub deletions: u32,
+    /// Source of a rename or copy, in the same form as `path`
+    #[serde(default)]
+    pub old_path: Option<String>,
+    /// Destination of a rename or copy; same as `path`
+    #[serde(default)]
+    pub new_path: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GitDiffTotals {
+    pub additions: u32,
+    pub deletions: u32,
+    /// Distinct paths across staged, unstaged and untracked
+    pub files: u32,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GitDiffStats {
+    pub staged: Vec<GitChangedFile>,
+    pub unstaged: Vec<GitChangedFile>,
+    /// Relative to the project root, like `GitChangedFile.path`
+    pub untracked: Vec<String>,
+    pub totals: GitDiffTotals,
+}
+
+/// The single-list shape the diff panel used before staged and unstaged were split
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LegacyGitDiffStats {
+    pub total_additions: u32,
+    pub total_deletions: u32,
+    pub files: Vec<GitChangedFile>,
+}
+
+/// One line of `--name-status` joined with its `--numstat` counts; paths are repo-relative
+#[derive(Debug, Clone)]
+struct DiffEntry {
+    status: String,
+    old_path: Option<String>,
+    path: String,
+    additions: u32,
+    deletions: u32,
+}
+
+fn git(path: &str, args: &[&str]) -> Result<String, String> {
+    let output = Command::new("git")
+        .args(args)
+        .current_dir(path)
+        .output()
+        .map_err(|e| format!("Failed to run git {}: {}", args.first().unwrap_or(&""), e))?;
+    if !output.status.success() {
+        return Err(format!(
+            "git {} failed: {}",
+            args.first().unwrap_or(&""),
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).to_string())
+}
+
+/// `--numstat -z` counts keyed by new path. A rename or copy leaves the path
+/// field empty and follows it with the old and new paths as fields of their
+/// own. Binary files (`-\t-\t`) count as 0.
+fn parse_numstat(output: &str) -> HashMap<String, (u32, u32)> {
+    let mut stats = HashMap::new();
+    let mut fields = output.split('\0');
+    while let Some(field) = fields.next() {
+        let mut parts = field.splitn(3, '\t');
+        let (Some(additions), Some(deletions), Some(path)) = (parts.next(), parts.next(), parts.next()) else {
+            continue;
+        };
+        let path = match path.is_empty() {
+            true => match (fields.next(), fields.next()) {
+                (Some(_old), Some(new)) => new,
+                _ => break,
+            },
+            false => path,
+        };
+        stats.insert(path.to_string(), (additions.parse().unwrap_or(0), deletions.parse().unwrap_or(0)));
+    }
+    stats
+}
+
+/// `--name-status -z` fields: a status (`M`, or `R087` / `C075` with the
+/// similarity after the letter), then its path, or the old and new paths for
+/// a rename or copy
+fn parse_name_status(output: &str, stats: &HashMap<String, (u32, u32)>) -> Vec<DiffEntry> {
+    let mut entries = Vec::new();
+    let mut fields = output.split('\0');
+    while let Some(code) = fields.next() {
+        let Some(status) = code.get(..1).filter(|s| !s.is_empty()) else {
+            continue;
+        };
+        let status = status.to_string();
+        let (old_path, path) = match (status.as_str(), fields.next()) {
+            ("R" | "C", Some(old)) => match fields.next() {
+                Some(new) => (Some(old.to_string()), new.to_string()),
+                None => break,
+            },
+            (_, Some(path)) => (None, path.to_string()),
+            (_, None) => break,
+        };
+        let (additions, deletions) = stats.get(&path).copied().unwrap_or((0, 0));
+        entries.push(DiffEntry { status, old_path, path, additions, deletions });
+    }
+    entries
+}
+
+/// Staged changes (index against HEAD) or unstaged ones (working tree against the index)
+fn diff_entries(working_dir: &str, staged: bool) -> Result<Vec<DiffEntry>, String> {
+    let side: &[&str] = if staged { &["--cached"] } else { &[] };
+    let run = |format: &str| {
+        let mut args = vec!["diff", format, "-z", "-C"];
+        args.extend_from_slice(side);
+        git(working_dir, &args)
+    };
+    let stats = parse_numstat(&run("--numstat")?);
+    Ok(parse_name_status(&run("--name-status")?, &stats))
+}
+
+fn parse_untracked(output: &str) -> Vec<String> {
+    output.split('\0').filter(|path| !path.is_empty()).map(String::from).collect()
+}
+
+fn untracked_paths(working_dir: &str) -> Result<Vec<String>, String> {
+    let output = git(working_dir, &["ls-files", "-z", "--others", "--exclude-standard", "--full-name"])?;
+    Ok(parse_untracked(&output))
+}
+
+fn repo_root(working_dir: &str) -> PathBuf {
+    git(working_dir, &["rev-parse", "--show-toplevel"])
+        .map(|out| PathBuf::from(out.trim()))
+        .unwrap_or_else(|_| PathBuf::from(working_dir))
+}
+
+/// Same normalization as build errors so the two can be joined by path
+fn project_path(working_dir: &str, repo_root: &Path, repo_relative: &str) -> paths::ProjectPath {
+    paths::normalize_project_path(Path::new(working_dir), &repo_root.join(repo_relative).to_string_lossy())
+}
+
+fn changed_file(working_dir: &str, repo_root: &Path, entry: DiffEntry) -> GitChangedFile {
+    let normalized = project_path(working_dir, repo_root, &entry.path);
+    let path = normalized.relative.unwrap_or(entry.path);
+    let old_path = entry
+        .old_path
+        .map(|old| project_path(working_dir, repo_root, &old).relative.unwrap_or(old));
+    GitChangedFile {
+        new_path: old_path.as_ref().map(|_| path.clone()),
+        path,
+        absolute_path: normalized.absolute,
+        status: entry.status,
+        additions: entry.additions,
+        deletions: entry.deletions,
+        old_path,
+    }
+}
+
+/// Staged, unstaged and untracked changes under `working_dir`'s repository
+pub fn diff_stats(working_dir: &str) -> Result<GitDiffStats, String> {
+    let root = repo_root(working_dir);
+    let staged: Vec<GitChangedFile> = diff_entries(working_dir, true)?
+        .into_iter()
+        .map(|e| changed_file(working_dir, &root, e))
+        .collect();
+    let unstaged: Vec<GitChangedFile> = diff_entries(working_dir, false)?
+        .into_iter()
+        .map(|e| changed_file(working_dir, &root, e))
+        .collect();
+    let untracked: Vec<String> = untracked_paths(working_dir)?
+        .into_iter()
+        .map(|p| project_path(working_dir, &root, &p).relative.unwrap_or(p))
+        .collect();
+
+    let mut paths: Vec<&str> = staged.iter().chain(&unstaged).map(|f| f.path.as_str()).collect();
+    paths.extend(untracked.iter().map(String::as_str));
+    paths.sort_unstable();
+    paths.dedup();
+
+    let totals = GitDiffTotals {
+        additions: staged.iter().chain(&unstaged).map(|f| f.additions).sum(),
+        deletions: staged.iter().chain(&unstaged).map(|f| f.deletions).sum(),
+        files: paths.len() as u32,
+    };
+    Ok(GitDiffStats { staged, unstaged, untracked, totals })
+}
+
+/// One entry per path with staged and unstaged counts added together, for
+/// callers that haven't moved to the split shape
+pub fn to_legacy(stats: GitDiffStats, working_dir: &str) -> LegacyGitDiffStats {
+    let mut files: Vec<GitChangedFile> = Vec::new();
+    for file in stats.staged.into_iter().chain(stats.unstaged) {
+        match files.iter_mut().find(|f| f.path == file.path) {
+            Some(existing) => {
+                existing.additions += file.additions;
+                existing.deletions += file.deletions;
+            }
+            None => files.push(file),
+        }
+    }
+    for path in stats.untracked {
+        files.push(GitChangedFile {
+            absolute_path: Path::new(working_dir).join(&path).to_string_lossy().to_string(),
+            path,
+            status: "??".to_string(),
+            additions: 0,
+            deletions: 0,
+            old_path: None,
+            new_path: None,
+        });
+    }
+    LegacyGitDiffStats {
+        total_additions: stats.totals.additions,
+        total_deletions: stats.totals.deletions,
+        files,
+    }
+}
+
+// ============ Structured file diff ============
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DiffLine {
+    /// "context", "add" or "delete"
+    pub kind: &'static str,
+    pub old_line: Option<u32>,
+    pub new_line: Option<u32>,
+    /// The line without its leading +/-/space
+    pub content: String,
+    /// Token classes, for languages with a lexer
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tokens: Option<Vec<TokenRange>>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DiffHunk {
+    /// The `@@ ... @@` line
+    pub header: String,
+    pub lines: Vec<DiffLine>,
+    /// The content limit was reached; `lines` is empty and the header is all there is
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub content_omitted: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FileDiff {
+    pub file_path: String,
+    /// From the extension, "text" when unknown
+    pub language: String,
+    pub hunks: Vec<DiffHunk>,
+    /// Hunks with `content_omitted`
+    pub omitted_hunks: u32,
+}
+
+/// Old and new start lines from a `@@ -a,b +c,d @@` header
+fn hunk_starts(header: &str) -> (u32, u32) {
+    let mut old_start = 0;
+    let mut new_start = 0;
+    for part in header.split_whitespace().skip(1).take(2) {
+        let number = |rest: &str| rest.split(',').next().and_then(|n| n.parse().ok()).unwrap_or(0);
+        if let Some(rest) = part.strip_prefix('-') {
+            old_start = number(rest);
+        } else if let Some(rest) = part.strip_prefix('+') {
+            new_start = number(rest);
+        }
+    }
+    (old_start, new_start)
+}
+
+/// Hunks of `git diff` output for one file. Deleted and added lines are lexed
+/// separately so a construct opened on one side doesn't run into the other.
+/// Once line content passes `max_content_bytes`, the hunk it's in and every
+/// later one are returned without lines.
+pub fn parse_file_diff(file_path: &str, diff: &str, max_content_bytes: usize) -> FileDiff {
+    let language = language_for_path(file_path);
+    let lexed = has_lexer(language);
+    let mut old_state = LexState::default();
+    let mut new_state = LexState::default();
+    let mut hunks: Vec<DiffHunk> = Vec::new();
+    let (mut old_line, mut new_line) = (0, 0);
+    let mut content_bytes = 0usize;
+    let mut exhausted = false;
+
+    for line in diff.lines() {
+        if line.starts_with("@@") {
+            (old_line, new_line) = hunk_starts(line);
+            hunks.push(DiffHunk { header: line.to_string(), lines: Vec::new(), content_omitted: exhausted });
+            continue;
+        }
+        // Lines before the first hunk are the diff/index/---/+++ header
+        let Some(hunk) = hunks.last_mut() else {
+            continue;
+        };
+        if hunk.content_omitted {
+            continue;
+        }
+        content_bytes += line.len();
+        if content_bytes > max_content_bytes {
+            exhausted = true;
+            hunk.content_omitted = true;
+            hunk.lines = Vec::new();
+            continue;
+        }
+        let (kind, content) = match line.chars().next() {
+            Some('+') => ("add", &line[1..]),
+            Some('-') => ("delete", &line[1..]),
+            Some(' ') => ("context", &line[1..]),
+            Some(_) => continue, // "\ No newline at end of file"
+            None => ("context", ""),
+        };
+        let tokens = match (lexed, kind) {
+            (false, _) => None,
+            (true, "add") => highlight_line(language, content, &mut new_state),
+            (true, "delete") => highlight_line(language, content, &mut old_state),
+            (true, _) => {
+                let _ = highlight_line(language, content, &mut old_state);
+                highlight_line(language, content, &mut new_state)
+            }
+        };
+        hunk.lines.push(DiffLine {
+            kind,
+            old_line: (kind != "add").then_some(old_line),
+            new_line: (kind != "delete").then_some(new_line),
+            content: content.to_string(),
+            tokens,
+        });
+        if kind != "add" {
+            old_line = old_line.saturating_add(1);
+        }
+        if kind != "delete" {
+            new_line = new_line.saturating_add(1);
+        }
+    }
+
+    let omitted_hunks = hunks.iter().filter(|h| h.content_omitted).count() as u32;
+    FileDiff { file_path: file_path.to_string(), language: language.to_string(), hunks, omitted_hunks }
+}
+
+/// `git diff HEAD` of one file, split into hunks with highlighting hints
+pub fn file_diff(working_dir: &str, file_path: &str, max_content_bytes: usize) -> Result<FileDiff, String> {
+    let output = Command::new("git")
+        .args(["diff", "HEAD", "--", file_path])
+        .current_dir(working_dir)
+        .output()
+        .map_err(|e| format!("Failed to get diff: {}", e))?;
+    Ok(parse_file_diff(file_path, &String::from_utf8_lossy(&output.stdout), max_content_bytes))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// (status, old path, path, additions, deletions)
+    fn entries(numstat: &str, name_status: &str) -> Vec<(String, Option<String>, String, u32, u32)> {
+        parse_name_status(name_status, &parse_numstat(numstat))
+            .into_iter()
+            .map(|e| (e.status, e.old_path, e.path, e.additions, e.deletions))
+            .collect()
+    }
+
+    #[test]
+    fn parses_staged_renames_and_copies() {
+        let staged = entries(
+            include_str!("../tests/fixtures/git_diff/staged_numstat.z"),
+            include_str!("../tests/fixtures/git_diff/staged_name_status.z"),
+        );
+        assert_eq!(
+            staged,
+            vec![
+                // Renamed across directories and edited
+                ("R".into(), Some("Sources/Models/User.swift".into()), "Sources/App/Account.swift".into(), 1, 1),
+                ("M".into(), None, "Sources/App/Config.swift".into(), 1, 1),
+                // Copied from the file as it was before the edit above
+                ("C".into(), Some("Sources/App/Config.swift".into()), "Sources/App/DebugConfig.swift".into(), 0, 0),
+                ("R".into(), Some("old\tnotes.txt".into()), "new\tnotes.txt".into(), 0, 0),
+            ]
+        );
+    }
+
+    #[test]
+    fn parses_unstaged_paths_with_tabs() {
+        let unstaged = entries(
+            include_str!("../tests/fixtures/git_diff/unstaged_numstat.z"),
+            include_str!("../tests/fixtures/git_diff/unstaged_name_status.z"),
+        );
+        assert_eq!(
+            unstaged,
+            vec![
+                ("M".into(), None, "Sources/App/ContentView.swift".into(), 1, 1),
+                ("M".into(), None, "new\tnotes.txt".into(), 1, 0),
+            ]
+        );
+        assert_eq!(parse_untracked(include_str!("../tests/fixtures/git_diff/untracked.z")), vec!["untracked\tfile.txt"]);
+    }
+
+    #[test]
+    fn binary_files_count_as_zero() {
+        let stats = parse_numstat("-\t-\tAssets/icon.png\0");
+        assert_eq!(stats.get("Assets/icon.png"), Some(&(0, 0)));
+    }
+
+    #[test]
+    fn truncated_output_is_dropped() {
+        assert!(parse_numstat("1\t1\t\0only/old.swift").is_empty());
+        assert!(parse_name_status("R090\0only/old.swift", &HashMap::new()).is_empty());
+        assert!(parse_name_status("", &HashMap::new()).is_empty());
+    }
+}
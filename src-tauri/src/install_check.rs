@@ -0,0 +1,101 @@
+//! Checking that the installed app has the bundle ID the launch will use
+//!
+//! `build_project` reads the bundle ID from the built Info.plist, which isn't
+//! always the one that gets installed (several app targets, per-configuration
+//! overrides). The launch then fails with "unknown bundle identifier" even
+//! though the install worked. So after installing, the simulator's or device's
+//! app list is checked for the ID, and if it's missing, the app installed from
+//! the same .app bundle gives the ID to launch instead.
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::io::Write;
+use std::path::Path;
+use std::process::{Command, Stdio};
+
+/// The bundle ID read from the build, and the one the app was installed under
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BundleIdCorrection {
+    pub expected: String,
+    pub installed: String,
+}
+
+/// What the installed app list says about `expected`
+pub enum InstallCheck {
+    /// Installed under the expected ID
+    Matches,
+    /// Installed under a different ID
+    Mismatch(String),
+    /// Neither the ID nor the bundle is in the list
+    NotInstalled,
+}
+
+/// "MyApp.app", for matching the built bundle against an installed one
+fn bundle_name(path: &str) -> Option<&str> {
+    Path::new(path.trim_end_matches('/')).file_name().and_then(|n| n.to_str())
+}
+
+/// Compare `(bundle id, installed path)` pairs against the built app
+fn check(installed: &[(String, Option<String>)], app_path: &str, expected: &str) -> InstallCheck {
+    if installed.iter().any(|(id, _)| id == expected) {
+        return InstallCheck::Matches;
+    }
+    let name = bundle_name(app_path);
+    installed
+        .iter()
+        .find(|(_, path)| name.is_some() && path.as_deref().and_then(bundle_name) == name)
+        .map_or(InstallCheck::NotInstalled, |(id, _)| InstallCheck::Mismatch(id.clone()))
+}
+
+/// `simctl listapps` prints an old-style plist, which plutil turns into JSON
+fn simulator_apps(udid: &str) -> Result<Vec<(String, Option<String>)>, String> {
+    let listed = Command::new("xcrun")
+        .args(["simctl", "listapps", udid])
+        .output()
+        .map_err(|e| format!("Failed to run simctl listapps: {}", e))?;
+    if !listed.status.success() {
+        return Err(format!("simctl listapps failed: {}", String::from_utf8_lossy(&listed.stderr).trim()));
+    }
+
+    let mut plutil = Command::new("plutil")
+        .args(["-convert", "json", "-o", "-", "-"])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| format!("Failed to run plutil: {}", e))?;
+    if let Some(mut stdin) = plutil.stdin.take() {
+        stdin
+            .write_all(&listed.stdout)
+            .map_err(|e| format!("Failed to write to plutil: {}", e))?;
+    }
+    let converted = plutil
+        .wait_with_output()
+        .map_err(|e| format!("Failed to read plutil output: {}", e))?;
+    if !converted.status.success() {
+        return Err(format!("plutil failed: {}", String::from_utf8_lossy(&converted.stderr).trim()));
+    }
+
+    let apps: serde_json::Map<String, Value> = serde_json::from_slice(&converted.stdout)
+        .map_err(|e| format!("Failed to parse simctl listapps output: {}", e))?;
+    Ok(apps
+        .into_iter()
+        .map(|(key, app)| {
+            let id = app.get("CFBundleIdentifier").and_then(|v| v.as_str()).map_or(key, String::from);
+            let path = app.get("Path").and_then(|v| v.as_str()).map(String::from);
+            (id, path)
+        })
+        .collect())
+}
+
+pub fn check_simulator(udid: &str, app_path: &str, expected: &str) -> Result<InstallCheck, String> {
+    Ok(check(&simulator_apps(udid)?, app_path, expected))
+}
+
+pub fn check_device(devicectl_id: &str, app_path: &str, expected: &str) -> Result<InstallCheck, String> {
+    let apps = crate::device_preflight::list_installed_apps(devicectl_id)
+        .ok_or("Couldn't list the apps installed on the device")?;
+    let installed: Vec<(String, Option<String>)> = apps.into_iter().map(|app| (app.bundle_id, app.path)).collect();
+    Ok(check(&installed, app_path, expected))
+}
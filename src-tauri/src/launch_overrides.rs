@@ -0,0 +1,173 @@
+//! Per-launch language, region and text size for the simulator
+//!
+//! These are app-level overrides, not device settings: they go to the app as
+//! launch arguments (`-AppleLanguages (ja)`, `-AppleLocale ja_JP`,
+//! `-UIPreferredContentSizeCategoryName ...`), which land in the app's
+//! argument domain of UserDefaults for that launch only. The simulator's own
+//! language and text size are left as they are, and so are other apps.
+//!
+//! Named sets are saved per project in `<project>/.nocur/launch_overrides.json`;
+//! a launch can start from one and override single fields. Launch arguments the
+//! user passes explicitly win over the generated ones with the same key.
+
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+const SAVED_FILE: &str = ".nocur/launch_overrides.json";
+
+const CONTENT_SIZE_PREFIX: &str = "UICTContentSizeCategory";
+const CONTENT_SIZES: &[&str] = &[
+    "XS", "S", "M", "L", "XL", "XXL", "XXXL",
+    "AccessibilityM", "AccessibilityL", "AccessibilityXL", "AccessibilityXXL", "AccessibilityXXXL",
+];
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct AccessibilityOptions {
+    /// "XS" through "XXXL" or "AccessibilityM" through "AccessibilityXXXL",
+    /// with or without the `UICTContentSizeCategory` prefix
+    pub content_size_category: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct LaunchOverrides {
+    /// A saved set to start from; the fields given here replace its values
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub preset: Option<String>,
+    /// Language, e.g. "ja" or "pt-BR"
+    pub locale: Option<String>,
+    /// Region code, e.g. "JP"; combined with the locale's language for `-AppleLocale`
+    pub region: Option<String>,
+    pub accessibility_options: AccessibilityOptions,
+    /// Passed to the app as-is, e.g. ["-FeatureFlag", "YES"]
+    pub launch_args: Vec<String>,
+}
+
+fn saved_path(project_path: &str) -> PathBuf {
+    Path::new(project_path).join(SAVED_FILE)
+}
+
+pub fn list_saved(project_path: &str) -> BTreeMap<String, LaunchOverrides> {
+    std::fs::read_to_string(saved_path(project_path))
+        .ok()
+        .and_then(|content| serde_json::from_str(&content).ok())
+        .unwrap_or_default()
+}
+
+fn write_saved(project_path: &str, saved: &BTreeMap<String, LaunchOverrides>) -> Result<(), String> {
+    let path = saved_path(project_path);
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent).map_err(|e| format!("Failed to create {}: {}", parent.display(), e))?;
+    }
+    let content = serde_json::to_string_pretty(saved).map_err(|e| format!("Failed to serialize launch overrides: {}", e))?;
+    std::fs::write(&path, content).map_err(|e| format!("Failed to write {}: {}", path.display(), e))
+}
+
+pub fn save(project_path: &str, name: &str, overrides: &LaunchOverrides) -> Result<(), String> {
+    if name.trim().is_empty() {
+        return Err("Launch override sets need a name".to_string());
+    }
+    // Checked now rather than at the next launch
+    launch_arguments(overrides)?;
+    let mut saved = list_saved(project_path);
+    saved.insert(name.to_string(), LaunchOverrides { preset: None, ..overrides.clone() });
+    write_saved(project_path, &saved)
+}
+
+pub fn delete(project_path: &str, name: &str) -> Result<(), String> {
+    let mut saved = list_saved(project_path);
+    if saved.remove(name).is_none() {
+        return Err(format!("No launch override set named '{}'", name));
+    }
+    write_saved(project_path, &saved)
+}
+
+/// `overrides` with its preset's values filled in where it has none
+pub fn resolve(project_path: Option<&str>, overrides: LaunchOverrides) -> Result<LaunchOverrides, String> {
+    let Some(name) = overrides.preset.clone() else {
+        return Ok(overrides);
+    };
+    let project_path = project_path.ok_or_else(|| format!("Launch override set '{}' needs a project path", name))?;
+    let base = list_saved(project_path)
+        .remove(&name)
+        .ok_or_else(|| format!("No launch override set named '{}'", name))?;
+    let mut launch_args = base.launch_args;
+    launch_args.extend(overrides.launch_args);
+    Ok(LaunchOverrides {
+        preset: None,
+        locale: overrides.locale.or(base.locale),
+        region: overrides.region.or(base.region),
+        accessibility_options: AccessibilityOptions {
+            content_size_category: overrides
+                .accessibility_options
+                .content_size_category
+                .or(base.accessibility_options.content_size_category),
+        },
+        launch_args,
+    })
+}
+
+fn is_language_id(value: &str) -> bool {
+    let mut parts = value.split(['-', '_']);
+    let language_ok = parts
+        .next()
+        .is_some_and(|l| (2..=3).contains(&l.len()) && l.chars().all(|c| c.is_ascii_alphabetic()));
+    language_ok && parts.all(|p| (2..=8).contains(&p.len()) && p.chars().all(|c| c.is_ascii_alphanumeric()))
+}
+
+fn content_size_category(value: &str) -> Result<String, String> {
+    let short = value.strip_prefix(CONTENT_SIZE_PREFIX).unwrap_or(value);
+    CONTENT_SIZES
+        .iter()
+        .find(|size| size.eq_ignore_ascii_case(short))
+        .map(|size| format!("{}{}", CONTENT_SIZE_PREFIX, size))
+        .ok_or_else(|| format!("Unknown content size category '{}'; expected one of {}", value, CONTENT_SIZES.join(", ")))
+}
+
+/// Keys the arguments set: each `-Key` followed by a value
+fn argument_keys(args: &[String]) -> Vec<&str> {
+    args.iter().filter(|arg| arg.starts_with('-') && arg.len() > 1).map(String::as_str).collect()
+}
+
+/// The launch arguments for `overrides`: the generated ones whose keys the
+/// explicit `launch_args` don't set, then the explicit ones
+pub fn launch_arguments(overrides: &LaunchOverrides) -> Result<Vec<String>, String> {
+    let mut generated: Vec<(&str, String)> = Vec::new();
+
+    let language = match overrides.locale.as_deref().map(str::trim).filter(|l| !l.is_empty()) {
+        Some(locale) if !is_language_id(locale) => return Err(format!("Invalid locale '{}'", locale)),
+        Some(locale) => Some(locale.replace('_', "-")),
+        None => None,
+    };
+    if let Some(language) = &language {
+        generated.push(("-AppleLanguages", format!("({})", language)));
+    }
+    let region = overrides.region.as_deref().map(str::trim).filter(|r| !r.is_empty());
+    if let Some(region) = region {
+        if !(2..=3).contains(&region.len()) || !region.chars().all(|c| c.is_ascii_alphanumeric()) {
+            return Err(format!("Invalid region '{}'", region));
+        }
+    }
+    // `-AppleLocale` wants language_REGION; the region comes from either field
+    let language_code = language.as_deref().and_then(|l| l.split('-').next()).unwrap_or("en");
+    let locale_region = region.map(str::to_uppercase).or_else(|| {
+        language.as_deref().and_then(|l| l.split('-').nth(1)).filter(|r| r.len() == 2).map(str::to_uppercase)
+    });
+    if let Some(locale_region) = locale_region {
+        generated.push(("-AppleLocale", format!("{}_{}", language_code, locale_region)));
+    }
+    if let Some(size) = &overrides.accessibility_options.content_size_category {
+        generated.push(("-UIPreferredContentSizeCategoryName", content_size_category(size)?));
+    }
+
+    let explicit = argument_keys(&overrides.launch_args);
+    let mut args: Vec<String> = generated
+        .into_iter()
+        .filter(|(key, _)| !explicit.contains(key))
+        .flat_map(|(key, value)| [key.to_string(), value])
+        .collect();
+    args.extend(overrides.launch_args.iter().cloned());
+    Ok(args)
+}
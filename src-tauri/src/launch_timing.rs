@@ -0,0 +1,99 @@
+//! How long a simulator launch takes
+//!
+//! There's no launch-finished signal from simctl, so the time is taken from
+//! the `simctl launch` call to the first line the app's process logs, which
+//! UIKit and the app's own startup produce as soon as it's up. A `log stream`
+//! filtered to the executable is started before the launch and stopped once
+//! that line arrives or `FIRST_LINE_TIMEOUT` passes.
+
+use std::io::{BufRead, BufReader};
+use std::process::{Child, Command, Stdio};
+use std::sync::mpsc::{self, Receiver};
+use std::time::{Duration, Instant};
+
+/// How long to wait for `log stream` to attach before launching anyway
+const ATTACH_TIMEOUT: Duration = Duration::from_secs(3);
+/// How long after the launch the first line may take before giving up
+const FIRST_LINE_TIMEOUT: Duration = Duration::from_secs(20);
+
+enum StreamLine {
+    /// `log stream`'s "Filtering the log data using ..." header
+    Attached,
+    Entry(Instant),
+}
+
+pub struct LaunchWatch {
+    child: Child,
+    lines: Receiver<StreamLine>,
+    launched_at: Option<Instant>,
+}
+
+impl LaunchWatch {
+    /// Start watching `executable_name` on the simulator; call before launching
+    pub fn start(sim_target: &str, executable_name: &str) -> Result<Self, String> {
+        let predicate = format!("process == {}", crate::predicate_literal(executable_name));
+        let mut child = Command::new("xcrun")
+            .args(["simctl", "spawn", sim_target, "log", "stream", "--style", "compact", "--level", "debug"])
+            .args(["--predicate", &predicate])
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()
+            .map_err(|e| format!("Failed to start log stream: {}", e))?;
+        let stdout = child.stdout.take().ok_or("Failed to capture log stream stdout")?;
+
+        let (sender, lines) = mpsc::channel();
+        std::thread::spawn(move || {
+            for line in BufReader::new(stdout).lines() {
+                let Ok(line) = line else {
+                    break;
+                };
+                let message = if line.starts_with("Filtering the log data") {
+                    StreamLine::Attached
+                } else if line.starts_with("Timestamp") || line.trim().is_empty() {
+                    continue;
+                } else {
+                    StreamLine::Entry(Instant::now())
+                };
+                if sender.send(message).is_err() {
+                    break;
+                }
+            }
+        });
+
+        let watch = Self { child, lines, launched_at: None };
+        if !matches!(watch.lines.recv_timeout(ATTACH_TIMEOUT), Ok(StreamLine::Attached)) {
+            log::warn!("Launch timing log stream didn't attach in time");
+        }
+        Ok(watch)
+    }
+
+    /// Mark the moment `simctl launch` is invoked
+    pub fn launching(&mut self) {
+        // Anything logged before the launch belongs to a previous run
+        while self.lines.try_recv().is_ok() {}
+        self.launched_at = Some(Instant::now());
+    }
+
+    /// Milliseconds from `launching` to the process's first log line, or None
+    /// if it didn't log within the timeout
+    pub fn finish(self) -> Option<u64> {
+        let launched_at = self.launched_at?;
+        let deadline = launched_at + FIRST_LINE_TIMEOUT;
+        let first_line = loop {
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            match self.lines.recv_timeout(remaining) {
+                Ok(StreamLine::Entry(at)) => break Some(at),
+                Ok(StreamLine::Attached) => continue,
+                Err(_) => break None,
+            }
+        };
+        first_line.map(|at| at.saturating_duration_since(launched_at).as_millis() as u64)
+    }
+}
+
+impl Drop for LaunchWatch {
+    fn drop(&mut self) {
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
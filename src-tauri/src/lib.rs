@@ -1,22 +1,96 @@
 use serde::{Deserialize, Serialize};
 use std::process::Command;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::io::{BufRead, BufReader};
-use std::time::{SystemTime, UNIX_EPOCH, Instant};
+use std::time::{Duration, SystemTime, UNIX_EPOCH, Instant};
 use std::process::Stdio;
-use tauri::{State, Emitter, Manager};
+use tauri::{State, Manager};
 use regex::Regex;
 use parking_lot::Mutex;
 
 mod ace;
+mod ace_curate;
+mod ace_reflect;
+mod agent_bridge;
+mod agent_profiles;
+mod app_defaults;
+mod archive;
+mod build_failure;
+mod build_history;
+mod build_logs;
+mod build_settings;
+mod builtin_skills;
+mod checkpoints;
 mod claude;
+mod claude_settings;
+mod claude_login;
+mod claude_md;
+mod claude_service;
+mod command_trace;
+mod config_bundle;
+mod context_budget;
+mod control_server;
+mod crash_loop;
+mod device_list;
+mod device_preflight;
+mod devicectl;
+mod dry_run;
+mod env_profiles;
+mod errors;
+mod event_coalescer;
+mod event_journal;
+mod file_reader;
+mod frame_stream;
+mod git_conflicts;
+mod git_diff;
+mod install_check;
+mod launch_overrides;
+mod launch_timing;
+mod log_filters;
 mod paths;
+mod prefetch;
 mod menu;
+mod messages;
+mod migrations;
+mod models;
+mod network;
+mod notifications;
+mod packages;
+mod parallel_sessions;
 mod permissions;
 mod project;
-
-use claude::{ClaudeSession, ClaudeState, ClaudeModel, ClaudeSessionConfig, SavedSession};
+mod redaction;
+mod run_lock;
+mod runtime_env;
+mod runtime_issues;
+mod runtimes;
+mod safe_mode;
+mod screenshot_presets;
+mod screenshots;
+mod schemes;
+mod session_identity;
+mod session_reports;
+mod signing;
+mod signing_health;
+mod simulator_input;
+mod storage;
+mod swift_package;
+mod symbols;
+mod syntax;
+mod terminal;
+mod test_results;
+mod tool_summary;
+mod tuist;
+mod ui_idle;
+mod verify;
+mod xcresult;
+
+use command_trace::traced;
+use messages::{msg, Message};
+use claude::{ClaudeSession, ClaudeState, ClaudeModel, ClaudeSessionConfig, SavedSession, StallRecovery};
+use errors::CommandError;
 use permissions::{PermissionState, PermissionResponse};
+use prefetch::PrefetchItem;
 use std::sync::Arc;
 
 fn nocur_swift_command(args: &[&str]) -> Command {
@@ -53,8 +127,27 @@ pub struct ClaudeCodeStatus {
     pub logged_in: bool,
     pub has_active_plan: bool,
     pub error: Option<String>,
+    /// Catalog key `error` was rendered from
+    #[serde(default)]
+    pub error_key: Option<String>,
+    /// The login probe was skipped for lack of a connection; the other fields
+    /// are from the last check that got an answer
+    #[serde(default)]
+    pub offline: bool,
+}
+
+impl ClaudeCodeStatus {
+    fn with_error(mut self, error: Message) -> Self {
+        self.error_key = error.key();
+        self.error = Some(error.text);
+        self
+    }
 }
 
+/// Last status the login probe produced, reported again while offline
+static LAST_CLAUDE_STATUS: Mutex<Option<ClaudeCodeStatus>> = Mutex::new(None);
+
+traced! {
 #[tauri::command]
 async fn check_claude_code_status() -> Result<ClaudeCodeStatus, String> {
     // Check if claude is installed
@@ -70,6 +163,8 @@ async fn check_claude_code_status() -> Result<ClaudeCodeStatus, String> {
             logged_in: false,
             has_active_plan: false,
             error: None,
+            error_key: None,
+            offline: false,
         });
     }
 
@@ -77,6 +172,34 @@ async fn check_claude_code_status() -> Result<ClaudeCodeStatus, String> {
         .trim()
         .to_string();
 
+    // The probe below is a real request; offline it only hangs and then looks
+    // like a login failure. Assume nothing changed since the last answer.
+    if tauri::async_runtime::spawn_blocking(network::is_offline).await.unwrap_or(false) {
+        let last = LAST_CLAUDE_STATUS.lock().clone();
+        return Ok(ClaudeCodeStatus {
+            installed: true,
+            path: Some(claude_path),
+            logged_in: last.as_ref().map(|s| s.logged_in).unwrap_or(true),
+            has_active_plan: last.as_ref().map(|s| s.has_active_plan).unwrap_or(true),
+            error: None,
+            error_key: None,
+            offline: true,
+        }.with_error(msg!("onboarding.offline")));
+    }
+
+    refresh_claude_status(claude_path)
+}
+}
+
+/// Probe claude and remember the answer for offline checks
+fn refresh_claude_status(claude_path: String) -> Result<ClaudeCodeStatus, String> {
+    let status = probe_claude_login(claude_path)?;
+    *LAST_CLAUDE_STATUS.lock() = Some(status.clone());
+    Ok(status)
+}
+
+/// Run a one-shot prompt to see whether claude is logged in with an active plan
+fn probe_claude_login(claude_path: String) -> Result<ClaudeCodeStatus, String> {
     // Test if claude works (logged in with active plan)
     let test_result = Command::new("claude")
         .args(["-p", "hi", "--output-format", "json"])
@@ -97,6 +220,8 @@ async fn check_claude_code_status() -> Result<ClaudeCodeStatus, String> {
                     logged_in: true,
                     has_active_plan: true,
                     error: None,
+                    error_key: None,
+                    offline: false,
                 });
             }
         }
@@ -111,8 +236,10 @@ async fn check_claude_code_status() -> Result<ClaudeCodeStatus, String> {
             path: Some(claude_path),
             logged_in: false,
             has_active_plan: false,
-            error: Some("Not logged in".to_string()),
-        });
+            error: None,
+            error_key: None,
+            offline: false,
+        }.with_error(msg!("onboarding.not_logged_in")));
     }
 
     if combined_output.contains("subscription") || combined_output.contains("plan") || combined_output.contains("billing") {
@@ -121,8 +248,10 @@ async fn check_claude_code_status() -> Result<ClaudeCodeStatus, String> {
             path: Some(claude_path),
             logged_in: true,
             has_active_plan: false,
-            error: Some("No active plan".to_string()),
-        });
+            error: None,
+            error_key: None,
+            offline: false,
+        }.with_error(msg!("onboarding.no_active_plan")));
     }
 
     // Unknown error state
@@ -131,20 +260,48 @@ async fn check_claude_code_status() -> Result<ClaudeCodeStatus, String> {
         path: Some(claude_path),
         logged_in: false,
         has_active_plan: false,
-        error: Some(format!("Unknown error: {}", stderr.chars().take(200).collect::<String>())),
-    })
+        error: None,
+        error_key: None,
+        offline: false,
+    }.with_error(msg!("onboarding.unknown_error", error = stderr.chars().take(200).collect::<String>())))
 }
 
+traced! {
+/// Run the CLI's login under a PTY and return the sign-in URL once it's printed.
+/// `claude-login-complete` follows when the account works.
 #[tauri::command]
-async fn open_claude_login() -> Result<(), String> {
-    // Open Claude Code in terminal for login
-    Command::new("open")
-        .args(["-a", "Terminal"])
-        .spawn()
-        .map_err(|e| e.to_string())?;
+async fn start_claude_login(app_handle: tauri::AppHandle) -> Result<claude_login::LoginStart, String> {
+    tauri::async_runtime::spawn_blocking(move || claude_login::start(app_handle))
+        .await
+        .map_err(|e| format!("Failed to start login: {}", e))?
+}
+}
 
+/// Send the code the browser shows back to the running login. Not traced: the
+/// code is a credential.
+#[tauri::command]
+fn submit_claude_login_code(code: String, login: State<'_, claude_login::ClaudeLogin>) -> Result<(), String> {
+    login.submit(&code)
+}
+
+traced! {
+#[tauri::command]
+fn cancel_claude_login(login: State<'_, claude_login::ClaudeLogin>) -> Result<(), String> {
+    login.cancel();
     Ok(())
 }
+}
+
+traced! {
+/// Copy the claude-service bundled with the app into ~/.nocur/claude-service,
+/// refreshing it if the bundle is newer
+#[tauri::command]
+async fn install_claude_service() -> Result<claude_service::ClaudeServiceInstall, String> {
+    tauri::async_runtime::spawn_blocking(claude_service::install)
+        .await
+        .map_err(|e| format!("Failed to install claude-service: {}", e))?
+}
+}
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
@@ -153,18 +310,149 @@ pub struct BuildResult {
     pub output: String,
     pub errors: Vec<BuildError>,
     pub warnings: u32,
+    /// Each warning with its location, like `errors`
+    #[serde(default)]
+    pub warning_details: Vec<BuildError>,
     pub build_time: Option<f64>,
     pub app_path: Option<String>,
     pub bundle_id: Option<String>,
+    /// The DerivedData this build used; each checkout and worktree has its own
+    #[serde(default)]
+    pub derived_data_path: Option<String>,
+    /// Set by `run_project` once the app is running
+    #[serde(default)]
+    pub launch: Option<LaunchInfo>,
+    /// Set by `run_project` when the app was installed under a different
+    /// bundle ID than the build reported, and launched under that one
+    #[serde(default)]
+    pub bundle_id_correction: Option<install_check::BundleIdCorrection>,
+    /// The build was stopped by `cancel_build`
+    #[serde(default)]
+    pub cancelled: bool,
+    /// Set by `run_project` when the app keeps crashing on launch; it isn't
+    /// launched again until `reset_crash_loop_counter`
+    #[serde(default)]
+    pub crash_loop: Option<crash_loop::CrashLoopDetected>,
+    /// The kept output, for `read_build_log`
+    #[serde(default)]
+    pub log_id: Option<String>,
+}
+
+impl BuildResult {
+    /// A failed result with `errors`. A run that fails after `build` succeeded
+    /// keeps the build's warnings, timing, DerivedData, app and log.
+    fn failed(output: String, errors: Vec<BuildError>, build: Option<&BuildResult>) -> Self {
+        Self {
+            success: false,
+            output,
+            errors,
+            warnings: build.map_or(0, |b| b.warnings),
+            warning_details: build.map(|b| b.warning_details.clone()).unwrap_or_default(),
+            build_time: build.and_then(|b| b.build_time),
+            app_path: build.and_then(|b| b.app_path.clone()),
+            bundle_id: build.and_then(|b| b.bundle_id.clone()),
+            derived_data_path: build.and_then(|b| b.derived_data_path.clone()),
+            launch: None,
+            bundle_id_correction: None,
+            cancelled: false,
+            crash_loop: None,
+            log_id: build.and_then(|b| b.log_id.clone()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CleanResult {
+    pub bytes_freed: u64,
+    pub clean_time: f64,
+    /// Build caches deleted by a deep clean
+    pub removed: Vec<String>,
+}
+
+/// The build in progress, for `cancel_build`
+#[derive(Default)]
+pub struct BuildState {
+    child_pid: RwLock<Option<u32>>,
+    cancelled: AtomicBool,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LaunchInfo {
+    /// Process ID reported by simctl or devicectl, when it printed one
+    pub pid: Option<u32>,
+    /// Log streaming was started before the app launched, so no early output was missed
+    pub log_streaming: bool,
+    /// "simulator" (log stream) or "devicectl" (console); None when not streaming
+    pub log_backend: Option<String>,
+    /// Why streaming didn't start when it was requested
+    pub log_error: Option<String>,
+    /// Simulator only: from `simctl launch` to the app's first log line
+    #[serde(default)]
+    pub launch_duration_ms: Option<u64>,
+    /// The launch was noticeably slower than recent ones
+    #[serde(default)]
+    pub launch_regression: bool,
+    /// Arguments the app was launched with, from `launch_overrides`
+    #[serde(default)]
+    pub launch_args: Vec<String>,
+}
+
+/// `app-launched`, so the frontend can start log streaming if the launch didn't
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AppLaunched {
+    pub bundle_id: String,
+    pub executable_name: Option<String>,
+    pub device_id: Option<String>,
+    /// "simulator" or "physical"
+    pub device_type: String,
+    pub device_name: String,
+    pub pid: Option<u32>,
+    pub log_streaming: bool,
+    pub log_backend: Option<String>,
+    pub launch_duration_ms: Option<u64>,
+    /// Slower than the trailing median by more than `launch_regression_percent`
+    pub regression: bool,
+    /// The `run_project` call that launched it; see `run_lock`
+    pub run_id: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct BuildError {
+    /// Absolute path as reported by the compiler
     pub file: Option<String>,
     pub line: Option<u32>,
     pub column: Option<u32>,
     pub message: String,
+    /// `file` relative to the project root, in the same form as `GitChangedFile.path`
+    #[serde(default)]
+    pub relative_path: Option<String>,
+    /// The file is in a package checkout or DerivedData, not the project's sources
+    #[serde(default)]
+    pub is_dependency: bool,
+    /// A fix the UI can offer in one click: "clean_build" when the error is a
+    /// stale DerivedData cache, "reset_crash_loop" when the app keeps crashing
+    /// on launch
+    #[serde(default)]
+    pub suggestion: Option<String>,
+    /// Values to pick from, e.g. the project's schemes when the one asked for
+    /// doesn't exist
+    #[serde(default)]
+    pub suggestions: Vec<String>,
+    /// `note:` lines and fix-it hints the compiler printed after the diagnostic
+    #[serde(default)]
+    pub notes: Vec<String>,
+    /// The source excerpt with its caret line, as printed
+    #[serde(default)]
+    pub snippet: Option<String>,
+    /// What kind of failure this is when it isn't a compiler diagnostic:
+    /// "signing_no_team", "signing_certificate_expired",
+    /// "signing_untrusted_device" or "signing" (see `signing`)
+    #[serde(default)]
+    pub category: Option<String>,
 }
 
 /// Events emitted during build process
@@ -173,46 +461,212 @@ pub struct BuildError {
 pub struct BuildEvent {
     pub event_type: String, // "started" | "output" | "error" | "completed"
     pub message: String,
+    /// Catalog key `message` was rendered from; None for tool output
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub message_key: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub message_args: Option<std::collections::BTreeMap<String, String>>,
     pub timestamp: u64,
+    /// Set when a network-dependent step was skipped for lack of a connection,
+    /// so the UI can show an indicator instead of an error
+    #[serde(default)]
+    pub offline: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub build_id: Option<String>,
+    /// The `run_project` call in progress, if any; see `run_lock`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub run_id: Option<String>,
 }
 
-fn emit_build_event(app_handle: &tauri::AppHandle, event_type: &str, message: &str) {
+fn emit_build_event(app_handle: &tauri::AppHandle, event_type: &str, message: impl Into<Message>) {
+    send_build_event(app_handle, event_type, message.into(), false);
+}
+
+/// A "warning" build event flagged `offline`
+fn emit_offline_build_event(app_handle: &tauri::AppHandle, message: Message) {
+    send_build_event(app_handle, "warning", message, true);
+}
+
+fn send_build_event(app_handle: &tauri::AppHandle, event_type: &str, message: Message, offline: bool) {
     let timestamp = SystemTime::now()
         .duration_since(UNIX_EPOCH)
         .unwrap_or_default()
         .as_millis() as u64;
 
-    let _ = app_handle.emit("build-event", BuildEvent {
+    // Builds are keyed by an ID assigned when they start, so a replay can ask
+    // for just the current one
+    let build_id = app_handle.try_state::<event_journal::EventJournal>().map(|journal| {
+        if event_type == "started" {
+            journal.begin_build()
+        } else {
+            journal.current_build()
+        }
+    });
+    let message_args = message
+        .args()
+        .map(|args| args.into_iter().map(|(name, value)| (name, redaction::redact(&value))).collect());
+    event_journal::emit(app_handle, event_journal::Channel::Build, build_id.clone(), BuildEvent {
         event_type: event_type.to_string(),
-        message: message.to_string(),
+        message_key: message.key(),
+        message_args,
+        message: redaction::redact(&message.text),
         timestamp,
+        offline,
+        build_id,
+        run_id: app_handle.try_state::<run_lock::RunLocks>().and_then(|locks| locks.current_run_id()),
     });
 }
 
-fn parse_build_errors(output: &str) -> (Vec<BuildError>, u32) {
-    let mut errors = Vec::new();
-    let mut warnings = 0u32;
+fn stale_cache_suggestion(message: &str) -> Option<String> {
+    build_history::is_stale_cache_error(message).then(|| "clean_build".to_string())
+}
+
+/// A diagnostic with a `file:line:column:` location
+fn located_diagnostic(caps: &regex::Captures, project_root: &Path) -> BuildError {
+    let file = paths::normalize_project_path(project_root, caps.get(1).map_or("", |m| m.as_str()));
+    let message = caps.get(5).map_or("", |m| m.as_str()).trim().to_string();
+    BuildError {
+        file: Some(file.absolute),
+        line: caps.get(2).and_then(|m| m.as_str().parse().ok()),
+        column: caps.get(3).and_then(|m| m.as_str().parse().ok()),
+        suggestion: stale_cache_suggestion(&message),
+        suggestions: Vec::new(),
+        notes: Vec::new(),
+        snippet: None,
+        category: None,
+        message,
+        relative_path: file.relative,
+        is_dependency: file.is_dependency,
+    }
+}
+
+fn unlocated_diagnostic(message: &str) -> BuildError {
+    BuildError {
+        file: None,
+        line: None,
+        column: None,
+        message: message.trim().to_string(),
+        relative_path: None,
+        is_dependency: false,
+        suggestion: stale_cache_suggestion(message),
+        suggestions: Vec::new(),
+        notes: Vec::new(),
+        snippet: None,
+        category: None,
+    }
+}
+
+/// A line of the source excerpt printed under a diagnostic: the indented
+/// source and caret lines, a fix-it's text, or Swift's `12 | code` framing
+fn is_snippet_line(line: &str) -> bool {
+    let trimmed = line.trim_start();
+    if trimmed.is_empty() {
+        return false;
+    }
+    let numbered = trimmed
+        .split_once('|')
+        .is_some_and(|(number, _)| number.trim().chars().all(|c| c.is_ascii_digit()));
+    numbered || line.starts_with(char::is_whitespace)
+}
 
-    // Regex for Xcode build errors: /path/to/file.swift:42:10: error: message
-    let error_regex = Regex::new(r"(.+?):(\d+):(\d+):\s*(error|warning):\s*(.+)").ok();
+/// The note in a `note:` line, with its location when it has one
+fn note_text(line: &str) -> Option<String> {
+    let (location, note) = line.split_once("note: ")?;
+    let location = location.trim().trim_start_matches(['|', '`', '-', ' ']).trim_end_matches(':').trim();
+    Some(match location.is_empty() {
+        true => note.trim().to_string(),
+        false => format!("{}: {}", location, note.trim()),
+    })
+}
 
-    for line in output.lines() {
-        if line.contains(": warning:") {
-            warnings += 1;
+/// Attach the excerpt collected for the current diagnostic and stop adding to it
+fn close_diagnostic(
+    current: &mut Option<(bool, usize)>,
+    snippet: &mut Vec<&str>,
+    errors: &mut [BuildError],
+    warnings: &mut [BuildError],
+) {
+    if let Some((is_warning, index)) = current.take() {
+        let list = if is_warning { warnings } else { errors };
+        if !snippet.is_empty() {
+            list[index].snippet = Some(snippet.join("\n"));
         }
-        if line.contains(": error:") {
-            if let Some(ref re) = error_regex {
-                if let Some(caps) = re.captures(line) {
-                    errors.push(BuildError {
-                        file: Some(caps.get(1).map_or("", |m| m.as_str()).to_string()),
-                        line: caps.get(2).and_then(|m| m.as_str().parse().ok()),
-                        column: caps.get(3).and_then(|m| m.as_str().parse().ok()),
-                        message: caps.get(5).map_or("", |m| m.as_str()).to_string(),
-                    });
+    }
+    snippet.clear();
+}
+
+/// `(errors, warnings)` from xcodebuild output. Diagnostics repeated in stdout
+/// and stderr, or by several compile steps, are listed once.
+///
+/// The `note:` lines, fix-its and source excerpt printed after a diagnostic
+/// are attached to it, until a blank line or the next line that isn't part of
+/// it. Errors without a location, like the linker's, are kept with `file: None`.
+fn parse_build_errors(output: &str, project_root: &Path) -> (Vec<BuildError>, Vec<BuildError>) {
+    let mut errors: Vec<BuildError> = Vec::new();
+    let mut warnings: Vec<BuildError> = Vec::new();
+    let mut seen = std::collections::HashSet::new();
+    // The diagnostic the following lines belong to: (is a warning, index)
+    let mut current: Option<(bool, usize)> = None;
+    let mut snippet: Vec<&str> = Vec::new();
+
+    // Regex for Xcode build diagnostics: /path/to/file.swift:42:10: error: message
+    let diagnostic_regex = Regex::new(r"(.+?):(\d+):(\d+):\s*(error|warning):\s*(.+)").ok();
+    // "error: ...", "ld: error: ...", "clang: error: ...", "<unknown>:0: error: ..."
+    let unlocated_regex = Regex::new(r"^(?:\S+:\s)?(error|warning):\s*(.+)").ok();
+
+    for raw in output.lines() {
+        let line = raw.trim();
+        let is_error = line.contains(": error:") || line.starts_with("error:");
+        let is_warning = !is_error && (line.contains(": warning:") || line.starts_with("warning:"));
+        let in_snippet = is_snippet_line(raw);
+
+        if !is_error && !is_warning {
+            let Some((is_warning, index)) = current else {
+                continue;
+            };
+            let note = note_text(line).or_else(|| line.starts_with("fix-it:").then(|| line.to_string()));
+            // Swift prints its notes inside the excerpt's framing
+            if in_snippet {
+                snippet.push(raw);
+            }
+            match note {
+                Some(note) => {
+                    let list = if is_warning { &mut warnings } else { &mut errors };
+                    list[index].notes.push(note);
                 }
+                None if in_snippet => {}
+                None => close_diagnostic(&mut current, &mut snippet, &mut errors, &mut warnings),
             }
+            continue;
+        }
+        // Swift repeats the diagnostic inside its excerpt ("`- error: ...")
+        if current.is_some() && in_snippet && !diagnostic_regex.as_ref().is_some_and(|re| re.is_match(line)) {
+            snippet.push(raw);
+            continue;
+        }
+        close_diagnostic(&mut current, &mut snippet, &mut errors, &mut warnings);
+
+        let located = diagnostic_regex
+            .as_ref()
+            .and_then(|re| re.captures(line))
+            .map(|caps| located_diagnostic(&caps, project_root));
+        let diagnostic = match located {
+            Some(diagnostic) => diagnostic,
+            None => match unlocated_regex.as_ref().and_then(|re| re.captures(line)) {
+                Some(caps) => unlocated_diagnostic(caps.get(2).map_or("", |m| m.as_str())),
+                None => continue,
+            },
+        };
+        let key = (diagnostic.file.clone(), diagnostic.line, diagnostic.column, diagnostic.message.clone(), is_warning);
+        if !seen.insert(key) {
+            // Its notes were attached the first time
+            continue;
         }
+        let list = if is_warning { &mut warnings } else { &mut errors };
+        list.push(diagnostic);
+        current = Some((is_warning, list.len() - 1));
     }
+    close_diagnostic(&mut current, &mut snippet, &mut errors, &mut warnings);
 
     (errors, warnings)
 }
@@ -235,7 +689,8 @@ fn check_physical_device_availability(device_id: &str) -> DeviceAvailability {
     let temp_file = std::env::temp_dir().join(format!("devicectl_check_{}.json", std::process::id()));
     
     let output = Command::new("xcrun")
-        .args(["devicectl", "list", "devices", "--json-output", temp_file.to_str().unwrap_or("")])
+        .args(["devicectl", "list", "devices", "--json-output"])
+        .arg(&temp_file)
         .output();
     
     let result = match output {
@@ -303,41 +758,6 @@ fn parse_device_availability(json_str: &str, device_id: &str) -> DeviceAvailabil
     DeviceAvailability::NotFound
 }
 
-/// Parse devicectl error output into a user-friendly message
-fn parse_devicectl_error(stderr: &str) -> String {
-    // Common error patterns and their user-friendly messages
-    if stderr.contains("device is not connected") || stderr.contains("no device found") {
-        return "Device is not connected. Check USB cable or WiFi connection.".to_string();
-    }
-    
-    if stderr.contains("tunnel") && stderr.contains("unavailable") {
-        return "Cannot establish connection to device. Try unplugging and reconnecting, or restarting the device.".to_string();
-    }
-    
-    if stderr.contains("timed out") || stderr.contains("timeout") {
-        return "Operation timed out. The device may be busy or unresponsive.".to_string();
-    }
-    
-    if stderr.contains("not paired") || stderr.contains("pairing") {
-        return "Device is not trusted. Connect via USB and tap 'Trust' on the device.".to_string();
-    }
-    
-    if stderr.contains("code signing") || stderr.contains("provisioning") {
-        return "Code signing error. Check your provisioning profile and signing certificate.".to_string();
-    }
-    
-    if stderr.contains("disk space") || stderr.contains("storage") {
-        return "Not enough storage on device. Free up space and try again.".to_string();
-    }
-    
-    if stderr.contains("locked") {
-        return "Device is locked. Unlock the device and try again.".to_string();
-    }
-    
-    // Return first line of error if no specific pattern matched
-    stderr.lines().next().unwrap_or("Unknown error").to_string()
-}
-
 // =============================================================================
 // Device Types
 // =============================================================================
@@ -353,6 +773,12 @@ pub struct DeviceInfo {
     pub device_type: DeviceType,
     pub state: DeviceState,
     pub is_available: bool,
+    /// devicectl pairing state for physical devices, e.g. "paired"
+    #[serde(default)]
+    pub pairing_state: Option<String>,
+    /// None for simulators and devices that don't report it
+    #[serde(default)]
+    pub developer_mode_enabled: Option<bool>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
@@ -378,6 +804,12 @@ pub struct DeviceListResult {
     pub devices: Vec<DeviceInfo>,
     pub simulator_count: i32,
     pub physical_count: i32,
+    /// How long simctl, devicectl and (when used) nocur-swift took
+    #[serde(default)]
+    pub timings: Vec<device_list::DeviceSourceTiming>,
+    /// The devices again, grouped, when `list_devices` was asked to group them
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub groups: Option<Vec<device_list::DeviceGroup>>,
 }
 
 /// App state for selected device
@@ -395,40 +827,113 @@ impl Default for AppState {
     }
 }
 
+/// Effective working directory per Claude session. A session that runs in a
+/// worktree maps to the worktree path, so session-scoped commands (diffs, file
+/// lists, terminals) follow the worktree instead of the project root.
+#[derive(Default)]
+pub struct SessionContext {
+    working_dirs: std::collections::HashMap<String, String>,
+}
+
+impl SessionContext {
+    pub fn set_working_dir(&mut self, session_id: &str, working_dir: &str) {
+        self.working_dirs.insert(session_id.to_string(), working_dir.to_string());
+    }
+
+    pub fn working_dir(&self, session_id: &str) -> Option<String> {
+        self.working_dirs.get(session_id).cloned()
+    }
+
+    /// Drop every session mapped to a directory (e.g. a removed worktree)
+    pub fn forget_working_dir(&mut self, working_dir: &str) {
+        self.working_dirs.retain(|_, dir| dir != working_dir);
+    }
+
+    /// Every directory some session is currently working in
+    pub fn working_dirs_in_use(&self) -> Vec<String> {
+        self.working_dirs.values().cloned().collect()
+    }
+}
+
+/// An explicit path always wins; otherwise fall back to the session's working directory
+fn resolve_working_dir(
+    path: Option<String>,
+    session_id: Option<&str>,
+    session_context: &Mutex<SessionContext>,
+) -> Option<String> {
+    path.or_else(|| session_id.and_then(|id| session_context.lock().working_dir(id)))
+}
+
 // =============================================================================
 // Device Commands
 // =============================================================================
 
+traced! {
+/// Simulators and physical devices. Simulators whose runtime was deleted are
+/// left out unless `include_unavailable`; `recent_first` puts the devices
+/// last launched on by `run_project` first, and `group_by` ("runtime" or
+/// "type") adds `groups` alongside the flat list.
 #[tauri::command]
-async fn list_devices() -> Result<DeviceListResult, String> {
-    // Run nocur-swift device list
-    let output = Command::new("swift")
-        .args(["run", "nocur-swift", "device", "list"])
-        .current_dir(format!("{}/nocur-swift", env!("CARGO_MANIFEST_DIR").replace("/src-tauri", "")))
-        .output()
-        .map_err(|e| format!("Failed to run nocur-swift: {}", e))?;
+async fn list_devices(
+    include_unavailable: Option<bool>,
+    group_by: Option<device_list::DeviceGrouping>,
+    recent_first: Option<bool>,
+    state: State<'_, Mutex<AppState>>,
+) -> Result<DeviceListResult, String> {
+    let result = match prefetch::get::<DeviceListResult>(PrefetchItem::Devices, "") {
+        Some(result) => result,
+        None => load_devices(state).await?,
+    };
+    let last_used = match recent_first {
+        Some(true) => Some(get_user_preferences().await.unwrap_or_default().device_last_used),
+        _ => None,
+    };
+    Ok(device_list::arrange(result, include_unavailable.unwrap_or(false), group_by, last_used.as_ref()))
+}
+}
 
-    if !output.status.success() {
-        let stderr = String::from_utf8_lossy(&output.stderr);
-        return Err(format!("nocur-swift device list failed: {}", stderr));
+/// Every device, unavailable ones included, as `list_devices` and the prefetch cache start from
+async fn load_devices(state: State<'_, Mutex<AppState>>) -> Result<DeviceListResult, String> {
+    let mut result = tauri::async_runtime::spawn_blocking(device_list::list_devices)
+        .await
+        .map_err(|e| format!("Failed to list devices: {}", e))??;
+
+    // Pairing and Developer Mode come from devicectl (already filled in unless
+    // nocur-swift listed the devices); the selected device gets the fresher
+    // per-device details
+    if result.devices.iter().any(|d| d.device_type == DeviceType::Physical) {
+        let selected_id = state.lock().selected_device_id.clone();
+        let needs_statuses = result.devices.iter().any(|d| d.device_type == DeviceType::Physical && d.pairing_state.is_none());
+        let mut statuses = if needs_statuses { device_preflight::list_device_statuses() } else { Default::default() };
+        for device in result.devices.iter_mut().filter(|d| d.device_type == DeviceType::Physical) {
+            let devicectl_id = device.core_device_id.clone().unwrap_or_else(|| device.id.clone());
+            let status = if selected_id.as_deref() == Some(device.id.as_str()) {
+                device_preflight::device_status(&devicectl_id)
+            } else {
+                statuses.remove(&devicectl_id).or_else(|| statuses.remove(&device.id))
+            };
+            if let Some(status) = status {
+                device.pairing_state = status.pairing_state;
+                device.developer_mode_enabled = status.developer_mode_enabled;
+            }
+        }
     }
 
-    let stdout = String::from_utf8_lossy(&output.stdout);
-    
-    // Parse the JSON output
-    let json: serde_json::Value = serde_json::from_str(&stdout)
-        .map_err(|e| format!("Failed to parse device list: {}", e))?;
-
-    // Extract the data field
-    let data = json.get("data")
-        .ok_or("Missing data field in response")?;
-    
-    let result: DeviceListResult = serde_json::from_value(data.clone())
-        .map_err(|e| format!("Failed to parse device list data: {}", e))?;
-
     Ok(result)
 }
 
+traced! {
+/// Whether a physical device is paired and has Developer Mode on, with a
+/// finding for each thing the user has to fix on the device
+#[tauri::command]
+async fn get_device_readiness(device_id: String) -> Result<device_preflight::DeviceReadiness, String> {
+    tauri::async_runtime::spawn_blocking(move || device_preflight::device_readiness(&device_id))
+        .await
+        .map_err(|e| format!("Failed to check device readiness: {}", e))
+}
+}
+
+traced! {
 #[tauri::command]
 async fn get_selected_device(
     state: State<'_, Mutex<AppState>>,
@@ -436,18 +941,97 @@ async fn get_selected_device(
     let app_state = state.lock();
     Ok(app_state.selected_device.clone())
 }
+}
 
+traced! {
+/// Select the device to build for. Selecting a physical device also checks
+/// signing health in the background (at most once a day per project and
+/// device) and sends a `signing-warning` event for each problem.
 #[tauri::command]
 async fn set_selected_device(
     device: DeviceInfo,
+    project_path: Option<String>,
+    app_handle: tauri::AppHandle,
     state: State<'_, Mutex<AppState>>,
 ) -> Result<(), String> {
+    if device.device_type == DeviceType::Physical {
+        let udid = device.id.clone();
+        tauri::async_runtime::spawn_blocking(move || {
+            let (health, fresh) = signing_health::cached_check(project_path.as_deref(), Some(&udid), false);
+            if fresh {
+                emit_signing_warnings(&app_handle, project_path.as_deref(), &health);
+            }
+        });
+    }
+
     let mut app_state = state.lock();
     app_state.selected_device_id = Some(device.id.clone());
     app_state.selected_device = Some(device);
     Ok(())
 }
+}
+
+/// `signing-warning` payload
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SigningWarningEvent {
+    pub project_path: Option<String>,
+    #[serde(flatten)]
+    pub warning: signing_health::SigningWarning,
+}
+
+fn emit_signing_warnings(app_handle: &tauri::AppHandle, project_path: Option<&str>, health: &signing_health::SigningHealth) {
+    for warning in &health.warnings {
+        event_journal::emit_stamped(app_handle, "signing-warning", SigningWarningEvent {
+            project_path: project_path.map(String::from),
+            warning: warning.clone(),
+        });
+    }
+}
+
+traced! {
+/// Certificates and provisioning profiles for the project's team that have
+/// expired or expire within 30 days, and whether a current profile covers the
+/// selected physical device
+#[tauri::command]
+async fn check_signing_health(
+    project_path: String,
+    force_refresh: Option<bool>,
+    app_handle: tauri::AppHandle,
+    state: State<'_, Mutex<AppState>>,
+) -> Result<signing_health::SigningHealth, String> {
+    let device_udid = state
+        .lock()
+        .selected_device
+        .as_ref()
+        .filter(|d| d.device_type == DeviceType::Physical)
+        .map(|d| d.id.clone());
+    let force_refresh = force_refresh.unwrap_or(false);
+    let path = project_path.clone();
+    let (health, fresh) = tauri::async_runtime::spawn_blocking(move || {
+        signing_health::cached_check(Some(&path), device_udid.as_deref(), force_refresh)
+    })
+    .await
+    .map_err(|e| format!("Failed to check signing health: {}", e))?;
+    if fresh {
+        emit_signing_warnings(&app_handle, Some(&project_path), &health);
+    }
+    Ok(health)
+}
+}
 
+traced! {
+/// The keychain's valid code signing identities, for `build_project`'s
+/// `code_sign_identity`
+#[tauri::command]
+async fn list_signing_identities() -> Result<Vec<signing_health::SigningIdentity>, String> {
+    tauri::async_runtime::spawn_blocking(signing_health::list_identities)
+        .await
+        .map_err(|e| format!("Failed to list signing identities: {}", e))
+}
+}
+
+traced! {
 #[tauri::command]
 async fn clear_selected_device(
     state: State<'_, Mutex<AppState>>,
@@ -457,112 +1041,437 @@ async fn clear_selected_device(
     app_state.selected_device = None;
     Ok(())
 }
+}
+
+/// simctl target for a simulator-only command: `device_id` if given, else the
+/// selected device, else whichever simulator is booted. Fails with `Unsupported`
+/// when the selected device is physical.
+fn simulator_target(device_id: Option<String>, app_state: &Mutex<AppState>) -> Result<String, CommandError> {
+    if let Some(id) = device_id {
+        return Ok(id);
+    }
+    match app_state.lock().selected_device.as_ref() {
+        Some(device) if device.device_type == DeviceType::Physical => Err(CommandError::Unsupported(format!(
+            "{} is a physical device; this only works on simulators",
+            device.name
+        ))),
+        Some(device) => Ok(device.id.clone()),
+        None => Ok("booted".to_string()),
+    }
+}
 
 // =============================================================================
 // Build Commands
 // =============================================================================
 
-#[tauri::command]
-async fn build_project(
-    project_path: Option<String>,
-    scheme: Option<String>,
-    device: Option<DeviceInfo>,
-    app_handle: tauri::AppHandle,
-) -> Result<BuildResult, String> {
-    let start_time = Instant::now();
+/// Locate the built .app in DerivedData and read its bundle ID from Info.plist
+fn find_built_app(project_dir: &str, configuration: &str, is_physical_device: bool) -> (Option<String>, Option<String>) {
+    // Find the built app - check both iphoneos (physical) and iphonesimulator paths
+    let sdk_suffix = if is_physical_device { "iphoneos" } else { "iphonesimulator" };
+    let derived_data = paths::derived_data_dir(Path::new(project_dir))
+        .join("Build/Products")
+        .join(format!("{}-{}", configuration, sdk_suffix));
+    let app_path = std::fs::read_dir(&derived_data)
+        .ok()
+        .and_then(|entries| {
+            entries
+                .filter_map(|e| e.ok())
+                .find(|e| e.path().extension().map_or(false, |ext| ext == "app"))
+                .map(|e| e.path().to_string_lossy().to_string())
+        });
 
-    // Emit build started event
-    emit_build_event(&app_handle, "started", &format!("Building {} ...", scheme.as_deref().unwrap_or("project")));
+    // Get bundle ID from Info.plist
+    let bundle_id = app_path.as_ref().and_then(|path| {
+        let plist_path = Path::new(path).join("Info.plist");
+        std::fs::read(&plist_path).ok().and_then(|data| {
+            plist::from_bytes::<plist::Dictionary>(&data).ok()
+        }).and_then(|dict| {
+            dict.get("CFBundleIdentifier").and_then(|v| v.as_string()).map(String::from)
+        })
+    });
 
-    // Determine project path - must be provided by the caller
-    let project_dir = project_path.clone().ok_or_else(|| {
-        "No project path provided. Please select a project first.".to_string()
-    })?;
+    (app_path, bundle_id)
+}
+
+/// The app's executable (process) name: CFBundleExecutable, else the .app name
+fn app_executable_name(app_path: &str) -> Option<String> {
+    let plist_path = Path::new(app_path).join("Info.plist");
+    std::fs::read(&plist_path)
+        .ok()
+        .and_then(|data| plist::from_bytes::<plist::Dictionary>(&data).ok())
+        .and_then(|dict| dict.get("CFBundleExecutable").and_then(|v| v.as_string()).map(String::from))
+        .or_else(|| {
+            std::path::Path::new(app_path)
+                .file_stem()
+                .map(|s| s.to_string_lossy().to_string())
+        })
+}
 
-    // Find .xcodeproj
-    let project_file = std::fs::read_dir(&project_dir)
+/// The .xcodeproj or .xcworkspace at the top of `project_dir`
+fn find_project_file(project_dir: &str) -> Result<PathBuf, String> {
+    std::fs::read_dir(project_dir)
         .map_err(|e| format!("Cannot read directory: {}", e))?
         .filter_map(|e| e.ok())
         .find(|e| {
-            e.path().extension().map_or(false, |ext| ext == "xcodeproj" || ext == "xcworkspace")
+            e.path().extension().is_some_and(|ext| ext == "xcodeproj" || ext == "xcworkspace")
         })
         .map(|e| e.path())
-        .ok_or_else(|| "No Xcode project found".to_string())?;
-
-    let is_workspace = project_file.extension().map_or(false, |ext| ext == "xcworkspace");
-
-    // Check for Tuist project (Project.swift exists)
-    let tuist_manifest = PathBuf::from(&project_dir).join("Project.swift");
-    let is_tuist_project = tuist_manifest.exists();
+        .ok_or_else(|| "No Xcode project found".to_string())
+}
 
-    // Determine scheme (use provided or default to project name)
-    let build_scheme = scheme.unwrap_or_else(|| {
-        project_file.file_stem()
-            .and_then(|s| s.to_str())
-            .unwrap_or("NocurTestApp")
-            .to_string()
+traced! {
+/// Build the project. Without a scheme, the one a previous build settled on is
+/// used, else the project's file name. If that scheme doesn't exist, the
+/// project's schemes come back as `suggestions` on the error, and when exactly
+/// one is shared the build is retried with it once and it's remembered.
+/// `configuration` defaults to Debug; another must be one of the project's.
+/// A directory with only a Package.swift is built as a Swift package. A Tuist
+/// project is generated first when it hasn't been or Project.swift changed;
+/// without tuist installed that fails with `MissingTool`. `development_team`
+/// and `code_sign_identity` override the project's signing settings (see
+/// `signing`); a failure to sign comes back as a categorized error.
+#[tauri::command]
+async fn build_project(
+    project_path: Option<String>,
+    scheme: Option<String>,
+    configuration: Option<String>,
+    device: Option<DeviceInfo>,
+    development_team: Option<String>,
+    code_sign_identity: Option<String>,
+    app_handle: tauri::AppHandle,
+) -> Result<BuildResult, CommandError> {
+    let signing = signing::SigningOverrides::new(development_team, code_sign_identity)?;
+    let Some(project_dir) = project_path.clone() else {
+        return run_build(project_path, scheme, configuration, device, signing, app_handle).await;
+    };
+    let scheme = scheme.or_else(|| schemes::saved_scheme(&project_dir));
+    let result = run_build(
+        project_path.clone(),
+        scheme.clone(),
+        configuration.clone(),
+        device.clone(),
+        signing.clone(),
+        app_handle.clone(),
+    )
+    .await?;
+
+    // Tuist generates its own schemes, so there's nothing to list before it runs
+    let is_tuist_project = PathBuf::from(&project_dir).join("Project.swift").exists();
+    let not_found = match schemes::scheme_not_found(&result.output) {
+        Some(line) if !result.success && !is_tuist_project => line,
+        _ => return Ok(result),
+    };
+    let Ok(project_file) = find_project_file(&project_dir) else {
+        return Ok(result);
+    };
+    let is_workspace = project_file.extension().is_some_and(|ext| ext == "xcworkspace");
+    let requested = scheme.unwrap_or_else(|| {
+        project_file.file_stem().and_then(|s| s.to_str()).unwrap_or("NocurTestApp").to_string()
     });
 
-    emit_build_event(&app_handle, "output", &format!("Project: {}", project_file.display()));
-    emit_build_event(&app_handle, "output", &format!("Scheme: {}", build_scheme));
-
-    // Determine destination based on device
-    let (destination, is_physical_device) = match &device {
-        Some(d) => {
-            let dest = match d.device_type {
-                DeviceType::Physical => format!("platform=iOS,id={}", d.id),
-                DeviceType::Simulator => format!("platform=iOS Simulator,id={}", d.id),
-            };
-            emit_build_event(&app_handle, "output", &format!("Device: {} ({})", d.name, if d.device_type == DeviceType::Physical { "physical" } else { "simulator" }));
-            (dest, d.device_type == DeviceType::Physical)
-        }
-        None => {
-            emit_build_event(&app_handle, "output", "Device: iPhone 16 Pro (simulator, default)");
-            ("platform=iOS Simulator,name=iPhone 16 Pro".to_string(), false)
+    let listing = {
+        let project_file = project_file.clone();
+        tauri::async_runtime::spawn_blocking(move || {
+            let available = schemes::list_schemes(&project_file, is_workspace)?;
+            let shared = schemes::shared_schemes(&project_file, is_workspace, &available);
+            Ok::<_, String>((available, shared))
+        })
+        .await
+        .map_err(|e| format!("Scheme listing task failed: {}", e))?
+    };
+    let (available, shared) = match listing {
+        Ok(listing) => listing,
+        Err(e) => {
+            log::warn!("Couldn't list schemes for {}: {}", project_dir, e);
+            (Vec::new(), Vec::new())
         }
     };
 
-    // Build output path - we'll use a consistent path for both Tuist and regular builds
-    let derived_data_path = format!("{}/DerivedData", project_dir);
-    
-    // Build command - use tuist build for Tuist projects (handles generation + caching)
-    let mut cmd;
-    
-    if is_tuist_project {
-        emit_build_event(&app_handle, "output", "Tuist project detected, using tuist build (with caching)...");
+    if let [only] = shared.as_slice() {
+        if *only != requested {
+            emit_build_event(&app_handle, "warning", msg!("build.scheme_substituted", requested = requested, scheme = only));
+            let retry = run_build(project_path, Some(only.clone()), configuration, device, signing, app_handle).await?;
+            if retry.success {
+                if let Err(e) = schemes::save_scheme(&project_dir, only) {
+                    log::warn!("Failed to save scheme: {}", e);
+                }
+            }
+            return Ok(retry);
+        }
+    }
+
+    if !available.is_empty() {
+        emit_build_event(&app_handle, "error", msg!("build.scheme_not_found", scheme = requested, schemes = available.join(", ")));
+    }
+    let mut result = result;
+    result.errors.retain(|e| e.file.is_some() || e.message != not_found);
+    result.errors.insert(0, BuildError { suggestions: available, ..unlocated_diagnostic(&not_found) });
+    Ok(result)
+}
+}
+
+traced! {
+/// The project's schemes with the targets they build and the configurations
+/// they can use, for picking the `scheme` to build
+#[tauri::command]
+async fn list_schemes(project_path: String) -> Result<Vec<schemes::SchemeInfo>, String> {
+    let project_file = find_project_file(&project_path)?;
+    let is_workspace = project_file.extension().is_some_and(|ext| ext == "xcworkspace");
+    tauri::async_runtime::spawn_blocking(move || schemes::scheme_infos(&project_file, is_workspace))
+        .await
+        .map_err(|e| format!("Scheme listing task failed: {}", e))?
+}
+}
+
+traced! {
+/// The resolved build settings of the scheme's app target: bundle ID, team,
+/// deployment target, device families and Swift version. `scheme` defaults
+/// like a build's, `configuration` to Debug. Cached until the project changes.
+#[tauri::command]
+async fn get_build_settings(
+    project_path: String,
+    scheme: Option<String>,
+    configuration: Option<String>,
+) -> Result<build_settings::BuildSettings, String> {
+    let project_file = find_project_file(&project_path)?;
+    let is_workspace = project_file.extension().is_some_and(|ext| ext == "xcworkspace");
+    let scheme = scheme.or_else(|| schemes::saved_scheme(&project_path)).unwrap_or_else(|| {
+        project_file.file_stem().and_then(|s| s.to_str()).unwrap_or("App").to_string()
+    });
+    let configuration = configuration.unwrap_or_else(|| DEFAULT_CONFIGURATION.to_string());
+    tauri::async_runtime::spawn_blocking(move || {
+        build_settings::build_settings(&project_file, is_workspace, &scheme, &configuration)
+    })
+    .await
+    .map_err(|e| format!("Build settings task failed: {}", e))?
+}
+}
+
+traced! {
+/// A failed build's errors with the output around them, its failing target
+/// and phase, linker and signing sections and the end of its log, fitted to
+/// `max_tokens` (estimated). Without `build_id`, the newest failed build of
+/// `project_path`, or of any project.
+#[tauri::command]
+async fn get_build_failure_context(
+    build_id: Option<String>,
+    project_path: Option<String>,
+    max_tokens: Option<usize>,
+) -> Result<build_failure::FailureContext, String> {
+    let max_tokens = max_tokens.unwrap_or(context_budget::DEFAULT_MAX_TOKENS);
+    tauri::async_runtime::spawn_blocking(move || {
+        build_failure::for_build(project_path.as_deref(), build_id.as_deref(), max_tokens)
+    })
+    .await
+    .map_err(|e| format!("Build failure context task failed: {}", e))?
+}
+}
+
+const DEFAULT_CONFIGURATION: &str = "Debug";
+
+/// `configuration` as the project spells it, or an error listing the ones it has.
+/// If the project can't be listed, xcodebuild gets to say what's wrong.
+async fn validate_configuration(project_file: PathBuf, is_workspace: bool, configuration: String) -> Result<String, String> {
+    let listing = {
+        let project_file = project_file.clone();
+        tauri::async_runtime::spawn_blocking(move || schemes::list_configurations(&project_file, is_workspace))
+            .await
+            .map_err(|e| format!("Configuration listing task failed: {}", e))?
+    };
+    let available = match listing {
+        Ok(available) if !available.is_empty() => available,
+        Ok(_) => return Ok(configuration),
+        Err(e) => {
+            log::warn!("Couldn't list build configurations for {}: {}", project_file.display(), e);
+            return Ok(configuration);
+        }
+    };
+    available
+        .iter()
+        .find(|c| c.eq_ignore_ascii_case(&configuration))
+        .cloned()
+        .ok_or_else(|| {
+            format!(
+                "Build configuration '{}' doesn't exist in {}; available: {}",
+                configuration,
+                project_file.display(),
+                available.join(", ")
+            )
+        })
+}
+
+/// xcodebuild's `-destination` for `device` and whether it's a physical one
+fn build_destination(app_handle: &tauri::AppHandle, device: Option<&DeviceInfo>) -> (String, bool) {
+    match device {
+        Some(d) => {
+            let dest = match d.device_type {
+                DeviceType::Physical => format!("platform=iOS,id={}", d.id),
+                DeviceType::Simulator => format!("platform=iOS Simulator,id={}", d.id),
+            };
+            emit_build_event(app_handle, "output", msg!("build.device", name = d.name, kind = if d.device_type == DeviceType::Physical { "physical" } else { "simulator" }));
+            (dest, d.device_type == DeviceType::Physical)
+        }
+        None => {
+            emit_build_event(app_handle, "output", msg!("build.device_default"));
+            ("platform=iOS Simulator,name=iPhone 16 Pro".to_string(), false)
+        }
+    }
+}
+
+/// One xcodebuild, tuist or swift build, with `scheme` or the file-name guess
+async fn run_build(
+    project_path: Option<String>,
+    scheme: Option<String>,
+    configuration: Option<String>,
+    device: Option<DeviceInfo>,
+    signing: signing::SigningOverrides,
+    app_handle: tauri::AppHandle,
+) -> Result<BuildResult, CommandError> {
+    let start_time = Instant::now();
+
+    // Emit build started event
+    emit_build_event(&app_handle, "started", msg!("build.started", scheme = scheme.as_deref().unwrap_or("project")));
+
+    // Determine project path - must be provided by the caller
+    let project_dir = project_path.clone().ok_or_else(|| {
+        "No project path provided. Please select a project first.".to_string()
+    })?;
+
+    // Check for Tuist project (Project.swift exists), and generate it so
+    // there's a project file to find
+    let tuist_manifest = PathBuf::from(&project_dir).join("Project.swift");
+    let is_tuist_project = tuist_manifest.exists();
+    if is_tuist_project && tuist::needs_generate(Path::new(&project_dir)) {
+        let generate_dir = PathBuf::from(&project_dir);
+        let generate_handle = app_handle.clone();
+        tauri::async_runtime::spawn_blocking(move || tuist::generate(&generate_handle, &generate_dir))
+            .await
+            .map_err(|e| format!("tuist generate task failed: {}", e))??;
+    }
+
+    // A bare Package.swift is built by swift, or for iOS by xcodebuild opening it
+    let is_package = swift_package::is_package(Path::new(&project_dir));
+    let swift_build = is_package && !swift_package::targets_ios(Path::new(&project_dir));
+    let project_file = match is_package {
+        true => PathBuf::from(&project_dir).join("Package.swift"),
+        false => find_project_file(&project_dir)?,
+    };
+
+    let is_workspace = project_file.extension().map_or(false, |ext| ext == "xcworkspace");
+
+    // Determine scheme (use provided or default to project name)
+    let build_scheme = scheme.unwrap_or_else(|| match is_package {
+        true => swift_package::package_name(Path::new(&project_dir)),
+        false => project_file.file_stem()
+            .and_then(|s| s.to_str())
+            .unwrap_or("NocurTestApp")
+            .to_string(),
+    });
+
+    // Tuist generates the project, so there's nothing to check against before it runs
+    let configuration = match configuration {
+        Some(configuration) if is_package => match swift_package::swift_configuration(&configuration)? {
+            "release" => "Release".to_string(),
+            _ => DEFAULT_CONFIGURATION.to_string(),
+        },
+        Some(configuration) if !is_tuist_project => {
+            validate_configuration(project_file.clone(), is_workspace, configuration).await?
+        }
+        configuration => configuration.unwrap_or_else(|| DEFAULT_CONFIGURATION.to_string()),
+    };
+
+    emit_build_event(&app_handle, "output", msg!("build.project", path = project_file.display()));
+    emit_build_event(&app_handle, "output", msg!("build.scheme", scheme = build_scheme));
+    if configuration != DEFAULT_CONFIGURATION {
+        emit_build_event(&app_handle, "output", msg!("build.configuration", configuration = configuration));
+    }
+
+    let (destination, is_physical_device) = build_destination(&app_handle, device.as_ref());
+    let signing_settings = signing.settings();
+    if !signing_settings.is_empty() && !swift_build {
+        emit_build_event(&app_handle, "output", msg!("build.signing_overrides", settings = signing_settings.join(", ")));
+    }
+
+    // Build output path - we'll use a consistent path for both Tuist and regular builds
+    let derived_data_path = paths::derived_data_dir(Path::new(&project_dir));
+
+    // DerivedData from another Xcode tends to fail the build on stale module caches
+    let xcode_version = tauri::async_runtime::spawn_blocking(build_history::xcode_version)
+        .await
+        .ok()
+        .flatten();
+    if let (Some(current), Some(previous)) = (xcode_version.as_deref(), build_history::last_xcode_version(&project_dir)) {
+        if previous != current {
+            emit_build_event(&app_handle, "warning", msg!("build.xcode_changed", previous = previous, current = current));
+            let auto_clean = read_user_preferences().map(|p| p.auto_clean_on_xcode_change).unwrap_or(false);
+            if auto_clean {
+                emit_build_event(&app_handle, "output", msg!("build.cleaning_stale_caches"));
+                if let Err(e) = build_history::clean_compiler_caches(&derived_data_path) {
+                    emit_build_event(&app_handle, "warning", msg!("build.clean_failed", error = e));
+                }
+            }
+        }
+    }
+    
+    // Offline, package resolution hangs until xcodebuild gives up; build against
+    // the checkouts already on disk instead
+    let offline = tauri::async_runtime::spawn_blocking(network::is_offline).await.unwrap_or(false);
+    if offline {
+        emit_offline_build_event(&app_handle, msg!("build.offline_cached_packages"));
+    }
+
+    // Build command - use tuist build for Tuist projects (handles generation + caching)
+    let mut cmd;
+    
+    if is_tuist_project {
+        emit_build_event(&app_handle, "output", msg!("build.tuist_detected"));
         
         cmd = Command::new("tuist");
-        cmd.args(["build", "--generate", &build_scheme]);
-        cmd.args(["--build-output-path", &format!("{}/Build/Products", derived_data_path)]);
+        cmd.args(["build", "--generate", &build_scheme, "--configuration", &configuration]);
+        cmd.arg("--build-output-path").arg(derived_data_path.join("Build/Products"));
         cmd.arg("--");
         cmd.args(["-destination", &destination]);
-        cmd.args(["-derivedDataPath", &derived_data_path]);
+        cmd.arg("-derivedDataPath").arg(&derived_data_path);
         
         // Add -allowProvisioningUpdates for physical devices
         if is_physical_device {
             cmd.arg("-allowProvisioningUpdates");
         }
+        if offline {
+            cmd.arg("-skipPackageUpdates");
+        }
+        signing.apply(&mut cmd);
+    } else if swift_build {
+        emit_build_event(&app_handle, "output", msg!("build.swift_package_detected", tool = "swift build"));
+        cmd = swift_package::swift_build_command(&configuration, &derived_data_path, offline)?;
     } else {
         // Regular xcodebuild for non-Tuist projects
         cmd = Command::new("xcodebuild");
 
         if is_workspace {
             cmd.arg("-workspace").arg(&project_file);
+        } else if is_package {
+            // Run in the package's directory, xcodebuild opens Package.swift itself
+            emit_build_event(&app_handle, "output", msg!("build.swift_package_detected", tool = "xcodebuild"));
         } else {
             cmd.arg("-project").arg(&project_file);
         }
 
         cmd.args([
             "-scheme", &build_scheme,
-            "-configuration", "Debug",
+            "-configuration", &configuration,
             "-destination", &destination,
-            "-derivedDataPath", &format!("{}/DerivedData", project_dir),
         ]);
+        cmd.arg("-derivedDataPath").arg(&derived_data_path);
 
         // Add -allowProvisioningUpdates for physical devices (automatic code signing)
         if is_physical_device {
             cmd.arg("-allowProvisioningUpdates");
         }
+        if offline {
+            cmd.arg("-skipPackageUpdates");
+        }
+        signing.apply(&mut cmd);
 
         cmd.arg("build");
     }
@@ -570,12 +1479,29 @@ async fn build_project(
     cmd.current_dir(&project_dir);
     cmd.stdout(Stdio::piped());
     cmd.stderr(Stdio::piped());
-
-    let build_tool = if is_tuist_project { "tuist build" } else { "xcodebuild" };
-    emit_build_event(&app_handle, "output", &format!("Starting {}...", build_tool));
+    // Its own process group, so `cancel_build` stops the compilers it spawns too
+    #[cfg(unix)]
+    std::os::unix::process::CommandExt::process_group(&mut cmd, 0);
+
+    let build_tool = if is_tuist_project {
+        "tuist build"
+    } else if swift_build {
+        "swift build"
+    } else {
+        "xcodebuild"
+    };
+    emit_build_event(&app_handle, "output", msg!("build.starting_tool", tool = build_tool));
     
     let mut child = cmd.spawn()
         .map_err(|e| format!("Failed to start {}: {}", build_tool, e))?;
+    // So `cancel_build` and `cancel_run` can stop it
+    if let Some(build_state) = app_handle.try_state::<BuildState>() {
+        build_state.cancelled.store(false, Ordering::SeqCst);
+        *build_state.child_pid.write().unwrap_or_else(|e| e.into_inner()) = Some(child.id());
+    }
+    if let Some(locks) = app_handle.try_state::<run_lock::RunLocks>() {
+        locks.set_build_pid(&project_dir, Some(child.id()));
+    }
 
     // Stream stdout
     let stdout = child.stdout.take().ok_or("Failed to capture stdout")?;
@@ -596,10 +1522,10 @@ async fn build_project(
                 if trimmed.starts_with("Compiling") || trimmed.starts_with("Compile") {
                     // Extract filename from compile line
                     if let Some(file) = trimmed.split_whitespace().last() {
-                        emit_build_event(&app_stdout, "output", &format!("Compiling {}", file));
+                        emit_build_event(&app_stdout, "output", msg!("build.compiling_file", file = file));
                     }
                 } else if trimmed.starts_with("Linking") || trimmed.starts_with("Link") {
-                    emit_build_event(&app_stdout, "output", "Linking...");
+                    emit_build_event(&app_stdout, "output", msg!("build.linking"));
                 } else if trimmed.contains(": error:") {
                     emit_build_event(&app_stdout, "error", trimmed);
                 } else if trimmed.contains(": warning:") {
@@ -607,13 +1533,13 @@ async fn build_project(
                 } else if trimmed.starts_with("Build") || trimmed.contains("BUILD") {
                     emit_build_event(&app_stdout, "output", trimmed);
                 } else if trimmed.starts_with("CodeSign") || trimmed.starts_with("Signing") {
-                    emit_build_event(&app_stdout, "output", "Signing...");
+                    emit_build_event(&app_stdout, "output", msg!("build.signing"));
                 } else if trimmed.starts_with("CompileSwiftSources") {
-                    emit_build_event(&app_stdout, "output", "Compiling Swift sources...");
+                    emit_build_event(&app_stdout, "output", msg!("build.compiling_swift"));
                 } else if trimmed.starts_with("ProcessInfoPlistFile") {
-                    emit_build_event(&app_stdout, "output", "Processing Info.plist...");
+                    emit_build_event(&app_stdout, "output", msg!("build.processing_info_plist"));
                 } else if trimmed.starts_with("PhaseScript") {
-                    emit_build_event(&app_stdout, "output", "Running build phase scripts...");
+                    emit_build_event(&app_stdout, "output", msg!("build.running_scripts"));
                 }
             }
         }
@@ -643,74 +1569,635 @@ async fn build_project(
     // Wait for process
     let status = child.wait()
         .map_err(|e| format!("Failed to wait for xcodebuild: {}", e))?;
+    if let Some(locks) = app_handle.try_state::<run_lock::RunLocks>() {
+        locks.set_build_pid(&project_dir, None);
+    }
 
     let stdout_output = stdout_handle.join().unwrap_or_default();
     let stderr_output = stderr_handle.join().unwrap_or_default();
 
-    let build_time = start_time.elapsed().as_secs_f64();
+    // Clearing the PID under its lock settles whether a cancel got in first
+    let cancelled = app_handle.try_state::<BuildState>().is_some_and(|build_state| {
+        let mut child_pid = build_state.child_pid.write().unwrap_or_else(|e| e.into_inner());
+        *child_pid = None;
+        build_state.cancelled.swap(false, Ordering::SeqCst)
+    });
+    let build_duration = start_time.elapsed();
+    if cancelled {
+        // `cancel_build` emits the "cancelled" event once this returns
+        return Ok(BuildResult {
+            build_time: Some(build_duration.as_secs_f64()),
+            derived_data_path: Some(derived_data_path.to_string_lossy().to_string()),
+            cancelled: true,
+            ..BuildResult::failed(format!("{}\n{}", stdout_output, stderr_output), Vec::new(), None)
+        });
+    }
+    let build_time = build_duration.as_secs_f64();
     let all_output = format!("{}\n{}", stdout_output, stderr_output);
-    let (errors, warnings) = parse_build_errors(&all_output);
+    let (mut errors, warning_details) = parse_build_errors(&all_output, Path::new(&project_dir));
 
     let success = status.success();
+    if !success {
+        signing::classify(&mut errors);
+    }
+
+    let build_id = app_handle.try_state::<event_journal::EventJournal>().map(|journal| journal.current_build());
+    let retention = read_user_preferences()
+        .ok()
+        .and_then(|prefs| prefs.build_log_retention)
+        .unwrap_or(build_logs::DEFAULT_RETENTION);
+    let redacted_output = redaction::redact(&all_output);
+    let kept_log = build_logs::write(&project_dir, build_id.as_deref(), &redacted_output, retention)
+        .map_err(|e| log::warn!("Failed to keep the build log: {}", e))
+        .ok();
+    let failure = match (&kept_log, success) {
+        (Some((_, log_path)), false) => Some(build_failure::index(log_path, &redacted_output, &errors)),
+        _ => None,
+    };
+    let log_id = kept_log.map(|(log_id, _)| log_id);
+    let session_id = app_handle.state::<Mutex<ClaudeState>>().lock().get_current_session_id();
+    let finished = build_history::FinishedBuild {
+        session_id,
+        succeeded: success,
+        duration_secs: build_time,
+        error_count: errors.len(),
+        build_id: build_id.clone(),
+        failure,
+        scheme: Some(build_scheme.clone()),
+        device: device.as_ref().map(|d| d.name.clone()),
+        log_id: log_id.clone(),
+    };
+    if let Err(e) = build_history::record_run(&project_dir, finished) {
+        log::warn!("Failed to record build run: {}", e);
+    }
 
     if success {
-        emit_build_event(&app_handle, "completed", &format!("Build succeeded in {:.1}s", build_time));
-
-        // Find the built app - check both iphoneos (physical) and iphonesimulator paths
-        let sdk_suffix = if is_physical_device { "iphoneos" } else { "iphonesimulator" };
-        let derived_data = format!("{}/DerivedData/Build/Products/Debug-{}", project_dir, sdk_suffix);
-        let app_path = std::fs::read_dir(&derived_data)
-            .ok()
-            .and_then(|entries| {
-                entries
-                    .filter_map(|e| e.ok())
-                    .find(|e| e.path().extension().map_or(false, |ext| ext == "app"))
-                    .map(|e| e.path().to_string_lossy().to_string())
-            });
+        emit_build_event(&app_handle, "completed", msg!("build.succeeded", seconds = format!("{:.1}", build_time)));
+        notifications::notify_completion(
+            &app_handle,
+            build_duration,
+            &msg!("build.notify_succeeded", duration = notifications::format_duration(build_duration)).text,
+            notifications::CompletedItem::new("build", build_id.clone()),
+        );
+
+        if let Some(version) = xcode_version.as_deref() {
+            if let Err(e) = build_history::record_success(&project_dir, version) {
+                log::warn!("Failed to record build history: {}", e);
+            }
+        }
 
-        // Get bundle ID from Info.plist
-        let bundle_id = app_path.as_ref().and_then(|path| {
-            let plist_path = format!("{}/Info.plist", path);
-            std::fs::read(&plist_path).ok().and_then(|data| {
-                plist::from_bytes::<plist::Dictionary>(&data).ok()
-            }).and_then(|dict| {
-                dict.get("CFBundleIdentifier").and_then(|v| v.as_string()).map(String::from)
-            })
-        });
+        // A package builds libraries and tools, not an app
+        let (app_path, bundle_id) = match is_package {
+            true => (None, None),
+            false => find_built_app(&project_dir, &configuration, is_physical_device),
+        };
 
         Ok(BuildResult {
             success: true,
             output: all_output,
             errors: vec![],
-            warnings,
+            warnings: warning_details.len() as u32,
+            warning_details,
             build_time: Some(build_time),
             app_path,
             bundle_id,
+            derived_data_path: Some(derived_data_path.to_string_lossy().to_string()),
+            launch: None,
+            bundle_id_correction: None,
+            cancelled: false,
+            crash_loop: None,
+            log_id,
         })
     } else {
-        emit_build_event(&app_handle, "completed", &format!("Build failed with {} error(s)", errors.len()));
+        emit_build_event(&app_handle, "completed", msg!("build.failed_with_errors", count = errors.len()));
+        notifications::notify_completion(
+            &app_handle,
+            build_duration,
+            &msg!(
+                "build.notify_failed",
+                duration = notifications::format_duration(build_duration),
+                count = errors.len(),
+            )
+            .text,
+            notifications::CompletedItem::new("build", build_id.clone()),
+        );
 
         Ok(BuildResult {
-            success: false,
-            output: all_output,
-            errors,
-            warnings,
+            warnings: warning_details.len() as u32,
+            warning_details,
             build_time: Some(build_time),
-            app_path: None,
-            bundle_id: None,
+            derived_data_path: Some(derived_data_path.to_string_lossy().to_string()),
+            log_id,
+            ..BuildResult::failed(all_output, errors, None)
+        })
+    }
+}
+
+traced! {
+/// Check whether the last build of a project can be installed on a physical device
+#[tauri::command]
+async fn preflight_device_install(
+    project_path: String,
+    device: DeviceInfo,
+) -> Result<Vec<device_preflight::PreflightFinding>, String> {
+    if device.device_type != DeviceType::Physical {
+        return Ok(Vec::new());
+    }
+    let (app_path, bundle_id) = find_built_app(&project_path, DEFAULT_CONFIGURATION, true);
+    let app_path = app_path.ok_or("No device build found. Build the project for this device first.")?;
+    let bundle_id = bundle_id.ok_or("Built app has no bundle ID")?;
+    let devicectl_id = device.core_device_id.clone().unwrap_or_else(|| device.id.clone());
+    Ok(device_preflight::preflight(&app_path, &bundle_id, &device.id, &devicectl_id))
+}
+}
+
+traced! {
+/// `xcodebuild clean` for the scheme and configuration a build would use, and
+/// with `deep` also delete the project's DerivedData: its cache under
+/// ~/.nocur and a legacy `<project>/DerivedData`. A deep clean goes ahead
+/// when `xcodebuild clean` fails, as broken build state is what it's for.
+/// With `dry_run`, report what would be deleted instead.
+#[tauri::command]
+async fn clean_project(
+    project_path: String,
+    scheme: Option<String>,
+    configuration: Option<String>,
+    deep: Option<bool>,
+    dry_run: Option<bool>,
+    app_handle: tauri::AppHandle,
+) -> Result<dry_run::Outcome<CleanResult>, CommandError> {
+    let deep = deep.unwrap_or(false);
+    let CleanPlan { project_file, is_workspace, scheme, configuration, derived_data_path, caches } =
+        plan_clean(&project_path, scheme, configuration, deep)?;
+    if dry_run.unwrap_or(false) {
+        let mut paths = clean_products(&derived_data_path, &configuration);
+        paths.retain(|product| !caches.iter().any(|cache| product.starts_with(cache)));
+        paths.extend(caches);
+        return Ok(dry_run::Outcome::DryRun(dry_run::DryRunReport::deleting(paths)));
+    }
+
+    // Not while a run of the project is building into the same DerivedData
+    let _ticket = app_handle
+        .state::<run_lock::RunLocks>()
+        .acquire(&app_handle, &project_path, false)
+        .await
+        .map_err(CommandError::RunInProgress)?;
+    let start_time = Instant::now();
+
+    emit_build_event(&app_handle, "started", msg!("build.clean_started", scheme = scheme));
+    emit_build_event(&app_handle, "output", msg!("build.project", path = project_file.display()));
+
+    let app = app_handle.clone();
+    let project_dir = project_path.clone();
+    let result = tauri::async_runtime::spawn_blocking(move || {
+        let size_before = storage::path_size(&derived_data_path);
+
+        let mut cmd = Command::new("xcodebuild");
+        if is_workspace {
+            cmd.arg("-workspace").arg(&project_file);
+        } else {
+            cmd.arg("-project").arg(&project_file);
+        }
+        cmd.args(["-scheme", &scheme, "-configuration", &configuration]);
+        cmd.arg("-derivedDataPath").arg(&derived_data_path);
+        cmd.arg("clean");
+        cmd.current_dir(&project_dir);
+
+        emit_build_event(&app, "output", msg!("build.starting_tool", tool = "xcodebuild clean"));
+        let clean_error = match cmd.output() {
+            Ok(output) if output.status.success() => None,
+            Ok(output) => {
+                let stderr = String::from_utf8_lossy(&output.stderr);
+                let stdout = String::from_utf8_lossy(&output.stdout);
+                let error = stderr
+                    .lines()
+                    .chain(stdout.lines())
+                    .find(|line| line.contains("error"))
+                    .unwrap_or("exited with an error")
+                    .trim()
+                    .to_string();
+                Some(error)
+            }
+            Err(e) => Some(e.to_string()),
+        };
+        match clean_error {
+            Some(error) if deep => emit_build_event(&app, "warning", msg!("build.clean_tool_failed", error = error)),
+            Some(error) => return Err(format!("xcodebuild clean failed: {}", error)),
+            None => {}
+        }
+
+        if !deep {
+            let freed = size_before.saturating_sub(storage::path_size(&derived_data_path));
+            return Ok((freed, Vec::new()));
+        }
+        let mut freed = 0;
+        let mut removed = Vec::new();
+        for cache in caches.iter().filter(|cache| cache.exists()) {
+            emit_build_event(&app, "output", msg!("build.clean_removing", path = cache.display()));
+            freed += storage::remove_path(cache)?;
+            removed.push(cache.to_string_lossy().to_string());
+        }
+        Ok((freed, removed))
+    })
+    .await
+    .map_err(|e| format!("Clean task failed: {}", e))?;
+
+    let (bytes_freed, removed) = match result {
+        Ok(result) => result,
+        Err(e) => {
+            emit_build_event(&app_handle, "error", msg!("build.clean_error", error = e));
+            return Err(e.into());
+        }
+    };
+    let clean_time = start_time.elapsed().as_secs_f64();
+    emit_build_event(
+        &app_handle,
+        "completed",
+        msg!(
+            "build.clean_finished",
+            seconds = format!("{:.1}", clean_time),
+            megabytes = format!("{:.1}", bytes_freed as f64 / (1024.0 * 1024.0)),
+        ),
+    );
+    Ok(dry_run::Outcome::Applied(CleanResult { bytes_freed, clean_time, removed }))
+}
+}
+
+/// What `clean_project` cleans
+struct CleanPlan {
+    project_file: PathBuf,
+    is_workspace: bool,
+    scheme: String,
+    configuration: String,
+    derived_data_path: PathBuf,
+    /// The build caches a deep clean deletes; none otherwise
+    caches: Vec<PathBuf>,
+}
+
+/// Resolve what `clean_project` cleans, for it and its dry run
+fn plan_clean(project_path: &str, scheme: Option<String>, configuration: Option<String>, deep: bool) -> Result<CleanPlan, String> {
+    let project_file = find_project_file(project_path)?;
+    let is_workspace = project_file.extension().is_some_and(|ext| ext == "xcworkspace");
+    let scheme = scheme.or_else(|| schemes::saved_scheme(project_path)).unwrap_or_else(|| {
+        project_file.file_stem().and_then(|s| s.to_str()).unwrap_or("NocurTestApp").to_string()
+    });
+    let configuration = configuration.unwrap_or_else(|| DEFAULT_CONFIGURATION.to_string());
+    let derived_data_path = paths::derived_data_dir(Path::new(project_path));
+    let caches = match deep {
+        // Not there yet when the project has never been built
+        true => [derived_data_path.clone(), paths::legacy_derived_data_dir(Path::new(project_path))]
+            .into_iter()
+            .filter(|cache| cache.exists())
+            .collect(),
+        false => Vec::new(),
+    };
+    Ok(CleanPlan { project_file, is_workspace, scheme, configuration, derived_data_path, caches })
+}
+
+/// The configuration's build products, e.g. `Build/Products/Debug-iphonesimulator`.
+/// `xcodebuild clean` decides what it clears; these are what a dry run
+/// expects it to.
+fn clean_products(derived_data_path: &Path, configuration: &str) -> Vec<PathBuf> {
+    let prefix = format!("{}-", configuration);
+    std::fs::read_dir(derived_data_path.join("Build").join("Products"))
+        .into_iter()
+        .flatten()
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| {
+            path.file_name()
+                .map(|name| name.to_string_lossy())
+                .is_some_and(|name| name == configuration || name.starts_with(&prefix))
         })
+        .collect()
+}
+
+traced! {
+/// Archive the scheme for generic iOS and export an IPA for `export_method`
+/// (development by default). Returns both paths; a failure to sign comes back
+/// as categorized errors, like a build's. See `archive`.
+#[tauri::command]
+async fn archive_project(
+    project_path: String,
+    scheme: Option<String>,
+    export_method: Option<archive::ExportMethod>,
+    development_team: Option<String>,
+    app_handle: tauri::AppHandle,
+) -> Result<archive::ArchiveResult, CommandError> {
+    // Not while a run of the project is building
+    let _ticket = app_handle
+        .state::<run_lock::RunLocks>()
+        .acquire(&app_handle, &project_path, false)
+        .await
+        .map_err(CommandError::RunInProgress)?;
+    let project_file = find_project_file(&project_path)?;
+    let is_workspace = project_file.extension().is_some_and(|ext| ext == "xcworkspace");
+    let scheme = scheme.or_else(|| schemes::saved_scheme(&project_path)).unwrap_or_else(|| {
+        project_file.file_stem().and_then(|s| s.to_str()).unwrap_or("NocurTestApp").to_string()
+    });
+    let max_age_hours = read_user_preferences()
+        .ok()
+        .and_then(|prefs| prefs.archive_max_age_hours)
+        .unwrap_or(archive::DEFAULT_MAX_AGE_HOURS);
+    let request = archive::ArchiveRequest {
+        project_path,
+        project_file,
+        is_workspace,
+        scheme,
+        export_method: export_method.unwrap_or_default(),
+        signing: signing::SigningOverrides::new(development_team, None)?,
+        max_age: std::time::Duration::from_secs(max_age_hours * 60 * 60),
+    };
+    let handle = app_handle.clone();
+    let result = tauri::async_runtime::spawn_blocking(move || archive::archive(&handle, &request))
+        .await
+        .map_err(|e| format!("Archive task failed: {}", e))??;
+    Ok(result)
+}
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TestRunResult {
+    /// Built and every test passed
+    pub success: bool,
+    pub counts: test_results::TestCounts,
+    pub failures: Vec<test_results::TestFailure>,
+    /// The tests didn't compile; `build_errors` says why
+    pub build_failed: bool,
+    pub build_errors: Vec<BuildError>,
+    pub test_time: f64,
+    pub output: String,
+    /// The run was stopped by `cancel_build`
+    pub cancelled: bool,
+}
+
+traced! {
+/// `xcodebuild test` for the scheme, on `device` or the default simulator.
+/// `only_testing` limits it to tests in `-only-testing` form, e.g.
+/// "AppTests/LoginTests/testInvalidPassword", to re-run a failure. Progress
+/// goes out as build events; a compile failure comes back as `build_errors`.
+#[tauri::command]
+async fn run_tests(
+    project_path: String,
+    scheme: Option<String>,
+    device: Option<DeviceInfo>,
+    only_testing: Option<Vec<String>>,
+    app_handle: tauri::AppHandle,
+) -> Result<TestRunResult, CommandError> {
+    // Not while a run of the project is building into the same DerivedData
+    let _ticket = app_handle
+        .state::<run_lock::RunLocks>()
+        .acquire(&app_handle, &project_path, false)
+        .await
+        .map_err(CommandError::RunInProgress)?;
+    let start_time = Instant::now();
+
+    let project_file = find_project_file(&project_path)?;
+    let is_workspace = project_file.extension().is_some_and(|ext| ext == "xcworkspace");
+    let scheme = scheme.or_else(|| schemes::saved_scheme(&project_path)).unwrap_or_else(|| {
+        project_file.file_stem().and_then(|s| s.to_str()).unwrap_or("NocurTestApp").to_string()
+    });
+    let only_testing = only_testing.unwrap_or_default();
+
+    emit_build_event(&app_handle, "started", msg!("test.started", scheme = scheme));
+    emit_build_event(&app_handle, "output", msg!("build.project", path = project_file.display()));
+    if !only_testing.is_empty() {
+        emit_build_event(&app_handle, "output", msg!("test.only_testing", tests = only_testing.join(", ")));
+    }
+    let (destination, is_physical_device) = build_destination(&app_handle, device.as_ref());
+    let derived_data_path = paths::derived_data_dir(Path::new(&project_path));
+
+    let mut cmd = Command::new("xcodebuild");
+    if is_workspace {
+        cmd.arg("-workspace").arg(&project_file);
+    } else {
+        cmd.arg("-project").arg(&project_file);
+    }
+    cmd.args(["-scheme", &scheme, "-destination", &destination]);
+    cmd.arg("-derivedDataPath").arg(&derived_data_path);
+    for test in &only_testing {
+        cmd.arg(format!("-only-testing:{}", test));
+    }
+    if is_physical_device {
+        cmd.arg("-allowProvisioningUpdates");
+    }
+    cmd.arg("test");
+    cmd.current_dir(&project_path);
+    cmd.stdout(Stdio::piped());
+    cmd.stderr(Stdio::piped());
+    // Its own process group, so `cancel_build` stops the runner it spawns too
+    #[cfg(unix)]
+    std::os::unix::process::CommandExt::process_group(&mut cmd, 0);
+
+    emit_build_event(&app_handle, "output", msg!("build.starting_tool", tool = "xcodebuild test"));
+    let mut child = cmd.spawn().map_err(|e| format!("Failed to start xcodebuild test: {}", e))?;
+    if let Some(build_state) = app_handle.try_state::<BuildState>() {
+        build_state.cancelled.store(false, Ordering::SeqCst);
+        *build_state.child_pid.write().unwrap_or_else(|e| e.into_inner()) = Some(child.id());
+    }
+    if let Some(locks) = app_handle.try_state::<run_lock::RunLocks>() {
+        locks.set_build_pid(&project_path, Some(child.id()));
+    }
+
+    let stdout = child.stdout.take().ok_or("Failed to capture stdout")?;
+    let stderr = child.stderr.take().ok_or("Failed to capture stderr")?;
+    let app_stdout = app_handle.clone();
+    let stdout_handle = std::thread::spawn(move || {
+        let mut output = String::new();
+        for line in BufReader::new(stdout).lines().map_while(Result::ok) {
+            output.push_str(&line);
+            output.push('\n');
+            let trimmed = line.trim();
+            match test_results::case_result(trimmed) {
+                Some((test, "passed")) => emit_build_event(&app_stdout, "output", msg!("test.case_passed", test = test)),
+                Some((test, "failed")) => emit_build_event(&app_stdout, "error", msg!("test.case_failed", test = test)),
+                Some((test, _)) => emit_build_event(&app_stdout, "output", msg!("test.case_skipped", test = test)),
+                None if trimmed.contains(": error:") => emit_build_event(&app_stdout, "error", trimmed),
+                None if trimmed.starts_with("Compiling") || trimmed.starts_with("Compile") => {
+                    if let Some(file) = trimmed.split_whitespace().last() {
+                        emit_build_event(&app_stdout, "output", msg!("build.compiling_file", file = file));
+                    }
+                }
+                None => {}
+            }
+        }
+        output
+    });
+    let stderr_handle = std::thread::spawn(move || {
+        let mut output = String::new();
+        for line in BufReader::new(stderr).lines().map_while(Result::ok) {
+            output.push_str(&line);
+            output.push('\n');
+        }
+        output
+    });
+
+    let status = child.wait().map_err(|e| format!("Failed to wait for xcodebuild test: {}", e))?;
+    if let Some(locks) = app_handle.try_state::<run_lock::RunLocks>() {
+        locks.set_build_pid(&project_path, None);
+    }
+    let output = format!(
+        "{}\n{}",
+        stdout_handle.join().unwrap_or_default(),
+        stderr_handle.join().unwrap_or_default()
+    );
+    let cancelled = app_handle.try_state::<BuildState>().is_some_and(|build_state| {
+        *build_state.child_pid.write().unwrap_or_else(|e| e.into_inner()) = None;
+        build_state.cancelled.swap(false, Ordering::SeqCst)
+    });
+    let test_duration = start_time.elapsed();
+    let test_time = test_duration.as_secs_f64();
+
+    let (counts, failures) = test_results::parse(&output);
+    // xcodebuild prints "TEST BUILD FAILED" when the tests didn't compile; no test ran
+    let no_tests_ran = counts.passed + counts.failed + counts.skipped == 0;
+    let build_failed =
+        !cancelled && (output.contains("** TEST BUILD FAILED **") || (!status.success() && no_tests_ran));
+    let build_errors = if build_failed {
+        parse_build_errors(&output, Path::new(&project_path)).0
+    } else {
+        Vec::new()
+    };
+
+    let build_id = app_handle.try_state::<event_journal::EventJournal>().map(|journal| journal.current_build());
+    let notify = |body: String| {
+        notifications::notify_completion(
+            &app_handle,
+            test_duration,
+            &body,
+            notifications::CompletedItem::new("tests", build_id.clone()),
+        );
+    };
+
+    // After a cancel, `cancel_build` emits the "cancelled" event
+    if build_failed {
+        emit_build_event(&app_handle, "completed", msg!("test.build_failed", count = build_errors.len()));
+        notify(
+            msg!(
+                "test.notify_build_failed",
+                duration = notifications::format_duration(test_duration),
+                count = build_errors.len(),
+            )
+            .text,
+        );
+    } else if !cancelled {
+        emit_build_event(
+            &app_handle,
+            "completed",
+            msg!(
+                "test.finished",
+                seconds = format!("{:.1}", test_time),
+                passed = counts.passed,
+                failed = counts.failed,
+                skipped = counts.skipped,
+            ),
+        );
+        notify(
+            msg!(
+                "test.notify_finished",
+                duration = notifications::format_duration(test_duration),
+                passed = counts.passed,
+                failed = counts.failed,
+            )
+            .text,
+        );
     }
+
+    Ok(TestRunResult {
+        success: status.success() && !cancelled && failures.is_empty(),
+        counts,
+        failures,
+        build_failed,
+        build_errors,
+        test_time,
+        output,
+        cancelled,
+    })
+}
+}
+
+traced! {
+/// Test failures (with their screenshots) and build issues from the
+/// `.xcresult` bundle at `path`, in either xcresulttool schema
+#[tauri::command]
+async fn inspect_xcresult(path: String) -> Result<xcresult::XcresultSummary, String> {
+    tokio::task::spawn_blocking(move || xcresult::inspect(Path::new(&path)))
+        .await
+        .map_err(|e| format!("xcresult task failed: {}", e))?
+}
 }
 
+traced! {
+/// Build, install and launch. With `auto_stream_logs` the matching log stream is
+/// started before the app launches and the result's `launch` says whether it did.
+/// The app is launched with `env_profile`'s variables from the project's
+/// `.nocur/env`, overridden by `launch_env`, and on a simulator with the
+/// launch arguments `launch_overrides` makes (see `launch_overrides`; they're
+/// reported back in `launch.launch_args`). Only one run per project goes at
+/// a time: another fails with `RunInProgress`, or with `queue` waits its turn.
+/// A `headless` run doesn't bring up the Simulator window and pauses the live
+/// view's frames until it's done.
 #[tauri::command]
 async fn run_project(
     project_path: Option<String>,
     scheme: Option<String>,
+    configuration: Option<String>,
     device: Option<DeviceInfo>,
+    auto_stream_logs: Option<bool>,
+    env_profile: Option<String>,
+    launch_env: Option<std::collections::HashMap<String, String>>,
+    queue: Option<bool>,
+    launch_overrides: Option<launch_overrides::LaunchOverrides>,
+    headless: Option<bool>,
     app_handle: tauri::AppHandle,
-) -> Result<BuildResult, String> {
+) -> Result<BuildResult, CommandError> {
+    let headless = headless.unwrap_or(false);
+    let _headless_run = headless.then(frame_stream::HeadlessRun::start);
+    let ticket = app_handle
+        .state::<run_lock::RunLocks>()
+        .acquire(&app_handle, project_path.as_deref().unwrap_or_default(), queue.unwrap_or(false))
+        .await
+        .map_err(CommandError::RunInProgress)?;
+    if let Some(package_dir) = project_path.as_deref().filter(|p| swift_package::is_package(Path::new(p))) {
+        return Err(CommandError::Unsupported(format!(
+            "{} is a Swift package: packages can't be installed to a device or simulator, only built",
+            package_dir
+        )));
+    }
+    let auto_stream_logs = auto_stream_logs.unwrap_or(false);
+    // Loaded up front so a bad profile fails before the build, not after it
+    let launch_env = env_profiles::launch_environment(project_path.as_deref(), env_profile.as_deref(), launch_env.as_ref())?;
+    let launch_args = match launch_overrides {
+        Some(overrides) => launch_overrides::launch_arguments(&launch_overrides::resolve(project_path.as_deref(), overrides)?)?,
+        None => Vec::new(),
+    };
+
+    // An untrusted device or one without Developer Mode fails every install, so
+    // don't spend a build on it
+    if let Some(physical) = device.as_ref().filter(|d| d.device_type == DeviceType::Physical) {
+        let devicectl_id = physical.core_device_id.clone().unwrap_or_else(|| physical.id.clone());
+        let readiness = tauri::async_runtime::spawn_blocking(move || device_preflight::device_readiness(&devicectl_id))
+            .await
+            .map_err(|e| format!("Failed to check device readiness: {}", e))?;
+        for finding in &readiness.findings {
+            let event_type = if finding.is_blocking() { "error" } else { "warning" };
+            emit_build_event(&app_handle, event_type, finding.to_message());
+        }
+        if !readiness.ready {
+            let errors = readiness
+                .findings
+                .iter()
+                .filter(|f| f.is_blocking())
+                .map(|f| unlocated_diagnostic(&f.message))
+                .collect();
+            return Ok(BuildResult::failed(format!("Device {} isn't ready for development", physical.name), errors, None));
+        }
+    }
+
     // First, build the project
-    let build_result = build_project(project_path.clone(), scheme, device.clone(), app_handle.clone()).await?;
+    let build_result = build_project(project_path.clone(), scheme, configuration, device.clone(), None, None, app_handle.clone()).await?;
+    check_run_cancelled(&ticket)?;
 
     if !build_result.success {
         return Ok(build_result);
@@ -719,8 +2206,21 @@ async fn run_project(
     // Get app path and bundle ID from build result
     let app_path = build_result.app_path.clone()
         .ok_or("Build succeeded but app path not found")?;
-    let bundle_id = build_result.bundle_id.clone()
+    let mut bundle_id = build_result.bundle_id.clone()
         .ok_or("Build succeeded but bundle ID not found")?;
+    let mut bundle_id_correction = None;
+    let crash_loop_key = project_path.clone().unwrap_or_default();
+
+    // Relaunching an app that crashes on launch only piles up crash reports
+    if let Some(detected) = app_handle.state::<crash_loop::CrashLoops>().looping(&crash_loop_key, &bundle_id) {
+        emit_build_event(&app_handle, "error", msg!("simulator.crash_loop", bundle_id = bundle_id, count = detected.consecutive_crashes, summary = detected.summary));
+        let error = BuildError {
+            suggestion: Some("reset_crash_loop".to_string()),
+            ..unlocated_diagnostic(&format!("{} keeps crashing on launch: {}", bundle_id, detected.summary))
+        };
+        let output = format!("Not launching {}: it crashed on launch {} times in a row", bundle_id, detected.consecutive_crashes);
+        return Ok(BuildResult { crash_loop: Some(detected), ..BuildResult::failed(output, vec![error], Some(&build_result)) });
+    }
 
     // Determine if this is a physical device or simulator
     let is_physical_device = device.as_ref()
@@ -732,181 +2232,180 @@ async fn run_project(
     // For devicectl, use core_device_id (falls back to id if not available)
     let core_device_id = device.as_ref().map(|d| d.core_device_id.clone().unwrap_or_else(|| d.id.clone()));
 
+    let launch;
+    let mut crash_loop_detected = None;
     if is_physical_device {
         // Physical device: use devicectl for install and launch
         // devicectl requires the CoreDevice UUID, not the xcodebuild UDID
         let devicectl_id = core_device_id.ok_or("Device ID required for physical device")?;
         let device_name = device.as_ref().map(|d| d.name.as_str()).unwrap_or("unknown");
         
-        emit_build_event(&app_handle, "output", &format!("Physical device detected: {} (devicectl ID: {})", device_name, devicectl_id));
-        emit_build_event(&app_handle, "output", &format!("App path: {}", app_path));
+        emit_build_event(&app_handle, "output", msg!("device.detected", name = device_name, id = devicectl_id));
+        emit_build_event(&app_handle, "output", msg!("device.app_path", path = app_path));
         
         // Check device availability before attempting install
-        emit_build_event(&app_handle, "output", &format!("Checking device {} availability...", device_name));
+        emit_build_event(&app_handle, "output", msg!("device.checking", name = device_name));
         
         let device_check = check_physical_device_availability(&devicectl_id);
         match device_check {
             DeviceAvailability::Available => {
-                emit_build_event(&app_handle, "output", &format!("Device {} is connected and ready", device_name));
+                emit_build_event(&app_handle, "output", msg!("device.ready", name = device_name));
             }
             DeviceAvailability::TunnelUnavailable => {
-                emit_build_event(&app_handle, "warning", &format!("Device {} tunnel is not ready, attempting to connect...", device_name));
+                emit_build_event(&app_handle, "warning", msg!("device.tunnel_connecting", name = device_name));
                 // Give devicectl a chance to establish the tunnel
                 std::thread::sleep(std::time::Duration::from_secs(2));
             }
             DeviceAvailability::NotFound => {
-                emit_build_event(&app_handle, "error", &format!("Device {} not found. Make sure the device is connected via USB or on the same network.", device_name));
-                return Ok(BuildResult {
-                    success: false,
-                    output: format!("Device not found: {}", device_name),
-                    errors: vec![BuildError {
-                        file: None,
-                        line: None,
-                        column: None,
-                        message: format!("Device '{}' not found. Ensure it is connected via USB or on the same WiFi network and is unlocked.", device_name),
-                    }],
-                    warnings: build_result.warnings,
-                    build_time: build_result.build_time,
-                    app_path: Some(app_path),
-                    bundle_id: Some(bundle_id),
-                });
+                emit_build_event(&app_handle, "error", msg!("device.not_found", name = device_name));
+                let error = unlocated_diagnostic(&format!("Device '{}' not found. Ensure it is connected via USB or on the same WiFi network and is unlocked.", device_name));
+                return Ok(BuildResult::failed(format!("Device not found: {}", device_name), vec![error], Some(&build_result)));
             }
             DeviceAvailability::NotPaired => {
-                emit_build_event(&app_handle, "error", &format!("Device {} is not paired. Trust this computer on the device.", device_name));
-                return Ok(BuildResult {
-                    success: false,
-                    output: format!("Device not paired: {}", device_name),
-                    errors: vec![BuildError {
-                        file: None,
-                        line: None,
-                        column: None,
-                        message: format!("Device '{}' is not paired. Connect via USB and tap 'Trust' on the device.", device_name),
-                    }],
-                    warnings: build_result.warnings,
-                    build_time: build_result.build_time,
-                    app_path: Some(app_path),
-                    bundle_id: Some(bundle_id),
-                });
+                emit_build_event(&app_handle, "error", msg!("device.not_paired", name = device_name));
+                let error = unlocated_diagnostic(&format!("Device '{}' is not paired. Connect via USB and tap 'Trust' on the device.", device_name));
+                return Ok(BuildResult::failed(format!("Device not paired: {}", device_name), vec![error], Some(&build_result)));
             }
         }
         
-        emit_build_event(&app_handle, "output", &format!("Installing app to physical device {}...", device_name));
+        // Catch signing/provisioning problems before devicectl fails on them
+        let device_udid = device_id.as_deref().unwrap_or(&devicectl_id);
+        emit_build_event(&app_handle, "output", msg!("device.preflight"));
+        let findings = device_preflight::preflight(&app_path, &bundle_id, device_udid, &devicectl_id);
+        for finding in &findings {
+            let event_type = if finding.is_blocking() { "error" } else { "warning" };
+            emit_build_event(&app_handle, event_type, finding.to_message());
+        }
+        let blocking: Vec<BuildError> = findings
+            .iter()
+            .filter(|f| f.is_blocking())
+            .map(|f| unlocated_diagnostic(&f.message))
+            .collect();
+        if !blocking.is_empty() {
+            let output = format!("Install preflight failed for {}", device_name);
+            return Ok(BuildResult::failed(output, blocking, Some(&build_result)));
+        }
+
+        emit_build_event(&app_handle, "output", msg!("device.installing", name = device_name));
 
         // Install using devicectl with timeout and retry logic
         let max_retries = 2;
-        let mut install_success = false;
-        let mut last_error = String::new();
-        
+        let mut install_error = None;
+
         for attempt in 1..=max_retries {
             if attempt > 1 {
-                emit_build_event(&app_handle, "output", &format!("Retrying install (attempt {}/{})...", attempt, max_retries));
+                emit_build_event(&app_handle, "output", msg!("device.install_retry", attempt = attempt, max = max_retries));
                 std::thread::sleep(std::time::Duration::from_secs(2));
             }
             
-            emit_build_event(&app_handle, "output", &format!("Running: xcrun devicectl device install app --device {} {}", &devicectl_id, &app_path));
-            
-            let install_output = Command::new("xcrun")
-                .args(["devicectl", "device", "install", "app", "--device", &devicectl_id, &app_path, "--timeout", "120"])
-                .output()
-                .map_err(|e| format!("Failed to run devicectl install: {}", e))?;
-
-            let stdout = String::from_utf8_lossy(&install_output.stdout);
-            let stderr = String::from_utf8_lossy(&install_output.stderr);
+            emit_build_event(&app_handle, "output", msg!("device.running_command", command = format!("xcrun devicectl device install app --device {} {}", &devicectl_id, &app_path)));
             
-            if !stdout.is_empty() {
-                emit_build_event(&app_handle, "output", &format!("Install stdout: {}", stdout.lines().take(5).collect::<Vec<_>>().join(" | ")));
-            }
-
-            if install_output.status.success() {
-                emit_build_event(&app_handle, "output", "Install succeeded!");
-                install_success = true;
-                break;
-            } else {
-                last_error = stderr.to_string();
-                emit_build_event(&app_handle, "warning", &format!("Install stderr: {}", stderr.lines().take(3).collect::<Vec<_>>().join(" | ")));
-                
-                // Check for specific retryable errors
-                if stderr.contains("tunnel") || stderr.contains("connection") || stderr.contains("timed out") {
-                    emit_build_event(&app_handle, "warning", &format!("Install attempt {} failed (connection issue): {}", attempt, stderr.lines().next().unwrap_or(&stderr)));
-                    continue;
-                } else {
-                    // Non-retryable error, break immediately
+            match devicectl::install_app(&devicectl_id, &app_path, 120) {
+                Ok(installed) => {
+                    if !installed.stdout.is_empty() {
+                        emit_build_event(&app_handle, "output", msg!("device.install_stdout", output = installed.stdout.lines().take(5).collect::<Vec<_>>().join(" | ")));
+                    }
+                    if let Some(path) = installed.installation_path {
+                        log::info!("Installed {} to {}", bundle_id, path);
+                    }
+                    emit_build_event(&app_handle, "output", msg!("device.install_succeeded"));
+                    install_error = None;
                     break;
                 }
+                Err(e) => {
+                    emit_build_event(&app_handle, "warning", msg!("device.install_stderr", output = e.to_string()));
+
+                    // Check for specific retryable errors
+                    let text = e.text();
+                    let retryable = text.contains("tunnel") || text.contains("connection") || text.contains("timed out");
+                    if retryable {
+                        emit_build_event(&app_handle, "warning", msg!("device.install_attempt_failed", attempt = attempt, error = e.to_string()));
+                    }
+                    install_error = Some(e);
+                    if !retryable {
+                        // Non-retryable error, break immediately
+                        break;
+                    }
+                }
             }
         }
 
-        if !install_success {
-            let error_summary = parse_devicectl_error(&last_error);
-            emit_build_event(&app_handle, "error", &format!("Install failed: {}", error_summary));
-            return Ok(BuildResult {
-                success: false,
-                output: format!("Install failed: {}", error_summary),
-                errors: vec![BuildError {
-                    file: None,
-                    line: None,
-                    column: None,
-                    message: format!("Failed to install app on {}: {}", device_name, error_summary),
-                }],
-                warnings: build_result.warnings,
-                build_time: build_result.build_time,
-                app_path: Some(app_path),
-                bundle_id: Some(bundle_id),
-            });
+        if let Some(error) = install_error {
+            let error_summary = error.summary();
+            emit_build_event(&app_handle, "error", msg!("device.install_failed", error = error_summary));
+            let error = unlocated_diagnostic(&format!("Failed to install app on {}: {}", device_name, error_summary));
+            return Ok(BuildResult::failed(format!("Install failed: {}", error_summary), vec![error], Some(&build_result)));
         }
 
-        emit_build_event(&app_handle, "output", "Launching app on physical device...");
-        emit_build_event(&app_handle, "output", &format!("Running: xcrun devicectl device process launch --device {} {}", &devicectl_id, &bundle_id));
-
-        // Launch using devicectl with timeout
-        let launch_output = Command::new("xcrun")
-            .args(["devicectl", "device", "process", "launch", "--device", &devicectl_id, &bundle_id, "--timeout", "60"])
-            .output()
-            .map_err(|e| format!("Failed to run devicectl launch: {}", e))?;
+        let installed = install_check::check_device(&devicectl_id, &app_path, &bundle_id);
+        if let Some(correction) = check_installed_bundle_id(&app_handle, installed, &bundle_id) {
+            bundle_id = correction.installed.clone();
+            bundle_id_correction = Some(correction);
+        }
+        check_run_cancelled(&ticket)?;
 
-        let launch_stdout = String::from_utf8_lossy(&launch_output.stdout);
-        let launch_stderr = String::from_utf8_lossy(&launch_output.stderr);
-        
-        if !launch_stdout.is_empty() {
-            emit_build_event(&app_handle, "output", &format!("Launch stdout: {}", launch_stdout.lines().take(3).collect::<Vec<_>>().join(" | ")));
+        if !launch_args.is_empty() {
+            emit_build_event(&app_handle, "warning", msg!("device.launch_overrides_ignored"));
         }
+        emit_build_event(&app_handle, "output", msg!("device.launching"));
+
+        // devicectl only attaches a console to a process it launches, so a streamed
+        // launch goes through the console stream instead of a plain launch
+        let mut launch_info = LaunchInfo::default();
+        let launch_result = if auto_stream_logs {
+            emit_build_event(&app_handle, "output", msg!("device.running_command", command = format!("xcrun devicectl device process launch --console --device {} {}", &devicectl_id, &bundle_id)));
+            match launch_with_device_console(&app_handle, &devicectl_id, &bundle_id, &launch_env) {
+                Ok(()) => {
+                    launch_info.log_streaming = true;
+                    launch_info.log_backend = Some("devicectl".to_string());
+                    Ok(())
+                }
+                Err(e) => {
+                    emit_build_event(&app_handle, "warning", msg!("device.console_launch_failed", error = e));
+                    launch_info.log_error = Some(e);
+                    launch_on_device(&app_handle, &devicectl_id, &bundle_id, &launch_env).map(|pid| launch_info.pid = pid)
+                }
+            }
+        } else {
+            emit_build_event(&app_handle, "output", msg!("device.running_command", command = format!("xcrun devicectl device process launch --device {} {}", &devicectl_id, &bundle_id)));
+            launch_on_device(&app_handle, &devicectl_id, &bundle_id, &launch_env).map(|pid| launch_info.pid = pid)
+        };
 
-        if !launch_output.status.success() {
-            let stderr = launch_stderr;
-            let error_summary = parse_devicectl_error(&stderr);
-            emit_build_event(&app_handle, "error", &format!("Launch failed: {}", error_summary));
+        if let Err(error) = launch_result {
+            let error_summary = devicectl::friendly_message(&error);
+            emit_build_event(&app_handle, "error", msg!("device.launch_failed", error = error_summary));
+            let error = unlocated_diagnostic(&format!("Failed to launch app on {}: {}", device_name, error_summary));
             return Ok(BuildResult {
-                success: false,
-                output: format!("Launch failed: {}", error_summary),
-                errors: vec![BuildError {
-                    file: None,
-                    line: None,
-                    column: None,
-                    message: format!("Failed to launch app on {}: {}", device_name, error_summary),
-                }],
-                warnings: build_result.warnings,
-                build_time: build_result.build_time,
-                app_path: Some(app_path),
                 bundle_id: Some(bundle_id),
+                bundle_id_correction,
+                ..BuildResult::failed(format!("Launch failed: {}", error_summary), vec![error], Some(&build_result))
             });
         }
 
-        emit_build_event(&app_handle, "completed", &format!("App launched on device: {}", bundle_id));
+        emit_build_event(&app_handle, "completed", msg!("device.launched", bundle_id = bundle_id));
         
-        // Emit app-launched event so frontend can start log streaming
+        // Emit app-launched event so frontend can start log streaming if we didn't
         // Use devicectl_id for log streaming since it uses devicectl
-        let _ = app_handle.emit("app-launched", serde_json::json!({
-            "bundleId": bundle_id.clone(),
-            "deviceId": devicectl_id,
-            "deviceType": "physical",
-            "deviceName": device.as_ref().map(|d| d.name.clone()).unwrap_or_default()
-        }));
+        event_journal::emit_stamped(&app_handle, "app-launched", AppLaunched {
+            bundle_id: bundle_id.clone(),
+            executable_name: app_executable_name(&app_path),
+            device_id: Some(devicectl_id.clone()),
+            device_type: "physical".to_string(),
+            device_name: device.as_ref().map(|d| d.name.clone()).unwrap_or_default(),
+            pid: launch_info.pid,
+            log_streaming: launch_info.log_streaming,
+            log_backend: launch_info.log_backend.clone(),
+            launch_duration_ms: launch_info.launch_duration_ms,
+            regression: launch_info.launch_regression,
+            run_id: Some(ticket.run_id().to_string()),
+        });
+        launch = launch_info;
     } else {
         // Simulator: use simctl for install and launch
         let sim_target = device_id.as_deref().unwrap_or("booted");
 
         // Check if the target simulator is booted
-        emit_build_event(&app_handle, "output", "Checking simulator status...");
+        emit_build_event(&app_handle, "output", msg!("simulator.checking"));
 
         let list_output = Command::new("xcrun")
             .args(["simctl", "list", "devices", "booted", "-j"])
@@ -930,99 +2429,177 @@ async fn run_project(
                 sim_target
             };
             
-            emit_build_event(&app_handle, "output", &format!("Booting simulator {}...", boot_target));
+            emit_build_event(&app_handle, "output", msg!("simulator.booting", target = boot_target));
 
             let boot_output = Command::new("xcrun")
                 .args(["simctl", "boot", boot_target])
                 .output()
                 .map_err(|e| format!("Failed to boot simulator: {}", e))?;
 
+            let mut booted_target = boot_target;
             if !boot_output.status.success() {
                 // Try with a different simulator name as fallback
+                booted_target = "iPhone 15 Pro";
                 let boot_fallback = Command::new("xcrun")
-                    .args(["simctl", "boot", "iPhone 15 Pro"])
+                    .args(["simctl", "boot", booted_target])
                     .output()
                     .map_err(|e| format!("Failed to boot fallback simulator: {}", e))?;
 
                 if !boot_fallback.status.success() {
                     let stderr = String::from_utf8_lossy(&boot_fallback.stderr);
-                    emit_build_event(&app_handle, "error", &format!("Failed to boot simulator: {}", stderr));
+                    emit_build_event(&app_handle, "error", msg!("simulator.boot_failed", error = stderr));
                 }
             }
 
-            // Wait a moment for simulator to boot
-            emit_build_event(&app_handle, "output", "Waiting for simulator to boot...");
-            std::thread::sleep(std::time::Duration::from_secs(3));
+            emit_build_event(&app_handle, "output", msg!("simulator.waiting_for_boot"));
+            if let Err(e) = wait_for_simulator_boot(booted_target) {
+                emit_build_event(&app_handle, "warning", &e);
+            }
         }
         
-        // Always ensure Simulator app is open and visible (even if already booted)
-        let _ = Command::new("open")
-            .args(["-a", "Simulator"])
-            .output();
+        // Ensure Simulator app is open and visible (even if already booted);
+        // readiness came from bootstatus, so a headless run doesn't need it
+        if !headless {
+            let _ = Command::new("open")
+                .args(["-a", "Simulator"])
+                .output();
+        }
 
-        emit_build_event(&app_handle, "output", "Installing app to simulator...");
+        emit_build_event(&app_handle, "output", msg!("simulator.installing"));
 
         // Install to simulator using simctl
-        let install_output = Command::new("xcrun")
-            .args(["simctl", "install", sim_target, &app_path])
-            .output()
+        let install_output = run_simctl_with_retry(&app_handle, "Install", &["install", sim_target, &app_path], &[])
             .map_err(|e| format!("Failed to install app: {}", e))?;
 
         if !install_output.status.success() {
             let stderr = String::from_utf8_lossy(&install_output.stderr);
-            emit_build_event(&app_handle, "error", &format!("Install failed: {}", stderr));
-            return Ok(BuildResult {
-                success: false,
-                output: format!("Install failed: {}", stderr),
-                errors: vec![BuildError {
-                    file: None,
-                    line: None,
-                    column: None,
-                    message: stderr.to_string(),
-                }],
-                warnings: build_result.warnings,
-                build_time: build_result.build_time,
-                app_path: Some(app_path),
-                bundle_id: Some(bundle_id),
-            });
+            emit_build_event(&app_handle, "error", msg!("simulator.install_failed", error = stderr));
+            let error = unlocated_diagnostic(&stderr);
+            return Ok(BuildResult::failed(format!("Install failed: {}", stderr), vec![error], Some(&build_result)));
         }
 
-        emit_build_event(&app_handle, "output", "Launching app...");
+        let installed = install_check::check_simulator(sim_target, &app_path, &bundle_id);
+        if let Some(correction) = check_installed_bundle_id(&app_handle, installed, &bundle_id) {
+            bundle_id = correction.installed.clone();
+            bundle_id_correction = Some(correction);
+        }
+        check_run_cancelled(&ticket)?;
+
+        // Start the log stream first so nothing the app prints at startup is missed
+        let mut launch_info = LaunchInfo::default();
+        if auto_stream_logs {
+            let executable_name = app_executable_name(&app_path);
+            match start_simulator_log_stream(&app_handle, sim_target, &bundle_id, executable_name.as_deref()) {
+                Ok(()) => {
+                    launch_info.log_streaming = true;
+                    launch_info.log_backend = Some("simulator".to_string());
+                }
+                Err(e) => {
+                    emit_build_event(&app_handle, "warning", msg!("simulator.log_stream_failed", error = e));
+                    launch_info.log_error = Some(e);
+                }
+            }
+        }
+
+        // Time the launch by the app's first log line
+        let mut launch_watch = app_executable_name(&app_path).and_then(|executable_name| {
+            launch_timing::LaunchWatch::start(sim_target, &executable_name)
+                .map_err(|e| log::warn!("Not timing the launch: {}", e))
+                .ok()
+        });
+
+        emit_build_event(&app_handle, "output", msg!("simulator.launching"));
 
         // Launch the app
-        let launch_output = Command::new("xcrun")
-            .args(["simctl", "launch", sim_target, &bundle_id])
-            .output()
+        if let Some(watch) = launch_watch.as_mut() {
+            watch.launching();
+        }
+        let mut simctl_args = vec!["launch", sim_target, bundle_id.as_str()];
+        simctl_args.extend(launch_args.iter().map(String::as_str));
+        launch_info.launch_args = launch_args.clone();
+        let launch_output = run_simctl_with_retry(&app_handle, "Launch", &simctl_args, &env_profiles::simctl_child_env(&launch_env))
             .map_err(|e| format!("Failed to launch app: {}", e))?;
 
         if !launch_output.status.success() {
+            if launch_info.log_streaming {
+                stop_simulator_log_stream(&app_handle);
+            }
             let stderr = String::from_utf8_lossy(&launch_output.stderr);
-            emit_build_event(&app_handle, "error", &format!("Launch failed: {}", stderr));
+            emit_build_event(&app_handle, "error", msg!("simulator.launch_failed", error = stderr));
+            let error = unlocated_diagnostic(&stderr);
             return Ok(BuildResult {
-                success: false,
-                output: format!("Launch failed: {}", stderr),
-                errors: vec![BuildError {
-                    file: None,
-                    line: None,
-                    column: None,
-                    message: stderr.to_string(),
-                }],
-                warnings: build_result.warnings,
-                build_time: build_result.build_time,
-                app_path: Some(app_path),
                 bundle_id: Some(bundle_id),
+                bundle_id_correction,
+                ..BuildResult::failed(format!("Launch failed: {}", stderr), vec![error], Some(&build_result))
             });
         }
 
-        emit_build_event(&app_handle, "completed", &format!("App launched: {}", bundle_id));
+        // simctl prints `<bundle id>: <pid>`
+        launch_info.pid = String::from_utf8_lossy(&launch_output.stdout)
+            .trim()
+            .rsplit(':')
+            .next()
+            .and_then(|pid| pid.trim().parse().ok());
+
+        if let Some(watch) = launch_watch {
+            let launch_duration_ms = tauri::async_runtime::spawn_blocking(move || watch.finish()).await.ok().flatten();
+            if let (Some(ms), Some(project)) = (launch_duration_ms, project_path.as_deref()) {
+                record_launch_time(&app_handle, project, ms, &mut launch_info);
+            }
+        }
+
+        emit_build_event(&app_handle, "completed", msg!("simulator.launched", bundle_id = bundle_id));
         
-        // Emit app-launched event so frontend can start log streaming
-        let _ = app_handle.emit("app-launched", serde_json::json!({
-            "bundleId": bundle_id.clone(),
-            "deviceId": device_id,
-            "deviceType": "simulator",
-            "deviceName": device.as_ref().map(|d| d.name.clone()).unwrap_or("Simulator".to_string())
-        }));
+        // Emit app-launched event so frontend can start log streaming if we didn't
+        event_journal::emit_stamped(&app_handle, "app-launched", AppLaunched {
+            bundle_id: bundle_id.clone(),
+            executable_name: app_executable_name(&app_path),
+            device_id: device_id.clone(),
+            device_type: "simulator".to_string(),
+            device_name: device.as_ref().map(|d| d.name.clone()).unwrap_or("Simulator".to_string()),
+            pid: launch_info.pid,
+            log_streaming: launch_info.log_streaming,
+            log_backend: launch_info.log_backend.clone(),
+            launch_duration_ms: launch_info.launch_duration_ms,
+            regression: launch_info.launch_regression,
+            run_id: Some(ticket.run_id().to_string()),
+        });
+
+        if let Some(pid) = launch_info.pid {
+            let handle = app_handle.clone();
+            let (project, watched_bundle_id) = (crash_loop_key.clone(), bundle_id.clone());
+            let crashes_so_far = app_handle.state::<crash_loop::CrashLoops>().consecutive_crashes(&project, &bundle_id);
+            let watch = tauri::async_runtime::spawn_blocking(move || {
+                crash_loop::watch_launch(&handle, &project, &watched_bundle_id, pid)
+            });
+            // This launch may be the one that makes a loop; don't report it as a success
+            if crashes_so_far + 1 >= crash_loop::THRESHOLD {
+                if let Ok(Some(detected)) = watch.await {
+                    emit_build_event(&app_handle, "error", msg!("simulator.crash_loop", bundle_id = bundle_id, count = detected.consecutive_crashes, summary = detected.summary));
+                    crash_loop_detected = Some(detected);
+                }
+            }
+        }
+        launch = launch_info;
+    }
+
+    if let Some(device) = &device {
+        record_device_use(&device.id).await;
+    }
+
+    if let Some(detected) = crash_loop_detected {
+        let error = BuildError {
+            suggestion: Some("reset_crash_loop".to_string()),
+            ..unlocated_diagnostic(&format!("{} keeps crashing on launch: {}", bundle_id, detected.summary))
+        };
+        let output = format!("{} crashed on launch {} times in a row", bundle_id, detected.consecutive_crashes);
+        return Ok(BuildResult {
+            bundle_id: Some(bundle_id),
+            launch: Some(launch),
+            bundle_id_correction,
+            crash_loop: Some(detected),
+            ..BuildResult::failed(output, vec![error], Some(&build_result))
+        });
     }
 
     Ok(BuildResult {
@@ -1030,17 +2607,290 @@ async fn run_project(
         output: format!("Build, install, and launch succeeded for {}", bundle_id),
         errors: vec![],
         warnings: build_result.warnings,
+        warning_details: build_result.warning_details.clone(),
         build_time: build_result.build_time,
         app_path: Some(app_path),
         bundle_id: Some(bundle_id),
+        derived_data_path: build_result.derived_data_path.clone(),
+        launch: Some(launch),
+        bundle_id_correction,
+        cancelled: false,
+        crash_loop: None,
+        log_id: build_result.log_id.clone(),
     })
 }
+}
+
+traced! {
+/// Acknowledge a crash loop so the project's next run launches its app again
+/// (see `crash_loop`). Returns false when no crashes were being counted.
+#[tauri::command]
+async fn reset_crash_loop_counter(project_path: String, crash_loops: State<'_, crash_loop::CrashLoops>) -> Result<bool, String> {
+    Ok(crash_loops.reset(&project_path))
+}
+}
+
+traced! {
+/// Runtime issues seen in the log stream per rule (see `runtime_issues`),
+/// counting only matches at or after `since` (ms since the epoch) when given
+#[tauri::command]
+async fn get_runtime_issue_summary(
+    since: Option<u64>,
+    runtime_issues: State<'_, runtime_issues::RuntimeIssues>,
+) -> Result<Vec<runtime_issues::RuntimeIssueCount>, String> {
+    Ok(runtime_issues.summary(since))
+}
+}
 
-/// Terminate an app running on a simulator
+traced! {
+/// Stop the build in progress: kill xcodebuild (or tuist) and everything it
+/// started, wait for its output to be read, and emit a "cancelled" build event.
+/// Returns false, and does nothing, when no build is running.
 #[tauri::command]
-async fn terminate_app_on_simulator(bundle_id: String) -> Result<(), String> {
+async fn cancel_build(app_handle: tauri::AppHandle) -> Result<bool, String> {
+    let handle = app_handle.clone();
+    let cancelled = tauri::async_runtime::spawn_blocking(move || {
+        let build_state = handle.state::<BuildState>();
+        {
+            let child_pid = build_state.child_pid.read().unwrap_or_else(|e| e.into_inner());
+            let Some(pid) = *child_pid else {
+                return Ok(false);
+            };
+            build_state.cancelled.store(true, Ordering::SeqCst);
+            let _ = Command::new("kill").args(["-TERM", "--", &format!("-{}", pid)]).output();
+        }
+
+        // `run_build` clears the PID once it has joined its output readers
+        let deadline = Instant::now() + std::time::Duration::from_secs(5);
+        while build_state.child_pid.read().unwrap_or_else(|e| e.into_inner()).is_some() {
+            if Instant::now() >= deadline {
+                return Err("The build did not stop".to_string());
+            }
+            std::thread::sleep(std::time::Duration::from_millis(50));
+        }
+        Ok(true)
+    })
+    .await
+    .map_err(|e| format!("Build cancel task failed: {}", e))??;
+
+    if cancelled {
+        emit_build_event(&app_handle, "cancelled", msg!("build.cancelled"));
+    }
+    Ok(cancelled)
+}
+}
+
+/// Stop a run that `cancel_run` cancelled before its next step
+fn check_run_cancelled(ticket: &run_lock::RunTicket) -> Result<(), CommandError> {
+    if ticket.is_cancelled() {
+        return Err(CommandError::Failed(format!("Run {} was cancelled", ticket.run_id())));
+    }
+    Ok(())
+}
+
+traced! {
+/// Cancel the project's run in progress, killing its build; returns the
+/// cancelled run's ID, None when nothing was running
+#[tauri::command]
+async fn cancel_run(project_path: String, run_locks: State<'_, run_lock::RunLocks>) -> Result<Option<String>, String> {
+    let cancelled = run_locks.cancel(&project_path);
+    if let Some(run_id) = &cancelled {
+        log::info!("Cancelled run {} of {}", run_id, project_path);
+    }
+    Ok(cancelled)
+}
+}
+
+/// How much slower than the recent median a launch has to be to count as a regression
+const DEFAULT_LAUNCH_REGRESSION_PERCENT: u32 = 20;
+
+/// Note the launch on `device_id` for `list_devices`' `recent_first`
+async fn record_device_use(device_id: &str) {
+    let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_secs();
+    let mut prefs = get_user_preferences().await.unwrap_or_default();
+    prefs.device_last_used.insert(device_id.to_string(), now);
+    if let Err(e) = save_user_preferences(prefs).await {
+        log::warn!("Failed to record device use: {}", e);
+    }
+}
+
+/// Add a launch time to the build history and the launch info, warning when
+/// it's a regression
+fn record_launch_time(app_handle: &tauri::AppHandle, project_path: &str, ms: u64, launch_info: &mut LaunchInfo) {
+    launch_info.launch_duration_ms = Some(ms);
+    emit_build_event(app_handle, "output", msg!("simulator.launch_time", ms = ms));
+    let threshold = read_user_preferences()
+        .ok()
+        .and_then(|prefs| prefs.launch_regression_percent)
+        .unwrap_or(DEFAULT_LAUNCH_REGRESSION_PERCENT);
+    match build_history::record_launch(project_path, ms, threshold) {
+        Ok(time) => {
+            launch_info.launch_regression = time.regression;
+            if let (true, Some(median)) = (time.regression, time.median_ms) {
+                let percent = (ms as f64 / median.max(1) as f64 - 1.0) * 100.0;
+                emit_build_event(app_handle, "warning", msg!(
+                    "simulator.launch_regressed",
+                    ms = ms,
+                    percent = format!("{:.0}", percent),
+                    median = median,
+                ));
+            }
+        }
+        Err(e) => log::warn!("Failed to record launch time: {}", e),
+    }
+}
+
+traced! {
+/// The project's timed simulator launches, newest first, each with the median
+/// of the launches before it
+#[tauri::command]
+async fn get_launch_time_history(project_path: String, limit: Option<usize>) -> Result<Vec<build_history::LaunchTime>, String> {
+    Ok(build_history::launch_history(&project_path, limit.unwrap_or(50)))
+}
+}
+
+traced! {
+/// The project's builds, newest first; `logId` is set while the output is still kept
+#[tauri::command]
+async fn list_build_history(project_path: String, limit: Option<usize>) -> Result<Vec<build_history::BuildRun>, String> {
+    tauri::async_runtime::spawn_blocking(move || build_history::project_runs(&project_path, limit.unwrap_or(50)))
+        .await
+        .map_err(|e| format!("Build history task failed: {}", e))
+}
+}
+
+traced! {
+/// The full, redacted output of the build with `id`, a `logId`
+#[tauri::command]
+async fn read_build_log(id: String) -> Result<String, String> {
+    tauri::async_runtime::spawn_blocking(move || build_logs::read(&id))
+        .await
+        .map_err(|e| format!("Build log task failed: {}", e))?
+}
+}
+
+/// Act on the post-install check: None when `bundle_id` is fine to launch
+/// (or the check couldn't say), otherwise the ID the app was installed under
+fn check_installed_bundle_id(
+    app_handle: &tauri::AppHandle,
+    installed: Result<install_check::InstallCheck, String>,
+    bundle_id: &str,
+) -> Option<install_check::BundleIdCorrection> {
+    match installed {
+        Ok(install_check::InstallCheck::Matches) => None,
+        Ok(install_check::InstallCheck::Mismatch(installed)) => {
+            emit_build_event(app_handle, "warning", msg!("install.bundle_id_corrected", expected = bundle_id, installed = installed));
+            let correction = install_check::BundleIdCorrection { expected: bundle_id.to_string(), installed };
+            event_journal::emit_stamped(app_handle, "bundle-id-corrected", correction.clone());
+            Some(correction)
+        }
+        Ok(install_check::InstallCheck::NotInstalled) => {
+            emit_build_event(app_handle, "warning", msg!("install.app_not_listed", bundle_id = bundle_id));
+            None
+        }
+        Err(e) => {
+            log::warn!("Couldn't check the installed bundle ID: {}", e);
+            None
+        }
+    }
+}
+
+/// Attempts for a simctl step that fails with a transient error
+const SIMCTL_MAX_ATTEMPTS: u32 = 3;
+/// How long `simctl bootstatus` may take before we go ahead anyway
+const SIMULATOR_BOOT_TIMEOUT_SECS: u64 = 60;
+
+/// Failures simctl reports while a freshly booted simulator is still coming up
+fn is_transient_simctl_error(stderr: &str) -> bool {
+    stderr.contains("Unable to lookup in current state: Booting")
+        || stderr.contains("FBSOpenApplicationServiceErrorDomain")
+}
+
+/// Run `xcrun simctl <args>`, retrying transient failures with 1s/2s/4s backoff.
+/// Other failures come back on the first attempt with their original stderr.
+fn run_simctl_with_retry(
+    app_handle: &tauri::AppHandle,
+    step: &str,
+    args: &[&str],
+    env: &[(String, String)],
+) -> std::io::Result<std::process::Output> {
+    let mut attempt = 1;
+    loop {
+        let output = Command::new("xcrun").arg("simctl").args(args).envs(env.iter().cloned()).output()?;
+        if output.status.success() || attempt >= SIMCTL_MAX_ATTEMPTS {
+            return Ok(output);
+        }
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        if !is_transient_simctl_error(&stderr) {
+            return Ok(output);
+        }
+
+        let delay = 1u64 << (attempt - 1);
+        emit_build_event(app_handle, "warning", msg!(
+            "simulator.retrying",
+            step = step,
+            delay = delay,
+            attempt = attempt + 1,
+            max = SIMCTL_MAX_ATTEMPTS,
+        ));
+        std::thread::sleep(std::time::Duration::from_secs(delay));
+        attempt += 1;
+    }
+}
+
+/// Block until `simctl bootstatus -b` reports the simulator ready, up to the boot timeout
+fn wait_for_simulator_boot(target: &str) -> Result<(), String> {
+    let mut child = Command::new("xcrun")
+        .args(["simctl", "bootstatus", target, "-b"])
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+        .map_err(|e| format!("Failed to check simulator boot status: {}", e))?;
+
+    let deadline = Instant::now() + std::time::Duration::from_secs(SIMULATOR_BOOT_TIMEOUT_SECS);
+    loop {
+        match child.try_wait() {
+            Ok(Some(status)) if status.success() => return Ok(()),
+            Ok(Some(_)) => return Err("simctl bootstatus failed; continuing anyway".to_string()),
+            Ok(None) if Instant::now() >= deadline => {
+                let _ = child.kill();
+                let _ = child.wait();
+                return Err(format!(
+                    "Simulator not ready after {}s; continuing anyway",
+                    SIMULATOR_BOOT_TIMEOUT_SECS
+                ));
+            }
+            Ok(None) => std::thread::sleep(std::time::Duration::from_millis(250)),
+            Err(e) => return Err(format!("Failed to check simulator boot status: {}", e)),
+        }
+    }
+}
+
+/// Plain devicectl launch; returns the PID from its JSON output, or stderr on failure
+fn launch_on_device(
+    app_handle: &tauri::AppHandle,
+    devicectl_id: &str,
+    bundle_id: &str,
+    env: &std::collections::BTreeMap<String, String>,
+) -> Result<Option<u32>, String> {
+    let launched = devicectl::launch_app(devicectl_id, bundle_id, &env_profiles::devicectl_env_args(env), 60)?;
+    if !launched.stdout.is_empty() {
+        emit_build_event(app_handle, "output", msg!("simulator.launch_stdout", output = launched.stdout.lines().take(3).collect::<Vec<_>>().join(" | ")));
+    }
+    Ok(launched.pid)
+}
+
+traced! {
+/// Terminate an app running on a simulator, by default the selected one
+#[tauri::command]
+async fn terminate_app_on_simulator(
+    bundle_id: String,
+    device_id: Option<String>,
+    app_state: State<'_, Mutex<AppState>>,
+) -> Result<(), CommandError> {
+    let target = simulator_target(device_id, app_state.inner())?;
     let output = Command::new("xcrun")
-        .args(["simctl", "terminate", "booted", &bundle_id])
+        .args(["simctl", "terminate", &target, &bundle_id])
         .output()
         .map_err(|e| format!("Failed to terminate app: {}", e))?;
 
@@ -1048,65 +2898,129 @@ async fn terminate_app_on_simulator(bundle_id: String) -> Result<(), String> {
         let stderr = String::from_utf8_lossy(&output.stderr);
         // Don't fail if app wasn't running
         if !stderr.contains("not found") {
-            return Err(format!("Failed to terminate app: {}", stderr));
+            return Err(format!("Failed to terminate app: {}", stderr).into());
         }
     }
 
     Ok(())
 }
+}
 
+traced! {
 /// Terminate an app running on a physical device
 #[tauri::command]
 async fn terminate_app_on_device(device_id: String, bundle_id: String) -> Result<(), String> {
     // Get the app name from bundle ID (last component, e.g., "NocurTestApp" from "com.nocur.NocurTestApp")
     let app_name = bundle_id.split('.').last().unwrap_or(&bundle_id);
-    
-    // List processes and find our app
-    let list_output = Command::new("xcrun")
-        .args(["devicectl", "device", "info", "processes", "--device", &device_id])
-        .output()
-        .map_err(|e| format!("Failed to list processes: {}", e))?;
+    let bundle_dir = format!("/{}.app/", app_name);
 
-    let stdout = String::from_utf8_lossy(&list_output.stdout);
-    let stderr = String::from_utf8_lossy(&list_output.stderr);
-    let combined = format!("{}{}", stdout, stderr);
-    
-    // Parse the text output to find PID
-    // Format: "58681   /private/var/containers/Bundle/Application/.../NocurTestApp.app/NocurTestApp"
-    for line in combined.lines() {
-        if line.contains(&format!("{}.app/{}", app_name, app_name)) || line.contains(&format!("/{}.app", app_name)) {
-            // Extract PID from the beginning of the line
-            let parts: Vec<&str> = line.split_whitespace().collect();
-            if let Some(pid_str) = parts.first() {
-                if let Ok(pid) = pid_str.parse::<i64>() {
-                    log::info!("Found app {} with PID {}, terminating...", app_name, pid);
-                    
-                    // Terminate by PID
-                    let term_output = Command::new("xcrun")
-                        .args(["devicectl", "device", "process", "terminate", "--device", &device_id, "--pid", &pid.to_string()])
-                        .output();
-                    
-                    if let Ok(output) = term_output {
-                        let term_stderr = String::from_utf8_lossy(&output.stderr);
-                        log::info!("Terminate result: {}", term_stderr);
-                    }
-                    
-                    return Ok(());
-                }
-            }
-        }
-    }
+    let processes = devicectl::running_processes(&device_id).map_err(|e| format!("Failed to list processes: {}", e.summary()))?;
+    let process = processes
+        .iter()
+        .find(|p| p.executable_path().is_some_and(|path| path.contains(&bundle_dir)));
 
-    log::warn!("Could not find running process for {}", bundle_id);
-    // If we couldn't find/terminate by PID, that's okay - the app might have already stopped
-    Ok(())
+    let Some(process) = process else {
+        log::warn!("Could not find running process for {}", bundle_id);
+        // The app might have already stopped
+        return Ok(());
+    };
+
+    log::info!("Found app {} with PID {}, terminating...", app_name, process.process_identifier);
+    devicectl::terminate_process(&device_id, process.process_identifier)
+        .map_err(|e| format!("Failed to terminate {}: {}", bundle_id, e.summary()))
+}
 }
 
 use std::fs;
 use base64::{Engine as _, engine::general_purpose::STANDARD as BASE64};
 
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ScreenshotCapture {
+    /// The encoded image as a `data:` URL
+    pub data_url: String,
+    #[serde(flatten)]
+    pub image: screenshot_presets::EncodedScreenshot,
+}
+
+traced! {
+/// Screenshot the simulator, sized for display unless `options` says otherwise;
+/// with a project, the capture is also kept in that project's screenshot history
 #[tauri::command]
-async fn take_screenshot() -> Result<String, String> {
+async fn take_screenshot(
+    project_path: Option<String>,
+    options: Option<screenshot_presets::ScreenshotOptions>,
+    app_handle: tauri::AppHandle,
+) -> Result<ScreenshotCapture, String> {
+    capture_screenshot(project_path, options, screenshot_presets::DISPLAY_PRESET, &app_handle)
+}
+}
+
+traced! {
+/// One frame of the simulator pane's live view, or None while streaming is
+/// off (the window is hidden, or a headless run is going)
+#[tauri::command]
+async fn capture_stream_frame(app_handle: tauri::AppHandle) -> Result<Option<ScreenshotCapture>, String> {
+    if !frame_stream::is_active() {
+        frame_stream::record_skipped();
+        return Ok(None);
+    }
+    let started = Instant::now();
+    let capture = tauri::async_runtime::spawn_blocking(move || {
+        capture_screenshot(None, None, screenshot_presets::DISPLAY_PRESET, &app_handle)
+    })
+    .await
+    .map_err(|e| format!("Screenshot task failed: {}", e))??;
+    frame_stream::record_captured(started.elapsed());
+    Ok(Some(capture))
+}
+}
+
+traced! {
+/// Turn the live view's frame capture on or off, e.g. while the window is minimized
+#[tauri::command]
+async fn set_streaming_enabled(enabled: bool) -> Result<(), String> {
+    frame_stream::set_enabled(enabled);
+    Ok(())
+}
+}
+
+traced! {
+/// Frames captured and skipped, and the capture time skipping saved
+#[tauri::command]
+async fn get_stream_stats() -> Result<frame_stream::StreamStats, String> {
+    Ok(frame_stream::stats())
+}
+}
+
+/// `take_screenshot` with `default_preset` for options that don't name one
+fn capture_screenshot(
+    project_path: Option<String>,
+    options: Option<screenshot_presets::ScreenshotOptions>,
+    default_preset: &str,
+    app_handle: &tauri::AppHandle,
+) -> Result<ScreenshotCapture, String> {
+    let options = screenshot_presets::resolve_with_preferences(options, default_preset)?;
+    let path = capture_simulator_screenshot()?;
+    // History keeps the full-size capture, whatever was asked for
+    if let Some(project_path) = project_path {
+        let context = screenshots::CaptureContext::for_active_session(app_handle, screenshots::CaptureTrigger::Manual);
+        if let Err(e) = screenshots::register(&project_path, Path::new(&path), context) {
+            log::warn!("Failed to keep screenshot: {}", e);
+        }
+    }
+
+    let image = screenshot_presets::encode(Path::new(&path), &options)?;
+    let image_data = fs::read(&image.path)
+        .map_err(|e| format!("Failed to read screenshot: {}", e))?;
+    Ok(ScreenshotCapture {
+        data_url: format!("data:{};base64,{}", image.mime_type(), BASE64.encode(&image_data)),
+        image,
+    })
+}
+
+/// Screenshot the booted simulator; returns the PNG's path
+fn capture_simulator_screenshot() -> Result<String, String> {
     let output = nocur_swift_command(&["sim", "screenshot"])
         .output()
         .map_err(|e| format!("Failed to run nocur-swift: {}", e))?;
@@ -1114,21 +3028,62 @@ async fn take_screenshot() -> Result<String, String> {
     let stdout = String::from_utf8_lossy(&output.stdout).to_string();
 
     // Parse JSON to get the path
-    if let Ok(json) = serde_json::from_str::<serde_json::Value>(&stdout) {
-        if let Some(data) = json.get("data") {
-            if let Some(path) = data.get("path").and_then(|v| v.as_str()) {
-                // Read the file and return as base64 data URL
-                let image_data = fs::read(path)
-                    .map_err(|e| format!("Failed to read screenshot: {}", e))?;
-                let base64_data = BASE64.encode(&image_data);
-                return Ok(format!("data:image/png;base64,{}", base64_data));
-            }
-        }
-    }
+    serde_json::from_str::<serde_json::Value>(&stdout)
+        .ok()
+        .and_then(|json| json.get("data")?.get("path")?.as_str().map(String::from))
+        .ok_or_else(|| format!("Failed to parse screenshot response: {}", stdout))
+}
+
+traced! {
+/// Build, launch, settle, screenshot and collect logs/crashes in one call
+#[tauri::command]
+async fn verify_change(
+    project_path: String,
+    options: Option<verify::VerifyOptions>,
+    app_handle: tauri::AppHandle,
+) -> Result<verify::VerificationResult, CommandError> {
+    verify::verify_change(project_path, options.unwrap_or_default(), app_handle).await
+}
+}
+
+traced! {
+/// A project's stored screenshots, newest first
+#[tauri::command]
+async fn list_screenshots(
+    project_path: String,
+    options: Option<screenshots::ListOptions>,
+) -> Result<Vec<screenshots::ScreenshotRecord>, String> {
+    Ok(screenshots::list(&project_path, &options.unwrap_or_default()))
+}
+}
+
+traced! {
+/// Delete stored screenshots by ID; returns how many were deleted
+#[tauri::command]
+async fn delete_screenshots(ids: Vec<String>) -> Result<usize, String> {
+    screenshots::delete(&ids)
+}
+}
 
-    Err(format!("Failed to parse screenshot response: {}", stdout))
+traced! {
+/// Wait until the simulator's screen has stopped changing, e.g. between a tap
+/// and the screenshot that checks it
+#[tauri::command]
+async fn wait_for_ui_idle(
+    device_id: Option<String>,
+    options: Option<ui_idle::IdleOptions>,
+    app_state: State<'_, Mutex<AppState>>,
+) -> Result<ui_idle::UiIdleResult, CommandError> {
+    let target = simulator_target(device_id, app_state.inner())?;
+    let options = options.unwrap_or_default();
+    tauri::async_runtime::spawn_blocking(move || ui_idle::wait_for_idle(&target, &options))
+        .await
+        .map_err(|e| format!("Idle wait task failed: {}", e))?
+        .map_err(CommandError::from)
+}
 }
 
+traced! {
 #[tauri::command]
 async fn get_view_hierarchy() -> Result<String, String> {
     let output = nocur_swift_command(&["ui", "hierarchy"])
@@ -1138,18 +3093,207 @@ async fn get_view_hierarchy() -> Result<String, String> {
     let stdout = String::from_utf8_lossy(&output.stdout).to_string();
     Ok(stdout)
 }
+}
+
+/// The view hierarchy cut down to fit `max_tokens` (estimated), optionally to
+/// the subtree around one element
+pub(crate) fn view_hierarchy_compact(max_tokens: Option<usize>, focus_accessibility_id: Option<String>) -> Result<context_budget::CompactHierarchy, String> {
+    let output = nocur_swift_command(&["ui", "hierarchy"])
+        .output()
+        .map_err(|e| format!("Failed to run nocur-swift: {}", e))?;
+    context_budget::compact_hierarchy(
+        &String::from_utf8_lossy(&output.stdout),
+        max_tokens.unwrap_or(context_budget::DEFAULT_MAX_TOKENS),
+        focus_accessibility_id,
+    )
+}
+
+traced! {
+/// `get_view_hierarchy` pruned for the agent: invisible nodes dropped, long
+/// labels cut and the deepest nodes left out past the budget
+#[tauri::command]
+async fn get_view_hierarchy_compact(
+    max_tokens: Option<usize>,
+    focus_accessibility_id: Option<String>,
+) -> Result<context_budget::CompactHierarchy, String> {
+    tauri::async_runtime::spawn_blocking(move || view_hierarchy_compact(max_tokens, focus_accessibility_id))
+        .await
+        .map_err(|e| format!("View hierarchy task failed: {}", e))?
+}
+}
+
+// ============ Simulator Input ============
+
+traced! {
+/// Choose how keys typed in the simulator pane reach the Simulator; returns the new mode
+#[tauri::command]
+async fn set_simulator_input_mode(
+    mode: simulator_input::InputMode,
+    state: State<'_, Mutex<simulator_input::SimulatorInputState>>,
+) -> Result<simulator_input::InputMode, String> {
+    state.lock().mode = mode;
+    Ok(mode)
+}
+}
+
+traced! {
+#[tauri::command]
+async fn get_simulator_input_mode(
+    state: State<'_, Mutex<simulator_input::SimulatorInputState>>,
+) -> Result<simulator_input::InputMode, String> {
+    Ok(state.lock().mode)
+}
+}
+
+traced! {
+/// Post a key from the simulator pane to the Simulator (`forward` mode).
+/// Send `down` for every keydown, flagged `repeat` while held, and `up` on release.
+#[tauri::command]
+async fn forward_simulator_key(
+    code: String,
+    modifiers: Option<simulator_input::KeyModifiers>,
+    phase: simulator_input::KeyPhase,
+    repeat: Option<bool>,
+    state: State<'_, Mutex<simulator_input::SimulatorInputState>>,
+) -> Result<(), CommandError> {
+    if state.lock().mode != simulator_input::InputMode::Forward {
+        return Err(CommandError::Failed("Key forwarding is off; switch the input mode to forward".to_string()));
+    }
+    simulator_input::forward_key(&code, modifiers.unwrap_or_default(), phase, repeat.unwrap_or(false))
+}
+}
+
+traced! {
+/// Bring Simulator.app to the front so it gets keys directly (`native` mode)
+#[tauri::command]
+async fn focus_simulator() -> Result<(), String> {
+    simulator_input::focus_simulator()
+}
+}
+
+traced! {
+/// Bring nocur back to the front after `focus_simulator`
+#[tauri::command]
+async fn return_focus(app_handle: tauri::AppHandle) -> Result<(), String> {
+    let window = app_handle
+        .get_webview_window("main")
+        .ok_or_else(|| "Main window not found".to_string())?;
+    window.set_focus().map_err(|e| format!("Failed to focus nocur: {}", e))
+}
+}
+
+// ============ App Defaults (UserDefaults) ============
+
+traced! {
+/// Read the app's UserDefaults domain on a simulator as JSON
+#[tauri::command]
+async fn read_app_defaults(
+    device_id: String,
+    bundle_id: String,
+) -> Result<app_defaults::AppDefaults, CommandError> {
+    app_defaults::read_app_defaults(&device_id, &bundle_id)
+}
+}
+
+traced! {
+/// Write a single UserDefaults key on a simulator
+#[tauri::command]
+async fn write_app_default(
+    device_id: String,
+    bundle_id: String,
+    key: String,
+    value: serde_json::Value,
+    value_type: String,
+) -> Result<(), CommandError> {
+    app_defaults::write_app_default(&device_id, &bundle_id, &key, &value, &value_type)
+}
+}
 
 /// Load an image from a file path and return as base64 data URL
 // Claude subprocess commands - uses JSON streaming mode
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StartSessionResponse {
+    pub session_id: String,
+    /// Set when the workspace has more uncommitted changes than the configured threshold
+    pub workspace_warning: Option<String>,
+}
+
+traced! {
 #[tauri::command]
+#[allow(clippy::too_many_arguments)]
 async fn start_claude_session(
     working_dir: String,
     skip_permissions: Option<bool>,
     model: Option<String>,
     resume_session_id: Option<String>,
+    profile: Option<String>,
     app_handle: tauri::AppHandle,
     state: State<'_, Mutex<ClaudeState>>,
-) -> Result<String, String> {
+    session_context: State<'_, Mutex<SessionContext>>,
+    catalog: State<'_, Mutex<models::ModelCatalog>>,
+) -> Result<StartSessionResponse, CommandError> {
+    // Explicit arguments win over what the profile says
+    let profile = match profile {
+        Some(name) => Some(
+            agent_profiles::find(&read_agent_profiles()?, &name)
+                .cloned()
+                .ok_or_else(|| format!("No agent profile named '{}'", name))?,
+        ),
+        None => None,
+    };
+    let model = model.or_else(|| profile.as_ref().and_then(|p| p.model.clone()));
+
+    // Reject unknown or unavailable models up front rather than letting the service fail later
+    let model_enum = match model {
+        Some(requested) => {
+            let requested = requested.to_lowercase();
+            let known = models::available_models(catalog.inner())
+                .into_iter()
+                .find(|m| m.id == requested);
+            match known {
+                Some(m) if !m.available => {
+                    return Err(CommandError::ModelUnavailable(format!(
+                        "{} is not available on your Claude plan",
+                        m.name
+                    )))
+                }
+                None => {
+                    return Err(CommandError::ModelUnavailable(format!("Unknown model: {}", requested)))
+                }
+                Some(_) => match requested.as_str() {
+                    "opus" => Some(ClaudeModel::Opus),
+                    "haiku" => Some(ClaudeModel::Haiku),
+                    _ => Some(ClaudeModel::Sonnet),
+                },
+            }
+        }
+        None => None,
+    };
+
+    // A resumed session that has its own worktree keeps running in it
+    let effective_dir = resume_session_id
+        .as_deref()
+        .and_then(|id| find_session_worktree(&working_dir, id))
+        .unwrap_or_else(|| working_dir.clone());
+
+    // Flag unrelated uncommitted work before the agent starts editing on top of it
+    let threshold = get_user_preferences()
+        .await
+        .ok()
+        .and_then(|p| p.workspace_warning_threshold)
+        .unwrap_or(DEFAULT_WORKSPACE_WARNING_THRESHOLD);
+    let workspace_warning = workspace_cleanliness(&effective_dir)
+        .ok()
+        .filter(|c| c.total_changes() > threshold)
+        .map(|c| {
+            format!(
+                "The workspace already has {} uncommitted change(s) ({} untracked). Consider stashing them so the agent's diff stays clean.",
+                c.total_changes(),
+                c.untracked_count
+            )
+        });
+
     let mut claude_state = state.lock();
 
     // Save current session to history before dropping
@@ -1160,29 +3304,61 @@ async fn start_claude_session(
     // Drop existing session
     claude_state.session = None;
 
-    // Parse model string to enum
-    let model_enum = model.and_then(|m| match m.to_lowercase().as_str() {
-        "sonnet" => Some(ClaudeModel::Sonnet),
-        "opus" => Some(ClaudeModel::Opus),
-        "haiku" => Some(ClaudeModel::Haiku),
-        _ => None,
-    });
-
     // Create session config
     let config = ClaudeSessionConfig {
         model: model_enum,
         resume_session_id,
-        skip_permissions: skip_permissions.unwrap_or(false),
+        skip_permissions: skip_permissions.unwrap_or_else(|| {
+            profile.as_ref().is_some_and(|p| p.permission_mode.as_deref() == Some("bypassPermissions"))
+        }),
+        profile: profile.as_ref().map(|p| claude::ProfileSettings {
+            name: Some(p.name.clone()),
+            permission_mode: p.permission_mode.clone(),
+            append_system_prompt: p.append_system_prompt.clone(),
+            max_turns: p.max_turns,
+        }).unwrap_or_default(),
     };
+    if let Some(profile) = profile.filter(|p| !p.skills.is_empty()) {
+        claude_state.skills = profile.skills;
+    }
 
     // Start new Claude session with config
-    let session = ClaudeSession::new_with_config(&working_dir, app_handle, config)?;
+    let session = ClaudeSession::new_with_config(&effective_dir, app_handle, config)?;
     let session_id = session.get_session_id().to_string();
     claude_state.session = Some(session);
 
-    Ok(session_id)
+    session_context.lock().set_working_dir(&session_id, &effective_dir);
+
+    Ok(StartSessionResponse {
+        session_id,
+        workspace_warning,
+    })
+}
+}
+
+/// `user-message`, echoing what was sent so the chat can show it
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UserMessage {
+    pub content: String,
+}
+
+/// Whether `working_dir` gets checkpoints: it's in `checkpoint_projects`, or
+/// it's a session worktree of a project that is
+fn checkpoints_enabled(checkpoint_projects: &[String], working_dir: &str) -> bool {
+    let canonical = |path: &str| std::fs::canonicalize(path).unwrap_or_else(|_| PathBuf::from(path));
+    let main_worktree = read_worktrees(working_dir)
+        .ok()
+        .and_then(|worktrees| worktrees.into_iter().find(|wt| wt.is_main))
+        .map(|wt| wt.path);
+    let dirs: Vec<PathBuf> = std::iter::once(working_dir.to_string())
+        .chain(main_worktree)
+        .map(|dir| canonical(&dir))
+        .collect();
+    checkpoint_projects.iter().any(|project| dirs.contains(&canonical(project)))
 }
 
+traced! {
 #[tauri::command]
 async fn send_claude_message(
     message: String,
@@ -1190,13 +3366,38 @@ async fn send_claude_message(
     app_handle: tauri::AppHandle,
     state: State<'_, Mutex<ClaudeState>>,
 ) -> Result<(), String> {
+    let session_dir = state
+        .lock()
+        .session
+        .as_ref()
+        .map(|s| (s.get_session_id().to_string(), s.get_working_dir().to_string()));
+
+    // Snapshot the tree before the agent gets to touch it; safe mode won't
+    // send a turn without one
+    if let Some((session_id, working_dir)) = session_dir {
+        let prefs = get_user_preferences().await.unwrap_or_default();
+        let required = safe_mode::is_enabled(&app_handle);
+        if required || checkpoints_enabled(&prefs.checkpoint_projects, &working_dir) {
+            match checkpoints::create_checkpoint(&working_dir, &session_id) {
+                Ok(checkpoint) => {
+                    event_journal::emit_stamped(&app_handle, "checkpoint-created", &checkpoint);
+                }
+                Err(e) if required => {
+                    return Err(format!("Safe mode needs a checkpoint before each turn, and creating one failed: {}", e));
+                }
+                Err(e) => log::warn!("Failed to create checkpoint: {}", e),
+            }
+        }
+    }
+
+    // A dead permission server leaves every tool call hanging at the hook
+    ensure_permission_server(&app_handle);
+
     let claude_state = state.lock();
 
     if let Some(ref session) = claude_state.session {
         // Emit user message event so the UI can display it
-        let _ = app_handle.emit("user-message", serde_json::json!({
-            "content": message
-        }));
+        event_journal::emit_stamped(&app_handle, "user-message", UserMessage { content: message.clone() });
 
         session.send_message(&message, agent_mode.as_deref(), app_handle)?;
         Ok(())
@@ -1204,23 +3405,65 @@ async fn send_claude_message(
         Err("No Claude session active. Start a session first.".to_string())
     }
 }
+}
 
+traced! {
 #[tauri::command]
 async fn stop_claude_session(
+    app_handle: tauri::AppHandle,
     state: State<'_, Mutex<ClaudeState>>,
 ) -> Result<(), String> {
     let mut claude_state = state.lock();
-    claude_state.session = None;
+    if let Some(session) = claude_state.session.take() {
+        let session_id = session.get_session_id().to_string();
+        session_reports::end(&app_handle, &session_id);
+        if read_user_preferences().map(|p| p.session_report_on_stop).unwrap_or(false) {
+            session_reports::spawn_on_stop(app_handle.clone(), session.get_working_dir().to_string(), session_id);
+        }
+    }
     claude_state.clear_session_info();
     Ok(())
 }
+}
+
+traced! {
+/// Recap what a session did in a project. With `save` the report is also
+/// written to ~/.nocur/reports.
+#[tauri::command]
+async fn generate_session_report(
+    project_path: String,
+    session_id: String,
+    save: Option<bool>,
+    app_handle: tauri::AppHandle,
+) -> Result<session_reports::SessionReport, String> {
+    tauri::async_runtime::spawn_blocking(move || {
+        let report = session_reports::generate(&app_handle, &project_path, &session_id);
+        if save.unwrap_or(false) {
+            session_reports::save(&report)?;
+        }
+        Ok(report)
+    })
+    .await
+    .map_err(|e| format!("Failed to generate session report: {}", e))?
+}
+}
+
+traced! {
+/// A saved report, e.g. the one a `session-report-ready` event names
+#[tauri::command]
+async fn get_session_report(report_id: String) -> Result<session_reports::SessionReport, String> {
+    session_reports::load(&report_id)
+}
+}
 
+traced! {
 #[tauri::command]
 async fn cancel_claude_request(
     working_dir: String,
     skip_permissions: Option<bool>,
     app_handle: tauri::AppHandle,
     state: State<'_, Mutex<ClaudeState>>,
+    session_context: State<'_, Mutex<SessionContext>>,
 ) -> Result<(), String> {
     let mut claude_state = state.lock();
 
@@ -1236,13 +3479,102 @@ async fn cancel_claude_request(
 
     // Start a new session
     let session = ClaudeSession::new(&working_dir, app_handle, skip_permissions.unwrap_or(false))?;
+    session_context.lock().set_working_dir(session.get_session_id(), &working_dir);
+    claude_state.session = Some(session);
+
+    // Restore session info
+    claude_state.skills = skills;
+    claude_state.model = model;
+
+    Ok(())
+}
+}
+
+/// How long `interrupt` recovery waits for the service to answer
+const STALL_INTERRUPT_WAIT: Duration = Duration::from_secs(10);
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StallRecoveryResult {
+    /// Whether the service responded; always true for `restart` and `ignore`
+    pub recovered: bool,
+    /// The session to use from now on; differs from the stalled one after a restart
+    pub session_id: String,
+}
+
+/// Replace the active session with a new service resuming the same
+/// conversation; returns the new session's ID
+fn restart_session_resuming(
+    claude_state: &mut ClaudeState,
+    app_handle: tauri::AppHandle,
+    session_context: &Mutex<SessionContext>,
+) -> Result<String, String> {
+    let session = claude_state.session.as_ref().ok_or("No active session")?;
+    let working_dir = session.get_working_dir().to_string();
+    let config = ClaudeSessionConfig {
+        model: session.get_model().cloned(),
+        // Without an SDK ID the service never started the conversation,
+        // so there's nothing to resume
+        resume_session_id: session.sdk_session_id(),
+        skip_permissions: session.skip_permissions(),
+        profile: session.profile().clone(),
+    };
+    session.stop();
+    claude_state.session = None;
+
+    let session = ClaudeSession::new_with_config(&working_dir, app_handle, config)?;
+    let session_id = session.get_session_id().to_string();
+    session_context.lock().set_working_dir(&session_id, &working_dir);
     claude_state.session = Some(session);
+    Ok(session_id)
+}
 
-    // Restore session info
-    claude_state.skills = skills;
-    claude_state.model = model;
+traced! {
+/// Recover from a `turn_stalled` event: interrupt the turn, restart the service
+/// resuming the same conversation, or keep waiting
+#[tauri::command]
+async fn recover_stalled_session(
+    session_id: String,
+    strategy: StallRecovery,
+    app_handle: tauri::AppHandle,
+    state: State<'_, Mutex<ClaudeState>>,
+    session_context: State<'_, Mutex<SessionContext>>,
+) -> Result<StallRecoveryResult, String> {
+    // The state lock has to be released before waiting on the interrupt
+    let (watch, since) = {
+        let mut claude_state = state.lock();
+        let session = claude_state
+            .session
+            .as_ref()
+            .filter(|s| s.has_id(&session_id))
+            .ok_or_else(|| format!("No active session {}", session_id))?;
+
+        match strategy {
+            StallRecovery::Ignore => {
+                session.turn_watch().snooze();
+                return Ok(StallRecoveryResult { recovered: true, session_id });
+            }
+            StallRecovery::Restart => {
+                let session_id = restart_session_resuming(&mut claude_state, app_handle, session_context.inner())?;
+                return Ok(StallRecoveryResult { recovered: true, session_id });
+            }
+            StallRecovery::Interrupt => {
+                let watch = session.turn_watch();
+                let since = watch.last_event_at();
+                session.interrupt()?;
+                (watch, since)
+            }
+        }
+    };
 
-    Ok(())
+    let recovered = tauri::async_runtime::spawn_blocking(move || watch.wait_for_event(since, STALL_INTERRUPT_WAIT))
+        .await
+        .map_err(|e| format!("Failed to wait for the session: {}", e))?;
+    if !recovered {
+        log::warn!("Stalled session {} didn't answer the interrupt", session_id);
+    }
+    Ok(StallRecoveryResult { recovered, session_id })
+}
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -1251,20 +3583,37 @@ pub struct ClaudeSessionInfo {
     pub active: bool,
     pub skills: Vec<String>,
     pub model: Option<String>,
+    /// Service output lines that couldn't be parsed; non-zero means events may be missing
+    #[serde(default)]
+    pub parse_failures: u64,
+    /// Agent profile the session was started from
+    pub profile: Option<String>,
+    #[serde(default)]
+    pub safe_mode: bool,
+    #[serde(default)]
+    pub identity: Option<session_identity::SessionIdentity>,
 }
 
+traced! {
 #[tauri::command]
 async fn get_claude_session_info(
     state: State<'_, Mutex<ClaudeState>>,
+    safe_mode: State<'_, safe_mode::SafeMode>,
 ) -> Result<ClaudeSessionInfo, String> {
     let claude_state = state.lock();
     Ok(ClaudeSessionInfo {
         active: claude_state.session.is_some(),
         skills: claude_state.skills.clone(),
         model: claude_state.model.clone(),
+        parse_failures: claude_state.session.as_ref().map_or(0, |s| s.parse_failures()),
+        profile: claude_state.session.as_ref().and_then(|s| s.profile().name.clone()),
+        safe_mode: safe_mode.is_enabled(),
+        identity: claude_state.session.as_ref().and_then(|s| session_identity::get(s.get_session_id())),
     })
 }
+}
 
+traced! {
 #[tauri::command]
 async fn set_claude_session_info(
     skills: Vec<String>,
@@ -1275,37 +3624,80 @@ async fn set_claude_session_info(
     claude_state.set_session_info(skills, model);
     Ok(())
 }
+}
 
-/// Get list of available Claude models
 #[derive(Debug, Clone, Serialize)]
 #[serde(rename_all = "camelCase")]
-pub struct ModelInfo {
-    pub id: String,
-    pub name: String,
-    pub description: String,
+pub struct SkillsReload {
+    /// "service" when the running service re-scanned, "resume" when the
+    /// session had to be restarted resuming the conversation
+    pub mechanism: String,
+    /// The session to use from now on; differs from the old one after "resume"
+    pub session_id: String,
+    pub skills: Vec<String>,
 }
 
+traced! {
+/// Make skills created or edited since the session started available to it
 #[tauri::command]
-async fn get_available_models() -> Result<Vec<ModelInfo>, String> {
-    Ok(vec![
-        ModelInfo {
-            id: "sonnet".to_string(),
-            name: "Claude Sonnet 4.5".to_string(),
-            description: "Fast and capable, great for most coding tasks".to_string(),
-        },
-        ModelInfo {
-            id: "opus".to_string(),
-            name: "Claude Opus 4.5".to_string(),
-            description: "Most powerful, best for complex reasoning".to_string(),
-        },
-        ModelInfo {
-            id: "haiku".to_string(),
-            name: "Claude Haiku 4.5".to_string(),
-            description: "Fastest and most economical".to_string(),
-        },
-    ])
+async fn reload_session_skills(
+    session_id: String,
+    app_handle: tauri::AppHandle,
+    state: State<'_, Mutex<ClaudeState>>,
+    session_context: State<'_, Mutex<SessionContext>>,
+) -> Result<SkillsReload, String> {
+    let (mechanism, session_id, working_dir) = {
+        let mut claude_state = state.lock();
+        let session = claude_state
+            .session
+            .as_ref()
+            .filter(|s| s.has_id(&session_id))
+            .ok_or_else(|| format!("No active session {}", session_id))?;
+        let working_dir = session.get_working_dir().to_string();
+        match session.reload_skills() {
+            Ok(()) => ("service", session_id, working_dir),
+            Err(e) => {
+                log::warn!("Service didn't take the skills reload ({}), restarting the session", e);
+                let session_id = restart_session_resuming(&mut claude_state, app_handle, session_context.inner())?;
+                ("resume", session_id, working_dir)
+            }
+        }
+    };
+
+    // Claude Code loads every skill on disk, so that's the session's set now
+    prefetch::invalidate(Some(&working_dir), Some(PrefetchItem::Skills));
+    let skills: Vec<String> = list_skills(Some(working_dir)).await?.into_iter().map(|s| s.name).collect();
+    state.lock().skills = skills.clone();
+
+    Ok(SkillsReload {
+        mechanism: mechanism.to_string(),
+        session_id,
+        skills,
+    })
+}
+}
+
+traced! {
+/// Socket path and token the agent uses to call nocur commands through the bridge
+#[tauri::command]
+async fn get_agent_bridge_info(
+    bridge: State<'_, agent_bridge::AgentBridge>,
+) -> Result<agent_bridge::AgentBridgeInfo, String> {
+    Ok(bridge.info())
+}
+}
+
+traced! {
+/// Get list of available Claude models
+#[tauri::command]
+async fn get_available_models(
+    catalog: State<'_, Mutex<models::ModelCatalog>>,
+) -> Result<Vec<models::ModelInfo>, String> {
+    Ok(models::available_models(catalog.inner()))
+}
 }
 
+traced! {
 /// Get recent sessions for resume functionality
 #[tauri::command]
 async fn get_recent_sessions(
@@ -1314,7 +3706,39 @@ async fn get_recent_sessions(
     let claude_state = state.lock();
     Ok(claude_state.get_recent_sessions())
 }
+}
+
+traced! {
+/// Streaming metrics (tokens/sec, time to first token) for a session's last turn
+#[tauri::command]
+async fn get_last_turn_metrics(
+    session_id: String,
+    state: State<'_, Mutex<ClaudeState>>,
+) -> Result<Option<claude::TurnMetrics>, String> {
+    Ok(state.lock().get_last_turn_metrics(&session_id))
+}
+}
+
+traced! {
+/// How much a session's event stream was coalesced and truncated
+#[tauri::command]
+async fn get_event_coalescing_stats(
+    session_id: String,
+    state: State<'_, Mutex<ClaudeState>>,
+) -> Result<Option<event_coalescer::CoalesceStats>, String> {
+    Ok(state.lock().get_coalesce_stats(&session_id))
+}
+}
+
+traced! {
+/// The whole content of a Claude event that was emitted with `truncatedBytes`
+#[tauri::command]
+async fn get_full_event_content(event_id: String) -> Result<String, String> {
+    event_coalescer::full_content(&event_id)
+}
+}
 
+traced! {
 /// Get current session ID
 #[tauri::command]
 async fn get_current_session_id(
@@ -1323,7 +3747,20 @@ async fn get_current_session_id(
     let claude_state = state.lock();
     Ok(claude_state.get_current_session_id())
 }
+}
+
+traced! {
+/// Get the working directory a session operates in (its worktree, if it has one)
+#[tauri::command]
+async fn get_effective_working_dir(
+    session_id: String,
+    session_context: State<'_, Mutex<SessionContext>>,
+) -> Result<Option<String>, String> {
+    Ok(session_context.lock().working_dir(&session_id))
+}
+}
 
+traced! {
 /// Save current session to history (call before ending important sessions)
 #[tauri::command]
 async fn save_session_to_history(
@@ -1334,19 +3771,178 @@ async fn save_session_to_history(
     claude_state.save_current_session(last_message);
     Ok(())
 }
+}
 
 // ============ Permission Commands ============
 
+traced! {
 #[tauri::command]
 async fn set_skip_permissions(
     enabled: bool,
     state: State<'_, Mutex<PermissionState>>,
+    safe_mode: State<'_, safe_mode::SafeMode>,
 ) -> Result<(), String> {
     let permission_state = state.lock();
+    if enabled && safe_mode.is_enabled() {
+        permission_state.server.set_auto_approve(false);
+        return Err("Safe mode is on; turn it off to skip permission prompts".to_string());
+    }
     permission_state.server.set_auto_approve(enabled);
     Ok(())
 }
+}
+
+traced! {
+/// Turn safe mode on or off and remember it across restarts. Turning it on
+/// also stops the permission server auto-approving.
+#[tauri::command]
+async fn set_safe_mode(
+    enabled: bool,
+    safe_mode: State<'_, safe_mode::SafeMode>,
+    permissions: State<'_, Mutex<PermissionState>>,
+) -> Result<(), String> {
+    let mut prefs = get_user_preferences().await.unwrap_or_default();
+    prefs.safe_mode = enabled;
+    save_user_preferences(prefs).await?;
+
+    safe_mode.set_enabled(enabled);
+    if enabled {
+        permissions.lock().server.set_auto_approve(false);
+    }
+    Ok(())
+}
+}
+
+traced! {
+#[tauri::command]
+async fn set_redaction_enabled(enabled: bool) -> Result<(), String> {
+    let mut prefs = get_user_preferences().await.unwrap_or_default();
+    prefs.redaction_disabled = !enabled;
+    save_user_preferences(prefs).await
+}
+}
+
+traced! {
+/// Switch one redaction pattern, built-in or custom, on or off by name
+#[tauri::command]
+async fn set_redaction_pattern_enabled(pattern: String, enabled: bool) -> Result<(), String> {
+    if !redaction::patterns().iter().any(|p| p.name == pattern) {
+        return Err(format!("Unknown redaction pattern: {}", pattern));
+    }
+    let mut prefs = get_user_preferences().await.unwrap_or_default();
+    prefs.redaction_disabled_patterns.retain(|p| *p != pattern);
+    if !enabled {
+        prefs.redaction_disabled_patterns.push(pattern);
+    }
+    save_user_preferences(prefs).await
+}
+}
+
+traced! {
+#[tauri::command]
+async fn list_redaction_patterns() -> Result<Vec<redaction::RedactionPattern>, String> {
+    Ok(redaction::patterns())
+}
+}
+
+traced! {
+/// A single-use token for one destructive command while safe mode is on;
+/// ask the user before requesting it
+#[tauri::command]
+async fn request_destructive_confirmation(
+    safe_mode: State<'_, safe_mode::SafeMode>,
+) -> Result<safe_mode::DestructiveConfirmation, String> {
+    Ok(safe_mode.issue_token())
+}
+}
+
+/// Restart the permission server if it's stopped answering
+fn ensure_permission_server(app_handle: &tauri::AppHandle) {
+    if let Err(e) = app_handle.state::<Mutex<PermissionState>>().lock().server.ensure_running() {
+        log::error!("Failed to restart permission server: {}", e);
+    }
+}
+
+/// Hand the imported Claude Code permission rules to the permission server
+fn apply_claude_permission_rules(app_handle: &tauri::AppHandle, prefs: &UserPreferences) {
+    let projects = prefs
+        .claude_project_permission_rules
+        .iter()
+        .map(|(path, rules)| (path.clone(), rules.clone()))
+        .collect();
+    app_handle
+        .state::<Mutex<PermissionState>>()
+        .lock()
+        .server
+        .set_rules(prefs.claude_permission_rules.clone(), projects);
+}
+
+/// Read Claude Code's settings into the preferences. A model already chosen
+/// in nocur is only replaced when `replace_model` is set.
+async fn import_claude_settings_into_preferences(
+    app_handle: &tauri::AppHandle,
+    project_path: Option<String>,
+    replace_model: bool,
+) -> Result<claude_settings::ImportReport, String> {
+    let read_path = project_path.clone();
+    let settings = tokio::task::spawn_blocking(move || claude_settings::read(read_path.as_deref()))
+        .await
+        .map_err(|e| format!("Failed to read Claude settings: {}", e))?;
+    let mut report = settings.report;
+
+    let mut prefs = get_user_preferences().await.unwrap_or_default();
+    if let Some(model) = settings.model {
+        if replace_model || prefs.model.is_none() {
+            prefs.model = Some(model);
+        } else if let Some(index) = report.imported.iter().position(|s| s.field == "model") {
+            let setting = report.imported.remove(index);
+            report.skipped.push(claude_settings::SkippedSetting {
+                source: setting.source,
+                field: setting.field,
+                value: setting.value,
+                reason: "A model is already chosen in nocur".to_string(),
+            });
+        }
+    }
+    prefs.claude_permission_rules = settings.global_rules;
+    if let Some(project_path) = project_path {
+        if settings.project_rules.is_empty() {
+            prefs.claude_project_permission_rules.remove(&project_path);
+        } else {
+            prefs.claude_project_permission_rules.insert(project_path, settings.project_rules);
+        }
+    }
+    prefs.claude_env = settings.env;
+    prefs.claude_settings_imported = true;
+    save_user_preferences(prefs.clone()).await?;
+    apply_claude_permission_rules(app_handle, &prefs);
+    Ok(report)
+}
+
+traced! {
+/// Import the model, permission rules and env from Claude Code's global
+/// settings and, with `project_path`, the project's. Claude's settings files
+/// are only read; the report says what was taken over and what was skipped.
+#[tauri::command]
+async fn import_claude_settings(
+    project_path: Option<String>,
+    app_handle: tauri::AppHandle,
+) -> Result<claude_settings::ImportReport, String> {
+    import_claude_settings_into_preferences(&app_handle, project_path, true).await
+}
+}
+
+traced! {
+/// Health and restart count of the permission server, for diagnostics
+#[tauri::command]
+async fn get_permission_server_status(
+    state: State<'_, Mutex<PermissionState>>,
+) -> Result<permissions::PermissionServerStatus, String> {
+    Ok(state.lock().server.status())
+}
+}
 
+traced! {
 #[tauri::command]
 async fn respond_to_permission(
     request_id: String,
@@ -1364,7 +3960,9 @@ async fn respond_to_permission(
     permission_state.server.respond(&request_id, response);
     Ok(())
 }
+}
 
+traced! {
 /// Add a permission rule to .claude/settings.local.json
 #[tauri::command]
 async fn add_permission_rule(
@@ -1434,6 +4032,7 @@ async fn add_permission_rule(
 
     Ok(())
 }
+}
 
 // ============ Skills Commands ============
 
@@ -1446,13 +4045,16 @@ pub struct SkillInfo {
     pub location: String, // "user" or "project"
 }
 
+traced! {
 #[tauri::command]
 async fn list_skills(project_path: Option<String>) -> Result<Vec<SkillInfo>, String> {
+    if let Some(skills) = project_path.as_deref().and_then(|path| prefetch::get::<Vec<SkillInfo>>(PrefetchItem::Skills, path)) {
+        return Ok(skills);
+    }
     let mut skills = Vec::new();
 
     // User-level skills: ~/.claude/skills/<skill-name>/SKILL.md
-    let home = std::env::var("HOME").unwrap_or_default();
-    let user_skills_dir = PathBuf::from(&home).join(".claude").join("skills");
+    let user_skills_dir = paths::claude_dir().map(|dir| dir.join("skills")).unwrap_or_default();
 
     if user_skills_dir.exists() {
         if let Ok(entries) = fs::read_dir(&user_skills_dir) {
@@ -1513,27 +4115,36 @@ async fn list_skills(project_path: Option<String>) -> Result<Vec<SkillInfo>, Str
 
     Ok(skills)
 }
+}
 
+/// Resolve the skills directory for a "user" or "project" location
+fn skills_base_dir(location: &str, project_path: Option<String>) -> Result<PathBuf, String> {
+    if location == "project" {
+        let proj = project_path.ok_or("Project path required for project skills")?;
+        Ok(PathBuf::from(proj).join(".claude").join("skills"))
+    } else {
+        Ok(paths::claude_dir()?.join("skills"))
+    }
+}
+
+traced! {
 #[tauri::command]
 async fn read_skill(skill_path: String) -> Result<String, String> {
     fs::read_to_string(&skill_path)
         .map_err(|e| format!("Failed to read skill: {}", e))
 }
+}
 
+traced! {
 #[tauri::command]
 async fn create_skill(
     name: String,
     content: String,
     location: String,
     project_path: Option<String>,
+    app_handle: tauri::AppHandle,
 ) -> Result<String, String> {
-    let base_skills_dir = if location == "project" {
-        let proj = project_path.ok_or("Project path required for project skills")?;
-        PathBuf::from(proj).join(".claude").join("skills")
-    } else {
-        let home = std::env::var("HOME").map_err(|_| "HOME not set")?;
-        PathBuf::from(home).join(".claude").join("skills")
-    };
+    let base_skills_dir = skills_base_dir(&location, project_path)?;
 
     // Skills are stored as: skills/<skill-name>/SKILL.md
     let skill_dir = base_skills_dir.join(&name);
@@ -1544,18 +4155,66 @@ async fn create_skill(
     fs::write(&file_path, &content)
         .map_err(|e| format!("Failed to write skill: {}", e))?;
 
-    Ok(file_path.to_string_lossy().to_string())
+    let path = file_path.to_string_lossy().to_string();
+    emit_skills_changed(&app_handle, &name, &path, "created");
+    Ok(path)
+}
+}
+
+/// `skills-changed`, so the UI can offer to reload the running session's skills
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SkillsChanged {
+    pub name: String,
+    pub path: String,
+    /// "created" or "updated"
+    pub change: String,
+}
+
+fn emit_skills_changed(app_handle: &tauri::AppHandle, name: &str, path: &str, change: &str) {
+    prefetch::invalidate(None, Some(PrefetchItem::Skills));
+    event_journal::emit_stamped(app_handle, "skills-changed", SkillsChanged {
+        name: name.to_string(),
+        path: path.to_string(),
+        change: change.to_string(),
+    });
+}
+
+traced! {
+/// Replace the content of an existing skill's SKILL.md
+#[tauri::command]
+async fn update_skill(
+    skill_path: String,
+    content: String,
+    app_handle: tauri::AppHandle,
+) -> Result<(), String> {
+    let file_path = PathBuf::from(&skill_path);
+    // Only ever write a skills/<name>/SKILL.md that's already there
+    let skill_dir = file_path.parent().filter(|_| file_path.file_name().is_some_and(|n| n == "SKILL.md"));
+    let in_skills_dir = skill_dir
+        .and_then(|dir| dir.parent())
+        .and_then(|dir| dir.file_name())
+        .is_some_and(|n| n == "skills");
+    if !in_skills_dir || !file_path.is_file() {
+        return Err(format!("Not a skill file: {}", skill_path));
+    }
+
+    fs::write(&file_path, &content)
+        .map_err(|e| format!("Failed to write skill: {}", e))?;
+
+    let name = skill_dir
+        .and_then(|dir| dir.file_name())
+        .map(|n| n.to_string_lossy().to_string())
+        .unwrap_or_default();
+    emit_skills_changed(&app_handle, &name, &skill_path, "updated");
+    Ok(())
+}
 }
 
+traced! {
 #[tauri::command]
 async fn open_skills_folder(location: String, project_path: Option<String>) -> Result<(), String> {
-    let skills_dir = if location == "project" {
-        let proj = project_path.ok_or("Project path required for project skills")?;
-        PathBuf::from(proj).join(".claude").join("skills")
-    } else {
-        let home = std::env::var("HOME").map_err(|_| "HOME not set")?;
-        PathBuf::from(home).join(".claude").join("skills")
-    };
+    let skills_dir = skills_base_dir(&location, project_path)?;
 
     // Create directory if it doesn't exist
     fs::create_dir_all(&skills_dir)
@@ -1568,6 +4227,44 @@ async fn open_skills_folder(location: String, project_path: Option<String>) -> R
 
     Ok(())
 }
+}
+
+traced! {
+#[tauri::command]
+fn list_builtin_skills() -> Vec<builtin_skills::BuiltinSkillInfo> {
+    builtin_skills::list_builtin_skills()
+}
+}
+
+traced! {
+/// Install a bundled skill template. Refuses to replace a copy the user has
+/// edited (or a same-named skill they wrote themselves) unless `overwrite` is set.
+#[tauri::command]
+async fn install_builtin_skill(
+    id: String,
+    location: String,
+    project_path: Option<String>,
+    overwrite: Option<bool>,
+    app_handle: tauri::AppHandle,
+) -> Result<String, String> {
+    let content = builtin_skills::render_builtin_skill(&id)?;
+
+    let existing_file = skills_base_dir(&location, project_path.clone())?
+        .join(&id)
+        .join("SKILL.md");
+    if let Ok(existing) = fs::read_to_string(&existing_file) {
+        if builtin_skills::is_user_modified(&existing) && !overwrite.unwrap_or(false) {
+            return Err(format!(
+                "Skill '{}' already exists at {} and has local changes. Confirm overwrite to replace it.",
+                id,
+                existing_file.display()
+            ));
+        }
+    }
+
+    create_skill(id, content, location, project_path, app_handle).await
+}
+}
 
 // ============ Git Info Commands ============
 
@@ -1583,6 +4280,17 @@ pub struct GitInfo {
     pub working_dir: String,
 }
 
+/// Whether a `git status --porcelain` line is a tracked modification
+fn is_dirty_status(line: &str) -> bool {
+    line.starts_with(" M") || line.starts_with("M ") || line.starts_with("MM") || line.starts_with("A ") || line.starts_with("D ") || line.starts_with("R ")
+}
+
+/// Whether a `git status --porcelain` line is an untracked file
+fn is_untracked_status(line: &str) -> bool {
+    line.starts_with("??")
+}
+
+traced! {
 #[tauri::command]
 async fn get_git_info(path: Option<String>) -> Result<GitInfo, String> {
     let working_dir = path.unwrap_or_else(|| {
@@ -1590,6 +4298,9 @@ async fn get_git_info(path: Option<String>) -> Result<GitInfo, String> {
             .map(|p| p.to_string_lossy().to_string())
             .unwrap_or_else(|_| ".".to_string())
     });
+    if let Some(info) = prefetch::get::<GitInfo>(PrefetchItem::GitInfo, &working_dir) {
+        return Ok(info);
+    }
 
     // Bail out early if this isn't a git worktree to avoid surfacing confusing "unknown" branches.
     let is_repo_output = Command::new("git")
@@ -1645,8 +4356,8 @@ async fn get_git_info(path: Option<String>) -> Result<GitInfo, String> {
 
     // Count modified and untracked files (skip first line which is branch info)
     let file_lines: Vec<&str> = lines.iter().skip(1).copied().collect();
-    let is_dirty = file_lines.iter().any(|l| l.starts_with(" M") || l.starts_with("M ") || l.starts_with("MM") || l.starts_with("A ") || l.starts_with("D ") || l.starts_with("R "));
-    let has_untracked = file_lines.iter().any(|l| l.starts_with("??"));
+    let is_dirty = file_lines.iter().any(|l| is_dirty_status(l));
+    let has_untracked = file_lines.iter().any(|l| is_untracked_status(l));
 
     // Build short status string
     let mut short_status = String::new();
@@ -1676,95 +4387,202 @@ async fn get_git_info(path: Option<String>) -> Result<GitInfo, String> {
         working_dir,
     })
 }
+}
 
-// ============ Git Diff/Status Commands ============
+// ============ Workspace Cleanliness ============
+
+/// Changed files beyond this count trigger a warning when a session starts
+const DEFAULT_WORKSPACE_WARNING_THRESHOLD: usize = 10;
+const LARGEST_CHANGES_LIMIT: usize = 5;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
-pub struct GitChangedFile {
+pub struct ChangedFile {
     pub path: String,
-    pub status: String, // "M" modified, "A" added, "D" deleted, "?" untracked
-    pub additions: u32,
-    pub deletions: u32,
+    pub lines_changed: u64,
+    pub untracked: bool,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
-pub struct GitDiffStats {
-    pub total_additions: u32,
-    pub total_deletions: u32,
-    pub files: Vec<GitChangedFile>,
+pub struct WorkspaceCleanliness {
+    pub dirty_count: usize,
+    pub untracked_count: usize,
+    /// Biggest changes by lines added + removed (untracked files count all their lines)
+    pub largest_changes: Vec<ChangedFile>,
 }
 
-#[tauri::command]
-async fn get_git_diff_stats(path: Option<String>) -> Result<GitDiffStats, String> {
-    let working_dir = path.unwrap_or_else(|| {
-        std::env::current_dir()
-            .map(|p| p.to_string_lossy().to_string())
-            .unwrap_or_else(|_| ".".to_string())
-    });
+impl WorkspaceCleanliness {
+    fn total_changes(&self) -> usize {
+        self.dirty_count + self.untracked_count
+    }
+}
 
-    // Get list of changed files with status
+fn workspace_cleanliness(working_dir: &str) -> Result<WorkspaceCleanliness, String> {
     let status_output = Command::new("git")
-        .args(["status", "--porcelain"])
-        .current_dir(&working_dir)
+        .args(["status", "--porcelain", "--untracked-files=all"])
+        .current_dir(working_dir)
         .output()
-        .map_err(|e| format!("Failed to get git status: {}", e))?;
+        .map_err(|e| format!("Failed to get status: {}", e))?;
 
-    let status_str = String::from_utf8_lossy(&status_output.stdout);
+    if !status_output.status.success() {
+        return Err("Not a git repository".to_string());
+    }
 
-    // Get diff stats (numstat)
-    let diff_output = Command::new("git")
-        .args(["diff", "--numstat", "HEAD"])
-        .current_dir(&working_dir)
+    let status_str = String::from_utf8_lossy(&status_output.stdout).to_string();
+    let dirty_count = status_str.lines().filter(|l| is_dirty_status(l)).count();
+    let untracked: Vec<String> = status_str
+        .lines()
+        .filter(|l| is_untracked_status(l))
+        .map(|l| l[3..].trim_matches('"').to_string())
+        .collect();
+
+    let mut changes: Vec<ChangedFile> = Vec::new();
+
+    // Staged and unstaged churn against HEAD (fails harmlessly on an unborn branch)
+    if let Ok(numstat) = Command::new("git")
+        .args(["diff", "HEAD", "--numstat"])
+        .current_dir(working_dir)
         .output()
-        .map_err(|e| format!("Failed to get git diff: {}", e))?;
+    {
+        for line in String::from_utf8_lossy(&numstat.stdout).lines() {
+            let mut parts = line.splitn(3, '\t');
+            let added: u64 = parts.next().and_then(|n| n.parse().ok()).unwrap_or(0);
+            let removed: u64 = parts.next().and_then(|n| n.parse().ok()).unwrap_or(0);
+            if let Some(path) = parts.next() {
+                changes.push(ChangedFile {
+                    path: path.to_string(),
+                    lines_changed: added + removed,
+                    untracked: false,
+                });
+            }
+        }
+    }
+
+    for path in &untracked {
+        let lines = fs::read(PathBuf::from(working_dir).join(path))
+            .map(|bytes| bytes.iter().filter(|b| **b == b'\n').count() as u64)
+            .unwrap_or(0);
+        changes.push(ChangedFile {
+            path: path.clone(),
+            lines_changed: lines,
+            untracked: true,
+        });
+    }
 
-    let diff_str = String::from_utf8_lossy(&diff_output.stdout);
+    changes.sort_by_key(|c| std::cmp::Reverse(c.lines_changed));
+    changes.truncate(LARGEST_CHANGES_LIMIT);
 
-    // Parse numstat for additions/deletions per file
-    let mut file_stats: std::collections::HashMap<String, (u32, u32)> = std::collections::HashMap::new();
-    for line in diff_str.lines() {
-        let parts: Vec<&str> = line.split('\t').collect();
-        if parts.len() >= 3 {
-            let additions = parts[0].parse().unwrap_or(0);
-            let deletions = parts[1].parse().unwrap_or(0);
-            let file_path = parts[2].to_string();
-            file_stats.insert(file_path, (additions, deletions));
-        }
+    Ok(WorkspaceCleanliness {
+        dirty_count,
+        untracked_count: untracked.len(),
+        largest_changes: changes,
+    })
+}
+
+traced! {
+/// Count uncommitted changes and list the largest ones
+#[tauri::command]
+async fn check_workspace_cleanliness(path: String) -> Result<WorkspaceCleanliness, String> {
+    workspace_cleanliness(&path)
+}
+}
+
+fn nocur_stash_message(label: &str) -> String {
+    format!("nocur: {}", label)
+}
+
+traced! {
+/// Stash all changes (including untracked files) under a nocur-labelled message
+#[tauri::command]
+async fn stash_workspace(path: String, label: String) -> Result<(), String> {
+    let output = Command::new("git")
+        .args(["stash", "push", "--include-untracked", "-m", &nocur_stash_message(&label)])
+        .current_dir(&path)
+        .output()
+        .map_err(|e| format!("Failed to run git stash: {}", e))?;
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(format!("Failed to stash changes: {}", stderr.trim()));
+    }
+    Ok(())
+}
+}
+
+traced! {
+/// Pop the most recent stash created by `stash_workspace` with this label
+#[tauri::command]
+async fn unstash_workspace(path: String, label: String) -> Result<(), String> {
+    let list_output = Command::new("git")
+        .args(["stash", "list", "--format=%gd%x09%s"])
+        .current_dir(&path)
+        .output()
+        .map_err(|e| format!("Failed to list stashes: {}", e))?;
+
+    // Subjects look like "On main: nocur: <label>"
+    let message = nocur_stash_message(&label);
+    let stash_ref = String::from_utf8_lossy(&list_output.stdout)
+        .lines()
+        .find_map(|line| {
+            let (stash_ref, subject) = line.split_once('\t')?;
+            subject.ends_with(&message).then(|| stash_ref.to_string())
+        })
+        .ok_or_else(|| format!("No nocur stash labelled '{}'", label))?;
+
+    let output = Command::new("git")
+        .args(["stash", "pop", &stash_ref])
+        .current_dir(&path)
+        .output()
+        .map_err(|e| format!("Failed to run git stash pop: {}", e))?;
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(format!("Failed to restore stash: {}", stderr.trim()));
     }
+    Ok(())
+}
+}
 
-    // Parse status and build file list
-    let mut files = Vec::new();
-    let mut total_additions = 0u32;
-    let mut total_deletions = 0u32;
+// ============ Git Diff/Status Commands ============
 
-    for line in status_str.lines() {
-        if line.len() < 3 {
-            continue;
-        }
-        let status = line[..2].trim().to_string();
-        let file_path = line[3..].to_string();
+/// `get_git_diff_stats` returns the legacy shape when asked for it
+#[derive(Debug, Clone, Serialize)]
+#[serde(untagged)]
+pub enum GitDiffStatsResponse {
+    Split(git_diff::GitDiffStats),
+    Legacy(git_diff::LegacyGitDiffStats),
+}
 
-        let (additions, deletions) = file_stats.get(&file_path).copied().unwrap_or((0, 0));
-        total_additions += additions;
-        total_deletions += deletions;
+traced! {
+/// Staged, unstaged and untracked changes. `legacy` returns the old single
+/// `files` list instead, until the diff panel moves to the split shape.
+#[tauri::command]
+async fn get_git_diff_stats(
+    path: Option<String>,
+    session_id: Option<String>,
+    legacy: Option<bool>,
+    session_context: State<'_, Mutex<SessionContext>>,
+) -> Result<GitDiffStatsResponse, String> {
+    let working_dir = resolve_working_dir(path, session_id.as_deref(), session_context.inner()).unwrap_or_else(|| {
+        std::env::current_dir()
+            .map(|p| p.to_string_lossy().to_string())
+            .unwrap_or_else(|_| ".".to_string())
+    });
 
-        files.push(GitChangedFile {
-            path: file_path,
-            status,
-            additions,
-            deletions,
-        });
+    let stats = match prefetch::get::<git_diff::GitDiffStats>(PrefetchItem::GitDiffStats, &working_dir) {
+        Some(stats) => stats,
+        None => git_diff::diff_stats(&working_dir)?,
+    };
+    if legacy.unwrap_or(false) {
+        Ok(GitDiffStatsResponse::Legacy(git_diff::to_legacy(stats, &working_dir)))
+    } else {
+        Ok(GitDiffStatsResponse::Split(stats))
     }
-
-    Ok(GitDiffStats {
-        total_additions,
-        total_deletions,
-        files,
-    })
+}
 }
 
+traced! {
 #[tauri::command]
 async fn get_file_diff(path: String, file_path: String) -> Result<String, String> {
     let output = Command::new("git")
@@ -1775,6 +4593,87 @@ async fn get_file_diff(path: String, file_path: String) -> Result<String, String
 
     Ok(String::from_utf8_lossy(&output.stdout).to_string())
 }
+}
+
+traced! {
+/// `get_file_diff` parsed into hunks, with the file's language and, for Swift
+/// and JSON, token classes for each line. Line content stops after
+/// `max_content_bytes` (2 MB by default); later hunks come back header only.
+#[tauri::command]
+async fn get_file_diff_structured(
+    path: String,
+    file_path: String,
+    max_content_bytes: Option<usize>,
+) -> Result<git_diff::FileDiff, String> {
+    let limit = max_content_bytes.unwrap_or(git_diff::DEFAULT_MAX_CONTENT_BYTES);
+    tokio::task::spawn_blocking(move || git_diff::file_diff(&path, &file_path, limit))
+        .await
+        .map_err(|e| format!("Diff task failed: {}", e))?
+}
+}
+
+traced! {
+/// Size, line count and whether it's binary, so the viewer can decide to
+/// page before asking for content
+#[tauri::command]
+async fn get_file_stats(path: String, file_path: String) -> Result<file_reader::FileStats, String> {
+    tokio::task::spawn_blocking(move || file_reader::stats(&path, &file_path))
+        .await
+        .map_err(|e| format!("File stats task failed: {}", e))?
+}
+}
+
+traced! {
+/// A file in the project, or with `range` just those lines. Files over 1 MB
+/// have to be read by range.
+#[tauri::command]
+async fn read_project_file(
+    path: String,
+    file_path: String,
+    range: Option<file_reader::LineRange>,
+) -> Result<file_reader::FileChunk, String> {
+    tokio::task::spawn_blocking(move || file_reader::read(&path, &file_path, range))
+        .await
+        .map_err(|e| format!("File read task failed: {}", e))?
+}
+}
+
+// ============ Git Merge Conflict Commands ============
+
+traced! {
+/// Unmerged files with their conflict hunk counts
+#[tauri::command]
+async fn list_merge_conflicts(path: String) -> Result<Vec<git_conflicts::MergeConflict>, String> {
+    git_conflicts::list_merge_conflicts(&path)
+}
+}
+
+traced! {
+/// Base, ours and theirs versions of a conflicted file
+#[tauri::command]
+async fn get_conflict_content(path: String, file: String) -> Result<git_conflicts::ConflictContent, String> {
+    git_conflicts::get_conflict_content(&path, &file)
+}
+}
+
+traced! {
+/// Resolve a conflicted file with `"ours"`, `"theirs"` or `{ "content": "..." }` and stage it
+#[tauri::command]
+async fn resolve_conflict(
+    path: String,
+    file: String,
+    resolution: git_conflicts::ConflictResolution,
+) -> Result<(), String> {
+    git_conflicts::resolve_conflict(&path, &file, resolution)
+}
+}
+
+traced! {
+#[tauri::command]
+async fn abort_merge(path: String) -> Result<(), String> {
+    git_conflicts::abort_merge(&path)
+}
+}
 
 // ============ Open In Commands ============
 
@@ -1802,14 +4701,20 @@ pub struct OpenInInfo {
     pub apps: Vec<InstalledApp>,
 }
 
-/// Detect projects in a directory and installed apps
-#[tauri::command]
-async fn get_open_in_options(path: String) -> Result<OpenInInfo, String> {
+/// Open In scan results. Installed apps are kept for the app's lifetime; detected
+/// projects are keyed by directory mtime, which changes whenever a top-level entry
+/// is added or removed.
+#[derive(Default)]
+pub struct OpenInCache {
+    apps: Option<Vec<InstalledApp>>,
+    projects: std::collections::HashMap<String, (SystemTime, Vec<DetectedProject>)>,
+}
+
+fn detect_projects(path: &str) -> Vec<DetectedProject> {
     let mut projects = Vec::new();
-    let mut apps = Vec::new();
 
     // Detect projects in the directory
-    if let Ok(entries) = fs::read_dir(&path) {
+    if let Ok(entries) = fs::read_dir(path) {
         for entry in entries.filter_map(|e| e.ok()) {
             let entry_path = entry.path();
             let name = entry_path.file_name()
@@ -1837,7 +4742,7 @@ async fn get_open_in_options(path: String) -> Result<OpenInInfo, String> {
             else if name == "Package.swift" {
                 projects.push(DetectedProject {
                     project_type: "swift-package".to_string(),
-                    name: PathBuf::from(&path).file_name()
+                    name: Path::new(path).file_name()
                         .and_then(|n| n.to_str())
                         .unwrap_or("Package")
                         .to_string(),
@@ -1848,7 +4753,7 @@ async fn get_open_in_options(path: String) -> Result<OpenInInfo, String> {
             else if name == "Cargo.toml" {
                 projects.push(DetectedProject {
                     project_type: "cargo".to_string(),
-                    name: PathBuf::from(&path).file_name()
+                    name: Path::new(path).file_name()
                         .and_then(|n| n.to_str())
                         .unwrap_or("Cargo")
                         .to_string(),
@@ -1859,7 +4764,7 @@ async fn get_open_in_options(path: String) -> Result<OpenInInfo, String> {
             else if name == "package.json" {
                 projects.push(DetectedProject {
                     project_type: "node".to_string(),
-                    name: PathBuf::from(&path).file_name()
+                    name: Path::new(path).file_name()
                         .and_then(|n| n.to_str())
                         .unwrap_or("Node")
                         .to_string(),
@@ -1869,6 +4774,12 @@ async fn get_open_in_options(path: String) -> Result<OpenInInfo, String> {
         }
     }
 
+    projects
+}
+
+fn detect_installed_apps() -> Vec<InstalledApp> {
+    let mut apps = Vec::new();
+
     // Check for installed apps
     let app_checks = vec![
         ("xcode", "Xcode", "/Applications/Xcode.app"),
@@ -1906,9 +4817,167 @@ async fn get_open_in_options(path: String) -> Result<OpenInInfo, String> {
         icon: None,
     });
 
-    Ok(OpenInInfo { projects, apps })
+    apps
+}
+
+fn cached_installed_apps(cache: &Mutex<OpenInCache>, force_refresh: bool) -> Vec<InstalledApp> {
+    if !force_refresh {
+        if let Some(apps) = cache.lock().apps.clone() {
+            return apps;
+        }
+    }
+    let apps = detect_installed_apps();
+    cache.lock().apps = Some(apps.clone());
+    apps
+}
+
+fn cached_detected_projects(cache: &Mutex<OpenInCache>, path: &str, force_refresh: bool) -> Vec<DetectedProject> {
+    let mtime = fs::metadata(path).and_then(|m| m.modified()).ok();
+    if !force_refresh {
+        if let (Some(mtime), Some((cached_mtime, projects))) = (mtime, cache.lock().projects.get(path)) {
+            if *cached_mtime == mtime {
+                return projects.clone();
+            }
+        }
+    }
+    let projects = detect_projects(path);
+    if let Some(mtime) = mtime {
+        cache.lock().projects.insert(path.to_string(), (mtime, projects.clone()));
+    }
+    projects
+}
+
+traced! {
+/// Detect projects in a directory and installed apps
+#[tauri::command]
+async fn get_open_in_options(
+    path: String,
+    force_refresh: Option<bool>,
+    cache: State<'_, Mutex<OpenInCache>>,
+) -> Result<OpenInInfo, String> {
+    let force_refresh = force_refresh.unwrap_or(false);
+    Ok(OpenInInfo {
+        projects: cached_detected_projects(cache.inner(), &path, force_refresh),
+        apps: cached_installed_apps(cache.inner(), force_refresh),
+    })
+}
+}
+
+traced! {
+/// Detect Xcode/SwiftPM/Cargo/Node projects at the top level of a directory
+#[tauri::command]
+async fn get_detected_projects(
+    path: String,
+    force_refresh: Option<bool>,
+    cache: State<'_, Mutex<OpenInCache>>,
+) -> Result<Vec<DetectedProject>, String> {
+    Ok(cached_detected_projects(cache.inner(), &path, force_refresh.unwrap_or(false)))
+}
+}
+
+traced! {
+/// Editors, terminals and Finder available to open a project in
+#[tauri::command]
+async fn get_installed_apps(
+    force_refresh: Option<bool>,
+    cache: State<'_, Mutex<OpenInCache>>,
+) -> Result<Vec<InstalledApp>, String> {
+    Ok(cached_installed_apps(cache.inner(), force_refresh.unwrap_or(false)))
+}
+}
+
+// =============================================================================
+// Prefetch
+// =============================================================================
+
+/// Run one prefetch load and cache what it returns
+async fn prefetch_item(app_handle: &tauri::AppHandle, item: PrefetchItem, project_path: String) -> prefetch::PrefetchReady {
+    let started = Instant::now();
+    let path = project_path.clone();
+    match item {
+        PrefetchItem::Devices => {
+            let result = load_devices(app_handle.state()).await;
+            prefetch::insert(item, "", &project_path, started, result)
+        }
+        PrefetchItem::GitInfo => {
+            let result = get_git_info(Some(path)).await;
+            prefetch::insert(item, &project_path, &project_path, started, result)
+        }
+        PrefetchItem::GitDiffStats => {
+            let result = tauri::async_runtime::spawn_blocking(move || git_diff::diff_stats(&path))
+                .await
+                .map_err(|e| format!("Failed to load diff stats: {}", e))
+                .and_then(|stats| stats);
+            prefetch::insert(item, &project_path, &project_path, started, result)
+        }
+        PrefetchItem::Sessions => {
+            let result = list_claude_code_sessions(path).await;
+            prefetch::insert(item, &project_path, &project_path, started, result)
+        }
+        PrefetchItem::Skills => {
+            let result = list_skills(Some(path)).await;
+            prefetch::insert(item, &project_path, &project_path, started, result)
+        }
+        PrefetchItem::Playbook => {
+            let result = tauri::async_runtime::spawn_blocking(move || ace::load_playbook(&path))
+                .await
+                .map_err(|e| format!("Failed to load playbook: {}", e))
+                .and_then(|playbook| playbook);
+            prefetch::insert(item, &project_path, &project_path, started, result)
+        }
+        PrefetchItem::OpenIn => {
+            // Fills `OpenInCache`, which get_open_in_options already serves from
+            let result = get_open_in_options(path, Some(true), app_handle.state()).await.map(|_| ());
+            prefetch::insert(item, &project_path, &project_path, started, result)
+        }
+    }
+}
+
+traced! {
+/// Start the slow loads for a project that was just opened, all at once.
+/// Returns right away; each load sends `prefetch-ready` when it's done.
+#[tauri::command]
+async fn prefetch_project_state(project_path: String, app_handle: tauri::AppHandle) -> Result<Vec<String>, String> {
+    prefetch::invalidate(Some(&project_path), None);
+    for item in PrefetchItem::ALL {
+        let app_handle = app_handle.clone();
+        let project_path = project_path.clone();
+        tauri::async_runtime::spawn(async move {
+            let ready = prefetch_item(&app_handle, item, project_path).await;
+            match &ready.error {
+                Some(e) => log::warn!("Prefetch of {} failed after {}ms: {}", ready.item, ready.duration_ms, e),
+                None => log::info!("Prefetched {} in {}ms", ready.item, ready.duration_ms),
+            }
+            event_journal::emit_stamped(&app_handle, "prefetch-ready", ready);
+        });
+    }
+    Ok(PrefetchItem::ALL.iter().map(|item| item.name().to_string()).collect())
+}
+}
+
+traced! {
+/// Drop prefetched results so the next calls load afresh. Without a project
+/// or item, everything goes.
+#[tauri::command]
+async fn invalidate_prefetch(project_path: Option<String>, item: Option<String>) -> Result<(), String> {
+    let item = match item {
+        Some(name) => Some(PrefetchItem::from_name(&name).ok_or_else(|| format!("Unknown prefetch item: {}", name))?),
+        None => None,
+    };
+    prefetch::invalidate(project_path.as_deref(), item);
+    Ok(())
+}
+}
+
+traced! {
+/// How long each prefetch for a project took and whether it's still served
+#[tauri::command]
+async fn get_prefetch_status(project_path: String) -> Result<Vec<prefetch::PrefetchStatus>, String> {
+    Ok(prefetch::status(&project_path))
+}
 }
 
+traced! {
 /// Open a path in a specific application
 #[tauri::command]
 async fn open_in_app(app_id: String, path: String, project_path: Option<String>) -> Result<(), String> {
@@ -2033,7 +5102,9 @@ async fn open_in_app(app_id: String, path: String, project_path: Option<String>)
 
     Ok(())
 }
+}
 
+traced! {
 /// Copy path to clipboard
 #[tauri::command]
 async fn copy_to_clipboard(text: String) -> Result<(), String> {
@@ -2051,6 +5122,7 @@ async fn copy_to_clipboard(text: String) -> Result<(), String> {
 
     Ok(())
 }
+}
 
 // ============ Git Worktree Commands ============
 
@@ -2061,8 +5133,41 @@ pub struct GitWorktree {
     pub branch: String,
     pub is_main: bool,
     pub session_id: Option<String>,
+    /// What the worktree's branch was created from, when it was given explicitly
+    #[serde(default)]
+    pub base_ref: Option<String>,
+    /// The owning session's badge, so the worktree can be shown in its colors
+    #[serde(default)]
+    pub identity: Option<session_identity::SessionIdentity>,
+}
+
+/// Branch config keys recording what a named session worktree belongs to; the
+/// branch name no longer says once the user picks it
+const WORKTREE_SESSION_KEY: &str = "nocursession";
+const WORKTREE_BASE_KEY: &str = "nocurbase";
+
+/// `branch.<name>.<key>` values for every branch that has `key` set
+fn branch_config_values(working_dir: &str, key: &str) -> std::collections::HashMap<String, String> {
+    let pattern = format!("^branch\\..*\\.{}$", key);
+    let Ok(output) = Command::new("git")
+        .args(["config", "--get-regexp", &pattern])
+        .current_dir(working_dir)
+        .output()
+    else {
+        return std::collections::HashMap::new();
+    };
+    let suffix = format!(".{}", key);
+    String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .filter_map(|line| {
+            let (name, value) = line.split_once(' ')?;
+            let branch = name.strip_prefix("branch.")?.strip_suffix(&suffix)?;
+            Some((branch.to_string(), value.to_string()))
+        })
+        .collect()
 }
 
+traced! {
 #[tauri::command]
 async fn list_worktrees(path: Option<String>) -> Result<Vec<GitWorktree>, String> {
     let working_dir = path.unwrap_or_else(|| {
@@ -2071,9 +5176,14 @@ async fn list_worktrees(path: Option<String>) -> Result<Vec<GitWorktree>, String
             .unwrap_or_else(|_| ".".to_string())
     });
 
+    read_worktrees(&working_dir)
+}
+}
+
+fn read_worktrees(working_dir: &str) -> Result<Vec<GitWorktree>, String> {
     let output = Command::new("git")
         .args(["worktree", "list", "--porcelain"])
-        .current_dir(&working_dir)
+        .current_dir(working_dir)
         .output()
         .map_err(|e| format!("Failed to list worktrees: {}", e))?;
 
@@ -2085,6 +5195,8 @@ async fn list_worktrees(path: Option<String>) -> Result<Vec<GitWorktree>, String
     let stdout = String::from_utf8_lossy(&output.stdout);
     let mut worktrees = Vec::new();
     let mut current_worktree: Option<GitWorktree> = None;
+    let sessions = branch_config_values(working_dir, WORKTREE_SESSION_KEY);
+    let bases = branch_config_values(working_dir, WORKTREE_BASE_KEY);
 
     for line in stdout.lines() {
         if line.starts_with("worktree ") {
@@ -2099,6 +5211,8 @@ async fn list_worktrees(path: Option<String>) -> Result<Vec<GitWorktree>, String
                 branch: String::new(),
                 is_main: false,
                 session_id: None,
+                base_ref: None,
+                identity: None,
             });
         } else if line.starts_with("branch ") {
             if let Some(ref mut wt) = current_worktree {
@@ -2107,9 +5221,13 @@ async fn list_worktrees(path: Option<String>) -> Result<Vec<GitWorktree>, String
                 );
                 wt.branch = branch.to_string();
                 // Check if this is a session worktree (branch name contains "session-")
-                if branch.starts_with("session-") {
+                if let Some(session_id) = sessions.get(branch) {
+                    wt.session_id = Some(session_id.clone());
+                } else if branch.starts_with("session-") {
                     wt.session_id = Some(branch.strip_prefix("session-").unwrap_or(branch).to_string());
                 }
+                wt.base_ref = bases.get(branch).cloned();
+                wt.identity = wt.session_id.as_deref().and_then(session_identity::get);
             }
         } else if line == "bare" {
             // Skip bare worktrees
@@ -2130,74 +5248,434 @@ async fn list_worktrees(path: Option<String>) -> Result<Vec<GitWorktree>, String
     Ok(worktrees)
 }
 
+/// Find the worktree created for a session by `create_session_worktree`, if any.
+/// Unnamed worktrees only carry the first 8 characters of the session ID.
+fn find_session_worktree(project_dir: &str, session_id: &str) -> Option<String> {
+    let short_id: String = session_id.chars().take(8).collect();
+    read_worktrees(project_dir)
+        .ok()?
+        .into_iter()
+        .find(|wt| !wt.is_main && wt.session_id.as_deref().is_some_and(|id| id == session_id || id == short_id))
+        .map(|wt| wt.path)
+}
+
+traced! {
 #[tauri::command]
 async fn create_session_worktree(
     path: String,
     session_id: String,
+    base_ref: Option<String>,
+    name: Option<String>,
+    session_context: State<'_, Mutex<SessionContext>>,
 ) -> Result<GitWorktree, String> {
-    // Create branch name from session ID
-    let branch_name = format!("session-{}", session_id.chars().take(8).collect::<String>());
-    let worktree_path = format!("{}/../{}-worktree", path, branch_name);
+    // Named worktrees get `session/<name>` and `<name>-worktree`; otherwise both come from the session ID
+    let (branch_name, worktree_path) = match name.as_deref() {
+        Some(name) => {
+            let slug = paths::slugify(name).ok_or_else(|| format!("Invalid worktree name: {:?}", name))?;
+            (format!("session/{}", slug), paths::sibling_worktree_path(Path::new(&path), &slug)?)
+        }
+        None => {
+            let branch_name = format!("session-{}", paths::sanitize_path_component(&session_id.chars().take(8).collect::<String>()));
+            let worktree_path = paths::sibling_worktree_path(Path::new(&path), &branch_name)?;
+            (branch_name, worktree_path)
+        }
+    };
+
+    if worktree_path.exists() {
+        return Err(format!("Worktree directory already exists: {}", worktree_path.display()));
+    }
 
-    // First create the branch from current HEAD
+    let base_ref = base_ref.map(|r| r.trim().to_string()).filter(|r| !r.is_empty());
+    if let Some(base) = &base_ref {
+        let verified = Command::new("git")
+            .args(["rev-parse", "--verify", "--quiet", &format!("{}^{{commit}}", base)])
+            .current_dir(&path)
+            .output()
+            .map_err(|e| format!("Failed to check base ref: {}", e))?;
+        if !verified.status.success() {
+            return Err(format!("Base ref {} does not name a commit", base));
+        }
+    }
+
+    // First create the branch, from the base ref or current HEAD
+    let mut branch_args = vec!["branch", branch_name.as_str()];
+    if let Some(base) = &base_ref {
+        branch_args.push(base);
+    }
     let branch_output = Command::new("git")
-        .args(["branch", &branch_name])
+        .args(&branch_args)
         .current_dir(&path)
         .output()
         .map_err(|e| format!("Failed to create branch: {}", e))?;
 
     if !branch_output.status.success() {
         let stderr = String::from_utf8_lossy(&branch_output.stderr);
-        // Branch might already exist, which is fine
+        // Branch might already exist, which is fine unless it was meant to start from base_ref
         if !stderr.contains("already exists") {
             return Err(format!("Failed to create branch: {}", stderr));
         }
+        if base_ref.is_some() {
+            return Err(format!("Branch {} already exists; pick another name", branch_name));
+        }
+    }
+
+    if name.is_some() {
+        let _ = Command::new("git")
+            .args(["config", &format!("branch.{}.{}", branch_name, WORKTREE_SESSION_KEY), &session_id])
+            .current_dir(&path)
+            .output();
+    }
+    if let Some(base) = &base_ref {
+        let _ = Command::new("git")
+            .args(["config", &format!("branch.{}.{}", branch_name, WORKTREE_BASE_KEY), base])
+            .current_dir(&path)
+            .output();
     }
 
     // Create the worktree
     let output = Command::new("git")
-        .args(["worktree", "add", &worktree_path, &branch_name])
+        .args(["worktree", "add"])
+        .arg(&worktree_path)
+        .arg(&branch_name)
         .current_dir(&path)
         .output()
         .map_err(|e| format!("Failed to create worktree: {}", e))?;
 
-    if !output.status.success() {
-        let stderr = String::from_utf8_lossy(&output.stderr);
-        return Err(format!("Failed to create worktree: {}", stderr));
-    }
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(format!("Failed to create worktree: {}", stderr));
+    }
+
+    // Resolve the full path
+    let full_path = std::fs::canonicalize(&worktree_path)
+        .unwrap_or(worktree_path)
+        .to_string_lossy()
+        .to_string();
+
+    session_context.lock().set_working_dir(&session_id, &full_path);
+
+    Ok(GitWorktree {
+        path: full_path,
+        branch: branch_name,
+        is_main: false,
+        identity: session_identity::get(&session_id),
+        session_id: Some(session_id),
+        base_ref,
+    })
+}
+}
+
+traced! {
+/// Remove a worktree; with `dry_run`, report what would go instead
+#[tauri::command]
+async fn remove_worktree(
+    worktree_path: String,
+    force: Option<bool>,
+    confirm_token: Option<String>,
+    dry_run: Option<bool>,
+    session_context: State<'_, Mutex<SessionContext>>,
+    safe_mode: State<'_, safe_mode::SafeMode>,
+) -> Result<dry_run::Outcome<()>, String> {
+    let force = force.unwrap_or(false);
+    let removal = plan_worktree_removal(&worktree_path)?;
+    if dry_run.unwrap_or(false) {
+        if !force && !removal.uncommitted.is_empty() {
+            return Err(format!(
+                "Worktree has {} uncommitted change(s); it can only be removed with force",
+                removal.uncommitted.len()
+            ));
+        }
+        let mut report = dry_run::DryRunReport::deleting(vec![PathBuf::from(&removal.canonical_path)]);
+        report.would_modify = removal.uncommitted;
+        return Ok(dry_run::Outcome::DryRun(report));
+    }
+
+    // A forced remove throws away the worktree's uncommitted changes
+    if force {
+        safe_mode.authorize("force-removing a worktree", confirm_token.as_deref())?;
+    }
+    remove_git_worktree(&worktree_path, force, None)?;
+
+    let mut context = session_context.lock();
+    context.forget_working_dir(&worktree_path);
+    context.forget_working_dir(&removal.canonical_path);
+
+    Ok(dry_run::Outcome::Applied(()))
+}
+}
+
+traced! {
+/// Start `options.count` sessions (2 by default) on the same prompt, each in a
+/// new worktree from the project's HEAD and with `options.models` in turn.
+/// They run alongside the chat's session. An attempt that fails to start is
+/// listed with its error; it's only an error if none start.
+#[tauri::command]
+async fn dispatch_parallel_sessions(
+    project_path: String,
+    prompt: String,
+    options: Option<parallel_sessions::DispatchOptions>,
+    app_handle: tauri::AppHandle,
+) -> Result<parallel_sessions::ParallelGroup, String> {
+    parallel_sessions::dispatch(&app_handle, &project_path, &prompt, options.unwrap_or_default()).await
+}
+}
+
+traced! {
+/// Each attempt of a parallel group: running, finished or failed, with its
+/// diff stats and cost, and for finished ones a build of its worktree.
+/// `complete` is false while any is still running.
+#[tauri::command]
+async fn compare_parallel_results(
+    group_id: String,
+    app_handle: tauri::AppHandle,
+) -> Result<parallel_sessions::ParallelComparison, String> {
+    parallel_sessions::compare(&app_handle, &group_id).await
+}
+}
+
+traced! {
+/// Commit and merge `session_id`'s worktree into the project, stop the
+/// group's sessions and remove all its worktrees and branches. The other
+/// attempts' changes are thrown away, which safe mode asks to confirm. With
+/// `dry_run`, report what would be merged and removed instead.
+#[tauri::command]
+async fn adopt_parallel_result(
+    group_id: String,
+    session_id: String,
+    confirm_token: Option<String>,
+    dry_run: Option<bool>,
+    app_handle: tauri::AppHandle,
+    safe_mode: State<'_, safe_mode::SafeMode>,
+) -> Result<dry_run::Outcome<parallel_sessions::AdoptResult>, String> {
+    let plan = parallel_sessions::plan_adopt(&app_handle, &group_id, &session_id)?;
+    if dry_run.unwrap_or(false) {
+        let report = tokio::task::spawn_blocking(move || parallel_sessions::adopt_dry_run(&plan))
+            .await
+            .map_err(|e| format!("Adopt task failed: {}", e))??;
+        return Ok(dry_run::Outcome::DryRun(report));
+    }
+
+    safe_mode.authorize("discarding the other parallel attempts", confirm_token.as_deref())?;
+    let result = tokio::task::spawn_blocking(move || parallel_sessions::adopt(&app_handle, &group_id, &plan))
+        .await
+        .map_err(|e| format!("Adopt task failed: {}", e))??;
+    Ok(dry_run::Outcome::Applied(result))
+}
+}
+
+struct WorktreeRemoval {
+    canonical_path: String,
+    /// Files `git status` lists, which a forced remove throws away
+    uncommitted: Vec<String>,
+}
+
+/// Resolve the worktree `remove_worktree` removes, and what it would lose
+fn plan_worktree_removal(worktree_path: &str) -> Result<WorktreeRemoval, String> {
+    let canonical_path = std::fs::canonicalize(worktree_path)
+        .map(|p| p.to_string_lossy().to_string())
+        .unwrap_or_else(|_| worktree_path.to_string());
+    let output = Command::new("git")
+        .args(["status", "--porcelain", "--untracked-files=all"])
+        .current_dir(&canonical_path)
+        .output()
+        .map_err(|e| format!("Failed to read worktree status: {}", e))?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(format!("Not a worktree: {}: {}", worktree_path, stderr.trim()));
+    }
+    let uncommitted = String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .filter_map(|line| line.get(3..))
+        .map(String::from)
+        .collect();
+    Ok(WorktreeRemoval { canonical_path, uncommitted })
+}
+
+/// `git worktree remove`, run from `repo_dir` when given
+fn remove_git_worktree(worktree_path: &str, force: bool, repo_dir: Option<&Path>) -> Result<(), String> {
+    let mut args = vec!["worktree", "remove"];
+    if force {
+        args.push("--force");
+    }
+    args.push(worktree_path);
+
+    let mut cmd = Command::new("git");
+    cmd.args(&args);
+    if let Some(dir) = repo_dir {
+        cmd.current_dir(dir);
+    }
+    let output = cmd
+        .output()
+        .map_err(|e| format!("Failed to remove worktree: {}", e))?;
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(format!("Failed to remove worktree: {}", stderr));
+    }
+
+    Ok(())
+}
+
+// ============ Launch Environment Profiles ============
+
+traced! {
+/// Env profiles in the project's `.nocur/env`, for `run_project`'s `env_profile`
+#[tauri::command]
+async fn list_env_profiles(project_path: String) -> Result<Vec<env_profiles::EnvProfile>, String> {
+    env_profiles::list_profiles(&project_path)
+}
+}
+
+traced! {
+#[tauri::command]
+async fn read_env_profile(project_path: String, name: String) -> Result<std::collections::BTreeMap<String, String>, String> {
+    env_profiles::read_profile(&project_path, &name)
+}
+}
+
+traced! {
+/// Create or replace a profile with `vars`
+#[tauri::command]
+async fn save_env_profile(
+    project_path: String,
+    name: String,
+    vars: std::collections::BTreeMap<String, String>,
+) -> Result<env_profiles::EnvProfile, String> {
+    env_profiles::save_profile(&project_path, &name, &vars)
+}
+}
+
+traced! {
+/// Saved launch override sets by name, for `run_project`'s `launch_overrides.preset`
+#[tauri::command]
+async fn list_launch_overrides(
+    project_path: String,
+) -> Result<std::collections::BTreeMap<String, launch_overrides::LaunchOverrides>, String> {
+    Ok(launch_overrides::list_saved(&project_path))
+}
+}
+
+traced! {
+/// Create or replace a named set of launch overrides; returns the arguments it makes
+#[tauri::command]
+async fn save_launch_overrides(
+    project_path: String,
+    name: String,
+    overrides: launch_overrides::LaunchOverrides,
+) -> Result<Vec<String>, String> {
+    launch_overrides::save(&project_path, &name, &overrides)?;
+    launch_overrides::launch_arguments(&overrides)
+}
+}
+
+traced! {
+#[tauri::command]
+async fn delete_launch_overrides(project_path: String, name: String) -> Result<(), String> {
+    launch_overrides::delete(&project_path, &name)
+}
+}
+
+// ============ CLAUDE.md ============
+
+traced! {
+/// The project's CLAUDE.md split into sections, with the hash writes need
+#[tauri::command]
+async fn read_claude_md(project_path: String) -> Result<claude_md::ClaudeMdDocument, String> {
+    claude_md::read(&project_path)
+}
+}
+
+traced! {
+/// Replace one section's body, creating the file from the project template
+/// if missing. Returns a conflict with the current file when `expected_hash`
+/// no longer matches it.
+#[tauri::command]
+async fn update_claude_md_section(
+    project_path: String,
+    heading: String,
+    new_content: String,
+    expected_hash: Option<String>,
+) -> Result<claude_md::ClaudeMdWrite, String> {
+    claude_md::update_section(&project_path, &heading, &new_content, expected_hash.as_deref())
+}
+}
 
-    // Resolve the full path
-    let full_path = std::fs::canonicalize(&worktree_path)
-        .map(|p| p.to_string_lossy().to_string())
-        .unwrap_or(worktree_path);
+traced! {
+/// Add a rule to CLAUDE.md's Guidelines, for "remember this" in the chat
+#[tauri::command]
+async fn append_claude_md_rule(project_path: String, text: String) -> Result<claude_md::ClaudeMdWrite, String> {
+    claude_md::append_rule(&project_path, &text)
+}
+}
 
-    Ok(GitWorktree {
-        path: full_path,
-        branch: branch_name,
-        is_main: false,
-        session_id: Some(session_id),
-    })
+// ============ Project Storage ============
+
+traced! {
+/// Disk used by a project's DerivedData, build logs, session worktrees and recordings
+#[tauri::command]
+async fn get_project_storage_report(
+    project_path: String,
+    session_context: State<'_, Mutex<SessionContext>>,
+) -> Result<storage::StorageReport, String> {
+    let in_use = session_context.lock().working_dirs_in_use();
+    tauri::async_runtime::spawn_blocking(move || storage::project_storage_report(&project_path, &in_use))
+        .await
+        .map_err(|e| format!("Storage report task failed: {}", e))?
+}
 }
 
+traced! {
+/// The Swift packages pinned in the project's Package.resolved, with licenses
+/// and, with `check_latest`, whether a newer release is tagged. An empty
+/// report for projects without packages.
 #[tauri::command]
-async fn remove_worktree(worktree_path: String, force: Option<bool>) -> Result<(), String> {
-    let mut args = vec!["worktree", "remove"];
-    if force.unwrap_or(false) {
-        args.push("--force");
-    }
-    args.push(&worktree_path);
+async fn list_package_dependencies(project_path: String, check_latest: Option<bool>) -> Result<packages::PackageReport, String> {
+    let check_latest = check_latest.unwrap_or(false);
+    tauri::async_runtime::spawn_blocking(move || packages::list_dependencies(&project_path, check_latest))
+        .await
+        .map_err(|e| format!("Package dependency task failed: {}", e))?
+}
+}
 
-    let output = Command::new("git")
-        .args(&args)
-        .output()
-        .map_err(|e| format!("Failed to remove worktree: {}", e))?;
+traced! {
+/// The build caches of a project's main checkout and each of its worktrees, with sizes
+#[tauri::command]
+async fn list_build_caches(project_path: String) -> Result<Vec<storage::BuildCache>, String> {
+    tauri::async_runtime::spawn_blocking(move || storage::list_build_caches(&project_path))
+        .await
+        .map_err(|e| format!("Build cache listing task failed: {}", e))?
+}
+}
 
-    if !output.status.success() {
-        let stderr = String::from_utf8_lossy(&output.stderr);
-        return Err(format!("Failed to remove worktree: {}", stderr));
+traced! {
+/// Delete the selected storage categories for a project; with `dry_run`, report what would go instead
+#[tauri::command]
+async fn cleanup_project_storage(
+    project_path: String,
+    options: storage::CleanupOptions,
+    dry_run: Option<bool>,
+    session_context: State<'_, Mutex<SessionContext>>,
+) -> Result<dry_run::Outcome<storage::CleanupResult>, String> {
+    let in_use = session_context.lock().working_dirs_in_use();
+    if dry_run.unwrap_or(false) {
+        return tauri::async_runtime::spawn_blocking(move || storage::cleanup_dry_run(&project_path, &options, &in_use))
+            .await
+            .map_err(|e| format!("Storage cleanup task failed: {}", e))?
+            .map(dry_run::Outcome::DryRun);
     }
+    let result = tauri::async_runtime::spawn_blocking(move || {
+        storage::cleanup_project_storage(&project_path, &options, &in_use)
+    })
+    .await
+    .map_err(|e| format!("Storage cleanup task failed: {}", e))??;
 
-    Ok(())
+    // Sessions can't be mapped to a worktree that no longer exists
+    let mut context = session_context.lock();
+    for path in &result.removed {
+        context.forget_working_dir(path);
+    }
+    Ok(dry_run::Outcome::Applied(result))
+}
 }
 
 // ============ Claude Code Session History ============
@@ -2233,16 +5711,24 @@ pub struct SessionMessage {
     pub tools_used: Option<Vec<ToolUsed>>,
 }
 
+traced! {
 /// Load messages from a Claude Code session file
 #[tauri::command]
 async fn load_session_messages(project_path: String, session_id: String) -> Result<Vec<SessionMessage>, String> {
-    let home = std::env::var("HOME").map_err(|_| "HOME not set")?;
-    let claude_projects_dir = PathBuf::from(&home).join(".claude").join("projects");
+    read_session_messages(&project_path, &session_id)
+}
+}
+
+/// Parse the user and assistant messages out of a Claude Code session JSONL.
+/// Looks under the project path and each parent up to home, since Claude Code
+/// files sessions by the directory it was started in.
+pub(crate) fn read_session_messages(project_path: &str, session_id: &str) -> Result<Vec<SessionMessage>, String> {
+    let claude_projects_dir = paths::claude_dir()?.join("projects");
 
     // Build list of paths to check (current + parents up to home)
     let mut paths_to_check = Vec::new();
     let mut current = PathBuf::from(&project_path);
-    let home_path = PathBuf::from(&home);
+    let home_path = paths::home_dir()?;
 
     while current.starts_with(&home_path) && current != home_path {
         paths_to_check.push(current.clone());
@@ -2351,11 +5837,14 @@ async fn load_session_messages(project_path: String, session_id: String) -> Resu
     Ok(messages)
 }
 
+traced! {
 /// List Claude Code sessions for a project
 #[tauri::command]
 async fn list_claude_code_sessions(project_path: String) -> Result<Vec<ClaudeCodeSession>, String> {
-    let home = std::env::var("HOME").map_err(|_| "HOME not set")?;
-    let claude_projects_dir = PathBuf::from(&home).join(".claude").join("projects");
+    if let Some(sessions) = prefetch::get::<Vec<ClaudeCodeSession>>(PrefetchItem::Sessions, &project_path) {
+        return Ok(sessions);
+    }
+    let claude_projects_dir = paths::claude_dir()?.join("projects");
 
     if !claude_projects_dir.exists() {
         return Ok(vec![]);
@@ -2370,7 +5859,7 @@ async fn list_claude_code_sessions(project_path: String) -> Result<Vec<ClaudeCod
     // Build list of paths to check: current path + all parent paths up to home
     let mut paths_to_check = Vec::new();
     let mut current = PathBuf::from(&project_path);
-    let home_path = PathBuf::from(&home);
+    let home_path = paths::home_dir()?;
 
     // Add current path and walk up to home directory
     while current.starts_with(&home_path) && current != home_path {
@@ -2489,6 +5978,7 @@ async fn list_claude_code_sessions(project_path: String) -> Result<Vec<ClaudeCod
 
     Ok(sessions)
 }
+}
 
 // ============ User Preferences ============
 
@@ -2502,18 +5992,151 @@ pub struct UserPreferences {
     pub agent_mode: Option<String>,  // "build" or "plan"
     #[serde(default)]
     pub session_names: std::collections::HashMap<String, String>,
+    /// Session ID -> its color and emoji; see `session_identity`
+    #[serde(default)]
+    pub session_badges: std::collections::HashMap<String, session_identity::SessionBadge>,
     /// Maps project path to active session ID
     #[serde(default)]
     pub active_sessions: std::collections::HashMap<String, String>,
+    /// Project paths that get a git checkpoint before each agent turn
+    #[serde(default)]
+    pub checkpoint_projects: Vec<String>,
+    /// Warn when starting a session with more uncommitted changes than this
+    #[serde(default)]
+    pub workspace_warning_threshold: Option<usize>,
+    /// Also append the command trace to ~/.nocur/logs/commands.jsonl
+    #[serde(default)]
+    pub trace_commands_to_file: bool,
+    /// When to post a notification for a finished build or agent turn
+    #[serde(default)]
+    pub notification_policy: notifications::NotificationPolicy,
+    /// Locale for backend messages, e.g. "en"; None means English
+    #[serde(default)]
+    pub locale: Option<String>,
+    /// Seconds without a Claude event mid-turn before `turn_stalled` is
+    /// emitted; None means the default, 0 turns the watchdog off
+    #[serde(default)]
+    pub stall_timeout_secs: Option<u64>,
+    /// Largest `content` a Claude event carries before it's truncated;
+    /// None means the default, 0 never truncates
+    #[serde(default)]
+    pub max_event_content_bytes: Option<usize>,
+    /// Saved simulator log filters by name; the built-in presets aren't stored
+    #[serde(default)]
+    pub log_filters: std::collections::BTreeMap<String, log_filters::LogFilter>,
+    /// Screenshots kept per project before the oldest are pruned; None means
+    /// the default, 0 keeps them all
+    #[serde(default)]
+    pub max_screenshots: Option<usize>,
+    /// Saved session templates; None until the defaults are first written
+    #[serde(default)]
+    pub agent_profiles: Option<Vec<agent_profiles::AgentProfile>>,
+    /// Clean stale compiler caches before a build when the project was last
+    /// built with a different Xcode
+    #[serde(default)]
+    pub auto_clean_on_xcode_change: bool,
+    /// Replacements for the "display" and "agent" screenshot presets, or extra
+    /// presets by name
+    #[serde(default)]
+    pub screenshot_presets: std::collections::BTreeMap<String, screenshot_presets::ScreenshotOptions>,
+    /// Write a session report to ~/.nocur/reports when a session is stopped
+    #[serde(default)]
+    pub session_report_on_stop: bool,
+    /// Keep the agent on a short leash; see `safe_mode`
+    #[serde(default)]
+    pub safe_mode: bool,
+    /// How much slower than the recent median a simulator launch can be, in
+    /// percent, before it's flagged; None means the default
+    #[serde(default)]
+    pub launch_regression_percent: Option<u32>,
+    /// How long an .xcarchive from `archive_project` is kept, in hours; None
+    /// means the default
+    #[serde(default)]
+    pub archive_max_age_hours: Option<u64>,
+    /// Build logs kept per project; None means the default
+    #[serde(default)]
+    pub build_log_retention: Option<usize>,
+    /// Leave secrets in logs, events and reports; see `redaction`
+    #[serde(default)]
+    pub redaction_disabled: bool,
+    /// Redaction patterns switched off, by name
+    #[serde(default)]
+    pub redaction_disabled_patterns: Vec<String>,
+    /// Patterns redacted on top of the built-in ones
+    #[serde(default)]
+    pub redaction_patterns: Vec<redaction::CustomPattern>,
+    /// Device ID -> unix seconds of the last launch on it, for `recent_first`
+    #[serde(default)]
+    pub device_last_used: std::collections::HashMap<String, u64>,
+    /// Claude Code's settings have been imported once; see `claude_settings`
+    #[serde(default)]
+    pub claude_settings_imported: bool,
+    /// Allow and deny rules from ~/.claude/settings.json
+    #[serde(default)]
+    pub claude_permission_rules: claude_settings::PermissionRules,
+    /// Project path -> rules from that project's .claude settings
+    #[serde(default)]
+    pub claude_project_permission_rules: std::collections::BTreeMap<String, claude_settings::PermissionRules>,
+    /// Env from ~/.claude/settings.json, set for every session
+    #[serde(default)]
+    pub claude_env: std::collections::BTreeMap<String, String>,
+    #[serde(default)]
+    pub schema_version: migrations::SchemaVersion<PREFERENCES_SCHEMA_VERSION>,
+}
+
+const PREFERENCES_SCHEMA_VERSION: u32 = PREFERENCES_MIGRATIONS.len() as u32;
+
+const PREFERENCES_MIGRATIONS: &[migrations::Migration] = &[migrations::Migration {
+    description: "Write out every preference, with defaults for those missing",
+    apply: fill_preference_defaults,
+}];
+
+/// Older builds wrote files missing `model`, `skills` or `skipPermissions`,
+/// which have no serde default. A value of the wrong type fails the
+/// migration, leaving the file as it was.
+fn fill_preference_defaults(document: &mut migrations::Document) -> Result<(), String> {
+    let defaults = serde_json::to_value(UserPreferences::default())
+        .map_err(|e| format!("Failed to serialize preferences: {}", e))?;
+    let (serde_json::Value::Object(mut merged), Some(existing)) = (defaults, document.value.as_object()) else {
+        return Err("not a JSON object".to_string());
+    };
+    merged.extend(existing.clone());
+    let merged = serde_json::Value::Object(merged);
+    serde_json::from_value::<UserPreferences>(merged.clone())
+        .map_err(|e| format!("Failed to parse preferences: {}", e))?;
+    document.value = merged;
+    Ok(())
 }
 
 fn get_preferences_path() -> PathBuf {
-    let home = std::env::var("HOME").unwrap_or_else(|_| ".".to_string());
-    PathBuf::from(home).join(".nocur").join("preferences.json")
+    paths::nocur_dir().join("preferences.json")
 }
 
+fn preferences_files() -> Vec<PathBuf> {
+    let path = get_preferences_path();
+    match path.exists() {
+        true => vec![path],
+        false => vec![],
+    }
+}
+
+traced! {
 #[tauri::command]
 async fn get_user_preferences() -> Result<UserPreferences, String> {
+    read_user_preferences()
+}
+}
+
+traced! {
+/// What the startup migrations did to the on-disk formats; see `migrations`
+#[tauri::command]
+async fn get_migration_report(report: State<'_, migrations::MigrationReport>) -> Result<migrations::MigrationReport, String> {
+    Ok(report.inner().clone())
+}
+}
+
+/// `get_user_preferences` for code that isn't async
+fn read_user_preferences() -> Result<UserPreferences, String> {
     let prefs_path = get_preferences_path();
 
     if prefs_path.exists() {
@@ -2526,8 +6149,30 @@ async fn get_user_preferences() -> Result<UserPreferences, String> {
     }
 }
 
+traced! {
 #[tauri::command]
 async fn save_user_preferences(preferences: UserPreferences) -> Result<(), String> {
+    write_user_preferences(&preferences)?;
+    command_trace::set_persist(preferences.trace_commands_to_file);
+    messages::set_locale(preferences.locale.as_deref());
+    apply_redaction_preferences(&preferences);
+    Ok(())
+}
+}
+
+fn apply_redaction_preferences(preferences: &UserPreferences) {
+    let errors = redaction::configure(
+        !preferences.redaction_disabled,
+        &preferences.redaction_disabled_patterns,
+        &preferences.redaction_patterns,
+    );
+    for error in errors {
+        log::warn!("{}", error);
+    }
+}
+
+/// Write the preferences file without applying the settings it changes
+fn write_user_preferences(preferences: &UserPreferences) -> Result<(), String> {
     let prefs_path = get_preferences_path();
 
     // Create .nocur directory if needed
@@ -2536,74 +6181,243 @@ async fn save_user_preferences(preferences: UserPreferences) -> Result<(), Strin
             .map_err(|e| format!("Failed to create preferences directory: {}", e))?;
     }
 
-    let content = serde_json::to_string_pretty(&preferences)
+    let content = serde_json::to_string_pretty(preferences)
         .map_err(|e| format!("Failed to serialize preferences: {}", e))?;
 
     fs::write(&prefs_path, content)
-        .map_err(|e| format!("Failed to write preferences: {}", e))?;
+        .map_err(|e| format!("Failed to write preferences: {}", e))
+}
 
-    Ok(())
+// ============ Configuration Export ============
+
+traced! {
+/// Zip the preferences, and with `options` the ACE playbooks, skills and
+/// session names, to `output_path` for setting up another Mac. Anything the
+/// redaction patterns flag as a secret is left out and listed in the manifest.
+#[tauri::command]
+async fn export_configuration(
+    output_path: String,
+    options: Option<config_bundle::ExportOptions>,
+) -> Result<config_bundle::Manifest, String> {
+    tokio::task::spawn_blocking(move || config_bundle::export(Path::new(&output_path), options.unwrap_or_default()))
+        .await
+        .map_err(|e| format!("Export task failed: {}", e))?
+}
 }
 
-// City names for stable session naming
-const CITY_NAMES: &[&str] = &[
-    "tokyo", "paris", "london", "berlin", "sydney", "cairo", "mumbai", "seoul",
-    "rome", "vienna", "prague", "lisbon", "dublin", "oslo", "stockholm", "helsinki",
-    "amsterdam", "brussels", "zurich", "milan", "barcelona", "madrid", "athens",
-    "istanbul", "dubai", "singapore", "bangkok", "hanoi", "manila", "jakarta",
-    "nairobi", "lagos", "casablanca", "capetown", "montreal", "vancouver", "seattle",
-    "denver", "austin", "miami", "boston", "chicago", "portland", "phoenix",
-    "havana", "lima", "bogota", "santiago", "buenosaires", "rio", "saopaulo",
-    "reykjavik", "tallinn", "riga", "vilnius", "warsaw", "budapest", "bucharest",
-    "sofia", "belgrade", "zagreb", "ljubljana", "bratislava", "kyiv", "minsk"
-];
+traced! {
+/// What's in an exported configuration and what importing it with `strategy`
+/// would conflict with, without changing anything
+#[tauri::command]
+async fn preview_configuration_import(
+    path: String,
+    strategy: config_bundle::ImportStrategy,
+) -> Result<config_bundle::ImportPreview, String> {
+    tokio::task::spawn_blocking(move || config_bundle::preview(Path::new(&path), strategy))
+        .await
+        .map_err(|e| format!("Import preview task failed: {}", e))?
+}
+}
 
-/// Get or create a stable city name for a session ID
+traced! {
+/// Import an exported configuration: `merge` keeps what's here on a conflict,
+/// `replace` takes the bundle's. The configuration before the import is saved
+/// to ~/.nocur/backups first.
 #[tauri::command]
-async fn get_session_name(session_id: String) -> Result<String, String> {
-    let prefs_path = get_preferences_path();
+async fn import_configuration(
+    path: String,
+    strategy: config_bundle::ImportStrategy,
+    app_handle: tauri::AppHandle,
+) -> Result<config_bundle::ImportResult, String> {
+    let result = tokio::task::spawn_blocking(move || config_bundle::import(Path::new(&path), strategy))
+        .await
+        .map_err(|e| format!("Import task failed: {}", e))??;
+    let prefs = read_user_preferences()?;
+    command_trace::set_persist(prefs.trace_commands_to_file);
+    messages::set_locale(prefs.locale.as_deref());
+    apply_redaction_preferences(&prefs);
+    apply_claude_permission_rules(&app_handle, &prefs);
+    Ok(result)
+}
+}
 
-    // Load existing preferences
-    let mut prefs: UserPreferences = if prefs_path.exists() {
-        let content = fs::read_to_string(&prefs_path)
-            .map_err(|e| format!("Failed to read preferences: {}", e))?;
-        serde_json::from_str(&content).unwrap_or_default()
-    } else {
-        UserPreferences::default()
-    };
+// ============ Log Filters ============
+
+traced! {
+/// Built-in presets followed by the user's saved log filters
+#[tauri::command]
+fn list_log_filters() -> Result<Vec<log_filters::SavedLogFilter>, String> {
+    Ok(log_filters::list(&read_user_preferences()?.log_filters))
+}
+}
+
+traced! {
+/// Save a log filter under `name`, replacing one with the same name
+#[tauri::command]
+fn save_log_filter(name: String, filter: log_filters::LogFilter) -> Result<Vec<log_filters::SavedLogFilter>, String> {
+    let name = name.trim().to_string();
+    log_filters::validate(&name, &filter)?;
+    let mut preferences = read_user_preferences()?;
+    preferences.log_filters.insert(name, filter);
+    write_user_preferences(&preferences)?;
+    Ok(log_filters::list(&preferences.log_filters))
+}
+}
+
+traced! {
+#[tauri::command]
+fn delete_log_filter(name: String) -> Result<Vec<log_filters::SavedLogFilter>, String> {
+    if log_filters::is_builtin(&name) {
+        return Err(format!("'{}' is a built-in filter and can't be deleted", name));
+    }
+    let mut preferences = read_user_preferences()?;
+    if preferences.log_filters.remove(&name).is_none() {
+        return Err(format!("No saved log filter named '{}'", name));
+    }
+    write_user_preferences(&preferences)?;
+    Ok(log_filters::list(&preferences.log_filters))
+}
+}
 
-    // Check if we already have a name for this session
-    if let Some(name) = prefs.session_names.get(&session_id) {
-        return Ok(name.clone());
+/// Saved agent profiles, writing the defaults the first time
+fn read_agent_profiles() -> Result<Vec<agent_profiles::AgentProfile>, String> {
+    let mut preferences = read_user_preferences()?;
+    if agent_profiles::ensure_defaults(&mut preferences.agent_profiles) {
+        write_user_preferences(&preferences)?;
     }
+    Ok(preferences.agent_profiles.unwrap_or_default())
+}
 
-    // Generate a new name - pick one not already used
-    let used_names: std::collections::HashSet<&String> = prefs.session_names.values().collect();
-    let available_name = CITY_NAMES
-        .iter()
-        .find(|&&name| !used_names.contains(&name.to_string()))
-        .map(|s| s.to_string())
-        .unwrap_or_else(|| {
-            // If all names used, generate one with a suffix
-            let base_name = CITY_NAMES[prefs.session_names.len() % CITY_NAMES.len()];
-            format!("{}-{}", base_name, prefs.session_names.len() / CITY_NAMES.len() + 1)
-        });
+traced! {
+/// Saved agent profiles; "Builder" and "Planner" are created the first time
+#[tauri::command]
+fn list_agent_profiles() -> Result<Vec<agent_profiles::AgentProfile>, String> {
+    read_agent_profiles()
+}
+}
 
-    // Save the new mapping
-    prefs.session_names.insert(session_id, available_name.clone());
+traced! {
+/// Save an agent profile, replacing one with the same name
+#[tauri::command]
+fn save_agent_profile(profile: agent_profiles::AgentProfile) -> Result<Vec<agent_profiles::AgentProfile>, String> {
+    let profile = agent_profiles::AgentProfile { name: profile.name.trim().to_string(), ..profile };
+    agent_profiles::validate(&profile)?;
+    let mut preferences = read_user_preferences()?;
+    agent_profiles::ensure_defaults(&mut preferences.agent_profiles);
+    let profiles = preferences.agent_profiles.get_or_insert_with(Vec::new);
+    agent_profiles::upsert(profiles, profile);
+    write_user_preferences(&preferences)?;
+    Ok(preferences.agent_profiles.unwrap_or_default())
+}
+}
 
-    // Write back to file
-    if let Some(parent) = prefs_path.parent() {
-        let _ = fs::create_dir_all(parent);
+traced! {
+#[tauri::command]
+fn delete_agent_profile(name: String) -> Result<Vec<agent_profiles::AgentProfile>, String> {
+    let mut preferences = read_user_preferences()?;
+    agent_profiles::ensure_defaults(&mut preferences.agent_profiles);
+    let profiles = preferences.agent_profiles.get_or_insert_with(Vec::new);
+    if agent_profiles::find(profiles, &name).is_none() {
+        return Err(format!("No agent profile named '{}'", name));
     }
-    let content = serde_json::to_string_pretty(&prefs)
-        .map_err(|e| format!("Failed to serialize preferences: {}", e))?;
-    fs::write(&prefs_path, content)
-        .map_err(|e| format!("Failed to write preferences: {}", e))?;
+    profiles.retain(|p| p.name != name);
+    write_user_preferences(&preferences)?;
+    Ok(preferences.agent_profiles.unwrap_or_default())
+}
+}
+
+// ============ Command Trace ============
+
+/// Recently invoked commands, newest first
+#[tauri::command]
+fn get_command_trace(limit: Option<usize>) -> Vec<command_trace::CommandTraceEntry> {
+    command_trace::recent(limit)
+}
 
-    Ok(available_name)
+traced! {
+/// Toggle appending the command trace to ~/.nocur/logs/commands.jsonl
+#[tauri::command]
+async fn set_command_trace_to_file(enabled: bool) -> Result<(), String> {
+    let mut prefs = get_user_preferences().await.unwrap_or_default();
+    prefs.trace_commands_to_file = enabled;
+    save_user_preferences(prefs).await
+}
+}
+
+// ============ Checkpoints ============
+
+traced! {
+/// Toggle per-turn git checkpoints for a project
+#[tauri::command]
+async fn set_checkpoints_enabled(project_path: String, enabled: bool) -> Result<(), String> {
+    let mut prefs = get_user_preferences().await.unwrap_or_default();
+    prefs.checkpoint_projects.retain(|p| p != &project_path);
+    if enabled {
+        prefs.checkpoint_projects.push(project_path);
+    }
+    save_user_preferences(prefs).await
+}
+}
+
+traced! {
+/// List a session's checkpoints, newest first
+#[tauri::command]
+async fn list_checkpoints(
+    session_id: String,
+    path: Option<String>,
+    session_context: State<'_, Mutex<SessionContext>>,
+) -> Result<Vec<checkpoints::Checkpoint>, String> {
+    let working_dir = resolve_working_dir(path, Some(&session_id), session_context.inner())
+        .ok_or("No working directory known for this session")?;
+    checkpoints::list_checkpoints(&working_dir, &session_id)
+}
+}
+
+traced! {
+/// Reset the working tree to a checkpoint. Discards uncommitted changes, so the
+/// caller must pass `confirm: true` once the user has agreed, and in safe mode
+/// a `confirm_token` too. A `dry_run` needs neither and reports what would change.
+#[tauri::command]
+async fn restore_checkpoint(
+    id: String,
+    session_id: String,
+    confirm: bool,
+    confirm_token: Option<String>,
+    path: Option<String>,
+    dry_run: Option<bool>,
+    session_context: State<'_, Mutex<SessionContext>>,
+    safe_mode: State<'_, safe_mode::SafeMode>,
+) -> Result<dry_run::Outcome<()>, String> {
+    let working_dir = resolve_working_dir(path, Some(&session_id), session_context.inner())
+        .ok_or("No working directory known for this session")?;
+    if dry_run.unwrap_or(false) {
+        return checkpoints::restore_dry_run(&working_dir, &id).map(dry_run::Outcome::DryRun);
+    }
+    if !confirm {
+        return Err("Restoring a checkpoint discards uncommitted changes; confirmation required".to_string());
+    }
+    safe_mode.authorize("restoring a checkpoint", confirm_token.as_deref())?;
+    checkpoints::restore_checkpoint(&working_dir, &id).map(dry_run::Outcome::Applied)
+}
+}
+
+traced! {
+/// Get or create a stable city name for a session ID
+#[tauri::command]
+async fn get_session_name(session_id: String) -> Result<String, String> {
+    session_identity::assign(&session_id).map(|identity| identity.name)
+}
+}
+
+traced! {
+/// The session's name, color and emoji, assigned now if it has none yet
+#[tauri::command]
+async fn get_session_identity(session_id: String) -> Result<session_identity::SessionIdentity, String> {
+    session_identity::assign(&session_id)
+}
 }
 
+traced! {
 /// Get all session name mappings
 #[tauri::command]
 async fn get_session_names() -> Result<std::collections::HashMap<String, String>, String> {
@@ -2618,7 +6432,9 @@ async fn get_session_names() -> Result<std::collections::HashMap<String, String>
         Ok(std::collections::HashMap::new())
     }
 }
+}
 
+traced! {
 /// Get the active session ID for a project
 #[tauri::command]
 async fn get_active_session(project_path: String) -> Result<Option<String>, String> {
@@ -2633,7 +6449,9 @@ async fn get_active_session(project_path: String) -> Result<Option<String>, Stri
         Ok(None)
     }
 }
+}
 
+traced! {
 /// Set the active session ID for a project
 #[tauri::command]
 async fn set_active_session(project_path: String, session_id: String) -> Result<(), String> {
@@ -2664,6 +6482,7 @@ async fn set_active_session(project_path: String, session_id: String) -> Result<
 
     Ok(())
 }
+}
 
 // ============ Simulator Log Streaming ============
 
@@ -2691,9 +6510,12 @@ impl SimulatorLogState {
 #[serde(rename_all = "camelCase")]
 pub struct SimulatorLogEntry {
     pub timestamp: u64,
-    pub level: String,      // "debug", "info", "warning", "error", "fault"
+    pub level: String,      // "debug", "info", "warning", "error", "fault", "runtime_issue"
     pub process: String,
     pub message: String,
+    /// The `runtime_issues` rule that matched, when `level` is "runtime_issue"
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rule_id: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize)]
@@ -2702,11 +6524,75 @@ pub struct LogStreamEvent {
     pub entries: Vec<SimulatorLogEntry>,
 }
 
+/// `device-log-started`; the filter and predicate are only set for simulator streams
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DeviceLogStarted {
+    pub device_id: String,
+    pub bundle_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub filter: Option<log_filters::SavedLogFilter>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub predicate: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DeviceLogError {
+    pub error: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DeviceLogStopped {
+    pub exit_status: Option<i32>,
+}
+
+/// Quote a value as an NSPredicate string literal
+fn predicate_literal(value: &str) -> String {
+    format!("'{}'", value.replace('\\', "\\\\").replace('\'', "\\'"))
+}
+
+/// Build the `log stream --predicate` filter for an app. The process name is the
+/// executable (target) name rather than the bundle ID, and os_log subsystems are
+/// usually prefixed with the bundle ID, so match any of those.
+#[cfg_attr(not(target_os = "macos"), allow(dead_code))]
+fn build_log_predicate(
+    bundle_id: Option<&str>,
+    executable_name: Option<&str>,
+    subsystems: &[String],
+) -> Option<String> {
+    let mut clauses = Vec::new();
+    if let Some(exe) = executable_name.filter(|e| !e.is_empty()) {
+        clauses.push(format!("process == {}", predicate_literal(exe)));
+    }
+    if let Some(bid) = bundle_id.filter(|b| !b.is_empty()) {
+        clauses.push(format!("subsystem BEGINSWITH {}", predicate_literal(bid)));
+        // Older launches without an executable name still match on the bundle ID
+        if executable_name.is_none() {
+            clauses.push(format!("process == {}", predicate_literal(bid)));
+        }
+    }
+    for subsystem in subsystems.iter().filter(|s| !s.is_empty()) {
+        clauses.push(format!("subsystem == {}", predicate_literal(subsystem)));
+    }
+
+    if clauses.is_empty() {
+        None
+    } else {
+        Some(clauses.join(" OR "))
+    }
+}
+
+traced! {
 /// Start streaming simulator logs
 #[cfg(target_os = "macos")]
 #[tauri::command]
 async fn start_simulator_logs(
     bundle_id: Option<String>,
+    executable_name: Option<String>,
+    subsystems: Option<Vec<String>>,
+    filter_name: Option<String>,
     app_handle: tauri::AppHandle,
     state: State<'_, Arc<SimulatorLogState>>,
 ) -> Result<(), String> {
@@ -2714,7 +6600,46 @@ async fn start_simulator_logs(
         return Ok(()); // Already streaming
     }
 
+    let Some(filter_name) = filter_name else {
+        // Filter to the app's process and subsystems if provided
+        let predicate = build_log_predicate(
+            bundle_id.as_deref(),
+            executable_name.as_deref(),
+            subsystems.as_deref().unwrap_or_default(),
+        );
+        return spawn_simulator_log_stream(
+            state.inner().clone(), "booted", predicate, None, bundle_id.as_deref(), None, app_handle,
+        );
+    };
+
+    let mut active = log_filters::find(&read_user_preferences()?.log_filters, &filter_name)
+        .ok_or_else(|| format!("No log filter named '{}'", filter_name))?;
+    // Subsystems passed alongside the filter add to its own
+    active.filter.subsystems.extend(subsystems.unwrap_or_default());
+    let app_predicate = build_log_predicate(bundle_id.as_deref(), executable_name.as_deref(), &[]);
+    let predicate = log_filters::to_predicate(&active.filter, app_predicate);
+    let level = log_filters::stream_level(&active.filter);
+    spawn_simulator_log_stream(
+        state.inner().clone(), "booted", predicate, level, bundle_id.as_deref(), Some(active), app_handle,
+    )
+}
+}
+
+/// Start `log stream` on a simulator and pump its output into the log state in the
+/// background. The process is running by the time this returns; `device-log-started`
+/// reports the device, app and the log filter in effect, if any.
+#[cfg(target_os = "macos")]
+fn spawn_simulator_log_stream(
+    state: Arc<SimulatorLogState>,
+    device: &str,
+    predicate: Option<String>,
+    level: Option<&str>,
+    bundle_id: Option<&str>,
+    active_filter: Option<log_filters::SavedLogFilter>,
+    app_handle: tauri::AppHandle,
+) -> Result<(), String> {
     state.is_streaming.store(true, Ordering::SeqCst);
+    app_handle.state::<runtime_issues::RuntimeIssues>().reload();
 
     // Clear existing logs
     {
@@ -2722,56 +6647,63 @@ async fn start_simulator_logs(
         logs.clear();
     }
 
-    let state_clone = state.inner().clone();
-    let app_handle_clone = app_handle.clone();
+    // Build the log stream command
+    let mut cmd = Command::new("xcrun");
+    cmd.args(["simctl", "spawn", device, "log", "stream", "--style", "compact"]);
+    if let Some(ref predicate) = predicate {
+        cmd.args(["--predicate", predicate]);
+    }
+    if let Some(level) = level {
+        cmd.args(["--level", level]);
+    }
 
-    // Spawn log streaming in background
-    std::thread::spawn(move || {
-        // Build the log stream command
-        let mut cmd = Command::new("xcrun");
-        cmd.args(["simctl", "spawn", "booted", "log", "stream", "--style", "compact"]);
+    cmd.stdout(Stdio::piped());
+    cmd.stderr(Stdio::piped());
 
-        // Filter by bundle ID if provided
-        if let Some(ref bid) = bundle_id {
-            cmd.args(["--predicate", &format!("subsystem == '{}' OR process == '{}'", bid, bid)]);
+    let mut child = match cmd.spawn() {
+        Ok(c) => c,
+        Err(e) => {
+            log::error!("Failed to start log stream: {}", e);
+            state.is_streaming.store(false, Ordering::SeqCst);
+            return Err(format!("Failed to start log stream: {}", e));
         }
+    };
 
-        cmd.stdout(Stdio::piped());
-        cmd.stderr(Stdio::piped());
-
-        let mut child = match cmd.spawn() {
-            Ok(c) => c,
-            Err(e) => {
-                log::error!("Failed to start log stream: {}", e);
-                state_clone.is_streaming.store(false, Ordering::SeqCst);
-                return;
-            }
-        };
+    // Store child PID for later killing
+    let pid = child.id();
+    *state.child_pid.write().unwrap_or_else(|e| e.into_inner()) = Some(pid);
 
-        // Store child PID for later killing
-        let pid = child.id();
-        *state_clone.child_pid.write().unwrap_or_else(|e| e.into_inner()) = Some(pid);
+    event_journal::emit_stamped(&app_handle, "device-log-started", DeviceLogStarted {
+        device_id: device.to_string(),
+        bundle_id: bundle_id.map(String::from),
+        filter: active_filter,
+        predicate,
+    });
 
+    // Read the stream in background
+    std::thread::spawn(move || {
         let Some(stdout) = child.stdout.take() else {
             log::error!("Failed to capture log stream stdout");
-            state_clone.is_streaming.store(false, Ordering::SeqCst);
-            *state_clone.child_pid.write().unwrap_or_else(|e| e.into_inner()) = None;
+            state.is_streaming.store(false, Ordering::SeqCst);
+            *state.child_pid.write().unwrap_or_else(|e| e.into_inner()) = None;
             return;
         };
         let reader = BufReader::new(stdout);
 
         for line in reader.lines() {
-            if !state_clone.is_streaming.load(Ordering::SeqCst) {
+            if !state.is_streaming.load(Ordering::SeqCst) {
                 break;
             }
 
             if let Ok(line) = line {
                 // Parse log line (format: "2024-01-01 12:00:00.000000 process[pid] <level> message")
-                let entry = parse_log_line(&line);
+                let mut entry = parse_log_line(&line);
+                entry.message = redaction::redact(&entry.message);
+                runtime_issues::annotate(&app_handle, &mut entry);
 
                 // Store in state
                 {
-                    let mut logs = state_clone.logs.write().unwrap_or_else(|e| e.into_inner());
+                    let mut logs = state.logs.write().unwrap_or_else(|e| e.into_inner());
                     logs.push(entry.clone());
                     // Keep only last 1000 entries
                     if logs.len() > 1000 {
@@ -2780,7 +6712,7 @@ async fn start_simulator_logs(
                 }
 
                 // Emit event to frontend
-                let _ = app_handle_clone.emit("simulator-log", LogStreamEvent {
+                event_journal::emit(&app_handle, event_journal::Channel::Log, None, LogStreamEvent {
                     entries: vec![entry],
                 });
             }
@@ -2788,8 +6720,8 @@ async fn start_simulator_logs(
 
         // Cleanup
         let _ = child.kill();
-        state_clone.is_streaming.store(false, Ordering::SeqCst);
-        *state_clone.child_pid.write().unwrap_or_else(|e| e.into_inner()) = None;
+        state.is_streaming.store(false, Ordering::SeqCst);
+        *state.child_pid.write().unwrap_or_else(|e| e.into_inner()) = None;
     });
 
     Ok(())
@@ -2827,9 +6759,11 @@ fn parse_log_line(line: &str) -> SimulatorLogEntry {
         level,
         process,
         message: line.to_string(),
+        rule_id: None,
     }
 }
 
+traced! {
 /// Stop streaming simulator logs
 #[cfg(target_os = "macos")]
 #[tauri::command]
@@ -2847,7 +6781,9 @@ async fn stop_simulator_logs(
 
     Ok(())
 }
+}
 
+traced! {
 /// Get all captured logs so far
 #[cfg(target_os = "macos")]
 #[tauri::command]
@@ -2857,7 +6793,31 @@ async fn get_simulator_logs(
     let logs = state.logs.read().unwrap_or_else(|e| e.into_inner());
     Ok(logs.clone())
 }
+}
+
+/// Captured simulator logs from `since` (ms since the epoch), sampled to fit
+/// `max_tokens` with every error kept
+pub(crate) fn logs_compact(app_handle: &tauri::AppHandle, max_tokens: Option<usize>, since: Option<u64>) -> Result<context_budget::CompactLogs, String> {
+    let state = app_handle
+        .try_state::<Arc<SimulatorLogState>>()
+        .ok_or("Log capture is not available")?;
+    let logs = state.logs.read().unwrap_or_else(|e| e.into_inner());
+    Ok(context_budget::compact_logs(&logs, max_tokens.unwrap_or(context_budget::DEFAULT_MAX_TOKENS), since))
+}
+
+traced! {
+/// `get_simulator_logs` sampled for the agent
+#[tauri::command]
+async fn get_logs_compact(
+    max_tokens: Option<usize>,
+    since: Option<u64>,
+    app_handle: tauri::AppHandle,
+) -> Result<context_budget::CompactLogs, String> {
+    logs_compact(&app_handle, max_tokens, since)
+}
+}
 
+traced! {
 /// Clear captured logs
 #[cfg(target_os = "macos")]
 #[tauri::command]
@@ -2868,6 +6828,7 @@ async fn clear_simulator_logs(
     logs.clear();
     Ok(())
 }
+}
 
 // ============ Physical Device Log Streaming ============
 
@@ -2886,6 +6847,7 @@ impl PhysicalDeviceLogState {
     }
 }
 
+traced! {
 /// Start streaming logs from a physical device app
 /// This uses `xcrun devicectl device process launch --console` to stream stdout/stderr
 #[cfg(target_os = "macos")]
@@ -2900,10 +6862,25 @@ async fn start_physical_device_logs(
         return Ok(()); // Already streaming
     }
 
-    state.is_streaming.store(true, Ordering::SeqCst);
+    spawn_physical_device_log_stream(state.inner().clone(), device_id, bundle_id, std::collections::BTreeMap::new(), app_handle, None);
+    Ok(())
+}
+}
 
-    let state_clone = state.inner().clone();
-    let app_handle_clone = app_handle.clone();
+/// Launch the app under `devicectl --console` in the background and stream its
+/// output. `launched` is signalled once devicectl reports the app running, and
+/// dropped without a signal if devicectl exits first.
+#[cfg(target_os = "macos")]
+fn spawn_physical_device_log_stream(
+    state: Arc<PhysicalDeviceLogState>,
+    device_id: String,
+    bundle_id: String,
+    env: std::collections::BTreeMap<String, String>,
+    app_handle: tauri::AppHandle,
+    mut launched: Option<std::sync::mpsc::Sender<()>>,
+) {
+    state.is_streaming.store(true, Ordering::SeqCst);
+    app_handle.state::<runtime_issues::RuntimeIssues>().reload();
 
     // Spawn log streaming in background
     std::thread::spawn(move || {
@@ -2915,8 +6892,9 @@ async fn start_physical_device_logs(
             "--device", &device_id,
             "--console",
             "--terminate-existing",
-            &bundle_id
         ]);
+        cmd.args(env_profiles::devicectl_env_args(&env));
+        cmd.arg(&bundle_id);
 
         cmd.stdout(Stdio::piped());
         cmd.stderr(Stdio::piped());
@@ -2925,38 +6903,40 @@ async fn start_physical_device_logs(
             Ok(c) => c,
             Err(e) => {
                 log::error!("Failed to start physical device log stream: {}", e);
-                let _ = app_handle_clone.emit("device-log-error", serde_json::json!({
-                    "error": format!("Failed to start log stream: {}", e)
-                }));
-                state_clone.is_streaming.store(false, Ordering::SeqCst);
+                event_journal::emit_stamped(&app_handle, "device-log-error", DeviceLogError {
+                    error: format!("Failed to start log stream: {}", e),
+                });
+                state.is_streaming.store(false, Ordering::SeqCst);
                 return;
             }
         };
 
         // Store child PID for later killing
         let pid = child.id();
-        *state_clone.child_pid.write().unwrap_or_else(|e| e.into_inner()) = Some(pid);
+        *state.child_pid.write().unwrap_or_else(|e| e.into_inner()) = Some(pid);
 
         // Emit that we started streaming
-        let _ = app_handle_clone.emit("device-log-started", serde_json::json!({
-            "deviceId": device_id,
-            "bundleId": bundle_id
-        }));
+        event_journal::emit_stamped(&app_handle, "device-log-started", DeviceLogStarted {
+            device_id: device_id.clone(),
+            bundle_id: Some(bundle_id.clone()),
+            filter: None,
+            predicate: None,
+        });
 
         let Some(stdout) = child.stdout.take() else {
             log::error!("Failed to capture physical device log stream stdout");
-            let _ = app_handle_clone.emit("device-log-error", serde_json::json!({
-                "error": "Failed to capture stdout".to_string()
-            }));
-            state_clone.is_streaming.store(false, Ordering::SeqCst);
-            *state_clone.child_pid.write().unwrap_or_else(|e| e.into_inner()) = None;
+            event_journal::emit_stamped(&app_handle, "device-log-error", DeviceLogError {
+                error: "Failed to capture stdout".to_string(),
+            });
+            state.is_streaming.store(false, Ordering::SeqCst);
+            *state.child_pid.write().unwrap_or_else(|e| e.into_inner()) = None;
             return;
         };
         let stderr = child.stderr.take();
 
         // Read stdout in a thread
-        let app_handle_stdout = app_handle_clone.clone();
-        let state_stdout = state_clone.clone();
+        let app_handle_stdout = app_handle.clone();
+        let state_stdout = state.clone();
         let stdout_thread = std::thread::spawn(move || {
             let reader = BufReader::new(stdout);
 
@@ -2966,6 +6946,12 @@ async fn start_physical_device_logs(
                 }
 
                 if let Ok(line) = line {
+                    if line.starts_with("Launched application") {
+                        if let Some(launched) = launched.take() {
+                            let _ = launched.send(());
+                        }
+                    }
+
                     // Skip devicectl status messages
                     if line.starts_with("Launched application") || 
                        line.starts_with("Process ") ||
@@ -2989,15 +6975,17 @@ async fn start_physical_device_logs(
                         "info"
                     }.to_string();
 
-                    let entry = SimulatorLogEntry {
+                    let mut entry = SimulatorLogEntry {
                         timestamp,
                         level,
                         process: "app".to_string(),
-                        message: line,
+                        message: redaction::redact(&line),
+                        rule_id: None,
                     };
+                    runtime_issues::annotate(&app_handle_stdout, &mut entry);
 
                     // Emit log entry - reuse the same event type as simulator
-                    let _ = app_handle_stdout.emit("simulator-log", LogStreamEvent {
+                    event_journal::emit(&app_handle_stdout, event_journal::Channel::Log, None, LogStreamEvent {
                         entries: vec![entry],
                     });
                 }
@@ -3006,8 +6994,8 @@ async fn start_physical_device_logs(
 
         // Also read stderr if available
         if let Some(stderr) = stderr {
-            let app_handle_stderr = app_handle_clone.clone();
-            let state_stderr = state_clone.clone();
+            let app_handle_stderr = app_handle.clone();
+            let state_stderr = state.clone();
             std::thread::spawn(move || {
                 let reader = BufReader::new(stderr);
 
@@ -3026,14 +7014,16 @@ async fn start_physical_device_logs(
                             .unwrap_or_default()
                             .as_millis() as u64;
 
-                        let entry = SimulatorLogEntry {
+                        let mut entry = SimulatorLogEntry {
                             timestamp,
                             level: "error".to_string(),
                             process: "app".to_string(),
-                            message: line,
+                            message: redaction::redact(&line),
+                            rule_id: None,
                         };
+                        runtime_issues::annotate(&app_handle_stderr, &mut entry);
 
-                        let _ = app_handle_stderr.emit("simulator-log", LogStreamEvent {
+                        event_journal::emit(&app_handle_stderr, event_journal::Channel::Log, None, LogStreamEvent {
                             entries: vec![entry],
                         });
                     }
@@ -3048,17 +7038,16 @@ async fn start_physical_device_logs(
         let exit_status = child.wait();
         
         // Emit that streaming stopped
-        let _ = app_handle_clone.emit("device-log-stopped", serde_json::json!({
-            "exitStatus": exit_status.map(|s| s.code()).ok().flatten()
-        }));
+        event_journal::emit_stamped(&app_handle, "device-log-stopped", DeviceLogStopped {
+            exit_status: exit_status.map(|s| s.code()).ok().flatten(),
+        });
 
-        state_clone.is_streaming.store(false, Ordering::SeqCst);
-        *state_clone.child_pid.write().unwrap_or_else(|e| e.into_inner()) = None;
+        state.is_streaming.store(false, Ordering::SeqCst);
+        *state.child_pid.write().unwrap_or_else(|e| e.into_inner()) = None;
     });
-
-    Ok(())
 }
 
+traced! {
 /// Stop streaming physical device logs
 #[cfg(target_os = "macos")]
 #[tauri::command]
@@ -3076,6 +7065,113 @@ async fn stop_physical_device_logs(
 
     Ok(())
 }
+}
+
+/// Stop a running stream and wait for its reader thread to clear the PID, so the
+/// old thread's cleanup can't flip `is_streaming` off under a new stream
+#[cfg(target_os = "macos")]
+fn stop_log_stream_and_wait(is_streaming: &AtomicBool, child_pid: &RwLock<Option<u32>>) -> Result<(), String> {
+    let Some(pid) = *child_pid.read().unwrap_or_else(|e| e.into_inner()) else {
+        is_streaming.store(false, Ordering::SeqCst);
+        return Ok(());
+    };
+    is_streaming.store(false, Ordering::SeqCst);
+    let _ = Command::new("kill").args(["-9", &pid.to_string()]).output();
+
+    let deadline = Instant::now() + std::time::Duration::from_secs(2);
+    while child_pid.read().unwrap_or_else(|e| e.into_inner()).is_some() {
+        if Instant::now() >= deadline {
+            return Err("The previous log stream did not stop".to_string());
+        }
+        std::thread::sleep(std::time::Duration::from_millis(50));
+    }
+    Ok(())
+}
+
+/// Start (or restart) the simulator log stream for an app ahead of its launch
+#[cfg(target_os = "macos")]
+fn start_simulator_log_stream(
+    app_handle: &tauri::AppHandle,
+    device: &str,
+    bundle_id: &str,
+    executable_name: Option<&str>,
+) -> Result<(), String> {
+    let state = app_handle
+        .try_state::<Arc<SimulatorLogState>>()
+        .ok_or("Simulator log streaming is not available")?
+        .inner()
+        .clone();
+    stop_log_stream_and_wait(&state.is_streaming, &state.child_pid)?;
+    let predicate = build_log_predicate(Some(bundle_id), executable_name, &[]);
+    spawn_simulator_log_stream(state, device, predicate, None, Some(bundle_id), None, app_handle.clone())
+}
+
+#[cfg(not(target_os = "macos"))]
+fn start_simulator_log_stream(
+    _app_handle: &tauri::AppHandle,
+    _device: &str,
+    _bundle_id: &str,
+    _executable_name: Option<&str>,
+) -> Result<(), String> {
+    Err("Simulator log streaming is only available on macOS".to_string())
+}
+
+/// Stop a stream started for a launch that then failed
+#[cfg(target_os = "macos")]
+fn stop_simulator_log_stream(app_handle: &tauri::AppHandle) {
+    if let Some(state) = app_handle.try_state::<Arc<SimulatorLogState>>() {
+        let _ = stop_log_stream_and_wait(&state.is_streaming, &state.child_pid);
+    }
+}
+
+#[cfg(not(target_os = "macos"))]
+fn stop_simulator_log_stream(_app_handle: &tauri::AppHandle) {}
+
+/// Launch through the devicectl console stream, returning once the app is running
+#[cfg(target_os = "macos")]
+fn launch_with_device_console(
+    app_handle: &tauri::AppHandle,
+    devicectl_id: &str,
+    bundle_id: &str,
+    env: &std::collections::BTreeMap<String, String>,
+) -> Result<(), String> {
+    use std::sync::mpsc::RecvTimeoutError;
+
+    let state = app_handle
+        .try_state::<Arc<PhysicalDeviceLogState>>()
+        .ok_or("Device log streaming is not available")?
+        .inner()
+        .clone();
+    stop_log_stream_and_wait(&state.is_streaming, &state.child_pid)?;
+
+    let (launched, on_launch) = std::sync::mpsc::channel();
+    spawn_physical_device_log_stream(
+        state.clone(),
+        devicectl_id.to_string(),
+        bundle_id.to_string(),
+        env.clone(),
+        app_handle.clone(),
+        Some(launched),
+    );
+
+    let error = match on_launch.recv_timeout(std::time::Duration::from_secs(60)) {
+        Ok(()) => return Ok(()),
+        Err(RecvTimeoutError::Timeout) => "Timed out waiting for devicectl to launch the app",
+        Err(RecvTimeoutError::Disconnected) => "devicectl exited before the app launched",
+    };
+    let _ = stop_log_stream_and_wait(&state.is_streaming, &state.child_pid);
+    Err(error.to_string())
+}
+
+#[cfg(not(target_os = "macos"))]
+fn launch_with_device_console(
+    _app_handle: &tauri::AppHandle,
+    _devicectl_id: &str,
+    _bundle_id: &str,
+    _env: &std::collections::BTreeMap<String, String>,
+) -> Result<(), String> {
+    Err("Device log streaming is only available on macOS".to_string())
+}
 
 // ============ Crash Reports ============
 
@@ -3090,6 +7186,7 @@ pub struct CrashReport {
     pub stack_trace: Option<String>,
 }
 
+traced! {
 /// Get recent crash reports from the simulator
 #[cfg(target_os = "macos")]
 #[tauri::command]
@@ -3097,10 +7194,14 @@ async fn get_crash_reports(
     bundle_id: Option<String>,
     since_timestamp: Option<u64>,
 ) -> Result<Vec<CrashReport>, String> {
-    let home = std::env::var("HOME").map_err(|_| "HOME not set")?;
+    collect_crash_reports(bundle_id.as_deref(), since_timestamp)
+}
+}
 
+/// Crash reports modified at or after `since_timestamp` (Unix seconds), newest first
+fn collect_crash_reports(bundle_id: Option<&str>, since_timestamp: Option<u64>) -> Result<Vec<CrashReport>, String> {
     // Simulator crash logs are in ~/Library/Logs/DiagnosticReports/
-    let crash_dir = PathBuf::from(&home)
+    let crash_dir = paths::home_dir()?
         .join("Library")
         .join("Logs")
         .join("DiagnosticReports");
@@ -3145,7 +7246,7 @@ async fn get_crash_reports(
                     .unwrap_or("");
 
                 // Filter by bundle ID if provided
-                if let Some(ref bid) = bundle_id {
+                if let Some(bid) = bundle_id {
                     if !content.contains(bid) && !file_name.contains(bid) {
                         continue;
                     }
@@ -3183,7 +7284,7 @@ async fn get_crash_reports(
     }
 
     // Sort by timestamp descending
-    reports.sort_by(|a, b| b.timestamp.cmp(&a.timestamp));
+    reports.sort_by_key(|r| std::cmp::Reverse(r.timestamp));
 
     // Limit to most recent 10
     reports.truncate(10);
@@ -3216,31 +7317,59 @@ fn extract_stack_trace(content: &str) -> Option<String> {
     }
 }
 
+/// A file offered for @ reference autocomplete
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FileSuggestion {
+    /// Path relative to the project root
+    pub path: String,
+    /// Last modification, in milliseconds since the Unix epoch
+    pub mtime: Option<u64>,
+    /// "session" for files the agent touched this session, otherwise "project"
+    pub source: String,
+    /// The file is in iCloud Drive but not downloaded; `path` is its real name,
+    /// not the `.name.icloud` placeholder on disk
+    pub cloud_placeholder: bool,
+}
+
+/// `list_project_files` result; `Legacy` is the plain path list older callers expect
+#[derive(Debug, Clone, Serialize)]
+#[serde(untagged)]
+pub enum FileSuggestions {
+    Suggestions(Vec<FileSuggestion>),
+    Legacy(Vec<String>),
+}
+
+/// With no query the whole project is walked so recency ranking sees every file
+const MAX_UNFILTERED_WALK: usize = 10_000;
+
+traced! {
 /// List project files for @ file reference autocomplete
-/// Uses the `ignore` crate to respect .gitignore
+/// Uses the `ignore` crate to respect .gitignore. With an empty query, files the
+/// agent edited this session come first, then everything else by mtime.
 #[tauri::command]
 async fn list_project_files(
-    project_path: String,
+    project_path: Option<String>,
     query: Option<String>,
     limit: Option<usize>,
-) -> Result<Vec<String>, String> {
-    use ignore::WalkBuilder;
+    session_id: Option<String>,
+    legacy: Option<bool>,
+    session_context: State<'_, Mutex<SessionContext>>,
+    claude_state: State<'_, Mutex<ClaudeState>>,
+) -> Result<FileSuggestions, String> {
+    let project_path = resolve_working_dir(project_path, session_id.as_deref(), session_context.inner())
+        .ok_or("No project path provided and no working directory known for this session")?;
 
     let limit = limit.unwrap_or(50);
     let query = query.unwrap_or_default().to_lowercase();
+    let walk_limit = if query.is_empty() { MAX_UNFILTERED_WALK } else { limit * 2 };  // Collect more to filter better
 
-    let mut files: Vec<String> = Vec::new();
+    let mut files: Vec<FileSuggestion> = Vec::new();
 
-    let walker = WalkBuilder::new(&project_path)
-        .hidden(false)  // Don't skip hidden files
-        .git_ignore(true)  // Respect .gitignore
-        .git_global(true)  // Respect global .gitignore
-        .git_exclude(true)  // Respect .git/info/exclude
-        .max_depth(Some(10))  // Limit depth
-        .build();
+    let walker = paths::project_walker(std::path::Path::new(&project_path));
 
     for entry in walker {
-        if files.len() >= limit * 2 {  // Collect more to filter better
+        if files.len() >= walk_limit {
             break;
         }
 
@@ -3254,7 +7383,10 @@ async fn list_project_files(
             continue;
         }
 
-        let path = entry.path();
+        // An evicted iCloud file is listed under the name it'll have once downloaded
+        let placeholder_target = paths::cloud_placeholder_target(entry.path());
+        let cloud_placeholder = placeholder_target.is_some();
+        let path = placeholder_target.as_deref().unwrap_or(entry.path());
 
         // Get relative path from project root
         let relative_path = path.strip_prefix(&project_path)
@@ -3267,17 +7399,27 @@ async fn list_project_files(
             continue;
         }
 
-        files.push(relative_path);
+        let mtime = entry.metadata().ok()
+            .and_then(|m| m.modified().ok())
+            .and_then(|t| t.duration_since(std::time::UNIX_EPOCH).ok())
+            .map(|d| d.as_millis() as u64);
+
+        files.push(FileSuggestion { path: relative_path, mtime, source: "project".to_string(), cloud_placeholder });
     }
 
+    // Mid-download, iCloud can briefly have both the file and its placeholder
+    let downloaded: std::collections::HashSet<String> =
+        files.iter().filter(|f| !f.cloud_placeholder).map(|f| f.path.clone()).collect();
+    files.retain(|f| !f.cloud_placeholder || !downloaded.contains(&f.path));
+
     // Sort and filter by query
     if !query.is_empty() {
         // Score each file by how well it matches the query
-        let mut scored: Vec<(String, i32)> = files
+        let mut scored: Vec<(FileSuggestion, i32)> = files
             .into_iter()
             .filter_map(|f| {
-                let lower = f.to_lowercase();
-                let filename = f.split('/').last().unwrap_or(&f).to_lowercase();
+                let lower = f.path.to_lowercase();
+                let filename = f.path.split('/').last().unwrap_or(&f.path).to_lowercase();
 
                 // Calculate match score
                 let score = if filename == query {
@@ -3298,34 +7440,186 @@ async fn list_project_files(
 
         // Sort by score (descending), then alphabetically
         scored.sort_by(|a, b| {
-            b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0))
+            b.1.cmp(&a.1).then_with(|| a.0.path.cmp(&b.0.path))
         });
 
         files = scored.into_iter().map(|(f, _)| f).take(limit).collect();
     } else {
-        // No query - sort by recency (we don't have mtime, so just alphabetically)
-        files.sort();
+        // No query - most recently modified first
+        files.sort_by(|a, b| b.mtime.cmp(&a.mtime).then_with(|| a.path.cmp(&b.path)));
+
+        // Pin what the agent just touched, in the order it touched them
+        let root = std::path::Path::new(&project_path);
+        let mut pinned: Vec<FileSuggestion> = Vec::new();
+        for edited in claude_state.lock().recent_edits() {
+            let Ok(relative) = std::path::Path::new(&edited).strip_prefix(root) else {
+                continue;
+            };
+            let relative = relative.to_string_lossy().to_string();
+            match files.iter().position(|f| f.path == relative) {
+                Some(index) => {
+                    let mut file = files.remove(index);
+                    file.source = "session".to_string();
+                    pinned.push(file);
+                }
+                // Outside the walk (ignored, or deleted since): only offer it if it still exists
+                None if !pinned.iter().any(|f| f.path == relative) => {
+                    if let Ok(metadata) = std::fs::metadata(&edited) {
+                        let mtime = metadata.modified().ok()
+                            .and_then(|t| t.duration_since(std::time::UNIX_EPOCH).ok())
+                            .map(|d| d.as_millis() as u64);
+                        pinned.push(FileSuggestion { path: relative, mtime, source: "session".to_string(), cloud_placeholder: false });
+                    }
+                }
+                None => {}
+            }
+        }
+
+        pinned.extend(files);
+        files = pinned;
         files.truncate(limit);
     }
 
-    Ok(files)
+    if legacy.unwrap_or(false) {
+        return Ok(FileSuggestions::Legacy(files.into_iter().map(|f| f.path).collect()));
+    }
+    Ok(FileSuggestions::Suggestions(files))
+}
+}
+
+// ============ Network ============
+
+traced! {
+/// Whether the network is reachable, cached for a minute unless `refresh` is set
+#[tauri::command]
+async fn get_network_status(refresh: Option<bool>) -> Result<network::NetworkStatus, String> {
+    let refresh = refresh.unwrap_or(false);
+    tauri::async_runtime::spawn_blocking(move || network::status(refresh))
+        .await
+        .map_err(|e| format!("Failed to check network status: {}", e))
+}
+}
+
+// ============ Event Replay ============
+
+traced! {
+/// Journaled events on `channel` after `since_seq`, for the frontend to catch
+/// up on after a reload. `key` narrows to one session (claude) or build (build).
+#[tauri::command]
+async fn replay_events(
+    channel: event_journal::Channel,
+    since_seq: Option<u64>,
+    key: Option<String>,
+    journal: State<'_, event_journal::EventJournal>,
+) -> Result<event_journal::ReplayResult, String> {
+    Ok(journal.replay(channel, since_seq.unwrap_or(0), key.as_deref()))
+}
+}
+
+// ============ Simulator Runtimes ============
+
+traced! {
+/// Installed simulator runtimes and the ones this Xcode can download
+#[tauri::command]
+async fn list_available_runtimes() -> Result<Vec<runtimes::RuntimeInfo>, String> {
+    runtimes::list_available_runtimes()
+}
+}
+
+traced! {
+/// Download a simulator runtime (latest for this Xcode when no version is given).
+/// Progress arrives as `runtime-download-progress` events; refuses to start on a
+/// nearly full disk unless `ignore_low_disk` is set, and fails with `Offline`
+/// without a connection.
+#[tauri::command]
+async fn download_runtime(
+    platform: String,
+    version: Option<String>,
+    ignore_low_disk: Option<bool>,
+    state: State<'_, Arc<runtimes::RuntimeDownloadState>>,
+    app_handle: tauri::AppHandle,
+) -> Result<(), CommandError> {
+    if !ignore_low_disk.unwrap_or(false) {
+        if let Some(warning) = runtimes::disk_space_warning() {
+            return Err(warning.into());
+        }
+    }
+
+    let state = state.inner().clone();
+    tauri::async_runtime::spawn_blocking(move || {
+        runtimes::download_runtime(&state, &platform, version.as_deref(), &app_handle)
+    })
+    .await
+    .map_err(|e| CommandError::Failed(format!("Runtime download task failed: {}", e)))?
+}
+}
+
+traced! {
+/// Cancel the runtime download in progress
+#[tauri::command]
+async fn cancel_runtime_download(
+    state: State<'_, Arc<runtimes::RuntimeDownloadState>>,
+) -> Result<bool, String> {
+    Ok(runtimes::cancel_download(state.inner()))
+}
+}
+
+// ============ Symbol Index ============
+
+traced! {
+/// Index top-level Swift declarations in a project (incremental by file mtime)
+#[tauri::command]
+async fn build_symbol_index(
+    project_path: String,
+    state: State<'_, Mutex<symbols::SymbolIndexState>>,
+) -> Result<symbols::SymbolIndexReport, String> {
+    symbols::build_symbol_index(state.inner(), &project_path)
+}
+}
+
+traced! {
+/// Look up symbols by name prefix/fuzzy match, optionally filtered by kind
+#[tauri::command]
+async fn query_symbols(
+    project_path: String,
+    query: String,
+    kind: Option<String>,
+    limit: Option<usize>,
+    state: State<'_, Mutex<symbols::SymbolIndexState>>,
+) -> Result<Vec<symbols::Symbol>, String> {
+    symbols::query_symbols(state.inner(), &project_path, &query, kind.as_deref(), limit)
+}
+}
+
+traced! {
+/// Report index size and how many files changed since it was built
+#[tauri::command]
+async fn get_symbol_index_status(
+    project_path: String,
+    state: State<'_, Mutex<symbols::SymbolIndexState>>,
+) -> Result<symbols::SymbolIndexStatus, String> {
+    Ok(symbols::symbol_index_status(state.inner(), &project_path))
+}
 }
 
+traced! {
 /// Write debug snapshot to file for agentic access
 #[cfg(debug_assertions)]
 #[tauri::command]
 async fn write_debug_snapshot(snapshot: String) -> Result<(), String> {
-    let debug_path = std::env::temp_dir().join("nocur-debug.json");
-    fs::write(debug_path, &snapshot)
+    let debug_path = runtime_env::debug_snapshot_path();
+    fs::write(debug_path, redaction::redact(&snapshot))
         .map_err(|e| format!("Failed to write debug snapshot: {}", e))?;
     Ok(())
 }
+}
 
+traced! {
 /// Read debug snapshot from file
 #[cfg(debug_assertions)]
 #[tauri::command]
 async fn read_debug_snapshot() -> Result<String, String> {
-    let debug_path = std::env::temp_dir().join("nocur-debug.json");
+    let debug_path = runtime_env::debug_snapshot_path();
     if debug_path.exists() {
         fs::read_to_string(&debug_path)
             .map_err(|e| format!("Failed to read debug snapshot: {}", e))
@@ -3333,7 +7627,9 @@ async fn read_debug_snapshot() -> Result<String, String> {
         Ok("{}".to_string())
     }
 }
+}
 
+traced! {
 /// Save base64 screenshots to temp files and return their paths
 #[tauri::command]
 async fn save_screenshots_to_temp(
@@ -3365,7 +7661,7 @@ async fn save_screenshots_to_temp(
         }
     }
 
-    let temp_dir = std::env::temp_dir().join("nocur_recordings");
+    let temp_dir = paths::recordings_dir();
     fs::create_dir_all(&temp_dir)
         .map_err(|e| format!("Failed to create temp dir: {}", e))?;
 
@@ -3401,21 +7697,113 @@ async fn save_screenshots_to_temp(
 
     Ok(paths)
 }
+}
+
+/// Project path and session ID of the active Claude session, if any
+fn active_session_env(state: &Mutex<ClaudeState>) -> (Option<String>, Option<String>) {
+    let claude_state = state.lock();
+    match claude_state.session {
+        Some(ref session) => (
+            Some(session.get_working_dir().to_string()),
+            Some(session.get_session_id().to_string()),
+        ),
+        None => (None, None),
+    }
+}
 
+traced! {
 #[tauri::command]
-fn get_shell_env(include_secrets: Option<bool>) -> std::collections::HashMap<String, String> {
+fn get_shell_env(
+    include_secrets: Option<bool>,
+    state: State<'_, Mutex<ClaudeState>>,
+) -> std::collections::HashMap<String, String> {
+    shell_env(include_secrets.unwrap_or(false), state.inner())
+}
+}
+
+/// Environment for embedded terminal shells
+fn shell_env(include_secrets: bool, state: &Mutex<ClaudeState>) -> std::collections::HashMap<String, String> {
     // Allow opting into passing the full environment to the embedded terminal.
     // Default is safe-by-default: pass only a minimal set of non-secret env vars.
-    if include_secrets.unwrap_or(false)
+    let mut env: std::collections::HashMap<String, String> = if include_secrets
         || std::env::var("NOCUR_FULL_SHELL_ENV").is_ok_and(|v| v == "1")
     {
-        return std::env::vars().collect();
-    }
+        std::env::vars().collect()
+    } else {
+        std::env::vars()
+            .filter(|(k, _)| is_safe_shell_env_key(k))
+            .collect()
+    };
+
+    let (project_path, session_id) = active_session_env(state);
+    runtime_env::merge_nocur_env(&mut env, project_path.as_deref(), session_id.as_deref());
+    env
+}
+
+// ============ Terminals ============
+
+traced! {
+/// Start a shell in a backend-owned PTY; output arrives as `terminal-data` events
+#[tauri::command]
+fn create_terminal(
+    working_dir: String,
+    shell: Option<String>,
+    cols: u16,
+    rows: u16,
+    registry: State<'_, terminal::TerminalRegistry>,
+    claude_state: State<'_, Mutex<ClaudeState>>,
+    app_handle: tauri::AppHandle,
+) -> Result<String, String> {
+    let shell = shell
+        .or_else(|| std::env::var("SHELL").ok())
+        .unwrap_or_else(|| "/bin/zsh".to_string());
+    let env = shell_env(false, claude_state.inner());
+    registry.create(&working_dir, &shell, cols, rows, env, app_handle)
+}
+}
+
+// Not traced: called for every keystroke, and the input can be a typed password
+#[tauri::command]
+fn write_terminal(id: String, data: String, registry: State<'_, terminal::TerminalRegistry>) -> Result<(), String> {
+    registry.write(&id, &data)
+}
+
+#[tauri::command]
+fn resize_terminal(
+    id: String,
+    cols: u16,
+    rows: u16,
+    registry: State<'_, terminal::TerminalRegistry>,
+) -> Result<(), String> {
+    registry.resize(&id, cols, rows)
+}
+
+traced! {
+#[tauri::command]
+fn close_terminal(id: String, registry: State<'_, terminal::TerminalRegistry>) -> Result<(), String> {
+    registry.close(&id)
+}
+}
+
+traced! {
+/// Recent output of a running terminal, for redrawing it after a reload
+#[tauri::command]
+fn get_terminal_scrollback(id: String, registry: State<'_, terminal::TerminalRegistry>) -> Result<String, String> {
+    registry.scrollback(&id)
+}
+}
 
-    std::env::vars()
-        .filter(|(k, _)| is_safe_shell_env_key(k))
+traced! {
+/// Current NOCUR_* values handed to spawned tools, for debugging
+#[tauri::command]
+fn get_runtime_env(state: State<'_, Mutex<ClaudeState>>) -> std::collections::HashMap<String, String> {
+    let (project_path, session_id) = active_session_env(state.inner());
+    runtime_env::nocur_env(project_path.as_deref(), session_id.as_deref())
+        .into_iter()
+        .map(|(k, v)| (k.to_string(), v))
         .collect()
 }
+}
 
 fn is_safe_shell_env_key(key: &str) -> bool {
     matches!(
@@ -3454,31 +7842,45 @@ fn is_safe_shell_env_key(key: &str) -> bool {
 // ACE (Agentic Context Engineering) Commands
 // ============================================================================
 
+traced! {
 #[tauri::command]
 fn ace_get_config() -> ace::ACEConfig {
     ace::load_ace_config()
 }
+}
 
+traced! {
 #[tauri::command]
 fn ace_save_config(config: ace::ACEConfig) -> Result<(), String> {
     ace::save_ace_config(&config)
 }
+}
 
+traced! {
 #[tauri::command]
 fn ace_get_playbook(project_path: String) -> Result<Option<ace::Playbook>, String> {
+    if let Some(playbook) = prefetch::get::<Option<ace::Playbook>>(PrefetchItem::Playbook, &project_path) {
+        return Ok(playbook);
+    }
     ace::load_playbook(&project_path)
 }
+}
 
+traced! {
 #[tauri::command]
 fn ace_get_or_create_playbook(project_path: String) -> Result<ace::Playbook, String> {
     ace::get_or_create_playbook(&project_path)
 }
+}
 
+traced! {
 #[tauri::command]
 fn ace_save_playbook(playbook: ace::Playbook) -> Result<(), String> {
     ace::save_playbook(&playbook)
 }
+}
 
+traced! {
 #[tauri::command]
 fn ace_add_bullet(
     project_path: String,
@@ -3487,7 +7889,9 @@ fn ace_add_bullet(
 ) -> Result<ace::Bullet, String> {
     ace::add_bullet(&project_path, section, content)
 }
+}
 
+traced! {
 #[tauri::command]
 fn ace_update_bullet(
     project_path: String,
@@ -3496,12 +7900,16 @@ fn ace_update_bullet(
 ) -> Result<ace::Bullet, String> {
     ace::update_bullet(&project_path, &bullet_id, content)
 }
+}
 
+traced! {
 #[tauri::command]
 fn ace_delete_bullet(project_path: String, bullet_id: String) -> Result<(), String> {
     ace::delete_bullet(&project_path, &bullet_id)
 }
+}
 
+traced! {
 #[tauri::command]
 fn ace_update_bullet_tags(
     project_path: String,
@@ -3509,17 +7917,23 @@ fn ace_update_bullet_tags(
 ) -> Result<(), String> {
     ace::update_bullet_tags(&project_path, tags)
 }
+}
 
+traced! {
 #[tauri::command]
 fn ace_set_enabled(project_path: String, enabled: bool) -> Result<(), String> {
     ace::set_ace_enabled(&project_path, enabled)
 }
+}
 
+traced! {
 #[tauri::command]
 fn ace_get_reflections(project_path: String) -> Result<Vec<ace::StoredReflection>, String> {
     ace::load_reflections(&project_path)
 }
+}
 
+traced! {
 #[tauri::command]
 fn ace_save_reflection(
     project_path: String,
@@ -3527,26 +7941,94 @@ fn ace_save_reflection(
 ) -> Result<(), String> {
     ace::save_reflection(&project_path, reflection)
 }
+}
+
+traced! {
+/// Run the reflector over a session, save the reflection and apply its bullet tags
+#[tauri::command]
+async fn ace_run_reflection(
+    project_path: String,
+    session_id: String,
+    task: Option<String>,
+    outcome: String,
+    app_handle: tauri::AppHandle,
+) -> Result<ace::StoredReflection, String> {
+    let reflection_path = project_path.clone();
+    let reflection = tauri::async_runtime::spawn_blocking(move || {
+        ace_reflect::run_reflection(&reflection_path, &session_id, task.as_deref().unwrap_or(""), &outcome)
+    })
+    .await
+    .map_err(|e| format!("Reflection task failed: {}", e))??;
+
+    ace_curate::maybe_auto_curate(app_handle, project_path);
+    Ok(reflection)
+}
+}
+
+traced! {
+/// Turn uncurated reflections into playbook changes; `dryRun` previews without applying
+#[tauri::command]
+async fn ace_run_curation(
+    project_path: String,
+    options: Option<ace_curate::CurationOptions>,
+) -> Result<ace_curate::CurationReport, String> {
+    tauri::async_runtime::spawn_blocking(move || {
+        ace_curate::run_curation(&project_path, &options.unwrap_or_default())
+    })
+    .await
+    .map_err(|e| format!("Curation task failed: {}", e))?
+}
+}
+
+traced! {
+/// Bullet, tag and reflection counts for a project's playbook
+#[tauri::command]
+fn ace_get_stats(project_path: String) -> ace::PlaybookStats {
+    ace::get_stats(&project_path)
+}
+}
 
+traced! {
 #[tauri::command]
 fn ace_list_playbooks() -> Result<Vec<String>, String> {
     ace::list_playbooks()
 }
+}
 
 // =============================================================================
 // Project Management Commands
 // =============================================================================
 
+traced! {
+/// Create a project from the built-in template or one under ~/.nocur/templates.
+/// A user template's post-create commands stream as `project-template-event`.
+#[tauri::command]
+async fn create_project(
+    request: project::CreateProjectRequest,
+    app_handle: tauri::AppHandle,
+) -> Result<project::ProjectInfo, String> {
+    tauri::async_runtime::spawn_blocking(move || project::create_project(&request, &app_handle))
+        .await
+        .map_err(|e| format!("Project creation task failed: {}", e))?
+}
+}
+
+traced! {
+/// Built-in and user project templates with the variables each one declares
 #[tauri::command]
-fn create_project(request: project::CreateProjectRequest) -> Result<project::ProjectInfo, String> {
-    project::create_project(&request)
+fn list_project_templates() -> Vec<project::ProjectTemplate> {
+    project::list_project_templates()
+}
 }
 
+traced! {
 #[tauri::command]
 fn get_recent_projects() -> Vec<project::ProjectInfo> {
     project::load_recent_projects()
 }
+}
 
+traced! {
 #[tauri::command]
 fn add_to_recent_projects(path: String, app_handle: tauri::AppHandle) -> Result<Vec<project::ProjectInfo>, String> {
     let result = project::add_recent_project(&path)?;
@@ -3554,25 +8036,51 @@ fn add_to_recent_projects(path: String, app_handle: tauri::AppHandle) -> Result<
     menu::update_recent_menu(&app_handle);
     Ok(result)
 }
+}
 
+traced! {
 #[tauri::command]
 fn remove_from_recent_projects(path: String, app_handle: tauri::AppHandle) -> Result<Vec<project::ProjectInfo>, String> {
     let result = project::remove_recent_project(&path)?;
     menu::update_recent_menu(&app_handle);
     Ok(result)
 }
+}
 
+traced! {
 #[tauri::command]
 fn clear_all_recent_projects(app_handle: tauri::AppHandle) -> Result<(), String> {
     project::clear_recent_projects()?;
     menu::update_recent_menu(&app_handle);
     Ok(())
 }
+}
 
+traced! {
 #[tauri::command]
 fn validate_project_path(path: String) -> Result<project::ProjectValidation, String> {
     project::validate_project(&path)
 }
+}
+
+traced! {
+/// Set up a project nocur didn't create: CLAUDE.md (proposed as a diff when
+/// one exists), .claude/settings.local.json, optional .gitignore entries, and
+/// a place in recents
+#[tauri::command]
+async fn setup_existing_project(
+    path: String,
+    update_gitignore: Option<bool>,
+    app_handle: tauri::AppHandle,
+) -> Result<project::ExistingProjectSetup, String> {
+    let update_gitignore = update_gitignore.unwrap_or(false);
+    let setup = tauri::async_runtime::spawn_blocking(move || project::setup_existing_project(&path, update_gitignore))
+        .await
+        .map_err(|e| format!("Failed to set up project: {}", e))??;
+    menu::update_recent_menu(&app_handle);
+    Ok(setup)
+}
+}
 
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
@@ -3586,9 +8094,29 @@ pub fn run() {
         .plugin(tauri_plugin_pty::init())
         .plugin(tauri_plugin_os::init())
         .plugin(tauri_plugin_dialog::init())
+        .plugin(tauri_plugin_notification::init())
+        .manage(notifications::PendingNotification::default())
         .manage(Mutex::new(ClaudeState::new()))
         .manage(Mutex::new(PermissionState::new()))
-        .manage(Mutex::new(AppState::default()));
+        .manage(Mutex::new(AppState::default()))
+        .manage(Mutex::new(SessionContext::default()))
+        .manage(Mutex::new(models::ModelCatalog::default()))
+        .manage(Mutex::new(symbols::SymbolIndexState::default()))
+        .manage(Arc::new(runtimes::RuntimeDownloadState::new()))
+        .manage(Mutex::new(OpenInCache::default()))
+        .manage(terminal::TerminalRegistry::default())
+        .manage(claude_login::ClaudeLogin::default())
+        .manage(safe_mode::SafeMode::new(read_user_preferences().map(|p| p.safe_mode).unwrap_or(false)))
+        .manage(session_reports::SessionTallies::default())
+        .manage(agent_bridge::AgentBridge::new())
+        .manage(control_server::ControlServer::new())
+        .manage(event_journal::EventJournal::new())
+        .manage(run_lock::RunLocks::default())
+        .manage(BuildState::default())
+        .manage(crash_loop::CrashLoops::default())
+        .manage(runtime_issues::RuntimeIssues::default())
+        .manage(parallel_sessions::ParallelSessions::default())
+        .manage(Mutex::new(simulator_input::SimulatorInputState::default()));
 
     #[cfg(target_os = "macos")]
     {
@@ -3607,9 +8135,47 @@ pub fn run() {
                 )?;
             }
 
+            if let Ok(dir) = app.path().resource_dir() {
+                paths::set_resource_dir(dir);
+            }
+            // Before anything else reads the files it may rewrite
+            app.manage(migrations::run_migrations());
+            std::thread::spawn(claude_service::verify_at_startup);
+            event_coalescer::clear_spool();
+
             // Start permission server
             let permission_state = app.state::<Mutex<PermissionState>>();
             permission_state.lock().server.start(app.handle().clone());
+            permissions::spawn_health_timer(app.handle().clone());
+
+            let prefs = read_user_preferences().unwrap_or_default();
+            // Safe mode was read before the preferences were migrated
+            app.state::<safe_mode::SafeMode>().set_enabled(prefs.safe_mode);
+            command_trace::set_persist(prefs.trace_commands_to_file);
+            messages::set_locale(prefs.locale.as_deref());
+            apply_redaction_preferences(&prefs);
+            apply_claude_permission_rules(app.handle(), &prefs);
+
+            // The first run takes over what the user set up for Claude Code
+            if !prefs.claude_settings_imported {
+                let handle = app.handle().clone();
+                tauri::async_runtime::spawn(async move {
+                    match import_claude_settings_into_preferences(&handle, None, false).await {
+                        Ok(report) => log::info!(
+                            "Imported Claude Code settings: {} imported, {} skipped",
+                            report.imported.len(),
+                            report.skipped.len()
+                        ),
+                        Err(e) => log::warn!("Failed to import Claude Code settings: {}", e),
+                    }
+                });
+            }
+
+            // Start the agent command bridge
+            app.state::<agent_bridge::AgentBridge>().start(app.handle().clone());
+
+            // Start the control server for nocur-ctl
+            app.state::<control_server::ControlServer>().start(app.handle().clone());
 
             // Set up application menu (macOS)
             #[cfg(target_os = "macos")]
@@ -3627,55 +8193,155 @@ pub fn run() {
         })
         .invoke_handler(tauri::generate_handler![
             check_claude_code_status,
-            open_claude_login,
+            start_claude_login,
+            submit_claude_login_code,
+            cancel_claude_login,
+            install_claude_service,
             build_project,
             run_project,
+            cancel_run,
+            cancel_build,
+            clean_project,
+            list_schemes,
+            get_build_settings,
+            get_build_failure_context,
+            archive_project,
+            list_signing_identities,
+            run_tests,
+            inspect_xcresult,
+            reset_crash_loop_counter,
+            get_runtime_issue_summary,
+            get_launch_time_history,
+            list_build_history,
+            read_build_log,
+            list_env_profiles,
+            read_env_profile,
+            save_env_profile,
+            list_launch_overrides,
+            save_launch_overrides,
+            delete_launch_overrides,
+            read_claude_md,
+            update_claude_md_section,
+            append_claude_md_rule,
+            preflight_device_install,
             terminate_app_on_simulator,
             terminate_app_on_device,
             list_devices,
+            get_device_readiness,
             get_selected_device,
             set_selected_device,
+            check_signing_health,
             clear_selected_device,
             take_screenshot,
+            capture_stream_frame,
+            set_streaming_enabled,
+            get_stream_stats,
+            list_screenshots,
+            delete_screenshots,
+            set_simulator_input_mode,
+            get_simulator_input_mode,
+            forward_simulator_key,
+            focus_simulator,
+            return_focus,
+            verify_change,
+            wait_for_ui_idle,
             get_view_hierarchy,
+            get_view_hierarchy_compact,
+            get_logs_compact,
+            read_app_defaults,
+            write_app_default,
             start_claude_session,
             send_claude_message,
             stop_claude_session,
+            generate_session_report,
+            get_session_report,
             cancel_claude_request,
+            recover_stalled_session,
             get_claude_session_info,
             set_claude_session_info,
+            reload_session_skills,
             get_available_models,
+            get_agent_bridge_info,
             get_recent_sessions,
             get_current_session_id,
+            get_last_turn_metrics,
+            get_event_coalescing_stats,
+            get_full_event_content,
+            get_effective_working_dir,
+            set_checkpoints_enabled,
+            list_checkpoints,
+            restore_checkpoint,
             save_session_to_history,
             set_skip_permissions,
+            set_safe_mode,
+            request_destructive_confirmation,
+            set_redaction_enabled,
+            set_redaction_pattern_enabled,
+            list_redaction_patterns,
             respond_to_permission,
+            get_permission_server_status,
+            import_claude_settings,
             add_permission_rule,
             list_skills,
             read_skill,
             create_skill,
+            update_skill,
             open_skills_folder,
+            list_builtin_skills,
+            install_builtin_skill,
             get_git_info,
+            check_workspace_cleanliness,
+            stash_workspace,
+            unstash_workspace,
             get_git_diff_stats,
             get_file_diff,
+            get_file_diff_structured,
+            get_file_stats,
+            read_project_file,
+            list_merge_conflicts,
+            get_conflict_content,
+            resolve_conflict,
+            abort_merge,
             get_open_in_options,
+            prefetch_project_state,
+            invalidate_prefetch,
+            get_prefetch_status,
+            get_detected_projects,
+            get_installed_apps,
             open_in_app,
             copy_to_clipboard,
             list_worktrees,
             create_session_worktree,
             remove_worktree,
+            dispatch_parallel_sessions,
+            compare_parallel_results,
+            adopt_parallel_result,
+            get_project_storage_report,
+            list_build_caches,
+            list_package_dependencies,
+            cleanup_project_storage,
             // Claude Code sessions
             list_claude_code_sessions,
             load_session_messages,
             // User preferences
             get_user_preferences,
+            get_migration_report,
             save_user_preferences,
+            get_command_trace,
+            set_command_trace_to_file,
             get_session_name,
             get_session_names,
+            get_session_identity,
             get_active_session,
             set_active_session,
             // Terminal
             get_shell_env,
+            get_runtime_env,
+            create_terminal,
+            write_terminal,
+            resize_terminal,
+            close_terminal,
+            get_terminal_scrollback,
             // ACE (Agentic Context Engineering)
             ace_get_config,
             ace_save_config,
@@ -3689,14 +8355,28 @@ pub fn run() {
             ace_set_enabled,
             ace_get_reflections,
             ace_save_reflection,
+            ace_get_stats,
             ace_list_playbooks,
+            ace_run_reflection,
+            ace_run_curation,
             // Project management
             create_project,
+            list_project_templates,
             get_recent_projects,
             add_to_recent_projects,
             remove_from_recent_projects,
             clear_all_recent_projects,
             validate_project_path,
+            setup_existing_project,
+            export_configuration,
+            preview_configuration_import,
+            import_configuration,
+            list_log_filters,
+            save_log_filter,
+            delete_log_filter,
+            list_agent_profiles,
+            save_agent_profile,
+            delete_agent_profile,
             // Log streaming (macOS only)
             #[cfg(target_os = "macos")]
             start_simulator_logs,
@@ -3721,7 +8401,275 @@ pub fn run() {
             read_debug_snapshot,
             // File autocomplete
             list_project_files,
+            // Network
+            get_network_status,
+            replay_events,
+            // Simulator runtimes
+            list_available_runtimes,
+            download_runtime,
+            cancel_runtime_download,
+            build_symbol_index,
+            query_symbols,
+            get_symbol_index_status,
         ])
-        .run(tauri::generate_context!())
-        .expect("error while running tauri application");
+        .build(tauri::generate_context!())
+        .expect("error while building tauri application")
+        .run(|app_handle, event| match event {
+            // Shells outlive the webview, so they have to be cleaned up here
+            tauri::RunEvent::Exit => {
+                app_handle.state::<terminal::TerminalRegistry>().close_all();
+                app_handle.state::<claude_login::ClaudeLogin>().cancel();
+                app_handle.state::<control_server::ControlServer>().stop();
+            }
+            tauri::RunEvent::WindowEvent { event: tauri::WindowEvent::Focused(true), .. } => {
+                notifications::activated(app_handle);
+            }
+            #[cfg(target_os = "macos")]
+            tauri::RunEvent::Reopen { .. } => notifications::activated(app_handle),
+            _ => {}
+        });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn log_predicate_quotes_names() {
+        let subsystems = [r"com.example.App\Network".to_string(), "com.example.it's".to_string(), String::new()];
+        assert_eq!(
+            build_log_predicate(Some("com.example.App"), Some("My App"), &subsystems).as_deref(),
+            Some(
+                r"process == 'My App' OR subsystem BEGINSWITH 'com.example.App' OR subsystem == 'com.example.App\\Network' OR subsystem == 'com.example.it\'s'"
+            )
+        );
+        // Without an executable name the bundle ID stands in for the process
+        assert_eq!(
+            build_log_predicate(Some("com.example.App"), None, &[]).as_deref(),
+            Some("subsystem BEGINSWITH 'com.example.App' OR process == 'com.example.App'")
+        );
+        assert_eq!(build_log_predicate(None, Some(""), &[]), None);
+    }
+
+    #[test]
+    fn swift_build_diagnostics() {
+        let output = include_str!("../tests/fixtures/build/swift_build.txt");
+        let (errors, warnings) = parse_build_errors(output, Path::new("/Users/dev/Greeter"));
+
+        assert_eq!(errors.len(), 1);
+        let error = &errors[0];
+        assert_eq!(error.file.as_deref(), Some("/Users/dev/Greeter/Sources/Greeter/Greeter.swift"));
+        assert_eq!(error.relative_path.as_deref(), Some("Sources/Greeter/Greeter.swift"));
+        assert_eq!((error.line, error.column), (Some(9), Some(16)));
+        assert_eq!(error.message, "cannot convert return expression of type 'Int' to return type 'String'");
+        assert!(!error.is_dependency);
+        // The excerpt keeps Swift's framing, including the repeated diagnostic
+        let snippet = error.snippet.as_deref().unwrap();
+        assert!(snippet.starts_with(" 7 | public struct Greeter {"));
+        assert!(snippet.contains("`- error: cannot convert"));
+        assert!(snippet.ends_with("11 | }"));
+
+        assert_eq!(warnings.len(), 1);
+        assert_eq!((warnings[0].line, warnings[0].column), (Some(3), Some(9)));
+        assert!(warnings[0].message.starts_with("initialization of immutable value 'unused'"));
+        assert_eq!(warnings[0].snippet.as_deref().map(|s| s.lines().count()), Some(6));
+    }
+
+    #[test]
+    fn diagnostics_repeated_across_targets_are_listed_once() {
+        let output = include_str!("../tests/fixtures/build/xcodebuild_two_targets.txt");
+        let (errors, _) = parse_build_errors(output, Path::new("/Users/dev/Shop"));
+
+        assert_eq!(errors.len(), 1);
+        let error = &errors[0];
+        assert_eq!(error.relative_path.as_deref(), Some("Shared/Money.swift"));
+        assert_eq!((error.line, error.column), (Some(14), Some(22)));
+        assert_eq!(error.message, "cannot find 'Decimall' in scope");
+        // Notes and the excerpt come from the first copy only
+        assert_eq!(error.notes, ["/Users/dev/Shop/Shared/Money.swift:3:8: did you mean 'Decimal'?"]);
+        assert_eq!(
+            error.snippet.as_deref(),
+            Some("        let amount = Decimall(cents) / 100\n                     ^~~~~~~~")
+        );
+    }
+
+    #[test]
+    fn linker_errors_are_kept_without_a_location() {
+        let output = include_str!("../tests/fixtures/build/xcodebuild_link_failure.txt");
+        let (errors, warnings) = parse_build_errors(output, Path::new("/Users/dev/Shop"));
+        assert!(warnings.is_empty());
+
+        assert_eq!(errors.len(), 2);
+        let ld = &errors[0];
+        assert_eq!(ld.message, "Undefined symbols:");
+        assert_eq!((&ld.file, ld.line, ld.column, &ld.relative_path), (&None, None, None, &None));
+        assert_eq!(
+            ld.snippet.as_deref(),
+            Some("  _OBJC_CLASS_$_FIRApp, referenced from:\n      in AppDelegate.o")
+        );
+        assert_eq!(
+            ld.notes,
+            [
+                "symbol '_OBJC_CLASS_$_FIRApp' is defined in FirebaseCore, which isn't linked into target 'Shop'",
+                "fix-it: add FirebaseCore to the target's Frameworks, Libraries, and Embedded Content",
+            ]
+        );
+
+        let clang = &errors[1];
+        assert_eq!(clang.message, "linker command failed with exit code 1 (use -v to see invocation)");
+        assert_eq!((&clang.file, clang.line, clang.column), (&None, None, None));
+        assert!(clang.notes.is_empty());
+    }
+
+    #[test]
+    fn warnings_keep_their_locations() {
+        let output = include_str!("../tests/fixtures/build/xcodebuild_two_targets.txt");
+        let (_, warnings) = parse_build_errors(output, Path::new("/Users/dev/Shop"));
+
+        assert_eq!(warnings.len(), 2);
+        let own = &warnings[0];
+        assert_eq!(own.file.as_deref(), Some("/Users/dev/Shop/Shop/CartView.swift"));
+        assert_eq!(own.relative_path.as_deref(), Some("Shop/CartView.swift"));
+        assert_eq!((own.line, own.column), (Some(31), Some(13)));
+        assert_eq!(own.message, "variable 'total' was never mutated; consider changing to 'let' constant");
+        assert!(!own.is_dependency);
+        assert_eq!(own.snippet.as_deref().map(|s| s.lines().count()), Some(3));
+
+        let dependency = &warnings[1];
+        assert_eq!((dependency.line, dependency.column), (Some(88), Some(5)));
+        assert_eq!(dependency.relative_path, None);
+        assert!(dependency.is_dependency);
+    }
+
+    #[test]
+    fn finds_the_built_app_of_a_project_with_spaces_and_non_ascii() {
+        let dir = tempfile::tempdir().unwrap();
+        let project = dir.path().join("My App ✨");
+        std::fs::create_dir(&project).unwrap();
+        let derived = paths::derived_data_dir(&project);
+        let app = derived.join("Build/Products/Debug-iphonesimulator/My App ✨.app");
+        std::fs::create_dir_all(&app).unwrap();
+        let mut info = plist::Dictionary::new();
+        info.insert("CFBundleIdentifier".to_string(), plist::Value::String("com.example.my-app".to_string()));
+        plist::Value::Dictionary(info).to_file_xml(app.join("Info.plist")).unwrap();
+
+        let project_dir = project.to_string_lossy();
+        let found = find_built_app(&project_dir, "Debug", false);
+        let physical = find_built_app(&project_dir, "Debug", true);
+        let _ = std::fs::remove_dir_all(&derived);
+
+        assert_eq!(found, (Some(app.to_string_lossy().to_string()), Some("com.example.my-app".to_string())));
+        assert_eq!(physical, (None, None));
+    }
+
+    fn device(id: &str, device_type: DeviceType) -> DeviceInfo {
+        DeviceInfo {
+            id: id.to_string(),
+            core_device_id: None,
+            name: format!("Device {}", id),
+            model: String::new(),
+            os_version: "18.0".to_string(),
+            device_type,
+            state: DeviceState::Booted,
+            is_available: true,
+            pairing_state: None,
+            developer_mode_enabled: None,
+        }
+    }
+
+    #[test]
+    fn simulator_commands_target_the_selected_simulator() {
+        let state = Mutex::new(AppState::default());
+        assert_eq!(simulator_target(None, &state).unwrap(), "booted");
+
+        state.lock().selected_device = Some(device("SIM-2", DeviceType::Simulator));
+        assert_eq!(simulator_target(None, &state).unwrap(), "SIM-2");
+        assert_eq!(simulator_target(Some("SIM-1".to_string()), &state).unwrap(), "SIM-1");
+
+        state.lock().selected_device = Some(device("00008110", DeviceType::Physical));
+        assert!(matches!(simulator_target(None, &state), Err(CommandError::Unsupported(_))));
+        assert_eq!(simulator_target(Some("SIM-1".to_string()), &state).unwrap(), "SIM-1");
+    }
+
+    #[test]
+    fn a_failed_run_keeps_what_its_build_reported() {
+        let build = BuildResult {
+            success: true,
+            output: String::new(),
+            errors: Vec::new(),
+            warnings: 1,
+            warning_details: vec![unlocated_diagnostic("deprecated")],
+            build_time: Some(12.5),
+            app_path: Some("/DerivedData/App.app".to_string()),
+            bundle_id: Some("com.example.app".to_string()),
+            derived_data_path: Some("/DerivedData".to_string()),
+            launch: None,
+            bundle_id_correction: None,
+            cancelled: false,
+            crash_loop: None,
+            log_id: Some("20261015-120000.000-3".to_string()),
+        };
+        let failed = BuildResult::failed("Install failed".to_string(), vec![unlocated_diagnostic("no space")], Some(&build));
+        assert!(!failed.success);
+        assert_eq!(failed.errors[0].message, "no space");
+        assert_eq!((failed.warnings, failed.warning_details.len(), failed.build_time), (1, 1, Some(12.5)));
+        assert_eq!(failed.app_path, build.app_path);
+        assert_eq!(failed.bundle_id, build.bundle_id);
+        assert_eq!(failed.derived_data_path, build.derived_data_path);
+        assert_eq!(failed.log_id, build.log_id);
+
+        let before_build = BuildResult::failed("Device isn't ready".to_string(), Vec::new(), None);
+        assert_eq!((before_build.warnings, before_build.build_time, before_build.app_path), (0, None, None));
+    }
+
+    fn git(dir: &Path, args: &[&str]) {
+        let output = Command::new("git").args(args).current_dir(dir).output().unwrap();
+        assert!(output.status.success(), "git {:?}: {}", args, String::from_utf8_lossy(&output.stderr));
+    }
+
+    #[test]
+    fn worktree_removal_plan_matches_what_is_removed() {
+        let root = tempfile::tempdir().unwrap();
+        let project = root.path().join("project");
+        std::fs::create_dir(&project).unwrap();
+        git(&project, &["init", "-q"]);
+        std::fs::write(project.join("App.swift"), "let a = 1\n").unwrap();
+        git(&project, &["add", "-A"]);
+        git(&project, &["-c", "user.name=test", "-c", "user.email=test@localhost", "commit", "-qm", "initial"]);
+        git(&project, &["worktree", "add", "-q", "-b", "session-test", "../worktree"]);
+        let worktree = root.path().join("worktree");
+        std::fs::write(worktree.join("App.swift"), "let a = 2\n").unwrap();
+        std::fs::write(worktree.join("New.swift"), "let b = 1\n").unwrap();
+
+        // A path through `..` resolves to the worktree itself
+        let given = project.join("../worktree");
+        let removal = plan_worktree_removal(&given.to_string_lossy()).unwrap();
+        assert_eq!(PathBuf::from(&removal.canonical_path), std::fs::canonicalize(&worktree).unwrap());
+        let mut uncommitted = removal.uncommitted.clone();
+        uncommitted.sort();
+        assert_eq!(uncommitted, vec!["App.swift".to_string(), "New.swift".to_string()]);
+
+        remove_git_worktree(&given.to_string_lossy(), true, Some(&project)).unwrap();
+        assert!(!Path::new(&removal.canonical_path).exists());
+    }
+
+    #[test]
+    fn session_worktrees_use_their_projects_checkpoint_setting() {
+        let root = tempfile::tempdir().unwrap();
+        let project = root.path().join("project");
+        std::fs::create_dir(&project).unwrap();
+        git(&project, &["init", "-q"]);
+        std::fs::write(project.join("App.swift"), "let a = 1\n").unwrap();
+        git(&project, &["add", "-A"]);
+        git(&project, &["-c", "user.name=test", "-c", "user.email=test@localhost", "commit", "-qm", "initial"]);
+        git(&project, &["worktree", "add", "-q", "-b", "session-test", "../worktree"]);
+        let worktree = root.path().join("worktree").to_string_lossy().to_string();
+        let project = project.to_string_lossy().to_string();
+
+        assert!(checkpoints_enabled(std::slice::from_ref(&project), &project));
+        assert!(checkpoints_enabled(std::slice::from_ref(&project), &worktree));
+        assert!(!checkpoints_enabled(&[], &worktree));
+        // Enabling a worktree doesn't enable its project
+        assert!(!checkpoints_enabled(&[worktree], &project));
+    }
 }
@@ -0,0 +1,176 @@
+//! Saved simulator log filters
+//!
+//! A `LogFilter` is what the log panel applies: which processes and
+//! subsystems to stream, the lowest level shown, subsystems to hide, plus
+//! options only the UI uses (regex highlights, hidden processes). The
+//! server-side part compiles to the `log stream --predicate` string. Filters
+//! are saved by name in the user preferences, next to two built-in presets
+//! that can't be removed.
+
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+use crate::predicate_literal;
+
+const APP_ONLY_PRESET: &str = "My app only";
+const ERRORS_PRESET: &str = "Errors & faults";
+
+/// os_log levels, lowest first
+const LEVELS: &[&str] = &["debug", "info", "default", "error", "fault"];
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct LogFilter {
+    /// Match the launched app's process and bundle-ID subsystems
+    pub app_only: bool,
+    pub processes: Vec<String>,
+    pub subsystems: Vec<String>,
+    /// Lowest level streamed: "debug", "info", "default", "error" or "fault"
+    pub min_level: Option<String>,
+    /// Subsystem prefixes to drop, e.g. "com.apple.coredata"
+    pub exclude_subsystems: Vec<String>,
+    /// Regexes the log panel highlights; not sent to `log stream`
+    pub highlights: Vec<String>,
+    /// Processes the log panel hides; not sent to `log stream`
+    pub hidden_processes: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SavedLogFilter {
+    pub name: String,
+    pub filter: LogFilter,
+    pub builtin: bool,
+}
+
+fn builtin_presets() -> Vec<SavedLogFilter> {
+    vec![
+        SavedLogFilter {
+            name: APP_ONLY_PRESET.to_string(),
+            filter: LogFilter { app_only: true, ..Default::default() },
+            builtin: true,
+        },
+        SavedLogFilter {
+            name: ERRORS_PRESET.to_string(),
+            filter: LogFilter { min_level: Some("error".to_string()), ..Default::default() },
+            builtin: true,
+        },
+    ]
+}
+
+pub fn is_builtin(name: &str) -> bool {
+    builtin_presets().iter().any(|p| p.name == name)
+}
+
+/// Built-in presets first, then the user's filters by name
+pub fn list(saved: &BTreeMap<String, LogFilter>) -> Vec<SavedLogFilter> {
+    let mut filters = builtin_presets();
+    filters.extend(saved.iter().map(|(name, filter)| SavedLogFilter {
+        name: name.clone(),
+        filter: filter.clone(),
+        builtin: false,
+    }));
+    filters
+}
+
+/// A built-in preset or saved filter by name
+#[cfg(target_os = "macos")]
+pub fn find(saved: &BTreeMap<String, LogFilter>, name: &str) -> Option<SavedLogFilter> {
+    list(saved).into_iter().find(|f| f.name == name)
+}
+
+/// Check a filter before it's saved, so a bad level or highlight fails here
+/// instead of when the stream starts
+pub fn validate(name: &str, filter: &LogFilter) -> Result<(), String> {
+    if name.trim().is_empty() {
+        return Err("Filter name can't be empty".to_string());
+    }
+    if is_builtin(name) {
+        return Err(format!("'{}' is a built-in filter and can't be changed", name));
+    }
+    if let Some(level) = filter.min_level.as_deref().filter(|l| !LEVELS.contains(l)) {
+        return Err(format!("Unknown log level '{}'; use one of {}", level, LEVELS.join(", ")));
+    }
+    for pattern in &filter.highlights {
+        regex::Regex::new(pattern).map_err(|e| format!("Invalid highlight pattern '{}': {}", pattern, e))?;
+    }
+    Ok(())
+}
+
+/// The `log stream --predicate` for `filter`. `app_predicate` is the launched
+/// app's clause, used when the filter is `app_only`.
+#[cfg_attr(not(target_os = "macos"), allow(dead_code))]
+pub fn to_predicate(filter: &LogFilter, app_predicate: Option<String>) -> Option<String> {
+    let mut sources: Vec<String> = Vec::new();
+    if filter.app_only {
+        sources.extend(app_predicate);
+    }
+    for process in filter.processes.iter().filter(|p| !p.is_empty()) {
+        sources.push(format!("process == {}", predicate_literal(process)));
+    }
+    for subsystem in filter.subsystems.iter().filter(|s| !s.is_empty()) {
+        sources.push(format!("subsystem == {}", predicate_literal(subsystem)));
+    }
+
+    let mut clauses: Vec<String> = Vec::new();
+    if !sources.is_empty() {
+        clauses.push(format!("({})", sources.join(" OR ")));
+    }
+    if let Some(min) = filter.min_level.as_deref().and_then(|l| LEVELS.iter().position(|&level| level == l)) {
+        if min > 0 {
+            let types: Vec<String> = LEVELS[min..].iter().map(|level| format!("messageType == {}", level)).collect();
+            clauses.push(format!("({})", types.join(" OR ")));
+        }
+    }
+    for excluded in filter.exclude_subsystems.iter().filter(|s| !s.is_empty()) {
+        clauses.push(format!("NOT subsystem BEGINSWITH {}", predicate_literal(excluded)));
+    }
+
+    if clauses.is_empty() {
+        None
+    } else {
+        Some(clauses.join(" AND "))
+    }
+}
+
+/// The `log stream --level` needed for `filter`'s lowest level to show up at
+/// all; debug and info messages aren't streamed by default
+#[cfg(target_os = "macos")]
+pub fn stream_level(filter: &LogFilter) -> Option<&'static str> {
+    match filter.min_level.as_deref() {
+        Some("debug") => Some("debug"),
+        Some("info") => Some("info"),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn quotes_processes_and_subsystems() {
+        let filter = LogFilter {
+            processes: vec!["My App".to_string()],
+            subsystems: vec![r"com.example.it's".to_string(), r#"say "hi""#.to_string()],
+            exclude_subsystems: vec![r"com.example\net".to_string()],
+            ..Default::default()
+        };
+        assert_eq!(
+            to_predicate(&filter, None).as_deref(),
+            Some(
+                r#"(process == 'My App' OR subsystem == 'com.example.it\'s' OR subsystem == 'say "hi"') AND NOT subsystem BEGINSWITH 'com.example\\net'"#
+            )
+        );
+    }
+
+    #[test]
+    fn combines_the_app_clause_and_level() {
+        let filter = LogFilter { app_only: true, min_level: Some("error".to_string()), ..Default::default() };
+        assert_eq!(
+            to_predicate(&filter, Some("process == 'App'".to_string())).as_deref(),
+            Some("(process == 'App') AND (messageType == error OR messageType == fault)")
+        );
+        assert_eq!(to_predicate(&LogFilter::default(), Some("process == 'App'".to_string())), None);
+    }
+}
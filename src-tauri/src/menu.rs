@@ -1,10 +1,30 @@
 use tauri::{
     menu::{Menu, MenuBuilder, MenuItemBuilder, PredefinedMenuItem, SubmenuBuilder},
-    AppHandle, Emitter,
+    AppHandle,
 };
 
+use serde::Serialize;
+
+use crate::event_journal::emit_stamped;
 use crate::project::load_recent_projects;
 
+/// `menu-event`: a menu item the UI handles itself
+#[derive(Debug, Clone, Serialize)]
+pub struct MenuAction {
+    /// "new-project" or "open-project"
+    pub action: &'static str,
+}
+
+/// `recent-projects-updated`
+#[derive(Debug, Clone, Serialize)]
+pub struct RecentProjectsUpdated {}
+
+/// `open-recent-project`
+#[derive(Debug, Clone, Serialize)]
+pub struct OpenRecentProject {
+    pub path: String,
+}
+
 /// Create the application menu
 pub fn create_menu(app: &AppHandle) -> Result<Menu<tauri::Wry>, tauri::Error> {
     // App submenu (macOS only shows this)
@@ -113,7 +133,7 @@ fn build_recent_projects_submenu(app: &AppHandle) -> Result<tauri::menu::Submenu
 
 /// Shorten a path for display (replace home dir with ~)
 fn shorten_path(path: &str) -> String {
-    if let Some(home) = dirs::home_dir() {
+    if let Ok(home) = crate::paths::home_dir() {
         let home_str = home.to_string_lossy();
         if path.starts_with(home_str.as_ref()) {
             return path.replacen(home_str.as_ref(), "~", 1);
@@ -125,26 +145,22 @@ fn shorten_path(path: &str) -> String {
 /// Handle menu events
 pub fn handle_menu_event(app: &AppHandle, event_id: &str) {
     match event_id {
-        "new-project" => {
-            let _ = app.emit("menu-event", "new-project");
-        }
-        "open-project" => {
-            let _ = app.emit("menu-event", "open-project");
-        }
+        "new-project" => emit_stamped(app, "menu-event", MenuAction { action: "new-project" }),
+        "open-project" => emit_stamped(app, "menu-event", MenuAction { action: "open-project" }),
         "clear-recent" => {
             let _ = crate::project::clear_recent_projects();
             // Rebuild menu to reflect cleared state
             if let Ok(menu) = create_menu(app) {
                 let _ = app.set_menu(menu);
             }
-            let _ = app.emit("recent-projects-updated", ());
+            emit_stamped(app, "recent-projects-updated", RecentProjectsUpdated {});
         }
         id if id.starts_with("recent-project-") => {
             // Extract index and get project
             if let Ok(index) = id.replace("recent-project-", "").parse::<usize>() {
                 let projects = load_recent_projects();
                 if let Some(project) = projects.get(index) {
-                    let _ = app.emit("open-recent-project", project.path.clone());
+                    emit_stamped(app, "open-recent-project", OpenRecentProject { path: project.path.clone() });
                 }
             }
         }
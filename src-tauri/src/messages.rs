@@ -0,0 +1,265 @@
+//! Catalog of user-facing strings
+//!
+//! Text the backend writes for the user (build events, agent errors, device and
+//! onboarding findings) is looked up by key instead of being formatted inline,
+//! so it reads consistently and can be translated. Templates use `{name}`
+//! placeholders. The locale comes from the `locale` preference; keys a catalog
+//! doesn't have fall back to English.
+//!
+//! Events carry the key and arguments next to the rendered text, so the
+//! frontend can render them in its own locale instead.
+//!
+//! To add a locale, add a table like `EN` and a match arm in `catalog`.
+
+use parking_lot::RwLock;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+pub const DEFAULT_LOCALE: &str = "en";
+
+static LOCALE: RwLock<Option<String>> = RwLock::new(None);
+
+const EN: &[(&str, &str)] = &[
+    // Build
+    ("build.started", "Building {scheme} ..."),
+    ("build.project", "Project: {path}"),
+    ("build.scheme", "Scheme: {scheme}"),
+    ("build.configuration", "Configuration: {configuration}"),
+    ("build.signing_overrides", "Signing overrides: {settings}"),
+    ("build.scheme_not_found", "Scheme '{scheme}' not found. Available schemes: {schemes}"),
+    ("build.scheme_substituted", "Scheme '{requested}' not found; retrying with '{scheme}', the only shared scheme"),
+    ("build.device", "Device: {name} ({kind})"),
+    ("build.device_default", "Device: iPhone 16 Pro (simulator, default)"),
+    ("build.offline_cached_packages", "Offline: skipping package updates, building with cached dependencies"),
+    ("build.xcode_changed", "Xcode changed since the last successful build ({previous} -> {current}); if the build fails on module caches, clean and rebuild"),
+    ("build.cleaning_stale_caches", "Cleaning compiler caches left by the previous Xcode..."),
+    ("build.clean_failed", "Couldn't clean the stale caches: {error}"),
+    ("build.tuist_detected", "Tuist project detected, using tuist build (with caching)..."),
+    ("build.tuist_generating", "Generating the Xcode project with tuist generate..."),
+    ("build.tuist_generated", "Project generated"),
+    ("build.swift_package_detected", "Swift package detected, building with {tool}..."),
+    ("build.starting_tool", "Starting {tool}..."),
+    ("build.compiling_file", "Compiling {file}"),
+    ("build.linking", "Linking..."),
+    ("build.signing", "Signing..."),
+    ("build.compiling_swift", "Compiling Swift sources..."),
+    ("build.processing_info_plist", "Processing Info.plist..."),
+    ("build.running_scripts", "Running build phase scripts..."),
+    ("build.succeeded", "Build succeeded in {seconds}s"),
+    ("build.failed_with_errors", "Build failed with {count} error(s)"),
+    ("build.notify_succeeded", "Build succeeded in {duration}"),
+    ("build.notify_failed", "Build failed after {duration} with {count} error(s)"),
+    ("build.cancelled", "Build cancelled"),
+    ("build.clean_started", "Cleaning {scheme} ..."),
+    ("build.clean_tool_failed", "xcodebuild clean failed: {error}"),
+    ("build.clean_removing", "Removing {path}"),
+    ("build.clean_error", "Clean failed: {error}"),
+    ("build.clean_finished", "Clean finished in {seconds}s, freed {megabytes} MB"),
+    ("archive.started", "Archiving {scheme} for {method} distribution..."),
+    ("archive.exporting", "Exporting the archive to {path}"),
+    ("archive.failed", "{step} failed with {count} error(s)"),
+    ("archive.succeeded", "Archived and exported in {seconds}s: {path}"),
+    ("test.started", "Running tests for {scheme}..."),
+    ("test.only_testing", "Only testing: {tests}"),
+    ("test.case_passed", "Passed: {test}"),
+    ("test.case_failed", "Failed: {test}"),
+    ("test.case_skipped", "Skipped: {test}"),
+    ("test.build_failed", "Test build failed with {count} error(s)"),
+    ("test.finished", "Tests finished in {seconds}s: {passed} passed, {failed} failed, {skipped} skipped"),
+    ("test.notify_build_failed", "Test build failed after {duration} with {count} error(s)"),
+    ("test.notify_finished", "Tests finished in {duration}: {passed} passed, {failed} failed"),
+    // Physical device install and launch
+    ("device.detected", "Physical device detected: {name} (devicectl ID: {id})"),
+    ("device.app_path", "App path: {path}"),
+    ("device.checking", "Checking device {name} availability..."),
+    ("device.ready", "Device {name} is connected and ready"),
+    ("device.tunnel_connecting", "Device {name} tunnel is not ready, attempting to connect..."),
+    ("device.not_found", "Device {name} not found. Make sure the device is connected via USB or on the same network."),
+    ("device.not_paired", "Device {name} is not paired. Trust this computer on the device."),
+    ("device.preflight", "Running install preflight checks..."),
+    ("device.installing", "Installing app to physical device {name}..."),
+    ("device.install_retry", "Retrying install (attempt {attempt}/{max})..."),
+    ("device.running_command", "Running: {command}"),
+    ("device.install_stdout", "Install stdout: {output}"),
+    ("device.install_succeeded", "Install succeeded!"),
+    ("device.install_stderr", "Install stderr: {output}"),
+    ("device.install_attempt_failed", "Install attempt {attempt} failed (connection issue): {error}"),
+    ("device.install_failed", "Install failed: {error}"),
+    ("device.launch_overrides_ignored", "Launch overrides (locale, region, text size, launch arguments) only apply on the simulator; launching without them"),
+    ("device.launching", "Launching app on physical device..."),
+    ("device.console_launch_failed", "Console launch failed, launching without logs: {error}"),
+    ("device.launch_failed", "Launch failed: {error}"),
+    ("device.launched", "App launched on device: {bundle_id}"),
+    // Simulator install and launch
+    ("simulator.checking", "Checking simulator status..."),
+    ("simulator.booting", "Booting simulator {target}..."),
+    ("simulator.boot_failed", "Failed to boot simulator: {error}"),
+    ("simulator.waiting_for_boot", "Waiting for simulator to boot..."),
+    ("simulator.installing", "Installing app to simulator..."),
+    ("simulator.install_failed", "Install failed: {error}"),
+    ("simulator.log_stream_failed", "Failed to start log streaming: {error}"),
+    ("simulator.launching", "Launching app..."),
+    ("simulator.launch_failed", "Launch failed: {error}"),
+    ("simulator.launched", "App launched: {bundle_id}"),
+    ("simulator.crash_loop", "{bundle_id} crashed on launch {count} times in a row, not relaunching until reset: {summary}"),
+    ("simulator.retrying", "{step} failed while the simulator was still starting, retrying in {delay}s (attempt {attempt}/{max})..."),
+    ("simulator.launch_stdout", "Launch stdout: {output}"),
+    ("simulator.launch_time", "App started logging {ms}ms after launch"),
+    ("simulator.launch_regressed", "Launch took {ms}ms, {percent}% slower than the recent median of {median}ms"),
+    // Post-install check
+    ("install.bundle_id_corrected", "The build reported bundle ID {expected}, but the app was installed as {installed}; launching {installed}"),
+    ("install.app_not_listed", "{bundle_id} isn't in the installed app list; the launch may fail"),
+    // Install preflight findings
+    ("preflight.invalid_bundle_id", "Bundle ID '{bundle_id}' is invalid. Use reverse-DNS form with only letters, digits, hyphens and dots."),
+    ("preflight.no_profile", "No provisioning profile found for {bundle_id} on this device. Signing may fail."),
+    ("preflight.profile_expired", "Provisioning profile '{profile}' has expired. Rebuild to let Xcode renew it."),
+    ("preflight.device_not_provisioned", "This device ({udid}) isn't in provisioning profile '{profile}'. Register it in your developer account or let Xcode add it."),
+    ("preflight.profile_bundle_mismatch", "Provisioning profile '{profile}' is for a different bundle ID than {bundle_id}."),
+    ("preflight.apps_unavailable", "Couldn't list installed apps on the device; skipping install conflict checks."),
+    ("preflight.bundle_id_installed", "'{name}' ({bundle_id}) is already installed. If it was signed by a different team the install will fail; delete it from the device first."),
+    ("preflight.free_account_limit", "Free developer accounts can only have {limit} apps installed on a device and {count} are present. Delete one from the device to continue."),
+    // Signing health
+    ("signing.no_certificate", "No code signing certificate found for {team}. Add your account in Xcode ▸ Settings ▸ Accounts to create one."),
+    ("signing.certificate_expired", "Signing certificate '{name}' expired on {date}. Device builds will fail until it's renewed in Xcode ▸ Settings ▸ Accounts."),
+    ("signing.certificate_expiring", "Signing certificate '{name}' expires on {date}. Renew it in Xcode ▸ Settings ▸ Accounts before then."),
+    ("signing.profile_expired", "Provisioning profile '{profile}' expired on {date}. Rebuild to let Xcode renew it, or renew it in your developer account."),
+    ("signing.profile_expiring", "Provisioning profile '{profile}' expires on {date}."),
+    ("signing.device_not_in_profiles", "None of the current provisioning profiles include this device ({udid}). Register it in your developer account or let Xcode add it."),
+    // Device readiness findings
+    ("readiness.device_not_found", "Device not found. Connect it via USB or join the same Wi-Fi network, and unlock it."),
+    ("readiness.not_paired", "This Mac isn't trusted by the device. Connect via USB, unlock it and tap 'Trust'."),
+    ("readiness.pairing_unknown", "Couldn't read the device's pairing state."),
+    ("readiness.developer_mode_off", "Developer Mode is off — enable it in Settings ▸ Privacy & Security ▸ Developer Mode, then restart the device."),
+    ("readiness.developer_mode_unknown", "Couldn't read whether Developer Mode is on. If installs fail, check Settings ▸ Privacy & Security ▸ Developer Mode."),
+    // Onboarding
+    ("onboarding.offline", "Offline; login not verified"),
+    ("onboarding.not_logged_in", "Not logged in"),
+    ("onboarding.no_active_plan", "No active plan"),
+    ("onboarding.unknown_error", "Unknown error: {error}"),
+    // Agent
+    ("agent.malformed_output_discarded", "Discarded {megabytes} MB of malformed output from the Claude service"),
+    ("agent.unknown_error", "Unknown error"),
+    ("agent.turn_stalled", "Claude hasn't responded for {seconds}s. Interrupt, restart the session or keep waiting."),
+];
+
+/// The table for `locale`, if there is one
+fn catalog(locale: &str) -> Option<&'static [(&'static str, &'static str)]> {
+    // Match on the language so "en-GB" uses the English table
+    match locale.split(['-', '_']).next().unwrap_or(locale) {
+        "en" => Some(EN),
+        _ => None,
+    }
+}
+
+fn lookup(table: &[(&'static str, &'static str)], key: &str) -> Option<&'static str> {
+    table.iter().find(|(k, _)| *k == key).map(|(_, template)| *template)
+}
+
+/// Switch the locale messages are rendered in; None or an unknown locale means English
+pub fn set_locale(locale: Option<&str>) {
+    *LOCALE.write() = locale.filter(|l| !l.is_empty()).map(String::from);
+}
+
+/// Fill `{name}` placeholders in one pass, so values that happen to contain
+/// braces are left alone. Unknown placeholders are kept as written.
+fn render(template: &str, args: &[(&str, String)]) -> String {
+    let mut text = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(open) = rest.find('{') {
+        text.push_str(&rest[..open]);
+        let after = &rest[open + 1..];
+        let value = after
+            .find('}')
+            .and_then(|close| args.iter().find(|(name, _)| *name == &after[..close]).map(|(_, v)| (close, v)));
+        match value {
+            Some((close, value)) => {
+                text.push_str(value);
+                rest = &after[close + 1..];
+            }
+            None => {
+                text.push('{');
+                rest = after;
+            }
+        }
+    }
+    text.push_str(rest);
+    text
+}
+
+/// The template for `key` in the current locale with `args` filled in. Falls
+/// back to English, then to the key itself.
+pub fn get_message(key: &str, args: &[(&str, String)]) -> String {
+    let locale = LOCALE.read().clone().unwrap_or_else(|| DEFAULT_LOCALE.to_string());
+    let template = catalog(&locale)
+        .and_then(|table| lookup(table, key))
+        .or_else(|| lookup(EN, key));
+    match template {
+        Some(template) => render(template, args),
+        None => {
+            log::warn!("Missing message key: {}", key);
+            key.to_string()
+        }
+    }
+}
+
+/// A rendered message and what it was rendered from. Text that didn't come from
+/// the catalog (compiler output, service errors) has no key.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Message {
+    pub key: Option<String>,
+    pub args: BTreeMap<String, String>,
+    pub text: String,
+}
+
+impl Message {
+    pub fn new(key: &'static str, args: Vec<(&str, String)>) -> Self {
+        Self {
+            text: get_message(key, &args),
+            key: Some(key.to_string()),
+            args: args.into_iter().map(|(name, value)| (name.to_string(), value)).collect(),
+        }
+    }
+
+    /// Text passed through as is
+    pub fn raw(text: impl Into<String>) -> Self {
+        Self { key: None, args: BTreeMap::new(), text: text.into() }
+    }
+
+    pub fn key(&self) -> Option<String> {
+        self.key.clone()
+    }
+
+    /// The arguments, or None when there are none
+    pub fn args(&self) -> Option<BTreeMap<String, String>> {
+        (!self.args.is_empty()).then(|| self.args.clone())
+    }
+}
+
+impl From<&str> for Message {
+    fn from(text: &str) -> Self {
+        Message::raw(text)
+    }
+}
+
+impl From<String> for Message {
+    fn from(text: String) -> Self {
+        Message::raw(text)
+    }
+}
+
+impl From<&String> for Message {
+    fn from(text: &String) -> Self {
+        Message::raw(text.as_str())
+    }
+}
+
+/// `msg!("build.failed_with_errors", count = errors.len())` renders a catalog
+/// message; arguments are anything `Display`
+macro_rules! msg {
+    ($key:literal $(, $name:ident = $value:expr)* $(,)?) => {
+        $crate::messages::Message::new($key, vec![$((stringify!($name), ($value).to_string())),*])
+    };
+}
+
+pub(crate) use msg;
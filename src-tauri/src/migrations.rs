@@ -0,0 +1,212 @@
+//! Versioned on-disk formats, migrated at startup
+//!
+//! Each store in `STORES` keeps a `schemaVersion` in its JSON files (none
+//! means 0) and lists its migrations in order: migration `n` takes a file
+//! from version `n` to `n + 1`, so the current version is the number of
+//! migrations. The modules that own the formats declare them next to their
+//! types.
+//!
+//! `run_migrations` runs first thing in `setup()`. A file with migrations
+//! pending is copied to `<file>.v<version>.bak`, migrated as JSON and written
+//! back, possibly under a new name. A migration that errors or panics leaves
+//! the file as it was and the backup in place; it's reported and the app
+//! carries on, reading the file as it would have before (or with defaults).
+//! `get_migration_report` returns what happened.
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::panic::{self, AssertUnwindSafe};
+use std::path::{Path, PathBuf};
+
+const VERSION_KEY: &str = "schemaVersion";
+
+/// A store's `schemaVersion`. Defaults to the current version, since anything
+/// this build creates is already in the current format.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct SchemaVersion<const CURRENT: u32>(pub u32);
+
+impl<const CURRENT: u32> Default for SchemaVersion<CURRENT> {
+    fn default() -> Self {
+        Self(CURRENT)
+    }
+}
+
+/// One file being migrated. A migration can change `path` to move it.
+pub struct Document {
+    pub path: PathBuf,
+    pub value: Value,
+}
+
+pub struct Migration {
+    /// What it changes, for the report
+    pub description: &'static str,
+    pub apply: fn(&mut Document) -> Result<(), String>,
+}
+
+pub struct Store {
+    pub name: &'static str,
+    /// Its files that exist now
+    pub files: fn() -> Vec<PathBuf>,
+    pub migrations: &'static [Migration],
+}
+
+/// Playbooks go first: the reflections migration looks up their project paths
+const STORES: &[Store] = &[
+    Store {
+        name: "preferences",
+        files: crate::preferences_files,
+        migrations: crate::PREFERENCES_MIGRATIONS,
+    },
+    Store {
+        name: "acePlaybooks",
+        files: crate::ace::playbook_files,
+        migrations: crate::ace::PLAYBOOK_MIGRATIONS,
+    },
+    Store {
+        name: "aceReflections",
+        files: crate::ace::reflection_files,
+        migrations: crate::ace::REFLECTION_MIGRATIONS,
+    },
+];
+
+/// One file that had migrations pending
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FileMigration {
+    pub store: String,
+    pub path: String,
+    pub from_version: u32,
+    /// The version it's at now; `from_version` if it failed
+    pub to_version: u32,
+    /// Descriptions of the migrations applied
+    pub applied: Vec<String>,
+    /// Set when a migration moved the file
+    pub moved_to: Option<String>,
+    pub backup_path: Option<String>,
+    pub error: Option<String>,
+}
+
+/// `get_migration_report`
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MigrationReport {
+    pub files: Vec<FileMigration>,
+    pub migrated: usize,
+    pub failed: usize,
+}
+
+fn panic_message(payload: Box<dyn std::any::Any + Send>) -> String {
+    payload
+        .downcast_ref::<&str>()
+        .map(|s| s.to_string())
+        .or_else(|| payload.downcast_ref::<String>().cloned())
+        .unwrap_or_else(|| "unknown panic".to_string())
+}
+
+fn backup_path(path: &Path, version: u32) -> PathBuf {
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(format!(".v{}.bak", version));
+    path.with_file_name(name)
+}
+
+/// Apply the pending migrations to `document`; the descriptions of those applied
+fn apply(document: &mut Document, pending: &[Migration]) -> Result<Vec<String>, String> {
+    let mut applied = Vec::new();
+    for migration in pending {
+        panic::catch_unwind(AssertUnwindSafe(|| (migration.apply)(document)))
+            .map_err(|payload| format!("'{}' panicked: {}", migration.description, panic_message(payload)))?
+            .map_err(|e| format!("'{}' failed: {}", migration.description, e))?;
+        applied.push(migration.description.to_string());
+    }
+    Ok(applied)
+}
+
+/// Bring one file up to date, or return None when it already is
+fn migrate_file(store: &Store, path: &Path) -> Option<FileMigration> {
+    let current = store.migrations.len() as u32;
+    let mut report = FileMigration {
+        store: store.name.to_string(),
+        path: path.to_string_lossy().to_string(),
+        from_version: 0,
+        to_version: 0,
+        applied: Vec::new(),
+        moved_to: None,
+        backup_path: None,
+        error: None,
+    };
+    let value = match std::fs::read_to_string(path).map_err(|e| e.to_string()).and_then(|content| {
+        serde_json::from_str::<Value>(&content).map_err(|e| format!("not valid JSON: {}", e))
+    }) {
+        Ok(value) => value,
+        Err(e) => {
+            report.error = Some(format!("Failed to read {}: {}", path.display(), e));
+            return Some(report);
+        }
+    };
+    let version = value.get(VERSION_KEY).and_then(Value::as_u64).unwrap_or(0) as u32;
+    report.from_version = version;
+    report.to_version = version;
+    if version >= current {
+        if version > current {
+            log::warn!("{} is schema version {}, newer than this build's {}", path.display(), version, current);
+        }
+        return None;
+    }
+
+    let backup = backup_path(path, version);
+    if let Err(e) = std::fs::copy(path, &backup) {
+        report.error = Some(format!("Failed to back up {}: {}", path.display(), e));
+        return Some(report);
+    }
+    report.backup_path = Some(backup.to_string_lossy().to_string());
+
+    let mut document = Document { path: path.to_path_buf(), value };
+    let result = apply(&mut document, &store.migrations[version as usize..]).and_then(|applied| {
+        let object = document.value.as_object_mut().ok_or("not a JSON object")?;
+        object.insert(VERSION_KEY.to_string(), Value::from(current));
+        let moved = document.path != path;
+        if moved && document.path.exists() {
+            return Err(format!("{} already exists", document.path.display()));
+        }
+        let content = serde_json::to_string_pretty(&document.value).map_err(|e| format!("Failed to serialize: {}", e))?;
+        std::fs::write(&document.path, content).map_err(|e| format!("Failed to write {}: {}", document.path.display(), e))?;
+        if moved {
+            let _ = std::fs::remove_file(path);
+        }
+        Ok((applied, moved))
+    });
+    match result {
+        Ok((applied, moved)) => {
+            report.applied = applied;
+            report.to_version = current;
+            report.moved_to = moved.then(|| document.path.to_string_lossy().to_string());
+        }
+        Err(e) => report.error = Some(e),
+    }
+    Some(report)
+}
+
+/// Migrate every store's files, logging a summary
+pub fn run_migrations() -> MigrationReport {
+    let mut report = MigrationReport::default();
+    for store in STORES {
+        for path in (store.files)() {
+            let Some(file) = migrate_file(store, &path) else {
+                continue;
+            };
+            match &file.error {
+                Some(e) => {
+                    log::warn!("Migrating {} ({}) failed: {}", file.path, store.name, e);
+                    report.failed += 1;
+                }
+                None => report.migrated += 1,
+            }
+            report.files.push(file);
+        }
+    }
+    if !report.files.is_empty() {
+        log::info!("Migrations: {} file(s) migrated, {} failed", report.migrated, report.failed);
+    }
+    report
+}
@@ -0,0 +1,149 @@
+//! Claude model catalog
+//!
+//! The agent service only understands the `sonnet`/`opus`/`haiku` aliases, so those
+//! are the models we offer. Display names and availability come from the installed
+//! claude CLI when it can tell us; otherwise we fall back to static defaults.
+
+use serde::{Deserialize, Serialize};
+use std::process::Command;
+use std::time::{Duration, Instant};
+
+/// How long a CLI query stays fresh. Long enough that we normally ask once per run.
+const CATALOG_TTL: Duration = Duration::from_secs(6 * 60 * 60);
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ModelInfo {
+    pub id: String,
+    pub name: String,
+    pub description: String,
+    /// False when the CLI reports the model isn't included in the user's plan
+    pub available: bool,
+}
+
+const DEFAULT_MODELS: &[(&str, &str, &str)] = &[
+    ("sonnet", "Claude Sonnet", "Fast and capable, great for most coding tasks"),
+    ("opus", "Claude Opus", "Most powerful, best for complex reasoning"),
+    ("haiku", "Claude Haiku", "Fastest and most economical"),
+];
+
+fn default_models() -> Vec<ModelInfo> {
+    DEFAULT_MODELS
+        .iter()
+        .map(|(id, name, description)| ModelInfo {
+            id: id.to_string(),
+            name: name.to_string(),
+            description: description.to_string(),
+            available: true,
+        })
+        .collect()
+}
+
+/// A model entry as reported by `claude models list --json`
+struct CliModel {
+    id: String,
+    alias: Option<String>,
+    name: Option<String>,
+    available: Option<bool>,
+}
+
+fn parse_cli_models(json: &serde_json::Value) -> Vec<CliModel> {
+    let entries = json
+        .as_array()
+        .or_else(|| json.get("models").and_then(|m| m.as_array()));
+
+    entries
+        .into_iter()
+        .flatten()
+        .filter_map(|entry| {
+            let str_field = |keys: &[&str]| {
+                keys.iter()
+                    .find_map(|k| entry.get(*k).and_then(|v| v.as_str()))
+                    .map(String::from)
+            };
+            Some(CliModel {
+                id: str_field(&["id", "model"])?,
+                alias: str_field(&["alias"]),
+                name: str_field(&["displayName", "display_name", "name"]),
+                available: entry.get("available").and_then(|v| v.as_bool()),
+            })
+        })
+        .collect()
+}
+
+/// Ask the CLI for its model list. Older CLIs don't have `models list`; for those
+/// `claude --help` at least tells us `--model` is understood, so the defaults stand.
+fn query_cli_models() -> Option<Vec<CliModel>> {
+    let output = Command::new("claude")
+        .args(["models", "list", "--json"])
+        .output()
+        .ok()?;
+
+    if output.status.success() {
+        if let Ok(json) = serde_json::from_slice::<serde_json::Value>(&output.stdout) {
+            let models = parse_cli_models(&json);
+            if !models.is_empty() {
+                return Some(models);
+            }
+        }
+    }
+
+    let help = Command::new("claude").arg("--help").output().ok()?;
+    let help = String::from_utf8_lossy(&help.stdout);
+    if !help.contains("--model") {
+        log::warn!("Installed claude CLI does not support --model");
+    }
+    None
+}
+
+fn merge_models(cli_models: &[CliModel]) -> Vec<ModelInfo> {
+    let mut models = default_models();
+    for model in &mut models {
+        let matched = cli_models.iter().find(|m| {
+            m.alias.as_deref() == Some(model.id.as_str())
+                || m.id == model.id
+                || m.id.contains(&format!("-{}-", model.id))
+        });
+        if let Some(cli) = matched {
+            if let Some(ref name) = cli.name {
+                model.name = name.clone();
+            }
+            if let Some(available) = cli.available {
+                model.available = available;
+            }
+        }
+    }
+    models
+}
+
+#[derive(Default)]
+pub struct ModelCatalog {
+    models: Vec<ModelInfo>,
+    fetched_at: Option<Instant>,
+}
+
+impl ModelCatalog {
+    fn fresh(&self) -> Option<Vec<ModelInfo>> {
+        self.fetched_at
+            .filter(|at| at.elapsed() < CATALOG_TTL)
+            .map(|_| self.models.clone())
+    }
+}
+
+/// Cached model list, refreshed from the CLI once the TTL has lapsed.
+/// The CLI runs without the lock held.
+pub fn available_models(catalog: &parking_lot::Mutex<ModelCatalog>) -> Vec<ModelInfo> {
+    if let Some(models) = catalog.lock().fresh() {
+        return models;
+    }
+
+    let models = match query_cli_models() {
+        Some(cli_models) => merge_models(&cli_models),
+        None => default_models(),
+    };
+
+    let mut catalog = catalog.lock();
+    catalog.models = models.clone();
+    catalog.fetched_at = Some(Instant::now());
+    models
+}
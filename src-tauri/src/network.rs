@@ -0,0 +1,70 @@
+//! Cheap reachability probe for network-dependent steps
+//!
+//! Without a connection, package resolution, runtime downloads and the Claude
+//! login probe hang until their own timeouts and then fail with unrelated
+//! errors. Those paths check `is_offline()` first and return an `Offline` result
+//! straight away. The probe is a TCP connect to well-known anycast addresses
+//! (literal IPs, so a dead resolver can't stall it), cached for a minute.
+
+use parking_lot::Mutex;
+use serde::Serialize;
+use std::net::{SocketAddr, TcpStream};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+/// Tried in order until one accepts; Cloudflare and Google DNS over HTTPS
+const PROBE_ADDRS: &[&str] = &["1.1.1.1:443", "8.8.8.8:443"];
+/// Budget for the whole probe, shared across addresses
+const PROBE_TIMEOUT: Duration = Duration::from_secs(2);
+const CACHE_TTL: Duration = Duration::from_secs(60);
+
+static CACHE: Mutex<Option<(Instant, NetworkStatus)>> = Mutex::new(None);
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NetworkStatus {
+    pub online: bool,
+    /// Connect time of the probe that succeeded
+    pub latency_ms: Option<u64>,
+    /// Unix milliseconds
+    pub checked_at: u64,
+}
+
+fn probe() -> NetworkStatus {
+    let started = Instant::now();
+    let online = PROBE_ADDRS.iter().any(|addr| {
+        let remaining = PROBE_TIMEOUT.saturating_sub(started.elapsed());
+        if remaining.is_zero() {
+            return false;
+        }
+        addr.parse::<SocketAddr>()
+            .map(|addr| TcpStream::connect_timeout(&addr, remaining).is_ok())
+            .unwrap_or(false)
+    });
+    NetworkStatus {
+        online,
+        latency_ms: online.then(|| started.elapsed().as_millis() as u64),
+        checked_at: SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_millis() as u64)
+            .unwrap_or(0),
+    }
+}
+
+/// The cached status, probing again once it's older than a minute or when
+/// `refresh` is set. Blocks for up to two seconds while probing.
+pub fn status(refresh: bool) -> NetworkStatus {
+    if !refresh {
+        if let Some((at, status)) = CACHE.lock().as_ref() {
+            if at.elapsed() < CACHE_TTL {
+                return status.clone();
+            }
+        }
+    }
+    let status = probe();
+    *CACHE.lock() = Some((Instant::now(), status.clone()));
+    status
+}
+
+pub fn is_offline() -> bool {
+    !status(false).online
+}
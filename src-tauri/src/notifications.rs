@@ -0,0 +1,136 @@
+//! Native notifications when a long build or agent turn finishes
+//!
+//! Gated by the `notificationPolicy` preference: never, only while the main
+//! window is unfocused, or always, and only for work that took at least
+//! `minDurationSecs`. They're posted with the notification plugin, so they're
+//! Nocur's own and clicking one activates the app.
+//!
+//! The plugin doesn't report clicks on desktop. A notification posted while
+//! the window was in the background is kept as pending, and the next time the
+//! app is activated it's taken as clicked: the main window is brought forward
+//! and `notification-clicked` names the finished item.
+
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Manager};
+use tauri_plugin_notification::NotificationExt;
+
+/// A pending notification older than this is no longer taken as clicked
+const CLICK_WINDOW: Duration = Duration::from_secs(15 * 60);
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum NotificationMode {
+    #[default]
+    Off,
+    WhenUnfocused,
+    Always,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct NotificationPolicy {
+    pub mode: NotificationMode,
+    /// Work that finishes faster than this never notifies
+    pub min_duration_secs: u64,
+}
+
+impl Default for NotificationPolicy {
+    fn default() -> Self {
+        Self {
+            mode: NotificationMode::Off,
+            min_duration_secs: 30,
+        }
+    }
+}
+
+/// What finished, as `notification-clicked` reports it
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CompletedItem {
+    /// "build", "tests" or "turn"
+    pub kind: String,
+    /// The build ID, or the session ID for a turn
+    pub id: Option<String>,
+}
+
+impl CompletedItem {
+    pub fn new(kind: &str, id: Option<String>) -> Self {
+        Self { kind: kind.to_string(), id }
+    }
+}
+
+/// The last notification posted while the main window was in the background
+#[derive(Default)]
+pub struct PendingNotification(Mutex<Option<(Instant, CompletedItem)>>);
+
+/// "3m12s", "45s", "1h05m"
+pub fn format_duration(duration: Duration) -> String {
+    let secs = duration.as_secs();
+    match secs {
+        0..=59 => format!("{}s", secs),
+        60..=3599 => format!("{}m{:02}s", secs / 60, secs % 60),
+        _ => format!("{}h{:02}m", secs / 3600, (secs % 3600) / 60),
+    }
+}
+
+fn main_window_focused(app_handle: &AppHandle) -> bool {
+    app_handle
+        .get_webview_window("main")
+        .and_then(|w| w.is_focused().ok())
+        .unwrap_or(false)
+}
+
+fn should_notify(policy: &NotificationPolicy, elapsed: Duration, app_handle: &AppHandle) -> bool {
+    if elapsed.as_secs() < policy.min_duration_secs {
+        return false;
+    }
+    match policy.mode {
+        NotificationMode::Off => false,
+        NotificationMode::WhenUnfocused => !main_window_focused(app_handle),
+        NotificationMode::Always => true,
+    }
+}
+
+fn post(app_handle: &AppHandle, body: &str, item: CompletedItem) {
+    if let Err(e) = app_handle.notification().builder().title("Nocur").body(body).show() {
+        log::warn!("Failed to post notification: {}", e);
+        return;
+    }
+    if !main_window_focused(app_handle) {
+        if let Some(pending) = app_handle.try_state::<PendingNotification>() {
+            *pending.0.lock() = Some((Instant::now(), item));
+        }
+    }
+}
+
+/// Post `body` about `item` if the user's policy wants a notification for
+/// work that took `elapsed`
+pub fn notify_completion(app_handle: &AppHandle, elapsed: Duration, body: &str, item: CompletedItem) {
+    let policy = crate::read_user_preferences()
+        .map(|prefs| prefs.notification_policy)
+        .unwrap_or_default();
+    if should_notify(&policy, elapsed, app_handle) {
+        post(app_handle, body, item);
+    }
+}
+
+/// The app was activated: if a notification is pending, that was its click
+pub fn activated(app_handle: &AppHandle) {
+    let Some(pending) = app_handle.try_state::<PendingNotification>() else {
+        return;
+    };
+    let Some((posted_at, item)) = pending.0.lock().take() else {
+        return;
+    };
+    if posted_at.elapsed() > CLICK_WINDOW {
+        return;
+    }
+    if let Some(window) = app_handle.get_webview_window("main") {
+        let _ = window.unminimize();
+        let _ = window.show();
+        let _ = window.set_focus();
+    }
+    crate::event_journal::emit_stamped(app_handle, "notification-clicked", item);
+}
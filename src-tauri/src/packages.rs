@@ -0,0 +1,274 @@
+//! Swift package dependencies: what's pinned, under which license, and
+//! whether there's a newer release
+//!
+//! Pins come from the project's Package.resolved (format 2 or 3, which share
+//! a layout; the older format 1 is read too). Licenses are classified from the
+//! LICENSE file in each package's checkout under a DerivedData
+//! `SourcePackages/checkouts`, so a package that was never built has none.
+//! With `check_latest`, `git ls-remote --tags` finds each package's highest
+//! release tag. Reports are cached by the Package.resolved contents; a cached
+//! newest-release check is reused for `LATEST_TTL`.
+
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::OnceLock;
+use std::time::{Duration, Instant};
+
+const LATEST_TTL: Duration = Duration::from_secs(60 * 60);
+
+const LICENSE_FILES: &[&str] = &["LICENSE", "LICENSE.md", "LICENSE.txt", "LICENCE", "LICENCE.md", "COPYING", "COPYING.md"];
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PackageDependency {
+    pub name: String,
+    pub url: String,
+    pub version: Option<String>,
+    pub revision: Option<String>,
+    /// Pinned to a branch rather than a version
+    pub branch: Option<String>,
+    /// SPDX-style identifier, "unknown" for a license file we can't classify,
+    /// None when no checkout or license file was found
+    pub license: Option<String>,
+    pub license_file: Option<String>,
+    /// Highest release tag, when `check_latest` was asked for and it worked
+    pub latest_version: Option<String>,
+    pub outdated: bool,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PackageReport {
+    /// None when the project doesn't use Swift packages
+    pub resolved_path: Option<String>,
+    pub format_version: Option<u32>,
+    pub dependencies: Vec<PackageDependency>,
+    /// Newer releases were looked up
+    pub latest_checked: bool,
+    /// `check_latest` was asked for but the network is down
+    pub offline: bool,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct PinState {
+    version: Option<String>,
+    revision: Option<String>,
+    branch: Option<String>,
+}
+
+/// A format 2/3 pin; format 1 names the fields `package` and `repositoryURL`
+#[derive(Deserialize)]
+struct Pin {
+    #[serde(alias = "package")]
+    identity: String,
+    #[serde(alias = "repositoryURL")]
+    location: String,
+    state: PinState,
+}
+
+#[derive(Deserialize)]
+struct V1Object {
+    pins: Vec<Pin>,
+}
+
+#[derive(Deserialize)]
+struct Resolved {
+    version: Option<u32>,
+    pins: Option<Vec<Pin>>,
+    object: Option<V1Object>,
+}
+
+/// The project's Package.resolved: a package's own, or the one Xcode keeps
+/// in the workspace or project
+fn find_resolved(project: &Path) -> Option<PathBuf> {
+    let own = project.join("Package.resolved");
+    if own.is_file() {
+        return Some(own);
+    }
+    let mut containers: Vec<PathBuf> = std::fs::read_dir(project)
+        .ok()?
+        .filter_map(|e| e.ok())
+        .map(|e| e.path())
+        .filter(|p| p.extension().is_some_and(|e| e == "xcworkspace" || e == "xcodeproj"))
+        .collect();
+    // Workspaces first, as they hold the resolution when there is one
+    containers.sort_by_key(|p| p.extension().is_some_and(|e| e == "xcodeproj"));
+    containers.into_iter().find_map(|container| {
+        [
+            container.join("xcshareddata/swiftpm/Package.resolved"),
+            container.join("project.xcworkspace/xcshareddata/swiftpm/Package.resolved"),
+        ]
+        .into_iter()
+        .find(|p| p.is_file())
+    })
+}
+
+/// "swift-collections" for "https://github.com/apple/swift-collections.git"
+fn repository_name(url: &str) -> &str {
+    let name = url.trim_end_matches('/').rsplit(['/', ':']).next().unwrap_or(url);
+    name.strip_suffix(".git").unwrap_or(name)
+}
+
+fn checkout_dir(derived_data: &[PathBuf], url: &str, identity: &str) -> Option<PathBuf> {
+    let repository = repository_name(url);
+    derived_data
+        .iter()
+        .map(|dir| dir.join("SourcePackages/checkouts"))
+        .flat_map(|checkouts| [checkouts.join(repository), checkouts.join(identity)])
+        .find(|dir| dir.is_dir())
+}
+
+/// Best guess at a license from its text
+fn classify_license(text: &str) -> &'static str {
+    let lower = text.to_lowercase();
+    let has = |s: &str| lower.contains(s);
+    if has("apache license") && has("version 2.0") {
+        "Apache-2.0"
+    } else if has("gnu lesser general public license") {
+        if has("version 3") { "LGPL-3.0" } else { "LGPL-2.1" }
+    } else if has("gnu affero general public license") {
+        "AGPL-3.0"
+    } else if has("gnu general public license") {
+        if has("version 3") { "GPL-3.0" } else { "GPL-2.0" }
+    } else if has("mozilla public license") {
+        "MPL-2.0"
+    } else if has("permission is hereby granted, free of charge") {
+        "MIT"
+    } else if has("redistribution and use in source and binary forms") {
+        if has("neither the name") { "BSD-3-Clause" } else { "BSD-2-Clause" }
+    } else if has("permission to use, copy, modify, and/or distribute") || has("isc license") {
+        "ISC"
+    } else if has("this is free and unencumbered software") {
+        "Unlicense"
+    } else if has("zlib license") || has("altered source versions must be plainly marked") {
+        "Zlib"
+    } else {
+        "unknown"
+    }
+}
+
+fn read_license(checkout: &Path) -> Option<(String, PathBuf)> {
+    LICENSE_FILES.iter().map(|name| checkout.join(name)).find_map(|path| {
+        let text = std::fs::read_to_string(&path).ok()?;
+        Some((classify_license(&text).to_string(), path))
+    })
+}
+
+/// Numeric parts of a release version; None for pre-releases and anything
+/// that isn't dotted numbers
+fn parse_version(version: &str) -> Option<Vec<u64>> {
+    let version = version.strip_prefix('v').unwrap_or(version);
+    let parts: Option<Vec<u64>> = version.split('.').map(|part| part.parse().ok()).collect();
+    parts.filter(|parts| !parts.is_empty() && parts.len() <= 4)
+}
+
+/// The highest release tag of the repository at `url`
+fn latest_release(url: &str) -> Option<String> {
+    let output = Command::new("git")
+        .args(["ls-remote", "--tags", "--refs", url])
+        .env("GIT_TERMINAL_PROMPT", "0")
+        .output()
+        .ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .filter_map(|line| line.split_once("refs/tags/").map(|(_, tag)| tag.trim().to_string()))
+        .filter_map(|tag| parse_version(&tag).map(|parts| (parts, tag)))
+        .max_by(|(a, _), (b, _)| a.cmp(b))
+        .map(|(_, tag)| tag)
+}
+
+fn parse(content: &str, derived_data: &[PathBuf]) -> Result<(Option<u32>, Vec<PackageDependency>), String> {
+    let resolved: Resolved = serde_json::from_str(content).map_err(|e| format!("Failed to parse Package.resolved: {}", e))?;
+    let pins = resolved.pins.or(resolved.object.map(|o| o.pins)).unwrap_or_default();
+    let dependencies = pins
+        .into_iter()
+        .map(|pin| {
+            let license = checkout_dir(derived_data, &pin.location, &pin.identity).and_then(|dir| read_license(&dir));
+            PackageDependency {
+                name: pin.identity,
+                url: pin.location,
+                version: pin.state.version,
+                revision: pin.state.revision,
+                branch: pin.state.branch,
+                license: license.as_ref().map(|(license, _)| license.clone()),
+                license_file: license.map(|(_, path)| path.to_string_lossy().to_string()),
+                latest_version: None,
+                outdated: false,
+            }
+        })
+        .collect();
+    Ok((resolved.version, dependencies))
+}
+
+/// Fill in `latest_version` and `outdated`, one `git ls-remote` per package at once
+fn check_latest_releases(dependencies: &mut [PackageDependency]) {
+    std::thread::scope(|scope| {
+        for dependency in dependencies.iter_mut() {
+            scope.spawn(move || {
+                let Some(latest) = latest_release(&dependency.url) else {
+                    return;
+                };
+                let pinned = dependency.version.as_deref().and_then(parse_version);
+                dependency.outdated = pinned.zip(parse_version(&latest)).is_some_and(|(pinned, latest)| latest > pinned);
+                dependency.latest_version = Some(latest);
+            });
+        }
+    });
+}
+
+struct CachedReport {
+    hash: String,
+    report: PackageReport,
+    latest_checked_at: Option<Instant>,
+}
+
+fn cache() -> &'static Mutex<HashMap<String, CachedReport>> {
+    static CACHE: OnceLock<Mutex<HashMap<String, CachedReport>>> = OnceLock::new();
+    CACHE.get_or_init(Default::default)
+}
+
+pub fn list_dependencies(project_path: &str, check_latest: bool) -> Result<PackageReport, String> {
+    let project = Path::new(project_path);
+    let Some(resolved_path) = find_resolved(project) else {
+        return Ok(PackageReport::default());
+    };
+    let content = std::fs::read_to_string(&resolved_path)
+        .map_err(|e| format!("Failed to read {}: {}", resolved_path.display(), e))?;
+    let hash = format!("{:x}", Sha256::digest(content.as_bytes()));
+    let offline = check_latest && crate::network::is_offline();
+
+    if let Some(cached) = cache().lock().get(project_path).filter(|c| c.hash == hash) {
+        let latest_fresh = cached.latest_checked_at.is_some_and(|at| at.elapsed() < LATEST_TTL);
+        if !check_latest || latest_fresh || offline {
+            let mut report = cached.report.clone();
+            report.offline = offline;
+            return Ok(report);
+        }
+    }
+
+    let (format_version, mut dependencies) = parse(&content, &crate::storage::derived_data_dirs(project))?;
+    let latest_checked = check_latest && !offline;
+    if latest_checked {
+        check_latest_releases(&mut dependencies);
+    }
+    let report = PackageReport {
+        resolved_path: Some(resolved_path.to_string_lossy().to_string()),
+        format_version,
+        dependencies,
+        latest_checked,
+        offline,
+    };
+    cache().lock().insert(
+        project_path.to_string(),
+        CachedReport { hash, report: report.clone(), latest_checked_at: latest_checked.then(Instant::now) },
+    );
+    Ok(report)
+}
@@ -0,0 +1,422 @@
+//! One prompt tried by several sessions at once, each in its own worktree
+//!
+//! `dispatch` creates a session worktree per attempt, all from the project's
+//! HEAD, and starts a Claude session in each with the same prompt. These run
+//! next to the chat's session instead of replacing it and are kept here by
+//! group. `compare` reports on each attempt: whether its turn has finished,
+//! its diff against the starting commit, a build of the worktree and what it
+//! cost. An attempt that failed to start or ended in an error is listed with
+//! its error and doesn't hold up the others. `adopt` commits the chosen
+//! worktree's changes, merges its branch into the project and removes every
+//! worktree of the group; `plan_adopt` works out what that touches, for it
+//! and its dry run.
+
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeSet, HashMap};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use tauri::{AppHandle, Manager};
+
+use crate::claude::{ClaudeModel, ClaudeSession, ClaudeSessionConfig};
+use crate::dry_run::DryRunReport;
+use crate::session_reports::SessionUsage;
+use crate::SessionContext;
+
+const DEFAULT_COUNT: usize = 2;
+const MAX_COUNT: usize = 5;
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct DispatchOptions {
+    /// Attempts to run; defaults to the number of `models`, else 2
+    pub count: Option<usize>,
+    /// Model per attempt, repeated when there are fewer than `count`
+    pub models: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ParallelMember {
+    pub session_id: String,
+    pub worktree_path: Option<String>,
+    pub branch: Option<String>,
+    pub model: Option<String>,
+    /// Why the attempt couldn't start
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ParallelGroup {
+    pub group_id: String,
+    pub project_path: String,
+    /// The commit every worktree started from
+    pub base_commit: String,
+    pub prompt: String,
+    pub members: Vec<ParallelMember>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BuildSummary {
+    pub success: bool,
+    pub errors: usize,
+    pub warnings: u32,
+    pub build_time: Option<f64>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ParallelResult {
+    pub session_id: String,
+    pub worktree_path: Option<String>,
+    pub branch: Option<String>,
+    pub model: Option<String>,
+    /// "running", "finished" or "failed"
+    pub status: String,
+    pub error: Option<String>,
+    pub files_changed: usize,
+    pub additions: u64,
+    pub deletions: u64,
+    /// Only for finished attempts
+    pub build: Option<BuildSummary>,
+    pub usage: Option<SessionUsage>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ParallelComparison {
+    pub group_id: String,
+    /// No attempt is still running
+    pub complete: bool,
+    pub results: Vec<ParallelResult>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AdoptResult {
+    pub merged_branch: String,
+    pub removed_worktrees: Vec<String>,
+    /// Worktrees or branches that couldn't be removed; the merge still happened
+    pub cleanup_errors: Vec<String>,
+}
+
+/// What adopting an attempt merges and removes
+pub struct AdoptPlan {
+    project_path: String,
+    prompt: String,
+    /// The chosen attempt's
+    worktree: String,
+    branch: String,
+    /// Every attempt's, the chosen one's included
+    worktrees: Vec<String>,
+    branches: Vec<String>,
+}
+
+struct Group {
+    info: ParallelGroup,
+    sessions: HashMap<String, ClaudeSession>,
+}
+
+/// Parallel groups by ID, with their running sessions
+#[derive(Default)]
+pub struct ParallelSessions {
+    groups: Mutex<HashMap<String, Group>>,
+}
+
+fn git(dir: &str, args: &[&str]) -> Result<String, String> {
+    let output = Command::new("git")
+        .args(args)
+        .current_dir(dir)
+        .output()
+        .map_err(|e| format!("Failed to run git {}: {}", args.join(" "), e))?;
+    if !output.status.success() {
+        return Err(format!(
+            "git {} failed: {}",
+            args.join(" "),
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+fn parse_model(name: &str) -> Result<ClaudeModel, String> {
+    match name.to_lowercase().as_str() {
+        "sonnet" => Ok(ClaudeModel::Sonnet),
+        "opus" => Ok(ClaudeModel::Opus),
+        "haiku" => Ok(ClaudeModel::Haiku),
+        _ => Err(format!("Unknown model: {}", name)),
+    }
+}
+
+/// Start one attempt: a session in its own worktree, given the prompt
+async fn start_member(
+    app_handle: &AppHandle,
+    group: &ParallelGroup,
+    index: usize,
+    model: Option<ClaudeModel>,
+) -> (ParallelMember, Option<ClaudeSession>) {
+    let mut member = ParallelMember {
+        session_id: String::new(),
+        worktree_path: None,
+        branch: None,
+        model: model.as_ref().map(|m| m.as_str().to_string()),
+        error: None,
+    };
+    let name = format!("parallel-{}-{}", &group.group_id[..8], index + 1);
+    let worktree = crate::create_session_worktree(
+        group.project_path.clone(),
+        name.clone(),
+        Some(group.base_commit.clone()),
+        Some(name),
+        app_handle.state::<Mutex<SessionContext>>(),
+    )
+    .await;
+    let worktree = match worktree {
+        Ok(worktree) => worktree,
+        Err(e) => {
+            member.error = Some(e);
+            return (member, None);
+        }
+    };
+    member.worktree_path = Some(worktree.path.clone());
+    member.branch = Some(worktree.branch.clone());
+
+    let config = ClaudeSessionConfig { model, ..Default::default() };
+    let session = match ClaudeSession::new_with_config(&worktree.path, app_handle.clone(), config) {
+        Ok(session) => session,
+        Err(e) => {
+            member.error = Some(e);
+            return (member, None);
+        }
+    };
+    member.session_id = session.get_session_id().to_string();
+    // The worktree was made before the session had an ID; point it at the session
+    app_handle.state::<Mutex<SessionContext>>().lock().set_working_dir(&member.session_id, &worktree.path);
+    let key = format!("branch.{}.{}", worktree.branch, crate::WORKTREE_SESSION_KEY);
+    if let Err(e) = git(&group.project_path, &["config", &key, &member.session_id]) {
+        log::warn!("Failed to record the session for {}: {}", worktree.branch, e);
+    }
+
+    if let Err(e) = session.send_message(&group.prompt, None, app_handle.clone()) {
+        member.error = Some(e);
+    }
+    (member, Some(session))
+}
+
+/// Start `options.count` sessions on `prompt`, each in a new worktree
+pub async fn dispatch(
+    app_handle: &AppHandle,
+    project_path: &str,
+    prompt: &str,
+    options: DispatchOptions,
+) -> Result<ParallelGroup, String> {
+    let count = options.count.unwrap_or(options.models.len().max(DEFAULT_COUNT));
+    if count == 0 || count > MAX_COUNT {
+        return Err(format!("Can run between 1 and {} attempts at once", MAX_COUNT));
+    }
+    let models = options
+        .models
+        .iter()
+        .map(|m| parse_model(m))
+        .collect::<Result<Vec<_>, _>>()?;
+    let base_commit = git(project_path, &["rev-parse", "HEAD"])?;
+
+    let mut group = ParallelGroup {
+        group_id: uuid::Uuid::new_v4().to_string(),
+        project_path: project_path.to_string(),
+        base_commit,
+        prompt: prompt.to_string(),
+        members: Vec::new(),
+    };
+    let mut sessions = HashMap::new();
+    for index in 0..count {
+        let model = (!models.is_empty()).then(|| models[index % models.len()].clone());
+        let (member, session) = start_member(app_handle, &group, index, model).await;
+        if let Some(session) = session {
+            sessions.insert(member.session_id.clone(), session);
+        }
+        group.members.push(member);
+    }
+    if sessions.is_empty() {
+        let errors: Vec<String> = group.members.iter().filter_map(|m| m.error.clone()).collect();
+        return Err(format!("No attempt could start: {}", errors.join("; ")));
+    }
+
+    app_handle
+        .state::<ParallelSessions>()
+        .groups
+        .lock()
+        .insert(group.group_id.clone(), Group { info: group.clone(), sessions });
+    Ok(group)
+}
+
+/// `(files, additions, deletions)` in the worktree against `base`, counting
+/// uncommitted and untracked files too
+fn diff_stats(worktree: &str, base: &str) -> (usize, u64, u64) {
+    let mut files = 0;
+    let mut additions = 0;
+    let mut deletions = 0;
+    for line in git(worktree, &["diff", "--numstat", base]).unwrap_or_default().lines() {
+        let mut parts = line.split('\t');
+        files += 1;
+        additions += parts.next().and_then(|n| n.parse::<u64>().ok()).unwrap_or(0);
+        deletions += parts.next().and_then(|n| n.parse::<u64>().ok()).unwrap_or(0);
+    }
+    let untracked = git(worktree, &["ls-files", "--others", "--exclude-standard"]).unwrap_or_default();
+    for file in untracked.lines().filter(|l| !l.is_empty()) {
+        files += 1;
+        additions += std::fs::read_to_string(Path::new(worktree).join(file)).map_or(0, |c| c.lines().count() as u64);
+    }
+    (files, additions, deletions)
+}
+
+/// Where each attempt stands; finished ones are built and diffed
+pub async fn compare(app_handle: &AppHandle, group_id: &str) -> Result<ParallelComparison, String> {
+    // Read what's needed under the lock; builds take minutes
+    let (group, outcomes) = {
+        let groups = app_handle.state::<ParallelSessions>();
+        let groups = groups.groups.lock();
+        let group = groups.get(group_id).ok_or_else(|| format!("No parallel group {}", group_id))?;
+        let outcomes: HashMap<String, Option<String>> = group
+            .sessions
+            .iter()
+            .map(|(id, session)| (id.clone(), session.turn_watch().outcome()))
+            .collect();
+        (group.info.clone(), outcomes)
+    };
+
+    let mut results = Vec::new();
+    for member in &group.members {
+        let outcome = outcomes.get(&member.session_id).cloned().flatten();
+        let (status, error) = match (&member.error, outcome.as_deref()) {
+            (Some(error), _) => ("failed", Some(error.clone())),
+            (None, Some("result")) => ("finished", None),
+            (None, Some(other)) => ("failed", Some(format!("The turn ended with {}", other))),
+            (None, None) => ("running", None),
+        };
+        let mut result = ParallelResult {
+            session_id: member.session_id.clone(),
+            worktree_path: member.worktree_path.clone(),
+            branch: member.branch.clone(),
+            model: member.model.clone(),
+            status: status.to_string(),
+            error,
+            files_changed: 0,
+            additions: 0,
+            deletions: 0,
+            build: None,
+            usage: crate::session_reports::usage(app_handle, &member.session_id),
+        };
+        if let Some(worktree) = member.worktree_path.clone() {
+            let base = group.base_commit.clone();
+            let worktree_dir = worktree.clone();
+            let stats = tauri::async_runtime::spawn_blocking(move || diff_stats(&worktree_dir, &base))
+                .await
+                .unwrap_or_default();
+            (result.files_changed, result.additions, result.deletions) = stats;
+
+            if status == "finished" {
+                // One at a time: builds share the build state that `cancel_build` uses
+                match crate::build_project(Some(worktree), None, None, None, None, None, app_handle.clone()).await {
+                    Ok(build) => {
+                        result.build = Some(BuildSummary {
+                            success: build.success,
+                            errors: build.errors.len(),
+                            warnings: build.warnings,
+                            build_time: build.build_time,
+                        })
+                    }
+                    Err(e) => result.error = Some(format!("Build failed to run: {}", e)),
+                }
+            }
+        }
+        results.push(result);
+    }
+
+    Ok(ParallelComparison {
+        group_id: group.group_id,
+        complete: results.iter().all(|r| r.status != "running"),
+        results,
+    })
+}
+
+/// Merge `session_id`'s worktree into the project and remove the group's worktrees
+/// Resolve the attempt `adopt` merges and the worktrees and branches it removes
+pub fn plan_adopt(app_handle: &AppHandle, group_id: &str, session_id: &str) -> Result<AdoptPlan, String> {
+    let group = {
+        let groups = app_handle.state::<ParallelSessions>();
+        let groups = groups.groups.lock();
+        groups.get(group_id).map(|g| g.info.clone()).ok_or_else(|| format!("No parallel group {}", group_id))?
+    };
+    let chosen = group
+        .members
+        .iter()
+        .find(|m| m.session_id == session_id)
+        .ok_or_else(|| format!("Session {} isn't part of group {}", session_id, group_id))?;
+    let (Some(worktree), Some(branch)) = (chosen.worktree_path.clone(), chosen.branch.clone()) else {
+        return Err(format!("Session {} has no worktree to adopt", session_id));
+    };
+    Ok(AdoptPlan {
+        worktrees: group.members.iter().filter_map(|m| m.worktree_path.clone()).collect(),
+        branches: group.members.iter().filter_map(|m| m.branch.clone()).collect(),
+        project_path: group.project_path,
+        prompt: group.prompt,
+        worktree,
+        branch,
+    })
+}
+
+/// What `adopt` would do: the files its merge changes in the project, which
+/// include the chosen attempt's uncommitted ones, and the worktrees and
+/// branches it deletes
+pub fn adopt_dry_run(plan: &AdoptPlan) -> Result<DryRunReport, String> {
+    let merged = git(&plan.project_path, &["diff", "--name-only", &format!("HEAD...{}", plan.branch)])?;
+    let uncommitted = git(&plan.worktree, &["diff", "--name-only", "HEAD"])?;
+    let untracked = git(&plan.worktree, &["ls-files", "--others", "--exclude-standard"])?;
+    let would_modify: BTreeSet<String> =
+        [merged, uncommitted, untracked].iter().flat_map(|files| files.lines().map(String::from)).collect();
+
+    let mut report = DryRunReport::deleting(plan.worktrees.iter().map(PathBuf::from).collect());
+    report.would_modify = would_modify.into_iter().collect();
+    report.would_delete_branches = plan.branches.clone();
+    Ok(report)
+}
+
+pub fn adopt(app_handle: &AppHandle, group_id: &str, plan: &AdoptPlan) -> Result<AdoptResult, String> {
+    let (worktree, branch) = (plan.worktree.as_str(), plan.branch.as_str());
+
+    // Stop every attempt so nothing writes to a worktree while it's merged or removed
+    if let Some(group) = app_handle.state::<ParallelSessions>().groups.lock().get(group_id) {
+        for session in group.sessions.values() {
+            session.stop();
+        }
+    }
+
+    if !git(worktree, &["status", "--porcelain"])?.is_empty() {
+        git(worktree, &["add", "-A"])?;
+        let first_line = plan.prompt.lines().next().unwrap_or("").chars().take(60).collect::<String>();
+        git(worktree, &["commit", "-m", &format!("Parallel attempt: {}", first_line)])?;
+    }
+    // A conflict leaves the merge for the user to resolve, and the worktrees in place
+    git(&plan.project_path, &["merge", "--no-ff", "--no-edit", branch])?;
+
+    let mut removed_worktrees = Vec::new();
+    let mut cleanup_errors = Vec::new();
+    for path in &plan.worktrees {
+        match crate::remove_git_worktree(path, true, Some(Path::new(&plan.project_path))) {
+            Ok(()) => removed_worktrees.push(path.clone()),
+            Err(e) => cleanup_errors.push(e),
+        }
+        app_handle.state::<Mutex<SessionContext>>().lock().forget_working_dir(path);
+    }
+    for member_branch in &plan.branches {
+        if let Err(e) = git(&plan.project_path, &["branch", "-D", member_branch]) {
+            cleanup_errors.push(e);
+        }
+    }
+    app_handle.state::<ParallelSessions>().groups.lock().remove(group_id);
+
+    Ok(AdoptResult { merged_branch: branch.to_string(), removed_worktrees, cleanup_errors })
+}
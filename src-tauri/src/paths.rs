@@ -1,4 +1,6 @@
+use sha2::{Digest, Sha256};
 use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
 
 const REPO_MARKER: &str = "src-tauri/Cargo.toml";
 
@@ -44,21 +46,94 @@ pub(crate) fn resolve_repo_root() -> Option<PathBuf> {
     None
 }
 
+/// The user's home directory. Everything nocur keeps or reads under home
+/// resolves it here, so a sandboxed build or a scratch HOME is handled in one place.
+pub(crate) fn home_dir() -> Result<PathBuf, String> {
+    dirs::home_dir().ok_or_else(|| "Couldn't determine the home directory".to_string())
+}
+
+/// `home_dir`, or the current directory when there isn't one
+pub(crate) fn home_dir_or_cwd() -> PathBuf {
+    dirs::home_dir().unwrap_or_else(|| PathBuf::from("."))
+}
+
+/// `~/.nocur`, where nocur keeps its own state
+pub(crate) fn nocur_dir() -> PathBuf {
+    home_dir_or_cwd().join(".nocur")
+}
+
+/// `~/.claude`, shared with Claude Code (skills, session history)
+pub(crate) fn claude_dir() -> Result<PathBuf, String> {
+    Ok(home_dir()?.join(".claude"))
+}
+
+/// The file an iCloud placeholder stands for: `.Name.swift.icloud` is what
+/// `Name.swift` becomes once iCloud Drive evicts its contents
+pub(crate) fn cloud_placeholder_target(path: &Path) -> Option<PathBuf> {
+    let name = path.file_name()?.to_str()?;
+    let original = name.strip_prefix('.')?.strip_suffix(".icloud")?;
+    if original.is_empty() {
+        return None;
+    }
+    Some(path.with_file_name(original))
+}
+
+/// The sync service a path lives under, if it's one with known pitfalls for
+/// builds and file watching: iCloud Drive, or a File Provider folder in
+/// `~/Library/CloudStorage` (Dropbox, OneDrive, Google Drive)
+pub(crate) fn cloud_sync_provider(path: &Path) -> Option<String> {
+    let home = home_dir().ok()?;
+    let path = std::fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf());
+
+    if path.starts_with(home.join("Library/Mobile Documents")) {
+        return Some("iCloud Drive".to_string());
+    }
+    let provider = path.strip_prefix(home.join("Library/CloudStorage")).ok()?.components().next()?;
+    // Folders are named like "Dropbox" or "OneDrive-Personal"
+    let provider = provider.as_os_str().to_string_lossy();
+    Some(provider.split('-').next().unwrap_or(&provider).to_string())
+}
+
 pub(crate) fn claude_service_entry(repo_root: &Path) -> PathBuf {
     repo_root.join("claude-service/dist/index.js")
 }
 
-pub(crate) fn resolve_claude_service_entry() -> Option<PathBuf> {
+static RESOURCE_DIR: OnceLock<PathBuf> = OnceLock::new();
+
+/// Record the app's resource directory; called once during setup
+pub(crate) fn set_resource_dir(dir: PathBuf) {
+    let _ = RESOURCE_DIR.set(dir);
+}
+
+pub(crate) fn resource_dir() -> Option<&'static Path> {
+    RESOURCE_DIR.get().map(PathBuf::as_path)
+}
+
+/// Where the claude-service entry is looked for, in order: the
+/// NOCUR_CLAUDE_SERVICE_PATH override, the copy installed under ~/.nocur,
+/// the one bundled in the app's resources, then a repo checkout
+pub(crate) fn claude_service_candidates() -> Vec<PathBuf> {
+    let mut candidates = Vec::new();
     if let Ok(path) = std::env::var("NOCUR_CLAUDE_SERVICE_PATH") {
-        let p = PathBuf::from(path);
-        if p.exists() {
-            return Some(p);
-        }
+        candidates.push(PathBuf::from(path));
     }
+    candidates.push(crate::claude_service::installed_dir().join("dist/index.js"));
+    if let Some(dir) = resource_dir() {
+        candidates.push(dir.join("claude-service/dist/index.js"));
+    }
+    if let Some(root) = resolve_repo_root() {
+        candidates.push(claude_service_entry(&root));
+    }
+    candidates
+}
 
-    resolve_repo_root()
-        .map(|root| claude_service_entry(&root))
-        .filter(|p| p.exists())
+pub(crate) fn resolve_claude_service_entry() -> Option<PathBuf> {
+    let bundled = resource_dir().map(|dir| dir.join("claude-service/dist/index.js"));
+    claude_service_candidates()
+        .into_iter()
+        // A bundle that failed its checksums at startup isn't used
+        .filter(|p| crate::claude_service::bundle_verified() || Some(p) != bundled.as_ref())
+        .find(|p| p.exists())
 }
 
 pub(crate) fn nocur_swift_release_binary(repo_root: &Path) -> PathBuf {
@@ -78,3 +153,235 @@ pub(crate) fn resolve_nocur_swift_binary() -> Option<PathBuf> {
         .filter(|p| p.exists())
 }
 
+
+/// Walk a project the way the file picker does: .gitignore-aware, hidden files included
+pub(crate) fn project_walker(root: &Path) -> ignore::Walk {
+    ignore::WalkBuilder::new(root)
+        .hidden(false)  // Don't skip hidden files
+        .git_ignore(true)  // Respect .gitignore
+        .git_global(true)  // Respect global .gitignore
+        .git_exclude(true)  // Respect .git/info/exclude
+        .max_depth(Some(10))  // Limit depth
+        .build()
+}
+
+/// A file path reported by a tool (xcodebuild, git), resolved against a project root
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct ProjectPath {
+    pub absolute: String,
+    /// Relative to the project root, or None when the file is outside it
+    pub relative: Option<String>,
+    /// True for package checkouts and DerivedData rather than the project's own sources
+    pub is_dependency: bool,
+}
+
+/// Resolve `path` (absolute, or relative to `project_root`) against the project.
+/// Symlinked roots are handled by retrying with both sides canonicalized, so
+/// `/tmp/App` and `/private/tmp/App` are treated as the same project.
+pub(crate) fn normalize_project_path(project_root: &Path, path: &str) -> ProjectPath {
+    let path = Path::new(path);
+    let absolute = if path.is_absolute() { path.to_path_buf() } else { project_root.join(path) };
+
+    let relative = absolute.strip_prefix(project_root).ok().map(Path::to_path_buf).or_else(|| {
+        let root = std::fs::canonicalize(project_root).ok()?;
+        // Deleted files can't be canonicalized, but their directory usually can
+        let file = std::fs::canonicalize(&absolute).ok().or_else(|| {
+            let parent = std::fs::canonicalize(absolute.parent()?).ok()?;
+            Some(parent.join(absolute.file_name()?))
+        })?;
+        file.strip_prefix(&root).ok().map(Path::to_path_buf)
+    });
+
+    let is_dependency = absolute
+        .components()
+        .any(|c| c.as_os_str() == "SourcePackages" || c.as_os_str() == "DerivedData");
+
+    ProjectPath {
+        absolute: absolute.to_string_lossy().to_string(),
+        relative: relative
+            .filter(|r| !r.as_os_str().is_empty())
+            .map(|r| r.to_string_lossy().to_string()),
+        is_dependency,
+    }
+}
+
+/// `~/.nocur/DerivedData`, holding every checkout's build cache
+pub(crate) fn derived_data_root() -> PathBuf {
+    nocur_dir().join("DerivedData")
+}
+
+/// Where builds put products and intermediates for a working directory:
+/// `<name>-<hash>` under `derived_data_root`, hashing the canonical path so each
+/// worktree of a project gets a cache of its own
+pub(crate) fn derived_data_dir(project_dir: &Path) -> PathBuf {
+    let canonical = std::fs::canonicalize(project_dir).unwrap_or_else(|_| project_dir.to_path_buf());
+    let name = canonical
+        .file_name()
+        .map_or_else(|| "project".to_string(), |n| sanitize_path_component(&n.to_string_lossy()));
+    let hash: String = Sha256::digest(canonical.to_string_lossy().as_bytes())
+        .iter()
+        .take(4)
+        .map(|b| format!("{:02x}", b))
+        .collect();
+    derived_data_root().join(format!("{}-{}", name, hash))
+}
+
+/// `<project>/DerivedData`, where builds went before caches moved under ~/.nocur
+pub(crate) fn legacy_derived_data_dir(project_dir: &Path) -> PathBuf {
+    project_dir.join("DerivedData")
+}
+
+/// Temp directory recordings' frames are written to
+pub(crate) fn recordings_dir() -> PathBuf {
+    std::env::temp_dir().join("nocur_recordings")
+}
+
+/// Replace anything but ASCII letters, digits, `-`, `_` and `.` so the result is safe
+/// as both a directory name and a git branch component
+pub(crate) fn sanitize_path_component(name: &str) -> String {
+    let sanitized: String = name
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.') { c } else { '-' })
+        .collect();
+    let trimmed = sanitized.trim_matches(|c| c == '-' || c == '.');
+    if trimmed.is_empty() { "session".to_string() } else { trimmed.to_string() }
+}
+
+/// Lowercase letters, digits and single hyphens, safe as both a branch and a
+/// directory name; None when nothing usable is left
+pub(crate) fn slugify(name: &str) -> Option<String> {
+    let mut slug = String::new();
+    for c in name.chars() {
+        if c.is_ascii_alphanumeric() {
+            slug.push(c.to_ascii_lowercase());
+        } else if !slug.is_empty() && !slug.ends_with('-') {
+            slug.push('-');
+        }
+    }
+    let slug = slug.trim_end_matches('-');
+    (!slug.is_empty()).then(|| slug.to_string())
+}
+
+/// `<parent of project>/<name>-worktree`. The project path is canonicalized first
+/// so trailing slashes, `..` and symlinks don't change which directory is the parent.
+pub(crate) fn sibling_worktree_path(project_path: &Path, name: &str) -> Result<PathBuf, String> {
+    let project = std::fs::canonicalize(project_path)
+        .map_err(|e| format!("Failed to resolve {}: {}", project_path.display(), e))?;
+    let parent = project
+        .parent()
+        .ok_or_else(|| format!("{} has no parent directory for a worktree", project.display()))?;
+    Ok(parent.join(format!("{}-worktree", sanitize_path_component(name))))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn resolves_paths_through_a_symlinked_root() {
+        let dir = tempfile::tempdir().unwrap();
+        let real = dir.path().join("real").join("App");
+        std::fs::create_dir_all(real.join("Sources")).unwrap();
+        std::fs::write(real.join("Sources/View.swift"), "").unwrap();
+        let link = dir.path().join("link");
+        std::os::unix::fs::symlink(dir.path().join("real"), &link).unwrap();
+        let linked = link.join("App");
+
+        let relative = |root: &Path, path: &Path| normalize_project_path(root, &path.to_string_lossy()).relative;
+        let expected = Some("Sources/View.swift".to_string());
+        assert_eq!(relative(&linked, &real.join("Sources/View.swift")), expected);
+        assert_eq!(relative(&real, &linked.join("Sources/View.swift")), expected);
+        // Deleted, so only its directory resolves
+        assert_eq!(relative(&linked, &real.join("Sources/Gone.swift")), Some("Sources/Gone.swift".to_string()));
+        assert_eq!(relative(&linked, &dir.path().join("real/Other/File.swift")), None);
+        assert_eq!(relative(&linked, &real), None);
+
+        let from_relative = normalize_project_path(&linked, "Sources/View.swift");
+        assert_eq!(from_relative.relative, expected);
+        assert_eq!(PathBuf::from(from_relative.absolute), linked.join("Sources/View.swift"));
+        assert!(!from_relative.is_dependency);
+    }
+
+    #[test]
+    fn flags_package_checkouts_and_derived_data() {
+        let dir = tempfile::tempdir().unwrap();
+        let project = dir.path().join("App");
+        let checkout = project.join("DerivedData/App/SourcePackages/checkouts/swift-log/Sources/Logging/Logger.swift");
+        let path = normalize_project_path(&project, &checkout.to_string_lossy());
+        assert!(path.is_dependency);
+        assert_eq!(
+            path.relative.as_deref(),
+            Some("DerivedData/App/SourcePackages/checkouts/swift-log/Sources/Logging/Logger.swift")
+        );
+
+        let outside = normalize_project_path(&project, "/Users/dev/Library/Developer/Xcode/DerivedData/App-abc/Build/Intermediates.noindex/App.swiftmodule");
+        assert!(outside.is_dependency);
+        assert_eq!(outside.relative, None);
+    }
+
+    #[test]
+    fn derived_data_dir_handles_spaces_and_non_ascii() {
+        let dir = tempfile::tempdir().unwrap();
+        let project = dir.path().join("My App ✨");
+        std::fs::create_dir_all(project.join("Sources")).unwrap();
+
+        let derived = derived_data_dir(&project);
+        assert_eq!(derived.parent(), Some(derived_data_root().as_path()));
+        let name = derived.file_name().unwrap().to_str().unwrap();
+        let (prefix, hash) = name.rsplit_once('-').unwrap();
+        assert_eq!(prefix, "My-App");
+        assert!(hash.len() == 8 && hash.chars().all(|c| c.is_ascii_hexdigit()));
+
+        // The same project however it's spelled, and not another one with the same sanitized name
+        assert_eq!(derived_data_dir(&project.join("Sources/..")), derived);
+        let other = dir.path().join("My App ✨✨");
+        std::fs::create_dir(&other).unwrap();
+        assert_ne!(derived_data_dir(&other), derived);
+        assert!(derived_data_dir(&other).file_name().unwrap().to_str().unwrap().starts_with("My-App-"));
+    }
+
+    #[test]
+    fn sanitized_components_round_trip() {
+        for (name, expected) in [
+            ("My App ✨", "My-App"),
+            ("Café Orders", "Caf--Orders"),
+            ("fix/layout bug", "fix-layout-bug"),
+            ("..hidden.", "hidden"),
+            ("✨", "session"),
+            ("plain-name_1.2", "plain-name_1.2"),
+        ] {
+            let sanitized = sanitize_path_component(name);
+            assert_eq!(sanitized, expected, "{:?}", name);
+            assert_eq!(sanitize_path_component(&sanitized), sanitized, "{:?} isn't stable", name);
+            assert!(sanitized.is_ascii());
+            assert_eq!(Path::new(&sanitized).file_name().and_then(|n| n.to_str()), Some(sanitized.as_str()));
+        }
+    }
+
+    #[test]
+    fn sibling_worktree_takes_a_real_worktree() {
+        let dir = tempfile::tempdir().unwrap();
+        let project = dir.path().join("My App ✨");
+        std::fs::create_dir(&project).unwrap();
+        let git = |args: &[&str]| {
+            let output = std::process::Command::new("git").args(args).current_dir(&project).output().unwrap();
+            assert!(output.status.success(), "git {:?}: {}", args, String::from_utf8_lossy(&output.stderr));
+            String::from_utf8_lossy(&output.stdout).to_string()
+        };
+        git(&["init", "-q"]);
+        std::fs::write(project.join("App.swift"), "let a = 1\n").unwrap();
+        git(&["add", "-A"]);
+        git(&["-c", "user.name=test", "-c", "user.email=test@localhost", "commit", "-qm", "initial"]);
+
+        let worktree = sibling_worktree_path(&project.join("./"), "Ünïcode session ✨").unwrap();
+        let parent = std::fs::canonicalize(dir.path()).unwrap();
+        assert_eq!(worktree, parent.join("n-code-session-worktree"));
+        let branch = format!("session-{}", sanitize_path_component("Ünïcode session ✨"));
+        git(&["worktree", "add", "-q", "-b", &branch, &worktree.to_string_lossy()]);
+
+        assert!(worktree.join("App.swift").is_file());
+        let listed = git(&["worktree", "list", "--porcelain"]);
+        assert!(listed.lines().any(|line| line == format!("worktree {}", worktree.display())));
+        assert!(listed.contains(&format!("branch refs/heads/{}", branch)));
+    }
+}
@@ -3,13 +3,16 @@ use std::collections::HashMap;
 use std::io::{BufRead, BufReader, Write};
 use std::os::unix::net::{UnixListener, UnixStream};
 use std::os::unix::fs::PermissionsExt;
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::Arc;
 use std::thread;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 use parking_lot::Mutex;
-use tauri::{AppHandle, Emitter};
+use tauri::{AppHandle, Manager};
 
-fn socket_path() -> std::path::PathBuf {
+use crate::claude_settings::PermissionRules;
+
+pub(crate) fn socket_path() -> std::path::PathBuf {
     std::env::temp_dir().join("nocur-permissions.sock")
 }
 
@@ -29,10 +32,63 @@ pub struct PermissionResponse {
     pub reason: Option<String>,
 }
 
+/// How long the accept loop may go without polling before it's taken as dead;
+/// it polls every 100ms while it's alive
+const ACCEPT_STALL: Duration = Duration::from_secs(10);
+/// How often the health timer checks the server
+const HEALTH_CHECK_INTERVAL: Duration = Duration::from_secs(30);
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PermissionServerStatus {
+    pub running: bool,
+    pub healthy: bool,
+    /// Why the health check failed, when it did
+    pub error: Option<String>,
+    /// Milliseconds since the accept loop last polled the socket
+    pub last_accept_activity_ms: u64,
+    pub restart_count: u64,
+    pub last_restart_reason: Option<String>,
+}
+
+/// Claude Code permission rules imported from its settings, so calls the user
+/// already allowed or denied there aren't asked about again
+#[derive(Debug, Default)]
+struct RuleCache {
+    global: PermissionRules,
+    /// Project path -> that project's rules
+    projects: HashMap<String, PermissionRules>,
+}
+
+impl RuleCache {
+    fn decide(&self, tool_name: &str, tool_input: &serde_json::Value, cwd: Option<&str>) -> Option<(bool, String)> {
+        let cwd = cwd.map(std::path::Path::new);
+        let project = cwd.and_then(|cwd| {
+            self.projects
+                .iter()
+                .filter(|(path, _)| cwd.starts_with(path))
+                .max_by_key(|(path, _)| path.len())
+                .map(|(_, rules)| rules)
+        });
+        match project {
+            Some(project) => crate::claude_settings::merged(&self.global, project).decide(tool_name, tool_input, cwd),
+            None => self.global.decide(tool_name, tool_input, cwd),
+        }
+    }
+}
+
 pub struct PermissionServer {
     pending_requests: Arc<Mutex<HashMap<String, tokio::sync::oneshot::Sender<PermissionResponse>>>>,
     running: Arc<Mutex<bool>>,
     auto_approve: Arc<Mutex<bool>>,
+    rules: Arc<Mutex<RuleCache>>,
+    /// Last time the accept loop got a connection or found none waiting
+    last_accept_activity: Arc<Mutex<Instant>>,
+    /// Bumped on each (re)bind; an accept loop from an older one exits
+    generation: Arc<AtomicU64>,
+    restart_count: AtomicU64,
+    last_restart_reason: Mutex<Option<String>>,
+    app_handle: Mutex<Option<AppHandle>>,
 }
 
 impl PermissionServer {
@@ -41,6 +97,12 @@ impl PermissionServer {
             pending_requests: Arc::new(Mutex::new(HashMap::new())),
             running: Arc::new(Mutex::new(false)),
             auto_approve: Arc::new(Mutex::new(false)),
+            rules: Arc::new(Mutex::new(RuleCache::default())),
+            last_accept_activity: Arc::new(Mutex::new(Instant::now())),
+            generation: Arc::new(AtomicU64::new(0)),
+            restart_count: AtomicU64::new(0),
+            last_restart_reason: Mutex::new(None),
+            app_handle: Mutex::new(None),
         }
     }
 
@@ -53,6 +115,11 @@ impl PermissionServer {
         *self.auto_approve.lock()
     }
 
+    /// Replace the imported Claude Code rules, global and per project
+    pub fn set_rules(&self, global: PermissionRules, projects: HashMap<String, PermissionRules>) {
+        *self.rules.lock() = RuleCache { global, projects };
+    }
+
     pub fn start(&self, app_handle: AppHandle) {
         // Check if already running
         {
@@ -63,65 +130,143 @@ impl PermissionServer {
             }
             *running = true;
         }
+        *self.app_handle.lock() = Some(app_handle.clone());
+
+        // Left marked as running, so the health check retries the bind
+        if let Err(e) = self.listen(app_handle) {
+            log::error!("{}", e);
+        }
+    }
+
+    /// Bind a fresh socket and start an accept loop on it; any older loop exits
+    fn listen(&self, app_handle: AppHandle) -> Result<(), String> {
+        let generation = self.generation.fetch_add(1, Ordering::SeqCst) + 1;
+        *self.last_accept_activity.lock() = Instant::now();
 
         let socket_path = socket_path();
         // Remove existing socket file
         let _ = std::fs::remove_file(&socket_path);
 
-        let pending = self.pending_requests.clone();
-        let running = self.running.clone();
-        let auto_approve = self.auto_approve.clone();
+        let listener = UnixListener::bind(&socket_path).map_err(|e| format!("Failed to bind permission socket: {}", e))?;
 
-        thread::spawn(move || {
-            let listener = match UnixListener::bind(&socket_path) {
-                Ok(l) => l,
-                Err(e) => {
-                    log::error!("Failed to bind permission socket: {}", e);
-                    *running.lock() = false;
-                    return;
-                }
-            };
+        // Restrict socket permissions to the current user.
+        if let Err(e) = std::fs::set_permissions(&socket_path, std::fs::Permissions::from_mode(0o600)) {
+            log::warn!("Failed to set permissions on {}: {}", socket_path.display(), e);
+        }
 
-            // Restrict socket permissions to the current user.
-            if let Err(e) = std::fs::set_permissions(&socket_path, std::fs::Permissions::from_mode(0o600)) {
-                log::warn!("Failed to set permissions on {}: {}", socket_path.display(), e);
-            }
+        log::info!("Permission server listening on {}", socket_path.display());
 
-            log::info!("Permission server listening on {}", socket_path.display());
+        // Set socket to non-blocking for graceful shutdown
+        listener.set_nonblocking(true).ok();
 
-            // Set socket to non-blocking for graceful shutdown
-            listener.set_nonblocking(true).ok();
+        let pending = self.pending_requests.clone();
+        let running = self.running.clone();
+        let auto_approve = self.auto_approve.clone();
+        let rules = self.rules.clone();
+        let last_activity = self.last_accept_activity.clone();
+        let current_generation = self.generation.clone();
 
-            while *running.lock() {
+        thread::spawn(move || {
+            let is_current = || current_generation.load(Ordering::SeqCst) == generation;
+            while *running.lock() && is_current() {
                 match listener.accept() {
                     Ok((stream, _)) => {
+                        *last_activity.lock() = Instant::now();
                         let pending_clone = pending.clone();
                         let app_clone = app_handle.clone();
                         let auto_approve_clone = auto_approve.clone();
+                        let rules_clone = rules.clone();
 
                         thread::spawn(move || {
-                            handle_connection(stream, pending_clone, app_clone, auto_approve_clone);
+                            handle_connection(stream, pending_clone, app_clone, auto_approve_clone, rules_clone);
                         });
                     }
                     Err(ref e) if e.kind() == std::io::ErrorKind::WouldBlock => {
+                        *last_activity.lock() = Instant::now();
                         // No connection available, sleep briefly
                         thread::sleep(Duration::from_millis(100));
                     }
                     Err(e) => {
+                        // Not counted as activity: a listener that only errors is dead
                         log::error!("Failed to accept connection: {}", e);
+                        thread::sleep(Duration::from_millis(100));
                     }
                 }
             }
 
-            log::info!("Permission server stopped");
-            let _ = std::fs::remove_file(&socket_path);
+            // A restart has already put a new socket file in place
+            if is_current() {
+                log::info!("Permission server stopped");
+                let _ = std::fs::remove_file(&socket_path);
+            }
         });
+        Ok(())
     }
 
     pub fn stop(&self) {
         *self.running.lock() = false;
     }
 
+    /// Whether the accept loop is still polling and the socket takes a
+    /// loopback connection
+    pub fn health_check(&self) -> Result<(), String> {
+        if !*self.running.lock() {
+            return Err("Permission server isn't running".to_string());
+        }
+        let idle = self.last_accept_activity.lock().elapsed();
+        if idle > ACCEPT_STALL {
+            return Err(format!("Accept loop hasn't polled for {}s", idle.as_secs()));
+        }
+        UnixStream::connect(socket_path())
+            .map(|_| ())
+            .map_err(|e| format!("Loopback connect to the permission socket failed: {}", e))
+    }
+
+    /// Unbind, remove the socket file and bind again
+    pub fn restart(&self, reason: &str) -> Result<(), String> {
+        let app_handle = self
+            .app_handle
+            .lock()
+            .clone()
+            .ok_or("Permission server was never started")?;
+        log::warn!("Restarting permission server: {}", reason);
+        *self.running.lock() = true;
+        self.listen(app_handle.clone())?;
+
+        let restart_count = self.restart_count.fetch_add(1, Ordering::SeqCst) + 1;
+        *self.last_restart_reason.lock() = Some(reason.to_string());
+        crate::event_journal::emit_stamped(
+            &app_handle,
+            "permission-server-restarted",
+            &serde_json::json!({ "reason": reason, "restartCount": restart_count }),
+        );
+        Ok(())
+    }
+
+    /// Restart the server if it was started and fails its health check.
+    /// Returns whether it restarted.
+    pub fn ensure_running(&self) -> Result<bool, String> {
+        if self.app_handle.lock().is_none() || !*self.running.lock() {
+            return Ok(false);
+        }
+        match self.health_check() {
+            Ok(()) => Ok(false),
+            Err(reason) => self.restart(&reason).map(|_| true),
+        }
+    }
+
+    pub fn status(&self) -> PermissionServerStatus {
+        let error = self.health_check().err();
+        PermissionServerStatus {
+            running: *self.running.lock(),
+            healthy: error.is_none(),
+            error,
+            last_accept_activity_ms: self.last_accept_activity.lock().elapsed().as_millis() as u64,
+            restart_count: self.restart_count.load(Ordering::SeqCst),
+            last_restart_reason: self.last_restart_reason.lock().clone(),
+        }
+    }
+
     pub fn respond(&self, request_id: &str, response: PermissionResponse) {
         let mut pending = self.pending_requests.lock();
         if let Some(sender) = pending.remove(request_id) {
@@ -132,11 +277,44 @@ impl PermissionServer {
     }
 }
 
+/// The answer to a tool call that needs no prompt: a rule from the user's
+/// Claude Code settings, or auto-approve. Safe mode never approves without
+/// asking, so then only `deny` rules answer.
+fn decide_unasked(
+    rules: &RuleCache,
+    auto_approve: bool,
+    safe_mode: bool,
+    tool_name: &str,
+    tool_input: &serde_json::Value,
+    cwd: Option<&str>,
+) -> Option<PermissionResponse> {
+    match rules.decide(tool_name, tool_input, cwd) {
+        Some((approve, rule)) if !(approve && safe_mode) => {
+            log::info!("Permission rule {} {} {}", rule, if approve { "approves" } else { "blocks" }, tool_name);
+            return Some(PermissionResponse {
+                decision: if approve { "approve" } else { "block" }.to_string(),
+                reason: Some(format!("Matched Claude Code permission rule {}", rule)),
+            });
+        }
+        Some((_, rule)) => log::info!("Safe mode: asking about {} despite permission rule {}", tool_name, rule),
+        None => {}
+    }
+    if auto_approve && !safe_mode {
+        log::info!("Auto-approving permission request for: {}", tool_name);
+        return Some(PermissionResponse {
+            decision: "approve".to_string(),
+            reason: Some("Auto-approved (skip permissions mode)".to_string()),
+        });
+    }
+    None
+}
+
 fn handle_connection(
     mut stream: UnixStream,
     pending: Arc<Mutex<HashMap<String, tokio::sync::oneshot::Sender<PermissionResponse>>>>,
     app_handle: AppHandle,
     auto_approve: Arc<Mutex<bool>>,
+    rules: Arc<Mutex<RuleCache>>,
 ) {
     // Set timeout for read
     stream.set_read_timeout(Some(Duration::from_secs(60))).ok();
@@ -158,6 +336,11 @@ fn handle_connection(
         return;
     }
 
+    // Health check probes connect and hang up without sending anything
+    if line.trim().is_empty() {
+        return;
+    }
+
     log::debug!("Received permission request: {}", line.trim());
 
     // Parse the tool request from hook
@@ -176,12 +359,13 @@ fn handle_connection(
         .and_then(|v| v.as_str())
         .unwrap_or("unknown");
 
-    // Check auto-approve mode - respond immediately without waiting for frontend
-    if *auto_approve.lock() {
-        log::info!("Auto-approving permission request for: {}", tool_name);
-        let response = r#"{"decision": "approve", "reason": "Auto-approved (skip permissions mode)"}"#;
-        if let Err(e) = writeln!(stream, "{}", response) {
-            log::error!("Failed to write auto-approve response: {}", e);
+    let tool_input = tool_info.get("tool_input").cloned().unwrap_or(serde_json::Value::Null);
+    let cwd = tool_info.get("cwd").and_then(|v| v.as_str());
+    let safe_mode = crate::safe_mode::is_enabled(&app_handle);
+    if let Some(response) = decide_unasked(&rules.lock(), *auto_approve.lock(), safe_mode, tool_name, &tool_input, cwd) {
+        let response_json = serde_json::to_string(&response).unwrap_or_default();
+        if let Err(e) = writeln!(stream, "{}", response_json) {
+            log::error!("Failed to write permission response: {}", e);
         }
         let _ = stream.flush();
         return;
@@ -194,9 +378,7 @@ fn handle_connection(
     let request = PermissionRequest {
         id: request_id.clone(),
         tool_name: tool_name.to_string(),
-        tool_input: tool_info.get("tool_input")
-            .cloned()
-            .unwrap_or(serde_json::Value::Null),
+        tool_input,
         session_id: tool_info.get("session_id")
             .and_then(|v| v.as_str())
             .map(String::from),
@@ -213,9 +395,7 @@ fn handle_connection(
 
     // Emit event to frontend
     log::info!("Emitting permission request: {} - {}", request.id, request.tool_name);
-    if let Err(e) = app_handle.emit("permission-request", &request) {
-        log::error!("Failed to emit permission request: {}", e);
-    }
+    crate::event_journal::emit_stamped(&app_handle, "permission-request", &request);
 
     // Wait for response (blocking with timeout)
     let response = {
@@ -269,3 +449,57 @@ impl PermissionState {
         }
     }
 }
+
+/// Check the permission server every `HEALTH_CHECK_INTERVAL` and restart it
+/// when it's stopped answering, as it can after the Mac sleeps
+pub fn spawn_health_timer(app_handle: AppHandle) {
+    thread::spawn(move || loop {
+        thread::sleep(HEALTH_CHECK_INTERVAL);
+        let Some(state) = app_handle.try_state::<Mutex<PermissionState>>() else {
+            return;
+        };
+        let result = state.lock().server.ensure_running();
+        if let Err(e) = result {
+            log::error!("Permission server health check: {}", e);
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::claude_settings::PermissionRules;
+
+    fn imported(allow: &[&str], deny: &[&str]) -> RuleCache {
+        RuleCache {
+            global: PermissionRules {
+                allow: allow.iter().map(|r| r.to_string()).collect(),
+                deny: deny.iter().map(|r| r.to_string()).collect(),
+            },
+            projects: HashMap::new(),
+        }
+    }
+
+    #[test]
+    fn safe_mode_asks_despite_an_allow_rule() {
+        let rules = imported(&["Bash(git status)"], &[]);
+        let input = serde_json::json!({ "command": "git status" });
+
+        let approved = decide_unasked(&rules, false, false, "Bash", &input, None).unwrap();
+        assert_eq!(approved.decision, "approve");
+        assert!(decide_unasked(&rules, false, true, "Bash", &input, None).is_none());
+        // Nor does auto-approve get past it
+        assert!(decide_unasked(&rules, true, true, "Bash", &input, None).is_none());
+    }
+
+    #[test]
+    fn safe_mode_still_honours_deny_rules() {
+        let rules = imported(&["Bash"], &["Bash(rm:*)"]);
+        let input = serde_json::json!({ "command": "rm -rf build" });
+        for safe_mode in [false, true] {
+            let blocked = decide_unasked(&rules, true, safe_mode, "Bash", &input, None).unwrap();
+            assert_eq!(blocked.decision, "block");
+            assert_eq!(blocked.reason.as_deref(), Some("Matched Claude Code permission rule Bash(rm:*)"));
+        }
+    }
+}
@@ -0,0 +1,167 @@
+//! Warming the slow per-project loads when a project opens
+//!
+//! Opening a project used to mean a run of sequential calls (devices, git,
+//! sessions, skills, playbook, Open In), each spawning processes. The
+//! `prefetch_project_state` command starts them all at once instead and keeps
+//! the results here; the commands serve a result while it's younger than its
+//! item's TTL and load normally otherwise. Each finished load is announced with
+//! a `prefetch-ready` event and recorded with how long it took.
+//!
+//! Only prefetching fills the cache, so ordinary calls never see an older
+//! result than they would have without it, beyond the TTL right after an open.
+//! Commands that change what's cached drop the entry with `invalidate`.
+
+use parking_lot::Mutex;
+use serde::Serialize;
+use std::any::Any;
+use std::collections::HashMap;
+use std::sync::OnceLock;
+use std::time::{Duration, Instant};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum PrefetchItem {
+    Devices,
+    GitInfo,
+    GitDiffStats,
+    Sessions,
+    Skills,
+    Playbook,
+    OpenIn,
+}
+
+impl PrefetchItem {
+    pub const ALL: [PrefetchItem; 7] = [
+        PrefetchItem::Devices,
+        PrefetchItem::GitInfo,
+        PrefetchItem::GitDiffStats,
+        PrefetchItem::Sessions,
+        PrefetchItem::Skills,
+        PrefetchItem::Playbook,
+        PrefetchItem::OpenIn,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            PrefetchItem::Devices => "devices",
+            PrefetchItem::GitInfo => "gitInfo",
+            PrefetchItem::GitDiffStats => "gitDiffStats",
+            PrefetchItem::Sessions => "sessions",
+            PrefetchItem::Skills => "skills",
+            PrefetchItem::Playbook => "playbook",
+            PrefetchItem::OpenIn => "openIn",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|item| item.name() == name)
+    }
+
+    /// How long a prefetched result is served. Git state changes with every
+    /// edit, so it's only meant to cover the burst of calls right after open.
+    pub fn ttl(self) -> Duration {
+        match self {
+            PrefetchItem::GitInfo | PrefetchItem::GitDiffStats => Duration::from_secs(5),
+            PrefetchItem::Sessions | PrefetchItem::Skills | PrefetchItem::Playbook => Duration::from_secs(15),
+            PrefetchItem::Devices => Duration::from_secs(30),
+            // Open In results live in `OpenInCache`; this only covers the timing
+            PrefetchItem::OpenIn => Duration::from_secs(300),
+        }
+    }
+}
+
+/// `prefetch-ready` payload, also what `get_prefetch_status` reports per item
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PrefetchReady {
+    pub project_path: String,
+    pub item: String,
+    pub duration_ms: u64,
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PrefetchStatus {
+    #[serde(flatten)]
+    pub ready: PrefetchReady,
+    pub age_ms: u64,
+    /// Still within the TTL, so commands are served from it
+    pub fresh: bool,
+}
+
+struct Entry {
+    fetched_at: Instant,
+    value: Option<Box<dyn Any + Send>>,
+    ready: PrefetchReady,
+}
+
+/// Keyed by item and project path; devices aren't per project and use ""
+type Entries = HashMap<(PrefetchItem, String), Entry>;
+
+fn entries() -> &'static Mutex<Entries> {
+    static ENTRIES: OnceLock<Mutex<Entries>> = OnceLock::new();
+    ENTRIES.get_or_init(Default::default)
+}
+
+/// A fresh prefetched result for `key`
+pub fn get<T: Clone + 'static>(item: PrefetchItem, key: &str) -> Option<T> {
+    let entries = entries().lock();
+    let entry = entries.get(&(item, key.to_string()))?;
+    if entry.fetched_at.elapsed() >= item.ttl() {
+        return None;
+    }
+    entry.value.as_ref()?.downcast_ref::<T>().cloned()
+}
+
+/// Record a finished prefetch; a failed one is kept for its timing only
+pub fn insert<T: Send + 'static>(
+    item: PrefetchItem,
+    key: &str,
+    project_path: &str,
+    started: Instant,
+    result: Result<T, String>,
+) -> PrefetchReady {
+    let (value, error) = match result {
+        Ok(value) => (Some(Box::new(value) as Box<dyn Any + Send>), None),
+        Err(e) => (None, Some(e)),
+    };
+    let ready = PrefetchReady {
+        project_path: project_path.to_string(),
+        item: item.name().to_string(),
+        duration_ms: started.elapsed().as_millis() as u64,
+        error,
+    };
+    entries().lock().insert(
+        (item, key.to_string()),
+        Entry { fetched_at: Instant::now(), value, ready: ready.clone() },
+    );
+    ready
+}
+
+/// Drop cached results, for one project and/or one item; None matches all.
+/// The device list goes with any project.
+pub fn invalidate(key: Option<&str>, item: Option<PrefetchItem>) {
+    entries().lock().retain(|(entry_item, entry_key), _| {
+        let key_matches = key.map_or(true, |key| entry_key == key || entry_key.is_empty());
+        !(key_matches && item.map_or(true, |item| *entry_item == item))
+    });
+}
+
+/// Timings of the prefetches made for `key`, and of the device list
+pub fn status(key: &str) -> Vec<PrefetchStatus> {
+    let entries = entries().lock();
+    let mut statuses: Vec<PrefetchStatus> = entries
+        .iter()
+        .filter(|((_, entry_key), _)| entry_key == key || entry_key.is_empty())
+        .map(|((item, _), entry)| {
+            let age = entry.fetched_at.elapsed();
+            PrefetchStatus {
+                ready: entry.ready.clone(),
+                age_ms: age.as_millis() as u64,
+                fresh: entry.value.is_some() && age < item.ttl(),
+            }
+        })
+        .collect();
+    statuses.sort_by(|a, b| a.ready.item.cmp(&b.ready.item));
+    statuses
+}
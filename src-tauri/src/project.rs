@@ -1,8 +1,11 @@
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use std::fs;
+use std::io::{BufRead, BufReader};
 use std::path::{Path, PathBuf};
-use std::process::Command;
+use std::process::{Command, Stdio};
 use chrono::Utc;
+use tauri::AppHandle;
 
 // =============================================================================
 // Types
@@ -39,6 +42,49 @@ pub struct CreateProjectRequest {
     pub location: String,
     #[serde(default)]
     pub bundle_id_prefix: Option<String>,
+    /// Template ID from `list_project_templates`; the built-in template when absent
+    #[serde(default)]
+    pub template: Option<String>,
+    /// Values for the template's declared variables
+    #[serde(default)]
+    pub variables: HashMap<String, String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TemplateVariable {
+    /// Placeholder name, used as `{{NAME}}`
+    pub name: String,
+    #[serde(default)]
+    pub label: Option<String>,
+    #[serde(default)]
+    pub default: Option<String>,
+    #[serde(default)]
+    pub required: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ProjectTemplate {
+    pub id: String,
+    pub name: String,
+    pub description: Option<String>,
+    pub builtin: bool,
+    /// Variables beyond PROJECT_NAME, BUNDLE_ID and YEAR the UI should prompt for
+    pub variables: Vec<TemplateVariable>,
+    pub post_create: Vec<String>,
+    /// Why the template's manifest couldn't be read
+    pub error: Option<String>,
+}
+
+/// Progress of a template's post-create commands, emitted as `project-template-event`
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TemplateHookEvent {
+    pub project_path: String,
+    pub command: String,
+    pub event_type: String, // "started" | "output" | "completed" | "failed"
+    pub message: String,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -51,6 +97,9 @@ pub struct ProjectValidation {
     pub has_xcodeproj: bool,
     pub has_package_swift: bool,
     pub error: Option<String>,
+    /// Problems that don't stop the project opening, e.g. living in a synced folder
+    #[serde(default)]
+    pub warnings: Vec<String>,
 }
 
 // =============================================================================
@@ -164,6 +213,7 @@ pub fn validate_project(path: &str) -> Result<ProjectValidation, String> {
             has_xcodeproj: false,
             has_package_swift: false,
             error: Some("Path does not exist".to_string()),
+            warnings: Vec::new(),
         });
     }
     
@@ -176,6 +226,7 @@ pub fn validate_project(path: &str) -> Result<ProjectValidation, String> {
             has_xcodeproj: false,
             has_package_swift: false,
             error: Some("Path is not a directory".to_string()),
+            warnings: Vec::new(),
         });
     }
     
@@ -210,6 +261,18 @@ pub fn validate_project(path: &str) -> Result<ProjectValidation, String> {
     };
     
     let is_valid = has_tuist || has_xcodeproj || has_package_swift;
+
+    let warnings = crate::paths::cloud_sync_provider(path)
+        .map(|provider| {
+            format!(
+                "This project is in {}. Files it evicts from this Mac show up as placeholders the agent \
+                 and the build can't read, half-synced files can break builds, and watchers see \
+                 duplicate entries. Moving the project to a local folder avoids this.",
+                provider
+            )
+        })
+        .into_iter()
+        .collect();
     
     Ok(ProjectValidation {
         is_valid,
@@ -223,6 +286,7 @@ pub fn validate_project(path: &str) -> Result<ProjectValidation, String> {
         } else {
             None
         },
+        warnings,
     })
 }
 
@@ -230,15 +294,15 @@ pub fn validate_project(path: &str) -> Result<ProjectValidation, String> {
 // Project Creation
 // =============================================================================
 
-pub fn create_project(request: &CreateProjectRequest) -> Result<ProjectInfo, String> {
+pub fn create_project(request: &CreateProjectRequest, app_handle: &AppHandle) -> Result<ProjectInfo, String> {
     fn expand_tilde(path: &str) -> PathBuf {
         if path == "~" {
-            return dirs::home_dir().unwrap_or_else(|| PathBuf::from(path));
+            return crate::paths::home_dir().unwrap_or_else(|_| PathBuf::from(path));
         }
         if let Some(rest) = path.strip_prefix("~/") {
-            return dirs::home_dir()
+            return crate::paths::home_dir()
                 .map(|home| home.join(rest))
-                .unwrap_or_else(|| PathBuf::from(path));
+                .unwrap_or_else(|_| PathBuf::from(path));
         }
         PathBuf::from(path)
     }
@@ -256,15 +320,6 @@ pub fn create_project(request: &CreateProjectRequest) -> Result<ProjectInfo, Str
         return Err("Invalid project name. Use only letters, numbers, and hyphens.".to_string());
     }
     
-    // Create project directory
-    fs::create_dir_all(&project_dir)
-        .map_err(|e| format!("Failed to create project directory: {}", e))?;
-    
-    // Create source directory
-    let source_dir = project_dir.join(&request.name);
-    fs::create_dir_all(&source_dir)
-        .map_err(|e| format!("Failed to create source directory: {}", e))?;
-    
     // Generate bundle ID
     let bundle_id_prefix = request
         .bundle_id_prefix
@@ -275,7 +330,69 @@ pub fn create_project(request: &CreateProjectRequest) -> Result<ProjectInfo, Str
         bundle_id_prefix,
         request.name.to_lowercase().replace("-", "")
     );
+
+    // Resolve a user template before touching the disk so a bad one leaves nothing behind
+    let user_template = match request.template.as_deref() {
+        None | Some(BUILTIN_TEMPLATE_ID) => None,
+        Some(id) => {
+            let template = load_user_template(id)?;
+            if let Some(error) = &template.error {
+                return Err(format!("Template {} is invalid: {}", id, error));
+            }
+            let variables = template_values(&template, request, &bundle_id)?;
+            Some((template, variables))
+        }
+    };
+
+    // Create project directory
+    fs::create_dir_all(&project_dir)
+        .map_err(|e| format!("Failed to create project directory: {}", e))?;
+
+    let project_type = match user_template {
+        Some((template, variables)) => {
+            copy_template_dir(&user_templates_dir().join(&template.id), &project_dir, &variables, true)?;
+            run_post_create(&template.post_create, &project_dir, app_handle);
+            validate_project(&project_dir.to_string_lossy())
+                .map(|v| v.project_type)
+                .unwrap_or(ProjectType::Unknown)
+        }
+        None => {
+            write_builtin_template(&project_dir, &request.name, &bundle_id)?;
+            ProjectType::Tuist
+        }
+    };
+
+    // Initialize git repository (best-effort).
+    // This improves UX for worktrees and prevents "unknown" git status in the UI.
+    try_init_git_repo(&project_dir);
     
+    let project_path = project_dir.to_string_lossy().to_string();
+    
+    // Add to recent projects
+    let _ = add_recent_project(&project_path);
+    
+    Ok(ProjectInfo {
+        path: project_path,
+        name: request.name.clone(),
+        last_opened: Utc::now().timestamp(),
+        project_type,
+    })
+}
+
+/// The built-in template's CLAUDE.md for a project
+pub fn render_claude_md(name: &str, bundle_id: &str) -> String {
+    TEMPLATE_CLAUDE_MD
+        .replace("{{PROJECT_NAME}}", name)
+        .replace("{{BUNDLE_ID}}", bundle_id)
+}
+
+/// The built-in SwiftUI + Tuist app
+fn write_builtin_template(project_dir: &Path, name: &str, bundle_id: &str) -> Result<(), String> {
+    // Create source directory
+    let source_dir = project_dir.join(name);
+    fs::create_dir_all(&source_dir)
+        .map_err(|e| format!("Failed to create source directory: {}", e))?;
+
     // Write Tuist.swift
     fs::write(
         project_dir.join("Tuist.swift"),
@@ -284,8 +401,8 @@ pub fn create_project(request: &CreateProjectRequest) -> Result<ProjectInfo, Str
     
     // Write Project.swift
     let project_swift = TEMPLATE_PROJECT_SWIFT
-        .replace("{{PROJECT_NAME}}", &request.name)
-        .replace("{{BUNDLE_ID}}", &bundle_id);
+        .replace("{{PROJECT_NAME}}", name)
+        .replace("{{BUNDLE_ID}}", bundle_id);
     fs::write(
         project_dir.join("Project.swift"),
         project_swift,
@@ -298,17 +415,14 @@ pub fn create_project(request: &CreateProjectRequest) -> Result<ProjectInfo, Str
     ).map_err(|e| format!("Failed to write .gitignore: {}", e))?;
     
     // Write CLAUDE.md
-    let claude_md = TEMPLATE_CLAUDE_MD
-        .replace("{{PROJECT_NAME}}", &request.name)
-        .replace("{{BUNDLE_ID}}", &bundle_id);
     fs::write(
         project_dir.join("CLAUDE.md"),
-        claude_md,
+        render_claude_md(name, bundle_id),
     ).map_err(|e| format!("Failed to write CLAUDE.md: {}", e))?;
     
     // Write App.swift
     let app_swift = TEMPLATE_APP_SWIFT
-        .replace("{{PROJECT_NAME}}", &request.name);
+        .replace("{{PROJECT_NAME}}", name);
     fs::write(
         source_dir.join("App.swift"),
         app_swift,
@@ -326,7 +440,7 @@ pub fn create_project(request: &CreateProjectRequest) -> Result<ProjectInfo, Str
     // Run tuist generate
     let tuist_result = Command::new("tuist")
         .args(["generate", "--no-open"])
-        .current_dir(&project_dir)
+        .current_dir(project_dir)
         .output();
     
     match tuist_result {
@@ -342,21 +456,7 @@ pub fn create_project(request: &CreateProjectRequest) -> Result<ProjectInfo, Str
         }
     }
 
-    // Initialize git repository (best-effort).
-    // This improves UX for worktrees and prevents "unknown" git status in the UI.
-    try_init_git_repo(&project_dir);
-    
-    let project_path = project_dir.to_string_lossy().to_string();
-    
-    // Add to recent projects
-    let _ = add_recent_project(&project_path);
-    
-    Ok(ProjectInfo {
-        path: project_path,
-        name: request.name.clone(),
-        last_opened: Utc::now().timestamp(),
-        project_type: ProjectType::Tuist,
-    })
+    Ok(())
 }
 
 fn try_init_git_repo(project_dir: &Path) {
@@ -488,6 +588,497 @@ fn create_asset_catalog(source_dir: &Path) -> Result<(), String> {
     Ok(())
 }
 
+// =============================================================================
+// Importing Existing Projects
+// =============================================================================
+
+/// What `setup_existing_project` found and did
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ExistingProjectSetup {
+    pub project: ProjectInfo,
+    pub validation: ProjectValidation,
+    pub schemes: Vec<String>,
+    /// Lowest iOS deployment target the project declares
+    pub deployment_target: Option<String>,
+    /// "swift-package-manager", "cocoapods" and/or "carthage"
+    pub package_managers: Vec<String>,
+    pub bundle_id: Option<String>,
+    pub build_command: String,
+    pub claude_md_written: bool,
+    /// When the project already has a CLAUDE.md, the generated one as a
+    /// unified diff against it; the file itself is left alone
+    pub claude_md_proposal: Option<String>,
+    pub settings_created: bool,
+    pub gitignore_updated: bool,
+}
+
+/// Lines added to .gitignore by `setup_existing_project`
+const NOCUR_GITIGNORE_ENTRIES: &[&str] = &[".nocur/", ".claude/settings.local.json"];
+
+/// The top-level workspace, or else project, and whether it's a workspace
+fn find_xcode_container(dir: &Path) -> Option<(PathBuf, bool)> {
+    let entries: Vec<PathBuf> = fs::read_dir(dir).ok()?.filter_map(|e| e.ok()).map(|e| e.path()).collect();
+    let with_extension = |ext: &str| entries.iter().find(|p| p.extension().is_some_and(|e| e == ext)).cloned();
+    with_extension("xcworkspace")
+        .map(|p| (p, true))
+        .or_else(|| with_extension("xcodeproj").map(|p| (p, false)))
+}
+
+/// First capture of `pattern` across `texts`, picked by `choose` from all matches
+fn find_setting(texts: &[String], pattern: &str, choose: fn(Vec<String>) -> Option<String>) -> Option<String> {
+    let re = regex::Regex::new(pattern).ok()?;
+    let values: Vec<String> = texts
+        .iter()
+        .flat_map(|text| re.captures_iter(text).map(|c| c[1].trim_matches('"').to_string()).collect::<Vec<_>>())
+        .filter(|v| !v.is_empty() && !v.contains("$("))
+        .collect();
+    choose(values)
+}
+
+/// The lowest of some dotted versions
+fn lowest_version(versions: Vec<String>) -> Option<String> {
+    let key = |v: &String| v.split('.').map(|p| p.parse::<u32>().unwrap_or(0)).collect::<Vec<_>>();
+    versions.into_iter().min_by_key(key)
+}
+
+/// The app's bundle ID: the first one that isn't a test or extension target's
+fn app_bundle_id(ids: Vec<String>) -> Option<String> {
+    let is_app = |id: &String| !["Tests", "UITests", "Extension", "Widget"].iter().any(|s| id.contains(s));
+    ids.iter().find(|id| is_app(id)).or(ids.first()).cloned()
+}
+
+/// Build settings sources: Tuist and package manifests plus the Xcode project files
+fn manifest_texts(dir: &Path, xcode: Option<&(PathBuf, bool)>) -> Vec<String> {
+    let mut files = vec![dir.join("Project.swift"), dir.join("Package.swift")];
+    if let Some((container, is_workspace)) = xcode {
+        if *is_workspace {
+            // The workspace's own projects sit next to it
+            files.extend(
+                fs::read_dir(dir)
+                    .into_iter()
+                    .flatten()
+                    .filter_map(|e| e.ok())
+                    .map(|e| e.path())
+                    .filter(|p| p.extension().is_some_and(|e| e == "xcodeproj"))
+                    .map(|p| p.join("project.pbxproj")),
+            );
+        } else {
+            files.push(container.join("project.pbxproj"));
+        }
+    }
+    files.iter().filter_map(|f| fs::read_to_string(f).ok()).collect()
+}
+
+/// A build setting's value from the project's pbxproj files or Tuist manifest
+pub(crate) fn build_setting(path: &str, name: &str) -> Option<String> {
+    let dir = Path::new(path);
+    let texts = manifest_texts(dir, find_xcode_container(dir).as_ref());
+    let name = regex::escape(name);
+    let first = |values: Vec<String>| values.into_iter().next();
+    find_setting(&texts, &format!(r"{} = ([^;\s]+);", name), first)
+        .or_else(|| find_setting(&texts, &format!(r#""{}":\s*"([^"]+)""#, name), first))
+}
+
+/// The app target's bundle ID, from the same sources as `build_setting`
+pub(crate) fn detected_bundle_id(path: &str) -> Option<String> {
+    let dir = Path::new(path);
+    let texts = manifest_texts(dir, find_xcode_container(dir).as_ref());
+    find_setting(&texts, r"PRODUCT_BUNDLE_IDENTIFIER = ([^;\s]+);", app_bundle_id)
+        .or_else(|| find_setting(&texts, r#"bundleId:\s*"([^"]+)""#, app_bundle_id))
+}
+
+fn detect_package_managers(dir: &Path) -> Vec<String> {
+    let mut managers = Vec::new();
+    if dir.join("Package.swift").exists() || has_resolved_packages(dir) {
+        managers.push("swift-package-manager".to_string());
+    }
+    if dir.join("Podfile").exists() {
+        managers.push("cocoapods".to_string());
+    }
+    if dir.join("Cartfile").exists() {
+        managers.push("carthage".to_string());
+    }
+    managers
+}
+
+/// Xcode-managed package dependencies leave a Package.resolved in the workspace
+fn has_resolved_packages(dir: &Path) -> bool {
+    fs::read_dir(dir)
+        .into_iter()
+        .flatten()
+        .filter_map(|e| e.ok())
+        .map(|e| e.path())
+        .any(|p| {
+            p.join("xcshareddata/swiftpm/Package.resolved").exists()
+                || p.join("project.xcworkspace/xcshareddata/swiftpm/Package.resolved").exists()
+        })
+}
+
+fn build_command(project_type: &ProjectType, xcode: Option<&(PathBuf, bool)>, scheme: Option<&str>) -> String {
+    match (project_type, xcode) {
+        (ProjectType::Tuist, _) => "tuist generate --no-open && tuist build".to_string(),
+        (_, Some((container, is_workspace))) => {
+            let file_name = container.file_name().and_then(|n| n.to_str()).unwrap_or_default();
+            let scheme = scheme
+                .map(String::from)
+                .unwrap_or_else(|| container.file_stem().and_then(|s| s.to_str()).unwrap_or("App").to_string());
+            format!(
+                "xcodebuild {} \"{}\" -scheme \"{}\" -destination 'platform=iOS Simulator,name=iPhone 16 Pro' build",
+                if *is_workspace { "-workspace" } else { "-project" },
+                file_name,
+                scheme
+            )
+        }
+        _ => "swift build".to_string(),
+    }
+}
+
+/// `diff -u` of the existing file against the proposed content
+fn unified_diff(existing: &Path, proposed: &str) -> Result<String, String> {
+    let proposed_path = std::env::temp_dir().join(format!("nocur-claude-md-{}.md", uuid::Uuid::new_v4()));
+    fs::write(&proposed_path, proposed).map_err(|e| format!("Failed to write proposal: {}", e))?;
+    let output = Command::new("diff")
+        .args(["-u", "--label", "CLAUDE.md", "--label", "CLAUDE.md (proposed)"])
+        .arg(existing)
+        .arg(&proposed_path)
+        .output();
+    let _ = fs::remove_file(&proposed_path);
+    let output = output.map_err(|e| format!("Failed to run diff: {}", e))?;
+    // diff exits 1 when the files differ, 2 on trouble
+    if output.status.code() == Some(2) {
+        return Err(format!("diff failed: {}", String::from_utf8_lossy(&output.stderr).trim()));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).to_string())
+}
+
+fn write_starter_settings(dir: &Path, build_command: &str) -> Result<bool, String> {
+    let claude_dir = dir.join(".claude");
+    fs::create_dir_all(&claude_dir).map_err(|e| format!("Failed to create .claude: {}", e))?;
+    let settings_path = claude_dir.join("settings.local.json");
+    if settings_path.exists() {
+        return Ok(false);
+    }
+    let build_tool = build_command.split_whitespace().next().unwrap_or("xcodebuild");
+    let settings = serde_json::json!({
+        "permissions": {
+            "allow": [
+                format!("Bash({}:*)", build_tool),
+                "Bash(xcrun simctl:*)",
+                "Bash(git status:*)",
+                "Bash(git diff:*)",
+            ]
+        }
+    });
+    let content = serde_json::to_string_pretty(&settings).map_err(|e| format!("Failed to serialize settings: {}", e))?;
+    fs::write(&settings_path, content).map_err(|e| format!("Failed to write settings.local.json: {}", e))?;
+    Ok(true)
+}
+
+/// Append the entries .gitignore doesn't have yet; false when it had them all
+fn add_gitignore_entries(dir: &Path) -> Result<bool, String> {
+    let path = dir.join(".gitignore");
+    let mut content = fs::read_to_string(&path).unwrap_or_default();
+    let missing: Vec<&str> = NOCUR_GITIGNORE_ENTRIES
+        .iter()
+        .copied()
+        .filter(|entry| !content.lines().any(|line| line.trim() == *entry))
+        .collect();
+    if missing.is_empty() {
+        return Ok(false);
+    }
+    if !content.is_empty() && !content.ends_with('\n') {
+        content.push('\n');
+    }
+    content.push_str("\n# Nocur\n");
+    for entry in missing {
+        content.push_str(entry);
+        content.push('\n');
+    }
+    fs::write(&path, content).map_err(|e| format!("Failed to write .gitignore: {}", e))?;
+    Ok(true)
+}
+
+/// Bring a project nocur didn't create into the workflow: detect how it builds,
+/// write a CLAUDE.md saying so (or propose one next to an existing file), add
+/// starter agent settings and register it in recents
+pub fn setup_existing_project(path: &str, update_gitignore: bool) -> Result<ExistingProjectSetup, String> {
+    let validation = validate_project(path)?;
+    if !validation.is_valid {
+        return Err(validation.error.unwrap_or_else(|| "Not a project directory".to_string()));
+    }
+    let dir = Path::new(path);
+
+    let xcode = find_xcode_container(dir);
+    let schemes = match &xcode {
+        Some((container, is_workspace)) => crate::schemes::list_schemes(container, *is_workspace).unwrap_or_else(|e| {
+            log::warn!("Couldn't list schemes for {}: {}", path, e);
+            Vec::new()
+        }),
+        None => Vec::new(),
+    };
+    let texts = manifest_texts(dir, xcode.as_ref());
+    let deployment_target = find_setting(&texts, r"IPHONEOS_DEPLOYMENT_TARGET = ([0-9.]+);", lowest_version)
+        .or_else(|| find_setting(&texts, r#"\.iOS\(\s*\.?v?"?([0-9._]+)"?\s*\)"#, lowest_version).map(|v| v.replace('_', ".")));
+    let bundle_id = detected_bundle_id(path);
+    let package_managers = detect_package_managers(dir);
+    let build_command = build_command(&validation.project_type, xcode.as_ref(), schemes.first().map(String::as_str));
+
+    let claude_md = expand_placeholders(
+        TEMPLATE_EXISTING_CLAUDE_MD,
+        &[
+            ("PROJECT_NAME".to_string(), validation.name.clone()),
+            ("BUILD_COMMAND".to_string(), build_command.clone()),
+            ("SCHEMES".to_string(), if schemes.is_empty() { "(none detected)".to_string() } else { schemes.join(", ") }),
+            ("DEPLOYMENT_TARGET".to_string(), deployment_target.clone().map_or("(not detected)".to_string(), |v| format!("iOS {}", v))),
+            ("PACKAGE_MANAGERS".to_string(), if package_managers.is_empty() { "none".to_string() } else { package_managers.join(", ") }),
+            ("BUNDLE_ID".to_string(), bundle_id.clone().unwrap_or_else(|| "(not detected)".to_string())),
+        ],
+    );
+    let claude_md_path = dir.join("CLAUDE.md");
+    let (claude_md_written, claude_md_proposal) = if claude_md_path.exists() {
+        (false, Some(unified_diff(&claude_md_path, &claude_md)?))
+    } else {
+        fs::write(&claude_md_path, &claude_md).map_err(|e| format!("Failed to write CLAUDE.md: {}", e))?;
+        (true, None)
+    };
+
+    let settings_created = write_starter_settings(dir, &build_command)?;
+    let gitignore_updated = update_gitignore && add_gitignore_entries(dir)?;
+
+    let project = add_recent_project(path)?
+        .into_iter()
+        .find(|p| p.path == path)
+        .ok_or("Project wasn't added to recents")?;
+
+    Ok(ExistingProjectSetup {
+        project,
+        validation,
+        schemes,
+        deployment_target,
+        package_managers,
+        bundle_id,
+        build_command,
+        claude_md_written,
+        claude_md_proposal,
+        settings_created,
+        gitignore_updated,
+    })
+}
+
+// =============================================================================
+// User Templates
+// =============================================================================
+
+/// ID of the template generated from the constants below
+const BUILTIN_TEMPLATE_ID: &str = "swiftui-tuist";
+/// Optional manifest at the root of a user template; never copied into the project
+const TEMPLATE_MANIFEST: &str = "template.json";
+/// Never copied out of a user template
+const TEMPLATE_SKIPPED_NAMES: &[&str] = &[".git", ".DS_Store"];
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+struct TemplateManifest {
+    name: Option<String>,
+    description: Option<String>,
+    variables: Vec<TemplateVariable>,
+    /// Shell commands run in the new project, in order, stopping at the first failure
+    #[serde(alias = "post_create")]
+    post_create: Vec<String>,
+}
+
+/// `~/.nocur/templates`; each subdirectory is a template named after it
+fn user_templates_dir() -> PathBuf {
+    crate::paths::nocur_dir().join("templates")
+}
+
+fn load_user_template(id: &str) -> Result<ProjectTemplate, String> {
+    if id.is_empty() || id.contains(['/', '\\']) || id.starts_with('.') {
+        return Err(format!("Invalid template name: {}", id));
+    }
+    let dir = user_templates_dir().join(id);
+    if !dir.is_dir() {
+        return Err(format!("Template not found: {}", dir.display()));
+    }
+
+    let manifest_path = dir.join(TEMPLATE_MANIFEST);
+    let (manifest, error) = if manifest_path.exists() {
+        match fs::read_to_string(&manifest_path)
+            .map_err(|e| format!("Failed to read {}: {}", TEMPLATE_MANIFEST, e))
+            .and_then(|content| {
+                serde_json::from_str::<TemplateManifest>(&content)
+                    .map_err(|e| format!("Failed to parse {}: {}", TEMPLATE_MANIFEST, e))
+            }) {
+            Ok(manifest) => (manifest, None),
+            Err(e) => (TemplateManifest::default(), Some(e)),
+        }
+    } else {
+        (TemplateManifest::default(), None)
+    };
+
+    Ok(ProjectTemplate {
+        id: id.to_string(),
+        name: manifest.name.unwrap_or_else(|| id.to_string()),
+        description: manifest.description,
+        builtin: false,
+        variables: manifest.variables,
+        post_create: manifest.post_create,
+        error,
+    })
+}
+
+/// The built-in template followed by every template under ~/.nocur/templates
+pub fn list_project_templates() -> Vec<ProjectTemplate> {
+    let mut templates = vec![ProjectTemplate {
+        id: BUILTIN_TEMPLATE_ID.to_string(),
+        name: "SwiftUI App (Tuist)".to_string(),
+        description: Some("A SwiftUI iOS app managed with Tuist".to_string()),
+        builtin: true,
+        variables: vec![],
+        post_create: vec![],
+        error: None,
+    }];
+
+    let mut ids: Vec<String> = fs::read_dir(user_templates_dir())
+        .into_iter()
+        .flatten()
+        .flatten()
+        .filter(|e| e.path().is_dir())
+        .map(|e| e.file_name().to_string_lossy().to_string())
+        .filter(|id| !id.starts_with('.'))
+        .collect();
+    ids.sort();
+    templates.extend(ids.iter().filter_map(|id| load_user_template(id).ok()));
+    templates
+}
+
+/// Placeholder values for a user template: the built-ins, then each declared
+/// variable from the request or its default
+fn template_values(
+    template: &ProjectTemplate,
+    request: &CreateProjectRequest,
+    bundle_id: &str,
+) -> Result<Vec<(String, String)>, String> {
+    let mut values = vec![
+        ("PROJECT_NAME".to_string(), request.name.clone()),
+        ("BUNDLE_ID".to_string(), bundle_id.to_string()),
+        ("YEAR".to_string(), Utc::now().format("%Y").to_string()),
+    ];
+    for variable in &template.variables {
+        let value = request
+            .variables
+            .get(&variable.name)
+            .filter(|v| !v.is_empty())
+            .or(variable.default.as_ref())
+            .cloned();
+        match value {
+            Some(value) => values.push((variable.name.clone(), value)),
+            None if variable.required => {
+                return Err(format!(
+                    "Template variable {} is required",
+                    variable.label.as_deref().unwrap_or(&variable.name)
+                ))
+            }
+            None => values.push((variable.name.clone(), String::new())),
+        }
+    }
+    Ok(values)
+}
+
+fn expand_placeholders(text: &str, values: &[(String, String)]) -> String {
+    values.iter().fold(text.to_string(), |text, (name, value)| {
+        text.replace(&format!("{{{{{}}}}}", name), value)
+    })
+}
+
+/// Copy a template tree, expanding placeholders in file and directory names and
+/// in every file that's valid UTF-8. Other files are copied byte for byte.
+fn copy_template_dir(src: &Path, dest: &Path, values: &[(String, String)], is_root: bool) -> Result<(), String> {
+    let entries = fs::read_dir(src).map_err(|e| format!("Failed to read {}: {}", src.display(), e))?;
+    for entry in entries.flatten() {
+        let file_name = entry.file_name().to_string_lossy().to_string();
+        if TEMPLATE_SKIPPED_NAMES.contains(&file_name.as_str()) || (is_root && file_name == TEMPLATE_MANIFEST) {
+            continue;
+        }
+        let source = entry.path();
+        let target = dest.join(expand_placeholders(&file_name, values));
+
+        if source.is_dir() {
+            fs::create_dir_all(&target)
+                .map_err(|e| format!("Failed to create {}: {}", target.display(), e))?;
+            copy_template_dir(&source, &target, values, false)?;
+            continue;
+        }
+
+        let bytes = fs::read(&source).map_err(|e| format!("Failed to read {}: {}", source.display(), e))?;
+        let contents = match String::from_utf8(bytes) {
+            Ok(text) => expand_placeholders(&text, values).into_bytes(),
+            Err(e) => e.into_bytes(),
+        };
+        fs::write(&target, contents).map_err(|e| format!("Failed to write {}: {}", target.display(), e))?;
+    }
+    Ok(())
+}
+
+fn emit_template_event(app_handle: &AppHandle, project_dir: &Path, command: &str, event_type: &str, message: &str) {
+    crate::event_journal::emit_stamped(app_handle, "project-template-event", TemplateHookEvent {
+        project_path: project_dir.to_string_lossy().to_string(),
+        command: command.to_string(),
+        event_type: event_type.to_string(),
+        message: message.to_string(),
+    });
+}
+
+/// Run a template's post-create commands through the user's login shell so
+/// Homebrew tools are on PATH. Output is streamed line by line; the first
+/// failure stops the rest, but the project is kept.
+fn run_post_create(commands: &[String], project_dir: &Path, app_handle: &AppHandle) {
+    let shell = std::env::var("SHELL").unwrap_or_else(|_| "/bin/zsh".to_string());
+    for command in commands {
+        emit_template_event(app_handle, project_dir, command, "started", command);
+
+        // stderr joins stdout so the lines arrive in order
+        let child = Command::new(&shell)
+            .arg("-lc")
+            .arg(format!("exec 2>&1\n{}", command))
+            .current_dir(project_dir)
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn();
+        let mut child = match child {
+            Ok(child) => child,
+            Err(e) => {
+                emit_template_event(app_handle, project_dir, command, "failed", &format!("Failed to run {}: {}", shell, e));
+                return;
+            }
+        };
+
+        if let Some(stdout) = child.stdout.take() {
+            for line in BufReader::new(stdout).lines().map_while(Result::ok) {
+                emit_template_event(app_handle, project_dir, command, "output", &line);
+            }
+        }
+
+        match child.wait() {
+            Ok(status) if status.success() => {
+                emit_template_event(app_handle, project_dir, command, "completed", "");
+            }
+            Ok(status) => {
+                let message = format!("Exited with {}", status);
+                eprintln!("Warning: post-create command `{}` failed: {}", command, message);
+                emit_template_event(app_handle, project_dir, command, "failed", &message);
+                return;
+            }
+            Err(e) => {
+                emit_template_event(app_handle, project_dir, command, "failed", &format!("Failed to wait: {}", e));
+                return;
+            }
+        }
+    }
+}
+
 // =============================================================================
 // Templates
 // =============================================================================
@@ -583,6 +1174,32 @@ tuist build             # Build the project
 - Use SwiftUI best practices
 "#;
 
+const TEMPLATE_EXISTING_CLAUDE_MD: &str = r#"# {{PROJECT_NAME}}
+
+## Project Overview
+An existing iOS project, imported into Nocur.
+
+## Build & Run
+The project builds automatically when you click Run in Nocur.
+
+```bash
+# Manual build
+{{BUILD_COMMAND}}
+```
+
+- Schemes: {{SCHEMES}}
+- Deployment target: {{DEPLOYMENT_TARGET}}
+- Package managers: {{PACKAGE_MANAGERS}}
+
+## Bundle ID
+`{{BUNDLE_ID}}`
+
+## Guidelines
+- After ANY code change: build and verify with screenshot
+- After ANY UI interaction: take screenshot to confirm
+- Follow the conventions already in the codebase
+"#;
+
 const TEMPLATE_APP_SWIFT: &str = r#"import SwiftUI
 
 @main
@@ -0,0 +1,164 @@
+//! One `run_project` at a time per project
+//!
+//! Two runs of the same project interleave their builds and installs, and the
+//! second can install an .app the first is still writing. A run takes the
+//! project's lock for its whole build-install-launch pipeline; a second run
+//! either fails with `RunInProgress` or, when queued, waits for the lock.
+//!
+//! Each run gets an ID, stamped on the build events emitted while it's the
+//! latest run and on its `app-launched`, so the frontend can drop events from
+//! a run that was superseded. `cancel` stops the run's build and frees the
+//! lock straight away; the cancelled run notices at its next step and returns.
+
+use parking_lot::Mutex;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::process::Command;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Instant;
+use tauri::{AppHandle, Manager};
+use tokio::sync::Notify;
+
+/// The run holding a project's lock, reported to a run that was turned away
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RunInProgress {
+    pub run_id: String,
+    pub project_path: String,
+    pub elapsed_ms: u64,
+}
+
+struct ActiveRun {
+    run_id: String,
+    started: Instant,
+    cancelled: Arc<AtomicBool>,
+    /// The run's xcodebuild or tuist process, while it builds
+    build_pid: Option<u32>,
+}
+
+#[derive(Default)]
+pub struct RunLocks {
+    runs: Mutex<HashMap<String, ActiveRun>>,
+    /// The latest run started, whose ID goes on build events
+    current: Mutex<Option<String>>,
+    released: Notify,
+}
+
+/// Worktrees canonicalize to different directories, so they don't block each other
+fn lock_key(project_path: &str) -> String {
+    std::fs::canonicalize(project_path).map_or_else(|_| project_path.to_string(), |p| p.to_string_lossy().to_string())
+}
+
+/// Holds a project's run lock until dropped
+pub struct RunTicket {
+    app_handle: AppHandle,
+    key: String,
+    run_id: String,
+    cancelled: Arc<AtomicBool>,
+}
+
+impl RunTicket {
+    pub fn run_id(&self) -> &str {
+        &self.run_id
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::SeqCst)
+    }
+}
+
+impl Drop for RunTicket {
+    fn drop(&mut self) {
+        if let Some(locks) = self.app_handle.try_state::<RunLocks>() {
+            locks.release(&self.key, &self.run_id);
+        }
+    }
+}
+
+impl RunLocks {
+    /// Take `project_path`'s lock, or with `queue` wait until it's free
+    pub async fn acquire(&self, app_handle: &AppHandle, project_path: &str, queue: bool) -> Result<RunTicket, RunInProgress> {
+        let key = lock_key(project_path);
+        loop {
+            // Created before checking, so a release in between still wakes it
+            let released = self.released.notified();
+            if let Some(ticket) = self.try_acquire(app_handle, &key, project_path, queue)? {
+                return Ok(ticket);
+            }
+            released.await;
+        }
+    }
+
+    /// None when the lock is taken and the caller should wait for it
+    fn try_acquire(
+        &self,
+        app_handle: &AppHandle,
+        key: &str,
+        project_path: &str,
+        queue: bool,
+    ) -> Result<Option<RunTicket>, RunInProgress> {
+        let mut runs = self.runs.lock();
+        if let Some(active) = runs.get(key) {
+            if queue {
+                return Ok(None);
+            }
+            return Err(RunInProgress {
+                run_id: active.run_id.clone(),
+                project_path: project_path.to_string(),
+                elapsed_ms: active.started.elapsed().as_millis() as u64,
+            });
+        }
+        let run_id = uuid::Uuid::new_v4().to_string();
+        let cancelled = Arc::new(AtomicBool::new(false));
+        runs.insert(key.to_string(), ActiveRun {
+            run_id: run_id.clone(),
+            started: Instant::now(),
+            cancelled: cancelled.clone(),
+            build_pid: None,
+        });
+        *self.current.lock() = Some(run_id.clone());
+        Ok(Some(RunTicket { app_handle: app_handle.clone(), key: key.to_string(), run_id, cancelled }))
+    }
+
+    /// Free the lock if `run_id` still holds it
+    fn release(&self, key: &str, run_id: &str) {
+        let mut runs = self.runs.lock();
+        if runs.get(key).is_some_and(|run| run.run_id == run_id) {
+            runs.remove(key);
+        }
+        drop(runs);
+        let mut current = self.current.lock();
+        if current.as_deref() == Some(run_id) {
+            *current = None;
+        }
+        drop(current);
+        self.released.notify_waiters();
+    }
+
+    /// ID of the latest run still going
+    pub fn current_run_id(&self) -> Option<String> {
+        self.current.lock().clone()
+    }
+
+    /// Record the build process of the project's active run, for `cancel`
+    pub fn set_build_pid(&self, project_path: &str, pid: Option<u32>) {
+        if let Some(run) = self.runs.lock().get_mut(&lock_key(project_path)) {
+            run.build_pid = pid;
+        }
+    }
+
+    /// Cancel the project's active run: stop its build and free the lock.
+    /// Returns the cancelled run's ID, None when nothing was running.
+    pub fn cancel(&self, project_path: &str) -> Option<String> {
+        let key = lock_key(project_path);
+        let run = self.runs.lock().remove(&key)?;
+        run.cancelled.store(true, Ordering::SeqCst);
+        if let Some(pid) = run.build_pid {
+            // Builds run in their own process group
+            let _ = Command::new("kill").args(["-TERM", "--", &format!("-{}", pid)]).output();
+        }
+        self.release(&key, &run.run_id);
+        Some(run.run_id)
+    }
+}
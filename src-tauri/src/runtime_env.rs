@@ -0,0 +1,94 @@
+//! Standard NOCUR_* environment for processes nocur spawns
+//!
+//! The claude-service (and through it Claude Code, its hooks and Bash tool) and the
+//! embedded terminal all receive the same variables so scripts can find the active
+//! project, session and sockets without guessing:
+//!
+//! - `NOCUR_PROJECT_PATH`: working directory of the active session
+//! - `NOCUR_SESSION_ID`: nocur's ID for the active session
+//! - `NOCUR_PERMISSION_SOCKET`: Unix socket the permission hook talks to
+//! - `NOCUR_DEBUG_SNAPSHOT_PATH`: where debug builds write the UI snapshot
+//!
+//! When merged into an environment from elsewhere (the user's shell, a launch
+//! configuration) these always win, so a stale or user-set value can't point
+//! tools at the wrong project or socket.
+
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+pub const NOCUR_PROJECT_PATH: &str = "NOCUR_PROJECT_PATH";
+pub const NOCUR_SESSION_ID: &str = "NOCUR_SESSION_ID";
+pub const NOCUR_PERMISSION_SOCKET: &str = "NOCUR_PERMISSION_SOCKET";
+pub const NOCUR_DEBUG_SNAPSHOT_PATH: &str = "NOCUR_DEBUG_SNAPSHOT_PATH";
+
+pub fn debug_snapshot_path() -> PathBuf {
+    std::env::temp_dir().join("nocur-debug.json")
+}
+
+/// The NOCUR_* variables for a session; project and session are omitted when unknown
+pub fn nocur_env(project_path: Option<&str>, session_id: Option<&str>) -> Vec<(&'static str, String)> {
+    let mut env = vec![
+        (
+            NOCUR_PERMISSION_SOCKET,
+            crate::permissions::socket_path().to_string_lossy().to_string(),
+        ),
+        (
+            NOCUR_DEBUG_SNAPSHOT_PATH,
+            debug_snapshot_path().to_string_lossy().to_string(),
+        ),
+    ];
+    if let Some(path) = project_path {
+        env.push((NOCUR_PROJECT_PATH, path.to_string()));
+    }
+    if let Some(id) = session_id {
+        env.push((NOCUR_SESSION_ID, id.to_string()));
+    }
+    env
+}
+
+/// Overlay the NOCUR_* variables onto `env`. Ours replace any existing values, and
+/// inherited ones we have no current value for are dropped rather than passed on stale.
+pub fn merge_nocur_env(env: &mut HashMap<String, String>, project_path: Option<&str>, session_id: Option<&str>) {
+    for key in [NOCUR_PROJECT_PATH, NOCUR_SESSION_ID, NOCUR_PERMISSION_SOCKET, NOCUR_DEBUG_SNAPSHOT_PATH] {
+        env.remove(key);
+    }
+    for (key, value) in nocur_env(project_path, session_id) {
+        env.insert(key.to_string(), value);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn user_env(pairs: &[(&str, &str)]) -> HashMap<String, String> {
+        pairs.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect()
+    }
+
+    #[test]
+    fn injected_values_win_over_the_users() {
+        let mut env = user_env(&[
+            (NOCUR_PROJECT_PATH, "/Users/dev/OldProject"),
+            (NOCUR_SESSION_ID, "stale-session"),
+            (NOCUR_PERMISSION_SOCKET, "/tmp/elsewhere.sock"),
+            ("PATH", "/usr/bin:/bin"),
+        ]);
+        merge_nocur_env(&mut env, Some("/Users/dev/App"), Some("session-1"));
+
+        assert_eq!(env[NOCUR_PROJECT_PATH], "/Users/dev/App");
+        assert_eq!(env[NOCUR_SESSION_ID], "session-1");
+        assert_eq!(env[NOCUR_PERMISSION_SOCKET], crate::permissions::socket_path().to_string_lossy());
+        assert_eq!(env[NOCUR_DEBUG_SNAPSHOT_PATH], debug_snapshot_path().to_string_lossy());
+        assert_eq!(env["PATH"], "/usr/bin:/bin");
+    }
+
+    #[test]
+    fn stale_values_without_a_current_one_are_dropped() {
+        let mut env = user_env(&[(NOCUR_PROJECT_PATH, "/Users/dev/OldProject"), (NOCUR_SESSION_ID, "stale-session")]);
+        merge_nocur_env(&mut env, None, None);
+
+        assert!(!env.contains_key(NOCUR_PROJECT_PATH));
+        assert!(!env.contains_key(NOCUR_SESSION_ID));
+        assert!(env.contains_key(NOCUR_PERMISSION_SOCKET));
+    }
+}
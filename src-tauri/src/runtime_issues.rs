@@ -0,0 +1,293 @@
+//! Spotting SwiftUI and UIKit runtime issues in the log stream
+//!
+//! Each line the simulator and device log readers see is matched against a
+//! list of known runtime-issue messages ("Modifying state during view
+//! update", unsatisfiable constraints, ...). A match turns the entry's level
+//! into `runtime_issue` with the rule's ID, is counted per rule for
+//! `get_runtime_issue_summary`, and goes out as `runtime-issue-detected` with
+//! what to do about it.
+//!
+//! The built-in rules are runtime_issue_rules.json. A `runtime_issue_rules.json`
+//! in ~/.nocur adds rules in the same shape; one with a built-in's ID replaces
+//! it, and `"enabled": false` turns it off. The rules are reread when a log
+//! stream starts.
+
+// The log streams that feed it only exist on macOS
+#![cfg_attr(not(target_os = "macos"), allow(dead_code))]
+
+use parking_lot::{Mutex, RwLock};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::PathBuf;
+use tauri::{AppHandle, Manager};
+
+use crate::SimulatorLogEntry;
+
+pub const LEVEL: &str = "runtime_issue";
+const BUILTIN_RULES: &str = include_str!("runtime_issue_rules.json");
+/// Repeats of one rule inside this window are counted but not emitted again
+const EMIT_INTERVAL_MS: u64 = 2_000;
+/// Occurrence timestamps kept per rule for `since` queries
+const MAX_OCCURRENCES: usize = 1_000;
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct RuleSpec {
+    id: String,
+    #[serde(default)]
+    title: String,
+    #[serde(default)]
+    pattern: String,
+    #[serde(default)]
+    remediation: String,
+    #[serde(default = "enabled_by_default")]
+    enabled: bool,
+}
+
+fn enabled_by_default() -> bool {
+    true
+}
+
+struct Rule {
+    id: String,
+    title: String,
+    remediation: String,
+    regex: Regex,
+}
+
+#[derive(Default)]
+struct Tally {
+    occurrences: Vec<u64>,
+    total: u64,
+    last_message: String,
+    last_emitted: u64,
+}
+
+/// `runtime-issue-detected`
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RuntimeIssueDetected {
+    pub rule_id: String,
+    pub title: String,
+    pub message: String,
+    pub process: String,
+    pub remediation: String,
+    pub timestamp: u64,
+    /// Times the rule has matched since nocur started, including this one
+    pub count: u64,
+}
+
+/// One rule's matches in `get_runtime_issue_summary`
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RuntimeIssueCount {
+    pub rule_id: String,
+    pub title: String,
+    pub remediation: String,
+    pub count: u64,
+    pub first_seen: u64,
+    pub last_seen: u64,
+    pub last_message: String,
+}
+
+/// The compiled rules and how often each has matched
+pub struct RuntimeIssues {
+    rules: RwLock<Vec<Rule>>,
+    tallies: Mutex<HashMap<String, Tally>>,
+}
+
+impl Default for RuntimeIssues {
+    fn default() -> Self {
+        Self { rules: RwLock::new(load_rules()), tallies: Mutex::new(HashMap::new()) }
+    }
+}
+
+impl RuntimeIssues {
+    /// Reread the built-in and user rules
+    pub fn reload(&self) {
+        *self.rules.write() = load_rules();
+    }
+
+    /// Matches per rule at or after `since` (ms since the epoch), most frequent first
+    pub fn summary(&self, since: Option<u64>) -> Vec<RuntimeIssueCount> {
+        let since = since.unwrap_or(0);
+        let rules = self.rules.read();
+        let tallies = self.tallies.lock();
+        let mut counts: Vec<RuntimeIssueCount> = tallies
+            .iter()
+            .filter_map(|(rule_id, tally)| {
+                let recent: Vec<u64> = tally.occurrences.iter().copied().filter(|t| *t >= since).collect();
+                let (first_seen, last_seen) = (*recent.first()?, *recent.last()?);
+                // Older matches fell out of `occurrences`; without a cutoff the total still counts them
+                let count = if since == 0 { tally.total } else { recent.len() as u64 };
+                let rule = rules.iter().find(|r| &r.id == rule_id);
+                Some(RuntimeIssueCount {
+                    rule_id: rule_id.clone(),
+                    title: rule.map(|r| r.title.clone()).unwrap_or_default(),
+                    remediation: rule.map(|r| r.remediation.clone()).unwrap_or_default(),
+                    count,
+                    first_seen,
+                    last_seen,
+                    last_message: tally.last_message.clone(),
+                })
+            })
+            .collect();
+        counts.sort_by(|a, b| b.count.cmp(&a.count).then_with(|| a.rule_id.cmp(&b.rule_id)));
+        counts
+    }
+
+    /// Record a match; returns the event to emit unless this rule was emitted recently
+    fn record(&self, rule: &Rule, entry: &SimulatorLogEntry) -> Option<RuntimeIssueDetected> {
+        let mut tallies = self.tallies.lock();
+        let tally = tallies.entry(rule.id.clone()).or_default();
+        tally.total += 1;
+        tally.occurrences.push(entry.timestamp);
+        if tally.occurrences.len() > MAX_OCCURRENCES {
+            tally.occurrences.remove(0);
+        }
+        tally.last_message = entry.message.clone();
+        if tally.last_emitted != 0 && entry.timestamp.saturating_sub(tally.last_emitted) < EMIT_INTERVAL_MS {
+            return None;
+        }
+        tally.last_emitted = entry.timestamp;
+        Some(RuntimeIssueDetected {
+            rule_id: rule.id.clone(),
+            title: rule.title.clone(),
+            message: entry.message.clone(),
+            process: entry.process.clone(),
+            remediation: rule.remediation.clone(),
+            timestamp: entry.timestamp,
+            count: tally.total,
+        })
+    }
+}
+
+fn user_rules_path() -> PathBuf {
+    crate::paths::nocur_dir().join("runtime_issue_rules.json")
+}
+
+fn parse_specs(json: &str, source: &str) -> Vec<RuleSpec> {
+    serde_json::from_str(json).unwrap_or_else(|e| {
+        log::warn!("Ignoring runtime issue rules in {}: {}", source, e);
+        Vec::new()
+    })
+}
+
+/// Built-in rules, overlaid with the user's by ID, compiled
+fn load_rules() -> Vec<Rule> {
+    let mut specs = parse_specs(BUILTIN_RULES, "runtime_issue_rules.json");
+    let path = user_rules_path();
+    if let Ok(json) = std::fs::read_to_string(&path) {
+        for spec in parse_specs(&json, &path.display().to_string()) {
+            match specs.iter_mut().find(|s| s.id == spec.id) {
+                Some(existing) => *existing = spec,
+                None => specs.push(spec),
+            }
+        }
+    }
+    specs
+        .into_iter()
+        .filter(|spec| spec.enabled && !spec.pattern.is_empty())
+        .filter_map(|spec| match Regex::new(&spec.pattern) {
+            Ok(regex) => Some(Rule {
+                title: if spec.title.is_empty() { spec.id.clone() } else { spec.title },
+                id: spec.id,
+                remediation: spec.remediation,
+                regex,
+            }),
+            Err(e) => {
+                log::warn!("Ignoring runtime issue rule {}: {}", spec.id, e);
+                None
+            }
+        })
+        .collect()
+}
+
+/// Mark `entry` as a runtime issue if a rule matches it, count it, and emit
+/// `runtime-issue-detected`
+pub fn annotate(app_handle: &AppHandle, entry: &mut SimulatorLogEntry) {
+    let issues = app_handle.state::<RuntimeIssues>();
+    let detected = {
+        let rules = issues.rules.read();
+        let Some(rule) = rules.iter().find(|r| r.regex.is_match(&entry.message)) else {
+            return;
+        };
+        entry.level = LEVEL.to_string();
+        entry.rule_id = Some(rule.id.clone());
+        issues.record(rule, entry)
+    };
+    if let Some(detected) = detected {
+        crate::event_journal::emit_stamped(app_handle, "runtime-issue-detected", detected);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A line each built-in rule should match, and one close to it that it shouldn't
+    const SAMPLES: &[(&str, &str, &str)] = &[
+        (
+            "swiftui.state_modified_during_update",
+            "Modifying state during view update, this will cause undefined behavior.",
+            "Modifying state during view setup",
+        ),
+        (
+            "swiftui.publishing_from_background_thread",
+            "Publishing changes from background threads is not allowed; make sure to publish values from the main thread (via operators like receive(on:)) on model updates.",
+            "Publishing changes from the main thread",
+        ),
+        (
+            "swiftui.publishing_during_view_update",
+            "Publishing changes from within view updates is not allowed, this will cause undefined behavior.",
+            "Publishing changes from within view updates is allowed",
+        ),
+        (
+            "swiftui.onchange_multiple_updates_per_frame",
+            "onChange(of: CGFloat) action tried to update multiple times per frame.",
+            "onChange(of:) action tried to update multiple times per frame.",
+        ),
+        (
+            "swiftui.state_accessed_outside_view",
+            "Accessing State's value outside of being installed on a View. This will result in a constant Binding of the initial value and will not update.",
+            "Accessing State's value inside a View",
+        ),
+        (
+            "swiftui.invalid_frame_dimension",
+            "Invalid frame dimension (negative or non-finite).",
+            "Invalid frame dimension",
+        ),
+        (
+            "uikit.unsatisfiable_constraints",
+            "[LayoutConstraints] Unable to simultaneously satisfy constraints.",
+            "Able to simultaneously satisfy constraints",
+        ),
+        (
+            "main_thread_checker.ui_off_main",
+            "Main Thread Checker: UI API called on a background thread: -[UIView setNeedsLayout]",
+            "Main Thread Checker: UI API called on the main thread",
+        ),
+        (
+            "swiftui.preview_crashed",
+            "XCPreviewAgent crashed due to an uncaught exception",
+            "PreviewShell launched",
+        ),
+    ];
+
+    #[test]
+    fn builtin_rules_match_their_samples() {
+        let specs = parse_specs(BUILTIN_RULES, "runtime_issue_rules.json");
+        assert!(!specs.is_empty(), "the built-in rules didn't parse");
+        for spec in &specs {
+            let regex = Regex::new(&spec.pattern).unwrap_or_else(|e| panic!("{}: {}", spec.id, e));
+            let (_, matching, near_miss) = SAMPLES
+                .iter()
+                .find(|(id, ..)| *id == spec.id)
+                .unwrap_or_else(|| panic!("no sample for {}", spec.id));
+            assert!(regex.is_match(matching), "{} didn't match {:?}", spec.id, matching);
+            assert!(!regex.is_match(near_miss), "{} matched {:?}", spec.id, near_miss);
+        }
+        assert_eq!(specs.len(), SAMPLES.len(), "a sample's rule is gone");
+    }
+}
@@ -0,0 +1,300 @@
+//! Simulator runtime discovery and download
+//!
+//! A fresh Xcode install can ship without any simulator runtimes, which leaves
+//! the device list empty. This lists what's installed next to what the installed
+//! Xcode can download, and drives `xcodebuild -downloadPlatform` with progress.
+
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::io::{BufReader, Read};
+use std::process::{Command, Stdio};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::RwLock;
+use tauri::AppHandle;
+
+use crate::errors::CommandError;
+
+/// Simulator runtimes are around 7 GB; leave headroom for unpacking
+const REQUIRED_FREE_BYTES: u64 = 8 * 1024 * 1024 * 1024;
+
+const PLATFORMS: &[&str] = &["iOS", "watchOS", "tvOS", "visionOS"];
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RuntimeInfo {
+    /// e.g. `com.apple.CoreSimulator.SimRuntime.iOS-18-0`; None for downloadable runtimes
+    pub identifier: Option<String>,
+    pub platform: String,
+    pub version: String,
+    pub build: Option<String>,
+    /// "installed", "unavailable" (installed but unusable with this Xcode) or "downloadable"
+    pub state: String,
+    pub size_bytes: Option<u64>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RuntimeDownloadProgress {
+    pub platform: String,
+    pub version: Option<String>,
+    pub percent: Option<f64>,
+    pub message: String,
+    pub done: bool,
+    /// The download didn't start because there's no connection
+    pub offline: bool,
+}
+
+/// The download in flight, if any
+pub struct RuntimeDownloadState {
+    is_downloading: AtomicBool,
+    cancelled: AtomicBool,
+    child_pid: RwLock<Option<u32>>,
+}
+
+impl RuntimeDownloadState {
+    pub fn new() -> Self {
+        Self {
+            is_downloading: AtomicBool::new(false),
+            cancelled: AtomicBool::new(false),
+            child_pid: RwLock::new(None),
+        }
+    }
+}
+
+fn run_json(program: &str, args: &[&str]) -> Option<serde_json::Value> {
+    let output = Command::new(program).args(args).output().ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    serde_json::from_slice(&output.stdout).ok()
+}
+
+/// Installed runtime sizes by runtime identifier, from `simctl runtime list`
+fn installed_runtime_sizes() -> HashMap<String, u64> {
+    let Some(json) = run_json("xcrun", &["simctl", "runtime", "list", "--json"]) else {
+        return HashMap::new();
+    };
+    json.as_object()
+        .map(|images| {
+            images
+                .values()
+                .filter_map(|image| {
+                    Some((
+                        image.get("runtimeIdentifier")?.as_str()?.to_string(),
+                        image.get("sizeBytes")?.as_u64()?,
+                    ))
+                })
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// Installed runtimes, plus one downloadable entry per simulator SDK in the
+/// active Xcode that has no matching runtime
+pub fn list_available_runtimes() -> Result<Vec<RuntimeInfo>, String> {
+    let json = run_json("xcrun", &["simctl", "list", "runtimes", "--json"])
+        .ok_or("Failed to list simulator runtimes. Is Xcode installed?")?;
+    let sizes = installed_runtime_sizes();
+
+    let mut runtimes: Vec<RuntimeInfo> = json
+        .get("runtimes")
+        .and_then(|r| r.as_array())
+        .map(|list| {
+            list.iter()
+                .filter_map(|r| {
+                    let identifier = r.get("identifier")?.as_str()?.to_string();
+                    let available = r.get("isAvailable").and_then(|a| a.as_bool()).unwrap_or(false);
+                    Some(RuntimeInfo {
+                        platform: r.get("platform").and_then(|p| p.as_str()).unwrap_or("iOS").to_string(),
+                        version: r.get("version")?.as_str()?.to_string(),
+                        build: r.get("buildversion").and_then(|b| b.as_str()).map(String::from),
+                        state: if available { "installed" } else { "unavailable" }.to_string(),
+                        size_bytes: sizes.get(&identifier).copied(),
+                        identifier: Some(identifier),
+                    })
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+
+    // `-showsdks` lists the simulator SDKs this Xcode builds against; each wants a
+    // runtime of the same version
+    let sdks = run_json("xcodebuild", &["-showsdks", "-json"]);
+    for sdk in sdks.as_ref().and_then(|s| s.as_array()).into_iter().flatten() {
+        let Some(platform) = sdk.get("platform").and_then(|p| p.as_str()).and_then(sdk_platform_name) else {
+            continue;
+        };
+        let Some(version) = sdk.get("sdkVersion").and_then(|v| v.as_str()) else {
+            continue;
+        };
+        let installed = runtimes
+            .iter()
+            .any(|r| r.platform == platform && r.state == "installed" && r.version.starts_with(version));
+        if !installed {
+            runtimes.push(RuntimeInfo {
+                identifier: None,
+                platform: platform.to_string(),
+                version: version.to_string(),
+                build: None,
+                state: "downloadable".to_string(),
+                size_bytes: None,
+            });
+        }
+    }
+
+    Ok(runtimes)
+}
+
+fn sdk_platform_name(sdk_platform: &str) -> Option<&'static str> {
+    match sdk_platform {
+        "iphonesimulator" => Some("iOS"),
+        "watchsimulator" => Some("watchOS"),
+        "appletvsimulator" => Some("tvOS"),
+        "xrsimulator" => Some("visionOS"),
+        _ => None,
+    }
+}
+
+/// Free bytes on the volume runtimes are installed to
+fn available_disk_bytes() -> Option<u64> {
+    let output = Command::new("df").args(["-k", "/Library/Developer"]).output().ok()?;
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let kilobytes: u64 = stdout.lines().nth(1)?.split_whitespace().nth(3)?.parse().ok()?;
+    Some(kilobytes * 1024)
+}
+
+/// Warning text if there probably isn't room for a runtime, None if there is (or we can't tell)
+pub fn disk_space_warning() -> Option<String> {
+    let free = available_disk_bytes()?;
+    if free >= REQUIRED_FREE_BYTES {
+        return None;
+    }
+    Some(format!(
+        "Only {:.1} GB free; simulator runtimes need about {} GB.",
+        free as f64 / (1024.0 * 1024.0 * 1024.0),
+        REQUIRED_FREE_BYTES / (1024 * 1024 * 1024)
+    ))
+}
+
+/// Download a simulator runtime, emitting `runtime-download-progress` until it
+/// finishes. Blocks for the duration of the download.
+pub fn download_runtime(
+    state: &RuntimeDownloadState,
+    platform: &str,
+    version: Option<&str>,
+    app_handle: &AppHandle,
+) -> Result<(), CommandError> {
+    if !PLATFORMS.contains(&platform) {
+        return Err(format!("Unknown platform '{}'. Expected one of: {}", platform, PLATFORMS.join(", ")).into());
+    }
+    if crate::network::is_offline() {
+        let message = "No network connection; reconnect and try the download again".to_string();
+        crate::event_journal::emit_stamped(app_handle, "runtime-download-progress", RuntimeDownloadProgress {
+            platform: platform.to_string(),
+            version: version.map(String::from),
+            percent: None,
+            message: message.clone(),
+            done: true,
+            offline: true,
+        });
+        return Err(CommandError::Offline(message));
+    }
+    if state.is_downloading.swap(true, Ordering::SeqCst) {
+        return Err("A runtime download is already in progress".into());
+    }
+    state.cancelled.store(false, Ordering::SeqCst);
+
+    let result = run_download(state, platform, version, app_handle);
+
+    state.is_downloading.store(false, Ordering::SeqCst);
+    *state.child_pid.write().unwrap_or_else(|e| e.into_inner()) = None;
+
+    let message = match &result {
+        Ok(()) => "Download complete".to_string(),
+        Err(e) => e.clone(),
+    };
+    crate::event_journal::emit_stamped(app_handle, "runtime-download-progress", RuntimeDownloadProgress {
+        platform: platform.to_string(),
+        version: version.map(String::from),
+        percent: result.is_ok().then_some(100.0),
+        message,
+        done: true,
+        offline: false,
+    });
+    result.map_err(CommandError::from)
+}
+
+fn run_download(
+    state: &RuntimeDownloadState,
+    platform: &str,
+    version: Option<&str>,
+    app_handle: &AppHandle,
+) -> Result<(), String> {
+    let mut cmd = Command::new("xcodebuild");
+    cmd.args(["-downloadPlatform", platform]);
+    // Without -buildVersion xcodebuild fetches the runtime matching this Xcode's SDK
+    if let Some(version) = version {
+        cmd.args(["-buildVersion", version]);
+    }
+    let mut child = cmd
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()
+        .map_err(|e| format!("Failed to start xcodebuild: {}", e))?;
+    *state.child_pid.write().unwrap_or_else(|e| e.into_inner()) = Some(child.id());
+
+    let percent_re = Regex::new(r"(\d{1,3}(?:\.\d+)?)%").map_err(|e| e.to_string())?;
+    let mut last_message = String::new();
+
+    if let Some(stdout) = child.stdout.take() {
+        // Progress is redrawn in place with \r, so split on both line endings
+        let mut reader = BufReader::new(stdout);
+        let mut line = Vec::new();
+        let mut byte = [0u8; 1];
+        while reader.read(&mut byte).map(|n| n > 0).unwrap_or(false) {
+            if byte[0] != b'\r' && byte[0] != b'\n' {
+                line.push(byte[0]);
+                continue;
+            }
+            let text = String::from_utf8_lossy(&line).trim().to_string();
+            line.clear();
+            if text.is_empty() {
+                continue;
+            }
+            let percent = percent_re
+                .captures(&text)
+                .and_then(|c| c[1].parse::<f64>().ok());
+            crate::event_journal::emit_stamped(app_handle, "runtime-download-progress", RuntimeDownloadProgress {
+                platform: platform.to_string(),
+                version: version.map(String::from),
+                percent,
+                message: text.clone(),
+                done: false,
+                offline: false,
+            });
+            last_message = text;
+        }
+    }
+
+    let status = child.wait().map_err(|e| format!("Failed to wait for xcodebuild: {}", e))?;
+    if state.cancelled.load(Ordering::SeqCst) {
+        return Err("Download cancelled".to_string());
+    }
+    if !status.success() {
+        return Err(format!("Runtime download failed: {}", last_message));
+    }
+    Ok(())
+}
+
+/// Stop the download in flight; returns false if nothing was downloading
+pub fn cancel_download(state: &RuntimeDownloadState) -> bool {
+    if !state.is_downloading.load(Ordering::SeqCst) {
+        return false;
+    }
+    state.cancelled.store(true, Ordering::SeqCst);
+    if let Some(pid) = *state.child_pid.read().unwrap_or_else(|e| e.into_inner()) {
+        let _ = Command::new("kill").args(["-TERM", &pid.to_string()]).output();
+    }
+    true
+}
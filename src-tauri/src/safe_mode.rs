@@ -0,0 +1,106 @@
+//! Safe mode: one switch for running the agent conservatively
+//!
+//! While the `safe_mode` preference is on, the command handlers (not just the
+//! UI) hold the agent back:
+//! - the permission server never auto-approves, and sessions start without
+//!   `--dangerously-skip-permissions` or an auto-approving permission mode
+//! - every turn gets a checkpoint first, and a turn whose checkpoint can't be
+//!   made isn't sent
+//! - commands that throw work away need a single-use token from
+//!   `request_destructive_confirmation`
+//!
+//! The flag is read from preferences at startup and kept here.
+
+use parking_lot::Mutex;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Manager};
+
+use crate::claude::ClaudeSessionConfig;
+
+/// How long a confirmation token can be used for
+const TOKEN_LIFETIME: Duration = Duration::from_secs(60);
+
+/// Permission modes that approve tool calls without asking
+const AUTO_APPROVING_MODES: &[&str] = &["bypassPermissions", "acceptEdits"];
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DestructiveConfirmation {
+    pub token: String,
+    pub expires_in_secs: u64,
+}
+
+#[derive(Default)]
+pub struct SafeMode {
+    enabled: AtomicBool,
+    /// Unused tokens and when they were issued
+    tokens: Mutex<HashMap<String, Instant>>,
+}
+
+impl SafeMode {
+    pub fn new(enabled: bool) -> Self {
+        Self { enabled: AtomicBool::new(enabled), ..Default::default() }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.enabled.load(Ordering::SeqCst)
+    }
+
+    pub fn set_enabled(&self, enabled: bool) {
+        self.enabled.store(enabled, Ordering::SeqCst);
+        if !enabled {
+            self.tokens.lock().clear();
+        }
+    }
+
+    pub fn issue_token(&self) -> DestructiveConfirmation {
+        let token = uuid::Uuid::new_v4().to_string();
+        let mut tokens = self.tokens.lock();
+        tokens.retain(|_, issued| issued.elapsed() < TOKEN_LIFETIME);
+        tokens.insert(token.clone(), Instant::now());
+        DestructiveConfirmation { token, expires_in_secs: TOKEN_LIFETIME.as_secs() }
+    }
+
+    /// Allow `action` when safe mode is off, or when `confirm_token` is a live
+    /// token, which is used up
+    pub fn authorize(&self, action: &str, confirm_token: Option<&str>) -> Result<(), String> {
+        if !self.is_enabled() {
+            return Ok(());
+        }
+        let issued = confirm_token.and_then(|token| self.tokens.lock().remove(token));
+        match issued {
+            Some(issued) if issued.elapsed() < TOKEN_LIFETIME => Ok(()),
+            Some(_) => Err(format!("The confirmation token for {} has expired; request a new one", action)),
+            None => Err(format!(
+                "Safe mode is on: {} needs a confirmation token from request_destructive_confirmation",
+                action
+            )),
+        }
+    }
+}
+
+pub fn is_enabled(app_handle: &AppHandle) -> bool {
+    app_handle.try_state::<SafeMode>().is_some_and(|s| s.is_enabled())
+}
+
+/// Take the permission bypasses out of `config` when safe mode is on
+pub fn restrict_session(app_handle: &AppHandle, config: &mut ClaudeSessionConfig) {
+    if !is_enabled(app_handle) {
+        return;
+    }
+    if config.skip_permissions {
+        log::info!("Safe mode: starting the session with permission prompts");
+    }
+    config.skip_permissions = false;
+    if config
+        .profile
+        .permission_mode
+        .as_deref()
+        .is_some_and(|mode| AUTO_APPROVING_MODES.contains(&mode))
+    {
+        config.profile.permission_mode = None;
+    }
+}
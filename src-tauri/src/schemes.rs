@@ -0,0 +1,275 @@
+//! Finding the scheme to build when the guess is wrong
+//!
+//! Without an explicit scheme, a build guesses the project or workspace file
+//! name. In a CocoaPods-style workspace the app's scheme lives in a nested
+//! project and is usually named something else, so the guess fails with
+//! "does not contain a scheme named". The build then lists the workspace's
+//! schemes, and if exactly one of them is shared (checked in under
+//! `xcshareddata`, and not a pod's) retries with it. The scheme that worked is
+//! kept per project in `~/.nocur/schemes.json` so later builds start with it.
+//!
+//! The same listing gives the build configurations a requested one is checked
+//! against, and `scheme_infos` for the scheme picker. Those are cached until
+//! the project file changes.
+
+use parking_lot::Mutex;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::OnceLock;
+use std::time::SystemTime;
+
+const SCHEME_NOT_FOUND: &str = "does not contain a scheme named";
+
+/// The xcodebuild line saying the scheme doesn't exist, if it's in `output`
+pub fn scheme_not_found(output: &str) -> Option<String> {
+    output
+        .lines()
+        .find(|line| line.contains(SCHEME_NOT_FOUND))
+        .map(|line| line.trim().trim_start_matches("xcodebuild: ").trim_start_matches("error: ").to_string())
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct Container {
+    #[serde(default)]
+    schemes: Vec<String>,
+    /// Only reported for a project; a workspace's come from its projects
+    #[serde(default)]
+    configurations: Vec<String>,
+}
+
+/// `xcodebuild -list -json` reports under "workspace" or "project"
+#[derive(Debug, Deserialize)]
+struct ListOutput {
+    workspace: Option<Container>,
+    project: Option<Container>,
+}
+
+fn list(project_file: &Path, is_workspace: bool) -> Result<Container, String> {
+    let flag = if is_workspace { "-workspace" } else { "-project" };
+    let output = Command::new("xcodebuild")
+        .args(["-list", "-json", flag])
+        .arg(project_file)
+        .output()
+        .map_err(|e| format!("Failed to run xcodebuild -list: {}", e))?;
+    if !output.status.success() {
+        return Err(format!("xcodebuild -list failed: {}", String::from_utf8_lossy(&output.stderr).trim()));
+    }
+
+    // xcodebuild sometimes prints warnings before the JSON
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let json = stdout.find('{').map_or(stdout.as_ref(), |start| &stdout[start..]);
+    let list: ListOutput =
+        serde_json::from_str(json).map_err(|e| format!("Failed to parse xcodebuild -list output: {}", e))?;
+    Ok(list.workspace.or(list.project).unwrap_or_default())
+}
+
+/// Every scheme xcodebuild can see in the workspace or project
+pub fn list_schemes(project_file: &Path, is_workspace: bool) -> Result<Vec<String>, String> {
+    list(project_file, is_workspace).map(|container| container.schemes)
+}
+
+/// The build configurations of the project, or of a workspace's (non-pod) projects
+pub fn list_configurations(project_file: &Path, is_workspace: bool) -> Result<Vec<String>, String> {
+    if !is_workspace {
+        return list(project_file, false).map(|container| container.configurations);
+    }
+    let mut configurations = Vec::new();
+    for project in workspace_projects(project_file).into_iter().filter(|p| !is_pods_project(p)) {
+        for configuration in list(&project, false)?.configurations {
+            if !configurations.contains(&configuration) {
+                configurations.push(configuration);
+            }
+        }
+    }
+    Ok(configurations)
+}
+
+/// The projects a workspace references, from its contents.xcworkspacedata
+fn workspace_projects(workspace: &Path) -> Vec<PathBuf> {
+    let Some(base) = workspace.parent() else {
+        return Vec::new();
+    };
+    let Ok(contents) = std::fs::read_to_string(workspace.join("contents.xcworkspacedata")) else {
+        return Vec::new();
+    };
+    let Ok(location_re) = Regex::new(r#"location\s*=\s*"(group|container|absolute):([^"]+\.xcodeproj)""#) else {
+        return Vec::new();
+    };
+    location_re
+        .captures_iter(&contents)
+        .map(|c| match &c[1] {
+            "absolute" => PathBuf::from(&c[2]),
+            _ => base.join(&c[2]),
+        })
+        .collect()
+}
+
+fn is_pods_project(project: &Path) -> bool {
+    project.file_stem().is_some_and(|stem| stem == "Pods")
+}
+
+/// Of `schemes`, those checked in as shared by the workspace or one of its
+/// (non-pod) projects
+pub fn shared_schemes(project_file: &Path, is_workspace: bool, schemes: &[String]) -> Vec<String> {
+    let mut containers = vec![project_file.to_path_buf()];
+    if is_workspace {
+        containers.extend(workspace_projects(project_file).into_iter().filter(|p| !is_pods_project(p)));
+    }
+    schemes
+        .iter()
+        .filter(|scheme| {
+            containers.iter().any(|container| {
+                container
+                    .join("xcshareddata/xcschemes")
+                    .join(format!("{}.xcscheme", scheme))
+                    .is_file()
+            })
+        })
+        .cloned()
+        .collect()
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SchemeInfo {
+    pub name: String,
+    /// Checked in under `xcshareddata`, so every checkout has it
+    pub shared: bool,
+    /// Targets the scheme builds, from its .xcscheme; empty when there's no file
+    pub targets: Vec<String>,
+    /// The configuration the scheme runs with, e.g. "Debug"
+    pub launch_configuration: Option<String>,
+    /// The project's configurations, which any of its schemes can build
+    pub configurations: Vec<String>,
+}
+
+/// The scheme's .xcscheme, shared or from a user's own schemes
+fn scheme_file(containers: &[PathBuf], scheme: &str) -> Option<(PathBuf, bool)> {
+    let file_name = format!("{}.xcscheme", scheme);
+    for container in containers {
+        let shared = container.join("xcshareddata/xcschemes").join(&file_name);
+        if shared.is_file() {
+            return Some((shared, true));
+        }
+    }
+    containers.iter().find_map(|container| {
+        std::fs::read_dir(container.join("xcuserdata"))
+            .ok()?
+            .filter_map(|e| e.ok())
+            .map(|e| e.path().join("xcschemes").join(&file_name))
+            .find(|path| path.is_file())
+            .map(|path| (path, false))
+    })
+}
+
+/// `(targets built, launch configuration)` from an .xcscheme
+fn parse_scheme_file(content: &str) -> (Vec<String>, Option<String>) {
+    let build_action = content
+        .find("<BuildAction")
+        .map(|start| &content[start..])
+        .map(|rest| rest.find("</BuildAction>").map_or(rest, |end| &rest[..end]))
+        .unwrap_or("");
+    let mut targets = Vec::new();
+    if let Ok(name_re) = Regex::new(r#"BlueprintName\s*=\s*"([^"]+)""#) {
+        for captures in name_re.captures_iter(build_action) {
+            if !targets.contains(&captures[1].to_string()) {
+                targets.push(captures[1].to_string());
+            }
+        }
+    }
+    let launch_configuration = Regex::new(r#"<LaunchAction[^>]*?buildConfiguration\s*=\s*"([^"]+)""#)
+        .ok()
+        .and_then(|re| re.captures(content).map(|c| c[1].to_string()));
+    (targets, launch_configuration)
+}
+
+/// When the project last changed: the container or the file inside it that
+/// Xcode rewrites
+pub fn project_modified(project_file: &Path) -> Option<SystemTime> {
+    [project_file.to_path_buf(), project_file.join("project.pbxproj"), project_file.join("contents.xcworkspacedata")]
+        .iter()
+        .filter_map(|path| std::fs::metadata(path).and_then(|m| m.modified()).ok())
+        .max()
+}
+
+type SchemeCache = HashMap<PathBuf, (SystemTime, Vec<SchemeInfo>)>;
+
+fn scheme_cache() -> &'static Mutex<SchemeCache> {
+    static CACHE: OnceLock<Mutex<SchemeCache>> = OnceLock::new();
+    CACHE.get_or_init(Default::default)
+}
+
+/// The schemes of the workspace or project with their targets and configurations
+pub fn scheme_infos(project_file: &Path, is_workspace: bool) -> Result<Vec<SchemeInfo>, String> {
+    let modified = project_modified(project_file);
+    if let Some((_, cached)) = scheme_cache()
+        .lock()
+        .get(project_file)
+        .filter(|(at, _)| Some(*at) == modified)
+    {
+        return Ok(cached.clone());
+    }
+
+    let schemes = list_schemes(project_file, is_workspace)?;
+    let configurations = list_configurations(project_file, is_workspace).unwrap_or_else(|e| {
+        log::warn!("Couldn't list build configurations for {}: {}", project_file.display(), e);
+        Vec::new()
+    });
+    let mut containers = vec![project_file.to_path_buf()];
+    if is_workspace {
+        containers.extend(workspace_projects(project_file).into_iter().filter(|p| !is_pods_project(p)));
+    }
+    let infos: Vec<SchemeInfo> = schemes
+        .into_iter()
+        .map(|name| {
+            let file = scheme_file(&containers, &name);
+            let (targets, launch_configuration) = file
+                .as_ref()
+                .and_then(|(path, _)| std::fs::read_to_string(path).ok())
+                .map_or((Vec::new(), None), |content| parse_scheme_file(&content));
+            SchemeInfo {
+                shared: file.is_some_and(|(_, shared)| shared),
+                name,
+                targets,
+                launch_configuration,
+                configurations: configurations.clone(),
+            }
+        })
+        .collect();
+
+    if let Some(modified) = modified {
+        scheme_cache().lock().insert(project_file.to_path_buf(), (modified, infos.clone()));
+    }
+    Ok(infos)
+}
+
+fn saved_path() -> PathBuf {
+    crate::paths::nocur_dir().join("schemes.json")
+}
+
+fn read_saved() -> BTreeMap<String, String> {
+    std::fs::read_to_string(saved_path())
+        .ok()
+        .and_then(|content| serde_json::from_str(&content).ok())
+        .unwrap_or_default()
+}
+
+/// The scheme a previous build of the project settled on
+pub fn saved_scheme(project_path: &str) -> Option<String> {
+    read_saved().remove(&crate::ace::generate_project_id(project_path))
+}
+
+pub fn save_scheme(project_path: &str, scheme: &str) -> Result<(), String> {
+    let mut saved = read_saved();
+    saved.insert(crate::ace::generate_project_id(project_path), scheme.to_string());
+
+    let path = saved_path();
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent).map_err(|e| format!("Failed to create {}: {}", parent.display(), e))?;
+    }
+    let content = serde_json::to_string_pretty(&saved).map_err(|e| format!("Failed to serialize schemes: {}", e))?;
+    std::fs::write(&path, content).map_err(|e| format!("Failed to write {}: {}", path.display(), e))
+}
@@ -0,0 +1,199 @@
+//! Sizing and encoding captured screenshots for who's going to look at them
+//!
+//! The UI wants full-resolution PNGs; an image attached to a Claude prompt
+//! costs tokens and latency by the pixel, so the agent gets a downscaled JPEG.
+//! `ScreenshotOptions` says how to re-encode a capture, either directly or by
+//! naming a preset. The `display` and `agent` presets have built-in defaults
+//! that the `screenshot_presets` preference can replace.
+
+use image::codecs::jpeg::JpegEncoder;
+use image::imageops::FilterType;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+pub const DISPLAY_PRESET: &str = "display";
+pub const AGENT_PRESET: &str = "agent";
+
+const DEFAULT_JPEG_QUALITY: u8 = 80;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ImageFormat {
+    #[default]
+    Png,
+    Jpeg,
+}
+
+impl ImageFormat {
+    fn extension(self) -> &'static str {
+        match self {
+            ImageFormat::Png => "png",
+            ImageFormat::Jpeg => "jpg",
+        }
+    }
+
+    fn mime_type(self) -> &'static str {
+        match self {
+            ImageFormat::Png => "image/png",
+            ImageFormat::Jpeg => "image/jpeg",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct ScreenshotOptions {
+    /// Preset to start from; the fields set here override it
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub preset: Option<String>,
+    /// Longest side in pixels, applied after `scale`; never upscales
+    pub max_dimension: Option<u32>,
+    /// PNG unless set
+    pub format: Option<ImageFormat>,
+    /// JPEG quality, 1-100
+    pub quality: Option<u8>,
+    /// Factor applied to the captured size, e.g. 0.5 for half
+    pub scale: Option<f32>,
+}
+
+impl ScreenshotOptions {
+    /// Fields set in `self` win over `base`
+    fn over(self, base: ScreenshotOptions) -> ScreenshotOptions {
+        ScreenshotOptions {
+            preset: self.preset.or(base.preset),
+            max_dimension: self.max_dimension.or(base.max_dimension),
+            format: self.format.or(base.format),
+            quality: self.quality.or(base.quality),
+            scale: self.scale.or(base.scale),
+        }
+    }
+}
+
+/// What was written, so callers on a budget can check it
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EncodedScreenshot {
+    pub path: String,
+    pub width: u32,
+    pub height: u32,
+    pub bytes: u64,
+    pub format: ImageFormat,
+}
+
+impl EncodedScreenshot {
+    pub fn mime_type(&self) -> &'static str {
+        self.format.mime_type()
+    }
+}
+
+fn builtin_preset(name: &str) -> Option<ScreenshotOptions> {
+    match name {
+        DISPLAY_PRESET => Some(ScreenshotOptions::default()),
+        AGENT_PRESET => Some(ScreenshotOptions {
+            max_dimension: Some(1024),
+            format: Some(ImageFormat::Jpeg),
+            quality: Some(60),
+            ..Default::default()
+        }),
+        _ => None,
+    }
+}
+
+/// `options` filled in from its preset, or from `default_preset` when it names none
+pub fn resolve(
+    options: Option<ScreenshotOptions>,
+    default_preset: &str,
+    saved: &BTreeMap<String, ScreenshotOptions>,
+) -> Result<ScreenshotOptions, String> {
+    let options = options.unwrap_or_default();
+    let name = options.preset.clone().unwrap_or_else(|| default_preset.to_string());
+    let preset = saved
+        .get(&name)
+        .cloned()
+        .or_else(|| builtin_preset(&name))
+        .ok_or_else(|| format!("Unknown screenshot preset '{}'", name))?;
+    let resolved = options.over(ScreenshotOptions { preset: Some(name), ..preset });
+
+    if resolved.quality.is_some_and(|q| q == 0 || q > 100) {
+        return Err("Screenshot quality must be between 1 and 100".to_string());
+    }
+    if resolved.scale.is_some_and(|s| s.is_nan() || s <= 0.0 || s > 1.0) {
+        return Err("Screenshot scale must be greater than 0 and at most 1".to_string());
+    }
+    if resolved.max_dimension == Some(0) {
+        return Err("maxDimension must be at least 1".to_string());
+    }
+    Ok(resolved)
+}
+
+/// `resolve` with the presets from the user preferences
+pub fn resolve_with_preferences(options: Option<ScreenshotOptions>, default_preset: &str) -> Result<ScreenshotOptions, String> {
+    let saved = crate::read_user_preferences().map(|p| p.screenshot_presets).unwrap_or_default();
+    resolve(options, default_preset, &saved)
+}
+
+/// Size after `scale` and `max_dimension`, at least 1x1
+fn target_size(width: u32, height: u32, options: &ScreenshotOptions) -> (u32, u32) {
+    let mut factor = options.scale.unwrap_or(1.0) as f64;
+    if let Some(max) = options.max_dimension {
+        let longest = width.max(height) as f64 * factor;
+        if longest > max as f64 {
+            factor *= max as f64 / longest;
+        }
+    }
+    let scaled = |side: u32| ((side as f64 * factor).round() as u32).max(1);
+    (scaled(width), scaled(height))
+}
+
+fn output_path(source: &Path, options: &ScreenshotOptions, format: ImageFormat) -> PathBuf {
+    let stem = source.file_stem().and_then(|s| s.to_str()).unwrap_or("screenshot");
+    let label = options.preset.as_deref().map_or_else(|| "custom".to_string(), crate::paths::sanitize_path_component);
+    source.with_file_name(format!("{}-{}.{}", stem, label, format.extension()))
+}
+
+/// Re-encode the capture at `source` per `options`. A capture that's already
+/// in the wanted format and size is returned as it is, since re-encoding a
+/// JPEG at the same size only loses detail.
+pub fn encode(source: &Path, options: &ScreenshotOptions) -> Result<EncodedScreenshot, String> {
+    let image = image::open(source).map_err(|e| format!("Failed to read screenshot {}: {}", source.display(), e))?;
+    let format = options.format.unwrap_or_default();
+    let (width, height) = target_size(image.width(), image.height(), options);
+
+    let source_format = match source.extension().and_then(|e| e.to_str()).map(str::to_lowercase).as_deref() {
+        Some("png") => Some(ImageFormat::Png),
+        Some("jpg" | "jpeg") => Some(ImageFormat::Jpeg),
+        _ => None,
+    };
+    let unchanged = (width, height) == (image.width(), image.height());
+    let path = if unchanged && source_format == Some(format) {
+        source.to_path_buf()
+    } else {
+        let image = if unchanged { image } else { image.resize_exact(width, height, FilterType::Triangle) };
+        let path = output_path(source, options, format);
+        match format {
+            ImageFormat::Png => image
+                .save_with_format(&path, image::ImageFormat::Png)
+                .map_err(|e| format!("Failed to write {}: {}", path.display(), e))?,
+            ImageFormat::Jpeg => {
+                let file = std::fs::File::create(&path).map_err(|e| format!("Failed to create {}: {}", path.display(), e))?;
+                let encoder = JpegEncoder::new_with_quality(std::io::BufWriter::new(file), options.quality.unwrap_or(DEFAULT_JPEG_QUALITY));
+                // JPEG has no alpha channel
+                image
+                    .to_rgb8()
+                    .write_with_encoder(encoder)
+                    .map_err(|e| format!("Failed to encode {}: {}", path.display(), e))?;
+            }
+        }
+        path
+    };
+
+    let bytes = std::fs::metadata(&path).map_err(|e| format!("Failed to read {}: {}", path.display(), e))?.len();
+    Ok(EncodedScreenshot {
+        path: path.to_string_lossy().to_string(),
+        width,
+        height,
+        bytes,
+        format,
+    })
+}
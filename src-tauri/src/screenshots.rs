@@ -0,0 +1,204 @@
+//! Per-project screenshot history
+//!
+//! Every capture worth keeping (a manual `take_screenshot` with a project, the
+//! agent's screenshots, `verify_change`) is copied to
+//! `~/.nocur/screenshots/<project-id>/<id>.<ext>` next to a `<id>.json`
+//! sidecar saying when, where and why it was taken. Adding one prunes the
+//! project's oldest captures beyond the configured limit.
+
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+use tauri::{AppHandle, Manager};
+
+/// Captures kept per project when the preference isn't set
+pub const DEFAULT_MAX_SCREENSHOTS: usize = 200;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum CaptureTrigger {
+    Manual,
+    Agent,
+    Verify,
+}
+
+/// Where a capture came from, as recorded in its sidecar
+#[derive(Debug, Clone)]
+pub struct CaptureContext {
+    pub session_id: Option<String>,
+    pub device: Option<String>,
+    pub trigger: CaptureTrigger,
+    pub related_build_id: Option<String>,
+}
+
+impl CaptureContext {
+    /// The selected device and the current build, as the app state has them
+    pub fn current(app_handle: &AppHandle, trigger: CaptureTrigger, session_id: Option<String>) -> Self {
+        let device = app_handle
+            .state::<Mutex<crate::AppState>>()
+            .lock()
+            .selected_device
+            .as_ref()
+            .map(|d| d.name.clone());
+        // Build IDs start at 1, so 0 means nothing has been built yet
+        let related_build_id = app_handle
+            .try_state::<crate::event_journal::EventJournal>()
+            .map(|journal| journal.current_build())
+            .filter(|id| id != "0");
+        Self { session_id, device, trigger, related_build_id }
+    }
+
+    /// `current` for the active Claude session, if there is one
+    pub fn for_active_session(app_handle: &AppHandle, trigger: CaptureTrigger) -> Self {
+        let session_id = app_handle.state::<Mutex<crate::claude::ClaudeState>>().lock().get_current_session_id();
+        Self::current(app_handle, trigger, session_id)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ScreenshotRecord {
+    pub id: String,
+    /// Image file name within the project's store
+    pub file_name: String,
+    /// Milliseconds since the epoch
+    pub timestamp: u64,
+    pub session_id: Option<String>,
+    pub device: Option<String>,
+    pub trigger: CaptureTrigger,
+    pub related_build_id: Option<String>,
+    /// Full path of the stored image, resolved against the store when read
+    #[serde(default, skip_deserializing)]
+    pub path: String,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ListOptions {
+    pub session_id: Option<String>,
+    /// Only captures at or after this many milliseconds since the epoch
+    pub since: Option<u64>,
+    /// Newest first, at most this many
+    pub limit: Option<usize>,
+}
+
+fn root_dir() -> PathBuf {
+    crate::paths::nocur_dir().join("screenshots")
+}
+
+fn store_dir(project_path: &str) -> PathBuf {
+    root_dir().join(crate::ace::generate_project_id(project_path))
+}
+
+/// IDs are generated UUIDs; anything else could name a path outside the store
+fn is_valid_id(id: &str) -> bool {
+    !id.is_empty() && id.chars().all(|c| c.is_ascii_alphanumeric() || c == '-')
+}
+
+/// Copy `source` into the project's store and record it; returns the stored capture
+pub fn register(project_path: &str, source: &Path, context: CaptureContext) -> Result<ScreenshotRecord, String> {
+    let dir = store_dir(project_path);
+    fs::create_dir_all(&dir).map_err(|e| format!("Failed to create {}: {}", dir.display(), e))?;
+
+    let id = uuid::Uuid::new_v4().to_string();
+    let extension = source.extension().and_then(|e| e.to_str()).unwrap_or("png");
+    let file_name = format!("{}.{}", id, extension);
+    let path = dir.join(&file_name);
+    fs::copy(source, &path).map_err(|e| format!("Failed to store screenshot {}: {}", source.display(), e))?;
+
+    let record = ScreenshotRecord {
+        id: id.clone(),
+        file_name,
+        timestamp: SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_millis() as u64,
+        session_id: context.session_id,
+        device: context.device,
+        trigger: context.trigger,
+        related_build_id: context.related_build_id,
+        path: path.to_string_lossy().to_string(),
+    };
+    let sidecar = serde_json::to_string_pretty(&record)
+        .map_err(|e| format!("Failed to serialize screenshot metadata: {}", e))?;
+    if let Err(e) = fs::write(dir.join(format!("{}.json", id)), sidecar) {
+        let _ = fs::remove_file(&path);
+        return Err(format!("Failed to write screenshot metadata: {}", e));
+    }
+
+    // A zero limit keeps everything
+    let keep = crate::read_user_preferences()
+        .ok()
+        .and_then(|p| p.max_screenshots)
+        .unwrap_or(DEFAULT_MAX_SCREENSHOTS);
+    if keep > 0 {
+        prune(&dir, keep);
+    }
+
+    Ok(record)
+}
+
+/// Every capture in `dir`, newest first; unreadable sidecars are skipped
+fn read_dir_records(dir: &Path) -> Vec<ScreenshotRecord> {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return vec![];
+    };
+    let mut records: Vec<ScreenshotRecord> = entries
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| path.extension().is_some_and(|e| e == "json"))
+        .filter_map(|path| {
+            let content = fs::read_to_string(&path).ok()?;
+            let mut record: ScreenshotRecord = serde_json::from_str(&content).ok()?;
+            record.path = dir.join(&record.file_name).to_string_lossy().to_string();
+            Some(record)
+        })
+        .collect();
+    records.sort_by_key(|r| std::cmp::Reverse(r.timestamp));
+    records
+}
+
+fn remove_record(dir: &Path, record: &ScreenshotRecord) {
+    let _ = fs::remove_file(dir.join(&record.file_name));
+    let _ = fs::remove_file(dir.join(format!("{}.json", record.id)));
+}
+
+fn prune(dir: &Path, keep: usize) {
+    for record in read_dir_records(dir).iter().skip(keep) {
+        remove_record(dir, record);
+    }
+}
+
+pub fn list(project_path: &str, options: &ListOptions) -> Vec<ScreenshotRecord> {
+    read_dir_records(&store_dir(project_path))
+        .into_iter()
+        .filter(|r| options.session_id.is_none() || r.session_id == options.session_id)
+        .filter(|r| options.since.map_or(true, |since| r.timestamp >= since))
+        .take(options.limit.unwrap_or(usize::MAX))
+        .collect()
+}
+
+/// Delete captures by ID from whichever project holds them; returns how many went
+pub fn delete(ids: &[String]) -> Result<usize, String> {
+    if let Some(bad) = ids.iter().find(|id| !is_valid_id(id)) {
+        return Err(format!("Invalid screenshot ID: {}", bad));
+    }
+    let Ok(projects) = fs::read_dir(root_dir()) else {
+        return Ok(0);
+    };
+
+    let mut deleted = 0;
+    for dir in projects.flatten().map(|entry| entry.path()).filter(|p| p.is_dir()) {
+        for id in ids {
+            let sidecar = dir.join(format!("{}.json", id));
+            let Some(record) = fs::read_to_string(&sidecar)
+                .ok()
+                .and_then(|content| serde_json::from_str::<ScreenshotRecord>(&content).ok())
+            else {
+                continue;
+            };
+            remove_record(&dir, &record);
+            deleted += 1;
+        }
+    }
+    Ok(deleted)
+}
@@ -0,0 +1,121 @@
+//! A badge per session so its builds, logs and worktrees can be told apart
+//!
+//! Each session gets a city name, a color (an index into the frontend's
+//! palette) and an emoji when it's created, kept in the preferences so a
+//! resumed session looks the same. Lookups for event stamps and the worktree
+//! list go through an in-memory copy instead of the preferences file.
+
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::OnceLock;
+
+/// City names for stable session naming
+const CITY_NAMES: &[&str] = &[
+    "tokyo", "paris", "london", "berlin", "sydney", "cairo", "mumbai", "seoul",
+    "rome", "vienna", "prague", "lisbon", "dublin", "oslo", "stockholm", "helsinki",
+    "amsterdam", "brussels", "zurich", "milan", "barcelona", "madrid", "athens",
+    "istanbul", "dubai", "singapore", "bangkok", "hanoi", "manila", "jakarta",
+    "nairobi", "lagos", "casablanca", "capetown", "montreal", "vancouver", "seattle",
+    "denver", "austin", "miami", "boston", "chicago", "portland", "phoenix",
+    "havana", "lima", "bogota", "santiago", "buenosaires", "rio", "saopaulo",
+    "reykjavik", "tallinn", "riga", "vilnius", "warsaw", "budapest", "bucharest",
+    "sofia", "belgrade", "zagreb", "ljubljana", "bratislava", "kyiv", "minsk"
+];
+
+/// Size of the frontend's session palette
+pub const COLOR_COUNT: u8 = 10;
+const EMOJIS: &[&str] = &[
+    "🦊", "🐙", "🦉", "🐝", "🦋", "🐢", "🦄", "🐳", "🦜", "🐼", "🦔", "🐧",
+    "🌵", "🍄", "🌻", "🍋", "🚀", "⚓", "🎈", "🔮",
+];
+
+/// Color and emoji as saved in the preferences; the name is in `session_names`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SessionBadge {
+    pub color_index: u8,
+    pub emoji: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SessionIdentity {
+    pub name: String,
+    pub color_index: u8,
+    pub emoji: String,
+}
+
+fn identities() -> &'static Mutex<HashMap<String, SessionIdentity>> {
+    static IDENTITIES: OnceLock<Mutex<HashMap<String, SessionIdentity>>> = OnceLock::new();
+    IDENTITIES.get_or_init(|| {
+        let prefs = crate::read_user_preferences().unwrap_or_default();
+        let loaded = prefs
+            .session_badges
+            .iter()
+            .filter_map(|(session_id, badge)| {
+                let name = prefs.session_names.get(session_id)?;
+                Some((session_id.clone(), SessionIdentity {
+                    name: name.clone(),
+                    color_index: badge.color_index,
+                    emoji: badge.emoji.clone(),
+                }))
+            })
+            .collect();
+        Mutex::new(loaded)
+    })
+}
+
+/// The identity already assigned to `session_id`, without touching the preferences
+pub fn get(session_id: &str) -> Option<SessionIdentity> {
+    identities().lock().get(session_id).cloned()
+}
+
+fn new_name(names: &HashMap<String, String>) -> String {
+    let used: std::collections::HashSet<&str> = names.values().map(String::as_str).collect();
+    CITY_NAMES
+        .iter()
+        .find(|name| !used.contains(*name))
+        .map(|s| s.to_string())
+        .unwrap_or_else(|| {
+            // If all names used, generate one with a suffix
+            let base_name = CITY_NAMES[names.len() % CITY_NAMES.len()];
+            format!("{}-{}", base_name, names.len() / CITY_NAMES.len() + 1)
+        })
+}
+
+/// The least used color and the first unused emoji, so sessions open at the
+/// same time differ
+fn new_badge(badges: &HashMap<String, SessionBadge>) -> SessionBadge {
+    let color_index = (0..COLOR_COUNT)
+        .min_by_key(|color| badges.values().filter(|b| b.color_index == *color).count())
+        .unwrap_or(0);
+    let emoji = EMOJIS
+        .iter()
+        .find(|emoji| !badges.values().any(|b| b.emoji == **emoji))
+        .unwrap_or(&EMOJIS[badges.len() % EMOJIS.len()]);
+    SessionBadge { color_index, emoji: emoji.to_string() }
+}
+
+/// The session's identity, assigning and saving whatever part of it is missing
+pub fn assign(session_id: &str) -> Result<SessionIdentity, String> {
+    if let Some(identity) = get(session_id) {
+        return Ok(identity);
+    }
+    let mut prefs = crate::read_user_preferences().unwrap_or_default();
+    let name = match prefs.session_names.get(session_id) {
+        Some(name) => name.clone(),
+        None => new_name(&prefs.session_names),
+    };
+    let badge = match prefs.session_badges.get(session_id) {
+        Some(badge) => badge.clone(),
+        None => new_badge(&prefs.session_badges),
+    };
+    prefs.session_names.insert(session_id.to_string(), name.clone());
+    prefs.session_badges.insert(session_id.to_string(), badge.clone());
+    crate::write_user_preferences(&prefs)?;
+
+    let identity = SessionIdentity { name, color_index: badge.color_index, emoji: badge.emoji };
+    identities().lock().insert(session_id.to_string(), identity.clone());
+    Ok(identity)
+}
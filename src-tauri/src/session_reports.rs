@@ -0,0 +1,339 @@
+//! End-of-session recaps
+//!
+//! A `SessionReport` says what a Claude session did: files changed and commits
+//! made since it started, builds run, tokens and cost, screenshots taken and
+//! how long it lasted. Usage and timing come from a tally kept per session
+//! while it runs (in memory, so a report for a session from before a restart
+//! has no usage). The rest is read back afterwards: the diff against the
+//! session's first checkpoint (or the HEAD it started on), the build run log
+//! and the screenshot history. Saved reports go to
+//! `~/.nocur/reports/<id>.json` with a Markdown copy next to it.
+
+use crate::claude::ClaudeEvent;
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fmt::Write as _;
+use std::path::PathBuf;
+use std::process::Command;
+use std::time::{SystemTime, UNIX_EPOCH};
+use tauri::{AppHandle, Manager};
+
+/// Token and cost totals over a session's queries
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SessionUsage {
+    pub input_tokens: u64,
+    pub output_tokens: u64,
+    pub cache_read_tokens: u64,
+    pub cache_creation_tokens: u64,
+    /// USD, as the SDK reports it
+    pub cost: f64,
+    pub queries: u32,
+    pub turns: u32,
+}
+
+impl SessionUsage {
+    fn record(&mut self, event: &ClaudeEvent) {
+        if event.event_type != "result" {
+            return;
+        }
+        self.input_tokens += event.input_tokens.unwrap_or(0);
+        self.output_tokens += event.output_tokens.unwrap_or(0);
+        self.cache_read_tokens += event.cache_read_tokens.unwrap_or(0);
+        self.cache_creation_tokens += event.cache_creation_tokens.unwrap_or(0);
+        self.cost += event.cost.unwrap_or(0.0);
+        self.queries += 1;
+        self.turns += event.num_turns.unwrap_or(0);
+    }
+}
+
+struct SessionTally {
+    /// Milliseconds since the epoch
+    started_at: u64,
+    ended_at: Option<u64>,
+    /// HEAD when the session started, for counting its commits
+    start_head: Option<String>,
+    usage: SessionUsage,
+}
+
+/// Tallies for the sessions started since launch, by session ID
+#[derive(Default)]
+pub struct SessionTallies {
+    tallies: Mutex<HashMap<String, SessionTally>>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FileChange {
+    pub path: String,
+    /// None for binary files
+    pub additions: Option<u64>,
+    pub deletions: Option<u64>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CommitSummary {
+    pub hash: String,
+    pub subject: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SessionReport {
+    pub id: String,
+    pub session_id: String,
+    pub project_path: String,
+    /// Milliseconds since the epoch
+    pub generated_at: u64,
+    pub started_at: Option<u64>,
+    /// None while the session is still running
+    pub ended_at: Option<u64>,
+    pub duration_secs: Option<u64>,
+    /// What the diff was taken against: a checkpoint ref or a commit
+    pub base: Option<String>,
+    pub files_changed: Vec<FileChange>,
+    pub commits: Vec<CommitSummary>,
+    pub builds: Vec<crate::build_history::BuildRun>,
+    /// None when the session wasn't tallied in this run of the app
+    pub usage: Option<SessionUsage>,
+    pub screenshots: usize,
+    pub markdown: String,
+}
+
+/// `session-report-ready`
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SessionReportReady {
+    pub report_id: String,
+    pub session_id: String,
+    pub project_path: String,
+}
+
+fn now_millis() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_millis() as u64
+}
+
+fn git(working_dir: &str, args: &[&str]) -> Option<String> {
+    let output = Command::new("git").args(args).current_dir(working_dir).output().ok()?;
+    output
+        .status
+        .success()
+        .then(|| String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+/// Start tallying a session; called when it starts. A resumed session picks
+/// up its existing tally.
+pub fn begin(app_handle: &AppHandle, session_id: &str, working_dir: &str) {
+    let start_head = git(working_dir, &["rev-parse", "--verify", "-q", "HEAD"]);
+    let tallies = app_handle.state::<SessionTallies>();
+    let mut tallies = tallies.tallies.lock();
+    let tally = tallies.entry(session_id.to_string()).or_insert_with(|| SessionTally {
+        started_at: now_millis(),
+        ended_at: None,
+        start_head,
+        usage: SessionUsage::default(),
+    });
+    tally.ended_at = None;
+}
+
+/// Add a session's event to its tally
+pub fn record_event(app_handle: &AppHandle, session_id: &str, event: &ClaudeEvent) {
+    if let Some(tally) = app_handle.state::<SessionTallies>().tallies.lock().get_mut(session_id) {
+        tally.usage.record(event);
+    }
+}
+
+/// Tokens and cost so far for a session tallied since launch
+pub fn usage(app_handle: &AppHandle, session_id: &str) -> Option<SessionUsage> {
+    app_handle.state::<SessionTallies>().tallies.lock().get(session_id).map(|tally| tally.usage.clone())
+}
+
+/// Mark a session finished; its tally is kept for reports
+pub fn end(app_handle: &AppHandle, session_id: &str) {
+    if let Some(tally) = app_handle.state::<SessionTallies>().tallies.lock().get_mut(session_id) {
+        tally.ended_at.get_or_insert_with(now_millis);
+    }
+}
+
+fn reports_dir() -> PathBuf {
+    crate::paths::nocur_dir().join("reports")
+}
+
+/// IDs are generated UUIDs; anything else could name a path outside the store
+fn is_valid_id(id: &str) -> bool {
+    !id.is_empty() && id.chars().all(|c| c.is_ascii_alphanumeric() || c == '-')
+}
+
+fn diff_against(working_dir: &str, base: &str) -> Vec<FileChange> {
+    git(working_dir, &["diff", "--numstat", base])
+        .unwrap_or_default()
+        .lines()
+        .filter_map(|line| {
+            let mut parts = line.splitn(3, '\t');
+            let additions = parts.next()?.parse().ok();
+            let deletions = parts.next()?.parse().ok();
+            Some(FileChange { path: parts.next()?.to_string(), additions, deletions })
+        })
+        .collect()
+}
+
+fn commits_since(working_dir: &str, start_head: &str) -> Vec<CommitSummary> {
+    git(working_dir, &["log", "--format=%h%x1f%s", &format!("{}..HEAD", start_head)])
+        .unwrap_or_default()
+        .lines()
+        .filter_map(|line| {
+            let (hash, subject) = line.split_once('\x1f')?;
+            Some(CommitSummary { hash: hash.to_string(), subject: subject.to_string() })
+        })
+        .collect()
+}
+
+fn format_duration(secs: u64) -> String {
+    match (secs / 3600, secs % 3600 / 60, secs % 60) {
+        (0, 0, s) => format!("{}s", s),
+        (0, m, s) => format!("{}m {}s", m, s),
+        (h, m, _) => format!("{}h {}m", h, m),
+    }
+}
+
+fn render_markdown(report: &SessionReport) -> String {
+    let mut md = String::new();
+    let _ = writeln!(md, "# Session report\n");
+    let _ = writeln!(md, "- Session: `{}`", report.session_id);
+    let _ = writeln!(md, "- Project: `{}`", report.project_path);
+    if let Some(secs) = report.duration_secs {
+        let running = if report.ended_at.is_none() { " (still running)" } else { "" };
+        let _ = writeln!(md, "- Duration: {}{}", format_duration(secs), running);
+    }
+    if let Some(usage) = &report.usage {
+        let _ = writeln!(
+            md,
+            "- Tokens: {} in, {} out ({} cache read, {} cache write)",
+            usage.input_tokens, usage.output_tokens, usage.cache_read_tokens, usage.cache_creation_tokens
+        );
+        let _ = writeln!(md, "- Cost: ${:.2} over {} queries, {} turns", usage.cost, usage.queries, usage.turns);
+    }
+    let _ = writeln!(md, "- Screenshots: {}", report.screenshots);
+
+    let _ = writeln!(md, "\n## Files changed ({})\n", report.files_changed.len());
+    if report.files_changed.is_empty() {
+        let _ = writeln!(md, "None");
+    }
+    for file in &report.files_changed {
+        match (file.additions, file.deletions) {
+            (Some(added), Some(removed)) => {
+                let _ = writeln!(md, "- `{}` +{} -{}", file.path, added, removed);
+            }
+            _ => {
+                let _ = writeln!(md, "- `{}` (binary)", file.path);
+            }
+        }
+    }
+
+    let _ = writeln!(md, "\n## Commits ({})\n", report.commits.len());
+    if report.commits.is_empty() {
+        let _ = writeln!(md, "None");
+    }
+    for commit in &report.commits {
+        let _ = writeln!(md, "- `{}` {}", commit.hash, commit.subject);
+    }
+
+    let passed = report.builds.iter().filter(|b| b.succeeded).count();
+    let _ = writeln!(md, "\n## Builds ({} passed, {} failed)\n", passed, report.builds.len() - passed);
+    if report.builds.is_empty() {
+        let _ = writeln!(md, "None");
+    }
+    for build in &report.builds {
+        let outcome = if build.succeeded {
+            "passed".to_string()
+        } else {
+            format!("failed with {} errors", build.error_count)
+        };
+        let _ = writeln!(md, "- {} in {:.1}s", outcome, build.duration_secs);
+    }
+    md
+}
+
+/// Put together the report for `session_id`'s work in `project_path`
+pub fn generate(app_handle: &AppHandle, project_path: &str, session_id: &str) -> SessionReport {
+    let (started_at, ended_at, start_head, usage) = match app_handle.state::<SessionTallies>().tallies.lock().get(session_id) {
+        Some(t) => (Some(t.started_at), t.ended_at, t.start_head.clone(), Some(t.usage.clone())),
+        None => (None, None, None, None),
+    };
+
+    // The first checkpoint holds the tree as it was before the agent touched it,
+    // uncommitted changes included; its parent is the HEAD the session started on
+    let first_checkpoint = crate::checkpoints::list_checkpoints(project_path, session_id)
+        .ok()
+        .and_then(|checkpoints| checkpoints.into_iter().min_by_key(|c| c.number));
+    let start_head = start_head.or_else(|| {
+        first_checkpoint
+            .as_ref()
+            .and_then(|c| git(project_path, &["rev-parse", "--verify", "-q", &format!("{}^", c.commit)]))
+    });
+    let base = first_checkpoint.map(|c| c.id).or_else(|| start_head.clone());
+
+    let screenshots = crate::screenshots::list(
+        project_path,
+        &crate::screenshots::ListOptions { session_id: Some(session_id.to_string()), ..Default::default() },
+    )
+    .len();
+
+    let mut report = SessionReport {
+        id: uuid::Uuid::new_v4().to_string(),
+        session_id: session_id.to_string(),
+        project_path: project_path.to_string(),
+        generated_at: now_millis(),
+        started_at,
+        ended_at,
+        duration_secs: started_at.map(|start| ended_at.unwrap_or_else(now_millis).saturating_sub(start) / 1000),
+        files_changed: base.as_deref().map(|base| diff_against(project_path, base)).unwrap_or_default(),
+        commits: start_head.as_deref().map(|head| commits_since(project_path, head)).unwrap_or_default(),
+        base,
+        builds: crate::build_history::runs_for_session(project_path, session_id),
+        usage,
+        screenshots,
+        markdown: String::new(),
+    };
+    report.markdown = crate::redaction::redact(&render_markdown(&report));
+    report
+}
+
+/// Write the report and its Markdown to the reports folder; returns the JSON path
+pub fn save(report: &SessionReport) -> Result<PathBuf, String> {
+    let dir = reports_dir();
+    std::fs::create_dir_all(&dir).map_err(|e| format!("Failed to create {}: {}", dir.display(), e))?;
+    let json_path = dir.join(format!("{}.json", report.id));
+    let content = serde_json::to_string_pretty(report).map_err(|e| format!("Failed to serialize report: {}", e))?;
+    std::fs::write(&json_path, content).map_err(|e| format!("Failed to write {}: {}", json_path.display(), e))?;
+    let md_path = dir.join(format!("{}.md", report.id));
+    std::fs::write(&md_path, &report.markdown).map_err(|e| format!("Failed to write {}: {}", md_path.display(), e))?;
+    Ok(json_path)
+}
+
+pub fn load(report_id: &str) -> Result<SessionReport, String> {
+    if !is_valid_id(report_id) {
+        return Err(format!("Invalid report ID: {}", report_id));
+    }
+    let path = reports_dir().join(format!("{}.json", report_id));
+    let content = std::fs::read_to_string(&path).map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+    serde_json::from_str(&content).map_err(|e| format!("Failed to parse {}: {}", path.display(), e))
+}
+
+/// Generate and save the report for a session that just stopped, then emit
+/// `session-report-ready`. Runs in the background since it shells out to git.
+pub fn spawn_on_stop(app_handle: AppHandle, project_path: String, session_id: String) {
+    std::thread::spawn(move || {
+        let report = generate(&app_handle, &project_path, &session_id);
+        match save(&report) {
+            Ok(_) => crate::event_journal::emit_stamped(&app_handle, "session-report-ready", SessionReportReady {
+                report_id: report.id,
+                session_id,
+                project_path,
+            }),
+            Err(e) => log::warn!("Failed to save session report: {}", e),
+        }
+    });
+}
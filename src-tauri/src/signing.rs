@@ -0,0 +1,113 @@
+//! Signing settings for device builds, and telling signing failures apart
+//!
+//! Device builds sign automatically with `-allowProvisioningUpdates`, which
+//! picks the project's DEVELOPMENT_TEAM. With several teams on the machine,
+//! or none set in the project, the caller can pass a team and identity that
+//! go to xcodebuild as build setting overrides.
+//!
+//! When a build fails on signing, the errors xcodebuild printed are tagged
+//! with a `category` (no team, expired certificate, untrusted device) so the
+//! UI can say what to do instead of showing the raw message.
+
+use std::process::Command;
+
+use crate::BuildError;
+
+/// Build setting overrides for signing; empty values are ignored
+#[derive(Debug, Clone, Default)]
+pub struct SigningOverrides {
+    pub development_team: Option<String>,
+    pub code_sign_identity: Option<String>,
+}
+
+impl SigningOverrides {
+    /// Trimmed, with a team ID checked for the 10-character form Apple uses
+    pub fn new(development_team: Option<String>, code_sign_identity: Option<String>) -> Result<Self, String> {
+        let non_empty = |value: Option<String>| value.map(|v| v.trim().to_string()).filter(|v| !v.is_empty());
+        let development_team = non_empty(development_team);
+        if let Some(team) = &development_team {
+            if team.len() != 10 || !team.chars().all(|c| c.is_ascii_uppercase() || c.is_ascii_digit()) {
+                return Err(format!("'{}' isn't a team ID; it should be 10 letters and digits, like ABCDE12345", team));
+            }
+        }
+        Ok(Self { development_team, code_sign_identity: non_empty(code_sign_identity) })
+    }
+
+    /// The `NAME=value` build settings, in the order xcodebuild gets them
+    pub fn settings(&self) -> Vec<String> {
+        let team = self.development_team.as_ref().map(|team| format!("DEVELOPMENT_TEAM={}", team));
+        let identity = self.code_sign_identity.as_ref().map(|identity| format!("CODE_SIGN_IDENTITY={}", identity));
+        team.into_iter().chain(identity).collect()
+    }
+
+    /// Append the settings to an xcodebuild command line
+    pub fn apply(&self, cmd: &mut Command) {
+        cmd.args(self.settings());
+    }
+}
+
+const NO_TEAM: &[&str] = &[
+    "requires a development team",
+    "No Account for Team",
+    "No signing certificate",
+    "No profiles for",
+    "doesn't match any valid certificate/private key pair",
+];
+const CERTIFICATE_EXPIRED: &[&str] = &[
+    "has expired",
+    "is expired",
+    "CSSMERR_TP_CERT_EXPIRED",
+    "has been revoked",
+    "CSSMERR_TP_CERT_REVOKED",
+];
+const UNTRUSTED_DEVICE: &[&str] = &[
+    "not trusted",
+    "untrusted",
+    "has not been paired",
+    "is not paired",
+    "Trust This Computer",
+    "Failed to prepare device for development",
+    "isn't registered in your developer account",
+];
+const SIGNING: &[&str] = &["Code Signing Error", "Signing for", "provisioning profile", "code signing", "CodeSign"];
+
+/// The signing failure `message` describes, if it's one
+pub fn category(message: &str) -> Option<&'static str> {
+    let lower = message.to_lowercase();
+    let matches = |patterns: &[&str]| patterns.iter().any(|p| lower.contains(&p.to_lowercase()));
+    if matches(CERTIFICATE_EXPIRED) {
+        Some("signing_certificate_expired")
+    } else if matches(UNTRUSTED_DEVICE) {
+        Some("signing_untrusted_device")
+    } else if matches(NO_TEAM) {
+        Some("signing_no_team")
+    } else if matches(SIGNING) {
+        Some("signing")
+    } else {
+        None
+    }
+}
+
+/// Tag the failed build's signing errors with their category and move them
+/// to the front. A missing team gets the keychain's teams as `suggestions`.
+pub fn classify(errors: &mut [BuildError]) {
+    let mut found = false;
+    // xcodebuild's signing errors have no line, so they're never located
+    for error in errors.iter_mut().filter(|e| e.file.is_none()) {
+        error.category = category(&error.message).map(String::from);
+        found |= error.category.is_some();
+    }
+    if !found {
+        return;
+    }
+    // Stable, so the signing errors keep xcodebuild's order
+    errors.sort_by_key(|e| e.category.is_none());
+
+    if let Some(error) = errors.iter_mut().find(|e| e.category.as_deref() == Some("signing_no_team")) {
+        let mut teams: Vec<String> =
+            crate::signing_health::list_certificates().into_iter().filter_map(|c| c.team_id).collect();
+        teams.sort();
+        teams.dedup();
+        error.suggestions = teams;
+    }
+}
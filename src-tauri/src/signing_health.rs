@@ -0,0 +1,287 @@
+//! Certificate and provisioning profile expiry, checked ahead of time
+//!
+//! A device build starts failing the day its signing certificate or profile
+//! expires, with an xcodebuild error that doesn't say so. This looks at the
+//! code signing identities in the keychain and the installed profiles for the
+//! project's team (`DEVELOPMENT_TEAM`), and warns about anything expired or
+//! expiring within `EXPIRY_WARNING_DAYS`, with the date. Given a device, it
+//! also checks that a current profile lists it.
+//!
+//! Results are cached per project and device for a day, since none of this
+//! changes often and reading certificates means several `security` calls.
+
+use chrono::{DateTime, NaiveDateTime, Utc};
+use parking_lot::Mutex;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::io::Write;
+use std::process::{Command, Stdio};
+use std::sync::OnceLock;
+use std::time::{Duration, Instant, SystemTime};
+
+use crate::device_preflight::{self, PreflightFinding, ProvisioningProfile};
+use crate::messages::msg;
+
+const EXPIRY_WARNING_DAYS: u64 = 30;
+const CACHE_TTL: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// A code signing identity from the keychain
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SigningCertificate {
+    /// e.g. "Apple Development: Jane Doe (ABCDE12345)"
+    pub name: String,
+    pub sha1: String,
+    /// The certificate subject's OU
+    pub team_id: Option<String>,
+    pub expires_at: Option<String>,
+    #[serde(skip)]
+    expiration: Option<SystemTime>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SigningWarning {
+    #[serde(flatten)]
+    pub finding: PreflightFinding,
+    /// When the certificate or profile expires (or expired), RFC 3339
+    pub expires_at: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SigningHealth {
+    /// The project's DEVELOPMENT_TEAM; without it every identity and profile is checked
+    pub team_id: Option<String>,
+    pub device_udid: Option<String>,
+    pub certificates: Vec<SigningCertificate>,
+    pub profiles_checked: usize,
+    pub warnings: Vec<SigningWarning>,
+    pub checked_at: String,
+}
+
+fn rfc3339(time: SystemTime) -> String {
+    DateTime::<Utc>::from(time).to_rfc3339()
+}
+
+fn date(time: SystemTime) -> String {
+    DateTime::<Utc>::from(time).format("%Y-%m-%d").to_string()
+}
+
+/// Expired, expiring soon, or fine
+fn expiry(expiration: Option<SystemTime>) -> Option<(bool, SystemTime)> {
+    let expiration = expiration?;
+    let now = SystemTime::now();
+    if expiration <= now {
+        return Some((true, expiration));
+    }
+    let remaining = expiration.duration_since(now).unwrap_or_default();
+    (remaining < Duration::from_secs(EXPIRY_WARNING_DAYS * 24 * 60 * 60)).then_some((false, expiration))
+}
+
+/// A valid code signing identity, as `security find-identity` lists it
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SigningIdentity {
+    /// The certificate's SHA-1, which `CODE_SIGN_IDENTITY` accepts as well as the name
+    pub id: String,
+    /// e.g. "Apple Development: Jane Doe (ABCDE12345)"
+    pub name: String,
+}
+
+/// `security find-identity` lines look like
+/// `  1) 0123...CDEF "Apple Development: Jane Doe (ABCDE12345)"`
+pub fn list_identities() -> Vec<SigningIdentity> {
+    let Ok(output) = Command::new("security").args(["find-identity", "-v", "-p", "codesigning"]).output() else {
+        return Vec::new();
+    };
+    String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .filter_map(|line| {
+            let (_, rest) = line.trim().split_once(") ")?;
+            let (sha1, name) = rest.split_once(' ')?;
+            Some(SigningIdentity { id: sha1.to_string(), name: name.trim().trim_matches('"').to_string() })
+        })
+        .collect()
+}
+
+/// Subject OU and expiry of a PEM certificate, via openssl
+fn certificate_details(pem: &str) -> (Option<String>, Option<SystemTime>) {
+    let child = Command::new("openssl")
+        .args(["x509", "-noout", "-enddate", "-subject", "-nameopt", "multiline"])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn();
+    let Ok(mut child) = child else {
+        return (None, None);
+    };
+    if let Some(mut stdin) = child.stdin.take() {
+        let _ = stdin.write_all(pem.as_bytes());
+    }
+    let Ok(output) = child.wait_with_output() else {
+        return (None, None);
+    };
+    let text = String::from_utf8_lossy(&output.stdout);
+
+    let expiration = text
+        .lines()
+        .find_map(|line| line.strip_prefix("notAfter="))
+        .and_then(|date| {
+            let date = date.split_whitespace().collect::<Vec<_>>().join(" ");
+            NaiveDateTime::parse_from_str(&date, "%b %d %H:%M:%S %Y GMT").ok()
+        })
+        .map(|date| SystemTime::from(date.and_utc()));
+    let team_id = text
+        .lines()
+        .find_map(|line| line.trim().strip_prefix("organizationalUnitName"))
+        .map(|value| value.trim_start_matches([' ', '=']).trim().to_string());
+    (team_id, expiration)
+}
+
+/// The keychain's code signing identities with their teams and expiry dates
+pub fn list_certificates() -> Vec<SigningCertificate> {
+    let identities = list_identities();
+    if identities.is_empty() {
+        return Vec::new();
+    }
+    // One call for all certificates; each PEM follows its "SHA-1 hash:" line
+    let Ok(output) = Command::new("security").args(["find-certificate", "-a", "-Z", "-p"]).output() else {
+        return Vec::new();
+    };
+    let text = String::from_utf8_lossy(&output.stdout);
+    let mut pems: HashMap<String, String> = HashMap::new();
+    let mut current_hash: Option<String> = None;
+    let mut pem = String::new();
+    for line in text.lines() {
+        if let Some(hash) = line.strip_prefix("SHA-1 hash:") {
+            current_hash = Some(hash.trim().to_uppercase());
+        } else if line.starts_with("-----BEGIN CERTIFICATE") {
+            pem = format!("{}\n", line);
+        } else if line.starts_with("-----END CERTIFICATE") {
+            pem.push_str(line);
+            pem.push('\n');
+            if let Some(hash) = current_hash.take() {
+                pems.insert(hash, std::mem::take(&mut pem));
+            }
+        } else if !pem.is_empty() {
+            pem.push_str(line);
+            pem.push('\n');
+        }
+    }
+
+    identities
+        .into_iter()
+        .map(|SigningIdentity { id: sha1, name }| {
+            let (team_id, expiration) = pems
+                .get(&sha1.to_uppercase())
+                .map(|pem| certificate_details(pem))
+                .unwrap_or((None, None));
+            SigningCertificate { name, sha1, team_id, expires_at: expiration.map(rfc3339), expiration }
+        })
+        .collect()
+}
+
+/// The profile for each app ID that expires last; Xcode leaves the ones it
+/// replaced behind, and those aren't worth a warning
+fn current_profiles(profiles: Vec<ProvisioningProfile>) -> Vec<ProvisioningProfile> {
+    let mut latest: HashMap<String, ProvisioningProfile> = HashMap::new();
+    for profile in profiles {
+        let key = profile.app_id.clone().unwrap_or_else(|| profile.uuid.clone());
+        match latest.get(&key) {
+            Some(existing) if existing.expiration >= profile.expiration => {}
+            _ => {
+                latest.insert(key, profile);
+            }
+        }
+    }
+    latest.into_values().collect()
+}
+
+pub fn check(project_path: Option<&str>, device_udid: Option<&str>) -> SigningHealth {
+    let team_id = project_path.and_then(|path| crate::project::build_setting(path, "DEVELOPMENT_TEAM"));
+    let bundle_id = project_path.and_then(crate::project::detected_bundle_id);
+    let for_team = |team: Option<&String>| team_id.is_none() || team == team_id.as_ref();
+    let mut warnings = Vec::new();
+
+    let certificates: Vec<SigningCertificate> =
+        list_certificates().into_iter().filter(|c| for_team(c.team_id.as_ref())).collect();
+    if certificates.is_empty() {
+        warnings.push(SigningWarning {
+            finding: PreflightFinding::blocking(
+                "noCertificate",
+                msg!("signing.no_certificate", team = team_id.as_deref().unwrap_or("any team")),
+            ),
+            expires_at: None,
+        });
+    }
+    for certificate in &certificates {
+        if let Some((expired, at)) = expiry(certificate.expiration) {
+            let finding = if expired {
+                PreflightFinding::blocking("certificateExpired", msg!("signing.certificate_expired", name = certificate.name, date = date(at)))
+            } else {
+                PreflightFinding::warning("certificateExpiring", msg!("signing.certificate_expiring", name = certificate.name, date = date(at)))
+            };
+            warnings.push(SigningWarning { finding, expires_at: Some(rfc3339(at)) });
+        }
+    }
+
+    let profiles = current_profiles(
+        device_preflight::list_provisioning_profiles()
+            .into_iter()
+            .filter(|p| for_team(p.team_id.as_ref()))
+            .filter(|p| bundle_id.as_deref().map_or(true, |id| p.matches_bundle_id(id)))
+            .collect(),
+    );
+    for profile in &profiles {
+        if let Some((expired, at)) = expiry(profile.expiration) {
+            let finding = if expired {
+                PreflightFinding::blocking("profileExpired", msg!("signing.profile_expired", profile = profile.name, date = date(at)))
+            } else {
+                PreflightFinding::warning("profileExpiring", msg!("signing.profile_expiring", profile = profile.name, date = date(at)))
+            };
+            warnings.push(SigningWarning { finding, expires_at: Some(rfc3339(at)) });
+        }
+    }
+    if let Some(udid) = device_udid {
+        let covered = profiles.iter().any(|p| !p.is_expired() && p.covers_device(udid));
+        if !profiles.is_empty() && !covered {
+            warnings.push(SigningWarning {
+                finding: PreflightFinding::warning("deviceNotInProfiles", msg!("signing.device_not_in_profiles", udid = udid)),
+                expires_at: None,
+            });
+        }
+    }
+
+    SigningHealth {
+        team_id,
+        device_udid: device_udid.map(String::from),
+        certificates,
+        profiles_checked: profiles.len(),
+        warnings,
+        checked_at: Utc::now().to_rfc3339(),
+    }
+}
+
+type Cache = HashMap<(Option<String>, Option<String>), (Instant, SigningHealth)>;
+
+fn cache() -> &'static Mutex<Cache> {
+    static CACHE: OnceLock<Mutex<Cache>> = OnceLock::new();
+    CACHE.get_or_init(Default::default)
+}
+
+/// `check`, reusing a result from the last day unless `force_refresh`.
+/// The flag is true when the result was computed just now.
+pub fn cached_check(project_path: Option<&str>, device_udid: Option<&str>, force_refresh: bool) -> (SigningHealth, bool) {
+    let key = (project_path.map(String::from), device_udid.map(String::from));
+    if !force_refresh {
+        if let Some((checked, health)) = cache().lock().get(&key) {
+            if checked.elapsed() < CACHE_TTL {
+                return (health.clone(), false);
+            }
+        }
+    }
+    let health = check(project_path, device_udid);
+    cache().lock().insert(key, (Instant::now(), health.clone()));
+    (health, true)
+}
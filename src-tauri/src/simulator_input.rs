@@ -0,0 +1,141 @@
+//! Keyboard routing between nocur and the Simulator
+//!
+//! With the simulator pane focused, keys either go through nocur or straight
+//! to Simulator.app:
+//!
+//! - `forward`: the pane captures keys and `forward_key` posts them to the
+//!   Simulator process as keyboard events, so nocur keeps focus. Modifiers
+//!   travel as event flags (Cmd+A, Shift+Left) and held keys as autorepeat
+//!   events. Posting needs the Accessibility permission.
+//! - `native`: clicking the pane brings Simulator to the front
+//!   (`focus_simulator`) so macOS routes keys itself; `return_focus` brings
+//!   nocur back.
+
+use serde::{Deserialize, Serialize};
+use std::process::Command;
+
+use crate::errors::CommandError;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum InputMode {
+    Forward,
+    /// Today's behavior: whichever app is frontmost gets the keys
+    #[default]
+    Native,
+}
+
+#[derive(Debug, Default)]
+pub struct SimulatorInputState {
+    pub mode: InputMode,
+}
+
+#[derive(Debug, Clone, Copy, Default, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct KeyModifiers {
+    pub meta: bool,
+    pub shift: bool,
+    pub alt: bool,
+    pub ctrl: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum KeyPhase {
+    Down,
+    Up,
+}
+
+/// macOS virtual key code for a DOM `KeyboardEvent.code`. Modifier keys
+/// aren't mapped; their state is sent as flags on the other keys.
+#[cfg(target_os = "macos")]
+pub fn key_code(code: &str) -> Option<u16> {
+    let key = match code {
+        "KeyA" => 0x00, "KeyS" => 0x01, "KeyD" => 0x02, "KeyF" => 0x03,
+        "KeyH" => 0x04, "KeyG" => 0x05, "KeyZ" => 0x06, "KeyX" => 0x07,
+        "KeyC" => 0x08, "KeyV" => 0x09, "KeyB" => 0x0B, "KeyQ" => 0x0C,
+        "KeyW" => 0x0D, "KeyE" => 0x0E, "KeyR" => 0x0F, "KeyY" => 0x10,
+        "KeyT" => 0x11, "KeyO" => 0x1F, "KeyU" => 0x20, "KeyI" => 0x22,
+        "KeyP" => 0x23, "KeyL" => 0x25, "KeyJ" => 0x26, "KeyK" => 0x28,
+        "KeyN" => 0x2D, "KeyM" => 0x2E,
+        "Digit1" => 0x12, "Digit2" => 0x13, "Digit3" => 0x14, "Digit4" => 0x15,
+        "Digit6" => 0x16, "Digit5" => 0x17, "Digit9" => 0x19, "Digit7" => 0x1A,
+        "Digit8" => 0x1C, "Digit0" => 0x1D,
+        "Equal" => 0x18, "Minus" => 0x1B, "BracketRight" => 0x1E, "BracketLeft" => 0x21,
+        "Quote" => 0x27, "Semicolon" => 0x29, "Backslash" => 0x2A, "Comma" => 0x2B,
+        "Slash" => 0x2C, "Period" => 0x2F, "Backquote" => 0x32,
+        "Enter" | "NumpadEnter" => 0x24, "Tab" => 0x30, "Space" => 0x31,
+        "Backspace" => 0x33, "Escape" => 0x35, "Delete" => 0x75,
+        "Home" => 0x73, "End" => 0x77, "PageUp" => 0x74, "PageDown" => 0x79,
+        "ArrowLeft" => 0x7B, "ArrowRight" => 0x7C, "ArrowDown" => 0x7D, "ArrowUp" => 0x7E,
+        "F1" => 0x7A, "F2" => 0x78, "F3" => 0x63, "F4" => 0x76, "F5" => 0x60, "F6" => 0x61,
+        "F7" => 0x62, "F8" => 0x64, "F9" => 0x65, "F10" => 0x6D, "F11" => 0x67, "F12" => 0x6F,
+        _ => return None,
+    };
+    Some(key)
+}
+
+#[cfg(target_os = "macos")]
+fn simulator_pid() -> Result<i32, String> {
+    let output = Command::new("pgrep")
+        .args(["-x", "Simulator"])
+        .output()
+        .map_err(|e| format!("Failed to find Simulator: {}", e))?;
+    String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .next()
+        .and_then(|pid| pid.trim().parse().ok())
+        .ok_or_else(|| "Simulator isn't running".to_string())
+}
+
+/// Post one key event to the Simulator without changing which app is frontmost
+#[cfg(target_os = "macos")]
+pub fn forward_key(code: &str, modifiers: KeyModifiers, phase: KeyPhase, repeat: bool) -> Result<(), CommandError> {
+    use core_graphics::event::{CGEvent, CGEventFlags, EventField};
+    use core_graphics::event_source::{CGEventSource, CGEventSourceStateID};
+
+    let key = key_code(code).ok_or_else(|| format!("Unsupported key: {}", code))?;
+    let pid = simulator_pid()?;
+
+    let source = CGEventSource::new(CGEventSourceStateID::HIDSystemState)
+        .map_err(|_| "Failed to create keyboard event source".to_string())?;
+    let event = CGEvent::new_keyboard_event(source, key, phase == KeyPhase::Down)
+        .map_err(|_| "Failed to create keyboard event".to_string())?;
+
+    let mut flags = CGEventFlags::CGEventFlagNull;
+    if modifiers.meta {
+        flags |= CGEventFlags::CGEventFlagCommand;
+    }
+    if modifiers.shift {
+        flags |= CGEventFlags::CGEventFlagShift;
+    }
+    if modifiers.alt {
+        flags |= CGEventFlags::CGEventFlagAlternate;
+    }
+    if modifiers.ctrl {
+        flags |= CGEventFlags::CGEventFlagControl;
+    }
+    event.set_flags(flags);
+    if repeat {
+        event.set_integer_value_field(EventField::KEYBOARD_EVENT_AUTOREPEAT, 1);
+    }
+    event.post_to_pid(pid);
+    Ok(())
+}
+
+#[cfg(not(target_os = "macos"))]
+pub fn forward_key(_code: &str, _modifiers: KeyModifiers, _phase: KeyPhase, _repeat: bool) -> Result<(), CommandError> {
+    Err(CommandError::Unsupported("Forwarding keys to the Simulator is only available on macOS".to_string()))
+}
+
+/// Bring Simulator.app to the front so it receives keys directly
+pub fn focus_simulator() -> Result<(), String> {
+    let status = Command::new("open")
+        .args(["-a", "Simulator"])
+        .status()
+        .map_err(|e| format!("Failed to focus Simulator: {}", e))?;
+    if !status.success() {
+        return Err("Failed to focus Simulator".to_string());
+    }
+    Ok(())
+}
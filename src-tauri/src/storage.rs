@@ -0,0 +1,460 @@
+//! Per-project disk usage report and cleanup
+//!
+//! Builds, session worktrees and recordings all leave files outside the project
+//! that are easy to forget. Each checkout and worktree has its own build cache
+//! under ~/.nocur/DerivedData, and those are included. The report sizes each
+//! location under a shared deadline so a huge DerivedData can't hang the UI;
+//! anything not finished in time is reported with `partial` set and its byte
+//! count as a lower bound.
+
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+use crate::dry_run::DryRunReport;
+use crate::paths;
+
+/// Budget for sizing everything in one report
+const SIZING_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// xcodebuild's logs live here inside a DerivedData folder; sized and cleaned as "logs"
+const LOGS_DIR: &str = "Logs";
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StorageItem {
+    /// "derivedData", "logs", "worktree" or "recordings"
+    pub category: String,
+    pub path: String,
+    pub bytes: u64,
+    /// Sizing hit the deadline; `bytes` is a lower bound
+    pub partial: bool,
+    /// Worktrees only: not registered with git, or no open session is using it
+    pub stale: bool,
+    pub note: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StorageReport {
+    pub project_path: String,
+    pub items: Vec<StorageItem>,
+    pub total_bytes: u64,
+    pub partial: bool,
+}
+
+/// One checkout's DerivedData
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BuildCache {
+    /// The checkout or worktree this cache builds
+    pub working_dir: String,
+    pub is_main: bool,
+    pub path: String,
+    pub bytes: u64,
+    /// Sizing hit the deadline; `bytes` is a lower bound
+    pub partial: bool,
+    /// A `<checkout>/DerivedData` from before caches moved under ~/.nocur
+    pub legacy: bool,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct CleanupOptions {
+    pub derived_data: bool,
+    pub stale_worktrees: bool,
+    pub recordings: bool,
+    pub logs: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CleanupResult {
+    pub bytes_reclaimed: u64,
+    pub removed: Vec<String>,
+    pub errors: Vec<String>,
+}
+
+/// Size of a file or directory tree, without following symlinks.
+/// The bool is false when the deadline passed before the walk finished.
+fn size_of(path: &Path, deadline: Instant, skip: Option<&Path>) -> (u64, bool) {
+    let Ok(metadata) = std::fs::symlink_metadata(path) else {
+        return (0, true);
+    };
+    if !metadata.is_dir() {
+        return (metadata.len(), true);
+    }
+
+    let mut total = 0;
+    let mut pending = vec![path.to_path_buf()];
+    while let Some(dir) = pending.pop() {
+        if Instant::now() >= deadline {
+            return (total, false);
+        }
+        let Ok(entries) = std::fs::read_dir(&dir) else {
+            continue;
+        };
+        for entry in entries.flatten() {
+            let entry_path = entry.path();
+            if skip == Some(entry_path.as_path()) {
+                continue;
+            }
+            let Ok(metadata) = entry.metadata() else {
+                continue;
+            };
+            if metadata.is_dir() {
+                pending.push(entry_path);
+            } else {
+                total += metadata.len();
+            }
+        }
+    }
+    (total, true)
+}
+
+/// The project's checkouts: the main one and every git worktree, as
+/// `(path, is_main)`. A directory that isn't a git repository is its own main checkout.
+fn checkouts(project: &Path) -> Vec<(PathBuf, bool)> {
+    match crate::read_worktrees(&project.to_string_lossy()) {
+        Ok(worktrees) if !worktrees.is_empty() => worktrees
+            .into_iter()
+            .map(|wt| (PathBuf::from(wt.path), wt.is_main))
+            .filter(|(path, _)| path.is_dir())
+            .collect(),
+        _ => vec![(project.to_path_buf(), true)],
+    }
+}
+
+/// `(checkout, is_main, cache, legacy)` for every build cache nocur made for
+/// the project's checkouts that's still on disk
+fn build_cache_dirs(project: &Path) -> Vec<(PathBuf, bool, PathBuf, bool)> {
+    let mut caches = Vec::new();
+    for (checkout, is_main) in checkouts(project) {
+        let current = paths::derived_data_dir(&checkout);
+        if current.is_dir() {
+            caches.push((checkout.clone(), is_main, current, false));
+        }
+        let legacy = paths::legacy_derived_data_dir(&checkout);
+        if legacy.is_dir() {
+            caches.push((checkout, is_main, legacy, true));
+        }
+    }
+    caches
+}
+
+/// DerivedData folders holding this project's builds: the caches nocur's builds
+/// use for each checkout, plus any in Xcode's default location whose info.plist
+/// points here. A worktree's legacy cache is inside the worktree and is
+/// reported with it.
+pub(crate) fn derived_data_dirs(project: &Path) -> Vec<PathBuf> {
+    let mut dirs: Vec<PathBuf> = build_cache_dirs(project)
+        .into_iter()
+        .filter(|(_, is_main, _, legacy)| *is_main || !legacy)
+        .map(|(_, _, cache, _)| cache)
+        .collect();
+
+    let Some(name) = project.file_name().map(|n| n.to_string_lossy().to_string()) else {
+        return dirs;
+    };
+    let Some(xcode_dir) = crate::paths::home_dir().ok().map(|h| h.join("Library/Developer/Xcode/DerivedData")) else {
+        return dirs;
+    };
+    let Ok(entries) = std::fs::read_dir(&xcode_dir) else {
+        return dirs;
+    };
+
+    // Xcode names these `<Name>-<hash>` and records the workspace it built
+    let prefix = format!("{}-", name);
+    for entry in entries.flatten() {
+        if !entry.file_name().to_string_lossy().starts_with(&prefix) {
+            continue;
+        }
+        let workspace = plist::Value::from_file(entry.path().join("info.plist"))
+            .ok()
+            .and_then(|v| v.as_dictionary()?.get("WorkspacePath")?.as_string().map(PathBuf::from));
+        if workspace.is_some_and(|w| w.starts_with(project)) {
+            dirs.push(entry.path());
+        }
+    }
+    dirs
+}
+
+/// A session worktree made by `create_session_worktree`: a registered worktree
+/// carrying a session ID, or a `session-*-worktree` sibling of the project
+struct WorktreeDir {
+    path: PathBuf,
+    registered: bool,
+    in_use: bool,
+}
+
+fn worktree_dirs(project: &Path, in_use: &[String]) -> Vec<WorktreeDir> {
+    let Some(parent) = project.parent() else {
+        return vec![];
+    };
+    let worktrees = crate::read_worktrees(&project.to_string_lossy()).unwrap_or_default();
+    let canonical = |path: &str| std::fs::canonicalize(path).unwrap_or_else(|_| PathBuf::from(path));
+    let registered: Vec<PathBuf> = worktrees.iter().filter(|wt| !wt.is_main).map(|wt| canonical(&wt.path)).collect();
+    // Named session worktrees don't follow the session-* pattern
+    let named: Vec<PathBuf> = worktrees
+        .iter()
+        .filter(|wt| !wt.is_main && wt.session_id.is_some())
+        .map(|wt| canonical(&wt.path))
+        .filter(|path| path.is_dir())
+        .collect();
+    let in_use: Vec<PathBuf> = in_use.iter().map(|dir| canonical(dir)).collect();
+
+    let mut paths: Vec<PathBuf> = std::fs::read_dir(parent)
+        .into_iter()
+        .flatten()
+        .flatten()
+        .filter(|e| {
+            let name = e.file_name().to_string_lossy().to_string();
+            name.starts_with("session-") && name.ends_with("-worktree") && e.path().is_dir()
+        })
+        .map(|e| std::fs::canonicalize(e.path()).unwrap_or_else(|_| e.path()))
+        .chain(named)
+        .collect();
+    paths.sort();
+    paths.dedup();
+    paths
+        .into_iter()
+        .map(|path| WorktreeDir {
+            registered: registered.contains(&path),
+            in_use: in_use.contains(&path),
+            path,
+        })
+        .collect()
+}
+
+fn canonical_project(project_path: &str) -> Result<PathBuf, String> {
+    std::fs::canonicalize(project_path).map_err(|e| format!("Failed to resolve {}: {}", project_path, e))
+}
+
+/// nocur's build caches for the project's main checkout and each worktree, sized
+pub fn list_build_caches(project_path: &str) -> Result<Vec<BuildCache>, String> {
+    let project = canonical_project(project_path)?;
+    let deadline = Instant::now() + SIZING_TIMEOUT;
+    Ok(build_cache_dirs(&project)
+        .into_iter()
+        .map(|(checkout, is_main, cache, legacy)| {
+            let (bytes, complete) = size_of(&cache, deadline, None);
+            BuildCache {
+                working_dir: checkout.to_string_lossy().to_string(),
+                is_main,
+                path: cache.to_string_lossy().to_string(),
+                bytes,
+                partial: !complete,
+                legacy,
+            }
+        })
+        .collect())
+}
+
+/// Itemized disk usage for a project. `in_use` is every working directory an open
+/// session is mapped to; worktrees outside it are reported as stale.
+pub fn project_storage_report(project_path: &str, in_use: &[String]) -> Result<StorageReport, String> {
+    let project = canonical_project(project_path)?;
+    let deadline = Instant::now() + SIZING_TIMEOUT;
+    let mut items = Vec::new();
+
+    let mut push = |category: &str, path: &Path, skip: Option<&Path>, stale: bool, note: Option<String>| {
+        let (bytes, complete) = size_of(path, deadline, skip);
+        items.push(StorageItem {
+            category: category.to_string(),
+            path: path.to_string_lossy().to_string(),
+            bytes,
+            partial: !complete,
+            stale,
+            note,
+        });
+    };
+
+    for dir in derived_data_dirs(&project).into_iter().filter(|d| d.is_dir()) {
+        let logs = dir.join(LOGS_DIR);
+        push("derivedData", &dir, Some(&logs), false, None);
+        if logs.is_dir() {
+            push("logs", &logs, None, false, None);
+        }
+    }
+
+    for worktree in worktree_dirs(&project, in_use) {
+        let note = if !worktree.registered {
+            Some("Not registered with git".to_string())
+        } else if worktree.in_use {
+            Some("In use by an open session".to_string())
+        } else {
+            None
+        };
+        push("worktree", &worktree.path, None, !worktree.in_use, note);
+    }
+
+    let recordings = paths::recordings_dir();
+    if recordings.is_dir() {
+        push("recordings", &recordings, None, false, Some("Shared by all projects".to_string()));
+    }
+
+    Ok(StorageReport {
+        project_path: project.to_string_lossy().to_string(),
+        total_bytes: items.iter().map(|i| i.bytes).sum(),
+        partial: items.iter().any(|i| i.partial),
+        items,
+    })
+}
+
+/// Bytes under `path`, as far as the walk gets within `SIZING_TIMEOUT`
+pub(crate) fn path_size(path: &Path) -> u64 {
+    size_of(path, Instant::now() + SIZING_TIMEOUT, None).0
+}
+
+/// Result of removing one location: bytes freed, or why it failed
+pub(crate) fn remove_path(path: &Path) -> Result<u64, String> {
+    let (bytes, _) = size_of(path, Instant::now() + SIZING_TIMEOUT, None);
+    let result = if path.is_dir() { std::fs::remove_dir_all(path) } else { std::fs::remove_file(path) };
+    result
+        .map(|_| bytes)
+        .map_err(|e| format!("Failed to remove {}: {}", path.display(), e))
+}
+
+/// One location `cleanup_project_storage` removes
+#[derive(Debug, Clone)]
+pub enum CleanupStep {
+    Remove(PathBuf),
+    /// A worktree git knows about, removed with `git worktree remove`
+    GitWorktree(PathBuf),
+    /// A session worktree directory git doesn't know about
+    LooseWorktree(PathBuf),
+}
+
+impl CleanupStep {
+    pub fn path(&self) -> &Path {
+        match self {
+            CleanupStep::Remove(path) | CleanupStep::GitWorktree(path) | CleanupStep::LooseWorktree(path) => path,
+        }
+    }
+}
+
+/// What cleaning the selected categories removes; both the cleanup and its dry run follow this
+pub fn cleanup_plan(project: &Path, options: &CleanupOptions, in_use: &[String]) -> Vec<CleanupStep> {
+    let mut steps = Vec::new();
+    if options.derived_data || options.logs {
+        for dir in derived_data_dirs(project).into_iter().filter(|d| d.is_dir()) {
+            let logs = dir.join(LOGS_DIR);
+            if options.derived_data && options.logs {
+                steps.push(CleanupStep::Remove(dir));
+                continue;
+            }
+            if options.logs && logs.is_dir() {
+                steps.push(CleanupStep::Remove(logs.clone()));
+            }
+            if options.derived_data {
+                // Keep the logs folder when only build products were asked for
+                for entry in std::fs::read_dir(&dir).into_iter().flatten().flatten() {
+                    if entry.path() != logs {
+                        steps.push(CleanupStep::Remove(entry.path()));
+                    }
+                }
+            }
+        }
+    }
+
+    if options.stale_worktrees {
+        for worktree in worktree_dirs(project, in_use).into_iter().filter(|w| !w.in_use) {
+            steps.push(match worktree.registered {
+                true => CleanupStep::GitWorktree(worktree.path),
+                false => CleanupStep::LooseWorktree(worktree.path),
+            });
+        }
+    }
+
+    if options.recordings {
+        let recordings = paths::recordings_dir();
+        if recordings.is_dir() {
+            steps.push(CleanupStep::Remove(recordings));
+        }
+    }
+    steps
+}
+
+/// What `cleanup_project_storage` would remove, without removing it
+pub fn cleanup_dry_run(project_path: &str, options: &CleanupOptions, in_use: &[String]) -> Result<DryRunReport, String> {
+    let project = canonical_project(project_path)?;
+    let paths = cleanup_plan(&project, options, in_use).iter().map(|step| step.path().to_path_buf()).collect();
+    Ok(DryRunReport::deleting(paths))
+}
+
+/// Delete the selected categories. Stale worktrees go through `git worktree remove`
+/// when git knows about them and are deleted directly when it doesn't.
+pub fn cleanup_project_storage(
+    project_path: &str,
+    options: &CleanupOptions,
+    in_use: &[String],
+) -> Result<CleanupResult, String> {
+    let project = canonical_project(project_path)?;
+    let mut result = CleanupResult {
+        bytes_reclaimed: 0,
+        removed: vec![],
+        errors: vec![],
+    };
+
+    let mut pruned = false;
+    for step in cleanup_plan(&project, options, in_use) {
+        let outcome = match &step {
+            CleanupStep::Remove(path) => remove_path(path),
+            CleanupStep::GitWorktree(path) => {
+                let (bytes, _) = size_of(path, Instant::now() + SIZING_TIMEOUT, None);
+                crate::remove_git_worktree(&path.to_string_lossy(), true, Some(&project)).map(|_| bytes)
+            }
+            CleanupStep::LooseWorktree(path) => {
+                pruned = true;
+                remove_path(path)
+            }
+        };
+        match outcome {
+            Ok(bytes) => {
+                result.bytes_reclaimed += bytes;
+                result.removed.push(step.path().to_string_lossy().to_string());
+            }
+            Err(e) => result.errors.push(e),
+        }
+    }
+    // Drop git's bookkeeping for worktree directories deleted by hand
+    if pruned {
+        let _ = std::process::Command::new("git")
+            .args(["worktree", "prune"])
+            .current_dir(&project)
+            .output();
+    }
+
+    Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cleanup_removes_what_its_dry_run_reports() {
+        let project = tempfile::tempdir().unwrap();
+        let derived_data = paths::legacy_derived_data_dir(project.path());
+        std::fs::create_dir_all(derived_data.join("Build/Products/Debug-iphonesimulator")).unwrap();
+        std::fs::create_dir_all(derived_data.join("ModuleCache.noindex")).unwrap();
+        std::fs::create_dir_all(derived_data.join(LOGS_DIR)).unwrap();
+        std::fs::write(derived_data.join("Build/Products/Debug-iphonesimulator/App"), "binary").unwrap();
+        let project_path = project.path().to_string_lossy();
+        let options = CleanupOptions { derived_data: true, ..Default::default() };
+
+        let report = cleanup_dry_run(&project_path, &options, &[]).unwrap();
+        let result = cleanup_project_storage(&project_path, &options, &[]).unwrap();
+
+        let mut planned: Vec<String> = report.would_delete.iter().map(|p| p.to_string_lossy().to_string()).collect();
+        let mut removed = result.removed.clone();
+        planned.sort();
+        removed.sort();
+        assert_eq!(planned.len(), 2);
+        assert_eq!(planned, removed);
+        assert!(result.errors.is_empty());
+        assert_eq!(report.bytes_affected, result.bytes_reclaimed);
+        // Only build products were asked for
+        assert!(derived_data.join(LOGS_DIR).is_dir());
+    }
+}
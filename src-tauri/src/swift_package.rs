@@ -0,0 +1,70 @@
+//! Building a project that's only a Package.swift
+//!
+//! A package without an Xcode project is built with `swift build`, unless its
+//! manifest lists iOS among its platforms: then it's built for the simulator
+//! or device with `xcodebuild`, which opens Package.swift itself and names
+//! the scheme after the package. Either way the diagnostics are swiftc's
+//! `file.swift:12:5: error: ...`, which `parse_build_errors` already reads.
+//! There's no app to install.
+
+use regex::Regex;
+use std::path::Path;
+use std::process::Command;
+
+/// True for a directory with a Package.swift and no .xcodeproj, .xcworkspace
+/// or Tuist manifest (whose dependencies can live in a Package.swift)
+pub fn is_package(project_dir: &Path) -> bool {
+    if !project_dir.join("Package.swift").exists() || project_dir.join("Project.swift").exists() {
+        return false;
+    }
+    let has_project = std::fs::read_dir(project_dir)
+        .map(|entries| {
+            entries.filter_map(|e| e.ok()).any(|e| {
+                e.path().extension().is_some_and(|ext| ext == "xcodeproj" || ext == "xcworkspace")
+            })
+        })
+        .unwrap_or(false);
+    !has_project
+}
+
+fn manifest(project_dir: &Path) -> String {
+    std::fs::read_to_string(project_dir.join("Package.swift")).unwrap_or_default()
+}
+
+/// The `name:` in `Package(...)`, else the directory's name
+pub fn package_name(project_dir: &Path) -> String {
+    let manifest = manifest(project_dir);
+    Regex::new(r#"Package\s*\(\s*name:\s*"([^"]+)""#)
+        .ok()
+        .and_then(|re| re.captures(&manifest).map(|caps| caps[1].to_string()))
+        .or_else(|| project_dir.file_name().map(|n| n.to_string_lossy().to_string()))
+        .unwrap_or_else(|| "Package".to_string())
+}
+
+/// Whether the manifest's `platforms:` include iOS
+pub fn targets_ios(project_dir: &Path) -> bool {
+    manifest(project_dir).contains(".iOS(")
+}
+
+/// `swift build`'s `-c`: "debug" or "release"
+pub fn swift_configuration(configuration: &str) -> Result<&'static str, String> {
+    match configuration.to_lowercase().as_str() {
+        "debug" => Ok("debug"),
+        "release" => Ok("release"),
+        _ => Err(format!(
+            "Build configuration '{}' doesn't exist for a Swift package; available: Debug, Release",
+            configuration
+        )),
+    }
+}
+
+/// `swift build` with its build directory under `derived_data_path`
+pub fn swift_build_command(configuration: &str, derived_data_path: &Path, offline: bool) -> Result<Command, String> {
+    let mut cmd = Command::new("swift");
+    cmd.args(["build", "-c", swift_configuration(configuration)?]);
+    cmd.arg("--scratch-path").arg(derived_data_path.join("SwiftPM"));
+    if offline {
+        cmd.arg("--skip-update");
+    }
+    Ok(cmd)
+}
@@ -0,0 +1,403 @@
+//! Lightweight Swift symbol index
+//!
+//! Extracts top-level declarations (class/struct/enum/protocol/extension/func) with a
+//! line-based scanner that tracks brace depth, so the agent can jump to a definition
+//! without grepping the whole project. Each file's entry is keyed by its mtime, and a
+//! JSON cache under ~/.nocur/symbol-index lets rebuilds reparse only changed files.
+
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+const DEFAULT_QUERY_LIMIT: usize = 50;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Symbol {
+    pub name: String,
+    pub kind: String,
+    /// Path relative to the project root
+    pub file: String,
+    pub line: usize,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct FileEntry {
+    mtime: u64,
+    symbols: Vec<Symbol>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ProjectIndex {
+    built_at: u64,
+    files: HashMap<String, FileEntry>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SymbolIndexReport {
+    pub project_path: String,
+    pub file_count: usize,
+    pub symbol_count: usize,
+    /// Files parsed in this build (everything else came from the cache)
+    pub reparsed_files: usize,
+    pub removed_files: usize,
+    /// Swift files evicted to iCloud; their symbols come from the cache when it has them
+    pub cloud_placeholders: usize,
+    pub built_at: u64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SymbolIndexStatus {
+    pub indexed: bool,
+    pub file_count: usize,
+    pub symbol_count: usize,
+    pub built_at: Option<u64>,
+    /// Swift files added, changed or deleted since the last build
+    pub stale_files: usize,
+}
+
+/// Per-project indexes kept in memory for the app's lifetime
+#[derive(Default)]
+pub struct SymbolIndexState {
+    indexes: HashMap<String, ProjectIndex>,
+}
+
+fn declaration_regex() -> &'static Regex {
+    static RE: OnceLock<Regex> = OnceLock::new();
+    RE.get_or_init(|| {
+        Regex::new(
+            r"^(?:(?:@\w+(?:\([^)]*\))?|public|private|fileprivate|internal|package|open|final|static|nonisolated|indirect)\s+)*(class|struct|enum|protocol|extension|func)\s+([A-Za-z_][A-Za-z0-9_.]*)",
+        )
+        .unwrap()
+    })
+}
+
+fn now_secs() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs()
+}
+
+fn file_mtime(path: &Path) -> u64 {
+    fs::metadata(path)
+        .and_then(|m| m.modified())
+        .ok()
+        .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+/// Net brace depth change for a line, skipping string literals and comments.
+/// `in_block_comment` carries `/* ... */` state across lines.
+fn brace_delta(line: &str, in_block_comment: &mut bool) -> i32 {
+    let mut delta = 0;
+    let mut in_string = false;
+    let mut chars = line.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        if *in_block_comment {
+            if c == '*' && chars.peek() == Some(&'/') {
+                chars.next();
+                *in_block_comment = false;
+            }
+            continue;
+        }
+        if in_string {
+            match c {
+                '\\' => {
+                    chars.next();
+                }
+                '"' => in_string = false,
+                _ => {}
+            }
+            continue;
+        }
+        match c {
+            '"' => in_string = true,
+            '/' if chars.peek() == Some(&'/') => break,
+            '/' if chars.peek() == Some(&'*') => {
+                chars.next();
+                *in_block_comment = true;
+            }
+            '{' => delta += 1,
+            '}' => delta -= 1,
+            _ => {}
+        }
+    }
+    delta
+}
+
+fn parse_swift_symbols(content: &str, relative_path: &str) -> Vec<Symbol> {
+    let re = declaration_regex();
+    let mut symbols = Vec::new();
+    let mut depth: i32 = 0;
+    let mut in_block_comment = false;
+    let mut in_multiline_string = false;
+
+    for (idx, line) in content.lines().enumerate() {
+        let trimmed = line.trim();
+
+        // Multi-line string literals can contain anything; skip them wholesale
+        if trimmed.matches("\"\"\"").count() % 2 == 1 {
+            in_multiline_string = !in_multiline_string;
+            continue;
+        }
+        if in_multiline_string {
+            continue;
+        }
+
+        if depth == 0 && !in_block_comment {
+            if let Some(caps) = re.captures(trimmed) {
+                symbols.push(Symbol {
+                    name: caps[2].to_string(),
+                    kind: caps[1].to_string(),
+                    file: relative_path.to_string(),
+                    line: idx + 1,
+                });
+            }
+        }
+
+        depth = (depth + brace_delta(line, &mut in_block_comment)).max(0);
+    }
+
+    symbols
+}
+
+fn is_swift(path: &Path) -> bool {
+    path.extension().and_then(|e| e.to_str()) == Some("swift")
+}
+
+/// Swift files in the project with their mtimes, plus the ones iCloud Drive
+/// has evicted (by their real relative paths), which can't be read until
+/// they're downloaded again
+fn scan_swift_files(project_path: &str) -> (HashMap<String, (PathBuf, u64)>, HashSet<String>) {
+    let root = Path::new(project_path);
+    let relative = |path: &Path| path.strip_prefix(root).unwrap_or(path).to_string_lossy().to_string();
+    let mut files = HashMap::new();
+    let mut evicted = HashSet::new();
+
+    for entry in crate::paths::project_walker(root).filter_map(|entry| entry.ok()) {
+        if !entry.file_type().map(|t| t.is_file()).unwrap_or(false) {
+            continue;
+        }
+        let path = entry.path();
+        match crate::paths::cloud_placeholder_target(path) {
+            Some(target) if is_swift(&target) => {
+                evicted.insert(relative(&target));
+            }
+            Some(_) => {}
+            None if is_swift(path) => {
+                files.insert(relative(path), (path.to_path_buf(), file_mtime(path)));
+            }
+            None => {}
+        }
+    }
+    // Both exist while a download is finishing
+    evicted.retain(|f| !files.contains_key(f));
+    (files, evicted)
+}
+
+fn cache_path(project_path: &str) -> PathBuf {
+    crate::paths::nocur_dir()
+        .join("symbol-index")
+        .join(format!("{}.json", crate::ace::generate_project_id(project_path)))
+}
+
+fn load_cached_index(project_path: &str) -> Option<ProjectIndex> {
+    let content = fs::read_to_string(cache_path(project_path)).ok()?;
+    serde_json::from_str(&content).ok()
+}
+
+fn save_cached_index(project_path: &str, index: &ProjectIndex) -> Result<(), String> {
+    let path = cache_path(project_path);
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).map_err(|e| format!("Failed to create symbol index directory: {}", e))?;
+    }
+    let content = serde_json::to_string(index).map_err(|e| format!("Failed to serialize symbol index: {}", e))?;
+    fs::write(&path, content).map_err(|e| format!("Failed to write symbol index: {}", e))
+}
+
+fn symbol_count(index: &ProjectIndex) -> usize {
+    index.files.values().map(|f| f.symbols.len()).sum()
+}
+
+/// Build or refresh a project's index, reparsing only files whose mtime changed
+pub fn build_symbol_index(
+    state: &parking_lot::Mutex<SymbolIndexState>,
+    project_path: &str,
+) -> Result<SymbolIndexReport, String> {
+    if !Path::new(project_path).is_dir() {
+        return Err(format!("Project directory not found: {}", project_path));
+    }
+
+    let previous = state
+        .lock()
+        .indexes
+        .get(project_path)
+        .cloned()
+        .or_else(|| load_cached_index(project_path))
+        .unwrap_or_default();
+
+    let (current, evicted) = scan_swift_files(project_path);
+    let removed_files = previous
+        .files
+        .keys()
+        .filter(|f| !current.contains_key(*f) && !evicted.contains(*f))
+        .count();
+
+    let mut reparsed_files = 0;
+    let mut files = HashMap::with_capacity(current.len());
+    // An evicted file hasn't changed, so what was indexed before still holds
+    for relative in &evicted {
+        if let Some(entry) = previous.files.get(relative) {
+            files.insert(relative.clone(), entry.clone());
+        }
+    }
+    for (relative, (path, mtime)) in current {
+        match previous.files.get(&relative) {
+            Some(entry) if entry.mtime == mtime => {
+                files.insert(relative, entry.clone());
+            }
+            _ => {
+                let Ok(content) = fs::read_to_string(&path) else {
+                    continue;
+                };
+                reparsed_files += 1;
+                let symbols = parse_swift_symbols(&content, &relative);
+                files.insert(relative, FileEntry { mtime, symbols });
+            }
+        }
+    }
+
+    let index = ProjectIndex {
+        built_at: now_secs(),
+        files,
+    };
+
+    if reparsed_files > 0 || removed_files > 0 {
+        if let Err(e) = save_cached_index(project_path, &index) {
+            log::warn!("{}", e);
+        }
+    }
+
+    let report = SymbolIndexReport {
+        project_path: project_path.to_string(),
+        file_count: index.files.len(),
+        symbol_count: symbol_count(&index),
+        reparsed_files,
+        removed_files,
+        cloud_placeholders: evicted.len(),
+        built_at: index.built_at,
+    };
+
+    state.lock().indexes.insert(project_path.to_string(), index);
+    Ok(report)
+}
+
+/// Size and freshness of a project's index without rebuilding it
+pub fn symbol_index_status(state: &parking_lot::Mutex<SymbolIndexState>, project_path: &str) -> SymbolIndexStatus {
+    let index = state
+        .lock()
+        .indexes
+        .get(project_path)
+        .cloned()
+        .or_else(|| load_cached_index(project_path));
+
+    let Some(index) = index else {
+        return SymbolIndexStatus {
+            indexed: false,
+            file_count: 0,
+            symbol_count: 0,
+            built_at: None,
+            stale_files: 0,
+        };
+    };
+
+    let (current, evicted) = scan_swift_files(project_path);
+    let changed = current
+        .iter()
+        .filter(|(relative, (_, mtime))| index.files.get(*relative).map(|f| f.mtime) != Some(*mtime))
+        .count();
+    let removed = index
+        .files
+        .keys()
+        .filter(|f| !current.contains_key(*f) && !evicted.contains(*f))
+        .count();
+
+    SymbolIndexStatus {
+        indexed: true,
+        file_count: index.files.len(),
+        symbol_count: symbol_count(&index),
+        built_at: Some(index.built_at),
+        stale_files: changed + removed,
+    }
+}
+
+/// Whether every character of `query` appears in `name` in order
+fn is_subsequence(query: &str, name: &str) -> bool {
+    let mut name_chars = name.chars();
+    query.chars().all(|q| name_chars.any(|c| c == q))
+}
+
+/// Prefix/fuzzy lookup by name, optionally restricted to one declaration kind.
+/// Builds the index first if this project hasn't been indexed yet.
+pub fn query_symbols(
+    state: &parking_lot::Mutex<SymbolIndexState>,
+    project_path: &str,
+    query: &str,
+    kind: Option<&str>,
+    limit: Option<usize>,
+) -> Result<Vec<Symbol>, String> {
+    let has_index = state.lock().indexes.contains_key(project_path);
+    if !has_index {
+        build_symbol_index(state, project_path)?;
+    }
+
+    let query = query.to_lowercase();
+    let state = state.lock();
+    let Some(index) = state.indexes.get(project_path) else {
+        return Ok(Vec::new());
+    };
+
+    let mut scored: Vec<(&Symbol, i32)> = index
+        .files
+        .values()
+        .flat_map(|f| f.symbols.iter())
+        .filter(|s| kind.map(|k| s.kind == k).unwrap_or(true))
+        .filter_map(|s| {
+            let name = s.name.to_lowercase();
+            let score = if query.is_empty() || name == query {
+                100  // Exact match (or no query: list everything)
+            } else if name.starts_with(&query) {
+                80  // Name starts with query
+            } else if name.contains(&query) {
+                60  // Name contains query
+            } else if is_subsequence(&query, &name) {
+                30  // Fuzzy match
+            } else {
+                return None;
+            };
+            Some((s, score))
+        })
+        .collect();
+
+    scored.sort_by(|a, b| {
+        b.1.cmp(&a.1)
+            .then(a.0.name.len().cmp(&b.0.name.len()))
+            .then(a.0.name.cmp(&b.0.name))
+    });
+
+    Ok(scored
+        .into_iter()
+        .take(limit.unwrap_or(DEFAULT_QUERY_LIMIT))
+        .map(|(s, _)| s.clone())
+        .collect())
+}
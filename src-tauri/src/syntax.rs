@@ -0,0 +1,374 @@
+//! Language detection and token classes for the diff viewer
+//!
+//! The webview doesn't ship a highlighter, so diffs come with a `language`
+//! from the file extension and, for Swift and JSON, the token classes of each
+//! line. The lexers are deliberately small: they know comments, strings,
+//! numbers, keywords and a few language specifics, which is what makes a diff
+//! readable. They step through the line's chars with bounds-checked lookups,
+//! so any input, however malformed, just gets fewer or odder classes.
+//!
+//! Ranges are in UTF-16 code units, which is how the frontend's strings count.
+
+use serde::Serialize;
+
+/// Language for a file path's extension, "text" when it isn't one we know
+pub fn language_for_path(path: &str) -> &'static str {
+    let file_name = path.rsplit('/').next().unwrap_or(path);
+    if file_name == "Podfile" || file_name == "Fastfile" || file_name == "Gemfile" {
+        return "ruby";
+    }
+    if file_name == "Package.resolved" {
+        return "json";
+    }
+    let Some((_, extension)) = file_name.rsplit_once('.') else {
+        return "text";
+    };
+    match extension.to_ascii_lowercase().as_str() {
+        "swift" => "swift",
+        "rs" => "rust",
+        "ts" | "tsx" => "typescript",
+        "js" | "jsx" | "mjs" | "cjs" => "javascript",
+        "json" => "json",
+        "plist" | "entitlements" => "plist",
+        "strings" | "stringsdict" => "strings",
+        "m" => "objective-c",
+        "mm" => "objective-cpp",
+        "h" | "c" => "c",
+        "cpp" | "cc" | "hpp" => "cpp",
+        "md" | "markdown" => "markdown",
+        "yml" | "yaml" => "yaml",
+        "toml" => "toml",
+        "xml" | "storyboard" | "xib" => "xml",
+        "html" | "htm" => "html",
+        "css" => "css",
+        "py" => "python",
+        "rb" => "ruby",
+        "sh" | "bash" | "zsh" => "shell",
+        "xcconfig" => "xcconfig",
+        "pbxproj" => "pbxproj",
+        _ => "text",
+    }
+}
+
+#[derive(Debug, Clone, Serialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct TokenRange {
+    pub start: usize,
+    pub end: usize,
+    /// "keyword", "type", "string", "number", "comment", "attribute",
+    /// "key" (JSON object keys) or "literal" (true/false/null)
+    pub class: &'static str,
+}
+
+/// What an unfinished construct on one line carries into the next
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum LexState {
+    #[default]
+    Normal,
+    /// Inside a Swift block comment, which nests
+    BlockComment(u32),
+    /// Inside a Swift `"""` string
+    MultilineString,
+}
+
+/// Languages `highlight_line` has a lexer for
+pub fn has_lexer(language: &str) -> bool {
+    matches!(language, "swift" | "json")
+}
+
+/// Token classes for one line, continuing from `state` and updating it.
+/// None for languages without a lexer.
+pub fn highlight_line(language: &str, line: &str, state: &mut LexState) -> Option<Vec<TokenRange>> {
+    let chars: Vec<char> = line.chars().collect();
+    let ranges = match language {
+        "swift" => lex_swift(&chars, state),
+        "json" => lex_json(&chars),
+        _ => return None,
+    };
+    Some(to_utf16(&chars, ranges))
+}
+
+/// Char-index ranges to UTF-16 ones
+fn to_utf16(chars: &[char], ranges: Vec<(usize, usize, &'static str)>) -> Vec<TokenRange> {
+    let mut offsets = Vec::with_capacity(chars.len() + 1);
+    let mut offset = 0;
+    offsets.push(0);
+    for c in chars {
+        offset += c.len_utf16();
+        offsets.push(offset);
+    }
+    ranges
+        .into_iter()
+        .filter(|(start, end, _)| start < end)
+        .map(|(start, end, class)| TokenRange {
+            start: offsets[start.min(chars.len())],
+            end: offsets[end.min(chars.len())],
+            class,
+        })
+        .collect()
+}
+
+fn at(chars: &[char], i: usize) -> Option<char> {
+    chars.get(i).copied()
+}
+
+fn starts_with(chars: &[char], i: usize, pattern: &str) -> bool {
+    pattern.chars().enumerate().all(|(k, p)| at(chars, i + k) == Some(p))
+}
+
+fn is_ident_start(c: char) -> bool {
+    c.is_alphabetic() || c == '_'
+}
+
+fn is_ident_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_'
+}
+
+fn ident_end(chars: &[char], mut i: usize) -> usize {
+    while at(chars, i).is_some_and(is_ident_char) {
+        i += 1;
+    }
+    i
+}
+
+/// End of a number starting at `i`: digits, hex and exponent letters,
+/// underscores, and a dot when a digit follows it
+fn number_end(chars: &[char], mut i: usize) -> usize {
+    while let Some(c) = at(chars, i) {
+        let continues = c.is_ascii_alphanumeric()
+            || c == '_'
+            || (c == '.' && at(chars, i + 1).is_some_and(|n| n.is_ascii_digit()))
+            || ((c == '-' || c == '+') && matches!(at(chars, i.wrapping_sub(1)), Some('e' | 'E' | 'p' | 'P')));
+        if !continues {
+            break;
+        }
+        i += 1;
+    }
+    i
+}
+
+/// End of a quoted string starting at `i` (the quote), past the closing quote
+/// or at the end of the line
+fn string_end(chars: &[char], mut i: usize, quote: char) -> usize {
+    i += 1;
+    while let Some(c) = at(chars, i) {
+        i += 1;
+        if c == '\\' {
+            i += 1;
+        } else if c == quote {
+            break;
+        }
+    }
+    i.min(chars.len())
+}
+
+const SWIFT_KEYWORDS: &[&str] = &[
+    "actor", "any", "as", "associatedtype", "async", "await", "break", "case", "catch", "class", "continue",
+    "convenience", "default", "defer", "deinit", "didSet", "do", "dynamic", "else", "enum", "extension",
+    "fallthrough", "false", "fileprivate", "final", "for", "func", "get", "guard", "if", "import", "in",
+    "indirect", "init", "inout", "internal", "is", "lazy", "let", "mutating", "nil", "nonisolated",
+    "nonmutating", "open", "operator", "optional", "override", "private", "protocol", "public", "repeat",
+    "required", "rethrows", "return", "self", "Self", "set", "some", "static", "struct", "subscript", "super",
+    "switch", "throw", "throws", "true", "try", "typealias", "unowned", "var", "weak", "where", "while",
+    "willSet",
+];
+
+/// Scan a block comment from `i` at nesting `depth` to its end or the end of
+/// the line; returns where it stopped and the depth still open there
+fn block_comment_end(chars: &[char], mut i: usize, mut depth: u32) -> (usize, u32) {
+    while i < chars.len() && depth > 0 {
+        if starts_with(chars, i, "*/") {
+            depth -= 1;
+            i += 2;
+        } else if starts_with(chars, i, "/*") {
+            depth += 1;
+            i += 2;
+        } else {
+            i += 1;
+        }
+    }
+    (i.min(chars.len()), depth)
+}
+
+/// Scan a `"""` string body from `i`; returns where it stopped and whether
+/// the closing quotes were found
+fn multiline_string_end(chars: &[char], mut i: usize) -> (usize, bool) {
+    while i < chars.len() {
+        if starts_with(chars, i, "\"\"\"") {
+            return (i + 3, true);
+        }
+        i += if at(chars, i) == Some('\\') { 2 } else { 1 };
+    }
+    (chars.len(), false)
+}
+
+fn lex_swift(chars: &[char], state: &mut LexState) -> Vec<(usize, usize, &'static str)> {
+    let mut ranges = Vec::new();
+    let mut i = 0;
+    while i < chars.len() {
+        match *state {
+            LexState::BlockComment(depth) => {
+                let (end, depth) = block_comment_end(chars, i, depth);
+                *state = if depth == 0 { LexState::Normal } else { LexState::BlockComment(depth) };
+                ranges.push((i, end, "comment"));
+                i = end;
+                continue;
+            }
+            LexState::MultilineString => {
+                let (end, closed) = multiline_string_end(chars, i);
+                if closed {
+                    *state = LexState::Normal;
+                }
+                ranges.push((i, end, "string"));
+                i = end;
+                continue;
+            }
+            LexState::Normal => {}
+        }
+
+        let c = chars[i];
+        if starts_with(chars, i, "//") {
+            ranges.push((i, chars.len(), "comment"));
+            break;
+        } else if starts_with(chars, i, "/*") {
+            let (end, depth) = block_comment_end(chars, i + 2, 1);
+            if depth > 0 {
+                *state = LexState::BlockComment(depth);
+            }
+            ranges.push((i, end, "comment"));
+            i = end;
+        } else if starts_with(chars, i, "\"\"\"") {
+            let (end, closed) = multiline_string_end(chars, i + 3);
+            if !closed {
+                *state = LexState::MultilineString;
+            }
+            ranges.push((i, end, "string"));
+            i = end;
+        } else if c == '"' {
+            let end = string_end(chars, i, '"');
+            ranges.push((i, end, "string"));
+            i = end;
+        } else if c.is_ascii_digit() {
+            let end = number_end(chars, i);
+            ranges.push((i, end, "number"));
+            i = end;
+        } else if (c == '@' || c == '#') && at(chars, i + 1).is_some_and(is_ident_start) {
+            let end = ident_end(chars, i + 1);
+            ranges.push((i, end, if c == '@' { "attribute" } else { "keyword" }));
+            i = end;
+        } else if is_ident_start(c) {
+            let end = ident_end(chars, i);
+            let word: String = chars[i..end].iter().collect();
+            if SWIFT_KEYWORDS.contains(&word.as_str()) {
+                ranges.push((i, end, "keyword"));
+            } else if c.is_uppercase() {
+                ranges.push((i, end, "type"));
+            }
+            i = end;
+        } else {
+            i += 1;
+        }
+    }
+    ranges
+}
+
+fn lex_json(chars: &[char]) -> Vec<(usize, usize, &'static str)> {
+    let mut ranges = Vec::new();
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        if c == '"' {
+            let end = string_end(chars, i, '"');
+            let mut next = end;
+            while at(chars, next).is_some_and(char::is_whitespace) {
+                next += 1;
+            }
+            let class = if at(chars, next) == Some(':') { "key" } else { "string" };
+            ranges.push((i, end, class));
+            i = end;
+        } else if c.is_ascii_digit() || (c == '-' && at(chars, i + 1).is_some_and(|n| n.is_ascii_digit())) {
+            let end = number_end(chars, i + 1);
+            ranges.push((i, end, "number"));
+            i = end;
+        } else if c.is_ascii_alphabetic() {
+            let end = ident_end(chars, i);
+            let word: String = chars[i..end].iter().collect();
+            if matches!(word.as_str(), "true" | "false" | "null") {
+                ranges.push((i, end, "literal"));
+            }
+            i = end;
+        } else {
+            i += 1;
+        }
+    }
+    ranges
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MALFORMED: &[(&str, &str)] = &[
+        ("swift", r#"let s = "unterminated"#),
+        ("swift", r#"let s = "escape at end\"#),
+        ("swift", r#"let s = """"#),
+        ("swift", "/* unterminated /* nested"),
+        ("swift", "*/ stray close */"),
+        ("swift", "@"),
+        ("swift", "#"),
+        ("swift", "0x"),
+        ("swift", "1e"),
+        ("swift", r#"let 名前 = "ü🐶" // コメント"#),
+        ("swift", "let flag = \"🇯🇵\" /* 🐶 */ + 3.14e-2"),
+        ("swift", "`"),
+        ("json", r#"{"key": "unterminated"#),
+        ("json", r#"{"key\"#),
+        ("json", r#"{"🐶": "é", "n": -1.5e+3, "t": tru"#),
+        ("json", "[nul"),
+        ("json", "-"),
+        ("json", "\""),
+    ];
+
+    fn check(language: &str, line: &str, state: &mut LexState) {
+        let len = line.encode_utf16().count();
+        let ranges = highlight_line(language, line, state).unwrap();
+        for range in &ranges {
+            assert!(range.start < range.end && range.end <= len, "{:?} out of bounds in {:?}", range, line);
+        }
+    }
+
+    #[test]
+    fn malformed_input_stays_in_bounds() {
+        let states = [LexState::Normal, LexState::BlockComment(2), LexState::MultilineString];
+        for (language, line) in MALFORMED {
+            // Every truncation of the line, from every state a line can start in
+            let chars: Vec<char> = line.chars().collect();
+            for end in 0..=chars.len() {
+                let truncated: String = chars[..end].iter().collect();
+                for state in states {
+                    check(language, &truncated, &mut state.clone());
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn ranges_count_utf16_units() {
+        let mut state = LexState::Normal;
+        let ranges = highlight_line("swift", r#"let 🐶 = "é""#, &mut state).unwrap();
+        let string = ranges.iter().find(|r| r.class == "string").unwrap();
+        // "let " is 4 units and the dog 2
+        assert_eq!((string.start, string.end), (9, 12));
+    }
+
+    #[test]
+    fn block_comments_carry_across_lines() {
+        let mut state = LexState::Normal;
+        highlight_line("swift", "/* outer /* inner */", &mut state);
+        assert_eq!(state, LexState::BlockComment(1));
+        let ranges = highlight_line("swift", "still comment */ let x = 1", &mut state).unwrap();
+        assert_eq!(state, LexState::Normal);
+        assert_eq!((ranges[0].start, ranges[0].end, ranges[0].class), (0, 16, "comment"));
+        assert!(ranges.iter().any(|r| r.class == "keyword"));
+    }
+}
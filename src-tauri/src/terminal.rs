@@ -0,0 +1,205 @@
+//! Terminal sessions owned by the backend
+//!
+//! Shells run under PTYs held here rather than by the webview, so they survive a
+//! reload: output streams out as `terminal-data` events tagged with the terminal
+//! id, and the last 64 KB is kept so a reloaded frontend can redraw and reattach.
+//! Every session is killed when the app exits.
+
+use parking_lot::Mutex;
+use portable_pty::{native_pty_system, Child, CommandBuilder, MasterPty, PtySize};
+use serde::Serialize;
+use std::collections::{HashMap, VecDeque};
+use std::io::{Read, Write};
+use std::sync::Arc;
+use tauri::AppHandle;
+
+/// Output kept per terminal for reattaching
+const SCROLLBACK_BYTES: usize = 64 * 1024;
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TerminalData {
+    pub id: String,
+    pub data: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TerminalExit {
+    pub id: String,
+}
+
+struct TerminalSession {
+    master: Box<dyn MasterPty + Send>,
+    writer: Box<dyn Write + Send>,
+    child: Box<dyn Child + Send + Sync>,
+    scrollback: Arc<Mutex<VecDeque<u8>>>,
+}
+
+#[derive(Default)]
+pub struct TerminalRegistry {
+    terminals: Mutex<HashMap<String, TerminalSession>>,
+}
+
+fn pty_size(cols: u16, rows: u16) -> PtySize {
+    PtySize {
+        rows: rows.max(1),
+        cols: cols.max(1),
+        pixel_width: 0,
+        pixel_height: 0,
+    }
+}
+
+/// Length of the longest prefix of `bytes` that doesn't end inside a UTF-8
+/// sequence; PTY reads can split a multi-byte character
+fn complete_utf8_len(bytes: &[u8]) -> usize {
+    match std::str::from_utf8(bytes) {
+        Ok(_) => bytes.len(),
+        // error_len() is None only when the input ends mid-character
+        Err(e) if e.error_len().is_none() => e.valid_up_to(),
+        Err(_) => bytes.len(),
+    }
+}
+
+impl TerminalRegistry {
+    /// Spawn `shell` as a login shell in `working_dir` and start streaming its output
+    pub fn create(
+        &self,
+        working_dir: &str,
+        shell: &str,
+        cols: u16,
+        rows: u16,
+        env: HashMap<String, String>,
+        app_handle: AppHandle,
+    ) -> Result<String, String> {
+        let pair = native_pty_system()
+            .openpty(pty_size(cols, rows))
+            .map_err(|e| format!("Failed to open PTY: {}", e))?;
+
+        let mut cmd = CommandBuilder::new(shell);
+        if cfg!(unix) {
+            cmd.arg("-l");
+        }
+        cmd.cwd(working_dir);
+        cmd.env_clear();
+        for (key, value) in env {
+            cmd.env(key, value);
+        }
+        cmd.env("TERM", "xterm-256color");
+        cmd.env("COLORTERM", "truecolor");
+
+        let child = pair
+            .slave
+            .spawn_command(cmd)
+            .map_err(|e| format!("Failed to spawn {}: {}", shell, e))?;
+        // The child holds its own handle; keeping ours would stop EOF arriving on exit
+        drop(pair.slave);
+
+        let mut reader = pair
+            .master
+            .try_clone_reader()
+            .map_err(|e| format!("Failed to read from PTY: {}", e))?;
+        let writer = pair
+            .master
+            .take_writer()
+            .map_err(|e| format!("Failed to write to PTY: {}", e))?;
+
+        let id = format!("terminal-{}", uuid::Uuid::new_v4());
+        let scrollback = Arc::new(Mutex::new(VecDeque::with_capacity(SCROLLBACK_BYTES)));
+
+        self.terminals.lock().insert(
+            id.clone(),
+            TerminalSession {
+                master: pair.master,
+                writer,
+                child,
+                scrollback: scrollback.clone(),
+            },
+        );
+
+        let terminal_id = id.clone();
+        std::thread::spawn(move || {
+            let mut buf = [0u8; 8192];
+            let mut pending: Vec<u8> = Vec::new();
+            loop {
+                let n = match reader.read(&mut buf) {
+                    Ok(0) | Err(_) => break,
+                    Ok(n) => n,
+                };
+
+                {
+                    let mut scrollback = scrollback.lock();
+                    scrollback.extend(&buf[..n]);
+                    let excess = scrollback.len().saturating_sub(SCROLLBACK_BYTES);
+                    scrollback.drain(..excess);
+                }
+
+                pending.extend_from_slice(&buf[..n]);
+                let complete = complete_utf8_len(&pending);
+                if complete == 0 {
+                    continue;
+                }
+                let data = String::from_utf8_lossy(&pending[..complete]).to_string();
+                pending.drain(..complete);
+                crate::event_journal::emit_stamped(&app_handle, "terminal-data", TerminalData {
+                    id: terminal_id.clone(),
+                    data,
+                });
+            }
+            crate::event_journal::emit_stamped(&app_handle, "terminal-exit", TerminalExit { id: terminal_id });
+        });
+
+        Ok(id)
+    }
+
+    pub fn write(&self, id: &str, data: &str) -> Result<(), String> {
+        let mut terminals = self.terminals.lock();
+        let terminal = terminals.get_mut(id).ok_or_else(|| format!("Terminal {} not found", id))?;
+        terminal
+            .writer
+            .write_all(data.as_bytes())
+            .and_then(|_| terminal.writer.flush())
+            .map_err(|e| format!("Failed to write to terminal: {}", e))
+    }
+
+    pub fn resize(&self, id: &str, cols: u16, rows: u16) -> Result<(), String> {
+        let terminals = self.terminals.lock();
+        let terminal = terminals.get(id).ok_or_else(|| format!("Terminal {} not found", id))?;
+        terminal
+            .master
+            .resize(pty_size(cols, rows))
+            .map_err(|e| format!("Failed to resize terminal: {}", e))
+    }
+
+    /// The buffered tail of the terminal's output
+    pub fn scrollback(&self, id: &str) -> Result<String, String> {
+        let terminals = self.terminals.lock();
+        let terminal = terminals.get(id).ok_or_else(|| format!("Terminal {} not found", id))?;
+        let scrollback = terminal.scrollback.lock();
+        let (front, back) = scrollback.as_slices();
+        let bytes = [front, back].concat();
+        // Trimming the front can cut a character in half; skip to the next boundary
+        let start = bytes.iter().position(|b| (b & 0xC0) != 0x80).unwrap_or(bytes.len());
+        Ok(String::from_utf8_lossy(&bytes[start..]).to_string())
+    }
+
+    pub fn close(&self, id: &str) -> Result<(), String> {
+        let mut terminal = self
+            .terminals
+            .lock()
+            .remove(id)
+            .ok_or_else(|| format!("Terminal {} not found", id))?;
+        let _ = terminal.child.kill();
+        let _ = terminal.child.wait();
+        Ok(())
+    }
+
+    /// Kill every session; called on app exit
+    pub fn close_all(&self) {
+        let terminals: Vec<TerminalSession> = self.terminals.lock().drain().map(|(_, t)| t).collect();
+        for mut terminal in terminals {
+            let _ = terminal.child.kill();
+            let _ = terminal.child.wait();
+        }
+    }
+}
@@ -0,0 +1,142 @@
+//! Reading `xcodebuild test` output
+//!
+//! XCTest reports each test as `Test Case '-[Module.Class testName]' passed
+//! (0.001 seconds).` and a failed assertion as
+//! `/path/File.swift:12: error: -[Module.Class testName] : message`. Swift
+//! Testing prints `Test testName() passed after ...` and
+//! `Test testName() recorded an issue at File.swift:12:5: message`. Tests are
+//! identified the way `-only-testing` takes them, `Module/Class/testName`, so
+//! a failure can be re-run on its own.
+
+use regex::Regex;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::sync::OnceLock;
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TestFailure {
+    /// In `-only-testing` form, e.g. "AppTests/LoginTests/testInvalidPassword"
+    pub test: String,
+    pub messages: Vec<String>,
+    /// Where the first failure was recorded
+    pub file: Option<String>,
+    pub line: Option<u32>,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TestCounts {
+    pub passed: u32,
+    pub failed: u32,
+    pub skipped: u32,
+}
+
+struct Patterns {
+    xctest_case: Regex,
+    swift_testing_case: Regex,
+    xctest_failure: Regex,
+    swift_testing_issue: Regex,
+}
+
+fn patterns() -> Option<&'static Patterns> {
+    static PATTERNS: OnceLock<Option<Patterns>> = OnceLock::new();
+    PATTERNS
+        .get_or_init(|| {
+            Some(Patterns {
+                xctest_case: Regex::new(r"Test Case '(-\[[^\]]+\])' (passed|failed|skipped)").ok()?,
+                swift_testing_case: Regex::new(r"(?:^|\s)Test (\w+)\(\) (passed|failed|skipped)").ok()?,
+                xctest_failure: Regex::new(r"^(.+?):(\d+): error: (-\[[^\]]+\]) : (.+)$").ok()?,
+                swift_testing_issue: Regex::new(r"(?:^|\s)Test (\w+)\(\) recorded an issue at (.+?):(\d+):\d+: (.+)$")
+                    .ok()?,
+            })
+        })
+        .as_ref()
+}
+
+/// `-[Module.Class testName]` as `Module/Class/testName`
+fn xctest_identifier(name: &str) -> String {
+    let inner = name.trim_start_matches("-[").trim_end_matches(']');
+    let (class, method) = inner.split_once(' ').unwrap_or((inner, ""));
+    let class = class.replace('.', "/");
+    match method.is_empty() {
+        true => class,
+        false => format!("{}/{}", class, method),
+    }
+}
+
+/// `(test, "passed" | "failed" | "skipped")` when `line` reports a finished test
+pub fn case_result(line: &str) -> Option<(String, &'static str)> {
+    let patterns = patterns()?;
+    let (test, status) = match patterns.xctest_case.captures(line) {
+        Some(caps) => (xctest_identifier(&caps[1]), caps.get(2)?.as_str()),
+        None => {
+            let caps = patterns.swift_testing_case.captures(line)?;
+            (caps[1].to_string(), caps.get(2)?.as_str())
+        }
+    };
+    let status = match status {
+        "passed" => "passed",
+        "failed" => "failed",
+        _ => "skipped",
+    };
+    Some((test, status))
+}
+
+/// Counts and the failing tests with their messages. A test reported more
+/// than once (e.g. with repetitions) counts by its last result.
+pub fn parse(output: &str) -> (TestCounts, Vec<TestFailure>) {
+    let Some(patterns) = patterns() else {
+        return (TestCounts::default(), Vec::new());
+    };
+    let mut results: BTreeMap<String, &'static str> = BTreeMap::new();
+    let mut failures: BTreeMap<String, TestFailure> = BTreeMap::new();
+
+    for line in output.lines().map(str::trim) {
+        if let Some((test, status)) = case_result(line) {
+            results.insert(test, status);
+            continue;
+        }
+        let failure = match patterns.xctest_failure.captures(line) {
+            Some(caps) => {
+                Some((xctest_identifier(&caps[3]), caps[1].to_string(), caps[2].parse().ok(), caps[4].to_string()))
+            }
+            None => patterns
+                .swift_testing_issue
+                .captures(line)
+                .map(|caps| (caps[1].to_string(), caps[2].to_string(), caps[3].parse().ok(), caps[4].to_string())),
+        };
+        if let Some((test, file, line, message)) = failure {
+            let entry = failures.entry(test.clone()).or_insert_with(|| TestFailure {
+                test,
+                messages: Vec::new(),
+                file: Some(file),
+                line,
+            });
+            if !entry.messages.contains(&message) {
+                entry.messages.push(message);
+            }
+        }
+    }
+
+    let mut counts = TestCounts::default();
+    for status in results.values() {
+        match *status {
+            "passed" => counts.passed += 1,
+            "failed" => counts.failed += 1,
+            _ => counts.skipped += 1,
+        }
+    }
+    // A failed test whose assertions weren't printed still gets an entry
+    for (test, status) in &results {
+        if *status == "failed" && !failures.contains_key(test) {
+            failures.insert(test.clone(), TestFailure { test: test.clone(), messages: Vec::new(), file: None, line: None });
+        }
+    }
+    // Only tests that ended up failing; a passing retry clears an earlier failure
+    let failures = failures
+        .into_values()
+        .filter(|f| results.get(&f.test).map_or(true, |status| *status == "failed"))
+        .collect();
+    (counts, failures)
+}
@@ -0,0 +1,291 @@
+//! Display metadata for tool calls in the chat
+//!
+//! `tool_input` reaches the frontend as the raw JSON the SDK sent, and every
+//! tool shapes it differently. `summarize` reduces the tools the chat knows
+//! how to show (file edits, shell commands, reads and searches) to a
+//! `ToolSummary` with the fields a bubble needs, cut to lengths that can't
+//! blow up the layout. Any other tool gets a truncated JSON preview. The raw
+//! input stays on the event either way.
+
+use crate::claude::truncate_to_char_boundary;
+use serde::Serialize;
+use serde_json::Value;
+
+/// Context lines kept on each side of an edit
+const CONTEXT_LINES: usize = 3;
+/// Changed lines shown per side of an edit before the snippet is cut
+const MAX_CHANGED_LINES: usize = 12;
+const MAX_LINE_BYTES: usize = 160;
+const MAX_PATH_BYTES: usize = 300;
+const MAX_COMMAND_BYTES: usize = 200;
+const MAX_PATTERN_BYTES: usize = 120;
+const MAX_PREVIEW_BYTES: usize = 200;
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "kind", rename_all = "camelCase")]
+pub enum ToolSummary {
+    /// Edit and MultiEdit; `snippet` is the first change as `-`/`+` lines
+    /// with context
+    #[serde(rename_all = "camelCase")]
+    Edit { path: String, snippet: String, edits: usize },
+    /// `snippet` is the start of the new file
+    #[serde(rename_all = "camelCase")]
+    Write { path: String, snippet: String, lines: usize },
+    /// `command` is the first line; `multiline` says there was more.
+    /// `working_dir` is the session's, which is where the shell starts.
+    #[serde(rename_all = "camelCase")]
+    Bash {
+        command: String,
+        multiline: bool,
+        working_dir: Option<String>,
+    },
+    #[serde(rename_all = "camelCase")]
+    Read {
+        path: String,
+        offset: Option<u64>,
+        limit: Option<u64>,
+    },
+    /// Glob and Grep
+    #[serde(rename_all = "camelCase")]
+    Search { pattern: String, path: Option<String> },
+    /// Any other tool: the input JSON, cut short
+    #[serde(rename_all = "camelCase")]
+    Generic { preview: String },
+}
+
+fn clip(text: &str, max_bytes: usize) -> String {
+    if text.len() <= max_bytes {
+        return text.to_string();
+    }
+    format!("{}…", truncate_to_char_boundary(text, max_bytes))
+}
+
+fn str_field(input: &Value, key: &str) -> Option<String> {
+    input.get(key).and_then(|v| v.as_str()).map(String::from)
+}
+
+fn prefixed<'a>(prefix: char, lines: &'a [&'a str]) -> impl Iterator<Item = String> + 'a {
+    lines.iter().map(move |line| format!("{}{}", prefix, clip(line, MAX_LINE_BYTES)))
+}
+
+/// The changed lines of `old` -> `new` as a diff snippet, with up to
+/// `CONTEXT_LINES` unchanged lines either side from within the edit
+fn edit_snippet(old: &str, new: &str) -> String {
+    let old_lines: Vec<&str> = old.lines().collect();
+    let new_lines: Vec<&str> = new.lines().collect();
+
+    let prefix = old_lines.iter().zip(&new_lines).take_while(|(a, b)| a == b).count();
+    let suffix = old_lines[prefix..]
+        .iter()
+        .rev()
+        .zip(new_lines[prefix..].iter().rev())
+        .take_while(|(a, b)| a == b)
+        .count();
+    let removed = &old_lines[prefix..old_lines.len() - suffix];
+    let added = &new_lines[prefix..new_lines.len() - suffix];
+
+    let before = &new_lines[prefix.saturating_sub(CONTEXT_LINES)..prefix];
+    let after_start = new_lines.len() - suffix;
+    let after = &new_lines[after_start..(after_start + CONTEXT_LINES).min(new_lines.len())];
+
+    let mut lines: Vec<String> = prefixed(' ', before).collect();
+    for (sign, changed) in [('-', removed), ('+', added)] {
+        lines.extend(prefixed(sign, &changed[..changed.len().min(MAX_CHANGED_LINES)]));
+        if changed.len() > MAX_CHANGED_LINES {
+            lines.push(format!("{}… {} more lines", sign, changed.len() - MAX_CHANGED_LINES));
+        }
+    }
+    lines.extend(prefixed(' ', after));
+    lines.join("\n")
+}
+
+fn summarize_edit(input: &Value, tool_name: &str) -> Option<ToolSummary> {
+    let path = str_field(input, "file_path")?;
+    let (first, edits) = if tool_name == "MultiEdit" {
+        let edits = input.get("edits")?.as_array()?;
+        (edits.first()?.clone(), edits.len())
+    } else {
+        (input.clone(), 1)
+    };
+    let old = str_field(&first, "old_string").unwrap_or_default();
+    let new = str_field(&first, "new_string").unwrap_or_default();
+    Some(ToolSummary::Edit {
+        path: clip(&path, MAX_PATH_BYTES),
+        snippet: edit_snippet(&old, &new),
+        edits,
+    })
+}
+
+fn summarize_write(input: &Value) -> Option<ToolSummary> {
+    let path = str_field(input, "file_path")?;
+    let content = str_field(input, "content").unwrap_or_default();
+    let lines: Vec<&str> = content.lines().collect();
+    let shown = &lines[..lines.len().min(CONTEXT_LINES * 2)];
+    Some(ToolSummary::Write {
+        path: clip(&path, MAX_PATH_BYTES),
+        snippet: prefixed('+', shown).collect::<Vec<_>>().join("\n"),
+        lines: lines.len(),
+    })
+}
+
+fn summarize_bash(input: &Value, working_dir: Option<&str>) -> Option<ToolSummary> {
+    let command = str_field(input, "command")?;
+    let mut lines = command.trim().lines();
+    let first = lines.next().unwrap_or("");
+    Some(ToolSummary::Bash {
+        command: clip(first, MAX_COMMAND_BYTES),
+        multiline: lines.next().is_some(),
+        working_dir: working_dir.map(|dir| clip(dir, MAX_PATH_BYTES)),
+    })
+}
+
+fn summarize_read(input: &Value) -> Option<ToolSummary> {
+    Some(ToolSummary::Read {
+        path: clip(&str_field(input, "file_path")?, MAX_PATH_BYTES),
+        offset: input.get("offset").and_then(|v| v.as_u64()),
+        limit: input.get("limit").and_then(|v| v.as_u64()),
+    })
+}
+
+fn summarize_search(input: &Value) -> Option<ToolSummary> {
+    Some(ToolSummary::Search {
+        pattern: clip(&str_field(input, "pattern")?, MAX_PATTERN_BYTES),
+        path: str_field(input, "path").map(|p| clip(&p, MAX_PATH_BYTES)),
+    })
+}
+
+fn generic(tool_input: &str) -> ToolSummary {
+    // Re-serialize so the preview is one line whatever the SDK sent
+    let compact = serde_json::from_str::<Value>(tool_input)
+        .map(|v| v.to_string())
+        .unwrap_or_else(|_| tool_input.split_whitespace().collect::<Vec<_>>().join(" "));
+    ToolSummary::Generic { preview: clip(&compact, MAX_PREVIEW_BYTES) }
+}
+
+/// Summary of a tool call for display. Known tools whose input is missing the
+/// fields they should have fall back to the generic preview too.
+pub fn summarize(tool_name: &str, tool_input: &str, working_dir: Option<&str>) -> ToolSummary {
+    let known = serde_json::from_str::<Value>(tool_input).ok().and_then(|input| match tool_name {
+        "Edit" | "MultiEdit" => summarize_edit(&input, tool_name),
+        "Write" => summarize_write(&input),
+        "Bash" => summarize_bash(&input, working_dir),
+        "Read" => summarize_read(&input),
+        "Glob" | "Grep" => summarize_search(&input),
+        _ => None,
+    });
+    known.unwrap_or_else(|| generic(tool_input))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn summary(tool_name: &str, input: Value) -> ToolSummary {
+        summarize(tool_name, &input.to_string(), None)
+    }
+
+    #[test]
+    fn edit_shows_the_change_with_context() {
+        let old = "a\nb\nc\nd\ne\nold\nf\ng";
+        let new = "a\nb\nc\nd\ne\nnew\nf\ng";
+        assert_eq!(
+            summary("Edit", json!({ "file_path": "/p/View.swift", "old_string": old, "new_string": new })),
+            ToolSummary::Edit { path: "/p/View.swift".into(), snippet: " c\n d\n e\n-old\n+new\n f\n g".into(), edits: 1 }
+        );
+    }
+
+    #[test]
+    fn multi_edit_shows_the_first_change_and_counts_them() {
+        let edits = json!([{ "old_string": "x", "new_string": "y" }, { "old_string": "p", "new_string": "q" }]);
+        assert_eq!(
+            summary("MultiEdit", json!({ "file_path": "/p/Model.swift", "edits": edits })),
+            ToolSummary::Edit { path: "/p/Model.swift".into(), snippet: "-x\n+y".into(), edits: 2 }
+        );
+    }
+
+    #[test]
+    fn write_shows_the_start_of_the_file() {
+        let content = (1..=8).map(|n| n.to_string()).collect::<Vec<_>>().join("\n");
+        assert_eq!(
+            summary("Write", json!({ "file_path": "/p/New.swift", "content": content })),
+            ToolSummary::Write { path: "/p/New.swift".into(), snippet: "+1\n+2\n+3\n+4\n+5\n+6".into(), lines: 8 }
+        );
+    }
+
+    #[test]
+    fn bash_keeps_the_first_line_and_the_working_dir() {
+        let input = json!({ "command": "  cd ios\nxcodebuild test  " }).to_string();
+        assert_eq!(
+            summarize("Bash", &input, Some("/Users/dev/My App")),
+            ToolSummary::Bash { command: "cd ios".into(), multiline: true, working_dir: Some("/Users/dev/My App".into()) }
+        );
+        assert_eq!(
+            summary("Bash", json!({ "command": "ls" })),
+            ToolSummary::Bash { command: "ls".into(), multiline: false, working_dir: None }
+        );
+    }
+
+    #[test]
+    fn read_keeps_offset_and_limit() {
+        assert_eq!(
+            summary("Read", json!({ "file_path": "/p/App.swift", "offset": 40, "limit": 20 })),
+            ToolSummary::Read { path: "/p/App.swift".into(), offset: Some(40), limit: Some(20) }
+        );
+        assert_eq!(
+            summary("Read", json!({ "file_path": "/p/App.swift" })),
+            ToolSummary::Read { path: "/p/App.swift".into(), offset: None, limit: None }
+        );
+    }
+
+    #[test]
+    fn glob_and_grep_are_searches() {
+        assert_eq!(
+            summary("Glob", json!({ "pattern": "**/*.swift", "path": "Sources" })),
+            ToolSummary::Search { pattern: "**/*.swift".into(), path: Some("Sources".into()) }
+        );
+        assert_eq!(
+            summary("Grep", json!({ "pattern": "TODO\\(", "output_mode": "content" })),
+            ToolSummary::Search { pattern: "TODO\\(".into(), path: None }
+        );
+    }
+
+    #[test]
+    fn known_tools_missing_fields_fall_back() {
+        assert_eq!(
+            summary("Edit", json!({ "old_string": "a" })),
+            ToolSummary::Generic { preview: r#"{"old_string":"a"}"#.into() }
+        );
+        assert_eq!(
+            summary("MultiEdit", json!({ "file_path": "/p/A.swift", "edits": [] })),
+            generic(r#"{"edits":[],"file_path":"/p/A.swift"}"#)
+        );
+        assert!(matches!(summary("Bash", json!({ "cmd": "ls" })), ToolSummary::Generic { .. }));
+        assert!(matches!(summary("Grep", json!({ "path": "Sources" })), ToolSummary::Generic { .. }));
+    }
+
+    #[test]
+    fn unknown_tools_get_a_truncated_preview() {
+        let input = json!({ "query": "x".repeat(500) }).to_string();
+        let ToolSummary::Generic { preview } = summarize("mcp__nocur__take_screenshot", &input, None) else {
+            panic!("expected a generic preview");
+        };
+        assert!(preview.starts_with(r#"{"query":"xxx"#));
+        assert!(preview.ends_with('…'));
+        assert_eq!(preview.len(), MAX_PREVIEW_BYTES + '…'.len_utf8());
+
+        // Input that isn't JSON is collapsed onto one line
+        assert_eq!(
+            summarize("Custom", "not\n  json", None),
+            ToolSummary::Generic { preview: "not json".into() }
+        );
+    }
+
+    #[test]
+    fn edit_snippet_clips_lines_on_a_char_boundary() {
+        // 180 bytes; byte 160 falls inside a character
+        let long = "✨".repeat(60);
+        let snippet = edit_snippet(&long, "");
+        assert_eq!(snippet, format!("-{}…", "✨".repeat(53)));
+    }
+}
@@ -0,0 +1,96 @@
+//! Generating a Tuist project before it's built
+//!
+//! Projects from `create_project` are Tuist manifests; until `tuist generate`
+//! has run there's no .xcodeproj for the build to find. `run_build` generates
+//! when the project is missing or Project.swift is newer than it, streaming
+//! tuist's output as build events.
+
+use std::io::{BufRead, BufReader};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::time::SystemTime;
+use tauri::AppHandle;
+
+use crate::errors::{CommandError, MissingTool};
+use crate::messages::msg;
+
+const INSTALL_COMMAND: &str = "brew install tuist";
+
+/// The generated .xcworkspace or .xcodeproj at the top of `project_dir`
+fn generated_project(project_dir: &Path) -> Option<PathBuf> {
+    let entries: Vec<PathBuf> = std::fs::read_dir(project_dir).ok()?.filter_map(|e| e.ok()).map(|e| e.path()).collect();
+    let with_extension = |ext: &str| entries.iter().find(|p| p.extension().is_some_and(|e| e == ext)).cloned();
+    with_extension("xcworkspace").or_else(|| with_extension("xcodeproj"))
+}
+
+fn modified(path: &Path) -> Option<SystemTime> {
+    std::fs::metadata(path).and_then(|m| m.modified()).ok()
+}
+
+/// True when there's no generated project yet, or Project.swift changed since
+/// it was generated
+pub fn needs_generate(project_dir: &Path) -> bool {
+    let Some(generated) = generated_project(project_dir) else {
+        return true;
+    };
+    // The bundle's own mtime doesn't change when tuist rewrites what's inside it
+    let contents = match generated.extension().is_some_and(|e| e == "xcworkspace") {
+        true => generated.join("contents.xcworkspacedata"),
+        false => generated.join("project.pbxproj"),
+    };
+    match (modified(&project_dir.join("Project.swift")), modified(&contents)) {
+        (Some(manifest), Some(generated)) => manifest > generated,
+        _ => false,
+    }
+}
+
+/// `tuist generate --no-open` in `project_dir`. Fails with `MissingTool` when
+/// tuist isn't installed.
+pub fn generate(app_handle: &AppHandle, project_dir: &Path) -> Result<(), CommandError> {
+    crate::emit_build_event(app_handle, "output", msg!("build.tuist_generating"));
+    let spawned = Command::new("tuist")
+        .args(["generate", "--no-open"])
+        .current_dir(project_dir)
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn();
+    let mut child = match spawned {
+        Ok(child) => child,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+            return Err(CommandError::MissingTool(MissingTool {
+                tool: "tuist".to_string(),
+                install_command: INSTALL_COMMAND.to_string(),
+            }));
+        }
+        Err(e) => return Err(format!("Failed to start tuist generate: {}", e).into()),
+    };
+
+    let stderr = child.stderr.take();
+    let app_stderr = app_handle.clone();
+    let stderr_handle = std::thread::spawn(move || {
+        let mut output = Vec::new();
+        for line in stderr.map(BufReader::new).into_iter().flat_map(|r| r.lines()).map_while(Result::ok) {
+            if !line.trim().is_empty() {
+                crate::emit_build_event(&app_stderr, "warning", line.trim());
+                output.push(line);
+            }
+        }
+        output
+    });
+    if let Some(stdout) = child.stdout.take() {
+        for line in BufReader::new(stdout).lines().map_while(Result::ok) {
+            if !line.trim().is_empty() {
+                crate::emit_build_event(app_handle, "output", line.trim());
+            }
+        }
+    }
+
+    let status = child.wait().map_err(|e| format!("Failed to wait for tuist generate: {}", e))?;
+    let stderr_lines = stderr_handle.join().unwrap_or_default();
+    if !status.success() {
+        let detail = stderr_lines.last().map(|l| format!(": {}", l.trim())).unwrap_or_default();
+        return Err(format!("tuist generate failed{}", detail).into());
+    }
+    crate::emit_build_event(app_handle, "output", msg!("build.tuist_generated"));
+    Ok(())
+}
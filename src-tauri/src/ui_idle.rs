@@ -0,0 +1,124 @@
+//! Waiting for the simulator's screen to stop changing
+//!
+//! A screenshot taken right after a tap often lands mid-animation. This
+//! captures frames with `simctl io screenshot` and hashes them, and returns
+//! once the hash has held for the quiet period or the timeout runs out.
+//! Frames are only hashed, never kept.
+
+use serde::{Deserialize, Serialize};
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use std::process::Command;
+use std::time::{Duration, Instant};
+
+const DEFAULT_QUIET_MS: u64 = 500;
+const DEFAULT_TIMEOUT_MS: u64 = 5000;
+/// Least time between the starts of two captures
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct IdleOptions {
+    /// Give up after this long; defaults to 5 s
+    pub timeout_ms: Option<u64>,
+    /// How long the screen must stay the same; defaults to 500 ms
+    pub quiet_ms: Option<u64>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum IdleCondition {
+    Idle,
+    Timeout,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UiIdleResult {
+    /// What ended the wait
+    pub condition: IdleCondition,
+    pub elapsed_ms: u64,
+    pub frames: u32,
+    /// Times a frame differed from the one before it
+    pub changes: u32,
+}
+
+/// Hash of the simulator's current frame
+fn frame_hash(device: &str, scratch: &std::path::Path) -> Result<u64, String> {
+    let output = Command::new("xcrun")
+        .args(["simctl", "io", device, "screenshot", "--type=png"])
+        .arg(scratch)
+        .output()
+        .map_err(|e| format!("Failed to capture frame: {}", e))?;
+    if !output.status.success() {
+        return Err(format!("Failed to capture frame: {}", String::from_utf8_lossy(&output.stderr).trim()));
+    }
+    let data = std::fs::read(scratch).map_err(|e| format!("Failed to read frame: {}", e))?;
+    let mut hasher = DefaultHasher::new();
+    data.hash(&mut hasher);
+    Ok(hasher.finish())
+}
+
+/// Block until `device`'s screen has been unchanged for the quiet period or the
+/// timeout expires. Fails only when no frame can be captured at all.
+pub fn wait_for_idle(device: &str, options: &IdleOptions) -> Result<UiIdleResult, String> {
+    let quiet = Duration::from_millis(options.quiet_ms.unwrap_or(DEFAULT_QUIET_MS));
+    let timeout = Duration::from_millis(options.timeout_ms.unwrap_or(DEFAULT_TIMEOUT_MS));
+    let scratch = std::env::temp_dir().join(format!("nocur-idle-{}.png", uuid::Uuid::new_v4()));
+
+    let started = Instant::now();
+    let mut last: Option<(u64, Instant)> = None;
+    let mut frames = 0;
+    let mut changes = 0;
+
+    let condition = loop {
+        let captured = Instant::now();
+        let hash = match frame_hash(device, &scratch) {
+            Ok(hash) => hash,
+            Err(e) if frames == 0 => {
+                let _ = std::fs::remove_file(&scratch);
+                return Err(e);
+            }
+            // A dropped frame mid-wait just doesn't count
+            Err(e) => {
+                log::warn!("{}", e);
+                if started.elapsed() >= timeout {
+                    break IdleCondition::Timeout;
+                }
+                std::thread::sleep(POLL_INTERVAL);
+                continue;
+            }
+        };
+        frames += 1;
+
+        match last {
+            Some((previous, since)) if previous == hash => {
+                if captured.duration_since(since) >= quiet {
+                    break IdleCondition::Idle;
+                }
+            }
+            Some(_) => {
+                changes += 1;
+                last = Some((hash, captured));
+            }
+            None => last = Some((hash, captured)),
+        }
+
+        if started.elapsed() >= timeout {
+            break IdleCondition::Timeout;
+        }
+        let next = captured + POLL_INTERVAL;
+        let now = Instant::now();
+        if next > now {
+            std::thread::sleep(next - now);
+        }
+    };
+
+    let _ = std::fs::remove_file(&scratch);
+    Ok(UiIdleResult {
+        condition,
+        elapsed_ms: started.elapsed().as_millis() as u64,
+        frames,
+        changes,
+    })
+}
@@ -0,0 +1,247 @@
+//! Composite "verify change" run for the agent loop
+//!
+//! Build, install and launch via `run_project`, let the app settle (for a fixed
+//! time, or until its screen stops changing with `waitForIdle`), screenshot it,
+//! keep it in the project's screenshot history, then gather logs and crash
+//! reports from since the launch. Each stage stops the run on failure, and
+//! whatever was collected up to that point is still returned with
+//! `failed_stage` saying where it stopped.
+
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tauri::{AppHandle, Manager};
+
+use crate::errors::CommandError;
+use crate::{BuildResult, CrashReport, DeviceInfo, DeviceType, SimulatorLogEntry};
+
+const DEFAULT_WAIT_MS: u64 = 2000;
+/// Error and fault lines included verbatim in the summary
+#[cfg(target_os = "macos")]
+const MAX_LOG_HIGHLIGHTS: usize = 20;
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct VerifyOptions {
+    pub scheme: Option<String>,
+    /// Defaults to the selected device, which has to be a simulator
+    pub device: Option<DeviceInfo>,
+    /// How long to let the app settle after launch before the screenshot
+    pub wait_ms: Option<u64>,
+    /// Wait for the screen to stop changing instead of `wait_ms`
+    pub wait_for_idle: Option<crate::ui_idle::IdleOptions>,
+    /// How to size `screenshot`; the "agent" preset unless set
+    pub screenshot: Option<crate::screenshot_presets::ScreenshotOptions>,
+    /// Stream logs from before the launch and summarize what the app logged
+    #[serde(default)]
+    pub capture_logs: bool,
+    /// For CI and minimized windows: no Simulator window and no live-view
+    /// frames for the whole run; the screenshot comes from simctl either way
+    #[serde(default)]
+    pub headless: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LogSummary {
+    pub total: usize,
+    pub errors: usize,
+    pub faults: usize,
+    pub warnings: usize,
+    /// The most recent error and fault entries
+    pub highlights: Vec<SimulatorLogEntry>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct VerificationResult {
+    pub build: BuildResult,
+    /// The full-size capture
+    pub screenshot_path: Option<String>,
+    /// The capture sized per `VerifyOptions::screenshot`, with its dimensions and byte size
+    pub screenshot: Option<crate::screenshot_presets::EncodedScreenshot>,
+    /// How the idle wait ended, when `waitForIdle` was set
+    pub idle: Option<crate::ui_idle::UiIdleResult>,
+    pub log_summary: Option<LogSummary>,
+    /// When the build failed: its errors in context, see `build_failure`
+    pub build_failure: Option<crate::build_failure::FailureContext>,
+    pub crashes: Vec<CrashReport>,
+    /// "build", "launch", "screenshot" or "crash"; None when every stage passed
+    pub failed_stage: Option<String>,
+    pub error: Option<String>,
+}
+
+impl VerificationResult {
+    fn new(build: BuildResult) -> Self {
+        Self {
+            build,
+            screenshot_path: None,
+            screenshot: None,
+            idle: None,
+            log_summary: None,
+            build_failure: None,
+            crashes: vec![],
+            failed_stage: None,
+            error: None,
+        }
+    }
+
+    fn fail(mut self, stage: &str, error: String) -> Self {
+        self.failed_stage = Some(stage.to_string());
+        self.error = Some(error);
+        self
+    }
+}
+
+/// Summary of the simulator log stream since `since_ms`; None when nothing is streaming
+#[cfg(target_os = "macos")]
+fn summarize_logs(app_handle: &AppHandle, since_ms: u64) -> Option<LogSummary> {
+    use tauri::Manager;
+
+    let state = app_handle.try_state::<std::sync::Arc<crate::SimulatorLogState>>()?;
+    let logs = state.logs.read().unwrap_or_else(|e| e.into_inner());
+    let recent: Vec<&SimulatorLogEntry> = logs.iter().filter(|e| e.timestamp >= since_ms).collect();
+
+    let count = |level: &str| recent.iter().filter(|e| e.level == level).count();
+    let mut highlights: Vec<SimulatorLogEntry> = recent
+        .iter()
+        .rev()
+        .filter(|e| e.level == "error" || e.level == "fault")
+        .take(MAX_LOG_HIGHLIGHTS)
+        .map(|e| (*e).clone())
+        .collect();
+    highlights.reverse();
+
+    Some(LogSummary {
+        total: recent.len(),
+        errors: count("error"),
+        faults: count("fault"),
+        warnings: count("warning"),
+        highlights,
+    })
+}
+
+/// Simulator log streaming only exists on macOS
+#[cfg(not(target_os = "macos"))]
+fn summarize_logs(_app_handle: &AppHandle, _since_ms: u64) -> Option<LogSummary> {
+    None
+}
+
+pub async fn verify_change(
+    project_path: String,
+    options: VerifyOptions,
+    app_handle: AppHandle,
+) -> Result<VerificationResult, CommandError> {
+    let started = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default();
+    let is_physical = options
+        .device
+        .as_ref()
+        .is_some_and(|d| d.device_type == DeviceType::Physical);
+    let target = match options.device.as_ref() {
+        Some(device) => device.id.clone(),
+        None => crate::simulator_target(None, app_handle.state::<Mutex<crate::AppState>>().inner())?,
+    };
+    let _headless_run = options.headless.then(crate::frame_stream::HeadlessRun::start);
+
+    let build = crate::run_project(
+        Some(project_path.clone()),
+        options.scheme,
+        None,
+        options.device,
+        Some(options.capture_logs),
+        None,
+        None,
+        None,
+        None,
+        Some(options.headless),
+        app_handle.clone(),
+    ).await?;
+    let bundle_id = build.bundle_id.clone();
+
+    if !build.success {
+        // An app path means the build itself succeeded and install/launch didn't
+        let stage = if build.app_path.is_some() { "launch" } else { "build" };
+        let error = build
+            .errors
+            .first()
+            .map(|e| e.message.clone())
+            .unwrap_or_else(|| format!("{} failed", stage));
+        let mut result = VerificationResult::new(build);
+        if stage == "build" {
+            // The run lock keeps another build of the project from finishing in
+            // between; one from before this run means the build never started
+            let path = project_path.clone();
+            result.build_failure = tauri::async_runtime::spawn_blocking(move || {
+                crate::build_failure::for_build(Some(&path), None, crate::context_budget::DEFAULT_MAX_TOKENS)
+            })
+            .await
+            .ok()
+            .and_then(Result::ok)
+            .filter(|failure| failure.finished_at >= started.as_secs());
+        }
+        return Ok(result.fail(stage, error));
+    }
+
+    let mut result = VerificationResult::new(build);
+    let screenshot_options = match crate::screenshot_presets::resolve_with_preferences(
+        options.screenshot,
+        crate::screenshot_presets::AGENT_PRESET,
+    ) {
+        Ok(o) => o,
+        Err(e) => return Ok(result.fail("screenshot", e)),
+    };
+
+    if is_physical {
+        std::thread::sleep(Duration::from_millis(options.wait_ms.unwrap_or(DEFAULT_WAIT_MS)));
+        return Ok(result.fail("screenshot", "Screenshots are only supported on simulators".to_string()));
+    }
+    match options.wait_for_idle {
+        Some(idle) => {
+            let waited = tauri::async_runtime::spawn_blocking(move || crate::ui_idle::wait_for_idle(&target, &idle)).await;
+            match waited {
+                Ok(Ok(idle)) => result.idle = Some(idle),
+                // The screenshot below reports a simulator that can't be captured
+                Ok(Err(e)) => log::warn!("Idle wait failed: {}", e),
+                Err(e) => log::warn!("Idle wait task failed: {}", e),
+            }
+        }
+        None => std::thread::sleep(Duration::from_millis(options.wait_ms.unwrap_or(DEFAULT_WAIT_MS))),
+    }
+    match tauri::async_runtime::spawn_blocking(crate::capture_simulator_screenshot).await {
+        Ok(Ok(path)) => {
+            match crate::screenshot_presets::encode(Path::new(&path), &screenshot_options) {
+                Ok(image) => result.screenshot = Some(image),
+                Err(e) => return Ok(result.fail("screenshot", e)),
+            }
+            let context = crate::screenshots::CaptureContext::for_active_session(&app_handle, crate::screenshots::CaptureTrigger::Verify);
+            result.screenshot_path = match crate::screenshots::register(&project_path, Path::new(&path), context) {
+                Ok(record) => Some(record.path),
+                // The capture is still usable from where nocur-swift left it
+                Err(e) => {
+                    log::warn!("Failed to keep verify screenshot: {}", e);
+                    Some(path)
+                }
+            };
+        }
+        Ok(Err(e)) => return Ok(result.fail("screenshot", e)),
+        Err(e) => return Ok(result.fail("screenshot", format!("Screenshot task failed: {}", e))),
+    }
+
+    if options.capture_logs {
+        result.log_summary = summarize_logs(&app_handle, started.as_millis() as u64);
+    }
+
+    result.crashes = crate::collect_crash_reports(bundle_id.as_deref(), Some(started.as_secs()))?;
+    if let Some(crash) = result.crashes.first() {
+        let reason = crash
+            .exception_type
+            .clone()
+            .or_else(|| crash.crash_reason.clone())
+            .unwrap_or_else(|| "unknown exception".to_string());
+        let message = format!("{} crashed after launch: {}", crash.process_name, reason);
+        return Ok(result.fail("crash", message));
+    }
+
+    Ok(result)
+}
@@ -0,0 +1,408 @@
+//! Test failures and build issues from an `.xcresult` bundle
+//!
+//! xcodebuild's output is a summary; the result bundle has every failure with
+//! its location and the screenshots UI tests attached. Xcode 16's
+//! xcresulttool reads it with `get test-results tests`, `get build-results`
+//! and `export attachments`; older ones only have `get --format json`, whose
+//! typed records (`{"_value": ...}`, `{"_values": [...]}`) are walked by
+//! reference id. Xcode 16 still reads that schema with `--legacy`, which is
+//! the fallback when the new commands fail.
+//!
+//! Image attachments of failing tests are exported under the temp directory
+//! so the frontend can show them.
+
+use serde::Serialize;
+use serde_json::Value;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+const IMAGE_EXTENSIONS: &[&str] = &["png", "jpg", "jpeg", "heic"];
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct XcresultTestFailure {
+    /// In `-only-testing` form, e.g. "AppUITests/LoginTests/testInvalidPassword"
+    pub test_identifier: String,
+    pub file: Option<String>,
+    pub line: Option<u32>,
+    pub message: String,
+    /// Exported screenshots attached to the failing test
+    pub attachments: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct XcresultIssue {
+    /// "error" or "warning"
+    pub severity: String,
+    pub message: String,
+    pub file: Option<String>,
+    pub line: Option<u32>,
+    /// e.g. "Swift Compiler Error"
+    pub issue_type: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct XcresultSummary {
+    /// "xcode16" or "legacy", whichever xcresulttool could read
+    pub schema: String,
+    pub test_failures: Vec<XcresultTestFailure>,
+    pub build_issues: Vec<XcresultIssue>,
+    /// Where attachments were exported
+    pub attachments_dir: Option<String>,
+}
+
+fn xcresulttool(args: &[&str], path: &Path) -> Result<Value, String> {
+    let output = Command::new("xcrun")
+        .arg("xcresulttool")
+        .args(args)
+        .arg("--path")
+        .arg(path)
+        .output()
+        .map_err(|e| format!("Failed to run xcresulttool: {}", e))?;
+    if !output.status.success() {
+        return Err(format!(
+            "xcresulttool {} failed: {}",
+            args.join(" "),
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    serde_json::from_slice(&output.stdout).map_err(|e| format!("Failed to parse xcresulttool output: {}", e))
+}
+
+/// Where a bundle's attachments go: one directory per bundle under the temp dir
+fn attachments_dir(path: &Path) -> PathBuf {
+    let id = crate::ace::generate_project_id(&path.to_string_lossy());
+    std::env::temp_dir().join("nocur-xcresult").join(id)
+}
+
+fn is_image(file_name: &str) -> bool {
+    Path::new(file_name)
+        .extension()
+        .and_then(|e| e.to_str())
+        .is_some_and(|ext| IMAGE_EXTENSIONS.contains(&ext.to_lowercase().as_str()))
+}
+
+/// `(file, line)` from a document URL like
+/// `file:///path/File.swift#EndingLineNumber=11&StartingLineNumber=11`.
+/// The fragment counts lines from 0.
+fn url_location(url: &str) -> (Option<String>, Option<u32>) {
+    let (file, fragment) = url.split_once('#').unwrap_or((url, ""));
+    let file = file.strip_prefix("file://").unwrap_or(file).replace("%20", " ");
+    let line = fragment
+        .split('&')
+        .find_map(|part| part.strip_prefix("StartingLineNumber="))
+        .and_then(|n| n.parse::<u32>().ok())
+        .map(|n| n + 1);
+    ((!file.is_empty()).then_some(file), line)
+}
+
+/// `Module/Class/test` without the `()` Swift puts after test names
+fn identifier(target: Option<&str>, test: &str) -> String {
+    let test = test.trim_start_matches("-[").trim_end_matches(']').replace([' ', '.'], "/");
+    let test = test.trim_end_matches("()");
+    match target {
+        Some(target) if !test.starts_with(&format!("{}/", target)) => format!("{}/{}", target, test),
+        _ => test.to_string(),
+    }
+}
+
+// ============ Xcode 16 ============
+
+/// "File.swift:12: message" as `(file, line, message)`
+fn split_failure_message(text: &str) -> (Option<String>, Option<u32>, String) {
+    let mut parts = text.splitn(3, ':');
+    match (parts.next(), parts.next().and_then(|l| l.trim().parse().ok()), parts.next()) {
+        (Some(file), Some(line), Some(message)) if !file.contains(' ') => {
+            (Some(file.to_string()), Some(line), message.trim().to_string())
+        }
+        _ => (None, None, text.to_string()),
+    }
+}
+
+fn collect_test_failures(node: &Value, target: Option<&str>, failures: &mut Vec<XcresultTestFailure>) {
+    let node_type = node.get("nodeType").and_then(Value::as_str).unwrap_or("");
+    let children = node.get("children").and_then(Value::as_array).map(Vec::as_slice).unwrap_or(&[]);
+    let target = match node_type {
+        "Unit test bundle" | "UI test bundle" => node.get("name").and_then(Value::as_str),
+        _ => target,
+    };
+    if node_type == "Test Case" && node.get("result").and_then(Value::as_str) == Some("Failed") {
+        let test = node
+            .get("nodeIdentifier")
+            .or_else(|| node.get("name"))
+            .and_then(Value::as_str)
+            .unwrap_or("");
+        let messages = children
+            .iter()
+            .filter(|child| child.get("nodeType").and_then(Value::as_str) == Some("Failure Message"))
+            .filter_map(|child| child.get("name").and_then(Value::as_str));
+        let mut any = false;
+        for text in messages {
+            any = true;
+            let (file, line, message) = split_failure_message(text);
+            failures.push(XcresultTestFailure { test_identifier: identifier(target, test), file, line, message, attachments: Vec::new() });
+        }
+        if !any {
+            failures.push(XcresultTestFailure {
+                test_identifier: identifier(target, test),
+                file: None,
+                line: None,
+                message: "Failed".to_string(),
+                attachments: Vec::new(),
+            });
+        }
+        return;
+    }
+    for child in children {
+        collect_test_failures(child, target, failures);
+    }
+}
+
+fn build_issues(results: &Value) -> Vec<XcresultIssue> {
+    let mut issues = Vec::new();
+    for (key, severity) in [("errors", "error"), ("warnings", "warning")] {
+        for issue in results.get(key).and_then(Value::as_array).into_iter().flatten() {
+            let (file, line) = issue
+                .get("sourceURL")
+                .and_then(Value::as_str)
+                .map_or((None, None), url_location);
+            issues.push(XcresultIssue {
+                severity: severity.to_string(),
+                message: issue.get("message").and_then(Value::as_str).unwrap_or("").to_string(),
+                file,
+                line,
+                issue_type: issue.get("issueType").and_then(Value::as_str).map(str::to_string),
+            });
+        }
+    }
+    issues
+}
+
+/// Export failing tests' attachments and hand the images to their failures
+fn export_attachments(path: &Path, failures: &mut [XcresultTestFailure]) -> Option<PathBuf> {
+    let dir = attachments_dir(path);
+    std::fs::create_dir_all(&dir).ok()?;
+    let output = Command::new("xcrun")
+        .args(["xcresulttool", "export", "attachments", "--only-failures"])
+        .arg("--path")
+        .arg(path)
+        .arg("--output-path")
+        .arg(&dir)
+        .output()
+        .ok()?;
+    if !output.status.success() {
+        log::warn!("Exporting attachments failed: {}", String::from_utf8_lossy(&output.stderr).trim());
+        return None;
+    }
+    let manifest: Value = serde_json::from_str(&std::fs::read_to_string(dir.join("manifest.json")).ok()?).ok()?;
+    for entry in manifest.as_array().into_iter().flatten() {
+        let test = entry.get("testIdentifier").and_then(Value::as_str).unwrap_or("");
+        let test = identifier(None, test);
+        let files: Vec<String> = entry
+            .get("attachments")
+            .and_then(Value::as_array)
+            .into_iter()
+            .flatten()
+            .filter_map(|a| a.get("exportedFileName").and_then(Value::as_str))
+            .filter(|name| is_image(name))
+            .map(|name| dir.join(name).to_string_lossy().to_string())
+            .collect();
+        if let Some(failure) = failures.iter_mut().find(|f| f.test_identifier.ends_with(&test)) {
+            failure.attachments.extend(files);
+        }
+    }
+    Some(dir)
+}
+
+fn inspect_xcode16(path: &Path) -> Result<XcresultSummary, String> {
+    let tests = xcresulttool(&["get", "test-results", "tests"], path)?;
+    let mut test_failures = Vec::new();
+    for node in tests.get("testNodes").and_then(Value::as_array).into_iter().flatten() {
+        collect_test_failures(node, None, &mut test_failures);
+    }
+    let build_issues = xcresulttool(&["get", "build-results"], path)
+        .map(|results| build_issues(&results))
+        .unwrap_or_default();
+    let attachments_dir = match test_failures.is_empty() {
+        true => None,
+        false => export_attachments(path, &mut test_failures),
+    };
+    Ok(XcresultSummary {
+        schema: "xcode16".to_string(),
+        test_failures,
+        build_issues,
+        attachments_dir: attachments_dir.map(|d| d.to_string_lossy().to_string()),
+    })
+}
+
+// ============ Legacy ============
+
+fn value<'a>(record: &'a Value, key: &str) -> Option<&'a str> {
+    record.get(key)?.get("_value")?.as_str()
+}
+
+fn values<'a>(record: &'a Value, key: &str) -> &'a [Value] {
+    record
+        .get(key)
+        .and_then(|v| v.get("_values"))
+        .and_then(Value::as_array)
+        .map(Vec::as_slice)
+        .unwrap_or(&[])
+}
+
+fn reference_id(record: &Value, key: &str) -> Option<String> {
+    record.get(key)?.get("id")?.get("_value")?.as_str().map(str::to_string)
+}
+
+/// `get --format json`, adding `--legacy` when this xcresulttool wants it
+fn legacy_get(path: &Path, id: Option<&str>) -> Result<Value, String> {
+    let mut args = vec!["get", "--format", "json"];
+    if let Some(id) = id {
+        args.extend(["--id", id]);
+    }
+    xcresulttool(&args, path).or_else(|e| {
+        args.push("--legacy");
+        xcresulttool(&args, path).map_err(|_| e)
+    })
+}
+
+fn legacy_location(summary: &Value) -> (Option<String>, Option<u32>) {
+    summary
+        .get("documentLocationInCreatingWorkspace")
+        .and_then(|location| value(location, "url"))
+        .map_or((None, None), url_location)
+}
+
+fn legacy_issues(issues: &Value, build_issues: &mut Vec<XcresultIssue>, test_failures: &mut Vec<XcresultTestFailure>) {
+    for (key, severity) in [("errorSummaries", "error"), ("warningSummaries", "warning")] {
+        for summary in values(issues, key) {
+            let (file, line) = legacy_location(summary);
+            build_issues.push(XcresultIssue {
+                severity: severity.to_string(),
+                message: value(summary, "message").unwrap_or("").to_string(),
+                file,
+                line,
+                issue_type: value(summary, "issueType").map(str::to_string),
+            });
+        }
+    }
+    for summary in values(issues, "testFailureSummaries") {
+        let (file, line) = legacy_location(summary);
+        test_failures.push(XcresultTestFailure {
+            test_identifier: identifier(value(summary, "producingTarget"), value(summary, "testCaseName").unwrap_or("")),
+            file,
+            line,
+            message: value(summary, "message").unwrap_or("").to_string(),
+            attachments: Vec::new(),
+        });
+    }
+}
+
+/// Failing tests' `summaryRef` ids by identifier, from a test plan run summary
+fn failing_summary_refs(group: &Value, refs: &mut Vec<(String, String)>) {
+    for test in values(group, "subtests").iter().chain(values(group, "tests")) {
+        if value(test, "testStatus") == Some("Failure") {
+            if let (Some(identifier), Some(id)) = (value(test, "identifier"), reference_id(test, "summaryRef")) {
+                refs.push((identifier.to_string(), id));
+            }
+        }
+        failing_summary_refs(test, refs);
+    }
+}
+
+/// Image attachments in a test's activities, with their payload ids
+fn activity_images(activities: &[Value], images: &mut Vec<(String, String)>) {
+    for activity in activities {
+        for attachment in values(activity, "attachments") {
+            if let (Some(name), Some(id)) = (value(attachment, "filename"), reference_id(attachment, "payloadRef")) {
+                if is_image(name) {
+                    images.push((name.to_string(), id));
+                }
+            }
+        }
+        activity_images(values(activity, "subactivities"), images);
+    }
+}
+
+fn export_legacy_attachments(path: &Path, tests_ref: &str, failures: &mut [XcresultTestFailure]) -> Option<PathBuf> {
+    let runs = legacy_get(path, Some(tests_ref)).ok()?;
+    let mut refs = Vec::new();
+    for run in values(&runs, "summaries") {
+        for testable in values(run, "testableSummaries") {
+            failing_summary_refs(testable, &mut refs);
+        }
+    }
+    let dir = attachments_dir(path);
+    std::fs::create_dir_all(&dir).ok()?;
+    let mut exported_count = 0;
+    for (test, summary_id) in refs {
+        let Ok(summary) = legacy_get(path, Some(&summary_id)) else {
+            continue;
+        };
+        let mut images = Vec::new();
+        activity_images(values(&summary, "activitySummaries"), &mut images);
+        let test = identifier(None, &test);
+        let Some(failure) = failures.iter_mut().find(|f| f.test_identifier.ends_with(&test)) else {
+            continue;
+        };
+        for (name, id) in images {
+            exported_count += 1;
+            let file = dir.join(format!("{}-{}", exported_count, name));
+            let exported = Command::new("xcrun")
+                .args(["xcresulttool", "export", "--type", "file", "--id", &id])
+                .arg("--path")
+                .arg(path)
+                .arg("--output-path")
+                .arg(&file)
+                .output()
+                .is_ok_and(|o| o.status.success());
+            if exported {
+                failure.attachments.push(file.to_string_lossy().to_string());
+            }
+        }
+    }
+    Some(dir)
+}
+
+fn inspect_legacy(path: &Path) -> Result<XcresultSummary, String> {
+    let record = legacy_get(path, None)?;
+    let mut test_failures = Vec::new();
+    let mut build_issues = Vec::new();
+    let mut attachments_dir = None;
+    for action in values(&record, "actions") {
+        for result in ["buildResult", "actionResult"] {
+            if let Some(issues) = action.get(result).and_then(|r| r.get("issues")) {
+                legacy_issues(issues, &mut build_issues, &mut test_failures);
+            }
+        }
+        let tests_ref = action.get("actionResult").and_then(|r| reference_id(r, "testsRef"));
+        if let Some(tests_ref) = tests_ref.filter(|_| !test_failures.is_empty()) {
+            attachments_dir = export_legacy_attachments(path, &tests_ref, &mut test_failures).or(attachments_dir);
+        }
+    }
+    // The record repeats the actions' issues at the top level when there's one action
+    if build_issues.is_empty() && test_failures.is_empty() {
+        if let Some(issues) = record.get("issues") {
+            legacy_issues(issues, &mut build_issues, &mut test_failures);
+        }
+    }
+    Ok(XcresultSummary {
+        schema: "legacy".to_string(),
+        test_failures,
+        build_issues,
+        attachments_dir: attachments_dir.map(|d| d.to_string_lossy().to_string()),
+    })
+}
+
+/// Test failures and build issues in the bundle at `path`
+pub fn inspect(path: &Path) -> Result<XcresultSummary, String> {
+    if !path.exists() {
+        return Err(format!("No result bundle at {}", path.display()));
+    }
+    inspect_xcode16(path).or_else(|e| {
+        log::info!("Reading {} with the legacy schema: {}", path.display(), e);
+        inspect_legacy(path)
+    })
+}
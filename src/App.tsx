@@ -13,6 +13,7 @@ import { BottomPanel, BottomPanelHandle } from "@/components/BottomPanel";
 import { PlaybookModal } from "@/components/PlaybookModal";
 import { DeviceSelector } from "@/components/DeviceSelector";
 import { ProjectProvider, useProject } from "@/lib/project-context";
+import { listenWithReplay, type EventStamp } from "@/lib/events";
 import { WelcomeScreen } from "@/components/WelcomeScreen";
 import { NewProjectModal } from "@/components/NewProjectModal";
 
@@ -25,12 +26,20 @@ interface ClaudeCodeStatus {
   loggedIn: boolean;
   hasActivePlan: boolean;
   error: string | null;
+  errorKey?: string | null;
+  offline?: boolean; // login probe skipped; other fields are from the last check
 }
 
 interface BuildEvent {
   eventType: string;
   message: string;
+  messageKey?: string;                  // catalog key `message` was rendered from
+  messageArgs?: Record<string, string>;
   timestamp: number;
+  offline?: boolean; // a network-dependent step was skipped
+  buildId?: string;
+  runId?: string;
+  stamp?: EventStamp; // ordering and session, for deduping replayed events
 }
 
 interface LogEntry {
@@ -44,9 +53,26 @@ interface BuildResult {
   output: string;
   errors: BuildError[];
   warnings: number;
+  /** Each warning with its location */
+  warningDetails?: BuildError[];
   buildTime: number | null;
   appPath: string | null;
   bundleId: string | null;
+  launch?: LaunchInfo | null;
+  /** Set when the app was installed (and launched) under a different bundle ID than the build reported */
+  bundleIdCorrection?: { expected: string; installed: string } | null;
+  /** The kept output, for read_build_log */
+  logId?: string | null;
+}
+
+interface LaunchInfo {
+  pid: number | null;
+  logStreaming: boolean;
+  logBackend: "simulator" | "devicectl" | null;
+  logError: string | null;
+  launchDurationMs?: number | null;
+  launchRegression?: boolean;
+  launchArgs?: string[]; // from launchOverrides, simulator only
 }
 
 interface BuildError {
@@ -54,6 +80,16 @@ interface BuildError {
   line: number | null;
   column: number | null;
   message: string;
+  relativePath?: string | null;
+  isDependency?: boolean;
+  /** "clean_build" when the error comes from stale DerivedData */
+  suggestion?: "clean_build" | "reset_crash_loop" | null;
+  /** Schemes to pick from when the requested one doesn't exist */
+  suggestions?: string[];
+  /** note: lines and fix-its printed after the diagnostic */
+  notes?: string[];
+  /** Source excerpt with the caret line */
+  snippet?: string | null;
 }
 
 type BuildStatus = "idle" | "building" | "success" | "failed";
@@ -67,6 +103,8 @@ interface DeviceInfo {
   deviceType: "simulator" | "physical";
   state: "booted" | "shutdown" | "connected" | "disconnected" | "unavailable";
   isAvailable: boolean;
+  pairingState?: string | null;          // devicectl pairing state (physical devices only)
+  developerModeEnabled?: boolean | null; // null when the device doesn't report it
 }
 
 interface DeviceListResult {
@@ -171,7 +209,7 @@ const AppContent = () => {
   const [rightCollapsed, setRightCollapsed] = useState(true); // Hidden by default
 
   // Session state
-  const [_currentSessionId, setCurrentSessionId] = useState<string | null>(null);
+  const [currentSessionId, setCurrentSessionId] = useState<string | null>(null);
 
   // Dev tools panel state
   const [showDevTools, setShowDevTools] = useState(false);
@@ -236,6 +274,27 @@ const AppContent = () => {
     return () => clearInterval(interval);
   }, [PROJECT_PATH]);
 
+  // Nobody sees the simulator's live frames while the window is minimized or
+  // hidden, so stop capturing them
+  useEffect(() => {
+    const appWindow = getCurrentWindow();
+    const updateStreaming = async () => {
+      const minimized = await appWindow.isMinimized().catch(() => false);
+      const enabled = !minimized && document.visibilityState === "visible";
+      invoke("set_streaming_enabled", { enabled }).catch((error) =>
+        console.error("Failed to update streaming:", error)
+      );
+    };
+    const unlistenResize = appWindow.onResized(updateStreaming);
+    const unlistenFocus = appWindow.onFocusChanged(updateStreaming);
+    document.addEventListener("visibilitychange", updateStreaming);
+    return () => {
+      unlistenResize.then((unlisten) => unlisten());
+      unlistenFocus.then((unlisten) => unlisten());
+      document.removeEventListener("visibilitychange", updateStreaming);
+    };
+  }, []);
+
   // Auto-select first available device on mount
   useEffect(() => {
     const loadSelectedDevice = async () => {
@@ -254,13 +313,13 @@ const AppContent = () => {
         );
         if (firstActive) {
           setSelectedDevice(firstActive);
-          await invoke("set_selected_device", { device: firstActive });
+          await invoke("set_selected_device", { device: firstActive, projectPath: currentProject?.path ?? null });
         } else if (result.devices.length > 0) {
           // If no active device, select first available one
           const firstAvailable = result.devices.find((d) => d.isAvailable);
           if (firstAvailable) {
             setSelectedDevice(firstAvailable);
-            await invoke("set_selected_device", { device: firstAvailable });
+            await invoke("set_selected_device", { device: firstAvailable, projectPath: currentProject?.path ?? null });
           }
         }
       } catch (err) {
@@ -275,12 +334,15 @@ const AppContent = () => {
     let unlisten: UnlistenFn | undefined;
 
     const setup = async () => {
-      unlisten = await listen<BuildEvent>("build-event", (event) => {
-        const { eventType, message } = event.payload;
+      // Replays the current build first, so a reload doesn't lose its log
+      unlisten = await listenWithReplay<BuildEvent>("build-event", "build", (payload) => {
+        const { eventType, message, messageKey, offline } = payload;
 
-        const logType = eventType === "error" ? "error"
+        // Offline notices are expected, not something to warn about
+        const logType = offline ? "info"
+          : eventType === "error" ? "error"
           : eventType === "warning" ? "warning"
-          : eventType === "completed" && message.includes("succeeded") ? "success"
+          : eventType === "completed" && messageKey === "build.succeeded" ? "success"
           : "info";
 
         setBuildLogs((prev) => [
@@ -293,7 +355,7 @@ const AppContent = () => {
           setBuildLogs([{ type: "info", message, timestamp: new Date() }]);
           setShowBottomPanel(true);
         }
-      });
+      }, { startAt: (payload) => payload.eventType === "started" });
     };
 
     setup();
@@ -455,6 +517,7 @@ const AppContent = () => {
         projectPath: PROJECT_PATH,
         scheme: PROJECT_SCHEME,
         device: selectedDevice,
+        autoStreamLogs: true,
       });
 
       if (result.success) {
@@ -487,6 +550,7 @@ const AppContent = () => {
         await invoke("stop_simulator_logs");
         // Terminate the app on simulator
         await invoke("terminate_app_on_simulator", {
+          deviceId: runningAppInfo.deviceId,
           bundleId: runningAppInfo.bundleId,
         });
       }
@@ -787,6 +851,7 @@ const AppContent = () => {
               >
                 <DevToolsPane
                   projectPath={ROOT_PROJECT_PATH}
+                  sessionId={currentSessionId}
                   onClose={() => setShowDevTools(false)}
                   onFileSelect={setSelectedDiffFile}
                   selectedFile={selectedDiffFile}
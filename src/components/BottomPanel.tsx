@@ -2,6 +2,7 @@ import { useState, useEffect, useRef, useCallback, forwardRef, useImperativeHand
 import { invoke } from "@tauri-apps/api/core";
 import { listen, UnlistenFn } from "@tauri-apps/api/event";
 import { XTerminal, XTerminalHandle } from "./XTerminal";
+import { listenWithReplay, type EventStamp } from "@/lib/events";
 
 interface LogEntry {
   type: "info" | "error" | "warning" | "success";
@@ -23,13 +24,21 @@ interface TerminalInstance {
 
 interface AppLaunchedEvent {
   bundleId: string;
+  executableName: string | null;
   deviceId: string | null;
   deviceType: "simulator" | "physical";
   deviceName: string;
+  pid?: number | null;
+  logStreaming?: boolean;
+  logBackend?: "simulator" | "devicectl" | null;
+  launchDurationMs?: number | null;
+  regression?: boolean;
+  runId?: string | null;
 }
 
 interface CurrentAppInfo {
   bundleId: string;
+  executableName: string | null;
   deviceName: string;
   deviceId: string | null;
   deviceType: "simulator" | "physical";
@@ -103,17 +112,28 @@ export const BottomPanel = forwardRef<BottomPanelHandle, BottomPanelProps>(({
     const setup = async () => {
       // Listen for app launch
       unlisten = await listen<AppLaunchedEvent>("app-launched", async (event) => {
-        const { bundleId, deviceId, deviceType, deviceName } = event.payload;
+        const { bundleId, executableName, deviceId, deviceType, deviceName, logStreaming } = event.payload;
         
-        setCurrentApp({ bundleId, deviceName, deviceId, deviceType });
-        setConsoleLogs([]);
+        setCurrentApp({ bundleId, executableName, deviceName, deviceId, deviceType });
         setActiveTab("console");
         setIsStreaming(true);
 
+        // run_project started the stream before launching, so output may already
+        // have arrived; take the simulator's buffer rather than clearing it
+        if (logStreaming) {
+          if (deviceType === "simulator") {
+            invoke<ConsoleLogEntry[]>("get_simulator_logs")
+              .then((logs) => setConsoleLogs(logs.slice(-500)))
+              .catch(() => {});
+          }
+          return;
+        }
+        setConsoleLogs([]);
+
         // Start log streaming based on device type
         try {
           if (deviceType === "simulator") {
-            await invoke("start_simulator_logs", { bundleId });
+            await invoke("start_simulator_logs", { bundleId, executableName });
           } else if (deviceType === "physical" && deviceId) {
             // For physical devices, use devicectl with --console
             // This will re-launch the app with console attached
@@ -126,9 +146,10 @@ export const BottomPanel = forwardRef<BottomPanelHandle, BottomPanelProps>(({
       });
 
       // Listen for incoming logs
-      logUnlisten = await listen<{ entries: ConsoleLogEntry[] }>("simulator-log", (event) => {
+      // Replays the recent tail first, so a reload doesn't clear the console
+      logUnlisten = await listenWithReplay<{ entries: ConsoleLogEntry[]; stamp?: EventStamp }>("simulator-log", "log", (payload) => {
         setConsoleLogs((prev) => {
-          const newLogs = [...prev, ...event.payload.entries];
+          const newLogs = [...prev, ...payload.entries];
           // Keep only last 500 entries in memory
           if (newLogs.length > 500) {
             return newLogs.slice(-500);
@@ -459,7 +480,10 @@ export const BottomPanel = forwardRef<BottomPanelHandle, BottomPanelProps>(({
                             bundleId: currentApp.bundleId 
                           });
                         } else {
-                          await invoke("start_simulator_logs", { bundleId: currentApp.bundleId });
+                          await invoke("start_simulator_logs", {
+                            bundleId: currentApp.bundleId,
+                            executableName: currentApp.executableName,
+                          });
                         }
                       } catch (err) {
                         console.error("Failed to start logs:", err);
@@ -526,6 +550,7 @@ export const BottomPanel = forwardRef<BottomPanelHandle, BottomPanelProps>(({
                 <XTerminal
                   ref={setTerminalRef(terminal.id)}
                   workingDir={projectPath}
+                  terminalKey={terminal.id}
                 />
               </div>
             ))}
@@ -567,7 +592,7 @@ export const BottomPanel = forwardRef<BottomPanelHandle, BottomPanelProps>(({
                     key={i} 
                     className={`leading-relaxed whitespace-pre-wrap ${
                       log.level === "error" || log.level === "fault" ? "text-error" :
-                      log.level === "warning" ? "text-warning" :
+                      log.level === "warning" || log.level === "runtime_issue" ? "text-warning" :
                       log.level === "debug" ? "text-text-tertiary" :
                       "text-text-secondary"
                     }`}
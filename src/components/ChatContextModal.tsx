@@ -28,7 +28,7 @@ export const ChatContextModal = ({
   onSend,
   initialMessage,
   fileReferences,
-  projectPath: _projectPath,
+  projectPath,
 }: ChatContextModalProps) => {
   const [message, setMessage] = useState(initialMessage);
   const [screenshot, setScreenshot] = useState<string | null>(null);
@@ -59,8 +59,12 @@ export const ChatContextModal = ({
     setScreenshotLoading(true);
     setScreenshotError(null);
     try {
-      const result = await invoke<string>("take_screenshot");
-      setScreenshot(result);
+      // Downscaled the same way as the agent's own screenshots, since this one goes to Claude
+      const result = await invoke<{ dataUrl: string }>("take_screenshot", {
+        projectPath,
+        options: { preset: "agent" },
+      });
+      setScreenshot(result.dataUrl);
     } catch (e) {
       console.error("Failed to capture screenshot:", e);
       setScreenshotError("Failed to capture");
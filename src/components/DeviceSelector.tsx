@@ -1,5 +1,6 @@
 import { useState, useEffect, useRef } from "react";
 import { invoke } from "@tauri-apps/api/core";
+import { useProject } from "@/lib/project-context";
 
 interface DeviceInfo {
   id: string;                    // UDID for xcodebuild
@@ -10,6 +11,8 @@ interface DeviceInfo {
   deviceType: "simulator" | "physical";
   state: "booted" | "shutdown" | "connected" | "disconnected" | "unavailable";
   isAvailable: boolean;
+  pairingState?: string | null;          // devicectl pairing state (physical devices only)
+  developerModeEnabled?: boolean | null; // null when the device doesn't report it
 }
 
 interface DeviceListResult {
@@ -29,6 +32,7 @@ export const DeviceSelector = ({
   onDeviceSelect,
   disabled = false,
 }: DeviceSelectorProps) => {
+  const { currentProject } = useProject();
   const [isOpen, setIsOpen] = useState(false);
   const [devices, setDevices] = useState<DeviceInfo[]>([]);
   const [isLoading, setIsLoading] = useState(false);
@@ -55,7 +59,7 @@ export const DeviceSelector = ({
     if (!isOpen) {
       setIsLoading(true);
       try {
-        const result = await invoke<DeviceListResult>("list_devices");
+        const result = await invoke<DeviceListResult>("list_devices", { recentFirst: true });
         setDevices(result.devices);
       } catch (error) {
         console.error("Failed to list devices:", error);
@@ -71,7 +75,7 @@ export const DeviceSelector = ({
     
     // Save selection to backend
     try {
-      await invoke("set_selected_device", { device });
+      await invoke("set_selected_device", { device, projectPath: currentProject?.path ?? null });
     } catch (error) {
       console.error("Failed to save device selection:", error);
     }
@@ -81,7 +85,7 @@ export const DeviceSelector = ({
     e.stopPropagation();
     setIsLoading(true);
     try {
-      const result = await invoke<DeviceListResult>("list_devices");
+      const result = await invoke<DeviceListResult>("list_devices", { recentFirst: true });
       setDevices(result.devices);
     } catch (error) {
       console.error("Failed to refresh devices:", error);
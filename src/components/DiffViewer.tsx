@@ -7,22 +7,79 @@ interface DiffViewerProps {
   onClose: () => void;
 }
 
+interface TokenRange {
+  start: number;
+  end: number;
+  class: string;
+}
+
+interface DiffLine {
+  kind: "context" | "add" | "delete";
+  oldLine: number | null;
+  newLine: number | null;
+  content: string;
+  tokens?: TokenRange[];
+}
+
+interface DiffHunk {
+  header: string;
+  lines: DiffLine[];
+  /** Past the backend's content limit; only the header was sent */
+  contentOmitted?: boolean;
+}
+
+interface FileDiff {
+  filePath: string;
+  language: string;
+  hunks: DiffHunk[];
+  omittedHunks: number;
+}
+
+// Token classes from the backend's lexer
+const TOKEN_CLASSES: Record<string, string> = {
+  keyword: "text-accent",
+  type: "text-warning",
+  string: "text-success",
+  number: "text-accent-muted",
+  comment: "text-text-tertiary italic",
+  attribute: "text-accent-muted",
+  key: "text-text-primary",
+  literal: "text-accent",
+};
+
+const renderTokens = (content: string, tokens?: TokenRange[]) => {
+  if (!tokens || tokens.length === 0) return content || " ";
+  const parts = [];
+  let position = 0;
+  tokens.forEach((token, i) => {
+    if (token.start > position) parts.push(content.slice(position, token.start));
+    parts.push(
+      <span key={i} className={TOKEN_CLASSES[token.class] ?? ""}>
+        {content.slice(token.start, token.end)}
+      </span>
+    );
+    position = token.end;
+  });
+  if (position < content.length) parts.push(content.slice(position));
+  return parts;
+};
+
 export const DiffViewer = ({ filePath, projectPath, onClose }: DiffViewerProps) => {
-  const [diff, setDiff] = useState<string>("");
+  const [diff, setDiff] = useState<FileDiff | null>(null);
   const [loading, setLoading] = useState(true);
 
   useEffect(() => {
     const fetchDiff = async () => {
       setLoading(true);
       try {
-        const diffContent = await invoke<string>("get_file_diff", {
+        const fileDiff = await invoke<FileDiff>("get_file_diff_structured", {
           path: projectPath,
           filePath: filePath,
         });
-        setDiff(diffContent);
+        setDiff(fileDiff);
       } catch (err) {
         console.error("Failed to get diff:", err);
-        setDiff("");
+        setDiff(null);
       } finally {
         setLoading(false);
       }
@@ -31,30 +88,44 @@ export const DiffViewer = ({ filePath, projectPath, onClose }: DiffViewerProps)
     fetchDiff();
   }, [filePath, projectPath]);
 
-  const renderDiffLine = (line: string, index: number) => {
+  const renderDiffLine = (line: DiffLine, index: number) => {
     let className = "text-text-secondary";
     let bgClass = "";
+    let marker = " ";
 
-    if (line.startsWith("+") && !line.startsWith("+++")) {
+    if (line.kind === "add") {
       className = "text-success";
       bgClass = "bg-success/10";
-    } else if (line.startsWith("-") && !line.startsWith("---")) {
+      marker = "+";
+    } else if (line.kind === "delete") {
       className = "text-error";
       bgClass = "bg-error/10";
-    } else if (line.startsWith("@@")) {
-      className = "text-accent";
-      bgClass = "bg-accent/5";
-    } else if (line.startsWith("diff ") || line.startsWith("index ") || line.startsWith("---") || line.startsWith("+++")) {
-      className = "text-text-tertiary";
+      marker = "-";
     }
 
     return (
-      <div key={index} className={`${className} ${bgClass} px-4 font-mono text-sm leading-6 whitespace-pre`}>
-        {line || " "}
+      <div key={index} className={`${line.tokens ? "text-text-secondary" : className} ${bgClass} px-4 font-mono text-sm leading-6 whitespace-pre`}>
+        <span className={className}>{marker}</span>
+        {renderTokens(line.content, line.tokens)}
       </div>
     );
   };
 
+  const renderHunk = (hunk: DiffHunk, index: number) => (
+    <div key={index}>
+      <div className="text-accent bg-accent/5 px-4 font-mono text-sm leading-6 whitespace-pre">
+        {hunk.header}
+      </div>
+      {hunk.contentOmitted ? (
+        <div className="text-text-tertiary px-4 font-mono text-sm leading-6">
+          Diff too large; content omitted
+        </div>
+      ) : (
+        hunk.lines.map((line, i) => renderDiffLine(line, i))
+      )}
+    </div>
+  );
+
   const fileName = filePath.split("/").pop() || filePath;
   const dirPath = filePath.includes("/") ? filePath.substring(0, filePath.lastIndexOf("/")) : "";
 
@@ -90,9 +161,9 @@ export const DiffViewer = ({ filePath, projectPath, onClose }: DiffViewerProps)
           <div className="flex items-center justify-center h-full">
             <span className="text-text-tertiary text-sm">Loading diff...</span>
           </div>
-        ) : diff ? (
+        ) : diff && diff.hunks.length > 0 ? (
           <div className="py-2">
-            {diff.split("\n").map((line, i) => renderDiffLine(line, i))}
+            {diff.hunks.map((hunk, i) => renderHunk(hunk, i))}
           </div>
         ) : (
           <div className="flex items-center justify-center h-full">
@@ -42,6 +42,7 @@ interface GitWorktree {
   branch: string;
   isMain: boolean;
   sessionId: string | null;
+  baseRef: string | null;
 }
 
 interface GitInfo {
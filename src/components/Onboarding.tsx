@@ -1,5 +1,6 @@
-import { useState, useEffect } from "react";
+import { useState, useEffect, useRef } from "react";
 import { invoke } from "@tauri-apps/api/core";
+import { listen } from "@tauri-apps/api/event";
 import { open } from "@tauri-apps/plugin-shell";
 
 /**
@@ -13,8 +14,30 @@ interface ClaudeCodeStatus {
   loggedIn: boolean;
   hasActivePlan: boolean;
   error: string | null;
+  errorKey?: string | null;
+  offline?: boolean; // login probe skipped; other fields are from the last check
 }
 
+// Result of start_claude_login
+type LoginStart =
+  | { state: "alreadyLoggedIn"; status: ClaudeCodeStatus }
+  | { state: "cliTooOld"; version: string; minimum: string }
+  | { state: "started"; command: string; url: string | null; code: string | null };
+
+type LoginFlow =
+  | { phase: "idle" }
+  | { phase: "starting" }
+  | { phase: "running"; command: string; url: string | null; code: string | null }
+  | { phase: "too-old"; version: string; minimum: string }
+  | { phase: "failed"; message: string };
+
+// Output lines kept for the login log
+const LOGIN_OUTPUT_LINES = 12;
+
+// Drop terminal escape sequences from the CLI's PTY output
+const stripAnsi = (text: string) =>
+  text.replace(/\x1b\[[0-9;?]*[A-Za-z]|\x1b\][^\x07]*\x07/g, "").replace(/\r/g, "");
+
 type OnboardingStep = "checking" | "not-installed" | "not-logged-in" | "no-plan" | "ready";
 
 const StepIndicator = ({ current, total }: { current: number; total: number }) => (
@@ -82,6 +105,10 @@ export const Onboarding = ({ onComplete }: OnboardingProps) => {
   const [status, setStatus] = useState<ClaudeCodeStatus | null>(null);
   const [isChecking, setIsChecking] = useState(false);
   const [isFadingOut, setIsFadingOut] = useState(false);
+  const [login, setLogin] = useState<LoginFlow>({ phase: "idle" });
+  const [loginOutput, setLoginOutput] = useState("");
+  const [loginCode, setLoginCode] = useState("");
+  const loginRunning = useRef(false);
 
   const checkStatus = async () => {
     // DEBUG: Force a specific step for testing
@@ -126,11 +153,75 @@ export const Onboarding = ({ onComplete }: OnboardingProps) => {
     await open("https://docs.anthropic.com/en/docs/claude-code");
   };
 
-  const openTerminal = async () => {
+  // Stream the login's output and pick up where it ends
+  useEffect(() => {
+    const unlisteners = [
+      listen<{ data: string }>("claude-login-output", (event) => {
+        setLoginOutput((prev) => (prev + stripAnsi(event.payload.data)).split("\n").slice(-LOGIN_OUTPUT_LINES).join("\n"));
+      }),
+      listen<{ status: ClaudeCodeStatus }>("claude-login-complete", () => {
+        loginRunning.current = false;
+        setLogin({ phase: "idle" });
+        checkStatus();
+      }),
+      listen<{ reason: string }>("claude-login-exit", (event) => {
+        loginRunning.current = false;
+        setLogin({ phase: "failed", message: event.payload.reason });
+      }),
+    ];
+    return () => {
+      unlisteners.forEach((p) => p.then((unlisten) => unlisten()));
+      // Leaving onboarding mid-login shouldn't leave the CLI running
+      if (loginRunning.current) {
+        invoke("cancel_claude_login").catch(() => {});
+      }
+    };
+  }, []);
+
+  const startLogin = async () => {
+    setLogin({ phase: "starting" });
+    setLoginOutput("");
+    setLoginCode("");
+    try {
+      const result = await invoke<LoginStart>("start_claude_login");
+      switch (result.state) {
+        case "alreadyLoggedIn":
+          setLogin({ phase: "idle" });
+          checkStatus();
+          break;
+        case "cliTooOld":
+          setLogin({ phase: "too-old", version: result.version, minimum: result.minimum });
+          break;
+        case "started":
+          loginRunning.current = true;
+          setLogin({ phase: "running", command: result.command, url: result.url, code: result.code });
+          if (result.url) {
+            await open(result.url);
+          }
+          break;
+      }
+    } catch (error) {
+      setLogin({ phase: "failed", message: String(error) });
+    }
+  };
+
+  const submitLoginCode = async () => {
+    if (!loginCode.trim()) return;
+    try {
+      await invoke("submit_claude_login_code", { code: loginCode });
+      setLoginCode("");
+    } catch (error) {
+      setLogin({ phase: "failed", message: String(error) });
+    }
+  };
+
+  const cancelLogin = async () => {
+    loginRunning.current = false;
+    setLogin({ phase: "idle" });
     try {
-      await invoke("open_claude_login");
+      await invoke("cancel_claude_login");
     } catch (error) {
-      console.error("Failed to open terminal:", error);
+      console.error("Failed to cancel login:", error);
     }
   };
 
@@ -232,7 +323,7 @@ export const Onboarding = ({ onComplete }: OnboardingProps) => {
                 <RequirementItem
                   status="current"
                   title="Sign in to Claude"
-                  description="Run 'claude' in your terminal"
+                  description="Connect your Anthropic account in the browser"
                 />
                 <RequirementItem
                   status="pending"
@@ -241,30 +332,94 @@ export const Onboarding = ({ onComplete }: OnboardingProps) => {
                 />
               </div>
 
-              {/* Login command */}
-              <div className="bg-surface-overlay rounded-md p-3 font-mono text-xs">
-                <div className="flex items-center gap-2 text-text-secondary">
-                  <span className="text-accent">❯</span>
-                  <span>claude</span>
+              {login.phase === "running" && (
+                <div className="space-y-3">
+                  <div className="bg-surface-overlay rounded-md p-3 font-mono text-xs">
+                    <div className="flex items-center gap-2 text-text-secondary">
+                      <span className="text-accent">❯</span>
+                      <span>{login.command}</span>
+                    </div>
+                    {loginOutput && (
+                      <pre className="text-text-tertiary mt-2 pl-4 whitespace-pre-wrap break-all max-h-32 overflow-y-auto">{loginOutput}</pre>
+                    )}
+                  </div>
+                  {login.code && (
+                    <p className="text-xs text-text-secondary">
+                      Enter <span className="font-mono text-text-primary">{login.code}</span> in the browser
+                    </p>
+                  )}
+                  <div className="flex gap-2">
+                    <input
+                      value={loginCode}
+                      onChange={(e) => setLoginCode(e.target.value)}
+                      onKeyDown={(e) => e.key === "Enter" && submitLoginCode()}
+                      placeholder="Paste the code from the browser"
+                      className="flex-1 px-3 py-2 text-xs font-mono rounded-md bg-surface-overlay border border-border text-text-primary placeholder:text-text-tertiary"
+                    />
+                    <button
+                      onClick={submitLoginCode}
+                      disabled={!loginCode.trim()}
+                      className="px-3 py-2 text-xs font-medium rounded-md bg-surface-overlay hover:bg-hover text-text-secondary transition-colors disabled:opacity-50"
+                    >
+                      Submit
+                    </button>
+                  </div>
+                  <p className="text-xs text-text-tertiary">Waiting for sign-in to finish...</p>
                 </div>
-                <p className="text-text-tertiary mt-2 pl-4">Follow the prompts to sign in</p>
-              </div>
+              )}
+
+              {login.phase === "too-old" && (
+                <div className="bg-surface-overlay rounded-md p-3 text-xs text-text-secondary space-y-2">
+                  <p>
+                    Claude Code {login.version} can't sign in from here; version {login.minimum} or newer is needed.
+                  </p>
+                  <div className="flex items-center gap-2 font-mono">
+                    <span className="text-accent">❯</span>
+                    <span>npm install -g @anthropic-ai/claude-code@latest</span>
+                  </div>
+                </div>
+              )}
+
+              {login.phase === "failed" && (
+                <p className="text-xs text-error whitespace-pre-wrap break-words">{login.message}</p>
+              )}
 
               {/* Actions */}
               <div className="flex gap-2 pt-2">
-                <button
-                  onClick={openTerminal}
-                  className="flex-1 px-4 py-2.5 text-sm font-medium rounded-md bg-surface-overlay hover:bg-hover text-text-primary transition-colors"
-                >
-                  Open Terminal
-                </button>
-                <button
-                  onClick={checkStatus}
-                  disabled={isChecking}
-                  className="px-4 py-2.5 text-sm font-medium rounded-md bg-surface-overlay hover:bg-hover text-text-secondary transition-colors disabled:opacity-50"
-                >
-                  {isChecking ? "..." : "Retry"}
-                </button>
+                {login.phase === "running" ? (
+                  <>
+                    <button
+                      onClick={() => login.url && open(login.url)}
+                      disabled={!login.url}
+                      className="flex-1 px-4 py-2.5 text-sm font-medium rounded-md bg-surface-overlay hover:bg-hover text-text-primary transition-colors disabled:opacity-50"
+                    >
+                      Open Browser
+                    </button>
+                    <button
+                      onClick={cancelLogin}
+                      className="px-4 py-2.5 text-sm font-medium rounded-md bg-surface-overlay hover:bg-hover text-text-secondary transition-colors"
+                    >
+                      Cancel
+                    </button>
+                  </>
+                ) : (
+                  <>
+                    <button
+                      onClick={startLogin}
+                      disabled={login.phase === "starting"}
+                      className="flex-1 px-4 py-2.5 text-sm font-medium rounded-md bg-surface-overlay hover:bg-hover text-text-primary transition-colors disabled:opacity-50"
+                    >
+                      {login.phase === "starting" ? "Starting..." : "Sign In"}
+                    </button>
+                    <button
+                      onClick={checkStatus}
+                      disabled={isChecking}
+                      className="px-4 py-2.5 text-sm font-medium rounded-md bg-surface-overlay hover:bg-hover text-text-secondary transition-colors disabled:opacity-50"
+                    >
+                      {isChecking ? "..." : "Retry"}
+                    </button>
+                  </>
+                )}
               </div>
             </div>
           )}
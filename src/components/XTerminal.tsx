@@ -1,13 +1,15 @@
 import { useEffect, useRef, useCallback, useImperativeHandle, forwardRef } from "react";
 import { Terminal } from "@xterm/xterm";
 import { FitAddon } from "@xterm/addon-fit";
-import { spawn, IPty } from "tauri-pty";
 import { platform } from "@tauri-apps/plugin-os";
 import { invoke } from "@tauri-apps/api/core";
+import { listen, UnlistenFn } from "@tauri-apps/api/event";
 import "@xterm/xterm/css/xterm.css";
 
 interface XTerminalProps {
   workingDir: string;
+  /** Stable key for reattaching to the same backend shell after a reload */
+  terminalKey?: string;
   onReady?: () => void;
   onExit?: () => void;
 }
@@ -19,10 +21,11 @@ export interface XTerminalHandle {
 }
 
 export const XTerminal = forwardRef<XTerminalHandle, XTerminalProps>(
-  ({ workingDir, onReady, onExit }, ref) => {
+  ({ workingDir, terminalKey, onReady, onExit }, ref) => {
     const terminalRef = useRef<HTMLDivElement>(null);
     const termRef = useRef<Terminal | null>(null);
-    const ptyRef = useRef<IPty | null>(null);
+    const terminalIdRef = useRef<string | null>(null);
+    const unlistenRefs = useRef<UnlistenFn[]>([]);
     const fitAddonRef = useRef<FitAddon | null>(null);
 
     // Expose methods to parent
@@ -88,54 +91,60 @@ export const XTerminal = forwardRef<XTerminalHandle, XTerminalProps>(
         // Use Tauri's platform API instead of process.platform
         const currentPlatform = platform();
         const shell = currentPlatform === "windows" ? "powershell.exe" : "/bin/zsh";
+        const storageKey = terminalKey ? `nocur-terminal:${workingDir}:${terminalKey}` : null;
+
+        // Output can arrive before create_terminal returns the id; hold it until then
+        const pending: { id: string; data: string }[] = [];
+        unlistenRefs.current.push(
+          await listen<{ id: string; data: string }>("terminal-data", (event) => {
+            if (terminalIdRef.current === null) {
+              pending.push(event.payload);
+            } else if (event.payload.id === terminalIdRef.current) {
+              term.write(event.payload.data);
+            }
+          }),
+          await listen<{ id: string }>("terminal-exit", (event) => {
+            if (event.payload.id !== terminalIdRef.current) return;
+            term.write("\r\n\x1b[90m[Process exited]\x1b[0m\r\n");
+            onExit?.();
+          }),
+        );
+
+        // Reattach to the shell from before a reload if it's still running
+        let id = storageKey ? sessionStorage.getItem(storageKey) : null;
+        if (id) {
+          try {
+            term.write(await invoke<string>("get_terminal_scrollback", { id }));
+            // Anything that arrived before the scrollback is already in it
+            pending.length = 0;
+            await invoke("resize_terminal", { id, cols: term.cols, rows: term.rows });
+          } catch {
+            id = null;
+          }
+        }
+        if (!id) {
+          id = await invoke<string>("create_terminal", {
+            workingDir,
+            shell,
+            cols: term.cols,
+            rows: term.rows,
+          });
+          if (storageKey) sessionStorage.setItem(storageKey, id);
+        }
 
-        console.log("[XTerminal] Platform:", currentPlatform);
-        console.log("[XTerminal] Shell:", shell);
-        console.log("[XTerminal] Working dir:", workingDir);
-        console.log("[XTerminal] Terminal size:", term.cols, "x", term.rows);
-
-        // Get environment from Rust backend (tauri-pty doesn't inherit parent env)
-        const env = await invoke<Record<string, string>>("get_shell_env");
-        console.log("[XTerminal] Got environment with", Object.keys(env).length, "variables");
-
-        // Use -l for login shell to properly initialize environment
-        // Also set name to xterm-256color for proper terminal emulation
-        const pty = await spawn(shell, ["-l"], {
-          cols: term.cols,
-          rows: term.rows,
-          cwd: workingDir,
-          name: "xterm-256color",
-          env: {
-            ...env,
-            TERM: "xterm-256color",
-            COLORTERM: "truecolor",
-          },
-        });
-
-        console.log("[XTerminal] PTY spawned successfully");
-
-        ptyRef.current = pty;
-
-        // Connect PTY to terminal
-        pty.onData((data: string) => {
-          console.log("[XTerminal] PTY data received:", data.length, "bytes");
-          term.write(data);
-        });
-
-        pty.onExit(() => {
-          term.write("\r\n\x1b[90m[Process exited]\x1b[0m\r\n");
-          onExit?.();
-        });
+        terminalIdRef.current = id;
+        for (const chunk of pending) {
+          if (chunk.id === id) term.write(chunk.data);
+        }
 
-        // Connect terminal input to PTY
+        // Connect terminal input to the shell
         term.onData((data: string) => {
-          console.log("[XTerminal] Terminal input:", JSON.stringify(data));
-          pty.write(data);
+          invoke("write_terminal", { id, data }).catch(() => {});
         });
 
         // Handle resize
         term.onResize(({ cols, rows }) => {
-          pty.resize(cols, rows);
+          invoke("resize_terminal", { id, cols, rows }).catch(() => {});
         });
 
         // Focus the terminal
@@ -145,19 +154,27 @@ export const XTerminal = forwardRef<XTerminalHandle, XTerminalProps>(
         console.error("Failed to spawn shell:", e);
         term.write(`\x1b[31mFailed to spawn shell: ${e}\x1b[0m\r\n`);
       }
-    }, [workingDir, onReady, onExit]);
+    }, [workingDir, terminalKey, onReady, onExit]);
 
     // Initialize terminal
     useEffect(() => {
       initTerminal();
 
+      // Unmounting closes the shell; a page reload never gets here, so the
+      // shell survives it and is reattached through terminalKey
       return () => {
-        ptyRef.current?.kill();
+        unlistenRefs.current.forEach((unlisten) => unlisten());
+        unlistenRefs.current = [];
+        const id = terminalIdRef.current;
+        if (id) {
+          invoke("close_terminal", { id }).catch(() => {});
+          if (terminalKey) sessionStorage.removeItem(`nocur-terminal:${workingDir}:${terminalKey}`);
+        }
         termRef.current?.dispose();
         termRef.current = null;
-        ptyRef.current = null;
+        terminalIdRef.current = null;
       };
-    }, [initTerminal]);
+    }, [initTerminal, terminalKey, workingDir]);
 
     // Handle resize
     useEffect(() => {
@@ -5,6 +5,16 @@ import ReactMarkdown from "react-markdown";
 import remarkGfm from "remark-gfm";
 import { SkillsModal } from "../SkillsModal";
 import { ChatContextModal } from "../ChatContextModal";
+import { errorMessage } from "@/lib/utils";
+
+// Display fields the backend pulls out of a tool call's input (tool_summary.rs)
+type ToolSummary =
+  | { kind: "edit"; path: string; snippet: string; edits: number }
+  | { kind: "write"; path: string; snippet: string; lines: number }
+  | { kind: "bash"; command: string; multiline: boolean; workingDir: string | null }
+  | { kind: "read"; path: string; offset: number | null; limit: number | null }
+  | { kind: "search"; pattern: string; path: string | null }
+  | { kind: "generic"; preview: string };
 
 interface ClaudeEvent {
   eventType: string;
@@ -12,6 +22,7 @@ interface ClaudeEvent {
   toolName: string | null;
   toolInput: string | null;
   toolId: string | null;
+  toolSummary?: ToolSummary;
   isError: boolean;
   rawJson: string | null;
   skills?: string[];
@@ -28,6 +39,9 @@ interface ClaudeEvent {
   numTurns?: number;
   // Result subtype (e.g., "error_max_turns", "end_turn")
   resultSubtype?: string;
+  // Set when content was cut; get_full_event_content(eventId) has the rest
+  truncatedBytes?: number;
+  eventId?: string;
 }
 
 interface Message {
@@ -39,7 +53,7 @@ interface Message {
   timestamp: Date;
   duration?: number;
   // Tools used during this response turn
-  toolsUsed?: Array<{ name: string; input?: string; result?: string; toolId?: string }>;
+  toolsUsed?: Array<{ name: string; input?: string; summary?: ToolSummary; result?: string; toolId?: string }>;
   // Token usage for this message
   outputTokens?: number;
   inputTokens?: number;
@@ -69,6 +83,18 @@ interface ModelInfo {
   id: string;
   name: string;
   description: string;
+  available: boolean;
+}
+
+interface StartSessionResponse {
+  sessionId: string;
+  workspaceWarning: string | null;
+}
+
+interface FileSuggestion {
+  path: string;
+  mtime: number | null;
+  source: "session" | "project";
 }
 
 interface SavedSession {
@@ -83,6 +109,7 @@ interface UserPreferences {
   skills: string[];
   skipPermissions: boolean;
   agentMode?: "build" | "plan";
+  checkpointProjects?: string[];
 }
 
 interface SessionMessage {
@@ -90,7 +117,7 @@ interface SessionMessage {
   messageType: string;
   content: string;
   timestamp: number;
-  toolsUsed?: Array<{ name: string; input?: string }>;
+  toolsUsed?: Array<{ name: string; input?: string; summary?: ToolSummary }>;
 }
 
 const PROJECT_DIR = ""; // Set dynamically via project context
@@ -227,12 +254,12 @@ const ChatInput = memo(({
       setSelectedIndex(0);
 
       // Fetch file suggestions
-      invoke<string[]>("list_project_files", {
+      invoke<FileSuggestion[]>("list_project_files", {
         projectPath,
         query: query || null,
         limit: 10,
       }).then(files => {
-        setAutocompleteItems(files);
+        setAutocompleteItems(files.map(f => f.path));
       }).catch(err => {
         console.error("Failed to fetch files:", err);
         setAutocompleteItems([]);
@@ -625,7 +652,7 @@ const normalizeToolName = (toolName: string): string => {
 };
 
 // Format tool calls in a user-friendly way
-const formatToolDisplay = (toolName: string, toolInput: string | undefined): {
+const formatToolDisplay = (toolName: string, toolInput: string | undefined, toolSummary?: ToolSummary): {
   summary: string;
   detail?: string;
   todos?: Array<{ content: string; status: string }>;
@@ -682,6 +709,10 @@ const formatToolDisplay = (toolName: string, toolInput: string | undefined): {
     case "Read": {
       const path = String(parsed.file_path || "");
       const filename = path.split("/").pop() || path;
+      if (toolSummary?.kind === "read" && toolSummary.offset != null) {
+        const end = toolSummary.limit != null ? `-${toolSummary.offset + toolSummary.limit}` : "";
+        return { summary: `Reading ${filename}`, detail: `${path}:${toolSummary.offset}${end}` };
+      }
       return { summary: `Reading ${filename}`, detail: path };
     }
     case "Edit": {
@@ -703,6 +734,10 @@ const formatToolDisplay = (toolName: string, toolInput: string | undefined): {
     case "Bash": {
       const cmd = String(parsed.command || "");
       const summary = parseBashCommand(cmd);
+      if (toolSummary?.kind === "bash") {
+        const command = toolSummary.multiline ? `${toolSummary.command} …` : toolSummary.command;
+        return { summary, detail: toolSummary.workingDir ? `${toolSummary.workingDir} $ ${command}` : command };
+      }
       return { summary, detail: cmd };
     }
     case "Glob": {
@@ -741,7 +776,10 @@ const formatToolDisplay = (toolName: string, toolInput: string | undefined): {
     }
     default:
       // Try to make any remaining tool names more readable
-      return { summary: normalizedName.replace(/_/g, " ").replace(/([a-z])([A-Z])/g, "$1 $2") };
+      return {
+        summary: normalizedName.replace(/_/g, " ").replace(/([a-z])([A-Z])/g, "$1 $2"),
+        detail: toolSummary?.kind === "generic" ? toolSummary.preview : undefined,
+      };
   }
 };
 
@@ -801,6 +839,7 @@ export const AgentPane = ({
   const [currentTurnTools, setCurrentTurnTools] = useState<Array<{
     name: string;
     input?: string;
+    summary?: ToolSummary;
     result?: string;
     toolId?: string;
   }>>([]);
@@ -811,7 +850,7 @@ export const AgentPane = ({
   const responseStartTimeRef = useRef<number>(0);
   const skipPermissionsRef = useRef(skipPermissions);
   // Use ref for turn tools to avoid React batching issues with rapid events
-  const currentTurnToolsRef = useRef<Array<{ name: string; input?: string; result?: string; toolId?: string }>>([]);
+  const currentTurnToolsRef = useRef<Array<{ name: string; input?: string; summary?: ToolSummary; result?: string; toolId?: string }>>([]);
   // Track token usage with ref for reliable access in event handlers (avoids stale closures)
   const tokenUsageRef = useRef({ input: 0, output: 0, cacheRead: 0, cacheCreation: 0 });
   // Track if initial mount setup has completed - prevents race conditions with skipPermissions effect
@@ -823,6 +862,24 @@ export const AgentPane = ({
   // Track if preferences have been loaded (to avoid saving defaults on mount)
   const prefsLoadedRef = useRef(false);
 
+  // Start (or restart) the Claude session, surfacing any dirty-workspace warning
+  const startSession = async (args: Record<string, unknown>): Promise<string> => {
+    const response = await invoke<StartSessionResponse>("start_claude_session", args);
+    const warning = response.workspaceWarning;
+    if (warning) {
+      setMessages((prev) => [
+        ...prev,
+        {
+          id: `workspace-${Date.now()}`,
+          type: "system",
+          content: warning,
+          timestamp: new Date(),
+        },
+      ]);
+    }
+    return response.sessionId;
+  };
+
   // Load user preferences on mount
   useEffect(() => {
     const loadPreferences = async () => {
@@ -856,8 +913,11 @@ export const AgentPane = ({
 
     const savePreferences = async () => {
       try {
+        // Keep fields managed elsewhere (session names, checkpoints, ...)
+        const existing = await invoke<UserPreferences>("get_user_preferences");
         await invoke("save_user_preferences", {
           preferences: {
+            ...existing,
             model: selectedModel,
             skills: availableSkills,
             skipPermissions: skipPermissions,
@@ -891,7 +951,7 @@ export const AgentPane = ({
         try {
           console.log("Restarting Claude with skipPermissions flag...");
           await invoke("stop_claude_session");
-          await invoke("start_claude_session", {
+          await startSession({
             workingDir: PROJECT_DIR,
             skipPermissions: true
           });
@@ -934,7 +994,7 @@ export const AgentPane = ({
           });
 
           // Resume session
-          const newSessionId = await invoke<string>("start_claude_session", {
+          const newSessionId = await startSession({
             workingDir: PROJECT_DIR,
             skipPermissions: skipPermissionsRef.current,
             model: selectedModel,
@@ -974,7 +1034,7 @@ export const AgentPane = ({
           if (currentSessionId) {
             await invoke("save_session_to_history", { lastMessage: messages[messages.length - 1]?.content || null });
           }
-          const sessionId = await invoke<string>("start_claude_session", {
+          const sessionId = await startSession({
             workingDir: PROJECT_DIR,
             skipPermissions: skipPermissionsRef.current,
             model: selectedModel,
@@ -988,7 +1048,7 @@ export const AgentPane = ({
         }
       } catch (err) {
         setStatus("error");
-        setError(String(err));
+        setError(errorMessage(err));
       }
     };
 
@@ -1247,9 +1307,26 @@ export const AgentPane = ({
           return;
         }
 
+        // The backend watchdog saw no events for a while mid-turn; the user can
+        // interrupt, restart or keep waiting via recover_stalled_session
+        if (eventType === "turn_stalled") {
+          if (content) {
+            setMessages((prev) => [
+              ...prev,
+              {
+                id: Date.now().toString(),
+                type: "system",
+                content,
+                timestamp: new Date(),
+              },
+            ]);
+          }
+          return;
+        }
+
         // Handle tool_use events (SDK sends these separately)
         if (eventType === "tool_use") {
-          const { toolInput, toolId } = event.payload;
+          const { toolInput, toolId, toolSummary } = event.payload;
           console.log("🔧 TOOL_USE event:", toolName, "id:", toolId, "ref count:", currentTurnToolsRef.current.length);
           if (toolName) {
             setCurrentActivity({
@@ -1263,7 +1340,7 @@ export const AgentPane = ({
             });
             // Add to turn tools history - use BOTH ref and state
             // Ref is for reliable capture on result, state is for UI display
-            const toolEntry = { name: toolName, input: toolInput || undefined, toolId: toolId || undefined };
+            const toolEntry = { name: toolName, input: toolInput || undefined, summary: toolSummary, toolId: toolId || undefined };
             currentTurnToolsRef.current = [...currentTurnToolsRef.current, toolEntry];
             console.log("🔧 Added to ref, new count:", currentTurnToolsRef.current.length);
             setCurrentTurnTools((prev) => [...prev, toolEntry]);
@@ -1394,7 +1471,7 @@ export const AgentPane = ({
         // Start Claude session with selected model, resuming if we have an active session
         // Note: The actual session ID from the SDK will be received via system_init event
         // and saved there. The Rust-generated ID is just internal.
-        await invoke<string>("start_claude_session", {
+        await startSession({
           workingDir: PROJECT_DIR,
           skipPermissions: false,
           model: selectedModel,
@@ -1420,7 +1497,7 @@ export const AgentPane = ({
         }
       } catch (err) {
         setStatus("error");
-        setError(String(err));
+        setError(errorMessage(err));
       }
     };
 
@@ -1497,13 +1574,13 @@ export const AgentPane = ({
     } catch (err) {
       console.error("📤 send_claude_message FAILED:", err);
       setStatus("error");
-      setError(String(err));
+      setError(errorMessage(err));
       setMessages((prev) => [
         ...prev,
         {
           id: Date.now().toString(),
           type: "error",
-          content: String(err),
+          content: errorMessage(err),
           timestamp: new Date(),
         },
       ]);
@@ -1514,6 +1591,32 @@ export const AgentPane = ({
     navigator.clipboard.writeText(text);
   };
 
+  // "Remember this": keep an instruction in CLAUDE.md's Guidelines for future sessions
+  const rememberRule = async (text: string) => {
+    try {
+      await invoke("append_claude_md_rule", { projectPath: PROJECT_DIR, text });
+      setMessages((prev) => [
+        ...prev,
+        {
+          id: Date.now().toString(),
+          type: "system",
+          content: "Added to CLAUDE.md guidelines",
+          timestamp: new Date(),
+        },
+      ]);
+    } catch (err) {
+      setMessages((prev) => [
+        ...prev,
+        {
+          id: Date.now().toString(),
+          type: "error",
+          content: errorMessage(err),
+          timestamp: new Date(),
+        },
+      ]);
+    }
+  };
+
   // Change model and restart session while preserving conversation
   const handleModelChange = async (modelId: string) => {
     setSelectedModel(modelId);
@@ -1522,7 +1625,7 @@ export const AgentPane = ({
     // Restart session with new model, keeping the same session to preserve conversation
     setStatus("connecting");
     try {
-      const sessionId = await invoke<string>("start_claude_session", {
+      const sessionId = await startSession({
         workingDir: PROJECT_DIR,
         skipPermissions: skipPermissions,
         model: modelId,
@@ -1532,7 +1635,7 @@ export const AgentPane = ({
       setStatus("connected");
     } catch (err) {
       setStatus("error");
-      setError(String(err));
+      setError(errorMessage(err));
     }
   };
 
@@ -1549,7 +1652,7 @@ export const AgentPane = ({
         sessionId: sessionId,
       });
 
-      const newSessionId = await invoke<string>("start_claude_session", {
+      const newSessionId = await startSession({
         workingDir: PROJECT_DIR,
         skipPermissions: skipPermissions,
         model: selectedModel,
@@ -1587,7 +1690,7 @@ export const AgentPane = ({
       }
     } catch (err) {
       setStatus("error");
-      setError(String(err));
+      setError(errorMessage(err));
     }
   };
 
@@ -1603,7 +1706,7 @@ export const AgentPane = ({
         await invoke("save_session_to_history", { lastMessage: messages[messages.length - 1]?.content || null });
       }
 
-      const sessionId = await invoke<string>("start_claude_session", {
+      const sessionId = await startSession({
         workingDir: PROJECT_DIR,
         skipPermissions: skipPermissions,
         model: selectedModel,
@@ -1617,7 +1720,7 @@ export const AgentPane = ({
       setSavedSessions(sessions);
     } catch (err) {
       setStatus("error");
-      setError(String(err));
+      setError(errorMessage(err));
     }
   };
 
@@ -1662,6 +1765,15 @@ export const AgentPane = ({
                       {msg.content}
                     </p>
                   </div>
+                  <button
+                    onClick={() => rememberRule(msg.content)}
+                    className="self-end ml-1 text-text-tertiary hover:text-text-secondary transition-colors"
+                    title="Remember this in CLAUDE.md"
+                  >
+                    <svg className="w-3.5 h-3.5" fill="none" stroke="currentColor" viewBox="0 0 24 24">
+                      <path strokeLinecap="round" strokeLinejoin="round" strokeWidth={1.5} d="M5 5a2 2 0 012-2h10a2 2 0 012 2v16l-7-3.5L5 21V5z" />
+                    </svg>
+                  </button>
                 </div>
               )}
 
@@ -1730,7 +1842,7 @@ export const AgentPane = ({
                       </summary>
                       <div className="mt-2 pl-4 space-y-1.5 font-mono text-xs border-l border-border-subtle">
                         {msg.toolsUsed.map((tool, i) => {
-                          const { summary, detail, diff } = formatToolDisplay(tool.name, tool.input);
+                          const { summary, detail, diff } = formatToolDisplay(tool.name, tool.input, tool.summary);
                           const normalizedName = tool.name.startsWith("mcp__")
                             ? tool.name.split("__").pop() || tool.name
                             : tool.name;
@@ -1968,7 +2080,7 @@ export const AgentPane = ({
               {currentTurnTools.length > 0 && (
                 <div className="space-y-2 font-mono text-sm">
                   {currentTurnTools.map((tool, i) => {
-                    const { summary, detail, diff } = formatToolDisplay(tool.name, tool.input);
+                    const { summary, detail, diff } = formatToolDisplay(tool.name, tool.input, tool.summary);
                     const isLast = i === currentTurnTools.length - 1;
                     return (
                       <div key={i} className="space-y-1">
@@ -2060,7 +2172,9 @@ export const AgentPane = ({
                           <button
                             key={model.id}
                             onClick={() => handleModelChange(model.id)}
-                            className={`w-full px-3 py-2 text-left text-xs hover:bg-hover transition-colors ${
+                            disabled={!model.available}
+                            title={model.available ? undefined : "Not available on your plan"}
+                            className={`w-full px-3 py-2 text-left text-xs hover:bg-hover transition-colors disabled:opacity-40 disabled:cursor-not-allowed ${
                               selectedModel === model.id ? "text-accent" : "text-text-primary"
                             }`}
                           >
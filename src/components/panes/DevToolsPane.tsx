@@ -3,6 +3,7 @@ import { invoke } from "@tauri-apps/api/core";
 
 interface GitChangedFile {
   path: string;
+  absolutePath: string;
   status: string;
   additions: number;
   deletions: number;
@@ -16,6 +17,7 @@ interface GitDiffStats {
 
 interface DevToolsPaneProps {
   projectPath: string;
+  sessionId?: string | null;
   onClose: () => void;
   onFileSelect: (filePath: string) => void;
   selectedFile: string | null;
@@ -43,7 +45,7 @@ const TabButton = ({
   </button>
 );
 
-export const DevToolsPane = ({ projectPath, onClose, onFileSelect, selectedFile }: DevToolsPaneProps) => {
+export const DevToolsPane = ({ projectPath, sessionId, onClose, onFileSelect, selectedFile }: DevToolsPaneProps) => {
   const [activeTab, setActiveTab] = useState<"changes" | "terminal">("changes");
   const [diffStats, setDiffStats] = useState<GitDiffStats | null>(null);
 
@@ -51,8 +53,13 @@ export const DevToolsPane = ({ projectPath, onClose, onFileSelect, selectedFile
   useEffect(() => {
     const fetchDiffStats = async () => {
       try {
+        // Worktree sessions should show the worktree's changes, not main's
+        const workingDir = sessionId
+          ? (await invoke<string | null>("get_effective_working_dir", { sessionId })) ?? projectPath
+          : projectPath;
         const stats = await invoke<GitDiffStats>("get_git_diff_stats", {
-          path: projectPath,
+          path: workingDir,
+          legacy: true,
         });
         setDiffStats(stats);
       } catch (err) {
@@ -63,7 +70,7 @@ export const DevToolsPane = ({ projectPath, onClose, onFileSelect, selectedFile
     fetchDiffStats();
     const interval = setInterval(fetchDiffStats, 3000);
     return () => clearInterval(interval);
-  }, [projectPath]);
+  }, [projectPath, sessionId]);
 
   const getStatusColor = (status: string) => {
     if (status === "M" || status.includes("M")) return "text-warning";
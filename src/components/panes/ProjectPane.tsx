@@ -15,9 +15,42 @@ interface BuildResult {
   output: string;
   errors: BuildError[];
   warnings: number;
+  /** Each warning with its location */
+  warningDetails?: BuildError[];
   buildTime: number | null;
   appPath: string | null;
   bundleId: string | null;
+  launch?: LaunchInfo | null;
+  /** Set when the app was installed (and launched) under a different bundle ID than the build reported */
+  bundleIdCorrection?: { expected: string; installed: string } | null;
+  /** The build was stopped by cancel_build */
+  cancelled?: boolean;
+  /** The kept output, for read_build_log */
+  logId?: string | null;
+}
+
+/** Commands returning a CommandError reject with `{ kind, message }`; a second run_project gets `runInProgress` */
+function commandErrorMessage(error: unknown): string {
+  if (typeof error === "object" && error !== null && "kind" in error) {
+    const { kind, message } = error as { kind: string; message: unknown };
+    if (kind === "runInProgress") return "A run of this project is already in progress";
+    if (kind === "missingTool") {
+      const { tool, installCommand } = message as { tool: string; installCommand: string };
+      return `${tool} isn't installed. Install it with: ${installCommand}`;
+    }
+    return String(message);
+  }
+  return String(error);
+}
+
+interface LaunchInfo {
+  pid: number | null;
+  logStreaming: boolean;
+  logBackend: "simulator" | "devicectl" | null;
+  logError: string | null;
+  launchDurationMs?: number | null;
+  launchRegression?: boolean;
+  launchArgs?: string[]; // from launchOverrides, simulator only
 }
 
 interface BuildError {
@@ -25,6 +58,18 @@ interface BuildError {
   line: number | null;
   column: number | null;
   message: string;
+  relativePath?: string | null;
+  isDependency?: boolean;
+  /** "clean_build" when the error comes from stale DerivedData */
+  suggestion?: "clean_build" | "reset_crash_loop" | null;
+  /** Schemes to pick from when the requested one doesn't exist */
+  suggestions?: string[];
+  /** note: lines and fix-its printed after the diagnostic */
+  notes?: string[];
+  /** Source excerpt with the caret line */
+  snippet?: string | null;
+  /** Set for failures that aren't compiler diagnostics, e.g. signing */
+  category?: "signing_no_team" | "signing_certificate_expired" | "signing_untrusted_device" | "signing" | null;
 }
 
 // No default project - users should select their own project
@@ -50,7 +95,9 @@ export const ProjectPane = () => {
         scheme: project.scheme,
       });
 
-      if (result.success) {
+      if (result.cancelled) {
+        setBuildStatus("idle");
+      } else if (result.success) {
         setBuildStatus("success");
         setBuildTime(result.buildTime);
         setWarnings(result.warnings);
@@ -63,7 +110,15 @@ export const ProjectPane = () => {
     } catch (error) {
       console.error("Build failed:", error);
       setBuildStatus("failed");
-      setBuildErrors([{ file: null, line: null, column: null, message: String(error) }]);
+      setBuildErrors([{ file: null, line: null, column: null, message: commandErrorMessage(error) }]);
+    }
+  };
+
+  const handleCancel = async () => {
+    try {
+      await invoke<boolean>("cancel_build");
+    } catch (error) {
+      console.error("Cancel failed:", error);
     }
   };
 
@@ -78,9 +133,12 @@ export const ProjectPane = () => {
       const result = await invoke<BuildResult>("run_project", {
         projectPath: project.path,
         scheme: project.scheme,
+        autoStreamLogs: true,
       });
 
-      if (result.success) {
+      if (result.cancelled) {
+        setBuildStatus("idle");
+      } else if (result.success) {
         setBuildStatus("success");
         setBuildTime(result.buildTime);
         setWarnings(result.warnings);
@@ -93,7 +151,7 @@ export const ProjectPane = () => {
     } catch (error) {
       console.error("Run failed:", error);
       setBuildStatus("failed");
-      setBuildErrors([{ file: null, line: null, column: null, message: String(error) }]);
+      setBuildErrors([{ file: null, line: null, column: null, message: commandErrorMessage(error) }]);
     }
   };
 
@@ -149,6 +207,14 @@ export const ProjectPane = () => {
                 >
                   ▶ Run
                 </button>
+                {buildStatus === "building" && (
+                  <button
+                    onClick={handleCancel}
+                    className="px-3 py-1.5 text-xs rounded bg-surface-overlay hover:bg-hover text-text-primary transition-colors font-medium"
+                  >
+                    Cancel
+                  </button>
+                )}
               </div>
             </div>
 
@@ -15,9 +15,10 @@ interface ClaudeEvent {
 // Log entry from simulator console
 interface SimulatorLogEntry {
   timestamp: number;
-  level: string;  // "debug", "info", "warning", "error", "fault"
+  level: string;  // "debug", "info", "warning", "error", "fault", "runtime_issue"
   process: string;
   message: string;
+  ruleId?: string;  // set for "runtime_issue"
 }
 
 // Crash report from iOS
@@ -46,6 +47,15 @@ interface RecordingData {
   endTime: number;
 }
 
+interface ScreenshotCapture {
+  dataUrl: string;
+  path: string;
+  width: number;
+  height: number;
+  bytes: number;
+  format: "png" | "jpeg";
+}
+
 interface SimulatorPaneProps {
   isAppRunning?: boolean;
   onCapture?: (data: RecordingData) => void;
@@ -70,15 +80,52 @@ export const SimulatorPane = ({ isAppRunning, onCapture }: SimulatorPaneProps) =
   const [errorCount, setErrorCount] = useState(0);
   // Flag to prevent auto-restart after manual stop/discard
   const [manualStopFlag, setManualStopFlag] = useState(false);
+  // How keys typed over the simulator view reach the Simulator (see set_simulator_input_mode)
+  const [inputMode, setInputMode] = useState<"forward" | "native">("native");
   const lastCaptureRef = useRef<string | null>(null);
   const captureIntervalRef = useRef<NodeJS.Timeout | null>(null);
   const recordingStartRef = useRef<number>(0);
   const captureInFlightRef = useRef(false);
 
+  useEffect(() => {
+    invoke<"forward" | "native">("get_simulator_input_mode").then(setInputMode).catch(console.error);
+  }, []);
+
+  const toggleInputMode = async () => {
+    const next = inputMode === "forward" ? "native" : "forward";
+    try {
+      setInputMode(await invoke<"forward" | "native">("set_simulator_input_mode", { mode: next }));
+    } catch (error) {
+      console.error("Failed to set input mode:", error);
+    }
+  };
+
+  // Forward mode: send keys to the Simulator without giving up focus. Modifier
+  // keys on their own aren't sent; they ride along as flags on the next key.
+  const forwardKey = (e: React.KeyboardEvent, phase: "down" | "up") => {
+    if (inputMode !== "forward") return;
+    if (["Meta", "Shift", "Alt", "Control", "CapsLock"].includes(e.key)) return;
+    e.preventDefault();
+    e.stopPropagation();
+    invoke("forward_simulator_key", {
+      code: e.code,
+      modifiers: { meta: e.metaKey, shift: e.shiftKey, alt: e.altKey, ctrl: e.ctrlKey },
+      phase,
+      repeat: e.repeat,
+    }).catch((error) => console.error("Failed to forward key:", error));
+  };
+
+  // Native mode: hand focus to the Simulator so macOS routes keys itself
+  const handleSimulatorClick = () => {
+    if (inputMode === "native") {
+      invoke("focus_simulator").catch((error) => console.error("Failed to focus Simulator:", error));
+    }
+  };
+
   const captureScreenshot = async () => {
     setIsCapturing(true);
     try {
-      const dataUrl = await invoke<string>("take_screenshot");
+      const { dataUrl } = await invoke<ScreenshotCapture>("take_screenshot");
       setScreenshotUrl(dataUrl);
       setState("running");
     } catch (error) {
@@ -134,7 +181,10 @@ export const SimulatorPane = ({ isAppRunning, onCapture }: SimulatorPaneProps) =
         if (captureInFlightRef.current) return;
         captureInFlightRef.current = true;
         try {
-          const dataUrl = await invoke<string>("take_screenshot");
+          // null while streaming is off (window hidden, or a headless run)
+          const capture = await invoke<ScreenshotCapture | null>("capture_stream_frame");
+          if (!capture) return;
+          const { dataUrl } = capture;
           setScreenshotUrl(dataUrl);
 
           if (dataUrl && dataUrl !== lastCaptureRef.current) {
@@ -441,7 +491,13 @@ export const SimulatorPane = ({ isAppRunning, onCapture }: SimulatorPaneProps) =
           // Running or Observing - show live feed
           <div className="h-full w-full flex items-center justify-center overflow-hidden">
             {screenshotUrl ? (
-              <div className="relative h-full w-full flex items-center justify-center">
+              <div
+                className="relative h-full w-full flex items-center justify-center outline-none"
+                tabIndex={0}
+                onClick={handleSimulatorClick}
+                onKeyDown={(e) => forwardKey(e, "down")}
+                onKeyUp={(e) => forwardKey(e, "up")}
+              >
                 <img
                   src={screenshotUrl}
                   alt="Simulator"
@@ -554,6 +610,20 @@ export const SimulatorPane = ({ isAppRunning, onCapture }: SimulatorPaneProps) =
               <path strokeWidth="2" strokeLinecap="round" d="M4 4v5h.582m15.356 2A8.001 8.001 0 004.582 9m0 0H9m11 11v-5h-.581m0 0a8.003 8.003 0 01-15.357-2m15.357 2H15" />
             </svg>
           </button>
+          <button
+            onClick={toggleInputMode}
+            className={`p-2 rounded hover:bg-hover transition-colors ${
+              inputMode === "forward" ? "text-accent" : "text-text-tertiary hover:text-text-primary"
+            }`}
+            title={inputMode === "forward"
+              ? "Keyboard: typing over the simulator is sent to it (click to switch to native)"
+              : "Keyboard: clicking the simulator focuses Simulator.app (click to capture keys instead)"}
+          >
+            <svg className="w-4 h-4" fill="none" stroke="currentColor" viewBox="0 0 24 24">
+              <rect x="3" y="6" width="18" height="12" rx="2" strokeWidth="2" />
+              <path strokeWidth="2" strokeLinecap="round" d="M7 10h.01M11 10h.01M15 10h.01M8 14h8" />
+            </svg>
+          </button>
           <button
             onClick={fetchHierarchy}
             className="p-2 rounded hover:bg-hover text-text-tertiary hover:text-text-primary transition-colors"
@@ -0,0 +1,82 @@
+import { invoke } from "@tauri-apps/api/core";
+import { listen, type UnlistenFn } from "@tauri-apps/api/event";
+
+/** Channels the backend journals for replay after a reload */
+export type ReplayChannel = "claude" | "build" | "log";
+
+/** Added to every event the backend emits (event_journal.rs) */
+export interface EventStamp {
+  /** One counter across all events; strictly increasing in emit order */
+  seq: number;
+  emittedAtMicros: number;
+  sessionId?: string;
+  /** Event name it was emitted as */
+  source: string;
+}
+
+interface ReplayResult<T> {
+  events: T[];
+  latestSeq: number;
+  truncated: boolean;
+}
+
+interface ReplayOptions<T> {
+  /** Only replay one session (claude) or build (build) */
+  key?: string;
+  /** Replay from the last event this matches, e.g. the start of the current turn */
+  startAt?: (payload: T) => boolean;
+}
+
+/**
+ * Listen to a journaled event after first replaying what the backend already
+ * emitted, so a reloaded webview picks up mid-turn or mid-build. Live events
+ * that arrive while the replay is loading are held and delivered after it;
+ * anything with a `stamp.seq` already seen is dropped.
+ */
+export async function listenWithReplay<T extends { stamp?: EventStamp }>(
+  event: string,
+  channel: ReplayChannel,
+  handler: (payload: T) => void,
+  options: ReplayOptions<T> = {},
+): Promise<UnlistenFn> {
+  let lastSeq = 0;
+  let pending: T[] | null = [];
+
+  const deliver = (payload: T) => {
+    if (payload.stamp) {
+      if (payload.stamp.seq <= lastSeq) return;
+      lastSeq = payload.stamp.seq;
+    }
+    handler(payload);
+  };
+
+  const unlisten = await listen<T>(event, (e) => {
+    if (pending) {
+      pending.push(e.payload);
+    } else {
+      deliver(e.payload);
+    }
+  });
+
+  try {
+    const replay = await invoke<ReplayResult<T>>("replay_events", {
+      channel,
+      sinceSeq: 0,
+      key: options.key,
+    });
+    let events = replay.events;
+    if (options.startAt) {
+      const start = events.map((p) => options.startAt!(p)).lastIndexOf(true);
+      events = start === -1 ? events : events.slice(start);
+    }
+    events.forEach(deliver);
+  } catch (err) {
+    console.error(`Failed to replay ${channel} events:`, err);
+  }
+
+  const held = pending;
+  pending = null;
+  held.forEach(deliver);
+
+  return unlisten;
+}
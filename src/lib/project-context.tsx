@@ -3,6 +3,7 @@ import { invoke } from "@tauri-apps/api/core";
 import { listen, UnlistenFn } from "@tauri-apps/api/event";
 import { open } from "@tauri-apps/plugin-dialog";
 import { getCurrentWindow } from "@tauri-apps/api/window";
+import { type EventStamp } from "@/lib/events";
 
 // =============================================================================
 // Types
@@ -25,11 +26,34 @@ export interface ProjectValidation {
   hasXcodeproj: boolean;
   hasPackageSwift: boolean;
   error: string | null;
+  /** Non-blocking problems, e.g. the project is in iCloud Drive */
+  warnings: string[];
 }
 
 export interface CreateProjectRequest {
   name: string;
   location: string;
+  /** Template ID from `list_project_templates`; the built-in template when omitted */
+  template?: string;
+  /** Values for the template's declared variables */
+  variables?: Record<string, string>;
+}
+
+export interface TemplateVariable {
+  name: string;
+  label?: string;
+  default?: string;
+  required: boolean;
+}
+
+export interface ProjectTemplate {
+  id: string;
+  name: string;
+  description?: string;
+  builtin: boolean;
+  variables: TemplateVariable[];
+  postCreate: string[];
+  error?: string;
 }
 
 interface ProjectContextValue {
@@ -103,17 +127,17 @@ export function ProjectProvider({ children }: ProjectProviderProps) {
 
     const setup = async () => {
       // Handle menu events (New Project, Open Project)
-      unlistenMenu = await listen<string>("menu-event", (event) => {
-        if (event.payload === "new-project") {
+      unlistenMenu = await listen<{ action: string; stamp?: EventStamp }>("menu-event", (event) => {
+        if (event.payload.action === "new-project") {
           setShowNewProjectModal(true);
-        } else if (event.payload === "open-project") {
+        } else if (event.payload.action === "open-project") {
           openProjectDialog();
         }
       });
 
       // Handle opening recent project from menu
-      unlistenRecent = await listen<string>("open-recent-project", async (event) => {
-        await openProject(event.payload);
+      unlistenRecent = await listen<{ path: string; stamp?: EventStamp }>("open-recent-project", async (event) => {
+        await openProject(event.payload.path);
       });
 
       // Handle recent projects updated (e.g., cleared from menu)
@@ -152,6 +176,11 @@ export function ProjectProvider({ children }: ProjectProviderProps) {
       // Validate and add to recent
       const projects = await invoke<ProjectInfo[]>("add_to_recent_projects", { path });
       setRecentProjects(projects);
+
+      // Start the slow loads now so the panes find them cached
+      invoke("prefetch_project_state", { projectPath: path }).catch((err) =>
+        console.error("Failed to start prefetch:", err)
+      );
       
       // Find the project info
       const projectInfo = projects.find(p => p.path === path);
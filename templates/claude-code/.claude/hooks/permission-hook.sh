@@ -4,8 +4,9 @@
 
 set -euo pipefail
 
+# Nocur passes the socket path to everything it spawns; fall back to the default location.
 SOCKET_DIR="${TMPDIR:-/tmp}"
-SOCKET_PATH="${SOCKET_DIR%/}/nocur-permissions.sock"
+SOCKET_PATH="${NOCUR_PERMISSION_SOCKET:-${SOCKET_DIR%/}/nocur-permissions.sock}"
 TIMEOUT=60 # seconds to wait for user response
 
 # Read the tool info from stdin